@@ -0,0 +1,38 @@
+use uuid::Uuid;
+
+use crate::client::Client;
+
+use super::models::{AssociateFloatingIpRequest, FloatingIp, NewFloatingIp};
+
+pub async fn list(client: &Client) -> anyhow::Result<Vec<FloatingIp>> {
+    client.get("/floating-ips").await
+}
+
+pub async fn create(client: &Client, floating_ip: &NewFloatingIp) -> anyhow::Result<String> {
+    client.post_text("/floating-ips", floating_ip).await
+}
+
+pub async fn delete(client: &Client, floating_ip_id: Uuid) -> anyhow::Result<()> {
+    client
+        .delete(&format!("/floating-ips/{floating_ip_id}"))
+        .await
+}
+
+pub async fn associate(
+    client: &Client,
+    floating_ip_id: Uuid,
+    request: &AssociateFloatingIpRequest,
+) -> anyhow::Result<FloatingIp> {
+    client
+        .post(
+            &format!("/floating-ips/{floating_ip_id}/associate"),
+            request,
+        )
+        .await
+}
+
+pub async fn disassociate(client: &Client, floating_ip_id: Uuid) -> anyhow::Result<FloatingIp> {
+    client
+        .post_empty_json(&format!("/floating-ips/{floating_ip_id}/disassociate"))
+        .await
+}
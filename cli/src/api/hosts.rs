@@ -4,7 +4,7 @@ use crate::client::Client;
 
 use super::models::{
     DeployHostRequest, Host, HostEvacuateResponse, HostGpu, HostResourceCapacity, NewHost,
-    UpdateHostPlacementRequest, UpdateHostRequest,
+    OverlayBdCacheReport, UpdateHostPlacementRequest, UpdateHostRequest,
 };
 
 pub async fn list(
@@ -104,3 +104,12 @@ pub async fn list_gpus(client: &Client, host_id: Uuid) -> anyhow::Result<Vec<Hos
 pub async fn resources(client: &Client, host_id: Uuid) -> anyhow::Result<HostResourceCapacity> {
     client.get(&format!("/hosts/{host_id}/resources")).await
 }
+
+pub async fn overlaybd_cache(
+    client: &Client,
+    host_id: Uuid,
+) -> anyhow::Result<OverlayBdCacheReport> {
+    client
+        .get(&format!("/hosts/{host_id}/overlaybd-cache"))
+        .await
+}
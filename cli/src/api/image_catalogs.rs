@@ -0,0 +1,46 @@
+use uuid::Uuid;
+
+use crate::client::Client;
+
+use super::models::{
+    ImageCatalog, ImageCatalogRefreshResponse, ImageCatalogVersion, NewImageCatalog,
+    UpdateImageCatalog,
+};
+
+pub async fn list(client: &Client, name: Option<&str>) -> anyhow::Result<Vec<ImageCatalog>> {
+    let path = match name {
+        Some(n) => format!("/image-catalogs?name={n}"),
+        None => "/image-catalogs".to_string(),
+    };
+    client.get(&path).await
+}
+
+pub async fn get(client: &Client, id: Uuid) -> anyhow::Result<ImageCatalog> {
+    client.get(&format!("/image-catalogs/{id}")).await
+}
+
+pub async fn create(client: &Client, catalog: &NewImageCatalog) -> anyhow::Result<String> {
+    client.post_text("/image-catalogs", catalog).await
+}
+
+pub async fn update(
+    client: &Client,
+    id: Uuid,
+    req: &UpdateImageCatalog,
+) -> anyhow::Result<ImageCatalog> {
+    client.patch(&format!("/image-catalogs/{id}"), req).await
+}
+
+pub async fn delete(client: &Client, id: Uuid) -> anyhow::Result<()> {
+    client.delete(&format!("/image-catalogs/{id}")).await
+}
+
+pub async fn list_versions(client: &Client, id: Uuid) -> anyhow::Result<Vec<ImageCatalogVersion>> {
+    client.get(&format!("/image-catalogs/{id}/versions")).await
+}
+
+pub async fn refresh(client: &Client, id: Uuid) -> anyhow::Result<ImageCatalogRefreshResponse> {
+    client
+        .post_empty_json(&format!("/image-catalogs/{id}/refresh"))
+        .await
+}
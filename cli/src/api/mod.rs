@@ -1,12 +1,16 @@
 pub mod audit_log;
 pub mod backups;
 pub mod boot_sources;
+pub mod floating_ips;
 pub mod hooks;
 pub mod hosts;
+pub mod image_catalogs;
 pub mod instance_types;
 pub mod jobs;
 pub mod models;
 pub mod networks;
+pub mod port_forwards;
+pub mod registry_credentials;
 pub mod sandbox_pools;
 pub mod sandboxes;
 pub mod security_groups;
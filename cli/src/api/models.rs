@@ -90,6 +90,12 @@ pub struct NewVm {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub persistent_upper_pool_id: Option<Uuid>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub overlaybd_disk_size_bytes: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root_disk_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root_disk_pool_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub placement_policy: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub guest_agent: Option<bool>,
@@ -135,6 +141,8 @@ pub struct VmTemplate {
     pub root_disk_object_id: Option<Uuid>,
     pub boot_mode: Option<String>,
     pub image_ref: Option<String>,
+    #[serde(default)]
+    pub image_catalog_id: Option<Uuid>,
     pub network_id: Option<Uuid>,
 }
 
@@ -160,6 +168,8 @@ pub struct NewVmTemplate {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub image_ref: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_catalog_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub network_id: Option<Uuid>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub config: Option<serde_json::Value>,
@@ -228,6 +238,47 @@ pub struct CommitVmResponse {
     pub job_id: Uuid,
 }
 
+#[derive(Debug, Serialize)]
+pub struct ExportVmRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage_pool_id: Option<Uuid>,
+    pub include_memory: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportVmResponse {
+    pub job_id: Uuid,
+    pub storage_object_id: Uuid,
+    pub path: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportVmRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage_object_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage_pool_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub network_map: BTreeMap<String, Uuid>,
+    pub storage_pool_map: BTreeMap<String, Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_storage_pool_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub boot_source_id: Option<Uuid>,
+    pub preserve_ips: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportVmResponse {
+    pub vm_id: Uuid,
+    pub job_id: Uuid,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VmMigrateRequest {
     pub target_host_id: Uuid,
@@ -282,6 +333,28 @@ pub struct Host {
     pub resources_updated_at: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OverlayBdCacheReport {
+    pub host_id: Uuid,
+    pub used_bytes: u64,
+    pub max_bytes: Option<u64>,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub images: Vec<OverlayBdCachedImage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OverlayBdCachedImage {
+    pub image_ref: String,
+    pub registry_url: String,
+    pub layers: u32,
+    pub cached_layers: u32,
+    pub cached_bytes: u64,
+    pub last_used_at: Option<String>,
+    pub in_use: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HostResourceCapacity {
     pub host_id: Uuid,
@@ -363,6 +436,33 @@ pub struct StoragePool {
     pub status: String,
     pub capacity_bytes: Option<i64>,
     pub allocated_bytes: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub host_capacity: Vec<HostPoolCapacity>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HostPoolCapacity {
+    pub host_id: Uuid,
+    pub host_name: String,
+    pub total_bytes: i64,
+    pub used_bytes: i64,
+    pub free_bytes: i64,
+    pub checked_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StoragePathHealth {
+    pub path: String,
+    pub state: String,
+    pub healthy: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HostPathHealth {
+    pub host_id: Uuid,
+    pub host_name: String,
+    pub paths: Vec<StoragePathHealth>,
+    pub checked_at: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -415,6 +515,14 @@ pub struct Transfer {
     pub transferred_bytes: i64,
     pub error_message: Option<String>,
     pub created_at: Option<String>,
+    #[serde(default)]
+    pub expected_checksum: Option<String>,
+    #[serde(default)]
+    pub actual_checksum: Option<String>,
+    #[serde(default)]
+    pub checksum_verified: Option<bool>,
+    #[serde(default)]
+    pub bandwidth_limit_bytes_per_sec: Option<i64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -422,6 +530,10 @@ pub struct NewTransfer {
     pub name: String,
     pub source: String,
     pub object_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_checksum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bandwidth_limit_bytes_per_sec: Option<i64>,
 }
 
 // Boot sources
@@ -456,13 +568,39 @@ pub struct Network {
     pub name: String,
     pub subnet: String,
     pub gateway: Option<String>,
+    #[serde(default)]
+    pub subnet6: Option<String>,
+    #[serde(default)]
+    pub gateway6: Option<String>,
     pub dns: Option<String>,
     pub vpc_name: Option<String>,
     #[serde(rename = "type", alias = "network_type")]
     pub network_type: Option<String>,
+    #[serde(default)]
+    pub vlan_id: Option<i32>,
+    #[serde(default)]
+    pub dhcp_options: DhcpOptions,
     pub status: String,
 }
 
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DhcpOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ntp_servers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtu: Option<u16>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub static_routes: Vec<DhcpStaticRoute>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DhcpStaticRoute {
+    pub destination: String,
+    pub gateway: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct NewNetwork {
     pub name: String,
@@ -470,11 +608,19 @@ pub struct NewNetwork {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub gateway: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub subnet6: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gateway6: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub dns: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub vpc_name: Option<String>,
     #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub network_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vlan_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dhcp_options: Option<DhcpOptions>,
 }
 
 #[derive(Debug, Serialize)]
@@ -515,6 +661,8 @@ pub struct SecurityGroupRule {
     pub direction: String,
     pub protocol: String,
     pub cidr: Option<String>,
+    #[serde(default)]
+    pub remote_security_group_id: Option<Uuid>,
     pub port_start: Option<i32>,
     pub port_end: Option<i32>,
     pub description: Option<String>,
@@ -527,6 +675,8 @@ pub struct NewSecurityGroupRule {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cidr: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_security_group_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub port_start: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub port_end: Option<i32>,
@@ -539,6 +689,106 @@ pub struct AttachSecurityGroupRequest {
     pub security_group_id: Uuid,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SecurityGroupStats {
+    pub security_group_id: Uuid,
+    pub rules: Vec<SecurityGroupRuleStats>,
+    pub members: Vec<SecurityGroupMemberStats>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SecurityGroupRuleStats {
+    pub rule_id: Uuid,
+    pub direction: String,
+    pub packets: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SecurityGroupMemberStats {
+    pub vm_id: Uuid,
+    pub dropped_ingress_packets: u64,
+    pub dropped_egress_packets: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VmFirewallLog {
+    pub enabled: bool,
+    pub denied: Vec<DeniedPacket>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeniedPacket {
+    pub timestamp: String,
+    pub direction: String,
+    pub protocol: String,
+    pub source: String,
+    pub destination: String,
+    pub source_port: Option<u32>,
+    pub destination_port: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpdateVmFirewallLog {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllowedAddressPair {
+    pub ip_address: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mac_address: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FloatingIp {
+    pub id: Uuid,
+    pub address: String,
+    pub description: Option<String>,
+    pub network_interface_id: Option<Uuid>,
+    pub vm_id: Option<Uuid>,
+    pub vm_ip: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NewFloatingIp {
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AssociateFloatingIpRequest {
+    pub vm_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_interface_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PortForward {
+    pub id: Uuid,
+    pub network_interface_id: Uuid,
+    pub vm_id: Uuid,
+    pub protocol: String,
+    pub host_port: i32,
+    pub vm_port: i32,
+    pub vm_ip: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NewPortForward {
+    pub vm_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_interface_id: Option<Uuid>,
+    pub protocol: String,
+    pub host_port: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vm_port: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
 // Jobs
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -549,6 +799,8 @@ pub struct Job {
     pub description: Option<String>,
     pub resource_id: Option<Uuid>,
     pub progress: Option<i32>,
+    #[serde(default)]
+    pub result: Option<serde_json::Value>,
     pub error: Option<String>,
     pub created_at: String,
     pub updated_at: String,
@@ -719,6 +971,29 @@ pub struct ImportToPoolResponse {
     pub storage_object_id: Uuid,
 }
 
+#[derive(Debug, Serialize)]
+pub struct PrewarmRequest {
+    pub image_ref: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host_ids: Option<Vec<Uuid>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrewarmResponse {
+    pub job_id: Uuid,
+    pub host_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageGcRequest {
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageGcResponse {
+    pub job_id: Uuid,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateDiskRequest {
     pub name: String,
@@ -726,8 +1001,14 @@ pub struct CreateDiskRequest {
     pub size_bytes: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_checksum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bandwidth_limit_bytes_per_sec: Option<i64>,
     #[serde(default)]
     pub preallocate: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_storage_object_id: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -743,6 +1024,28 @@ pub struct RegisterLunRequest {
     pub size_bytes: i64,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LunSyncEntry {
+    pub storage_object_id: Uuid,
+    pub name: String,
+    pub lun: u32,
+    pub size_bytes: i64,
+    #[serde(default)]
+    pub previous_size_bytes: Option<i64>,
+    #[serde(default)]
+    pub wwn: Option<String>,
+    #[serde(default)]
+    pub serial: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LunDiscoveryReport {
+    pub new: Vec<LunSyncEntry>,
+    pub resized: Vec<LunSyncEntry>,
+    pub vanished: Vec<LunSyncEntry>,
+    pub unchanged: usize,
+}
+
 // Sandboxes
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -865,6 +1168,84 @@ pub struct UpdateLifecycleHook {
     pub active: Option<bool>,
 }
 
+// Registry Credentials
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegistryCredential {
+    pub id: Uuid,
+    pub name: String,
+    pub registry: String,
+    pub username: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Serialize)]
+pub struct NewRegistryCredential {
+    pub name: String,
+    pub registry: String,
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Serialize)]
+pub struct UpdateRegistryCredential {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+// Image catalogs
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageCatalog {
+    pub id: Uuid,
+    pub name: String,
+    pub source_ref: String,
+    pub storage_pool_id: Uuid,
+    pub refresh_interval_secs: i32,
+    pub keep_versions: i32,
+    pub last_checked_at: Option<String>,
+    pub last_error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageCatalogVersion {
+    pub id: Uuid,
+    pub catalog_id: Uuid,
+    pub source_digest: String,
+    pub image_ref: String,
+    pub storage_object_id: Option<Uuid>,
+    pub created_at: String,
+}
+
+#[derive(Serialize)]
+pub struct NewImageCatalog {
+    pub name: String,
+    pub source_ref: String,
+    pub storage_pool_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_interval_secs: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_versions: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct UpdateImageCatalog {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_interval_secs: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_versions: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageCatalogRefreshResponse {
+    pub job_id: Uuid,
+}
+
 // Audit Logs
 
 #[derive(Debug, Serialize, Deserialize)]
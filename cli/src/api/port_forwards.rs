@@ -0,0 +1,23 @@
+use uuid::Uuid;
+
+use crate::client::Client;
+
+use super::models::{NewPortForward, PortForward};
+
+pub async fn list(client: &Client, vm_id: Option<Uuid>) -> anyhow::Result<Vec<PortForward>> {
+    let path = match vm_id {
+        Some(vm_id) => format!("/port-forwards?vm_id={vm_id}"),
+        None => "/port-forwards".to_string(),
+    };
+    client.get(&path).await
+}
+
+pub async fn create(client: &Client, forward: &NewPortForward) -> anyhow::Result<String> {
+    client.post_text("/port-forwards", forward).await
+}
+
+pub async fn delete(client: &Client, port_forward_id: Uuid) -> anyhow::Result<()> {
+    client
+        .delete(&format!("/port-forwards/{port_forward_id}"))
+        .await
+}
@@ -0,0 +1,35 @@
+use uuid::Uuid;
+
+use crate::client::Client;
+
+use super::models::{NewRegistryCredential, RegistryCredential, UpdateRegistryCredential};
+
+pub async fn list(client: &Client, name: Option<&str>) -> anyhow::Result<Vec<RegistryCredential>> {
+    let path = match name {
+        Some(n) => format!("/registry-credentials?name={n}"),
+        None => "/registry-credentials".to_string(),
+    };
+    client.get(&path).await
+}
+
+pub async fn get(client: &Client, id: Uuid) -> anyhow::Result<RegistryCredential> {
+    client.get(&format!("/registry-credentials/{id}")).await
+}
+
+pub async fn create(client: &Client, credential: &NewRegistryCredential) -> anyhow::Result<String> {
+    client.post_text("/registry-credentials", credential).await
+}
+
+pub async fn update(
+    client: &Client,
+    id: Uuid,
+    req: &UpdateRegistryCredential,
+) -> anyhow::Result<RegistryCredential> {
+    client
+        .patch(&format!("/registry-credentials/{id}"), req)
+        .await
+}
+
+pub async fn delete(client: &Client, id: Uuid) -> anyhow::Result<()> {
+    client.delete(&format!("/registry-credentials/{id}")).await
+}
@@ -4,7 +4,7 @@ use crate::client::Client;
 
 use super::models::{
     AttachSecurityGroupRequest, NewSecurityGroup, NewSecurityGroupRule, SecurityGroup,
-    SecurityGroupRule,
+    SecurityGroupRule, SecurityGroupStats,
 };
 
 pub async fn list(client: &Client, name: Option<&str>) -> anyhow::Result<Vec<SecurityGroup>> {
@@ -40,6 +40,12 @@ pub async fn list_rules(
         .await
 }
 
+pub async fn stats(client: &Client, security_group_id: Uuid) -> anyhow::Result<SecurityGroupStats> {
+    client
+        .get(&format!("/security-groups/{security_group_id}/stats"))
+        .await
+}
+
 pub async fn create_rule(
     client: &Client,
     security_group_id: Uuid,
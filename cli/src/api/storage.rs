@@ -3,9 +3,10 @@ use uuid::Uuid;
 use crate::client::Client;
 
 use super::models::{
-    AttachHostToPoolRequest, CreateDiskRequest, CreateDiskResponse, ImportToPoolRequest,
-    ImportToPoolResponse, NewStorageObject, NewStoragePool, RegisterLunRequest, StorageObject,
-    StoragePool,
+    AttachHostToPoolRequest, CreateDiskRequest, CreateDiskResponse, HostPathHealth, ImageGcRequest,
+    ImageGcResponse, ImportToPoolRequest, ImportToPoolResponse, LunDiscoveryReport,
+    NewStorageObject, NewStoragePool, PrewarmRequest, PrewarmResponse, RegisterLunRequest,
+    StorageObject, StoragePool,
 };
 
 // Storage pools
@@ -55,6 +56,13 @@ pub async fn detach_host_from_pool(
         .await
 }
 
+pub async fn list_pool_paths(
+    client: &Client,
+    pool_id: Uuid,
+) -> anyhow::Result<Vec<HostPathHealth>> {
+    client.get(&format!("/storage-pools/{pool_id}/paths")).await
+}
+
 // Storage objects
 
 pub async fn list_objects(
@@ -116,6 +124,26 @@ pub async fn import_to_pool(
         .await
 }
 
+pub async fn prewarm(
+    client: &Client,
+    pool_id: Uuid,
+    req: &PrewarmRequest,
+) -> anyhow::Result<PrewarmResponse> {
+    client
+        .post(&format!("/storage-pools/{pool_id}/prewarm"), req)
+        .await
+}
+
+pub async fn gc_images(
+    client: &Client,
+    pool_id: Uuid,
+    req: &ImageGcRequest,
+) -> anyhow::Result<ImageGcResponse> {
+    client
+        .post(&format!("/storage-pools/{pool_id}/gc"), req)
+        .await
+}
+
 pub async fn register_lun(
     client: &Client,
     pool_id: Uuid,
@@ -125,3 +153,9 @@ pub async fn register_lun(
         .post(&format!("/storage-pools/{pool_id}/luns"), req)
         .await
 }
+
+pub async fn discover_luns(client: &Client, pool_id: Uuid) -> anyhow::Result<LunDiscoveryReport> {
+    client
+        .post_empty_json(&format!("/storage-pools/{pool_id}/luns/discover"))
+        .await
+}
@@ -32,3 +32,27 @@ pub async fn create(
         .post(&format!("/storage-pools/{pool_id}/transfers"), transfer)
         .await
 }
+
+/// Sends bytes `start..start + data.len()` of a `total`-byte upload. The
+/// returned transfer is `completed` once the last chunk has been stored.
+pub async fn upload_chunk(
+    client: &Client,
+    pool_id: Uuid,
+    name: &str,
+    object_type: &str,
+    start: i64,
+    total: i64,
+    data: Vec<u8>,
+) -> anyhow::Result<Transfer> {
+    let end = start + data.len() as i64 - 1;
+    client
+        .put_bytes(
+            &format!(
+                "/storage-pools/{pool_id}/uploads/{}?object_type={object_type}",
+                urlencoding::encode(name)
+            ),
+            &[("content-range", format!("bytes {start}-{end}/{total}"))],
+            data,
+        )
+        .await
+}
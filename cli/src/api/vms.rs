@@ -5,11 +5,13 @@ use uuid::Uuid;
 use crate::client::Client;
 
 use super::models::{
-    AttachDiskRequest, CommitVmRequest, CommitVmResponse, CreateSnapshotRequest, CreateVmResponse,
-    CreateVmResult, DiskResizeRequest, ExecVmRequest, ExecVmResponse, HotplugNicRequest,
-    NetworkInterface, NewVm, RestoreRequest, SecurityGroup, Snapshot, StorageObject, Vm, VmDisk,
-    VmImagePreflightRequest, VmImagePreflightResponse, VmMigrateRequest, VmMigrateResponse,
-    VmResizeRequest, VmStartResponse,
+    AllowedAddressPair, AttachDiskRequest, CommitVmRequest, CommitVmResponse,
+    CreateSnapshotRequest, CreateVmResponse, CreateVmResult, DiskResizeRequest, ExecVmRequest,
+    ExecVmResponse, ExportVmRequest, ExportVmResponse, HotplugNicRequest, ImportVmRequest,
+    ImportVmResponse, NetworkInterface, NewVm, RestoreRequest, SecurityGroup, Snapshot,
+    StorageObject, UpdateVmFirewallLog, Vm, VmDisk, VmFirewallLog, VmImagePreflightRequest,
+    VmImagePreflightResponse, VmMigrateRequest, VmMigrateResponse, VmResizeRequest,
+    VmStartResponse,
 };
 
 pub async fn list(client: &Client, name: Option<&str>, tags: &[String]) -> anyhow::Result<Vec<Vm>> {
@@ -132,6 +134,32 @@ pub async fn list_nics(client: &Client, vm_id: Uuid) -> anyhow::Result<Vec<Netwo
     client.get(&format!("/vms/{vm_id}/nics")).await
 }
 
+pub async fn list_allowed_address_pairs(
+    client: &Client,
+    vm_id: Uuid,
+    device_id: &str,
+) -> anyhow::Result<Vec<AllowedAddressPair>> {
+    client
+        .get(&format!(
+            "/vms/{vm_id}/nics/{device_id}/allowed-address-pairs"
+        ))
+        .await
+}
+
+pub async fn update_allowed_address_pairs(
+    client: &Client,
+    vm_id: Uuid,
+    device_id: &str,
+    pairs: &[AllowedAddressPair],
+) -> anyhow::Result<Vec<AllowedAddressPair>> {
+    client
+        .put(
+            &format!("/vms/{vm_id}/nics/{device_id}/allowed-address-pairs"),
+            &pairs,
+        )
+        .await
+}
+
 pub async fn list_security_groups(
     client: &Client,
     vm_id: Uuid,
@@ -139,6 +167,24 @@ pub async fn list_security_groups(
     client.get(&format!("/vms/{vm_id}/security-groups")).await
 }
 
+pub async fn get_firewall_log(client: &Client, vm_id: Uuid) -> anyhow::Result<VmFirewallLog> {
+    client.get(&format!("/vms/{vm_id}/firewall-log")).await
+}
+
+pub async fn update_firewall_log(
+    client: &Client,
+    vm_id: Uuid,
+    enabled: bool,
+) -> anyhow::Result<()> {
+    let _: serde_json::Value = client
+        .put(
+            &format!("/vms/{vm_id}/firewall-log"),
+            &UpdateVmFirewallLog { enabled },
+        )
+        .await?;
+    Ok(())
+}
+
 pub async fn create_snapshot(
     client: &Client,
     vm_id: Uuid,
@@ -159,6 +205,16 @@ pub async fn list_snapshots(
     client.get(&path).await
 }
 
+pub async fn delete_snapshot(
+    client: &Client,
+    vm_id: Uuid,
+    snapshot_id: Uuid,
+) -> anyhow::Result<()> {
+    client
+        .delete(&format!("/vms/{vm_id}/snapshots/{snapshot_id}"))
+        .await
+}
+
 pub async fn restore(client: &Client, vm_id: Uuid, req: &RestoreRequest) -> anyhow::Result<Vm> {
     client.post(&format!("/vms/{vm_id}/restore"), req).await
 }
@@ -193,3 +249,15 @@ pub async fn commit(
 ) -> anyhow::Result<CommitVmResponse> {
     client.post(&format!("/vms/{vm_id}/commit"), req).await
 }
+
+pub async fn export(
+    client: &Client,
+    vm_id: Uuid,
+    req: &ExportVmRequest,
+) -> anyhow::Result<ExportVmResponse> {
+    client.post(&format!("/vms/{vm_id}/export"), req).await
+}
+
+pub async fn import(client: &Client, req: &ImportVmRequest) -> anyhow::Result<ImportVmResponse> {
+    client.post("/vms/import", req).await
+}
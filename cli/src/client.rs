@@ -155,6 +155,33 @@ impl Client {
             .context("failed to parse response")
     }
 
+    /// PUT request with a raw byte body and the given extra headers,
+    /// deserializing the response as JSON.
+    pub async fn put_bytes<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        headers: &[(&str, String)],
+        body: Vec<u8>,
+    ) -> anyhow::Result<T> {
+        let mut request = self
+            .inner
+            .put(self.url(path))
+            .header("content-type", "application/octet-stream")
+            .body(body);
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+        let resp = request
+            .send()
+            .await
+            .with_context(|| format!("PUT {path}"))?;
+        Self::check_error(resp)
+            .await?
+            .json()
+            .await
+            .context("failed to parse response")
+    }
+
     /// PATCH request with a JSON body, deserializing the response as JSON.
     pub async fn patch<B: Serialize, T: DeserializeOwned>(
         &self,
@@ -40,6 +40,11 @@ enum BackupCommand {
         /// Backup name or ID
         backup: String,
     },
+    /// Delete a backup and remove its files from the storage pool
+    Delete {
+        /// Backup name or ID
+        backup: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -215,6 +220,11 @@ pub async fn run(args: BackupArgs, client: &Client, output: OutputFormat) -> any
                 }
             }
         }
+        BackupCommand::Delete { backup } => {
+            let backup_id = resolve_backup_id(client, &backup).await?;
+            api::backups::delete(client, backup_id).await?;
+            println!("Deleted backup: {backup_id}");
+        }
     }
 
     Ok(())
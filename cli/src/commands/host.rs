@@ -118,6 +118,11 @@ enum HostCommand {
         /// Host name or ID
         host: String,
     },
+    /// Show OverlayBD layer cache usage and hit/miss counters on a host
+    OverlaybdCache {
+        /// Host name or ID
+        host: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -205,6 +210,20 @@ struct GpuRow {
     vm_id: String,
 }
 
+#[derive(Tabled)]
+struct CachedImageRow {
+    #[tabled(rename = "Image")]
+    image_ref: String,
+    #[tabled(rename = "Layers")]
+    layers: String,
+    #[tabled(rename = "Cached")]
+    cached: String,
+    #[tabled(rename = "In use")]
+    in_use: bool,
+    #[tabled(rename = "Last used")]
+    last_used_at: String,
+}
+
 #[derive(Tabled)]
 struct HostResourcesRow {
     #[tabled(rename = "Resource")]
@@ -565,6 +584,38 @@ pub async fn run(args: HostArgs, client: &Client, output: OutputFormat) -> anyho
                 println!("{}", Table::new(rows).with(Style::psql()));
             }
         }
+
+        HostCommand::OverlaybdCache { host } => {
+            let id = resolve_host_id(client, &host).await?;
+            let cache = api::hosts::overlaybd_cache(client, id).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&cache, output)?;
+            } else {
+                let cap = cache
+                    .max_bytes
+                    .map(|b| format_bytes(b as i64))
+                    .unwrap_or_else(|| "unlimited".to_string());
+                println!(
+                    "Used:      {} of {}",
+                    format_bytes(cache.used_bytes as i64),
+                    cap
+                );
+                println!("Layers:    {} hits, {} misses", cache.hits, cache.misses);
+                println!("Evictions: {}", cache.evictions);
+                let rows: Vec<CachedImageRow> = cache
+                    .images
+                    .iter()
+                    .map(|i| CachedImageRow {
+                        image_ref: i.image_ref.clone(),
+                        layers: format!("{}/{}", i.cached_layers, i.layers),
+                        cached: format_bytes(i.cached_bytes as i64),
+                        in_use: i.in_use,
+                        last_used_at: i.last_used_at.clone().unwrap_or_else(|| "-".into()),
+                    })
+                    .collect();
+                println!("{}", Table::new(rows).with(Style::psql()));
+            }
+        }
     }
 
     Ok(())
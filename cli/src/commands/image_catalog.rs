@@ -0,0 +1,245 @@
+use clap::{Args, Subcommand};
+use tabled::{Table, Tabled, settings::Style};
+
+use crate::{
+    api::{
+        self,
+        models::{NewImageCatalog, UpdateImageCatalog},
+    },
+    client::Client,
+};
+
+use super::{
+    OutputFormat, print_output, resolve_image_catalog_id, resolve_pool_id,
+    storage::poll_job_to_completion,
+};
+
+#[derive(Args)]
+pub struct ImageCatalogArgs {
+    #[command(subcommand)]
+    command: ImageCatalogCommand,
+}
+
+#[derive(Subcommand)]
+enum ImageCatalogCommand {
+    /// List image catalogs
+    List,
+    /// Get details of an image catalog and its versions
+    Get {
+        /// Catalog name or ID
+        catalog: String,
+    },
+    /// Track an upstream image and import every new digest of it
+    Create {
+        /// Catalog name
+        #[arg(long)]
+        name: String,
+        /// Upstream image reference to follow, e.g. docker.io/library/ubuntu:24.04
+        #[arg(long)]
+        source: String,
+        /// OverlayBD pool name or ID to import versions into
+        #[arg(long)]
+        pool: String,
+        /// Seconds between digest checks (default: one day)
+        #[arg(long)]
+        refresh_interval: Option<i32>,
+        /// Number of versions to keep (default: 3)
+        #[arg(long)]
+        keep: Option<i32>,
+    },
+    /// Change the refresh interval or retention of a catalog
+    Update {
+        /// Catalog name or ID
+        catalog: String,
+        /// Seconds between digest checks
+        #[arg(long)]
+        refresh_interval: Option<i32>,
+        /// Number of versions to keep
+        #[arg(long)]
+        keep: Option<i32>,
+    },
+    /// Check the upstream digest now and import it if it changed
+    Refresh {
+        /// Catalog name or ID
+        catalog: String,
+    },
+    /// Stop tracking an image (imported versions are kept)
+    Delete {
+        /// Catalog name or ID
+        catalog: String,
+    },
+}
+
+#[derive(Tabled)]
+struct ImageCatalogRow {
+    #[tabled(rename = "ID")]
+    id: String,
+    #[tabled(rename = "Name")]
+    name: String,
+    #[tabled(rename = "Source")]
+    source_ref: String,
+    #[tabled(rename = "Keep")]
+    keep_versions: i32,
+    #[tabled(rename = "Last checked")]
+    last_checked_at: String,
+    #[tabled(rename = "Last error")]
+    last_error: String,
+}
+
+#[derive(Tabled)]
+struct ImageCatalogVersionRow {
+    #[tabled(rename = "Digest")]
+    digest: String,
+    #[tabled(rename = "Storage object")]
+    storage_object_id: String,
+    #[tabled(rename = "Imported")]
+    created_at: String,
+}
+
+pub async fn run(
+    args: ImageCatalogArgs,
+    client: &Client,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    match args.command {
+        ImageCatalogCommand::List => {
+            let catalogs = api::image_catalogs::list(client, None).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&catalogs, output)?;
+            } else {
+                let rows: Vec<ImageCatalogRow> = catalogs
+                    .iter()
+                    .map(|c| ImageCatalogRow {
+                        id: c.id.to_string(),
+                        name: c.name.clone(),
+                        source_ref: c.source_ref.clone(),
+                        keep_versions: c.keep_versions,
+                        last_checked_at: c
+                            .last_checked_at
+                            .clone()
+                            .unwrap_or_else(|| "never".to_string()),
+                        last_error: c.last_error.clone().unwrap_or_else(|| "-".to_string()),
+                    })
+                    .collect();
+                println!("{}", Table::new(rows).with(Style::psql()));
+            }
+        }
+
+        ImageCatalogCommand::Get { catalog } => {
+            let id = resolve_image_catalog_id(client, &catalog).await?;
+            let c = api::image_catalogs::get(client, id).await?;
+            let versions = api::image_catalogs::list_versions(client, id).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(
+                    &serde_json::json!({ "catalog": c, "versions": versions }),
+                    output,
+                )?;
+            } else {
+                println!("ID:           {}", c.id);
+                println!("Name:         {}", c.name);
+                println!("Source:       {}", c.source_ref);
+                println!("Pool:         {}", c.storage_pool_id);
+                println!("Refresh:      every {}s", c.refresh_interval_secs);
+                println!("Keep:         {}", c.keep_versions);
+                println!(
+                    "Last checked: {}",
+                    c.last_checked_at.as_deref().unwrap_or("never")
+                );
+                if let Some(error) = &c.last_error {
+                    println!("Last error:   {error}");
+                }
+                if let Some(latest) = versions.first() {
+                    println!("Latest:       {}", latest.image_ref);
+                }
+                let rows: Vec<ImageCatalogVersionRow> = versions
+                    .iter()
+                    .map(|v| ImageCatalogVersionRow {
+                        digest: v.source_digest.clone(),
+                        storage_object_id: v
+                            .storage_object_id
+                            .map(|id| id.to_string())
+                            .unwrap_or_else(|| "-".to_string()),
+                        created_at: v.created_at.clone(),
+                    })
+                    .collect();
+                if !rows.is_empty() {
+                    println!("{}", Table::new(rows).with(Style::psql()));
+                }
+            }
+        }
+
+        ImageCatalogCommand::Create {
+            name,
+            source,
+            pool,
+            refresh_interval,
+            keep,
+        } => {
+            let storage_pool_id = resolve_pool_id(client, &pool).await?;
+            let new_catalog = NewImageCatalog {
+                name,
+                source_ref: source,
+                storage_pool_id,
+                refresh_interval_secs: refresh_interval,
+                keep_versions: keep,
+            };
+            let id = api::image_catalogs::create(client, &new_catalog).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&serde_json::json!({ "image_catalog_id": id }), output)?;
+            } else {
+                println!("Created image catalog: {id}");
+            }
+        }
+
+        ImageCatalogCommand::Update {
+            catalog,
+            refresh_interval,
+            keep,
+        } => {
+            let id = resolve_image_catalog_id(client, &catalog).await?;
+            let req = UpdateImageCatalog {
+                refresh_interval_secs: refresh_interval,
+                keep_versions: keep,
+            };
+            let updated = api::image_catalogs::update(client, id, &req).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&updated, output)?;
+            } else {
+                println!("Updated image catalog: {}", updated.name);
+            }
+        }
+
+        ImageCatalogCommand::Refresh { catalog } => {
+            let id = resolve_image_catalog_id(client, &catalog).await?;
+            let resp = api::image_catalogs::refresh(client, id).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&resp, output)?;
+            } else {
+                println!("Refresh job: {}", resp.job_id);
+                poll_job_to_completion(client, resp.job_id, "Image catalog refresh").await?;
+                let result = api::jobs::get(client, resp.job_id)
+                    .await?
+                    .result
+                    .unwrap_or_default();
+                let digest = result["digest"].as_str().unwrap_or_default();
+                if result["imported"].as_bool().unwrap_or(false) {
+                    println!("Imported new version {digest}");
+                    let pruned = result["pruned"].as_u64().unwrap_or(0);
+                    if pruned > 0 {
+                        println!("Pruned {pruned} old version(s)");
+                    }
+                } else {
+                    println!("Up to date at {digest}");
+                }
+            }
+        }
+
+        ImageCatalogCommand::Delete { catalog } => {
+            let id = resolve_image_catalog_id(client, &catalog).await?;
+            api::image_catalogs::delete(client, id).await?;
+            println!("Deleted image catalog: {id}");
+        }
+    }
+
+    Ok(())
+}
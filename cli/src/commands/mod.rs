@@ -4,9 +4,11 @@ pub mod boot_source;
 pub mod configure;
 pub mod hook;
 pub mod host;
+pub mod image_catalog;
 pub mod instance_type;
 pub mod job;
 pub mod network;
+pub mod registry_credential;
 pub mod sandbox;
 pub mod security_group;
 pub mod storage;
@@ -91,6 +93,19 @@ pub async fn resolve_network_id(client: &Client, name_or_id: &str) -> anyhow::Re
         .ok_or_else(|| anyhow::anyhow!("no network named {:?}", name_or_id))
 }
 
+/// Resolve a floating IP address or UUID string to a UUID.
+pub async fn resolve_floating_ip_id(client: &Client, address_or_id: &str) -> anyhow::Result<Uuid> {
+    if let Ok(id) = Uuid::parse_str(address_or_id) {
+        return Ok(id);
+    }
+    api::floating_ips::list(client)
+        .await?
+        .into_iter()
+        .find(|floating_ip| floating_ip.address == address_or_id)
+        .map(|floating_ip| floating_ip.id)
+        .ok_or_else(|| anyhow::anyhow!("no floating IP {:?}", address_or_id))
+}
+
 /// Resolve a security-group name or UUID string to a UUID.
 pub async fn resolve_security_group_id(client: &Client, name_or_id: &str) -> anyhow::Result<Uuid> {
     if let Ok(id) = Uuid::parse_str(name_or_id) {
@@ -117,6 +132,35 @@ pub async fn resolve_hook_id(client: &Client, name_or_id: &str) -> anyhow::Resul
         .ok_or_else(|| anyhow::anyhow!("no hook named {:?}", name_or_id))
 }
 
+/// Resolve a registry credential name or UUID string to a UUID.
+pub async fn resolve_registry_credential_id(
+    client: &Client,
+    name_or_id: &str,
+) -> anyhow::Result<Uuid> {
+    if let Ok(id) = Uuid::parse_str(name_or_id) {
+        return Ok(id);
+    }
+    let credentials = api::registry_credentials::list(client, Some(name_or_id)).await?;
+    credentials
+        .into_iter()
+        .next()
+        .map(|c| c.id)
+        .ok_or_else(|| anyhow::anyhow!("no registry credential named {:?}", name_or_id))
+}
+
+/// Resolve an image catalog name or UUID string to a UUID.
+pub async fn resolve_image_catalog_id(client: &Client, name_or_id: &str) -> anyhow::Result<Uuid> {
+    if let Ok(id) = Uuid::parse_str(name_or_id) {
+        return Ok(id);
+    }
+    let catalogs = api::image_catalogs::list(client, Some(name_or_id)).await?;
+    catalogs
+        .into_iter()
+        .next()
+        .map(|c| c.id)
+        .ok_or_else(|| anyhow::anyhow!("no image catalog named {:?}", name_or_id))
+}
+
 /// Resolve a boot source name or UUID string to a UUID.
 pub async fn resolve_boot_source_id(client: &Client, name_or_id: &str) -> anyhow::Result<Uuid> {
     if let Ok(id) = Uuid::parse_str(name_or_id) {
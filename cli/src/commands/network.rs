@@ -2,11 +2,20 @@ use clap::{Args, Subcommand};
 use tabled::{Table, Tabled, settings::Style};
 
 use crate::{
-    api::{self, models::NewNetwork},
+    api::{
+        self,
+        models::{
+            AssociateFloatingIpRequest, DhcpOptions, DhcpStaticRoute, NewFloatingIp, NewNetwork,
+            NewPortForward,
+        },
+    },
     client::Client,
 };
 
-use super::{OutputFormat, print_output, resolve_host_id, resolve_network_id};
+use super::{
+    OutputFormat, print_output, resolve_floating_ip_id, resolve_host_id, resolve_network_id,
+    resolve_vm_id,
+};
 
 #[derive(Args)]
 pub struct NetworkArgs {
@@ -34,15 +43,36 @@ enum NetworkCommand {
         /// Gateway IP address (e.g. 10.0.0.1)
         #[arg(long)]
         gateway: Option<String>,
+        /// IPv6 /64 for a dual-stack network (e.g. fd00:10::/64)
+        #[arg(long)]
+        subnet6: Option<String>,
+        /// IPv6 gateway address (defaults to the first address of --subnet6)
+        #[arg(long, requires = "subnet6")]
+        gateway6: Option<String>,
         /// DNS server IP address
         #[arg(long)]
         dns: Option<String>,
         /// Optional VPC name. Networks with the same VPC name can route between subnets across attached hosts.
         #[arg(long)]
         vpc: Option<String>,
-        /// Network type (bridge or vlan)
+        /// Network type (bridge, isolated, stretched or vlan)
         #[arg(long, value_name = "TYPE", default_value = "bridge")]
         network_type: String,
+        /// 802.1Q VLAN id (1-4094) for --network-type vlan
+        #[arg(long)]
+        vlan_id: Option<i32>,
+        /// DNS search domain handed out by DHCP (defaults to <name>.internal)
+        #[arg(long)]
+        domain_name: Option<String>,
+        /// NTP server handed out by DHCP (repeatable)
+        #[arg(long = "ntp-server", value_name = "IP")]
+        ntp_servers: Vec<String>,
+        /// Interface MTU handed out by DHCP
+        #[arg(long)]
+        mtu: Option<u16>,
+        /// Extra route handed out by DHCP, e.g. 10.50.0.0/16=10.0.0.254 (repeatable)
+        #[arg(long = "static-route", value_name = "DEST=GATEWAY", value_parser = parse_static_route)]
+        static_routes: Vec<DhcpStaticRoute>,
     },
     /// Delete a network
     Delete {
@@ -62,7 +92,8 @@ enum NetworkCommand {
         bridge_name: String,
         /// Parent NIC to bridge (e.g. eth0). If set, bridges the NIC instead of
         /// creating an isolated bridge — skips NAT. VMs get IPs from
-        /// the upstream network.
+        /// the upstream network. Required for vlan networks, which bridge
+        /// the NIC's tagged sub-interface instead.
         #[arg(long)]
         parent_interface: Option<String>,
     },
@@ -80,6 +111,86 @@ enum NetworkCommand {
         /// Network name or ID
         network: String,
     },
+    /// Manage floating IPs: uplink addresses NATed 1:1 to a VM
+    FloatingIp {
+        #[command(subcommand)]
+        command: FloatingIpCommand,
+    },
+    /// Manage host ports forwarded to VMs
+    PortForward {
+        #[command(subcommand)]
+        command: PortForwardCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum FloatingIpCommand {
+    /// List floating IPs
+    List,
+    /// Reserve a floating IP
+    Create {
+        /// IPv4 address routed to the hosts' uplink network
+        #[arg(long)]
+        address: String,
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Release and delete a floating IP
+    Delete {
+        /// Floating IP address or ID
+        floating_ip: String,
+    },
+    /// Point a floating IP at a VM, moving it if already associated
+    Associate {
+        /// Floating IP address or ID
+        floating_ip: String,
+        /// VM name or ID
+        #[arg(long)]
+        vm: String,
+        /// Network interface ID (defaults to the VM's first managed NIC)
+        #[arg(long)]
+        interface: Option<uuid::Uuid>,
+    },
+    /// Detach a floating IP from its VM
+    Disassociate {
+        /// Floating IP address or ID
+        floating_ip: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum PortForwardCommand {
+    /// List port forwards
+    List {
+        /// Only show forwards to this VM (name or ID)
+        #[arg(long)]
+        vm: Option<String>,
+    },
+    /// Forward a port on the VM's host to the VM
+    Create {
+        /// VM name or ID
+        #[arg(long)]
+        vm: String,
+        /// tcp or udp
+        #[arg(long, default_value = "tcp")]
+        protocol: String,
+        /// Port on the host; unique across the cluster
+        #[arg(long)]
+        host_port: i32,
+        /// Port on the VM (defaults to --host-port)
+        #[arg(long)]
+        vm_port: Option<i32>,
+        /// Network interface ID (defaults to the VM's first managed NIC)
+        #[arg(long)]
+        interface: Option<uuid::Uuid>,
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Delete a port forward
+    Delete {
+        /// Port forward ID
+        port_forward_id: uuid::Uuid,
+    },
 }
 
 #[derive(Tabled)]
@@ -90,6 +201,8 @@ struct NetworkRow {
     name: String,
     #[tabled(rename = "Subnet")]
     subnet: String,
+    #[tabled(rename = "Subnet6")]
+    subnet6: String,
     #[tabled(rename = "Gateway")]
     gateway: String,
     #[tabled(rename = "VPC")]
@@ -100,6 +213,34 @@ struct NetworkRow {
     status: String,
 }
 
+#[derive(Tabled)]
+struct FloatingIpRow {
+    #[tabled(rename = "ID")]
+    id: String,
+    #[tabled(rename = "Address")]
+    address: String,
+    #[tabled(rename = "VM")]
+    vm_id: String,
+    #[tabled(rename = "VM IP")]
+    vm_ip: String,
+    #[tabled(rename = "Description")]
+    description: String,
+}
+
+#[derive(Tabled)]
+struct PortForwardRow {
+    #[tabled(rename = "ID")]
+    id: String,
+    #[tabled(rename = "Protocol")]
+    protocol: String,
+    #[tabled(rename = "Host Port")]
+    host_port: i32,
+    #[tabled(rename = "VM")]
+    vm_id: String,
+    #[tabled(rename = "Target")]
+    target: String,
+}
+
 #[derive(Tabled)]
 struct IpRow {
     #[tabled(rename = "IP")]
@@ -123,6 +264,7 @@ pub async fn run(args: NetworkArgs, client: &Client, output: OutputFormat) -> an
                         id: n.id.to_string(),
                         name: n.name.clone(),
                         subnet: n.subnet.clone(),
+                        subnet6: n.subnet6.clone().unwrap_or_else(|| "-".to_string()),
                         gateway: n.gateway.clone().unwrap_or_else(|| "-".to_string()),
                         vpc_name: n.vpc_name.clone().unwrap_or_else(|| "-".to_string()),
                         network_type: n.network_type.clone().unwrap_or_else(|| "-".to_string()),
@@ -146,6 +288,10 @@ pub async fn run(args: NetworkArgs, client: &Client, output: OutputFormat) -> an
                     "Gateway: {}",
                     net.gateway.unwrap_or_else(|| "-".to_string())
                 );
+                if let Some(subnet6) = &net.subnet6 {
+                    println!("Subnet6: {subnet6}");
+                    println!("Gateway6: {}", net.gateway6.as_deref().unwrap_or("-"));
+                }
                 println!("DNS:     {}", net.dns.unwrap_or_else(|| "-".to_string()));
                 println!(
                     "VPC:     {}",
@@ -155,6 +301,22 @@ pub async fn run(args: NetworkArgs, client: &Client, output: OutputFormat) -> an
                     "Type:    {}",
                     net.network_type.unwrap_or_else(|| "-".to_string())
                 );
+                if let Some(vlan_id) = net.vlan_id {
+                    println!("VLAN:    {vlan_id}");
+                }
+                let dhcp = &net.dhcp_options;
+                if let Some(domain_name) = &dhcp.domain_name {
+                    println!("Domain:  {domain_name}");
+                }
+                if !dhcp.ntp_servers.is_empty() {
+                    println!("NTP:     {}", dhcp.ntp_servers.join(", "));
+                }
+                if let Some(mtu) = dhcp.mtu {
+                    println!("MTU:     {mtu}");
+                }
+                for route in &dhcp.static_routes {
+                    println!("Route:   {} via {}", route.destination, route.gateway);
+                }
                 println!("Status:  {}", net.status);
             }
         }
@@ -163,17 +325,38 @@ pub async fn run(args: NetworkArgs, client: &Client, output: OutputFormat) -> an
             name,
             subnet,
             gateway,
+            subnet6,
+            gateway6,
             dns,
             vpc,
             network_type,
+            vlan_id,
+            domain_name,
+            ntp_servers,
+            mtu,
+            static_routes,
         } => {
+            let dhcp_options = DhcpOptions {
+                domain_name,
+                ntp_servers,
+                mtu,
+                static_routes,
+            };
+            let has_dhcp_options = dhcp_options.domain_name.is_some()
+                || !dhcp_options.ntp_servers.is_empty()
+                || dhcp_options.mtu.is_some()
+                || !dhcp_options.static_routes.is_empty();
             let new_net = NewNetwork {
                 name,
                 subnet,
                 gateway,
+                subnet6,
+                gateway6,
                 dns,
                 vpc_name: vpc,
                 network_type: Some(network_type),
+                vlan_id,
+                dhcp_options: has_dhcp_options.then_some(dhcp_options),
             };
             let id = api::networks::create(client, &new_net).await?;
             if !matches!(output, OutputFormat::Table) {
@@ -249,7 +432,185 @@ pub async fn run(args: NetworkArgs, client: &Client, output: OutputFormat) -> an
                 println!("{}", Table::new(rows).with(Style::psql()));
             }
         }
+
+        NetworkCommand::FloatingIp { command } => {
+            run_floating_ip(command, client, output).await?;
+        }
+
+        NetworkCommand::PortForward { command } => {
+            run_port_forward(command, client, output).await?;
+        }
     }
 
     Ok(())
 }
+
+async fn run_floating_ip(
+    command: FloatingIpCommand,
+    client: &Client,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    match command {
+        FloatingIpCommand::List => {
+            let floating_ips = api::floating_ips::list(client).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&floating_ips, output)?;
+            } else {
+                let rows: Vec<FloatingIpRow> = floating_ips
+                    .iter()
+                    .map(|f| FloatingIpRow {
+                        id: f.id.to_string(),
+                        address: f.address.clone(),
+                        vm_id: f
+                            .vm_id
+                            .map(|v| v.to_string())
+                            .unwrap_or_else(|| "-".to_string()),
+                        vm_ip: f.vm_ip.clone().unwrap_or_else(|| "-".to_string()),
+                        description: f.description.clone().unwrap_or_else(|| "-".to_string()),
+                    })
+                    .collect();
+                println!("{}", Table::new(rows).with(Style::psql()));
+            }
+        }
+
+        FloatingIpCommand::Create {
+            address,
+            description,
+        } => {
+            let id = api::floating_ips::create(
+                client,
+                &NewFloatingIp {
+                    address,
+                    description,
+                },
+            )
+            .await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&serde_json::json!({ "floating_ip_id": id }), output)?;
+            } else {
+                println!("Created floating IP: {id}");
+            }
+        }
+
+        FloatingIpCommand::Delete { floating_ip } => {
+            let id = resolve_floating_ip_id(client, &floating_ip).await?;
+            api::floating_ips::delete(client, id).await?;
+            println!("Deleted floating IP: {floating_ip}");
+        }
+
+        FloatingIpCommand::Associate {
+            floating_ip,
+            vm,
+            interface,
+        } => {
+            let id = resolve_floating_ip_id(client, &floating_ip).await?;
+            let vm_id = resolve_vm_id(client, &vm).await?;
+            let associated = api::floating_ips::associate(
+                client,
+                id,
+                &AssociateFloatingIpRequest {
+                    vm_id,
+                    network_interface_id: interface,
+                },
+            )
+            .await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&associated, output)?;
+            } else {
+                println!(
+                    "Associated {} with VM {vm} ({})",
+                    associated.address,
+                    associated.vm_ip.as_deref().unwrap_or("-")
+                );
+            }
+        }
+
+        FloatingIpCommand::Disassociate { floating_ip } => {
+            let id = resolve_floating_ip_id(client, &floating_ip).await?;
+            let released = api::floating_ips::disassociate(client, id).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&released, output)?;
+            } else {
+                println!("Disassociated floating IP: {}", released.address);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_port_forward(
+    command: PortForwardCommand,
+    client: &Client,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    match command {
+        PortForwardCommand::List { vm } => {
+            let vm_id = match vm {
+                Some(vm) => Some(resolve_vm_id(client, &vm).await?),
+                None => None,
+            };
+            let forwards = api::port_forwards::list(client, vm_id).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&forwards, output)?;
+            } else {
+                let rows: Vec<PortForwardRow> = forwards
+                    .iter()
+                    .map(|f| PortForwardRow {
+                        id: f.id.to_string(),
+                        protocol: f.protocol.clone(),
+                        host_port: f.host_port,
+                        vm_id: f.vm_id.to_string(),
+                        target: format!("{}:{}", f.vm_ip.as_deref().unwrap_or("-"), f.vm_port),
+                    })
+                    .collect();
+                println!("{}", Table::new(rows).with(Style::psql()));
+            }
+        }
+
+        PortForwardCommand::Create {
+            vm,
+            protocol,
+            host_port,
+            vm_port,
+            interface,
+            description,
+        } => {
+            let vm_id = resolve_vm_id(client, &vm).await?;
+            let id = api::port_forwards::create(
+                client,
+                &NewPortForward {
+                    vm_id,
+                    network_interface_id: interface,
+                    protocol,
+                    host_port,
+                    vm_port,
+                    description,
+                },
+            )
+            .await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&serde_json::json!({ "port_forward_id": id }), output)?;
+            } else {
+                println!("Created port forward: {id}");
+            }
+        }
+
+        PortForwardCommand::Delete { port_forward_id } => {
+            api::port_forwards::delete(client, port_forward_id).await?;
+            println!("Deleted port forward: {port_forward_id}");
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_static_route(value: &str) -> anyhow::Result<DhcpStaticRoute> {
+    let (destination, gateway) = value
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("expected DEST=GATEWAY, got {value:?}"))?;
+    Ok(DhcpStaticRoute {
+        destination: destination.to_string(),
+        gateway: gateway.to_string(),
+    })
+}
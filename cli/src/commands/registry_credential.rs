@@ -0,0 +1,187 @@
+use std::io::{self, Read};
+
+use clap::{Args, Subcommand};
+use tabled::{Table, Tabled, settings::Style};
+
+use crate::{
+    api::{
+        self,
+        models::{NewRegistryCredential, UpdateRegistryCredential},
+    },
+    client::Client,
+};
+
+use super::{OutputFormat, print_output, resolve_registry_credential_id};
+
+#[derive(Args)]
+pub struct RegistryCredentialArgs {
+    #[command(subcommand)]
+    command: RegistryCredentialCommand,
+}
+
+#[derive(Subcommand)]
+enum RegistryCredentialCommand {
+    /// List registry credentials (passwords are never shown)
+    List,
+    /// Get details of a registry credential
+    Get {
+        /// Credential name or ID
+        credential: String,
+    },
+    /// Store a login for a private OCI registry
+    Create {
+        /// Credential name
+        #[arg(long)]
+        name: String,
+        /// Registry host[:port], e.g. ghcr.io
+        #[arg(long)]
+        registry: String,
+        /// Registry username
+        #[arg(long)]
+        username: String,
+        /// Password or access token (prefer --password-stdin)
+        #[arg(long, conflicts_with = "password_stdin")]
+        password: Option<String>,
+        /// Read the password or token from stdin
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Change the username or rotate the password of a credential
+    Update {
+        /// Credential name or ID
+        credential: String,
+        /// New username
+        #[arg(long)]
+        username: Option<String>,
+        /// New password or access token
+        #[arg(long, conflicts_with = "password_stdin")]
+        password: Option<String>,
+        /// Read the new password or token from stdin
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Delete a registry credential
+    Delete {
+        /// Credential name or ID
+        credential: String,
+    },
+}
+
+#[derive(Tabled)]
+struct RegistryCredentialRow {
+    #[tabled(rename = "ID")]
+    id: String,
+    #[tabled(rename = "Name")]
+    name: String,
+    #[tabled(rename = "Registry")]
+    registry: String,
+    #[tabled(rename = "Username")]
+    username: String,
+    #[tabled(rename = "Updated")]
+    updated_at: String,
+}
+
+fn read_password(password: Option<String>, from_stdin: bool) -> anyhow::Result<Option<String>> {
+    if !from_stdin {
+        return Ok(password);
+    }
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+    let input = input.trim_end_matches(['\r', '\n']).to_string();
+    if input.is_empty() {
+        anyhow::bail!("no password read from stdin");
+    }
+    Ok(Some(input))
+}
+
+pub async fn run(
+    args: RegistryCredentialArgs,
+    client: &Client,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    match args.command {
+        RegistryCredentialCommand::List => {
+            let credentials = api::registry_credentials::list(client, None).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&credentials, output)?;
+            } else {
+                let rows: Vec<RegistryCredentialRow> = credentials
+                    .iter()
+                    .map(|c| RegistryCredentialRow {
+                        id: c.id.to_string(),
+                        name: c.name.clone(),
+                        registry: c.registry.clone(),
+                        username: c.username.clone(),
+                        updated_at: c.updated_at.clone(),
+                    })
+                    .collect();
+                println!("{}", Table::new(rows).with(Style::psql()));
+            }
+        }
+
+        RegistryCredentialCommand::Get { credential } => {
+            let id = resolve_registry_credential_id(client, &credential).await?;
+            let c = api::registry_credentials::get(client, id).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&c, output)?;
+            } else {
+                println!("ID:       {}", c.id);
+                println!("Name:     {}", c.name);
+                println!("Registry: {}", c.registry);
+                println!("Username: {}", c.username);
+                println!("Created:  {}", c.created_at);
+                println!("Updated:  {}", c.updated_at);
+            }
+        }
+
+        RegistryCredentialCommand::Create {
+            name,
+            registry,
+            username,
+            password,
+            password_stdin,
+        } => {
+            let password = read_password(password, password_stdin)?
+                .ok_or_else(|| anyhow::anyhow!("--password or --password-stdin is required"))?;
+            let new_credential = NewRegistryCredential {
+                name,
+                registry,
+                username,
+                password,
+            };
+            let id = api::registry_credentials::create(client, &new_credential).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&serde_json::json!({ "credential_id": id }), output)?;
+            } else {
+                println!("Created registry credential: {id}");
+            }
+        }
+
+        RegistryCredentialCommand::Update {
+            credential,
+            username,
+            password,
+            password_stdin,
+        } => {
+            let id = resolve_registry_credential_id(client, &credential).await?;
+            let req = UpdateRegistryCredential {
+                username,
+                password: read_password(password, password_stdin)?,
+            };
+            let updated = api::registry_credentials::update(client, id, &req).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&updated, output)?;
+            } else {
+                println!("Updated registry credential: {}", updated.name);
+            }
+        }
+
+        RegistryCredentialCommand::Delete { credential } => {
+            let id = resolve_registry_credential_id(client, &credential).await?;
+            api::registry_credentials::delete(client, id).await?;
+            println!("Deleted registry credential: {id}");
+        }
+    }
+
+    Ok(())
+}
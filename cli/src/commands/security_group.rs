@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use clap::{Args, Subcommand};
 use tabled::{Table, Tabled, settings::Style};
 
@@ -43,6 +45,11 @@ enum SecurityGroupCommand {
         /// Security group name or ID
         security_group: String,
     },
+    /// Show packet counters for a security group's rules and members
+    Stats {
+        /// Security group name or ID
+        security_group: String,
+    },
     /// Add a rule to a security group
     AddRule {
         /// Security group name or ID
@@ -54,6 +61,9 @@ enum SecurityGroupCommand {
         protocol: String,
         #[arg(long)]
         cidr: Option<String>,
+        /// Match the VMs of another security group (name or ID) instead of a CIDR
+        #[arg(long, conflicts_with = "cidr")]
+        remote_security_group: Option<String>,
         #[arg(long)]
         port_start: Option<i32>,
         #[arg(long)]
@@ -90,14 +100,36 @@ struct SecurityGroupRuleRow {
     direction: String,
     #[tabled(rename = "Protocol")]
     protocol: String,
-    #[tabled(rename = "CIDR")]
-    cidr: String,
+    #[tabled(rename = "Peer")]
+    peer: String,
     #[tabled(rename = "Ports")]
     ports: String,
     #[tabled(rename = "Description")]
     description: String,
 }
 
+#[derive(Tabled)]
+struct SecurityGroupRuleStatsRow {
+    #[tabled(rename = "Rule")]
+    rule_id: String,
+    #[tabled(rename = "Direction")]
+    direction: String,
+    #[tabled(rename = "Packets")]
+    packets: u64,
+    #[tabled(rename = "Bytes")]
+    bytes: u64,
+}
+
+#[derive(Tabled)]
+struct SecurityGroupMemberStatsRow {
+    #[tabled(rename = "VM")]
+    vm_id: String,
+    #[tabled(rename = "Dropped In")]
+    dropped_ingress_packets: u64,
+    #[tabled(rename = "Dropped Out")]
+    dropped_egress_packets: u64,
+}
+
 pub async fn run(
     args: SecurityGroupArgs,
     client: &Client,
@@ -159,13 +191,27 @@ pub async fn run(
             if !matches!(output, OutputFormat::Table) {
                 print_output(&rules, output)?;
             } else {
+                let group_names: HashMap<_, _> = api::security_groups::list(client, None)
+                    .await?
+                    .into_iter()
+                    .map(|group| (group.id, group.name))
+                    .collect();
                 let rows: Vec<_> = rules
                     .iter()
                     .map(|rule| SecurityGroupRuleRow {
                         id: rule.id.to_string(),
                         direction: rule.direction.clone(),
                         protocol: rule.protocol.clone(),
-                        cidr: rule.cidr.clone().unwrap_or_else(|| "0.0.0.0/0".to_string()),
+                        peer: match rule.remote_security_group_id {
+                            Some(remote_id) => format!(
+                                "sg:{}",
+                                group_names
+                                    .get(&remote_id)
+                                    .cloned()
+                                    .unwrap_or_else(|| remote_id.to_string())
+                            ),
+                            None => rule.cidr.clone().unwrap_or_else(|| "0.0.0.0/0".to_string()),
+                        },
                         ports: match (rule.port_start, rule.port_end) {
                             (Some(start), Some(end)) if start == end => start.to_string(),
                             (Some(start), Some(end)) => format!("{start}-{end}"),
@@ -177,16 +223,54 @@ pub async fn run(
                 println!("{}", Table::new(rows).with(Style::psql()));
             }
         }
+        SecurityGroupCommand::Stats { security_group } => {
+            let id = resolve_security_group_id(client, &security_group).await?;
+            let stats = api::security_groups::stats(client, id).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&stats, output)?;
+            } else {
+                let rules: Vec<_> = stats
+                    .rules
+                    .iter()
+                    .map(|rule| SecurityGroupRuleStatsRow {
+                        rule_id: rule.rule_id.to_string(),
+                        direction: rule.direction.clone(),
+                        packets: rule.packets,
+                        bytes: rule.bytes,
+                    })
+                    .collect();
+                println!("{}", Table::new(rules).with(Style::psql()));
+                if stats.members.is_empty() {
+                    println!("\nNo running members");
+                } else {
+                    let members: Vec<_> = stats
+                        .members
+                        .iter()
+                        .map(|member| SecurityGroupMemberStatsRow {
+                            vm_id: member.vm_id.to_string(),
+                            dropped_ingress_packets: member.dropped_ingress_packets,
+                            dropped_egress_packets: member.dropped_egress_packets,
+                        })
+                        .collect();
+                    println!("\n{}", Table::new(members).with(Style::psql()));
+                }
+            }
+        }
         SecurityGroupCommand::AddRule {
             security_group,
             direction,
             protocol,
             cidr,
+            remote_security_group,
             port_start,
             port_end,
             description,
         } => {
             let id = resolve_security_group_id(client, &security_group).await?;
+            let remote_security_group_id = match remote_security_group {
+                Some(remote) => Some(resolve_security_group_id(client, &remote).await?),
+                None => None,
+            };
             let rule_id = api::security_groups::create_rule(
                 client,
                 id,
@@ -194,6 +278,7 @@ pub async fn run(
                     direction,
                     protocol,
                     cidr,
+                    remote_security_group_id,
                     port_start,
                     port_end,
                     description,
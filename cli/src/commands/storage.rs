@@ -8,8 +8,8 @@ use crate::{
     api::{
         self,
         models::{
-            CreateDiskRequest, ImportToPoolRequest, NewStorageObject, NewStoragePool,
-            RegisterLunRequest,
+            CreateDiskRequest, ImageGcRequest, ImportToPoolRequest, NewStorageObject,
+            NewStoragePool, PrewarmRequest, RegisterLunRequest,
         },
     },
     client::Client,
@@ -21,7 +21,11 @@ use super::{
 };
 
 /// Poll a job to completion, printing progress to stderr. Returns an error if the job fails.
-async fn poll_job_to_completion(client: &Client, job_id: Uuid, label: &str) -> anyhow::Result<()> {
+pub(super) async fn poll_job_to_completion(
+    client: &Client,
+    job_id: Uuid,
+    label: &str,
+) -> anyhow::Result<()> {
     use std::io::Write as _;
     loop {
         let job = jobs::get(client, job_id).await?;
@@ -73,7 +77,7 @@ enum StoragePoolCommand {
         /// Pool name
         #[arg(long)]
         name: String,
-        /// Pool type (local, nfs, overlaybd, or block)
+        /// Pool type (local, nfs, overlaybd, block, or rbd)
         #[arg(long, value_name = "TYPE")]
         pool_type: String,
         /// Capacity in bytes
@@ -91,12 +95,24 @@ enum StoragePoolCommand {
         /// Shorthand for --config '{"url":"..."}' with --pool-type nfs or overlaybd.
         #[arg(long, conflicts_with = "config")]
         url: Option<String>,
-        /// iSCSI target portal (host:port) for a block pool.
+        /// iSCSI target portal (host:port) for a block pool. Repeat for multipath.
         #[arg(long, conflicts_with = "config", requires = "iqn")]
-        portal: Option<String>,
+        portal: Vec<String>,
         /// iSCSI target IQN for a block pool.
         #[arg(long, conflicts_with = "config", requires = "portal")]
         iqn: Option<String>,
+        /// CHAP username the initiator logs in with (block pools)
+        #[arg(long, requires_all = ["iqn", "chap_secret"])]
+        chap_username: Option<String>,
+        /// CHAP secret for --chap-username
+        #[arg(long, requires = "chap_username")]
+        chap_secret: Option<String>,
+        /// Username the target must present back (mutual CHAP)
+        #[arg(long, requires_all = ["chap_username", "mutual_chap_secret"])]
+        mutual_chap_username: Option<String>,
+        /// Secret for --mutual-chap-username
+        #[arg(long, requires = "mutual_chap_username")]
+        mutual_chap_secret: Option<String>,
         /// Host to attach this pool to (name or ID). Required for local pools.
         #[arg(long, required_if_eq("pool_type", "local"))]
         host: Option<String>,
@@ -123,7 +139,12 @@ enum StoragePoolCommand {
         /// Host name or ID
         host: String,
     },
-    /// Create a disk in the pool (blank, or populated from a source URL)
+    /// Show the per-host path health of a pool (iSCSI sessions per portal)
+    Paths {
+        /// Pool name or ID
+        pool: String,
+    },
+    /// Create a disk in the pool (blank, populated from a source URL, or an RBD clone)
     CreateDisk {
         /// Pool name or ID
         #[arg(long)]
@@ -137,9 +158,18 @@ enum StoragePoolCommand {
         /// URL to populate the disk from (e.g. a cloud image). Makes the operation async.
         #[arg(long)]
         source: Option<String>,
+        /// Expected digest of the source image (sha256:<hex> or sha512:<hex>)
+        #[arg(long, requires = "source")]
+        checksum: Option<String>,
+        /// Maximum download rate per second (e.g. 50MiB)
+        #[arg(long, requires = "source")]
+        bandwidth_limit: Option<String>,
         /// Reserve blocks upfront with fallocate (default: sparse)
         #[arg(long)]
         preallocate: bool,
+        /// RBD disk (name or ID) in the same pool to clone
+        #[arg(long, conflicts_with_all = ["size", "source"])]
+        clone_from: Option<String>,
     },
     /// Register a pre-provisioned LUN on a BLOCK pool as a disk storage object
     RegisterLun {
@@ -156,6 +186,11 @@ enum StoragePoolCommand {
         #[arg(long)]
         size: String,
     },
+    /// Discover the LUNs a BLOCK pool's target exports and register new ones
+    DiscoverLuns {
+        /// Pool name or ID
+        pool: String,
+    },
     /// Import an OCI image into the pool (convert to OverlayBD)
     Import {
         /// Pool name or ID
@@ -168,6 +203,107 @@ enum StoragePoolCommand {
         #[arg(long)]
         name: String,
     },
+    /// Download an imported OverlayBD image into the layer cache of pool hosts
+    Prewarm {
+        /// Pool name or ID
+        #[arg(long)]
+        pool: String,
+        /// Converted image reference in the pool registry
+        #[arg(long)]
+        image_ref: String,
+        /// Host name or ID to pre-warm (repeatable; default: all UP pool hosts)
+        #[arg(long = "host")]
+        hosts: Vec<String>,
+    },
+    /// Delete images from an OverlayBD pool registry that nothing references
+    Gc {
+        /// Pool name or ID
+        #[arg(long)]
+        pool: String,
+        /// List what would be deleted without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Upload a local file into a Local or NFS pool (resumes an interrupted upload)
+    Upload {
+        /// Path of the file to upload
+        file: std::path::PathBuf,
+        /// Pool name or ID
+        #[arg(long)]
+        pool: String,
+        /// Name for the resulting storage object (default: the file name)
+        #[arg(long)]
+        name: Option<String>,
+        /// Object type (disk, kernel, initrd, iso)
+        #[arg(long, value_name = "TYPE", default_value = "disk")]
+        object_type: String,
+        /// Bytes sent per request (e.g. 64MiB)
+        #[arg(long, default_value = "64MiB")]
+        chunk_size: String,
+    },
+}
+
+/// Upload `path` in `chunk_size` pieces, picking up where a previous attempt with
+/// the same name stopped. Prints a progress line to stderr after every chunk.
+async fn upload_file(
+    client: &Client,
+    pool_id: Uuid,
+    path: &std::path::Path,
+    name: &str,
+    object_type: &str,
+    chunk_size: i64,
+) -> anyhow::Result<api::models::Transfer> {
+    use std::io::Write as _;
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| anyhow::anyhow!("cannot open {}: {e}", path.display()))?;
+    let total = file.metadata().await?.len() as i64;
+    if total == 0 {
+        return Err(anyhow::anyhow!("{} is empty", path.display()));
+    }
+
+    let in_progress = api::transfers::list(client, pool_id, Some(name))
+        .await?
+        .into_iter()
+        .find(|t| t.transfer_type == "upload" && t.status == "running");
+    let mut offset = match in_progress {
+        Some(t) if t.total_bytes == Some(total) => {
+            eprintln!("Resuming upload at {}", format_bytes(t.transferred_bytes));
+            t.transferred_bytes
+        }
+        Some(_) => {
+            return Err(anyhow::anyhow!(
+                "an upload named '{name}' with a different size is already in progress"
+            ));
+        }
+        None => 0,
+    };
+
+    loop {
+        let len = chunk_size.min(total - offset) as usize;
+        let mut chunk = vec![0u8; len];
+        file.seek(std::io::SeekFrom::Start(offset as u64)).await?;
+        file.read_exact(&mut chunk).await?;
+
+        let transfer =
+            api::transfers::upload_chunk(client, pool_id, name, object_type, offset, total, chunk)
+                .await?;
+        offset = transfer.transferred_bytes;
+        eprint!(
+            "\r[uploading] {:.0}% ({} / {})   ",
+            offset as f64 / total as f64 * 100.0,
+            format_bytes(offset),
+            format_bytes(total)
+        );
+        let _ = std::io::stderr().flush();
+
+        if transfer.status == "completed" {
+            eprintln!("\r[completed]                              ");
+            return Ok(transfer);
+        }
+    }
 }
 
 #[derive(Tabled)]
@@ -186,6 +322,50 @@ struct PoolRow {
     allocated: String,
 }
 
+#[derive(Tabled)]
+struct HostCapacityRow {
+    #[tabled(rename = "Host")]
+    host: String,
+    #[tabled(rename = "Total")]
+    total: String,
+    #[tabled(rename = "Used")]
+    used: String,
+    #[tabled(rename = "Free")]
+    free: String,
+    #[tabled(rename = "Checked")]
+    checked_at: String,
+}
+
+#[derive(Tabled)]
+struct PathRow {
+    #[tabled(rename = "Host")]
+    host: String,
+    #[tabled(rename = "Path")]
+    path: String,
+    #[tabled(rename = "State")]
+    state: String,
+    #[tabled(rename = "Healthy")]
+    healthy: String,
+    #[tabled(rename = "Checked")]
+    checked_at: String,
+}
+
+#[derive(Tabled)]
+struct LunSyncRow {
+    #[tabled(rename = "Change")]
+    change: String,
+    #[tabled(rename = "LUN")]
+    lun: u32,
+    #[tabled(rename = "Name")]
+    name: String,
+    #[tabled(rename = "Size")]
+    size: String,
+    #[tabled(rename = "WWN")]
+    wwn: String,
+    #[tabled(rename = "ID")]
+    id: String,
+}
+
 pub async fn run_pool(
     args: StoragePoolArgs,
     client: &Client,
@@ -240,6 +420,21 @@ pub async fn run_pool(
                         .map(format_bytes)
                         .unwrap_or_else(|| "-".to_string())
                 );
+                if !pool.host_capacity.is_empty() {
+                    let rows: Vec<HostCapacityRow> = pool
+                        .host_capacity
+                        .iter()
+                        .map(|c| HostCapacityRow {
+                            host: c.host_name.clone(),
+                            total: format_bytes(c.total_bytes),
+                            used: format_bytes(c.used_bytes),
+                            free: format_bytes(c.free_bytes),
+                            checked_at: c.checked_at.clone(),
+                        })
+                        .collect();
+                    println!("Measured by hosts:");
+                    println!("{}", Table::new(rows).with(Style::psql()));
+                }
             }
         }
 
@@ -252,18 +447,27 @@ pub async fn run_pool(
             url,
             portal,
             iqn,
+            chap_username,
+            chap_secret,
+            mutual_chap_username,
+            mutual_chap_secret,
             host,
         } => {
-            let config = match (config, path, url, portal, iqn) {
-                (Some(s), _, _, _, _) => serde_json::from_str(&s)
+            let mut config = match (config, path, url, iqn) {
+                (Some(s), _, _, _) => serde_json::from_str(&s)
                     .map_err(|e| anyhow::anyhow!("Invalid JSON for --config: {e}"))?,
-                (_, Some(p), _, _, _) => serde_json::json!({ "path": p }),
-                (_, _, Some(u), _, _) => serde_json::json!({ "url": u }),
-                (_, _, _, Some(p), Some(i)) => {
-                    serde_json::json!({ "portal": p, "iqn": i })
-                }
+                (_, Some(p), _, _) => serde_json::json!({ "path": p }),
+                (_, _, Some(u), _) => serde_json::json!({ "url": u }),
+                (_, _, _, Some(i)) => serde_json::json!({ "portals": portal, "iqn": i }),
                 _ => serde_json::json!({}),
             };
+            if let (Some(username), Some(secret)) = (chap_username, chap_secret) {
+                config["chap"] = serde_json::json!({ "username": username, "secret": secret });
+            }
+            if let (Some(username), Some(secret)) = (mutual_chap_username, mutual_chap_secret) {
+                config["mutual_chap"] =
+                    serde_json::json!({ "username": username, "secret": secret });
+            }
             let new_pool = NewStoragePool {
                 name,
                 pool_type,
@@ -324,6 +528,28 @@ pub async fn run_pool(
             }
         }
 
+        StoragePoolCommand::Paths { pool } => {
+            let pool_id = resolve_pool_id(client, &pool).await?;
+            let hosts = api::storage::list_pool_paths(client, pool_id).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&hosts, output)?;
+            } else {
+                let rows: Vec<PathRow> = hosts
+                    .iter()
+                    .flat_map(|h| {
+                        h.paths.iter().map(|p| PathRow {
+                            host: h.host_name.clone(),
+                            path: p.path.clone(),
+                            state: p.state.clone(),
+                            healthy: p.healthy.to_string(),
+                            checked_at: h.checked_at.clone().unwrap_or_else(|| "-".into()),
+                        })
+                    })
+                    .collect();
+                println!("{}", Table::new(rows).with(Style::psql()));
+            }
+        }
+
         StoragePoolCommand::DetachHost { pool, host } => {
             let pool_id = resolve_pool_id(client, &pool).await?;
             let host_id = resolve_host_id(client, &host).await?;
@@ -343,11 +569,19 @@ pub async fn run_pool(
             name,
             size,
             source,
+            checksum,
+            bandwidth_limit,
             preallocate,
+            clone_from,
         } => {
             let pool_id = resolve_pool_id(client, &pool).await?;
+            let source_storage_object_id = match &clone_from {
+                Some(disk) => Some(resolve_object_id(client, disk).await?),
+                None => None,
+            };
 
             let size_bytes = match (&size, &source) {
+                (None, None) if clone_from.is_some() => None,
                 (None, None) => {
                     return Err(anyhow::anyhow!(
                         "--size is required when --source is not provided"
@@ -361,7 +595,13 @@ pub async fn run_pool(
                 name,
                 size_bytes,
                 source_url: source.clone(),
+                expected_checksum: checksum,
+                bandwidth_limit_bytes_per_sec: bandwidth_limit
+                    .as_deref()
+                    .map(parse_size)
+                    .transpose()?,
                 preallocate,
+                source_storage_object_id,
             };
             let resp = api::storage::create_disk(client, pool_id, &req).await?;
 
@@ -401,6 +641,50 @@ pub async fn run_pool(
             }
         }
 
+        StoragePoolCommand::DiscoverLuns { pool } => {
+            let pool_id = resolve_pool_id(client, &pool).await?;
+            let report = api::storage::discover_luns(client, pool_id).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&report, output)?;
+            } else {
+                let groups = [
+                    ("new", &report.new),
+                    ("resized", &report.resized),
+                    ("vanished", &report.vanished),
+                ];
+                let rows: Vec<LunSyncRow> = groups
+                    .iter()
+                    .flat_map(|(change, entries)| {
+                        entries.iter().map(|e| LunSyncRow {
+                            change: change.to_string(),
+                            lun: e.lun,
+                            name: e.name.clone(),
+                            size: match e.previous_size_bytes {
+                                Some(prev) => format!(
+                                    "{} -> {}",
+                                    format_bytes(prev),
+                                    format_bytes(e.size_bytes)
+                                ),
+                                None => format_bytes(e.size_bytes),
+                            },
+                            wwn: e.wwn.clone().unwrap_or_else(|| "-".into()),
+                            id: e.storage_object_id.to_string(),
+                        })
+                    })
+                    .collect();
+                if !rows.is_empty() {
+                    println!("{}", Table::new(rows).with(Style::psql()));
+                }
+                println!(
+                    "{} new, {} resized, {} vanished, {} unchanged",
+                    report.new.len(),
+                    report.resized.len(),
+                    report.vanished.len(),
+                    report.unchanged
+                );
+            }
+        }
+
         StoragePoolCommand::Import {
             pool,
             image_ref,
@@ -417,6 +701,106 @@ pub async fn run_pool(
                 poll_job_to_completion(client, resp.job_id, "Import").await?;
             }
         }
+
+        StoragePoolCommand::Prewarm {
+            pool,
+            image_ref,
+            hosts,
+        } => {
+            let pool_id = resolve_pool_id(client, &pool).await?;
+            let host_ids = if hosts.is_empty() {
+                None
+            } else {
+                let mut ids = Vec::with_capacity(hosts.len());
+                for host in &hosts {
+                    ids.push(resolve_host_id(client, host).await?);
+                }
+                Some(ids)
+            };
+            let req = PrewarmRequest {
+                image_ref,
+                host_ids,
+            };
+            let resp = api::storage::prewarm(client, pool_id, &req).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&resp, output)?;
+            } else {
+                println!(
+                    "Pre-warm job: {} ({} host(s))",
+                    resp.job_id,
+                    resp.host_ids.len()
+                );
+                poll_job_to_completion(client, resp.job_id, "Pre-warm").await?;
+            }
+        }
+
+        StoragePoolCommand::Gc { pool, dry_run } => {
+            let pool_id = resolve_pool_id(client, &pool).await?;
+            let resp =
+                api::storage::gc_images(client, pool_id, &ImageGcRequest { dry_run }).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&resp, output)?;
+            } else {
+                println!("Image GC job: {}", resp.job_id);
+                poll_job_to_completion(client, resp.job_id, "Image GC").await?;
+                let result = jobs::get(client, resp.job_id)
+                    .await?
+                    .result
+                    .unwrap_or_default();
+                for image in result["images"].as_array().into_iter().flatten() {
+                    println!(
+                        "  {}@{} [{}] {}",
+                        image["repository"].as_str().unwrap_or_default(),
+                        image["digest"].as_str().unwrap_or_default(),
+                        image["tags"]
+                            .as_array()
+                            .into_iter()
+                            .flatten()
+                            .filter_map(|t| t.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        format_bytes(image["size_bytes"].as_i64().unwrap_or(0))
+                    );
+                }
+                let reclaimed = format_bytes(result["reclaimed_bytes"].as_i64().unwrap_or(0));
+                if dry_run {
+                    println!("Would reclaim {reclaimed}");
+                } else {
+                    println!("Reclaimed {reclaimed}");
+                }
+            }
+        }
+
+        StoragePoolCommand::Upload {
+            file,
+            pool,
+            name,
+            object_type,
+            chunk_size,
+        } => {
+            let pool_id = resolve_pool_id(client, &pool).await?;
+            let name = match name {
+                Some(name) => name,
+                None => file
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("cannot derive a name from {}", file.display())
+                    })?,
+            };
+            let chunk_size = parse_size(&chunk_size)?;
+            if chunk_size <= 0 {
+                return Err(anyhow::anyhow!("--chunk-size must be greater than 0"));
+            }
+
+            let transfer =
+                upload_file(client, pool_id, &file, &name, &object_type, chunk_size).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&transfer, output)?;
+            } else if let Some(object_id) = transfer.storage_object_id {
+                println!("Uploaded {name}: storage object {object_id}");
+            }
+        }
     }
 
     Ok(())
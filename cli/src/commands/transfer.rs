@@ -7,7 +7,7 @@ use crate::{
     client::Client,
 };
 
-use super::{OutputFormat, format_bytes, print_output, resolve_pool_id};
+use super::{OutputFormat, format_bytes, parse_size, print_output, resolve_pool_id};
 
 #[derive(Args)]
 pub struct TransferArgs {
@@ -45,6 +45,12 @@ enum TransferCommand {
         /// Object type (disk, kernel, initrd, iso, snapshot, oci_image)
         #[arg(long, value_name = "TYPE")]
         object_type: String,
+        /// Expected digest of the downloaded data (sha256:<hex> or sha512:<hex>)
+        #[arg(long)]
+        checksum: Option<String>,
+        /// Maximum download rate per second (e.g. 50MiB)
+        #[arg(long)]
+        bandwidth_limit: Option<String>,
         /// Block until the transfer completes
         #[arg(short, long)]
         wait: bool,
@@ -106,6 +112,19 @@ pub async fn run(args: TransferArgs, client: &Client, output: OutputFormat) -> a
                 if let Some(total) = transfer.total_bytes {
                     println!("Total:       {}", format_bytes(total));
                 }
+                if let Some(checksum) = &transfer.actual_checksum {
+                    let verified = match transfer.checksum_verified {
+                        Some(true) => "verified",
+                        Some(false) => "MISMATCH",
+                        None => "unverified",
+                    };
+                    println!("Checksum:    {checksum} ({verified})");
+                } else if let Some(expected) = &transfer.expected_checksum {
+                    println!("Expected:    {expected}");
+                }
+                if let Some(limit) = transfer.bandwidth_limit_bytes_per_sec {
+                    println!("Bandwidth:   {}/s", format_bytes(limit));
+                }
                 if let Some(err) = &transfer.error_message {
                     println!("Error:       {err}");
                 }
@@ -117,6 +136,8 @@ pub async fn run(args: TransferArgs, client: &Client, output: OutputFormat) -> a
             name,
             source,
             object_type,
+            checksum,
+            bandwidth_limit,
             wait,
         } => {
             let pool_id = resolve_pool_id(client, &pool).await?;
@@ -124,6 +145,11 @@ pub async fn run(args: TransferArgs, client: &Client, output: OutputFormat) -> a
                 name,
                 source,
                 object_type,
+                expected_checksum: checksum,
+                bandwidth_limit_bytes_per_sec: bandwidth_limit
+                    .as_deref()
+                    .map(parse_size)
+                    .transpose()?,
             };
             let transfer = api::transfers::create(client, pool_id, &new_transfer).await?;
             let transfer = if wait {
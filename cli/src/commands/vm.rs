@@ -7,9 +7,10 @@ use crate::{
     api::{
         self,
         models::{
-            AttachDiskRequest, CommitVmRequest, CreateSnapshotRequest, CreateVmResult,
-            DiskResizeRequest, ExecVmRequest, HotplugNicRequest, NewVm, NewVmNetwork,
-            RestoreRequest, VmImagePreflightRequest, VmMigrateRequest, VmResizeRequest,
+            AllowedAddressPair, AttachDiskRequest, CommitVmRequest, CreateSnapshotRequest,
+            CreateVmResult, DiskResizeRequest, ExecVmRequest, ExportVmRequest, HotplugNicRequest,
+            ImportVmRequest, NewVm, NewVmNetwork, RestoreRequest, VmImagePreflightRequest,
+            VmMigrateRequest, VmResizeRequest,
         },
     },
     client::Client,
@@ -140,6 +141,16 @@ enum VmCommand {
         /// Pool must be Local or NFS and attached to the host running the VM.
         #[arg(long, requires = "image_ref")]
         persistent_upper_pool: Option<String>,
+        /// Virtual size of the OCI-booted root disk in whole GiB, e.g. 100GiB
+        /// (requires --image-ref; defaults to 64GiB, or to twice the image's
+        /// unpacked size with --full-clone-pool)
+        #[arg(long, requires = "image_ref", value_parser = super::parse_size)]
+        overlaybd_disk_size: Option<i64>,
+        /// Copy the OCI image into a raw root disk on this storage pool (name or ID)
+        /// instead of booting it lazily. The VM then has no registry dependency.
+        /// Pool must be Local or NFS and attached to the host running the VM.
+        #[arg(long, requires = "image_ref", conflicts_with = "persistent_upper_pool")]
+        full_clone_pool: Option<String>,
         /// Require a host from this reservation class
         #[arg(long)]
         reservation_class: Option<String>,
@@ -299,6 +310,20 @@ enum VmCommand {
         #[arg(long)]
         device_id: String,
     },
+    /// Show or replace the extra addresses a NIC on a managed network may send from
+    AllowedAddressPairs {
+        /// VM name or ID
+        vm: String,
+        /// NIC device ID (e.g. "net0")
+        #[arg(long)]
+        device_id: String,
+        /// Allowed address or CIDR, optionally with a MAC (e.g. 10.0.0.100=00:00:5e:00:01:07); repeatable, replaces the current list
+        #[arg(long = "set", value_name = "IP[=MAC]", conflicts_with = "clear")]
+        pairs: Vec<String>,
+        /// Remove all allowed address pairs
+        #[arg(long)]
+        clear: bool,
+    },
     /// List security groups bound to a VM
     ListSecurityGroups {
         /// VM name or ID
@@ -320,6 +345,17 @@ enum VmCommand {
         #[arg(long)]
         security_group: String,
     },
+    /// Show denied packets logged for a VM, or turn denied-packet logging on or off
+    FirewallLog {
+        /// VM name or ID
+        vm: String,
+        /// Log packets dropped by the VM's security groups
+        #[arg(long, conflicts_with = "disable")]
+        enable: bool,
+        /// Stop logging dropped packets
+        #[arg(long)]
+        disable: bool,
+    },
     /// Resize a disk attached to a stopped VM
     ResizeDisk {
         /// VM name or ID
@@ -361,6 +397,50 @@ enum VmCommand {
         #[arg(long)]
         size: i64,
     },
+    /// Export a VM (spec, disks, optional memory) to a portable bundle archive
+    Export {
+        /// VM name or ID
+        vm: String,
+        /// Bundle name (auto-generated if omitted)
+        #[arg(long)]
+        name: Option<String>,
+        /// Storage pool name or ID to write the bundle to (Local or NFS)
+        #[arg(long)]
+        storage_pool: Option<String>,
+        /// Include a memory snapshot (VM must be running or paused)
+        #[arg(long)]
+        include_memory: bool,
+    },
+    /// Recreate a VM from an exported bundle
+    Import {
+        /// Bundle storage object name or ID on this installation
+        #[arg(long, conflicts_with_all = ["storage_pool", "path"])]
+        bundle: Option<String>,
+        /// Storage pool name or ID holding a copied-in bundle archive
+        #[arg(long, requires = "path")]
+        storage_pool: Option<String>,
+        /// Absolute path of the bundle archive inside --storage-pool
+        #[arg(long, requires = "storage_pool")]
+        path: Option<String>,
+        /// Name for the imported VM (defaults to the exported name)
+        #[arg(long)]
+        name: Option<String>,
+        /// Remap a source network ID or name to a local network (SOURCE=NETWORK, repeatable)
+        #[arg(long = "map-network")]
+        map_network: Vec<String>,
+        /// Remap a source storage pool ID or name to a local pool (SOURCE=POOL, repeatable)
+        #[arg(long = "map-pool")]
+        map_pool: Vec<String>,
+        /// Pool for disks without a --map-pool entry (defaults to the bundle's pool)
+        #[arg(long)]
+        default_pool: Option<String>,
+        /// Boot source name or ID (boot sources are not part of the bundle)
+        #[arg(long)]
+        boot_source: Option<String>,
+        /// Keep the exported IP addresses when they are free
+        #[arg(long)]
+        preserve_ips: bool,
+    },
     /// Manage VM snapshots
     Snapshot {
         #[command(subcommand)]
@@ -424,6 +504,13 @@ enum SnapshotCommand {
         #[arg(long)]
         snapshot: String,
     },
+    /// Delete a snapshot that is not held by a backup
+    Delete {
+        /// VM name or ID
+        vm: String,
+        /// Snapshot name or ID
+        snapshot: String,
+    },
 }
 
 #[derive(Tabled)]
@@ -567,6 +654,8 @@ pub async fn run(args: VmArgs, client: &Client, output: OutputFormat) -> anyhow:
             min_vram,
             numa_node,
             persistent_upper_pool,
+            overlaybd_disk_size,
+            full_clone_pool,
             reservation_class,
             required_host_labels,
             preferred_host_labels,
@@ -654,6 +743,10 @@ pub async fn run(args: VmArgs, client: &Client, output: OutputFormat) -> anyhow:
                 Some(ref p) => Some(resolve_pool_id(client, p).await?),
                 None => None,
             };
+            let root_disk_pool_id = match full_clone_pool {
+                Some(ref p) => Some(resolve_pool_id(client, p).await?),
+                None => None,
+            };
             let placement_policy = build_placement_policy(
                 reservation_class,
                 &required_host_labels,
@@ -697,6 +790,9 @@ pub async fn run(args: VmArgs, client: &Client, output: OutputFormat) -> anyhow:
                 accelerator_config,
                 numa_config,
                 persistent_upper_pool_id,
+                overlaybd_disk_size_bytes: overlaybd_disk_size,
+                root_disk_mode: root_disk_pool_id.map(|_| "full_clone".to_string()),
+                root_disk_pool_id,
                 placement_policy,
                 guest_agent: guest_agent.then_some(true),
             };
@@ -939,6 +1035,46 @@ pub async fn run(args: VmArgs, client: &Client, output: OutputFormat) -> anyhow:
             println!("Removed NIC {device_id} from VM {vm}");
         }
 
+        VmCommand::AllowedAddressPairs {
+            vm,
+            device_id,
+            pairs,
+            clear,
+        } => {
+            let vm_id = resolve_vm_id(client, &vm).await?;
+            let pairs = if clear || !pairs.is_empty() {
+                let pairs: Vec<AllowedAddressPair> = pairs
+                    .iter()
+                    .map(|pair| match pair.split_once('=') {
+                        Some((ip, mac)) => AllowedAddressPair {
+                            ip_address: ip.to_string(),
+                            mac_address: Some(mac.to_string()),
+                        },
+                        None => AllowedAddressPair {
+                            ip_address: pair.clone(),
+                            mac_address: None,
+                        },
+                    })
+                    .collect();
+                api::vms::update_allowed_address_pairs(client, vm_id, &device_id, &pairs).await?
+            } else {
+                api::vms::list_allowed_address_pairs(client, vm_id, &device_id).await?
+            };
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&pairs, output)?;
+            } else if pairs.is_empty() {
+                println!("No allowed address pairs on NIC {device_id} of VM {vm}");
+            } else {
+                for pair in pairs {
+                    println!(
+                        "{}\t{}",
+                        pair.ip_address,
+                        pair.mac_address.as_deref().unwrap_or("-")
+                    );
+                }
+            }
+        }
+
         VmCommand::ListSecurityGroups { vm } => {
             let vm_id = resolve_vm_id(client, &vm).await?;
             let groups = api::vms::list_security_groups(client, vm_id).await?;
@@ -972,6 +1108,41 @@ pub async fn run(args: VmArgs, client: &Client, output: OutputFormat) -> anyhow:
             println!("Detached security group {security_group} from VM {vm}");
         }
 
+        VmCommand::FirewallLog {
+            vm,
+            enable,
+            disable,
+        } => {
+            let vm_id = resolve_vm_id(client, &vm).await?;
+            if enable || disable {
+                api::vms::update_firewall_log(client, vm_id, enable).await?;
+                let state = if enable { "enabled" } else { "disabled" };
+                println!("Denied-packet logging {state} for VM {vm}");
+            } else {
+                let log = api::vms::get_firewall_log(client, vm_id).await?;
+                if !matches!(output, OutputFormat::Table) {
+                    print_output(&log, output)?;
+                } else {
+                    let state = if log.enabled { "enabled" } else { "disabled" };
+                    println!("Denied-packet logging: {state}");
+                    for packet in log.denied {
+                        let endpoint = |address: &str, port: Option<u32>| match port {
+                            Some(port) => format!("{address}:{port}"),
+                            None => address.to_string(),
+                        };
+                        println!(
+                            "{}\t{}\t{}\t{} -> {}",
+                            packet.timestamp,
+                            packet.direction,
+                            packet.protocol,
+                            endpoint(&packet.source, packet.source_port),
+                            endpoint(&packet.destination, packet.destination_port)
+                        );
+                    }
+                }
+            }
+        }
+
         VmCommand::ResizeDisk { vm, disk, size } => {
             let vm_id = resolve_vm_id(client, &vm).await?;
             let req = DiskResizeRequest {
@@ -1045,6 +1216,92 @@ pub async fn run(args: VmArgs, client: &Client, output: OutputFormat) -> anyhow:
             }
         }
 
+        VmCommand::Export {
+            vm,
+            name,
+            storage_pool,
+            include_memory,
+        } => {
+            let vm_id = resolve_vm_id(client, &vm).await?;
+            let storage_pool_id = match storage_pool {
+                Some(pool) => Some(resolve_pool_id(client, &pool).await?),
+                None => None,
+            };
+            let req = ExportVmRequest {
+                name,
+                storage_pool_id,
+                include_memory,
+            };
+            let resp = api::vms::export(client, vm_id, &req).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&resp, output)?;
+            } else {
+                println!("Exporting VM: {vm}");
+                println!("Bundle:       {} ({})", resp.storage_object_id, resp.path);
+                println!("Job:          {}", resp.job_id);
+                poll_job(client, resp.job_id).await?;
+            }
+        }
+
+        VmCommand::Import {
+            bundle,
+            storage_pool,
+            path,
+            name,
+            map_network,
+            map_pool,
+            default_pool,
+            boot_source,
+            preserve_ips,
+        } => {
+            if bundle.is_none() && path.is_none() {
+                return Err(anyhow!("specify --bundle or --storage-pool with --path"));
+            }
+            let storage_object_id = match bundle {
+                Some(bundle) => Some(resolve_object_id(client, &bundle).await?),
+                None => None,
+            };
+            let storage_pool_id = match storage_pool {
+                Some(pool) => Some(resolve_pool_id(client, &pool).await?),
+                None => None,
+            };
+            let mut network_map = std::collections::BTreeMap::new();
+            for (source, target) in parse_key_value_pairs(&map_network)? {
+                network_map.insert(source, resolve_network_id(client, &target).await?);
+            }
+            let mut storage_pool_map = std::collections::BTreeMap::new();
+            for (source, target) in parse_key_value_pairs(&map_pool)? {
+                storage_pool_map.insert(source, resolve_pool_id(client, &target).await?);
+            }
+            let default_storage_pool_id = match default_pool {
+                Some(pool) => Some(resolve_pool_id(client, &pool).await?),
+                None => None,
+            };
+            let boot_source_id = match boot_source {
+                Some(source) => Some(resolve_boot_source_id(client, &source).await?),
+                None => None,
+            };
+            let req = ImportVmRequest {
+                storage_object_id,
+                storage_pool_id,
+                path,
+                name,
+                network_map,
+                storage_pool_map,
+                default_storage_pool_id,
+                boot_source_id,
+                preserve_ips,
+            };
+            let resp = api::vms::import(client, &req).await?;
+            if !matches!(output, OutputFormat::Table) {
+                print_output(&resp, output)?;
+            } else {
+                println!("Importing VM: {}", resp.vm_id);
+                println!("Job:          {}", resp.job_id);
+                poll_job(client, resp.job_id).await?;
+            }
+        }
+
         VmCommand::Snapshot { command } => match command {
             SnapshotCommand::Create { vm, name, pool } => {
                 let id = resolve_vm_id(client, &vm).await?;
@@ -1094,6 +1351,13 @@ pub async fn run(args: VmArgs, client: &Client, output: OutputFormat) -> anyhow:
                     println!("Status:      {}", restored.status);
                 }
             }
+
+            SnapshotCommand::Delete { vm, snapshot } => {
+                let id = resolve_vm_id(client, &vm).await?;
+                let snapshot_id = super::resolve_snapshot_id(client, id, &snapshot).await?;
+                api::vms::delete_snapshot(client, id, snapshot_id).await?;
+                println!("Deleted snapshot: {snapshot_id}");
+            }
         },
     }
 
@@ -10,8 +10,8 @@ use crate::{
 };
 
 use super::{
-    OutputFormat, format_bytes, print_output, resolve_boot_source_id, resolve_network_id,
-    resolve_object_id, resolve_vm_id, resolve_vm_template_id,
+    OutputFormat, format_bytes, print_output, resolve_boot_source_id, resolve_image_catalog_id,
+    resolve_network_id, resolve_object_id, resolve_vm_id, resolve_vm_template_id,
 };
 
 #[derive(Args)]
@@ -63,8 +63,11 @@ enum VmTemplateCommand {
         #[arg(long)]
         boot_mode: Option<String>,
         /// OCI image reference
-        #[arg(long)]
+        #[arg(long, conflicts_with = "image_catalog")]
         image_ref: Option<String>,
+        /// Image catalog name or ID; VMs use its newest version
+        #[arg(long)]
+        image_catalog: Option<String>,
         /// Network name or ID to attach the VM to
         #[arg(long)]
         network: Option<String>,
@@ -157,6 +160,9 @@ pub async fn run(
                 if let Some(image_ref) = &template.image_ref {
                     println!("Image:       {image_ref}");
                 }
+                if let Some(catalog_id) = template.image_catalog_id {
+                    println!("Catalog:     {catalog_id}");
+                }
                 if let Some(network_id) = template.network_id {
                     println!("Network:     {network_id}");
                 }
@@ -174,6 +180,7 @@ pub async fn run(
             root_disk,
             boot_mode,
             image_ref,
+            image_catalog,
             network,
         } => {
             let boot_source_id = match boot_source {
@@ -188,6 +195,10 @@ pub async fn run(
                 Some(ref object) => Some(resolve_object_id(client, object).await?),
                 None => None,
             };
+            let image_catalog_id = match image_catalog {
+                Some(ref catalog) => Some(resolve_image_catalog_id(client, catalog).await?),
+                None => None,
+            };
             let id = if let Some(from_vm) = from_vm {
                 if hypervisor.is_some()
                     || vcpus.is_some()
@@ -197,6 +208,7 @@ pub async fn run(
                     || root_disk_object_id.is_some()
                     || boot_mode.is_some()
                     || image_ref.is_some()
+                    || image_catalog_id.is_some()
                     || network_id.is_some()
                 {
                     anyhow::bail!(
@@ -218,6 +230,7 @@ pub async fn run(
                     root_disk_object_id,
                     boot_mode,
                     image_ref,
+                    image_catalog_id,
                     network_id,
                     config: None,
                 };
@@ -80,6 +80,7 @@ pub enum Commands {
     /// Hypervisor host operations
     Host(commands::host::HostArgs),
     /// Storage pool operations
+    #[command(alias = "storage")]
     StoragePool(commands::storage::StoragePoolArgs),
     /// Storage object operations
     StorageObject(commands::storage::StorageObjectArgs),
@@ -95,6 +96,10 @@ pub enum Commands {
     VmTemplate(commands::vm_template::VmTemplateArgs),
     /// Network operations
     Network(commands::network::NetworkArgs),
+    /// Image catalog operations (tracked upstream images)
+    ImageCatalog(commands::image_catalog::ImageCatalogArgs),
+    /// Private registry credential operations
+    RegistryCredential(commands::registry_credential::RegistryCredentialArgs),
     /// Security group operations
     SecurityGroup(commands::security_group::SecurityGroupArgs),
     /// Async job operations
@@ -135,6 +140,12 @@ async fn main() -> Result<()> {
         Commands::SecurityGroup(args) => {
             commands::security_group::run(args, &client, cli.output).await
         }
+        Commands::ImageCatalog(args) => {
+            commands::image_catalog::run(args, &client, cli.output).await
+        }
+        Commands::RegistryCredential(args) => {
+            commands::registry_credential::run(args, &client, cli.output).await
+        }
         Commands::Transfer(args) => commands::transfer::run(args, &client, cli.output).await,
         Commands::BootSource(args) => commands::boot_source::run(args, &client, cli.output).await,
         Commands::VmTemplate(args) => commands::vm_template::run(args, &client, cli.output).await,
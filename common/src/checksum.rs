@@ -0,0 +1,82 @@
+use std::fmt;
+
+/// Digest algorithms accepted for download verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Sha512,
+}
+
+impl ChecksumAlgorithm {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    /// Length of the hex-encoded digest.
+    pub fn hex_len(self) -> usize {
+        match self {
+            ChecksumAlgorithm::Sha256 => 64,
+            ChecksumAlgorithm::Sha512 => 128,
+        }
+    }
+}
+
+/// An expected digest in `<algorithm>:<hex>` form, e.g. `sha256:9f86d0...`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checksum {
+    pub algorithm: ChecksumAlgorithm,
+    /// Lower-case hex digest.
+    pub digest: String,
+}
+
+impl Checksum {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (algorithm, digest) = spec
+            .trim()
+            .split_once(':')
+            .ok_or_else(|| format!("checksum '{spec}' must be in <algorithm>:<hex> form"))?;
+        let algorithm = match algorithm.to_ascii_lowercase().as_str() {
+            "sha256" => ChecksumAlgorithm::Sha256,
+            "sha512" => ChecksumAlgorithm::Sha512,
+            other => return Err(format!("unsupported checksum algorithm '{other}'")),
+        };
+        let digest = digest.to_ascii_lowercase();
+        if digest.len() != algorithm.hex_len() || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!(
+                "{} digest must be {} hex characters",
+                algorithm.as_str(),
+                algorithm.hex_len()
+            ));
+        }
+        Ok(Self { algorithm, digest })
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm.as_str(), self.digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_normalizes_case() {
+        let checksum = Checksum::parse(&format!("SHA256:{}", "AB".repeat(32))).unwrap();
+        assert_eq!(checksum.algorithm, ChecksumAlgorithm::Sha256);
+        assert_eq!(checksum.to_string(), format!("sha256:{}", "ab".repeat(32)));
+    }
+
+    #[test]
+    fn parse_rejects_bad_input() {
+        assert!(Checksum::parse("deadbeef").is_err());
+        assert!(Checksum::parse(&format!("md5:{}", "0".repeat(32))).is_err());
+        assert!(Checksum::parse(&format!("sha512:{}", "0".repeat(64))).is_err());
+        assert!(Checksum::parse(&format!("sha256:{}", "z".repeat(64))).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 pub mod architecture;
+pub mod checksum;
 pub mod cpu_list;
 pub mod telemtry;
 
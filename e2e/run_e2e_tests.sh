@@ -23,6 +23,10 @@ cd "$(dirname "$0")"
 export CLOUD_HYPERVISOR_VERSION
 : "${FIRECRACKER_VERSION:=$(cat ../versions/firecracker-version)}"
 export FIRECRACKER_VERSION
+# Registry credentials and CHAP secrets need a key to be stored. Test stacks
+# are thrown away afterwards, so a fresh key per run is enough.
+: "${QARAX_ENCRYPTION_KEY:=$(head -c 32 /dev/urandom | od -An -tx1 | tr -d ' \n')}"
+export QARAX_ENCRYPTION_KEY
 
 default_webhook_host() {
 	if [ "$(uname -s)" != "Linux" ]; then
@@ -39,6 +39,8 @@ CH_VERSION_FILE="${REPO_ROOT}/versions/cloud-hypervisor-version"
 export CLOUD_HYPERVISOR_VERSION="${CLOUD_HYPERVISOR_VERSION:-$(tr -d '\n' <"$CH_VERSION_FILE")}"
 FC_VERSION_FILE="${REPO_ROOT}/versions/firecracker-version"
 export FIRECRACKER_VERSION="${FIRECRACKER_VERSION:-$(tr -d '\n' <"$FC_VERSION_FILE")}"
+# Registry credentials and CHAP secrets need a key to be stored.
+export QARAX_ENCRYPTION_KEY="${QARAX_ENCRYPTION_KEY:-$(head -c 32 /dev/urandom | od -An -tx1 | tr -d ' \n')}"
 
 COMPOSE_ARGS=()
 E2E_COMPOSE_ARGS=(-f "${REPO_ROOT}/e2e/docker-compose.yml")
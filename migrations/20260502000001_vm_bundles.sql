@@ -0,0 +1,3 @@
+ALTER TYPE storage_object_type ADD VALUE IF NOT EXISTS 'VM_BUNDLE';
+ALTER TYPE job_type ADD VALUE IF NOT EXISTS 'VM_EXPORT';
+ALTER TYPE job_type ADD VALUE IF NOT EXISTS 'VM_IMPORT';
@@ -0,0 +1,7 @@
+-- Optional integrity check and rate limit for URL downloads, plus the
+-- verification outcome once the node has finished streaming.
+ALTER TABLE transfers
+ADD COLUMN expected_checksum TEXT,
+ADD COLUMN actual_checksum TEXT,
+ADD COLUMN checksum_verified BOOLEAN,
+ADD COLUMN bandwidth_limit_bytes_per_sec BIGINT;
@@ -0,0 +1 @@
+ALTER TYPE transfer_type ADD VALUE IF NOT EXISTS 'UPLOAD';
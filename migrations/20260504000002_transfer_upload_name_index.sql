@@ -0,0 +1,5 @@
+-- Separate migration: a new enum value cannot be referenced in the
+-- transaction that added it.
+CREATE UNIQUE INDEX transfers_upload_name_key
+    ON transfers (storage_pool_id, name)
+    WHERE transfer_type = 'UPLOAD' AND status = 'RUNNING';
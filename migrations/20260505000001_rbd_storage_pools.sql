@@ -0,0 +1,3 @@
+-- Ceph RBD pools: images live in a RADOS pool and are mapped on each node via
+-- krbd or rbd-nbd.
+ALTER TYPE storage_pool_type ADD VALUE IF NOT EXISTS 'RBD';
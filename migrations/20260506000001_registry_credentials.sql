@@ -0,0 +1,12 @@
+-- Logins for private OCI registries. The password is AES-256-GCM encrypted by
+-- the control plane (nonce || ciphertext || tag) and is only ever decrypted to
+-- build gRPC requests for nodes.
+CREATE TABLE IF NOT EXISTS registry_credentials (
+    id                 UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name               VARCHAR(100) UNIQUE NOT NULL,
+    registry           VARCHAR(255) UNIQUE NOT NULL,
+    username           VARCHAR(255) NOT NULL,
+    password_encrypted BYTEA NOT NULL,
+    created_at         TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at         TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
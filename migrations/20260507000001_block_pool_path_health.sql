@@ -0,0 +1,7 @@
+-- BLOCK pools can lose individual iSCSI paths while staying usable.
+ALTER TYPE storage_pool_status ADD VALUE IF NOT EXISTS 'DEGRADED';
+
+-- Last per-portal path state reported by each attached host.
+ALTER TABLE host_storage_pools
+    ADD COLUMN IF NOT EXISTS path_health JSONB,
+    ADD COLUMN IF NOT EXISTS path_health_checked_at TIMESTAMPTZ;
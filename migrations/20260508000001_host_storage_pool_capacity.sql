@@ -0,0 +1,7 @@
+-- Space of each pool as last measured by each attached host (statvfs for
+-- Local/NFS, summed LUN sizes for BLOCK, the image cache for OverlayBD).
+ALTER TABLE host_storage_pools
+    ADD COLUMN IF NOT EXISTS total_bytes BIGINT,
+    ADD COLUMN IF NOT EXISTS used_bytes BIGINT,
+    ADD COLUMN IF NOT EXISTS free_bytes BIGINT,
+    ADD COLUMN IF NOT EXISTS capacity_checked_at TIMESTAMPTZ;
@@ -0,0 +1,4 @@
+-- Copy of a persistent OverlayBD upper layer taken with a VM snapshot; restore
+-- rolls the VM's upper layer back to it.
+ALTER TABLE vm_snapshots
+    ADD COLUMN IF NOT EXISTS upper_storage_object_id UUID REFERENCES storage_objects(id) ON DELETE SET NULL;
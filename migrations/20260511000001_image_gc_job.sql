@@ -0,0 +1 @@
+ALTER TYPE job_type ADD VALUE IF NOT EXISTS 'IMAGE_GC';
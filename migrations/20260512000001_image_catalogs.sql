@@ -0,0 +1,35 @@
+-- Image catalogs follow an upstream reference (e.g. ubuntu:24.04). Each new
+-- digest is imported into an OverlayBD pool and recorded as a version; only
+-- the newest keep_versions are retained.
+CREATE TABLE IF NOT EXISTS image_catalogs (
+    id                    UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name                  VARCHAR(100) UNIQUE NOT NULL,
+    source_ref            VARCHAR(512) NOT NULL,
+    storage_pool_id       UUID NOT NULL REFERENCES storage_pools(id) ON DELETE CASCADE,
+    refresh_interval_secs INTEGER NOT NULL DEFAULT 86400 CHECK (refresh_interval_secs >= 60),
+    keep_versions         INTEGER NOT NULL DEFAULT 3 CHECK (keep_versions >= 1),
+    last_checked_at       TIMESTAMPTZ,
+    last_error            TEXT,
+    created_at            TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at            TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- image_ref is the upstream reference pinned to source_digest
+-- ("ubuntu:24.04@sha256:..."); the converted image is the storage object.
+CREATE TABLE IF NOT EXISTS image_catalog_versions (
+    id                UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    catalog_id        UUID NOT NULL REFERENCES image_catalogs(id) ON DELETE CASCADE,
+    source_digest     VARCHAR(255) NOT NULL,
+    image_ref         VARCHAR(768) NOT NULL,
+    storage_object_id UUID REFERENCES storage_objects(id) ON DELETE SET NULL,
+    created_at        TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (catalog_id, source_digest)
+);
+
+CREATE INDEX IF NOT EXISTS idx_image_catalog_versions_catalog
+    ON image_catalog_versions (catalog_id, created_at DESC);
+
+ALTER TABLE vm_templates
+    ADD COLUMN IF NOT EXISTS image_catalog_id UUID REFERENCES image_catalogs(id) ON DELETE SET NULL;
+
+ALTER TYPE job_type ADD VALUE IF NOT EXISTS 'IMAGE_CATALOG_REFRESH';
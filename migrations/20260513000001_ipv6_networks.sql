@@ -0,0 +1,22 @@
+-- Dual-stack networks: an optional /64 next to the IPv4 subnet. Guests get
+-- their IPv6 address by SLAAC from the node's router advertisements, so the
+-- prefix length is fixed.
+ALTER TABLE networks
+ADD COLUMN IF NOT EXISTS subnet6  CIDR,
+ADD COLUMN IF NOT EXISTS gateway6 INET;
+
+ALTER TABLE networks
+ADD CONSTRAINT check_networks_subnet6 CHECK (
+    subnet6 IS NULL OR (family(subnet6) = 6 AND masklen(subnet6) = 64)
+);
+
+-- IPv6 allocations belong to one interface (the address is derived from its
+-- MAC), so they go away with the interface.
+ALTER TABLE ip_allocations
+ADD COLUMN IF NOT EXISTS interface_id UUID REFERENCES network_interfaces(id) ON DELETE CASCADE;
+
+CREATE INDEX IF NOT EXISTS idx_ip_allocations_interface ON ip_allocations(interface_id) WHERE interface_id IS NOT NULL;
+
+-- Room for IPv6 literals and DNS names.
+ALTER TABLE hosts
+ALTER COLUMN address TYPE VARCHAR(255);
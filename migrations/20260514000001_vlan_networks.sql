@@ -0,0 +1,12 @@
+-- VLAN provider networks: the node bridges an 802.1Q sub-interface of the
+-- uplink, so several tenant VLANs can share one physical NIC.
+ALTER TABLE networks
+ADD COLUMN IF NOT EXISTS vlan_id INTEGER;
+
+ALTER TABLE networks
+ADD CONSTRAINT check_networks_vlan_id CHECK (
+    vlan_id IS NULL OR (vlan_id BETWEEN 1 AND 4094)
+);
+
+-- Two networks on the same VLAN would share one L2 segment.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_networks_vlan_id ON networks(vlan_id) WHERE vlan_id IS NOT NULL;
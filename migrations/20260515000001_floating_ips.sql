@@ -0,0 +1,32 @@
+-- Floating IPs are addresses on the uplink of whichever host runs the VM,
+-- 1:1 NATed to one of its NICs. Deleting the NIC releases the address.
+CREATE TABLE IF NOT EXISTS floating_ips (
+    id                   UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    address              INET UNIQUE NOT NULL,
+    description          TEXT,
+    network_interface_id UUID UNIQUE REFERENCES network_interfaces(id) ON DELETE SET NULL,
+    created_at           TIMESTAMP WITHOUT TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT check_floating_ips_host_address CHECK (
+        family(address) = 4 AND masklen(address) = 32
+    )
+);
+
+CREATE TYPE port_forward_protocol AS ENUM ('TCP', 'UDP');
+
+-- Host ports are unique across the cluster so a forward keeps its port when
+-- the VM migrates to another host.
+CREATE TABLE IF NOT EXISTS port_forwards (
+    id                   UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    network_interface_id UUID NOT NULL REFERENCES network_interfaces(id) ON DELETE CASCADE,
+    protocol             port_forward_protocol NOT NULL,
+    host_port            INTEGER NOT NULL,
+    vm_port              INTEGER NOT NULL,
+    description          TEXT,
+    created_at           TIMESTAMP WITHOUT TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (protocol, host_port),
+    CONSTRAINT check_port_forwards_ports CHECK (
+        host_port BETWEEN 1 AND 65535 AND vm_port BETWEEN 1 AND 65535
+    )
+);
+
+CREATE INDEX IF NOT EXISTS idx_port_forwards_interface ON port_forwards(network_interface_id);
@@ -0,0 +1,2 @@
+-- Extra DHCP options (domain, NTP, MTU, static routes) handed to guests
+ALTER TABLE networks ADD COLUMN dhcp_options JSONB NOT NULL DEFAULT '{}'::jsonb;
@@ -0,0 +1,12 @@
+-- A rule's peer is either a CIDR or the members of another security group.
+-- NO ACTION rather than RESTRICT so a group whose own rules name it as their
+-- peer can still be deleted: the check runs after those rules cascade away.
+ALTER TABLE security_group_rules
+    ADD COLUMN remote_security_group_id UUID REFERENCES security_groups(id) ON DELETE NO ACTION,
+    ADD CONSTRAINT check_security_group_rule_single_peer CHECK (
+        cidr IS NULL OR remote_security_group_id IS NULL
+    );
+
+CREATE INDEX IF NOT EXISTS idx_security_group_rules_remote_group
+    ON security_group_rules(remote_security_group_id)
+    WHERE remote_security_group_id IS NOT NULL;
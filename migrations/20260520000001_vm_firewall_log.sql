@@ -0,0 +1,2 @@
+-- Log packets dropped by a VM's security groups, rate limited, on its host.
+ALTER TABLE vms ADD COLUMN firewall_log_denied BOOLEAN NOT NULL DEFAULT false;
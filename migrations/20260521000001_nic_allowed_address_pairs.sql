@@ -0,0 +1,4 @@
+-- Extra source addresses a NIC may send from past anti-spoofing, such as a
+-- VRRP virtual IP: [{"ip_address": "10.0.0.100/32", "mac_address": null}].
+ALTER TABLE network_interfaces
+    ADD COLUMN allowed_address_pairs JSONB NOT NULL DEFAULT '[]'::jsonb;
@@ -0,0 +1,37 @@
+-- allocated_bytes is the sum of size_bytes over a pool's storage objects,
+-- kept current by a trigger. Admission against capacity_bytes happens in the
+-- API, so imported and discovered objects can still be recorded on a pool
+-- that is already over its configured capacity.
+ALTER TABLE storage_pools DROP CONSTRAINT IF EXISTS check_capacity;
+
+UPDATE storage_pools sp
+SET allocated_bytes = COALESCE(
+    (SELECT SUM(so.size_bytes) FROM storage_objects so WHERE so.storage_pool_id = sp.id),
+    0
+);
+
+ALTER TABLE storage_pools
+    ALTER COLUMN allocated_bytes SET NOT NULL;
+
+CREATE OR REPLACE FUNCTION update_pool_allocated_bytes()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        UPDATE storage_pools
+        SET allocated_bytes = allocated_bytes - OLD.size_bytes
+        WHERE id = OLD.storage_pool_id;
+    END IF;
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        UPDATE storage_pools
+        SET allocated_bytes = allocated_bytes + NEW.size_bytes
+        WHERE id = NEW.storage_pool_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ language 'plpgsql';
+
+DROP TRIGGER IF EXISTS update_storage_pools_allocated_bytes ON storage_objects;
+CREATE TRIGGER update_storage_pools_allocated_bytes
+AFTER INSERT OR DELETE OR UPDATE OF size_bytes, storage_pool_id ON storage_objects
+FOR EACH ROW
+EXECUTE FUNCTION update_pool_allocated_bytes();
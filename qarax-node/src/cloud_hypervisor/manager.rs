@@ -32,12 +32,13 @@ use prost::Message;
 use crate::overlaybd::OverlayBdManager;
 use crate::rpc::node::StoragePoolKind;
 use crate::rpc::node::{
-    ConsoleConfig as ProtoConsoleConfig, ConsoleMode as ProtoConsoleMode,
-    CpuTopology as ProtoCpuTopology, CpusConfig as ProtoCpusConfig, DiskConfig as ProtoDiskConfig,
-    ExecVmResponse, MemoryConfig as ProtoMemoryConfig, NetConfig as ProtoNetConfig,
+    CloudInitConfig as ProtoCloudInitConfig, ConsoleConfig as ProtoConsoleConfig,
+    ConsoleMode as ProtoConsoleMode, CpuTopology as ProtoCpuTopology,
+    CpusConfig as ProtoCpusConfig, DiskConfig as ProtoDiskConfig, ExecVmResponse,
+    MemoryConfig as ProtoMemoryConfig, NetConfig as ProtoNetConfig,
     NumaPlacement as ProtoNumaPlacement, PayloadConfig as ProtoPayloadConfig,
     RateLimiterConfig as ProtoRateLimiterConfig, RngConfig as ProtoRngConfig,
-    TokenBucket as ProtoTokenBucket, VfioDeviceConfig as ProtoVfioDeviceConfig,
+    TokenBucket as ProtoTokenBucket, UpperLayerCopy, VfioDeviceConfig as ProtoVfioDeviceConfig,
     VhostMode as ProtoVhostMode, VmConfig as ProtoVmConfig, VmState, VmStatus,
     VsockConfig as ProtoVsockConfig,
 };
@@ -110,9 +111,9 @@ struct VmInstance {
     console_pty_path: Option<String>,
     /// Host-side UNIX socket used for virtio-vsock guest-agent access
     vsock_socket_path: Option<PathBuf>,
-    /// Which storage backend mapped a disk for this VM (if any).
-    /// Used by delete_vm to call the correct backend's unmap().
-    storage_backend_kind: Option<StoragePoolKind>,
+    /// Storage backends that mapped disks for this VM.
+    /// Used by delete_vm to call each backend's unmap().
+    storage_backend_kinds: Vec<StoragePoolKind>,
 }
 
 impl VmInstance {
@@ -198,6 +199,122 @@ impl VmManager {
         self.storage_backends.get(kind)
     }
 
+    /// Resolve disks that live in a storage backend (OverlayBD images, RBD
+    /// images) to local device paths. Returns the backends now holding
+    /// mappings for this VM; on error, mappings made so far are released.
+    pub(crate) async fn map_backend_disks(
+        &self,
+        vm_id: &str,
+        disks: &mut [ProtoDiskConfig],
+    ) -> Result<Vec<StoragePoolKind>, VmManagerError> {
+        let mut kinds = Vec::new();
+        for disk in disks.iter_mut() {
+            let mapped = self.map_backend_disk(vm_id, disk).await;
+            match mapped {
+                Ok(Some(kind)) if !kinds.contains(&kind) => kinds.push(kind),
+                Ok(_) => {}
+                Err(e) => {
+                    self.unmap_backend_disks(vm_id, &kinds).await;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(kinds)
+    }
+
+    /// Map a single disk if it is backend-managed; returns the backend used.
+    pub(crate) async fn map_backend_disk(
+        &self,
+        vm_id: &str,
+        disk: &mut ProtoDiskConfig,
+    ) -> Result<Option<StoragePoolKind>, VmManagerError> {
+        let (kind, disk_config) = if let (Some(image_ref), Some(registry_url)) =
+            (disk.oci_image_ref.clone(), disk.registry_url.clone())
+        {
+            let mut disk_config = serde_json::json!({
+                "image_ref": image_ref,
+                "registry_url": registry_url,
+            });
+            if let Some(ref upper_data) = disk.upper_data_path {
+                disk_config["upper_data_path"] = serde_json::Value::String(upper_data.clone());
+            }
+            if let Some(ref upper_index) = disk.upper_index_path {
+                disk_config["upper_index_path"] = serde_json::Value::String(upper_index.clone());
+            }
+            if let Some(size_bytes) = disk.overlaybd_size_bytes {
+                disk_config["size_bytes"] = size_bytes.into();
+            }
+            if !disk.overlaybd_frozen_layers.is_empty() {
+                disk_config["frozen_layers"] = disk.overlaybd_frozen_layers.clone().into();
+            }
+            (StoragePoolKind::Overlaybd, disk_config)
+        } else if let Some(rbd_config) = &disk.rbd_config_json {
+            let disk_config = serde_json::from_str(rbd_config).map_err(|e| {
+                VmManagerError::InvalidConfig(format!(
+                    "Disk {} has invalid RBD config: {e}",
+                    disk.id
+                ))
+            })?;
+            (StoragePoolKind::Rbd, disk_config)
+        } else if let Some(block_config) = &disk.block_config_json {
+            let disk_config = serde_json::from_str(block_config).map_err(|e| {
+                VmManagerError::InvalidConfig(format!(
+                    "Disk {} has invalid BLOCK config: {e}",
+                    disk.id
+                ))
+            })?;
+            (StoragePoolKind::Block, disk_config)
+        } else {
+            return Ok(None);
+        };
+
+        let backend = self.storage_backends.get(kind).ok_or_else(|| {
+            VmManagerError::StorageError(format!(
+                "Disk {} requests {:?} but no such storage backend is configured",
+                disk.id, kind
+            ))
+        })?;
+        let mapped = backend
+            .map(vm_id, &disk_config)
+            .await
+            .map_err(|e| VmManagerError::StorageError(e.to_string()))?;
+
+        disk.path = Some(mapped.device_path);
+        disk.oci_image_ref = None;
+        disk.registry_url = None;
+        disk.rbd_config_json = None;
+        disk.block_config_json = None;
+        Ok(Some(kind))
+    }
+
+    /// Release every backend mapping held for a VM, logging failures.
+    pub(crate) async fn unmap_backend_disks(&self, vm_id: &str, kinds: &[StoragePoolKind]) {
+        for kind in kinds {
+            if let Some(backend) = self.storage_backends.get(*kind)
+                && let Err(e) = backend.unmap(vm_id).await
+            {
+                warn!("Failed to unmap {:?} storage for VM {}: {}", kind, vm_id, e);
+            }
+        }
+    }
+
+    /// Release the mapping made for one disk, logging failures.
+    pub(crate) async fn unmap_backend_device(
+        &self,
+        vm_id: &str,
+        kind: StoragePoolKind,
+        device_path: &str,
+    ) {
+        if let Some(backend) = self.storage_backends.get(kind)
+            && let Err(e) = backend.unmap_device(vm_id, device_path).await
+        {
+            warn!(
+                "Failed to unmap {:?} device {} for VM {}: {}",
+                kind, device_path, vm_id, e
+            );
+        }
+    }
+
     /// Get the runtime directory path
     pub fn runtime_dir(&self) -> &std::path::Path {
         &self.runtime_dir
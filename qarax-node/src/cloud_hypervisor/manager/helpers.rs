@@ -111,6 +111,106 @@ impl VmManager {
         processes.clear();
     }
 
+    /// Create TAP devices (or passt backends) for the networks that need them,
+    /// injecting their names into `networks`, and attach TAPs to their
+    /// bridges. Everything created is removed again if a step fails.
+    pub(super) async fn prepare_networks(
+        &self,
+        vm_id: &str,
+        networks: &mut [ProtoNetConfig],
+    ) -> Result<(Vec<String>, Vec<Child>), VmManagerError> {
+        let mut tap_devices: Vec<String> = Vec::new();
+        let mut passt_processes: Vec<Child> = Vec::new();
+        let mut result = Ok(());
+        for (i, net) in networks.iter_mut().enumerate() {
+            if Self::should_spawn_passt(net) {
+                let socket_path = self.passt_socket_path(vm_id, i);
+                match Self::start_passt_backend(&socket_path).await {
+                    Ok(passt) => {
+                        net.vhost_socket = Some(socket_path.to_string_lossy().to_string());
+                        passt_processes.push(passt);
+                    }
+                    Err(e) => {
+                        result = Err(e);
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            if !net.vhost_user.unwrap_or(false) && net.tap.is_none() {
+                let tap_name = Self::tap_name_for_net(vm_id, i);
+                if let Err(e) = Self::create_tap_device(&tap_name).await {
+                    result = Err(e);
+                    break;
+                }
+                net.tap = Some(tap_name.clone());
+                tap_devices.push(tap_name);
+            }
+        }
+
+        if result.is_ok() {
+            for net in networks.iter() {
+                if let (Some(tap_name), Some(bridge_name)) = (&net.tap, &net.bridge)
+                    && let Err(e) =
+                        crate::networking::bridge::attach_to_bridge(tap_name, bridge_name).await
+                {
+                    error!(
+                        "Failed to attach TAP {} to bridge {}: {}",
+                        tap_name, bridge_name, e
+                    );
+                    result = Err(VmManagerError::TapError(format!(
+                        "Failed to attach TAP {} to bridge {}: {}",
+                        tap_name, bridge_name, e
+                    )));
+                    break;
+                }
+            }
+        }
+
+        match result {
+            Ok(()) => Ok((tap_devices, passt_processes)),
+            Err(e) => {
+                for tap in &tap_devices {
+                    Self::delete_tap_device(tap).await;
+                }
+                Self::cleanup_passt_processes(&mut passt_processes).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Write a VM's cloud-init NoCloud seed image and return the read-only
+    /// disk that carries it, or `None` when the VM has no user-data.
+    pub(super) async fn write_cloud_init_seed(
+        &self,
+        vm_id: &str,
+        cloud_init: Option<&ProtoCloudInitConfig>,
+    ) -> Result<Option<ProtoDiskConfig>, VmManagerError> {
+        let Some(ci) = cloud_init.filter(|ci| !ci.user_data.is_empty()) else {
+            return Ok(None);
+        };
+        let seed_path = self.cloud_init_seed_path(vm_id);
+        // runtime_dir is created unconditionally by the callers; seed_path lives there.
+        let network_config = (!ci.network_config.is_empty()).then_some(ci.network_config.as_str());
+        let buf = super::super::cloud_init::build_seed_image(
+            &ci.user_data,
+            &ci.meta_data,
+            network_config,
+        )
+        .map_err(|e| VmManagerError::InvalidConfig(e.to_string()))?;
+        tokio::fs::write(&seed_path, buf)
+            .await
+            .map_err(VmManagerError::SpawnError)?;
+        info!("Cloud-init seed disk attached for VM {}", vm_id);
+        Ok(Some(ProtoDiskConfig {
+            id: "cidata".to_string(),
+            path: Some(seed_path.display().to_string()),
+            readonly: Some(true),
+            ..Default::default()
+        }))
+    }
+
     /// Create a TAP device and bring it up.
     pub(super) async fn create_tap_device(name: &str) -> Result<(), VmManagerError> {
         let add = Command::new("ip")
@@ -61,22 +61,38 @@ impl VmManager {
         vm_id: &str,
         config: &ProtoDiskConfig,
     ) -> Result<(), VmManagerError> {
-        let vms = self.vms.lock().await;
-        let instance = vms
-            .get(vm_id)
-            .ok_or_else(|| VmManagerError::VmNotFound(vm_id.to_string()))?;
+        let socket_path = {
+            let vms = self.vms.lock().await;
+            let instance = vms
+                .get(vm_id)
+                .ok_or_else(|| VmManagerError::VmNotFound(vm_id.to_string()))?;
+            instance.socket_path.clone()
+        };
+
+        let mut config = config.clone();
+        let mapped_kind = self.map_backend_disk(vm_id, &mut config).await?;
 
-        let sdk_config = Self::proto_disk_to_sdk(config);
+        let sdk_config = Self::proto_disk_to_sdk(&config);
         let body = serde_json::to_string(&sdk_config)
             .map_err(|e| VmManagerError::InvalidConfig(e.to_string()))?;
 
-        Self::send_api_request(
-            &instance.socket_path,
-            "PUT",
-            "/api/v1/vm.add-disk",
-            Some(&body),
-        )
-        .await?;
+        if let Err(e) =
+            Self::send_api_request(&socket_path, "PUT", "/api/v1/vm.add-disk", Some(&body)).await
+        {
+            if let (Some(kind), Some(device_path)) = (mapped_kind, &config.path) {
+                self.unmap_backend_device(vm_id, kind, device_path).await;
+            }
+            return Err(e);
+        }
+
+        if let Some(kind) = mapped_kind {
+            let mut vms = self.vms.lock().await;
+            if let Some(instance) = vms.get_mut(vm_id)
+                && !instance.storage_backend_kinds.contains(&kind)
+            {
+                instance.storage_backend_kinds.push(kind);
+            }
+        }
 
         Ok(())
     }
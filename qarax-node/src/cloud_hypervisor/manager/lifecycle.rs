@@ -141,7 +141,7 @@ impl VmManager {
                 serial_pty_path: None,
                 console_pty_path: None,
                 vsock_socket_path,
-                storage_backend_kind: None, // Recovery doesn't restore OverlayBD state
+                storage_backend_kinds: Vec::new(), // Recovery doesn't restore backend mappings
             };
 
             let mut vms = self.vms.lock().await;
@@ -163,126 +163,25 @@ impl VmManager {
             }
         }
 
-        // Create TAP devices for networks that need them, injecting the names
-        // into the config so CH uses our managed devices (and we can clean them up).
         let mut config = config;
         if let Some(vsock) = config.vsock.as_mut() {
             self.resolve_vsock_config(&vm_id, vsock);
         }
-        let mut tap_devices: Vec<String> = Vec::new();
-        let mut passt_processes: Vec<Child> = Vec::new();
-        for (i, net) in config.networks.iter_mut().enumerate() {
-            if Self::should_spawn_passt(net) {
-                let socket_path = self.passt_socket_path(&vm_id, i);
-                let passt = Self::start_passt_backend(&socket_path).await?;
-                net.vhost_socket = Some(socket_path.to_string_lossy().to_string());
-                passt_processes.push(passt);
-                continue;
-            }
-
-            if !net.vhost_user.unwrap_or(false) && net.tap.is_none() {
-                let tap_name = Self::tap_name_for_net(&vm_id, i);
-                if let Err(e) = Self::create_tap_device(&tap_name).await {
-                    for tap in &tap_devices {
-                        Self::delete_tap_device(tap).await;
-                    }
-                    Self::cleanup_passt_processes(&mut passt_processes).await;
-                    return Err(e);
-                }
-                net.tap = Some(tap_name.clone());
-                tap_devices.push(tap_name);
-            }
-        }
-
-        // Attach TAP devices to bridges if specified
-        for net in config.networks.iter() {
-            if let (Some(tap_name), Some(bridge_name)) = (&net.tap, &net.bridge)
-                && let Err(e) =
-                    crate::networking::bridge::attach_to_bridge(tap_name, bridge_name).await
-            {
-                tracing::error!(
-                    "Failed to attach TAP {} to bridge {}: {}",
-                    tap_name,
-                    bridge_name,
-                    e
-                );
-                // Clean up TAPs we created
-                for tap in &tap_devices {
-                    Self::delete_tap_device(tap).await;
-                }
-                Self::cleanup_passt_processes(&mut passt_processes).await;
-                return Err(VmManagerError::TapError(format!(
-                    "Failed to attach TAP {} to bridge {}: {}",
-                    tap_name, bridge_name, e
-                )));
-            }
-        }
+        // Create TAP devices for networks that need them, injecting the names
+        // into the config so CH uses our managed devices (and we can clean them up).
+        let (tap_devices, mut passt_processes) =
+            self.prepare_networks(&vm_id, &mut config.networks).await?;
 
-        // Resolve storage-backed disks: map each disk that has oci_image_ref set
-        // through the appropriate storage backend.
-        let mut storage_backend_kind = None;
-        for disk in config.disks.iter_mut() {
-            if let (Some(image_ref), Some(registry_url)) =
-                (disk.oci_image_ref.clone(), disk.registry_url.clone())
-            {
-                let backend = self
-                    .storage_backends
-                    .get(StoragePoolKind::Overlaybd)
-                    .ok_or_else(|| {
-                        VmManagerError::StorageError(format!(
-                            "Disk {} requests OverlayBD but no OverlayBD backend is configured",
-                            disk.id
-                        ))
-                    })?;
-
-                let mut disk_config = serde_json::json!({
-                    "image_ref": image_ref,
-                    "registry_url": registry_url,
-                });
-                if let Some(ref upper_data) = disk.upper_data_path {
-                    disk_config["upper_data_path"] = serde_json::Value::String(upper_data.clone());
-                }
-                if let Some(ref upper_index) = disk.upper_index_path {
-                    disk_config["upper_index_path"] =
-                        serde_json::Value::String(upper_index.clone());
-                }
-                let mapped = backend
-                    .map(&vm_id, &disk_config)
-                    .await
-                    .map_err(|e| VmManagerError::StorageError(e.to_string()))?;
-
-                disk.path = Some(mapped.device_path);
-                disk.oci_image_ref = None;
-                disk.registry_url = None;
-                storage_backend_kind = Some(StoragePoolKind::Overlaybd);
-            }
-        }
+        // Resolve storage-backed disks (OverlayBD, RBD) to local device paths.
+        let storage_backend_kinds = self.map_backend_disks(&vm_id, &mut config.disks).await?;
 
         // Generate a cloud-init NoCloud seed image and attach it as a read-only
         // disk if the VM has cloud-init data configured.
-        if let Some(ci) = &config.cloud_init
-            && !ci.user_data.is_empty()
+        if let Some(seed_disk) = self
+            .write_cloud_init_seed(&vm_id, config.cloud_init.as_ref())
+            .await?
         {
-            let seed_path = self.cloud_init_seed_path(&vm_id);
-            // runtime_dir is created unconditionally below; seed_path lives there.
-            let network_config =
-                (!ci.network_config.is_empty()).then_some(ci.network_config.as_str());
-            let buf = super::super::cloud_init::build_seed_image(
-                &ci.user_data,
-                &ci.meta_data,
-                network_config,
-            )
-            .map_err(|e| VmManagerError::InvalidConfig(e.to_string()))?;
-            tokio::fs::write(&seed_path, buf)
-                .await
-                .map_err(VmManagerError::SpawnError)?;
-            config.disks.push(ProtoDiskConfig {
-                id: "cidata".to_string(),
-                path: Some(seed_path.display().to_string()),
-                readonly: Some(true),
-                ..Default::default()
-            });
-            info!("Cloud-init seed disk attached for VM {}", vm_id);
+            config.disks.push(seed_disk);
         }
 
         // Ensure runtime directory exists
@@ -449,7 +348,7 @@ impl VmManager {
             serial_pty_path: serial_pty,
             console_pty_path: console_pty,
             vsock_socket_path,
-            storage_backend_kind,
+            storage_backend_kinds,
         };
 
         let state = instance.to_vm_state();
@@ -661,20 +560,19 @@ impl VmManager {
             Self::delete_tap_device(tap).await;
         }
 
-        if let Err(e) = crate::networking::iptables::teardown_vm_firewall(vm_id).await {
+        if let Err(e) = crate::networking::firewall::teardown_vm_firewall(vm_id).await {
             warn!("Failed to tear down firewall state for VM {}: {}", vm_id, e);
         }
+        if let Err(e) = crate::networking::floating_ip::teardown_vm_nat(vm_id).await {
+            warn!("Failed to tear down NAT state for VM {}: {}", vm_id, e);
+        }
 
         // Stop passt backends created by qarax-node
         Self::cleanup_passt_processes(&mut instance.passt_processes).await;
 
-        // Unmap storage backend device if this VM used one
-        if let Some(kind) = instance.storage_backend_kind
-            && let Some(backend) = self.storage_backends.get(kind)
-            && let Err(e) = backend.unmap(vm_id).await
-        {
-            warn!("Failed to unmap storage for VM {}: {}", vm_id, e);
-        }
+        // Unmap storage backend devices this VM used
+        self.unmap_backend_disks(vm_id, &instance.storage_backend_kinds)
+            .await;
 
         info!("VM {} deleted successfully", vm_id);
         Ok(())
@@ -31,31 +31,61 @@ impl VmManager {
     /// Spawns a fresh Cloud Hypervisor process for the given vm_id, then calls
     /// `PUT /api/v1/vm.restore` (without a preceding `vm.create`). Cloud Hypervisor
     /// reads all VM config from the snapshot, so no VmConfig is needed here.
-    pub async fn restore_vm(&self, vm_id: &str, source_url: &str) -> Result<(), VmManagerError> {
+    ///
+    /// When `config` is given it is the VM's current config on this host: its
+    /// disks replace `disks`, TAP devices (or passt backends) are created for
+    /// its networks and its cloud-init seed is rewritten, so a snapshot taken
+    /// on another host or under another VM id restores against local paths.
+    ///
+    /// When there are disks, the VM's backend disks are unmapped, the
+    /// `upper_copies` are rolled back, and the disks are mapped again. Their
+    /// device paths can change across a remap, so they are written into the
+    /// snapshot config, by id, before Cloud Hypervisor reads it.
+    pub async fn restore_vm(
+        &self,
+        vm_id: &str,
+        source_url: &str,
+        config: Option<ProtoVmConfig>,
+        mut disks: Vec<ProtoDiskConfig>,
+        upper_copies: &[UpperLayerCopy],
+    ) -> Result<(), VmManagerError> {
         info!("Restoring VM {} from {}", vm_id, source_url);
 
         let config_path = self.config_path(vm_id);
-        let proto_config = match tokio::fs::read(&config_path).await {
-            Ok(config_bytes) => ProtoVmConfig::decode(config_bytes.as_slice()).map_err(|e| {
-                VmManagerError::InvalidConfig(format!(
-                    "Failed to decode persisted config for restored VM {}: {}",
-                    vm_id, e
-                ))
-            })?,
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ProtoVmConfig {
-                vm_id: vm_id.to_string(),
-                serial: Some(ProtoConsoleConfig {
-                    mode: ProtoConsoleMode::Pty as i32,
-                    file: None,
-                    socket: None,
-                    iommu: None,
-                }),
-                ..Default::default()
+        let rebuild = config.is_some();
+        let mut proto_config = match config {
+            Some(mut config) => {
+                disks = std::mem::take(&mut config.disks);
+                if let Some(vsock) = config.vsock.as_mut() {
+                    self.resolve_vsock_config(vm_id, vsock);
+                }
+                config
+            }
+            None => match tokio::fs::read(&config_path).await {
+                Ok(config_bytes) => {
+                    ProtoVmConfig::decode(config_bytes.as_slice()).map_err(|e| {
+                        VmManagerError::InvalidConfig(format!(
+                            "Failed to decode persisted config for restored VM {}: {}",
+                            vm_id, e
+                        ))
+                    })?
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => ProtoVmConfig {
+                    vm_id: vm_id.to_string(),
+                    serial: Some(ProtoConsoleConfig {
+                        mode: ProtoConsoleMode::Pty as i32,
+                        file: None,
+                        socket: None,
+                        iommu: None,
+                    }),
+                    ..Default::default()
+                },
+                Err(e) => return Err(VmManagerError::SpawnError(e)),
             },
-            Err(e) => return Err(VmManagerError::SpawnError(e)),
         };
 
         // Clean up any existing CH process for this vm_id.
+        let mut storage_backend_kinds = Vec::new();
         {
             let mut vms = self.vms.lock().await;
             if let Some(mut instance) = vms.remove(vm_id) {
@@ -65,13 +95,100 @@ impl VmManager {
                 if instance.socket_path.exists() {
                     let _ = tokio::fs::remove_file(&instance.socket_path).await;
                 }
+                if rebuild {
+                    for tap in &instance.tap_devices {
+                        Self::delete_tap_device(tap).await;
+                    }
+                    Self::cleanup_passt_processes(&mut instance.passt_processes).await;
+                }
+                storage_backend_kinds = instance.storage_backend_kinds;
+            }
+        }
+
+        let remapped = !disks.is_empty();
+        if remapped {
+            // Mappings recovered after a node restart are not tracked on an
+            // instance, so release the OverlayBD device by VM id regardless.
+            if disks.iter().any(|d| d.oci_image_ref.is_some())
+                && !storage_backend_kinds.contains(&StoragePoolKind::Overlaybd)
+            {
+                storage_backend_kinds.push(StoragePoolKind::Overlaybd);
             }
+            self.unmap_backend_disks(vm_id, &storage_backend_kinds)
+                .await;
+            for copy in upper_copies {
+                Self::copy_upper_layer(copy).await?;
+            }
+            storage_backend_kinds = self.map_backend_disks(vm_id, &mut disks).await?;
         }
 
         // Ensure runtime directory exists.
-        tokio::fs::create_dir_all(&self.runtime_dir)
+        if let Err(e) = tokio::fs::create_dir_all(&self.runtime_dir).await {
+            self.unmap_backend_disks(vm_id, &storage_backend_kinds)
+                .await;
+            return Err(VmManagerError::SpawnError(e));
+        }
+
+        let (tap_devices, mut passt_processes) = if rebuild {
+            let prepared = match self
+                .prepare_networks(vm_id, &mut proto_config.networks)
+                .await
+            {
+                Ok(prepared) => prepared,
+                Err(e) => {
+                    self.unmap_backend_disks(vm_id, &storage_backend_kinds)
+                        .await;
+                    return Err(e);
+                }
+            };
+            match self
+                .write_cloud_init_seed(vm_id, proto_config.cloud_init.as_ref())
+                .await
+            {
+                Ok(seed_disk) => disks.extend(seed_disk),
+                Err(e) => {
+                    let (taps, mut passt) = prepared;
+                    self.abort_restore(vm_id, &storage_backend_kinds, &taps, &mut passt)
+                        .await;
+                    return Err(e);
+                }
+            }
+            prepared
+        } else {
+            (Vec::new(), Vec::new())
+        };
+
+        if remapped || rebuild {
+            let source_dir = source_url.strip_prefix("file://").unwrap_or(source_url);
+            let networks = if rebuild {
+                proto_config.networks.as_slice()
+            } else {
+                &[]
+            };
+            if let Err(e) = Self::rewrite_snapshot_config(
+                Path::new(source_dir),
+                &disks,
+                networks,
+                proto_config.vsock.as_ref(),
+            )
             .await
-            .map_err(VmManagerError::SpawnError)?;
+            {
+                self.abort_restore(
+                    vm_id,
+                    &storage_backend_kinds,
+                    &tap_devices,
+                    &mut passt_processes,
+                )
+                .await;
+                return Err(e);
+            }
+        }
+        if rebuild {
+            proto_config.disks = disks;
+            if let Err(e) = tokio::fs::write(&config_path, proto_config.encode_to_vec()).await {
+                warn!("Failed to persist config for VM {}: {}", vm_id, e);
+            }
+        }
 
         let socket_path = self.socket_path(vm_id);
         let log_path = self.log_path(vm_id);
@@ -80,21 +197,37 @@ impl VmManager {
             let _ = tokio::fs::remove_file(&socket_path).await;
         }
 
-        let log_file = tokio::fs::File::create(&log_path)
-            .await
-            .map_err(VmManagerError::SpawnError)?
-            .into_std()
-            .await;
-        let stderr_file = log_file.try_clone().map_err(VmManagerError::SpawnError)?;
-
-        let process = Command::new(&self.ch_binary)
-            .arg("--api-socket")
-            .arg(&socket_path)
-            .stdout(std::process::Stdio::from(log_file))
-            .stderr(std::process::Stdio::from(stderr_file))
-            .kill_on_drop(true)
-            .spawn()
-            .map_err(VmManagerError::SpawnError)?;
+        let spawned = async {
+            let log_file = tokio::fs::File::create(&log_path)
+                .await
+                .map_err(VmManagerError::SpawnError)?
+                .into_std()
+                .await;
+            let stderr_file = log_file.try_clone().map_err(VmManagerError::SpawnError)?;
+
+            Command::new(&self.ch_binary)
+                .arg("--api-socket")
+                .arg(&socket_path)
+                .stdout(std::process::Stdio::from(log_file))
+                .stderr(std::process::Stdio::from(stderr_file))
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(VmManagerError::SpawnError)
+        }
+        .await;
+        let process = match spawned {
+            Ok(process) => process,
+            Err(e) => {
+                self.abort_restore(
+                    vm_id,
+                    &storage_backend_kinds,
+                    &tap_devices,
+                    &mut passt_processes,
+                )
+                .await;
+                return Err(e);
+            }
+        };
 
         info!(
             "Cloud Hypervisor process for restore started with PID: {:?}",
@@ -111,7 +244,16 @@ impl VmManager {
                     retries += 1;
                     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
                 }
-                Err(e) => return Err(VmManagerError::SpawnError(e)),
+                Err(e) => {
+                    self.abort_restore(
+                        vm_id,
+                        &storage_backend_kinds,
+                        &tap_devices,
+                        &mut passt_processes,
+                    )
+                    .await;
+                    return Err(VmManagerError::SpawnError(e));
+                }
             }
         }
 
@@ -123,6 +265,13 @@ impl VmManager {
         {
             // Kill the CH process if restore fails.
             let _ = tokio::fs::remove_file(&socket_path).await;
+            self.abort_restore(
+                vm_id,
+                &storage_backend_kinds,
+                &tap_devices,
+                &mut passt_processes,
+            )
+            .await;
             return Err(e);
         }
 
@@ -157,12 +306,12 @@ impl VmManager {
             vm,
             socket_path: socket_path.clone(),
             status: VmStatus::Running,
-            tap_devices: vec![],
-            passt_processes: vec![],
+            tap_devices,
+            passt_processes,
             serial_pty_path: serial_pty,
             console_pty_path: console_pty,
             vsock_socket_path,
-            storage_backend_kind: None,
+            storage_backend_kinds,
         };
 
         {
@@ -174,6 +323,142 @@ impl VmManager {
         Ok(())
     }
 
+    /// Release what a failed restore set up before Cloud Hypervisor took over.
+    async fn abort_restore(
+        &self,
+        vm_id: &str,
+        storage_backend_kinds: &[StoragePoolKind],
+        tap_devices: &[String],
+        passt_processes: &mut Vec<Child>,
+    ) {
+        self.unmap_backend_disks(vm_id, storage_backend_kinds).await;
+        for tap in tap_devices {
+            Self::delete_tap_device(tap).await;
+        }
+        Self::cleanup_passt_processes(passt_processes).await;
+    }
+
+    /// Overwrite an upper layer with its snapshot copy. The disk must not be
+    /// mapped while this runs. The copy is staged next to the live files and
+    /// renamed over them, so a failed copy leaves the upper as it was.
+    pub(super) async fn copy_upper_layer(copy: &UpperLayerCopy) -> Result<(), VmManagerError> {
+        let staged_data = format!("{}.restore-new", copy.dest_data);
+        let staged_index = format!("{}.restore-new", copy.dest_index);
+        let old_data = format!("{}.restore-old", copy.dest_data);
+
+        let result = async {
+            for (source, staged) in [
+                (&copy.source_data, &staged_data),
+                (&copy.source_index, &staged_index),
+            ] {
+                tokio::fs::copy(source, staged)
+                    .await
+                    .map_err(|e| format!("copy {} to {}: {}", source, staged, e))?;
+            }
+            // Keep the old data reachable until the index has moved too, so
+            // data and index never come from different uppers.
+            let _ = tokio::fs::remove_file(&old_data).await;
+            tokio::fs::hard_link(&copy.dest_data, &old_data)
+                .await
+                .map_err(|e| format!("link {}: {}", old_data, e))?;
+            tokio::fs::rename(&staged_data, &copy.dest_data)
+                .await
+                .map_err(|e| format!("rename {}: {}", staged_data, e))?;
+            if let Err(e) = tokio::fs::rename(&staged_index, &copy.dest_index).await {
+                let _ = tokio::fs::rename(&old_data, &copy.dest_data).await;
+                return Err(format!("rename {}: {}", staged_index, e));
+            }
+            Ok(())
+        }
+        .await;
+
+        for path in [&staged_data, &staged_index, &old_data] {
+            let _ = tokio::fs::remove_file(path).await;
+        }
+        result.map_err(|e| {
+            VmManagerError::StorageError(format!(
+                "Failed to restore upper layer {} from {}: {}",
+                copy.dest_data, copy.source_data, e
+            ))
+        })?;
+        info!("Rolled back OverlayBD upper layer {}", copy.dest_data);
+        Ok(())
+    }
+
+    /// Point a snapshot's `config.json` at this host: disks take the paths
+    /// of `disks`, NICs the TAP or vhost-user socket of `networks`, both
+    /// matched by id, and the vsock device the socket of `vsock`.
+    async fn rewrite_snapshot_config(
+        snapshot_dir: &Path,
+        disks: &[ProtoDiskConfig],
+        networks: &[ProtoNetConfig],
+        vsock: Option<&ProtoVsockConfig>,
+    ) -> Result<(), VmManagerError> {
+        let config_path = snapshot_dir.join("config.json");
+        let bytes = tokio::fs::read(&config_path)
+            .await
+            .map_err(VmManagerError::SpawnError)?;
+        let mut config: serde_json::Value = serde_json::from_slice(&bytes).map_err(|e| {
+            VmManagerError::InvalidConfig(format!(
+                "Failed to parse snapshot config {}: {}",
+                config_path.display(),
+                e
+            ))
+        })?;
+        Self::localize_snapshot_config(&mut config, disks, networks, vsock);
+
+        let bytes = serde_json::to_vec(&config).map_err(|e| {
+            VmManagerError::InvalidConfig(format!("Failed to encode snapshot config: {}", e))
+        })?;
+        tokio::fs::write(&config_path, bytes)
+            .await
+            .map_err(VmManagerError::SpawnError)?;
+        Ok(())
+    }
+
+    pub(super) fn localize_snapshot_config(
+        config: &mut serde_json::Value,
+        disks: &[ProtoDiskConfig],
+        networks: &[ProtoNetConfig],
+        vsock: Option<&ProtoVsockConfig>,
+    ) {
+        if let Some(snapshot_disks) = config.get_mut("disks").and_then(|d| d.as_array_mut()) {
+            for snapshot_disk in snapshot_disks {
+                let Some(path) = snapshot_disk["id"]
+                    .as_str()
+                    .and_then(|id| disks.iter().find(|d| d.id == id))
+                    .and_then(|d| d.path.clone())
+                else {
+                    continue;
+                };
+                snapshot_disk["path"] = serde_json::Value::String(path);
+            }
+        }
+
+        if let Some(snapshot_nets) = config.get_mut("net").and_then(|n| n.as_array_mut()) {
+            for snapshot_net in snapshot_nets {
+                let Some(net) = snapshot_net["id"]
+                    .as_str()
+                    .and_then(|id| networks.iter().find(|n| n.id == id))
+                else {
+                    continue;
+                };
+                if let Some(tap) = &net.tap {
+                    snapshot_net["tap"] = serde_json::Value::String(tap.clone());
+                }
+                if let Some(socket) = &net.vhost_socket {
+                    snapshot_net["vhost_socket"] = serde_json::Value::String(socket.clone());
+                }
+            }
+        }
+
+        if let Some(socket) = vsock.and_then(|v| v.socket.as_ref())
+            && let Some(snapshot_vsock) = config.get_mut("vsock").filter(|v| v.is_object())
+        {
+            snapshot_vsock["socket"] = serde_json::Value::String(socket.clone());
+        }
+    }
+
     /// Prepare this node to receive a live migration for the given VM.
     ///
     /// Steps:
@@ -251,46 +536,11 @@ impl VmManager {
             }
         }
 
-        // Resolve storage-backed disks before spawning CH — the OverlayBD
-        // backend needs to mount a TCMU device on this host for the same image
-        // the source node was using.
-        let mut storage_backend_kind = None;
-        for disk in mutable_config.disks.iter_mut() {
-            if let (Some(image_ref), Some(registry_url)) =
-                (disk.oci_image_ref.clone(), disk.registry_url.clone())
-            {
-                let backend = self
-                    .storage_backends
-                    .get(StoragePoolKind::Overlaybd)
-                    .ok_or_else(|| {
-                        VmManagerError::StorageError(format!(
-                            "Disk {} requests OverlayBD but no OverlayBD backend is configured",
-                            disk.id
-                        ))
-                    })?;
-
-                let mut disk_config = serde_json::json!({
-                    "image_ref": image_ref,
-                    "registry_url": registry_url,
-                });
-                if let Some(ref upper_data) = disk.upper_data_path {
-                    disk_config["upper_data_path"] = serde_json::Value::String(upper_data.clone());
-                }
-                if let Some(ref upper_index) = disk.upper_index_path {
-                    disk_config["upper_index_path"] =
-                        serde_json::Value::String(upper_index.clone());
-                }
-                let mapped = backend
-                    .map(vm_id, &disk_config)
-                    .await
-                    .map_err(|e| VmManagerError::StorageError(e.to_string()))?;
-
-                disk.path = Some(mapped.device_path);
-                disk.oci_image_ref = None;
-                disk.registry_url = None;
-                storage_backend_kind = Some(StoragePoolKind::Overlaybd);
-            }
-        }
+        // Resolve storage-backed disks before spawning CH — OverlayBD and RBD
+        // images have to be mapped on this host just as they were on the source.
+        let storage_backend_kinds = self
+            .map_backend_disks(vm_id, &mut mutable_config.disks)
+            .await?;
 
         // Ensure runtime directory exists.
         tokio::fs::create_dir_all(&self.runtime_dir)
@@ -397,7 +647,7 @@ impl VmManager {
             serial_pty_path: None,
             console_pty_path: None,
             vsock_socket_path,
-            storage_backend_kind,
+            storage_backend_kinds,
         };
 
         {
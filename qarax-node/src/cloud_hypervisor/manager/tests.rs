@@ -193,3 +193,134 @@ fn build_guest_exec_request_is_newline_framed_json() {
     assert_eq!(json["command"], serde_json::json!(["/bin/echo", "hello"]));
     assert_eq!(json["timeout_secs"], 5);
 }
+
+#[test]
+fn localize_snapshot_config_rewrites_host_paths_by_id() {
+    let mut config = serde_json::json!({
+        "disks": [
+            {"id": "rootfs", "path": "/var/lib/qarax/pools/src/disk.raw"},
+            {"id": "cidata", "path": "/var/lib/qarax/vms/source-cidata.img"},
+        ],
+        "net": [
+            {"id": "net0", "tap": "qt11111111n0", "mac": "52:54:00:00:00:01"},
+            {"id": "net1", "tap": "qt11111111n1"},
+        ],
+        "vsock": {"cid": 16384, "socket": "/var/lib/qarax/vms/source.vsock"},
+    });
+    let disks = vec![
+        ProtoDiskConfig {
+            id: "rootfs".into(),
+            path: Some("/mnt/pool/target.raw".into()),
+            ..Default::default()
+        },
+        ProtoDiskConfig {
+            id: "cidata".into(),
+            path: Some("/var/lib/qarax/vms/target-cidata.img".into()),
+            ..Default::default()
+        },
+    ];
+    let networks = vec![ProtoNetConfig {
+        id: "net0".into(),
+        tap: Some("qt22222222n0".into()),
+        ..Default::default()
+    }];
+    let vsock = ProtoVsockConfig {
+        socket: Some("/var/lib/qarax/vms/target.vsock".into()),
+        ..Default::default()
+    };
+
+    VmManager::localize_snapshot_config(&mut config, &disks, &networks, Some(&vsock));
+
+    assert_eq!(config["disks"][0]["path"], "/mnt/pool/target.raw");
+    assert_eq!(
+        config["disks"][1]["path"],
+        "/var/lib/qarax/vms/target-cidata.img"
+    );
+    assert_eq!(config["net"][0]["tap"], "qt22222222n0");
+    assert_eq!(config["net"][0]["mac"], "52:54:00:00:00:01");
+    assert_eq!(config["net"][1]["tap"], "qt11111111n1");
+    assert_eq!(config["vsock"]["socket"], "/var/lib/qarax/vms/target.vsock");
+    assert_eq!(config["vsock"]["cid"], 16384);
+}
+
+#[test]
+fn localize_snapshot_config_leaves_unmatched_devices_alone() {
+    let mut config = serde_json::json!({
+        "disks": [
+            {"id": "rootfs", "path": "/var/lib/qarax/pools/src/disk.raw"},
+            {"path": "/var/lib/qarax/pools/src/anonymous.raw"},
+        ],
+        "net": [{"id": "net0", "vhost_user": true, "vhost_socket": "/run/src.sock"}],
+        "vsock": null,
+    });
+    let original = config.clone();
+    let disks = vec![ProtoDiskConfig {
+        // A disk the node resolves itself (OverlayBD, RBD) has no path yet.
+        id: "rootfs".into(),
+        path: None,
+        ..Default::default()
+    }];
+    let vsock = ProtoVsockConfig {
+        socket: Some("/var/lib/qarax/vms/target.vsock".into()),
+        ..Default::default()
+    };
+
+    VmManager::localize_snapshot_config(&mut config, &disks, &[], Some(&vsock));
+    assert_eq!(config, original);
+
+    let networks = vec![ProtoNetConfig {
+        id: "net0".into(),
+        vhost_socket: Some("/run/target.sock".into()),
+        ..Default::default()
+    }];
+    VmManager::localize_snapshot_config(&mut config, &[], &networks, None);
+    assert_eq!(config["net"][0]["vhost_socket"], "/run/target.sock");
+    assert!(config["net"][0].get("tap").is_none());
+}
+
+#[tokio::test]
+async fn copy_upper_layer_replaces_both_files_or_neither() {
+    let dir = TempDir::new().unwrap();
+    let path = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+    for (name, contents) in [
+        ("live.data", "old data"),
+        ("live.index", "old index"),
+        ("copy.data", "new data"),
+        ("copy.index", "new index"),
+    ] {
+        std::fs::write(path(name), contents).unwrap();
+    }
+    let copy = UpperLayerCopy {
+        source_data: path("copy.data"),
+        source_index: path("missing.index"),
+        dest_data: path("live.data"),
+        dest_index: path("live.index"),
+    };
+
+    // A failed copy leaves the live upper and nothing else behind.
+    VmManager::copy_upper_layer(&copy).await.unwrap_err();
+    assert_eq!(
+        std::fs::read_to_string(path("live.data")).unwrap(),
+        "old data"
+    );
+    assert_eq!(
+        std::fs::read_to_string(path("live.index")).unwrap(),
+        "old index"
+    );
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 4);
+
+    let copy = UpperLayerCopy {
+        source_index: path("copy.index"),
+        ..copy
+    };
+    VmManager::copy_upper_layer(&copy).await.unwrap();
+    assert_eq!(
+        std::fs::read_to_string(path("live.data")).unwrap(),
+        "new data"
+    );
+    assert_eq!(
+        std::fs::read_to_string(path("live.index")).unwrap(),
+        "new index"
+    );
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 4);
+}
@@ -58,8 +58,15 @@ impl crate::vmm::VmmManager for VmManager {
             .map_err(Into::into)
     }
 
-    async fn restore_vm(&self, vm_id: &str, source_url: &str) -> Result<(), crate::vmm::VmmError> {
-        VmManager::restore_vm(self, vm_id, source_url)
+    async fn restore_vm(
+        &self,
+        vm_id: &str,
+        source_url: &str,
+        config: Option<ProtoVmConfig>,
+        disks: Vec<ProtoDiskConfig>,
+        upper_copies: &[crate::rpc::node::UpperLayerCopy],
+    ) -> Result<(), crate::vmm::VmmError> {
+        VmManager::restore_vm(self, vm_id, source_url, config, disks, upper_copies)
             .await
             .map_err(Into::into)
     }
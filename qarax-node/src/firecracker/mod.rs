@@ -496,12 +496,15 @@ impl VmmManager for FirecrackerManager {
             Self::delete_tap_device(tap).await;
         }
 
-        if let Err(e) = crate::networking::iptables::teardown_vm_firewall(vm_id).await {
+        if let Err(e) = crate::networking::firewall::teardown_vm_firewall(vm_id).await {
             warn!(
                 "FC: Failed to tear down firewall state for VM {}: {}",
                 vm_id, e
             );
         }
+        if let Err(e) = crate::networking::floating_ip::teardown_vm_nat(vm_id).await {
+            warn!("FC: Failed to tear down NAT state for VM {}: {}", vm_id, e);
+        }
 
         info!("FC: VM {} deleted", vm_id);
         Ok(())
@@ -584,8 +587,20 @@ impl VmmManager for FirecrackerManager {
         Ok(())
     }
 
-    async fn restore_vm(&self, vm_id: &str, source_url: &str) -> Result<(), VmmError> {
+    async fn restore_vm(
+        &self,
+        vm_id: &str,
+        source_url: &str,
+        config: Option<crate::rpc::node::VmConfig>,
+        disks: Vec<crate::rpc::node::DiskConfig>,
+        upper_copies: &[crate::rpc::node::UpperLayerCopy],
+    ) -> Result<(), VmmError> {
         info!("FC: Restoring VM {} from {}", vm_id, source_url);
+        if config.is_some() || !disks.is_empty() || !upper_copies.is_empty() {
+            return Err(VmmError::InvalidConfig(
+                "Firecracker snapshots cannot remap disks or NICs".to_string(),
+            ));
+        }
 
         // Clean up any existing instance.
         {
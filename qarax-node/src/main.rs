@@ -12,6 +12,9 @@ static ALLOC: dhat::Alloc = dhat::Alloc;
 
 use qarax_node::cloud_hypervisor::VmManager;
 use qarax_node::firecracker::FirecrackerManager;
+use qarax_node::networking::dhcp;
+use qarax_node::networking::firewall::{self, FirewallBackend};
+use qarax_node::networking::firewall_log;
 use qarax_node::overlaybd::OverlayBdManager;
 use qarax_node::rpc::node::StoragePoolKind;
 use qarax_node::rpc::node::file_transfer_service_server::FileTransferServiceServer;
@@ -23,6 +26,7 @@ use qarax_node::storage::block::BlockBackend;
 use qarax_node::storage::local::LocalBackend;
 use qarax_node::storage::nfs::NfsBackend;
 use qarax_node::storage::overlaybd::OverlayBdBackend;
+use qarax_node::storage::rbd::RbdBackend;
 use qarax_node::vmm::VmmManager;
 
 #[derive(Parser, Debug)]
@@ -57,10 +61,23 @@ pub struct Args {
     #[clap(long, default_value = "/var/lib/qarax/overlaybd")]
     overlaybd_cache_dir: PathBuf,
 
+    /// Size cap for cached OverlayBD image layers, in bytes. Least recently
+    /// used images not backing a running VM are evicted above it.
+    #[clap(long)]
+    overlaybd_cache_max_bytes: Option<u64>,
+
     /// Path to firecracker binary (enables Firecracker backend when present)
     #[clap(long, default_value = "/usr/local/bin/firecracker")]
     firecracker_binary: PathBuf,
 
+    /// Directory for DHCP lease files, kept so leases survive a restart
+    #[clap(long, default_value = "/var/lib/qarax/dhcp")]
+    dhcp_lease_dir: PathBuf,
+
+    /// Firewall backend for NAT, network isolation and security groups
+    #[clap(long, value_enum, default_value_t = FirewallBackend::Iptables)]
+    firewall_backend: FirewallBackend,
+
     /// Enable OpenTelemetry export
     #[clap(long, default_value = "false", env = "OTEL_ENABLED")]
     otel_enabled: bool,
@@ -130,9 +147,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         args.overlaybd_cache_dir.display()
     );
 
+    info!("Firewall backend: {:?}", args.firewall_backend);
+    firewall::init(args.firewall_backend).await?;
+    firewall_log::start();
+
     // Ensure directories exist
     tokio::fs::create_dir_all(&args.runtime_dir).await?;
     tokio::fs::create_dir_all(&args.overlaybd_cache_dir).await?;
+    dhcp::init(&args.dhcp_lease_dir).await?;
 
     // Build OverlayBdManager if convertor binary is present
     let overlaybd_manager = if args.convertor_binary.exists() {
@@ -140,6 +162,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let mgr = Arc::new(OverlayBdManager::new(
             &args.convertor_binary,
             &args.overlaybd_cache_dir,
+            args.overlaybd_cache_max_bytes,
         ));
         mgr.recover().await;
         Some(mgr)
@@ -165,7 +188,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut storage_backends = StorageBackendRegistry::new();
     storage_backends.register(StoragePoolKind::Local, Arc::new(LocalBackend));
     storage_backends.register(StoragePoolKind::Nfs, Arc::new(NfsBackend));
-    storage_backends.register(StoragePoolKind::Block, Arc::new(BlockBackend));
+    storage_backends.register(StoragePoolKind::Block, Arc::new(BlockBackend::new()));
+    storage_backends.register(StoragePoolKind::Rbd, Arc::new(RbdBackend::new()));
     if let Some(ref obd) = overlaybd_manager {
         storage_backends.register(
             StoragePoolKind::Overlaybd,
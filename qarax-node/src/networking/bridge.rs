@@ -1,7 +1,10 @@
 use anyhow::{Context, Result};
 use futures::TryStreamExt;
 use netlink_packet_route::{
-    AddressFamily, address::AddressAttribute, link::LinkAttribute, route::RouteAddress,
+    AddressFamily,
+    address::AddressAttribute,
+    link::{InfoKind, LinkAttribute, LinkInfo},
+    route::RouteAddress,
     route::RouteAttribute,
 };
 use rtnetlink::{Handle, IpVersion};
@@ -146,9 +149,62 @@ async fn find_bridge_member(handle: &Handle, bridge_name: &str) -> Result<String
     anyhow::bail!("No physical member found for bridge {bridge_name}")
 }
 
+/// Find the 802.1Q sub-interface enslaved to the bridge at `bridge_idx`.
+async fn find_vlan_member(handle: &Handle, bridge_idx: u32) -> Result<Option<String>> {
+    let mut stream = handle.link().get().execute();
+    while let Some(link) = stream.try_next().await? {
+        let mut master: Option<u32> = None;
+        let mut name = String::new();
+        let mut is_vlan = false;
+        for attr in &link.attributes {
+            match attr {
+                LinkAttribute::IfName(n) => name = n.clone(),
+                LinkAttribute::Controller(idx) => master = Some(*idx),
+                LinkAttribute::LinkInfo(info) => {
+                    is_vlan = info
+                        .iter()
+                        .any(|i| matches!(i, LinkInfo::Kind(InfoKind::Vlan)))
+                }
+                _ => {}
+            }
+        }
+        if master == Some(bridge_idx) && is_vlan && !name.is_empty() {
+            return Ok(Some(name));
+        }
+    }
+    Ok(None)
+}
+
+/// Delete a link by name. A link that is already gone counts as deleted, so
+/// teardown can be retried after a partial failure.
+async fn delete_link(handle: &Handle, name: &str) -> Result<()> {
+    let Ok(idx) = link_index(handle, name).await else {
+        debug!("Link {name} already deleted");
+        return Ok(());
+    };
+    if let Err(e) = handle.link().del(idx).execute().await {
+        // Somebody else may have removed it between the lookup and the delete.
+        if link_index(handle, name).await.is_ok() {
+            return Err(e).with_context(|| format!("Failed to delete link {name}"));
+        }
+    }
+    Ok(())
+}
+
 use super::validate_iface_name;
 
-fn parse_cidr(cidr: &str) -> Result<(Ipv4Addr, u8)> {
+/// Name of the VLAN sub-interface: `parent.VID` when that fits in IFNAMSIZ,
+/// otherwise the parent's ifindex stands in for its name.
+fn vlan_interface_name(parent: &str, parent_idx: u32, vlan_id: u16) -> String {
+    let name = format!("{parent}.{vlan_id}");
+    if name.len() <= 15 {
+        name
+    } else {
+        format!("qv{parent_idx}.{vlan_id}")
+    }
+}
+
+fn parse_cidr(cidr: &str) -> Result<(IpAddr, u8)> {
     let (ip_str, prefix_str) = cidr
         .split_once('/')
         .ok_or_else(|| anyhow::anyhow!("Invalid CIDR: {cidr}"))?;
@@ -187,7 +243,7 @@ pub async fn create_bridge(name: &str) -> Result<()> {
     Ok(())
 }
 
-/// Assign an IP address to the bridge (gateway for the subnet).
+/// Assign an IPv4 or IPv6 address to the bridge (gateway for the subnet).
 pub async fn set_bridge_ip(name: &str, gateway_cidr: &str) -> Result<()> {
     info!("Setting bridge {name} IP to {gateway_cidr}");
     let (ip, prefix) = parse_cidr(gateway_cidr)?;
@@ -196,7 +252,7 @@ pub async fn set_bridge_ip(name: &str, gateway_cidr: &str) -> Result<()> {
 
     handle
         .address()
-        .add(idx, ip.into(), prefix)
+        .add(idx, ip, prefix)
         .execute()
         .await
         .with_context(|| format!("Failed to set IP {gateway_cidr} on bridge {name}"))?;
@@ -204,6 +260,103 @@ pub async fn set_bridge_ip(name: &str, gateway_cidr: &str) -> Result<()> {
     Ok(())
 }
 
+/// Name of the interface carrying the IPv4 default route.
+pub async fn default_route_interface() -> Result<String> {
+    let handle = netlink_handle().await?;
+    let mut stream = handle.route().get(IpVersion::V4).execute();
+    let mut oif = None;
+    while let Some(route) = stream.try_next().await? {
+        if route.header.destination_prefix_length != 0 {
+            continue;
+        }
+        oif = route.attributes.iter().find_map(|attr| match attr {
+            RouteAttribute::Oif(idx) => Some(*idx),
+            _ => None,
+        });
+        if oif.is_some() {
+            break;
+        }
+    }
+    let idx = oif.ok_or_else(|| anyhow::anyhow!("No IPv4 default route"))?;
+
+    let link = handle
+        .link()
+        .get()
+        .match_index(idx)
+        .execute()
+        .try_next()
+        .await
+        .with_context(|| format!("Failed to query link {idx}"))?
+        .ok_or_else(|| anyhow::anyhow!("Interface {idx} not found"))?;
+    link.attributes
+        .iter()
+        .find_map(|attr| match attr {
+            LinkAttribute::IfName(name) => Some(name.clone()),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow::anyhow!("Interface {idx} has no name"))
+}
+
+/// Add `ip/32` to an interface unless it is already there.
+pub async fn add_host_address(iface: &str, ip: Ipv4Addr) -> Result<()> {
+    validate_iface_name(iface)?;
+    let handle = netlink_handle().await?;
+    let idx = link_index(&handle, iface).await?;
+    if ipv4_addrs(&handle, idx).await?.contains(&(ip, 32)) {
+        return Ok(());
+    }
+
+    info!("Adding {ip}/32 to {iface}");
+    handle
+        .address()
+        .add(idx, IpAddr::V4(ip), 32)
+        .execute()
+        .await
+        .with_context(|| format!("Failed to add {ip}/32 to {iface}"))?;
+    Ok(())
+}
+
+/// Remove `ip/32` from an interface. Succeeds if it is not there.
+pub async fn del_host_address(iface: &str, ip: Ipv4Addr) -> Result<()> {
+    validate_iface_name(iface)?;
+    let handle = netlink_handle().await?;
+    let idx = link_index(&handle, iface).await?;
+    info!("Removing {ip}/32 from {iface}");
+    del_ipv4_addr(&handle, idx, ip, 32)
+        .await
+        .with_context(|| format!("Failed to remove {ip}/32 from {iface}"))
+}
+
+/// Give a bridge the anycast gateway MAC of a stretched network. Every host on
+/// the segment carries the same gateway addresses on purpose, so IPv6 duplicate
+/// address detection is turned off before they are assigned.
+pub async fn set_anycast_gateway(name: &str, mac: &str) -> Result<()> {
+    validate_iface_name(name)?;
+    super::validate_mac(mac)?;
+    info!("Setting bridge {name} anycast gateway MAC to {mac}");
+    let mac_bytes = mac
+        .split(':')
+        .map(|octet| u8::from_str_radix(octet, 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .with_context(|| format!("Invalid MAC address {mac}"))?;
+
+    tokio::fs::write(format!("/proc/sys/net/ipv6/conf/{name}/accept_dad"), b"0")
+        .await
+        .with_context(|| format!("Failed to disable DAD on bridge {name}"))?;
+
+    let handle = netlink_handle().await?;
+    let idx = link_index(&handle, name).await?;
+    handle
+        .link()
+        .set(idx)
+        .address(mac_bytes)
+        .execute()
+        .await
+        .with_context(|| format!("Failed to set MAC on bridge {name}"))?;
+
+    Ok(())
+}
+
 /// Delete a bridge device.
 pub async fn delete_bridge(name: &str) -> Result<()> {
     info!("Deleting bridge: {name}");
@@ -445,6 +598,100 @@ pub async fn unbridge_interface(bridge_name: &str) -> Result<()> {
     Ok(())
 }
 
+/// Bridge one tagged VLAN of an uplink: create the `parent.VID` sub-interface
+/// and enslave it to a new bridge. The parent keeps its addresses and stays
+/// out of any bridge, so several VLAN networks can share it. Devices left over
+/// from an earlier attempt are reused.
+pub async fn create_vlan_bridge(bridge_name: &str, parent_iface: &str, vlan_id: u16) -> Result<()> {
+    validate_iface_name(bridge_name)?;
+    validate_iface_name(parent_iface)?;
+    if !(1..=4094).contains(&vlan_id) {
+        anyhow::bail!("VLAN id {vlan_id} is outside 1-4094");
+    }
+    info!("Bridging VLAN {vlan_id} of {parent_iface} onto bridge {bridge_name}");
+    let handle = netlink_handle().await?;
+
+    let parent_idx = link_index(&handle, parent_iface).await?;
+    let vlan_name = vlan_interface_name(parent_iface, parent_idx, vlan_id);
+
+    if link_index(&handle, &vlan_name).await.is_err() {
+        handle
+            .link()
+            .add()
+            .vlan(vlan_name.clone(), parent_idx, vlan_id)
+            .execute()
+            .await
+            .with_context(|| format!("Failed to create VLAN interface {vlan_name}"))?;
+    }
+    if link_index(&handle, bridge_name).await.is_err() {
+        handle
+            .link()
+            .add()
+            .bridge(bridge_name.to_string())
+            .execute()
+            .await
+            .with_context(|| format!("Failed to create bridge {bridge_name}"))?;
+    }
+
+    let vlan_idx = link_index(&handle, &vlan_name).await?;
+    let bridge_idx = link_index(&handle, bridge_name).await?;
+
+    // Tagged frames only flow while the uplink itself is up.
+    handle
+        .link()
+        .set(parent_idx)
+        .up()
+        .execute()
+        .await
+        .with_context(|| format!("Failed to bring up {parent_iface}"))?;
+    handle
+        .link()
+        .set(vlan_idx)
+        .controller(bridge_idx)
+        .up()
+        .execute()
+        .await
+        .with_context(|| format!("Failed to add {vlan_name} to bridge {bridge_name}"))?;
+    handle
+        .link()
+        .set(bridge_idx)
+        .up()
+        .execute()
+        .await
+        .with_context(|| format!("Failed to bring up bridge {bridge_name}"))?;
+
+    info!("Bridge {bridge_name} created on VLAN interface {vlan_name}");
+    Ok(())
+}
+
+/// Undo create_vlan_bridge: delete the VLAN sub-interface and the bridge.
+/// Devices that are already gone are skipped, so detaching twice succeeds.
+pub async fn delete_vlan_bridge(bridge_name: &str) -> Result<()> {
+    validate_iface_name(bridge_name)?;
+    info!("Deleting VLAN bridge {bridge_name}");
+    let handle = netlink_handle().await?;
+
+    let Ok(bridge_idx) = link_index(&handle, bridge_name).await else {
+        debug!("Bridge {bridge_name} already deleted");
+        return Ok(());
+    };
+    if let Some(vlan_name) = find_vlan_member(&handle, bridge_idx).await? {
+        delete_link(&handle, &vlan_name).await?;
+    }
+    delete_link(&handle, bridge_name).await
+}
+
+/// Check if a bridge carries a VLAN sub-interface (i.e., was created by create_vlan_bridge).
+pub async fn is_vlan_bridge(bridge_name: &str) -> bool {
+    let Ok(handle) = netlink_handle().await else {
+        return false;
+    };
+    let Ok(bridge_idx) = link_index(&handle, bridge_name).await else {
+        return false;
+    };
+    matches!(find_vlan_member(&handle, bridge_idx).await, Ok(Some(_)))
+}
+
 /// Check if a bridge has a physical NIC member (i.e., was created by bridge_interface).
 pub async fn is_bridged_interface(bridge_name: &str) -> bool {
     let Ok(handle) = netlink_handle().await else {
@@ -1,40 +1,90 @@
 use std::collections::HashMap;
 use std::net::{Ipv4Addr, SocketAddrV4};
-use std::sync::{LazyLock, Mutex};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, OnceLock, RwLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result};
-use edge_dhcp::server::{Server, ServerOptions};
-use edge_dhcp::{Options, Packet};
+use edge_dhcp::server::{Action, Server, ServerOptions};
+use edge_dhcp::{DhcpOption, Options, Packet};
+use serde::{Deserialize, Serialize};
 use tokio::net::UdpSocket;
 use tokio::task::JoinHandle;
 use tracing::{debug, info, warn};
 
+use crate::rpc::node::{DhcpOptions, DhcpReservation};
+
 const DHCP_SERVER_PORT: u16 = 67;
 const DHCP_CLIENT_PORT: u16 = 68;
 const MAX_LEASES: usize = 256;
 const LEASE_DURATION_SECS: u32 = 43200; // 12 hours
 
+const DHCPREQUEST: u8 = 3;
+
+const OPT_HOSTNAME: u8 = 12;
+const OPT_DOMAIN_NAME: u8 = 15;
+const OPT_MTU: u8 = 26;
+const OPT_NTP: u8 = 42;
+const OPT_CLASSLESS_ROUTES: u8 = 121;
+
 static SERVERS: LazyLock<Mutex<HashMap<String, JoinHandle<()>>>> =
     LazyLock::new(|| Mutex::new(HashMap::new()));
 
+static LEASE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Reservation {
+    ip: Ipv4Addr,
+    hostname: Option<String>,
+}
+
+type Reservations = HashMap<[u8; 6], Reservation>;
+
+/// MAC -> address reservations per bridge. Kept apart from the server task so
+/// the control plane can push them before the server starts. They are not
+/// stored on the node: the control plane sends them with every attach, so
+/// re-attaching a network after a node restart restores them.
+static RESERVATIONS: LazyLock<RwLock<HashMap<String, Reservations>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Lease {
+    ip: Ipv4Addr,
+    expires: u64,
+}
+
+/// Dynamic leases handed to clients without a reservation, by MAC.
+type Leases = HashMap<[u8; 6], Lease>;
+
+/// Keep each bridge's leases in `<lease_dir>/<bridge>.json` so they survive a
+/// node restart. Without it leases are only kept in memory.
+pub async fn init(lease_dir: &Path) -> Result<()> {
+    tokio::fs::create_dir_all(lease_dir)
+        .await
+        .with_context(|| format!("Failed to create {}", lease_dir.display()))?;
+    if LEASE_DIR.set(lease_dir.to_path_buf()).is_err() {
+        anyhow::bail!("DHCP lease directory already initialized");
+    }
+    Ok(())
+}
+
 /// Start an in-process DHCP server on the specified bridge interface.
 pub async fn start_dhcp_server(
     bridge: &str,
+    subnet: &str,
     range_start: &str,
     range_end: &str,
     gateway: &str,
     dns: &str,
+    options: Option<&DhcpOptions>,
 ) -> Result<()> {
     info!(
         "Starting DHCP server on {bridge} (range {range_start}-{range_end}, gw={gateway}, dns={dns})"
     );
 
     // Validate inputs before any side effects (stopping existing server, binding socket)
-    let gateway_ip: Ipv4Addr = gateway.parse().context("Invalid gateway IP")?;
-    let range_start_ip: Ipv4Addr = range_start.parse().context("Invalid DHCP range start IP")?;
-    let range_end_ip: Ipv4Addr = range_end.parse().context("Invalid DHCP range end IP")?;
-    let dns_ip: Ipv4Addr = dns.parse().context("Invalid DNS IP")?;
+    let config = ServerConfig::parse(subnet, range_start, range_end, gateway, dns, options)?;
+    let leases = load_leases(bridge).await;
 
     // Hold lock across stop + socket bind + insert to prevent races between
     // concurrent start_dhcp_server calls for the same bridge.
@@ -50,16 +100,7 @@ pub async fn start_dhcp_server(
     let bridge_name = bridge_key.clone();
 
     let handle = tokio::spawn(async move {
-        if let Err(e) = run_dhcp_loop(
-            socket,
-            gateway_ip,
-            range_start_ip,
-            range_end_ip,
-            dns_ip,
-            &bridge_name,
-        )
-        .await
-        {
+        if let Err(e) = run_dhcp_loop(socket, config, leases, &bridge_name).await {
             tracing::error!("DHCP server for {bridge_name} exited with error: {e}");
         }
     });
@@ -78,6 +119,83 @@ pub async fn stop_dhcp_server(bridge: &str) -> Result<()> {
     Ok(())
 }
 
+/// Drop a bridge's reservations and lease file once the network is detached.
+pub async fn forget_dhcp_state(bridge: &str) -> Result<()> {
+    RESERVATIONS.write().unwrap().remove(bridge);
+    if let Some(path) = lease_file(bridge) {
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to remove {}", path.display()));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether a DHCP server is currently serving a bridge.
+pub fn is_dhcp_server_running(bridge: &str) -> bool {
+    SERVERS
+        .lock()
+        .unwrap()
+        .get(bridge)
+        .is_some_and(|handle| !handle.is_finished())
+}
+
+/// Replace the MAC -> address reservations of a bridge. A running server
+/// picks them up with the next request.
+pub fn set_dhcp_reservations(bridge: &str, reservations: &[DhcpReservation]) -> Result<()> {
+    super::validate_iface_name(bridge)?;
+
+    let mut parsed = HashMap::with_capacity(reservations.len());
+    for reservation in reservations {
+        let mac = parse_mac(&reservation.mac)?;
+        let ip: Ipv4Addr = reservation
+            .ip
+            .parse()
+            .with_context(|| format!("Invalid reserved IP for {}", reservation.mac))?;
+        let hostname = &reservation.hostname;
+        if hostname.len() > 63
+            || !hostname
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        {
+            anyhow::bail!("Invalid hostname {hostname:?}");
+        }
+        parsed.insert(
+            mac,
+            Reservation {
+                ip,
+                hostname: (!hostname.is_empty()).then(|| hostname.clone()),
+            },
+        );
+    }
+
+    debug!("DHCP reservations for {bridge}: {}", parsed.len());
+    RESERVATIONS
+        .write()
+        .unwrap()
+        .insert(bridge.to_string(), parsed);
+    Ok(())
+}
+
+fn parse_mac(mac: &str) -> Result<[u8; 6]> {
+    super::validate_mac(mac)?;
+    let mut octets = [0u8; 6];
+    for (octet, hex) in octets.iter_mut().zip(mac.split(':')) {
+        *octet = u8::from_str_radix(hex, 16)?;
+    }
+    Ok(octets)
+}
+
+fn format_mac(mac: &[u8; 6]) -> String {
+    mac.iter()
+        .map(|octet| format!("{octet:02x}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
 fn create_dhcp_socket(bridge: &str) -> Result<UdpSocket> {
     let socket = socket2::Socket::new(
         socket2::Domain::IPV4,
@@ -115,28 +233,360 @@ fn now_secs() -> u64 {
         .as_secs()
 }
 
-async fn run_dhcp_loop(
-    socket: UdpSocket,
+/// The server's addressing plus the options edge-dhcp does not send itself,
+/// encoded once so every reply can append them.
+#[derive(Debug)]
+struct ServerConfig {
     gateway: Ipv4Addr,
+    subnet_mask: Ipv4Addr,
     range_start: Ipv4Addr,
     range_end: Ipv4Addr,
     dns: Ipv4Addr,
+    /// Option code and value. Values over 255 bytes are split into
+    /// consecutive instances that clients concatenate (RFC 3396).
+    extra_options: Vec<(u8, Vec<u8>)>,
+}
+
+impl ServerConfig {
+    fn parse(
+        subnet: &str,
+        range_start: &str,
+        range_end: &str,
+        gateway: &str,
+        dns: &str,
+        options: Option<&DhcpOptions>,
+    ) -> Result<Self> {
+        let prefix_len: u8 = subnet
+            .split_once('/')
+            .map(|(_, prefix)| prefix)
+            .unwrap_or("24")
+            .parse()
+            .context("Invalid subnet prefix length")?;
+        let mut config = Self {
+            gateway: gateway.parse().context("Invalid gateway IP")?,
+            subnet_mask: prefix_mask(prefix_len)?,
+            range_start: range_start.parse().context("Invalid DHCP range start IP")?,
+            range_end: range_end.parse().context("Invalid DHCP range end IP")?,
+            dns: dns.parse().context("Invalid DNS IP")?,
+            extra_options: Vec::new(),
+        };
+        let Some(options) = options else {
+            return Ok(config);
+        };
+
+        if !options.domain_name.is_empty() {
+            if options.domain_name.len() > 253 {
+                anyhow::bail!("Domain name {:?} is too long", options.domain_name);
+            }
+            config.push_option(OPT_DOMAIN_NAME, options.domain_name.as_bytes());
+        }
+        if options.mtu != 0 {
+            let mtu = u16::try_from(options.mtu)
+                .ok()
+                .filter(|mtu| *mtu >= 68)
+                .with_context(|| format!("Invalid MTU {}", options.mtu))?;
+            config.push_option(OPT_MTU, &mtu.to_be_bytes());
+        }
+        let mut ntp_servers = Vec::new();
+        for server in &options.ntp_servers {
+            let server: Ipv4Addr = server
+                .parse()
+                .with_context(|| format!("Invalid NTP server {server:?}"))?;
+            ntp_servers.extend_from_slice(&server.octets());
+        }
+        if !ntp_servers.is_empty() {
+            config.push_option(OPT_NTP, &ntp_servers);
+        }
+        if !options.static_routes.is_empty() {
+            // Clients that accept option 121 ignore the router option
+            // (RFC 3442), so the default route has to be repeated here.
+            let mut routes = Vec::new();
+            for route in &options.static_routes {
+                let (destination, prefix_len) =
+                    route.destination.split_once('/').with_context(|| {
+                        format!("Invalid route destination {:?}", route.destination)
+                    })?;
+                let prefix_len: u8 = prefix_len.parse().context("Invalid route prefix length")?;
+                let mask = u32::from(prefix_mask(prefix_len)?);
+                let destination: Ipv4Addr = destination.parse().with_context(|| {
+                    format!("Invalid route destination {:?}", route.destination)
+                })?;
+                let next_hop: Ipv4Addr = route
+                    .gateway
+                    .parse()
+                    .with_context(|| format!("Invalid route gateway {:?}", route.gateway))?;
+                push_route(
+                    &mut routes,
+                    Ipv4Addr::from(u32::from(destination) & mask),
+                    prefix_len,
+                    next_hop,
+                );
+            }
+            push_route(&mut routes, Ipv4Addr::UNSPECIFIED, 0, config.gateway);
+            config.push_option(OPT_CLASSLESS_ROUTES, &routes);
+        }
+        Ok(config)
+    }
+
+    fn push_option(&mut self, code: u8, value: &[u8]) {
+        for chunk in value.chunks(255) {
+            self.extra_options.push((code, chunk.to_vec()));
+        }
+    }
+}
+
+fn prefix_mask(prefix_len: u8) -> Result<Ipv4Addr> {
+    if prefix_len > 32 {
+        anyhow::bail!("Invalid prefix length {prefix_len}");
+    }
+    Ok(Ipv4Addr::from(
+        u32::MAX
+            .checked_shl(32 - u32::from(prefix_len))
+            .unwrap_or(0),
+    ))
+}
+
+/// One RFC 3442 route: prefix length, the significant destination octets,
+/// then the next hop.
+fn push_route(out: &mut Vec<u8>, destination: Ipv4Addr, prefix_len: u8, next_hop: Ipv4Addr) {
+    out.push(prefix_len);
+    out.extend_from_slice(&destination.octets()[..usize::from(prefix_len).div_ceil(8)]);
+    out.extend_from_slice(&next_hop.octets());
+}
+
+/// First address the dynamic pool may hand out. The control plane allocates
+/// reserved addresses from the bottom of the range, so the pool starts past
+/// the highest reservation inside it. `None` when reservations fill the
+/// range and there is no dynamic pool left.
+fn dynamic_range_start(
+    range_start: Ipv4Addr,
+    range_end: Ipv4Addr,
+    reservations: &Reservations,
+) -> Option<Ipv4Addr> {
+    let Some(highest) = reservations
+        .values()
+        .map(|reservation| reservation.ip)
+        .filter(|ip| (range_start..=range_end).contains(ip))
+        .max()
+    else {
+        return Some(range_start);
+    };
+    u32::from(highest)
+        .checked_add(1)
+        .map(Ipv4Addr::from)
+        .filter(|ip| *ip <= range_end)
+}
+
+/// Answer a client that has a reservation: it is offered its reserved
+/// address, and NAKed when it asks for any other.
+fn reserved_reply<'o>(
+    server_options: &ServerOptions<'_>,
+    request: &'o Packet<'o>,
+    ip: Ipv4Addr,
+    opt_buf: &'o mut [DhcpOption<'o>],
+) -> Option<Packet<'o>> {
+    match server_options.process(request)? {
+        Action::Discover(..) => Some(server_options.offer(request, ip, opt_buf)),
+        Action::Request(requested, _) => {
+            Some(server_options.ack_nak(request, (requested == ip).then_some(ip), opt_buf))
+        }
+        _ => None,
+    }
+}
+
+fn client_mac(request: &Packet<'_>) -> [u8; 6] {
+    let mut mac = [0u8; 6];
+    mac.copy_from_slice(&request.chaddr[..6]);
+    mac
+}
+
+/// A raw BOOTREQUEST from `mac` with a message type and requested address.
+fn client_request(mac: &[u8; 6], message_type: u8, requested: Ipv4Addr) -> Vec<u8> {
+    let mut packet = vec![0u8; 240];
+    packet[..4].copy_from_slice(&[1, 1, 6, 0]);
+    packet[4..8].copy_from_slice(&[1, 2, 3, 4]);
+    packet[28..34].copy_from_slice(mac);
+    packet[236..240].copy_from_slice(&[99, 130, 83, 99]);
+    packet.extend_from_slice(&[53, 1, message_type, 50, 4]);
+    packet.extend_from_slice(&requested.octets());
+    packet.push(255);
+    packet
+}
+
+/// Put stored leases back into edge-dhcp's table by replaying each one as a
+/// DHCPREQUEST. edge-dhcp starts the lease's clock afresh, so an address is
+/// at most held a little longer than it was granted. Leases the server no
+/// longer grants (outside the range, or now reserved) are dropped.
+fn seed_leases<F>(
+    server: &mut Server<F, MAX_LEASES>,
+    server_options: &ServerOptions<'_>,
+    leases: &mut Leases,
+    reservations: &Reservations,
+) where
+    F: FnMut() -> u64,
+{
+    leases.retain(|mac, lease| {
+        if reservations.contains_key(mac)
+            || reservations
+                .values()
+                .any(|reservation| reservation.ip == lease.ip)
+        {
+            return false;
+        }
+        let packet = client_request(mac, DHCPREQUEST, lease.ip);
+        let Ok(request) = Packet::decode(&packet) else {
+            return false;
+        };
+        let mut opt_buf = Options::buf();
+        server
+            .handle_request(&mut opt_buf, server_options, &request)
+            .is_some_and(|reply| reply.yiaddr == lease.ip)
+    });
+}
+
+/// Mirror what edge-dhcp just did for a client without a reservation into
+/// `leases`. Returns whether the table changed.
+fn record_lease(
+    leases: &mut Leases,
+    server_options: &ServerOptions<'_>,
+    request: &Packet<'_>,
+    reply: Option<&Packet<'_>>,
+    now: u64,
+) -> bool {
+    let mac = client_mac(request);
+    let before = leases.len();
+    leases.retain(|_, lease| lease.expires > now);
+    let expired = leases.len() != before;
+
+    match server_options.process(request) {
+        Some(Action::Request(ip, _)) if reply.is_some_and(|reply| reply.yiaddr == ip) => {
+            leases.insert(
+                mac,
+                Lease {
+                    ip,
+                    expires: now + u64::from(LEASE_DURATION_SECS),
+                },
+            );
+            true
+        }
+        Some(Action::Release(ip, _) | Action::Decline(ip, _))
+            if leases.get(&mac).is_some_and(|lease| lease.ip == ip) =>
+        {
+            leases.remove(&mac);
+            true
+        }
+        _ => expired,
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredLease {
+    ip: Ipv4Addr,
+    mac: String,
+    expires: u64,
+}
+
+fn lease_file(bridge: &str) -> Option<PathBuf> {
+    LEASE_DIR
+        .get()
+        .map(|dir| dir.join(format!("{bridge}.json")))
+}
+
+async fn load_leases(bridge: &str) -> Leases {
+    let mut leases = Leases::new();
+    let Some(path) = lease_file(bridge) else {
+        return leases;
+    };
+    let contents = match tokio::fs::read(&path).await {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return leases,
+        Err(e) => {
+            warn!("Failed to read {}: {e}", path.display());
+            return leases;
+        }
+    };
+    let stored: Vec<StoredLease> = match serde_json::from_slice(&contents) {
+        Ok(stored) => stored,
+        Err(e) => {
+            warn!("Ignoring corrupt lease file {}: {e}", path.display());
+            return leases;
+        }
+    };
+
+    let now = now_secs();
+    for lease in stored.into_iter().filter(|lease| lease.expires > now) {
+        if let Ok(mac) = parse_mac(&lease.mac) {
+            leases.insert(
+                mac,
+                Lease {
+                    ip: lease.ip,
+                    expires: lease.expires,
+                },
+            );
+        }
+    }
+    info!("Loaded {} DHCP leases for {bridge}", leases.len());
+    leases
+}
+
+/// Write the lease table next to its final path and rename it into place, so
+/// a crash never leaves a truncated file.
+async fn save_leases(bridge: &str, leases: &Leases) -> Result<()> {
+    let Some(path) = lease_file(bridge) else {
+        return Ok(());
+    };
+    let mut stored: Vec<StoredLease> = leases
+        .iter()
+        .map(|(mac, lease)| StoredLease {
+            ip: lease.ip,
+            mac: format_mac(mac),
+            expires: lease.expires,
+        })
+        .collect();
+    stored.sort_by_key(|lease| lease.ip);
+
+    let tmp = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, serde_json::to_vec_pretty(&stored)?)
+        .await
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    tokio::fs::rename(&tmp, &path)
+        .await
+        .with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+async fn run_dhcp_loop(
+    socket: UdpSocket,
+    config: ServerConfig,
+    mut leases: Leases,
     bridge: &str,
 ) -> Result<()> {
-    let mut server = Server::<_, MAX_LEASES>::new(now_secs, gateway);
-    server.range_start = range_start;
-    server.range_end = range_end;
+    let mut server = Server::<_, MAX_LEASES>::new(now_secs, config.gateway);
+    server.range_start = config.range_start;
+    server.range_end = config.range_end;
 
-    let gw_buf = [gateway];
-    let dns_buf = [dns];
-    let mut options = ServerOptions::new(gateway, None);
+    let gw_buf = [config.gateway];
+    let dns_buf = [config.dns];
+    let mut options = ServerOptions::new(config.gateway, None);
     options.gateways = &gw_buf;
     options.dns = &dns_buf;
+    options.subnet = Some(config.subnet_mask);
     options.lease_duration_secs = LEASE_DURATION_SECS;
 
-    info!("DHCP server for {bridge}: range {range_start}-{range_end}, gw={gateway}, dns={dns}");
+    {
+        let reservations = RESERVATIONS.read().unwrap();
+        let none = HashMap::new();
+        let reservations = reservations.get(bridge).unwrap_or(&none);
+        seed_leases(&mut server, &options, &mut leases, reservations);
+    }
+
+    info!(
+        "DHCP server for {bridge}: range {}-{}, gw={}, dns={}",
+        config.range_start, config.range_end, config.gateway, config.dns
+    );
 
     let mut buf = [0u8; 1500];
+    let mut out = [0u8; 1500];
 
     loop {
         let (len, _src) = socket.recv_from(&mut buf).await?;
@@ -149,27 +599,249 @@ async fn run_dhcp_loop(
             }
         };
 
+        let (reservation, dynamic_start) = {
+            let reservations = RESERVATIONS.read().unwrap();
+            let none = HashMap::new();
+            let reservations = reservations.get(bridge).unwrap_or(&none);
+            (
+                reservations.get(&client_mac(&request)).cloned(),
+                dynamic_range_start(config.range_start, config.range_end, reservations),
+            )
+        };
+
         let mut opt_buf = Options::buf();
+        let mut leases_changed = false;
+        let reply = match (&reservation, dynamic_start) {
+            (Some(reservation), _) => {
+                reserved_reply(&options, &request, reservation.ip, &mut opt_buf)
+            }
+            (None, Some(dynamic_start)) => {
+                server.range_start = dynamic_start;
+                let reply = server.handle_request(&mut opt_buf, &options, &request);
+                leases_changed =
+                    record_lease(&mut leases, &options, &request, reply.as_ref(), now_secs());
+                reply
+            }
+            (None, None) => {
+                debug!(
+                    "Reservations fill the DHCP range on {bridge}; ignoring {}",
+                    format_mac(&client_mac(&request))
+                );
+                None
+            }
+        };
+        if leases_changed && let Err(e) = save_leases(bridge, &leases).await {
+            warn!("Failed to save DHCP leases for {bridge}: {e:#}");
+        }
+        let Some(reply) = reply else {
+            continue;
+        };
 
-        if let Some(reply) = server.handle_request(&mut opt_buf, &options, &request) {
-            // RFC 2131 Section 4.1 destination rules
-            let (dest_ip, dest_port) = if !request.giaddr.is_unspecified() {
-                (request.giaddr, DHCP_SERVER_PORT)
-            } else if !request.ciaddr.is_unspecified() && !request.broadcast {
-                (request.ciaddr, DHCP_CLIENT_PORT)
-            } else {
-                (Ipv4Addr::BROADCAST, DHCP_CLIENT_PORT)
-            };
-
-            match reply.encode(&mut buf) {
-                Ok(encoded) => {
-                    let dest = SocketAddrV4::new(dest_ip, dest_port);
-                    if let Err(e) = socket.send_to(encoded, dest).await {
-                        warn!("Failed to send DHCP reply on {bridge}: {e}");
-                    }
+        // NAKs carry no address, and none of the address's options.
+        let mut reply_options: Vec<DhcpOption<'_>> = reply.options.iter().collect();
+        if !reply.yiaddr.is_unspecified() {
+            if let Some(hostname) = reservation.as_ref().and_then(|r| r.hostname.as_deref()) {
+                reply_options.push(DhcpOption::Unrecognized(OPT_HOSTNAME, hostname.as_bytes()));
+            }
+            reply_options.extend(
+                config
+                    .extra_options
+                    .iter()
+                    .map(|(code, value)| DhcpOption::Unrecognized(*code, value.as_slice())),
+            );
+        }
+        let reply = Packet {
+            options: Options::new(&reply_options),
+            ..reply
+        };
+
+        // RFC 2131 Section 4.1 destination rules
+        let (dest_ip, dest_port) = if !request.giaddr.is_unspecified() {
+            (request.giaddr, DHCP_SERVER_PORT)
+        } else if !request.ciaddr.is_unspecified() && !request.broadcast {
+            (request.ciaddr, DHCP_CLIENT_PORT)
+        } else {
+            (Ipv4Addr::BROADCAST, DHCP_CLIENT_PORT)
+        };
+
+        match reply.encode(&mut out) {
+            Ok(encoded) => {
+                let dest = SocketAddrV4::new(dest_ip, dest_port);
+                if let Err(e) = socket.send_to(encoded, dest).await {
+                    warn!("Failed to send DHCP reply on {bridge}: {e}");
                 }
-                Err(e) => warn!("Failed to encode DHCP reply on {bridge}: {e:?}"),
             }
+            Err(e) => warn!("Failed to encode DHCP reply on {bridge}: {e:?}"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::node::DhcpStaticRoute;
+
+    const MAC_A: [u8; 6] = [0x52, 0x54, 0, 0, 0, 0xa];
+
+    fn reservation(ip: &str) -> Reservation {
+        Reservation {
+            ip: ip.parse().unwrap(),
+            hostname: None,
+        }
+    }
+
+    fn request(message_type: u8, requested: Ipv4Addr) -> Vec<u8> {
+        client_request(&MAC_A, message_type, requested)
+    }
+
+    #[test]
+    fn options_edge_dhcp_does_not_send_are_encoded_once() {
+        let config = ServerConfig::parse(
+            "10.0.0.0/24",
+            "10.0.0.2",
+            "10.0.0.254",
+            "10.0.0.1",
+            "10.0.0.1",
+            Some(&DhcpOptions {
+                domain_name: "app.internal".into(),
+                ntp_servers: vec!["10.0.0.1".into()],
+                mtu: 1450,
+                static_routes: vec![DhcpStaticRoute {
+                    destination: "10.50.0.0/16".into(),
+                    gateway: "10.0.0.254".into(),
+                }],
+            }),
+        )
+        .unwrap();
+
+        assert_eq!(config.subnet_mask, Ipv4Addr::new(255, 255, 255, 0));
+        assert_eq!(
+            config.extra_options,
+            vec![
+                (OPT_DOMAIN_NAME, b"app.internal".to_vec()),
+                (OPT_MTU, 1450u16.to_be_bytes().to_vec()),
+                (OPT_NTP, vec![10, 0, 0, 1]),
+                (
+                    OPT_CLASSLESS_ROUTES,
+                    vec![16, 10, 50, 10, 0, 0, 254, 0, 10, 0, 0, 1]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn dynamic_pool_starts_past_the_reservations_in_the_range() {
+        let start = Ipv4Addr::new(10, 0, 0, 2);
+        let end = Ipv4Addr::new(10, 0, 0, 254);
+        let reservations = HashMap::from([
+            ([1; 6], reservation("10.0.0.2")),
+            ([2; 6], reservation("10.0.0.5")),
+            // Outside the range, so it cannot collide with the pool.
+            ([3; 6], reservation("10.9.0.9")),
+        ]);
+
+        assert_eq!(
+            dynamic_range_start(start, end, &reservations),
+            Some(Ipv4Addr::new(10, 0, 0, 6))
+        );
+        assert_eq!(
+            dynamic_range_start(start, end, &HashMap::new()),
+            Some(start)
+        );
+    }
+
+    #[test]
+    fn reservations_filling_the_range_leave_no_dynamic_pool() {
+        let start = Ipv4Addr::new(10, 0, 0, 2);
+        let end = Ipv4Addr::new(10, 0, 0, 3);
+        let reservations = HashMap::from([
+            ([1; 6], reservation("10.0.0.2")),
+            ([2; 6], reservation("10.0.0.3")),
+        ]);
+        assert_eq!(dynamic_range_start(start, end, &reservations), None);
+
+        let end = Ipv4Addr::BROADCAST;
+        let reservations = HashMap::from([([1; 6], reservation("255.255.255.255"))]);
+        assert_eq!(dynamic_range_start(start, end, &reservations), None);
+    }
+
+    #[test]
+    fn acked_leases_are_recorded_and_seeded_into_a_new_server() {
+        let gateway = Ipv4Addr::new(10, 0, 0, 1);
+        let leased = Ipv4Addr::new(10, 0, 0, 2);
+        let server_options = ServerOptions::new(gateway, None);
+
+        let ack_request = request(3, leased);
+        let ack_request = Packet::decode(&ack_request).unwrap();
+        let mut opt_buf = Options::buf();
+        let ack = server_options.ack_nak(&ack_request, Some(leased), &mut opt_buf);
+        let mut leases = Leases::new();
+        assert!(record_lease(
+            &mut leases,
+            &server_options,
+            &ack_request,
+            Some(&ack),
+            1000
+        ));
+        assert_eq!(
+            leases.get(&MAC_A),
+            Some(&Lease {
+                ip: leased,
+                expires: 1000 + u64::from(LEASE_DURATION_SECS),
+            })
+        );
+
+        let mut opt_buf = Options::buf();
+        let nak = server_options.ack_nak(&ack_request, None, &mut opt_buf);
+        let mut unchanged = Leases::new();
+        assert!(!record_lease(
+            &mut unchanged,
+            &server_options,
+            &ack_request,
+            Some(&nak),
+            1000
+        ));
+        assert!(unchanged.is_empty());
+
+        // After a restart the seeded lease keeps another client off the
+        // address.
+        let mut server = Server::<_, MAX_LEASES>::new(now_secs, gateway);
+        server.range_start = leased;
+        server.range_end = Ipv4Addr::new(10, 0, 0, 3);
+        seed_leases(&mut server, &server_options, &mut leases, &HashMap::new());
+        assert_eq!(leases.len(), 1);
+
+        let discover = client_request(&[0x52, 0x54, 0, 0, 0, 0xb], 1, leased);
+        let discover = Packet::decode(&discover).unwrap();
+        let mut opt_buf = Options::buf();
+        let offer = server
+            .handle_request(&mut opt_buf, &server_options, &discover)
+            .unwrap();
+        assert_eq!(offer.yiaddr, Ipv4Addr::new(10, 0, 0, 3));
+    }
+
+    #[test]
+    fn reserved_clients_get_their_address_and_nothing_else() {
+        let gateway = Ipv4Addr::new(10, 0, 0, 1);
+        let reserved = Ipv4Addr::new(10, 0, 0, 5);
+        let server_options = ServerOptions::new(gateway, None);
+
+        let discover = request(1, Ipv4Addr::new(10, 0, 0, 9));
+        let discover = Packet::decode(&discover).unwrap();
+        let mut opt_buf = Options::buf();
+        let offer = reserved_reply(&server_options, &discover, reserved, &mut opt_buf).unwrap();
+        assert_eq!(offer.yiaddr, reserved);
+
+        let other = request(3, Ipv4Addr::new(10, 0, 0, 9));
+        let other = Packet::decode(&other).unwrap();
+        let mut opt_buf = Options::buf();
+        let nak = reserved_reply(&server_options, &other, reserved, &mut opt_buf).unwrap();
+        assert!(nak.yiaddr.is_unspecified());
+
+        let own = request(3, reserved);
+        let own = Packet::decode(&own).unwrap();
+        let mut opt_buf = Options::buf();
+        let ack = reserved_reply(&server_options, &own, reserved, &mut opt_buf).unwrap();
+        assert_eq!(ack.yiaddr, reserved);
+    }
+}
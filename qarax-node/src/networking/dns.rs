@@ -0,0 +1,549 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::rpc::node::SyncNetworkDnsRequest;
+
+const DNS_PORT: u16 = 53;
+/// Short, so a VM that moves or is recreated is picked up quickly by guests
+/// that cache.
+const RECORD_TTL_SECS: u32 = 30;
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_MESSAGE_LEN: usize = 4096;
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_AAAA: u16 = 28;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+const RCODE_NOERROR: u8 = 0;
+const RCODE_SERVFAIL: u8 = 2;
+const RCODE_NXDOMAIN: u8 = 3;
+
+/// Records served for one network, looked up by lowercase name.
+#[derive(Debug, Default)]
+struct Zone {
+    /// e.g. `app.internal`, without the trailing dot.
+    origin: String,
+    v4: HashMap<String, Vec<Ipv4Addr>>,
+    v6: HashMap<String, Vec<Ipv6Addr>>,
+    /// Reverse name (`5.0.0.10.in-addr.arpa`) to the owning FQDN.
+    ptr: HashMap<String, String>,
+}
+
+struct Resolver {
+    listen: Vec<IpAddr>,
+    zone: Arc<RwLock<Zone>>,
+    upstreams: Arc<RwLock<Vec<SocketAddr>>>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+static RESOLVERS: LazyLock<Mutex<HashMap<String, Resolver>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Serve `req.zone` on the bridge gateway, replacing the records of a
+/// resolver that is already running. Bridges that carry no gateway address
+/// of their own (bridged or VLAN uplinks) are skipped.
+pub async fn sync_network_dns(req: &SyncNetworkDnsRequest) -> Result<()> {
+    super::validate_iface_name(&req.bridge_name)?;
+    if super::bridge::is_bridged_interface(&req.bridge_name).await
+        || super::bridge::is_vlan_bridge(&req.bridge_name).await
+    {
+        debug!(
+            "Bridge {} has no local gateway; not serving DNS",
+            req.bridge_name
+        );
+        return stop_network_dns(&req.bridge_name).await;
+    }
+
+    let zone = Zone::from_request(req)?;
+    let mut listen = vec![IpAddr::V4(
+        req.listen_ip.parse().context("Invalid DNS listen IP")?,
+    )];
+    if !req.listen_ip6.is_empty() {
+        listen.push(IpAddr::V6(
+            req.listen_ip6.parse().context("Invalid DNS listen IPv6")?,
+        ));
+    }
+    let upstreams = read_upstreams(&listen).await;
+
+    // Same locking as the DHCP server: stop + bind + insert happen together.
+    let mut resolvers = RESOLVERS.lock().unwrap();
+    if let Some(resolver) = resolvers.get(&req.bridge_name)
+        && resolver.listen == listen
+        && resolver.handles.iter().all(|handle| !handle.is_finished())
+    {
+        debug!(
+            "Updating DNS zone {} on {} ({} names)",
+            zone.origin,
+            req.bridge_name,
+            zone.v4.len().max(zone.v6.len())
+        );
+        *resolver.zone.write().unwrap() = zone;
+        *resolver.upstreams.write().unwrap() = upstreams;
+        return Ok(());
+    }
+
+    if let Some(resolver) = resolvers.remove(&req.bridge_name) {
+        resolver.abort();
+    }
+
+    info!(
+        "Starting DNS resolver for {} on {} ({:?})",
+        zone.origin, req.bridge_name, listen
+    );
+    let zone = Arc::new(RwLock::new(zone));
+    let upstreams = Arc::new(RwLock::new(upstreams));
+    let mut handles: Vec<JoinHandle<()>> = Vec::with_capacity(listen.len());
+    for ip in &listen {
+        let socket = match create_dns_socket(*ip) {
+            Ok(socket) => Arc::new(socket),
+            Err(e) => {
+                for handle in &handles {
+                    handle.abort();
+                }
+                return Err(e);
+            }
+        };
+        let zone = zone.clone();
+        let upstreams = upstreams.clone();
+        let bridge_name = req.bridge_name.clone();
+        handles.push(tokio::spawn(async move {
+            if let Err(e) = run_dns_loop(socket, zone, upstreams, &bridge_name).await {
+                tracing::error!("DNS resolver for {bridge_name} exited with error: {e}");
+            }
+        }));
+    }
+
+    resolvers.insert(
+        req.bridge_name.clone(),
+        Resolver {
+            listen,
+            zone,
+            upstreams,
+            handles,
+        },
+    );
+    Ok(())
+}
+
+/// Stop the DNS resolver for a bridge.
+pub async fn stop_network_dns(bridge: &str) -> Result<()> {
+    if let Some(resolver) = RESOLVERS.lock().unwrap().remove(bridge) {
+        info!("Stopping DNS resolver for bridge {bridge}");
+        resolver.abort();
+    }
+    Ok(())
+}
+
+impl Resolver {
+    fn abort(&self) {
+        for handle in &self.handles {
+            handle.abort();
+        }
+    }
+}
+
+impl Zone {
+    fn from_request(req: &SyncNetworkDnsRequest) -> Result<Self> {
+        let origin = req.zone.trim_end_matches('.').to_ascii_lowercase();
+        if origin.is_empty() || !origin.split('.').all(is_valid_label) {
+            anyhow::bail!("Invalid DNS zone {:?}", req.zone);
+        }
+
+        let mut zone = Zone {
+            origin,
+            ..Zone::default()
+        };
+        for record in &req.records {
+            let name = record.name.to_ascii_lowercase();
+            if !is_valid_label(&name) {
+                anyhow::bail!("Invalid DNS name {:?}", record.name);
+            }
+            let fqdn = format!("{name}.{}", zone.origin);
+            if !record.ip.is_empty() {
+                let ip: Ipv4Addr = record
+                    .ip
+                    .parse()
+                    .with_context(|| format!("Invalid IPv4 address for {fqdn}"))?;
+                zone.v4.entry(name.clone()).or_default().push(ip);
+                zone.ptr
+                    .entry(reverse_name(IpAddr::V4(ip)))
+                    .or_insert_with(|| fqdn.clone());
+            }
+            if !record.ip6.is_empty() {
+                let ip: Ipv6Addr = record
+                    .ip6
+                    .parse()
+                    .with_context(|| format!("Invalid IPv6 address for {fqdn}"))?;
+                zone.v6.entry(name.clone()).or_default().push(ip);
+                zone.ptr
+                    .entry(reverse_name(IpAddr::V6(ip)))
+                    .or_insert_with(|| fqdn.clone());
+            }
+        }
+        Ok(zone)
+    }
+}
+
+fn is_valid_label(label: &str) -> bool {
+    !label.is_empty()
+        && label.len() <= 63
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+        && label
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+fn reverse_name(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, d] = ip.octets();
+            format!("{d}.{c}.{b}.{a}.in-addr.arpa")
+        }
+        IpAddr::V6(ip) => {
+            let mut name = String::with_capacity(72);
+            for byte in ip.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", byte & 0x0f, byte >> 4));
+            }
+            name.push_str("ip6.arpa");
+            name
+        }
+    }
+}
+
+/// Nameservers from the node's resolv.conf, minus our own listen addresses so
+/// a host pointed at its own bridge cannot loop.
+async fn read_upstreams(listen: &[IpAddr]) -> Vec<SocketAddr> {
+    let contents = match tokio::fs::read_to_string(RESOLV_CONF).await {
+        Ok(contents) => contents,
+        Err(e) => {
+            warn!("Failed to read {RESOLV_CONF}; only local names will resolve: {e}");
+            return Vec::new();
+        }
+    };
+    parse_nameservers(&contents)
+        .into_iter()
+        .filter(|ip| !listen.contains(ip))
+        .map(|ip| SocketAddr::new(ip, DNS_PORT))
+        .collect()
+}
+
+fn parse_nameservers(resolv_conf: &str) -> Vec<IpAddr> {
+    resolv_conf
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            (fields.next()? == "nameserver").then_some(())?;
+            // Drop an IPv6 zone index (fe80::1%eth0); the kernel picks the
+            // interface from the routing table.
+            let address = fields.next()?.split('%').next()?;
+            address.parse().ok()
+        })
+        .collect()
+}
+
+fn create_dns_socket(ip: IpAddr) -> Result<UdpSocket> {
+    let domain = match ip {
+        IpAddr::V4(_) => socket2::Domain::IPV4,
+        IpAddr::V6(_) => socket2::Domain::IPV6,
+    };
+    let socket = socket2::Socket::new(domain, socket2::Type::DGRAM, Some(socket2::Protocol::UDP))
+        .context("Failed to create UDP socket")?;
+
+    socket
+        .set_reuse_address(true)
+        .context("Failed to set SO_REUSEADDR")?;
+    // The bridge IPv6 address may still be tentative right after attach.
+    match ip {
+        IpAddr::V4(_) => socket.set_freebind(true),
+        IpAddr::V6(_) => socket.set_freebind_ipv6(true),
+    }
+    .context("Failed to set IP_FREEBIND")?;
+    socket
+        .bind(&socket2::SockAddr::from(SocketAddr::new(ip, DNS_PORT)))
+        .with_context(|| format!("Failed to bind DNS socket to {ip}"))?;
+    socket
+        .set_nonblocking(true)
+        .context("Failed to set socket non-blocking")?;
+
+    UdpSocket::from_std(socket.into()).context("Failed to create tokio UdpSocket")
+}
+
+async fn run_dns_loop(
+    socket: Arc<UdpSocket>,
+    zone: Arc<RwLock<Zone>>,
+    upstreams: Arc<RwLock<Vec<SocketAddr>>>,
+    bridge: &str,
+) -> Result<()> {
+    let mut buf = [0u8; MAX_MESSAGE_LEN];
+
+    loop {
+        let (len, client) = socket.recv_from(&mut buf).await?;
+        let query = &buf[..len];
+
+        let local = answer(query, &zone.read().unwrap());
+        if let Some(reply) = local {
+            if let Err(e) = socket.send_to(&reply, client).await {
+                warn!("Failed to send DNS reply on {bridge}: {e}");
+            }
+            continue;
+        }
+
+        // A slow upstream must not hold up local names.
+        let query = query.to_vec();
+        let upstreams = upstreams.read().unwrap().clone();
+        let socket = socket.clone();
+        tokio::spawn(async move {
+            let reply = match forward(&query, &upstreams).await {
+                Some(reply) => reply,
+                None => match error_reply(&query, RCODE_SERVFAIL) {
+                    Some(reply) => reply,
+                    None => return,
+                },
+            };
+            let _ = socket.send_to(&reply, client).await;
+        });
+    }
+}
+
+/// Relay a query to each upstream in turn and return the first reply that
+/// carries the query's id.
+async fn forward(query: &[u8], upstreams: &[SocketAddr]) -> Option<Vec<u8>> {
+    if query.len() < 12 {
+        return None;
+    }
+    for upstream in upstreams {
+        let bind: SocketAddr = match upstream {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let Ok(socket) = UdpSocket::bind(bind).await else {
+            continue;
+        };
+        if socket.send_to(query, upstream).await.is_err() {
+            continue;
+        }
+        let mut buf = vec![0u8; MAX_MESSAGE_LEN];
+        match tokio::time::timeout(UPSTREAM_TIMEOUT, socket.recv_from(&mut buf)).await {
+            Ok(Ok((len, from))) if from == *upstream && len >= 12 && buf[..2] == query[..2] => {
+                buf.truncate(len);
+                return Some(buf);
+            }
+            _ => debug!("No usable DNS reply from {upstream}"),
+        }
+    }
+    None
+}
+
+struct Question {
+    /// Lowercase, without the trailing dot.
+    name: String,
+    qtype: u16,
+    /// Offset just past the question section.
+    end: usize,
+}
+
+/// Parse a standard query with exactly one question. Anything else (responses,
+/// other opcodes, compressed question names) is left to the upstream.
+fn parse_question(query: &[u8]) -> Option<Question> {
+    if query.len() < 12 || query[2] & 0x80 != 0 || (query[2] >> 3) & 0x0f != 0 {
+        return None;
+    }
+    if u16::from_be_bytes([query[4], query[5]]) != 1 {
+        return None;
+    }
+
+    let mut labels = Vec::new();
+    let mut pos = 12;
+    loop {
+        let len = *query.get(pos)? as usize;
+        pos += 1;
+        if len == 0 {
+            break;
+        }
+        if len > 63 {
+            return None;
+        }
+        let label = query.get(pos..pos + len)?;
+        labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+        pos += len;
+    }
+    let qtype = u16::from_be_bytes([*query.get(pos)?, *query.get(pos + 1)?]);
+    let qclass = u16::from_be_bytes([*query.get(pos + 2)?, *query.get(pos + 3)?]);
+    if qclass != CLASS_IN {
+        return None;
+    }
+
+    Some(Question {
+        name: labels.join("."),
+        qtype,
+        end: pos + 4,
+    })
+}
+
+/// Answer a query from the zone, or `None` when it should be forwarded.
+fn answer(query: &[u8], zone: &Zone) -> Option<Vec<u8>> {
+    let question = parse_question(query)?;
+    let mut records: Vec<(u16, Vec<u8>)> = Vec::new();
+
+    let rcode = if let Some(host) = question
+        .name
+        .strip_suffix(&zone.origin)
+        .and_then(|prefix| prefix.strip_suffix('.'))
+        .or_else(|| (question.name == zone.origin).then_some(""))
+    {
+        let v4 = zone.v4.get(host);
+        let v6 = zone.v6.get(host);
+        if matches!(question.qtype, TYPE_A | TYPE_ANY) {
+            for ip in v4.into_iter().flatten() {
+                records.push((TYPE_A, ip.octets().to_vec()));
+            }
+        }
+        if matches!(question.qtype, TYPE_AAAA | TYPE_ANY) {
+            for ip in v6.into_iter().flatten() {
+                records.push((TYPE_AAAA, ip.octets().to_vec()));
+            }
+        }
+        if host.is_empty() || v4.is_some() || v6.is_some() {
+            RCODE_NOERROR
+        } else {
+            RCODE_NXDOMAIN
+        }
+    } else if let Some(target) = zone.ptr.get(&question.name) {
+        if matches!(question.qtype, TYPE_PTR | TYPE_ANY) {
+            records.push((TYPE_PTR, encode_name(target)));
+        }
+        RCODE_NOERROR
+    } else {
+        return None;
+    };
+
+    let mut reply = reply_header(query, &question, rcode, records.len() as u16, true);
+    for (rtype, rdata) in records {
+        // Every answer owns the question name: a pointer to offset 12.
+        reply.extend_from_slice(&[0xc0, 0x0c]);
+        reply.extend_from_slice(&rtype.to_be_bytes());
+        reply.extend_from_slice(&CLASS_IN.to_be_bytes());
+        reply.extend_from_slice(&RECORD_TTL_SECS.to_be_bytes());
+        reply.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        reply.extend_from_slice(&rdata);
+    }
+    Some(reply)
+}
+
+fn error_reply(query: &[u8], rcode: u8) -> Option<Vec<u8>> {
+    let question = parse_question(query)?;
+    Some(reply_header(query, &question, rcode, 0, false))
+}
+
+/// Header plus the echoed question. Additional records (EDNS) in the query
+/// are dropped, which leaves the client on plain 512-byte DNS.
+fn reply_header(
+    query: &[u8],
+    question: &Question,
+    rcode: u8,
+    answers: u16,
+    authoritative: bool,
+) -> Vec<u8> {
+    let mut reply = Vec::with_capacity(question.end + 16 * answers as usize);
+    reply.extend_from_slice(&query[..2]);
+    let mut flags = 0x80 | (query[2] & 0x01);
+    if authoritative {
+        flags |= 0x04;
+    }
+    reply.push(flags);
+    reply.push(0x80 | rcode);
+    reply.extend_from_slice(&1u16.to_be_bytes());
+    reply.extend_from_slice(&answers.to_be_bytes());
+    reply.extend_from_slice(&[0, 0, 0, 0]);
+    reply.extend_from_slice(&query[12..question.end]);
+    reply
+}
+
+fn encode_name(name: &str) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(name.len() + 2);
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        encoded.push(label.len() as u8);
+        encoded.extend_from_slice(label.as_bytes());
+    }
+    encoded.push(0);
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::node::DnsRecord;
+
+    fn zone() -> Zone {
+        Zone::from_request(&SyncNetworkDnsRequest {
+            bridge_name: "qbr0".into(),
+            zone: "app.internal".into(),
+            listen_ip: "10.0.0.1".into(),
+            listen_ip6: String::new(),
+            records: vec![DnsRecord {
+                name: "web-1".into(),
+                ip: "10.0.0.5".into(),
+                ip6: "fd00::5".into(),
+            }],
+        })
+        .unwrap()
+    }
+
+    fn query(name: &str, qtype: u16) -> Vec<u8> {
+        let mut query = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        query.extend(encode_name(name));
+        query.extend_from_slice(&qtype.to_be_bytes());
+        query.extend_from_slice(&CLASS_IN.to_be_bytes());
+        query
+    }
+
+    #[test]
+    fn answers_names_in_the_zone() {
+        let reply = answer(&query("WEB-1.app.internal", TYPE_A), &zone()).unwrap();
+        assert_eq!(&reply[..2], &[0x12, 0x34]);
+        assert_eq!(reply[3] & 0x0f, RCODE_NOERROR);
+        assert_eq!(u16::from_be_bytes([reply[6], reply[7]]), 1);
+        assert_eq!(&reply[reply.len() - 4..], &[10, 0, 0, 5]);
+
+        let reply = answer(&query("db.app.internal", TYPE_A), &zone()).unwrap();
+        assert_eq!(reply[3] & 0x0f, RCODE_NXDOMAIN);
+
+        let reply = answer(&query("web-1.app.internal", TYPE_AAAA), &zone()).unwrap();
+        assert_eq!(
+            &reply[reply.len() - 16..],
+            &"fd00::5".parse::<Ipv6Addr>().unwrap().octets()
+        );
+    }
+
+    #[test]
+    fn answers_ptr_and_forwards_the_rest() {
+        let reply = answer(&query("5.0.0.10.in-addr.arpa", TYPE_PTR), &zone()).unwrap();
+        assert!(reply.ends_with(&encode_name("web-1.app.internal")));
+
+        assert!(answer(&query("example.com", TYPE_A), &zone()).is_none());
+        assert!(answer(&query("9.0.0.10.in-addr.arpa", TYPE_PTR), &zone()).is_none());
+    }
+
+    #[test]
+    fn parse_nameservers_strips_zone_index() {
+        let resolv_conf =
+            "# generated\nnameserver 127.0.0.53\nnameserver fe80::1%eth0\noptions edns0\n";
+        assert_eq!(
+            parse_nameservers(resolv_conf),
+            vec![
+                "127.0.0.53".parse::<IpAddr>().unwrap(),
+                "fe80::1".parse::<IpAddr>().unwrap()
+            ]
+        );
+    }
+}
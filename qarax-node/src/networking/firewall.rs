@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
+use std::sync::OnceLock;
+
+use anyhow::Result;
+
+use super::floating_ip::{FloatingIpNat, PortForwardNat};
+use super::port_security::TapPortSecurity;
+use super::{firewall_log, iptables, nftables};
+use crate::rpc::node::{FirewallDirection, FirewallRuleCounter, VmFirewallInterface};
+
+/// Comment carried by every rule expanded from a security-group rule, so
+/// counters can be read back per rule.
+const RULE_COMMENT_PREFIX: &str = "qarax:";
+/// Comment on the default drop at the end of a VM's chains.
+pub(super) const DEFAULT_DROP_COMMENT: &str = "qarax:default-drop";
+
+/// Tooling used to program NAT, network isolation and security groups.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum FirewallBackend {
+    /// One `iptables`/`ip6tables` invocation per rule.
+    #[default]
+    Iptables,
+    /// Atomic `nft -f` transactions against an `inet qarax` table.
+    Nftables,
+}
+
+static BACKEND: OnceLock<FirewallBackend> = OnceLock::new();
+
+/// Select the backend for the lifetime of the process. Called once at
+/// startup; rules written by the other backend are left in place.
+pub async fn init(backend: FirewallBackend) -> Result<()> {
+    if backend == FirewallBackend::Nftables {
+        nftables::check_available().await?;
+    }
+    if BACKEND.set(backend).is_err() {
+        anyhow::bail!("Firewall backend already initialized");
+    }
+    Ok(())
+}
+
+fn backend() -> FirewallBackend {
+    BACKEND.get().copied().unwrap_or_default()
+}
+
+pub async fn setup_nat(bridge: &str, subnet: &str, subnet6: Option<&str>) -> Result<()> {
+    match backend() {
+        FirewallBackend::Iptables => iptables::setup_nat(bridge, subnet, subnet6).await,
+        FirewallBackend::Nftables => nftables::setup_nat(bridge, subnet, subnet6).await,
+    }
+}
+
+pub async fn teardown_nat(bridge: &str, subnet: &str, subnet6: Option<&str>) -> Result<()> {
+    match backend() {
+        FirewallBackend::Iptables => iptables::teardown_nat(bridge, subnet, subnet6).await,
+        FirewallBackend::Nftables => nftables::teardown_nat(bridge, subnet, subnet6).await,
+    }
+}
+
+pub async fn sync_network_isolation(
+    bridge: &str,
+    local_subnet: &str,
+    local_subnet6: Option<&str>,
+    blocked_subnets: &[String],
+    nat_exempt_subnets: &[String],
+) -> Result<()> {
+    match backend() {
+        FirewallBackend::Iptables => {
+            iptables::sync_network_isolation(
+                bridge,
+                local_subnet,
+                local_subnet6,
+                blocked_subnets,
+                nat_exempt_subnets,
+            )
+            .await
+        }
+        FirewallBackend::Nftables => {
+            nftables::sync_network_isolation(
+                bridge,
+                local_subnet,
+                local_subnet6,
+                blocked_subnets,
+                nat_exempt_subnets,
+            )
+            .await
+        }
+    }
+}
+
+pub async fn teardown_network_isolation(bridge: &str, dual_stack: bool) -> Result<()> {
+    match backend() {
+        FirewallBackend::Iptables => iptables::teardown_network_isolation(bridge, dual_stack).await,
+        FirewallBackend::Nftables => nftables::teardown_network_isolation(bridge).await,
+    }
+}
+
+pub async fn sync_vm_firewall(
+    vm_id: &str,
+    interfaces: &[VmFirewallInterface],
+    log_denied: bool,
+) -> Result<()> {
+    match backend() {
+        FirewallBackend::Iptables => {
+            iptables::sync_vm_firewall(vm_id, interfaces, log_denied).await
+        }
+        FirewallBackend::Nftables => {
+            nftables::sync_vm_firewall(vm_id, interfaces, log_denied).await
+        }
+    }
+}
+
+pub async fn teardown_vm_firewall(vm_id: &str) -> Result<()> {
+    firewall_log::forget(vm_id);
+    teardown_vm_port_security(vm_id).await?;
+    match backend() {
+        FirewallBackend::Iptables => iptables::teardown_vm_firewall(vm_id).await,
+        FirewallBackend::Nftables => nftables::teardown_vm_firewall(vm_id).await,
+    }
+}
+
+pub async fn sync_vm_port_security(vm_id: &str, ports: &[TapPortSecurity]) -> Result<()> {
+    match backend() {
+        FirewallBackend::Iptables => iptables::sync_vm_port_security(vm_id, ports).await,
+        FirewallBackend::Nftables => nftables::sync_vm_port_security(vm_id, ports).await,
+    }
+}
+
+pub async fn teardown_vm_port_security(vm_id: &str) -> Result<()> {
+    match backend() {
+        FirewallBackend::Iptables => iptables::teardown_vm_port_security(vm_id).await,
+        FirewallBackend::Nftables => nftables::teardown_vm_port_security(vm_id).await,
+    }
+}
+
+/// Per-rule counters of a VM's security groups, summed over address families
+/// and over the rules a peer-group rule expanded into.
+pub async fn vm_firewall_counters(vm_id: &str) -> Result<Vec<FirewallRuleCounter>> {
+    let raw = match backend() {
+        FirewallBackend::Iptables => iptables::vm_firewall_counters(vm_id).await?,
+        FirewallBackend::Nftables => nftables::vm_firewall_counters(vm_id).await?,
+    };
+    Ok(merge_counters(raw))
+}
+
+/// Comment tagging a rule with its security-group rule id, or `None` for
+/// rules sent without one.
+pub(super) fn rule_comment(rule_id: &str) -> Result<Option<String>> {
+    if rule_id.is_empty() {
+        return Ok(None);
+    }
+    if rule_id.len() > 64
+        || !rule_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        anyhow::bail!("Invalid firewall rule id {rule_id:?}");
+    }
+    Ok(Some(format!("{RULE_COMMENT_PREFIX}{rule_id}")))
+}
+
+/// Counter for a rule found with `comment`, skipping rules qarax did not tag.
+pub(super) fn counter_for_comment(
+    comment: &str,
+    direction: FirewallDirection,
+    packets: u64,
+    bytes: u64,
+) -> Option<FirewallRuleCounter> {
+    let rule_id = if comment == DEFAULT_DROP_COMMENT {
+        String::new()
+    } else {
+        comment.strip_prefix(RULE_COMMENT_PREFIX)?.to_string()
+    };
+    Some(FirewallRuleCounter {
+        rule_id,
+        direction: direction as i32,
+        packets,
+        bytes,
+    })
+}
+
+fn merge_counters(raw: Vec<FirewallRuleCounter>) -> Vec<FirewallRuleCounter> {
+    let mut merged: BTreeMap<(String, i32), (u64, u64)> = BTreeMap::new();
+    for counter in raw {
+        let entry = merged
+            .entry((counter.rule_id, counter.direction))
+            .or_default();
+        entry.0 += counter.packets;
+        entry.1 += counter.bytes;
+    }
+    merged
+        .into_iter()
+        .map(
+            |((rule_id, direction), (packets, bytes))| FirewallRuleCounter {
+                rule_id,
+                direction,
+                packets,
+                bytes,
+            },
+        )
+        .collect()
+}
+
+pub async fn sync_vm_nat(
+    vm_id: &str,
+    uplink: &str,
+    floating_ips: &[FloatingIpNat],
+    port_forwards: &[PortForwardNat],
+) -> Result<()> {
+    match backend() {
+        FirewallBackend::Iptables => {
+            iptables::sync_vm_nat(vm_id, uplink, floating_ips, port_forwards).await
+        }
+        FirewallBackend::Nftables => {
+            nftables::sync_vm_nat(vm_id, uplink, floating_ips, port_forwards).await
+        }
+    }
+}
+
+pub async fn teardown_vm_nat(vm_id: &str) -> Result<()> {
+    match backend() {
+        FirewallBackend::Iptables => iptables::teardown_vm_nat(vm_id).await,
+        FirewallBackend::Nftables => nftables::teardown_vm_nat(vm_id).await,
+    }
+}
+
+pub async fn vm_floating_ips(vm_id: &str) -> Result<Vec<Ipv4Addr>> {
+    match backend() {
+        FirewallBackend::Iptables => iptables::vm_floating_ips(vm_id).await,
+        FirewallBackend::Nftables => nftables::vm_floating_ips(vm_id).await,
+    }
+}
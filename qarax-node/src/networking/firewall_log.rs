@@ -0,0 +1,181 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::sync::{LazyLock, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tracing::{info, warn};
+
+use super::sanitize_suffix;
+use crate::rpc::node::{FirewallDeniedPacket, FirewallDirection};
+
+const KMSG: &str = "/dev/kmsg";
+/// Marks kernel log lines written by a VM's default-drop LOG rule.
+const LOG_PREFIX: &str = "qxd:";
+/// Denied packets kept per VM; older entries are dropped first.
+const MAX_ENTRIES_PER_VM: usize = 256;
+/// Rate limit applied to the LOG rule, per VM and direction, so a flood of
+/// denied traffic cannot fill the kernel log.
+pub(super) const LOG_RATE_PER_MINUTE: u32 = 10;
+pub(super) const LOG_BURST: u32 = 5;
+
+/// Recent denied packets, keyed by the VM tag in the log prefix.
+static DENIED: LazyLock<Mutex<HashMap<String, VecDeque<FirewallDeniedPacket>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Kernel log prefix for a VM's denied packets in one direction. iptables
+/// caps prefixes at 29 characters, so the VM id is shortened.
+pub(super) fn log_prefix(vm_id: &str, direction: FirewallDirection) -> String {
+    let direction = match direction {
+        FirewallDirection::Ingress => "in",
+        FirewallDirection::Egress => "out",
+    };
+    format!("{LOG_PREFIX}{}:{direction} ", vm_tag(vm_id))
+}
+
+fn vm_tag(vm_id: &str) -> String {
+    sanitize_suffix(vm_id, 16)
+}
+
+/// Tail the kernel log on a background thread and collect denied packets.
+/// Only entries logged after startup are read. Without `/dev/kmsg` (e.g. an
+/// unprivileged container) denied packets are still dropped, just not
+/// reported.
+pub fn start() {
+    let mut kmsg = match File::open(KMSG) {
+        Ok(file) => file,
+        Err(e) => {
+            warn!("Cannot read {KMSG}, denied-packet logs are unavailable: {e}");
+            return;
+        }
+    };
+    if let Err(e) = kmsg.seek(SeekFrom::End(0)) {
+        warn!("Failed to seek to the end of {KMSG}: {e}");
+    }
+
+    let spawned = std::thread::Builder::new()
+        .name("firewall-log".into())
+        .spawn(move || {
+            // /dev/kmsg hands out exactly one record per read.
+            let mut buf = vec![0u8; 8192];
+            loop {
+                match kmsg.read(&mut buf) {
+                    Ok(0) => return,
+                    Ok(n) => {
+                        let record = String::from_utf8_lossy(&buf[..n]);
+                        if let Some((tag, packet)) = parse_record(&record, now_ms()) {
+                            record_denied(tag, packet);
+                        }
+                    }
+                    // Records were overwritten before we read them.
+                    Err(e) if e.kind() == ErrorKind::BrokenPipe => continue,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                    Err(e) => {
+                        warn!("Stopped reading {KMSG}: {e}");
+                        return;
+                    }
+                }
+            }
+        });
+    match spawned {
+        Ok(_) => info!("Collecting denied-packet logs from {KMSG}"),
+        Err(e) => warn!("Failed to start the denied-packet log reader: {e}"),
+    }
+}
+
+/// Denied packets logged for a VM, oldest first.
+pub fn denied_packets(vm_id: &str) -> Vec<FirewallDeniedPacket> {
+    DENIED
+        .lock()
+        .unwrap()
+        .get(&vm_tag(vm_id))
+        .map(|entries| entries.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+pub fn forget(vm_id: &str) {
+    DENIED.lock().unwrap().remove(&vm_tag(vm_id));
+}
+
+fn record_denied(tag: String, packet: FirewallDeniedPacket) {
+    let mut denied = DENIED.lock().unwrap();
+    let entries = denied.entry(tag).or_default();
+    if entries.len() == MAX_ENTRIES_PER_VM {
+        entries.pop_front();
+    }
+    entries.push_back(packet);
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Parse one `/dev/kmsg` record (`prio,seq,usec,flags;message`) written by
+/// a qarax LOG rule into the VM tag and the packet it describes.
+fn parse_record(record: &str, timestamp_ms: i64) -> Option<(String, FirewallDeniedPacket)> {
+    let (_, message) = record.split_once(';')?;
+    let message = message.lines().next()?;
+    let rest = &message[message.find(LOG_PREFIX)? + LOG_PREFIX.len()..];
+    let (tag, rest) = rest.split_once(':')?;
+    let (direction, fields) = rest.split_once(' ')?;
+    let direction = match direction {
+        "in" => FirewallDirection::Ingress,
+        "out" => FirewallDirection::Egress,
+        _ => return None,
+    };
+
+    let field = |name: &str| {
+        fields
+            .split_whitespace()
+            .find_map(|token| token.strip_prefix(name)?.strip_prefix('='))
+    };
+    let packet = FirewallDeniedPacket {
+        timestamp_ms,
+        direction: direction as i32,
+        protocol: field("PROTO").unwrap_or_default().to_ascii_lowercase(),
+        source: field("SRC")?.to_string(),
+        destination: field("DST")?.to_string(),
+        source_port: field("SPT").and_then(|port| port.parse().ok()),
+        destination_port: field("DPT").and_then(|port| port.parse().ok()),
+    };
+    Some((tag.to_string(), packet))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_records_parse_into_denied_packets() {
+        let vm_id = "7f1c2a9e-0000-4000-8000-000000000001";
+        let prefix = log_prefix(vm_id, FirewallDirection::Ingress);
+        assert!(prefix.len() <= 29);
+
+        let record = format!(
+            "4,1520,93812345,-;{prefix}IN=qbr0 OUT=tap0 MAC=52:54:00:12:34:56 \
+             SRC=10.0.0.9 DST=10.0.0.5 LEN=60 TOS=0x00 TTL=64 PROTO=TCP SPT=40022 DPT=5432 \
+             WINDOW=64240 SYN URGP=0\n SUBSYSTEM=net\n"
+        );
+        let (tag, packet) = parse_record(&record, 1_700_000_000_000).unwrap();
+
+        assert_eq!(tag, vm_tag(vm_id));
+        assert_eq!(packet.direction, FirewallDirection::Ingress as i32);
+        assert_eq!(packet.protocol, "tcp");
+        assert_eq!(packet.source, "10.0.0.9");
+        assert_eq!(packet.destination, "10.0.0.5");
+        assert_eq!(packet.source_port, Some(40022));
+        assert_eq!(packet.destination_port, Some(5432));
+
+        let icmp6 = "4,1521,93812399,-;qxd:7f1c2a9e00004000:out IN=qbr0 OUT=eth0 \
+                     SRC=fd00::5 DST=2001:db8::1 LEN=104 PROTO=ICMPv6 TYPE=128 CODE=0";
+        let (_, packet) = parse_record(icmp6, 0).unwrap();
+        assert_eq!(packet.direction, FirewallDirection::Egress as i32);
+        assert_eq!(packet.protocol, "icmpv6");
+        assert_eq!(packet.destination_port, None);
+
+        assert!(parse_record("6,1522,93812400,-;eth0: link up", 0).is_none());
+    }
+}
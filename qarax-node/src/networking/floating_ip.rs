@@ -0,0 +1,172 @@
+use std::collections::HashSet;
+use std::net::Ipv4Addr;
+
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+
+use super::{bridge, firewall};
+use crate::rpc::node::{FirewallProtocol, SyncVmNatRequest};
+
+/// A floating IP held on the uplink and translated 1:1 to a VM address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FloatingIpNat {
+    pub floating_ip: Ipv4Addr,
+    pub vm_ip: Ipv4Addr,
+}
+
+/// A host port forwarded to a port on a VM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PortForwardNat {
+    /// `tcp` or `udp`.
+    pub protocol: &'static str,
+    pub host_port: u16,
+    pub vm_ip: Ipv4Addr,
+    pub vm_port: u16,
+}
+
+/// Bring a VM's floating IPs and port forwards on this host in line with
+/// the request. New addresses are claimed on the uplink before the rules that
+/// use them, and released ones are dropped only after the rules are gone.
+pub async fn sync_vm_nat(req: &SyncVmNatRequest) -> Result<()> {
+    let (floating_ips, port_forwards) = parse_request(req)?;
+    let previous = firewall::vm_floating_ips(&req.vm_id).await?;
+    if floating_ips.is_empty() && port_forwards.is_empty() && previous.is_empty() {
+        return firewall::teardown_vm_nat(&req.vm_id).await;
+    }
+
+    let uplink = bridge::default_route_interface().await?;
+    for nat in &floating_ips {
+        bridge::add_host_address(&uplink, nat.floating_ip).await?;
+    }
+
+    firewall::sync_vm_nat(&req.vm_id, &uplink, &floating_ips, &port_forwards).await?;
+
+    let desired: HashSet<Ipv4Addr> = floating_ips.iter().map(|nat| nat.floating_ip).collect();
+    for ip in previous.into_iter().filter(|ip| !desired.contains(ip)) {
+        bridge::del_host_address(&uplink, ip).await?;
+    }
+    for nat in &floating_ips {
+        announce(&uplink, nat.floating_ip).await;
+    }
+
+    info!(
+        "Synced NAT for VM {}: {} floating IP(s), {} port forward(s)",
+        req.vm_id,
+        floating_ips.len(),
+        port_forwards.len()
+    );
+    Ok(())
+}
+
+/// Drop a VM's NAT rules and release its floating IPs from the uplink.
+pub async fn teardown_vm_nat(vm_id: &str) -> Result<()> {
+    let previous = firewall::vm_floating_ips(vm_id).await?;
+    firewall::teardown_vm_nat(vm_id).await?;
+    if previous.is_empty() {
+        return Ok(());
+    }
+
+    let uplink = bridge::default_route_interface().await?;
+    for ip in previous {
+        bridge::del_host_address(&uplink, ip).await?;
+    }
+    Ok(())
+}
+
+fn parse_request(req: &SyncVmNatRequest) -> Result<(Vec<FloatingIpNat>, Vec<PortForwardNat>)> {
+    let floating_ips = req
+        .floating_ips
+        .iter()
+        .map(|mapping| {
+            Ok(FloatingIpNat {
+                floating_ip: parse_ipv4(&mapping.floating_ip)?,
+                vm_ip: parse_ipv4(&mapping.vm_ip)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let port_forwards = req
+        .port_forwards
+        .iter()
+        .map(|rule| {
+            let protocol = match FirewallProtocol::try_from(rule.protocol) {
+                Ok(FirewallProtocol::Tcp) => "tcp",
+                Ok(FirewallProtocol::Udp) => "udp",
+                _ => anyhow::bail!("Port forwards must be TCP or UDP"),
+            };
+            Ok(PortForwardNat {
+                protocol,
+                host_port: parse_port(rule.host_port)?,
+                vm_ip: parse_ipv4(&rule.vm_ip)?,
+                vm_port: parse_port(rule.vm_port)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok((floating_ips, port_forwards))
+}
+
+fn parse_ipv4(ip: &str) -> Result<Ipv4Addr> {
+    ip.parse()
+        .with_context(|| format!("Invalid IPv4 address: {ip:?}"))
+}
+
+fn parse_port(port: u32) -> Result<u16> {
+    match u16::try_from(port) {
+        Ok(port) if port != 0 => Ok(port),
+        _ => anyhow::bail!("Invalid port: {port}"),
+    }
+}
+
+/// Send a gratuitous ARP so the upstream network learns the floating IP's new
+/// location straight away instead of waiting for its ARP cache to expire.
+/// Best effort: without `arping` the address still moves, just more slowly.
+async fn announce(uplink: &str, ip: Ipv4Addr) {
+    let result = tokio::process::Command::new("arping")
+        .args(["-U", "-c", "1", "-I", uplink, &ip.to_string()])
+        .output()
+        .await;
+    match result {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => warn!(
+            "Gratuitous ARP for {ip} on {uplink} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => warn!("Gratuitous ARP for {ip} on {uplink} skipped: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::node::{FloatingIpMapping, PortForwardRule};
+
+    fn request(protocol: FirewallProtocol, host_port: u32) -> SyncVmNatRequest {
+        SyncVmNatRequest {
+            vm_id: "vm".into(),
+            floating_ips: vec![FloatingIpMapping {
+                floating_ip: "203.0.113.5".into(),
+                vm_ip: "10.0.0.5".into(),
+            }],
+            port_forwards: vec![PortForwardRule {
+                protocol: protocol as i32,
+                host_port,
+                vm_ip: "10.0.0.5".into(),
+                vm_port: 80,
+            }],
+        }
+    }
+
+    #[test]
+    fn parse_request_accepts_tcp_and_udp_only() {
+        let (floating_ips, port_forwards) =
+            parse_request(&request(FirewallProtocol::Udp, 5353)).unwrap();
+        assert_eq!(floating_ips[0].floating_ip, Ipv4Addr::new(203, 0, 113, 5));
+        assert_eq!(port_forwards[0].protocol, "udp");
+        assert_eq!(port_forwards[0].host_port, 5353);
+
+        assert!(parse_request(&request(FirewallProtocol::Icmp, 5353)).is_err());
+        assert!(parse_request(&request(FirewallProtocol::Tcp, 0)).is_err());
+        assert!(parse_request(&request(FirewallProtocol::Tcp, 70000)).is_err());
+    }
+}
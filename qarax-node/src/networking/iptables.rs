@@ -1,11 +1,70 @@
 use std::collections::HashSet;
+use std::net::Ipv4Addr;
 
 use anyhow::{Context, Result};
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::rpc::node::{FirewallDirection, FirewallProtocol, VmFirewallInterface};
+use super::firewall::{DEFAULT_DROP_COMMENT, counter_for_comment, rule_comment};
+use super::firewall_log::{self, LOG_BURST, LOG_RATE_PER_MINUTE};
+use super::floating_ip::{FloatingIpNat, PortForwardNat};
+use super::port_security::TapPortSecurity;
+use super::sanitize_suffix;
+use crate::rpc::node::{
+    FirewallDirection, FirewallProtocol, FirewallRuleCounter, VmFirewallInterface, VmFirewallRule,
+};
 
 const FORWARD_CHAIN: &str = "FORWARD";
+/// Anti-spoofing lives in the ebtables `nat` table, whose PREROUTING chain
+/// sees every frame a TAP hands to the bridge.
+const EBTABLES_PREROUTING: &str = "PREROUTING";
+
+/// ICMPv6 types a dual-stack guest cannot live without: router discovery and
+/// neighbour discovery.
+const ICMPV6_DISCOVERY_TYPES: [&str; 4] = [
+    "router-solicitation",
+    "router-advertisement",
+    "neighbour-solicitation",
+    "neighbour-advertisement",
+];
+
+/// Address family a rule set is written for. IPv4 goes through `iptables`,
+/// IPv6 through `ip6tables`; chain names are the same in both.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum IpFamily {
+    V4,
+    V6,
+}
+
+impl IpFamily {
+    fn of_cidr(cidr: &str) -> Result<Self> {
+        if super::validate_ipv4_cidr(cidr).is_ok() {
+            return Ok(Self::V4);
+        }
+        super::validate_ipv6_cidr(cidr)?;
+        Ok(Self::V6)
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            Self::V4 => "iptables",
+            Self::V6 => "ip6tables",
+        }
+    }
+
+    fn host_prefix(self) -> u8 {
+        match self {
+            Self::V4 => 32,
+            Self::V6 => 128,
+        }
+    }
+
+    fn icmp_protocol(self) -> &'static str {
+        match self {
+            Self::V4 => "icmp",
+            Self::V6 => "ipv6-icmp",
+        }
+    }
+}
 
 struct VmFirewallChains {
     out_parent: String,
@@ -30,60 +89,107 @@ impl VmFirewallChains {
     }
 }
 
-/// Set up NAT masquerade and forwarding rules for a bridge subnet.
-pub async fn setup_nat(bridge: &str, subnet: &str) -> Result<()> {
+/// `nat` table chains holding a VM's floating IP and port forward rules.
+struct VmNatChains {
+    dnat: String,
+    snat: String,
+}
+
+impl VmNatChains {
+    fn new(vm_id: &str) -> Self {
+        let suffix = sanitize_suffix(vm_id, 21);
+        Self {
+            dnat: format!("QXVDN{suffix}"),
+            snat: format!("QXVSN{suffix}"),
+        }
+    }
+}
+
+/// ebtables `nat` chains holding a VM's anti-spoofing rules. PREROUTING
+/// jumps to the parent for each of the VM's TAPs, and the parent to whichever
+/// child is live, so a resync swaps in a complete rule set.
+struct VmPortSecurityChains {
+    parent: String,
+    a: String,
+    b: String,
+}
+
+impl VmPortSecurityChains {
+    fn new(vm_id: &str) -> Self {
+        let suffix = sanitize_suffix(vm_id, 21);
+        Self {
+            parent: format!("QXPS{suffix}"),
+            a: format!("QXPA{suffix}"),
+            b: format!("QXPB{suffix}"),
+        }
+    }
+}
+
+/// One VM interface as seen by a single address family.
+struct FamilyInterface<'a> {
+    bridge_name: &'a str,
+    ip: &'a str,
+    rules: Vec<&'a VmFirewallRule>,
+    restrict_egress: bool,
+}
+
+/// Set up NAT masquerade and forwarding rules for a bridge subnet, and for
+/// its IPv6 prefix on a dual-stack network.
+pub async fn setup_nat(bridge: &str, subnet: &str, subnet6: Option<&str>) -> Result<()> {
     super::validate_iface_name(bridge)?;
     super::validate_ipv4_cidr(subnet)?;
+    if let Some(subnet6) = subnet6 {
+        super::validate_ipv6_cidr(subnet6)?;
+    }
     info!("Setting up NAT for bridge {} subnet {}", bridge, subnet);
 
     // Enable IP forwarding via /proc (sysctl binary may not be present)
     tokio::fs::write("/proc/sys/net/ipv4/ip_forward", b"1")
         .await
         .context("Failed to enable ip_forward")?;
+    apply_nat_rules(IpFamily::V4, "-A", bridge, subnet).await;
 
-    let _ = run_cmd(
-        "iptables",
-        &[
-            "-t",
-            "nat",
-            "-A",
-            "POSTROUTING",
-            "-s",
-            subnet,
-            "!",
-            "-o",
-            bridge,
-            "-j",
-            "MASQUERADE",
-        ],
-    )
-    .await;
-    let _ = run_cmd(
-        "iptables",
-        &["-A", FORWARD_CHAIN, "-i", bridge, "-j", "ACCEPT"],
-    )
-    .await;
-    let _ = run_cmd(
-        "iptables",
-        &["-A", FORWARD_CHAIN, "-o", bridge, "-j", "ACCEPT"],
-    )
-    .await;
+    if let Some(subnet6) = subnet6 {
+        info!("Setting up NAT for bridge {} prefix {}", bridge, subnet6);
+        tokio::fs::write("/proc/sys/net/ipv6/conf/all/forwarding", b"1")
+            .await
+            .context("Failed to enable IPv6 forwarding")?;
+        apply_nat_rules(IpFamily::V6, "-A", bridge, subnet6).await;
+    }
 
     Ok(())
 }
 
 /// Remove NAT and forwarding rules for a bridge subnet.
-pub async fn teardown_nat(bridge: &str, subnet: &str) -> Result<()> {
+pub async fn teardown_nat(bridge: &str, subnet: &str, subnet6: Option<&str>) -> Result<()> {
     super::validate_iface_name(bridge)?;
     super::validate_ipv4_cidr(subnet)?;
+    if let Some(subnet6) = subnet6 {
+        super::validate_ipv6_cidr(subnet6)?;
+    }
     info!("Tearing down NAT for bridge {} subnet {}", bridge, subnet);
 
+    apply_nat_rules(IpFamily::V4, "-D", bridge, subnet).await;
+    let _ = remove_nat_exemption_rules(IpFamily::V4, bridge, subnet).await;
+
+    if let Some(subnet6) = subnet6 {
+        apply_nat_rules(IpFamily::V6, "-D", bridge, subnet6).await;
+        let _ = remove_nat_exemption_rules(IpFamily::V6, bridge, subnet6).await;
+    }
+
+    Ok(())
+}
+
+/// Append (`-A`) or delete (`-D`) the masquerade and forward-accept rules of
+/// a bridge. Failures are ignored so setup and teardown stay idempotent.
+async fn apply_nat_rules(family: IpFamily, op: &str, bridge: &str, subnet: &str) {
+    let program = family.program();
     let _ = run_cmd(
-        "iptables",
+        program,
         &[
             "-t",
             "nat",
-            "-D",
+            op,
             "POSTROUTING",
             "-s",
             subnet,
@@ -95,137 +201,244 @@ pub async fn teardown_nat(bridge: &str, subnet: &str) -> Result<()> {
         ],
     )
     .await;
-    let _ = run_cmd(
-        "iptables",
-        &["-D", FORWARD_CHAIN, "-i", bridge, "-j", "ACCEPT"],
-    )
-    .await;
-    let _ = run_cmd(
-        "iptables",
-        &["-D", FORWARD_CHAIN, "-o", bridge, "-j", "ACCEPT"],
-    )
-    .await;
-    let _ = remove_nat_exemption_rules(bridge, subnet).await;
-
-    Ok(())
+    let _ = run_cmd(program, &[op, FORWARD_CHAIN, "-i", bridge, "-j", "ACCEPT"]).await;
+    let _ = run_cmd(program, &[op, FORWARD_CHAIN, "-o", bridge, "-j", "ACCEPT"]).await;
 }
 
 pub async fn sync_network_isolation(
     bridge: &str,
     local_subnet: &str,
+    local_subnet6: Option<&str>,
     blocked_subnets: &[String],
     nat_exempt_subnets: &[String],
 ) -> Result<()> {
     super::validate_iface_name(bridge)?;
     super::validate_ipv4_cidr(local_subnet)?;
-    for subnet in blocked_subnets {
-        super::validate_ipv4_cidr(subnet)?;
+    if let Some(local_subnet6) = local_subnet6 {
+        super::validate_ipv6_cidr(local_subnet6)?;
     }
-    for subnet in nat_exempt_subnets {
-        super::validate_ipv4_cidr(subnet)?;
+    let blocked = split_by_family(blocked_subnets)?;
+    let nat_exempt = split_by_family(nat_exempt_subnets)?;
+
+    sync_network_isolation_family(
+        IpFamily::V4,
+        bridge,
+        local_subnet,
+        &blocked.0,
+        &nat_exempt.0,
+    )
+    .await?;
+    // A bridge without an IPv6 prefix has no routable IPv6 guests to isolate.
+    if let Some(local_subnet6) = local_subnet6 {
+        sync_network_isolation_family(
+            IpFamily::V6,
+            bridge,
+            local_subnet6,
+            &blocked.1,
+            &nat_exempt.1,
+        )
+        .await?;
     }
+    Ok(())
+}
 
+/// Split subnets into (IPv4, IPv6), rejecting anything that is neither.
+fn split_by_family(subnets: &[String]) -> Result<(Vec<String>, Vec<String>)> {
+    let mut v4 = Vec::new();
+    let mut v6 = Vec::new();
+    for subnet in subnets {
+        match IpFamily::of_cidr(subnet)? {
+            IpFamily::V4 => v4.push(subnet.clone()),
+            IpFamily::V6 => v6.push(subnet.clone()),
+        }
+    }
+    Ok((v4, v6))
+}
+
+async fn sync_network_isolation_family(
+    family: IpFamily,
+    bridge: &str,
+    local_subnet: &str,
+    blocked_subnets: &[String],
+    nat_exempt_subnets: &[String],
+) -> Result<()> {
+    let program = family.program();
     let out_chain = format!("QXNETO{}", sanitize_suffix(bridge, 20));
     let in_chain = format!("QXNETI{}", sanitize_suffix(bridge, 20));
 
-    ensure_chain(&out_chain).await?;
-    ensure_chain(&in_chain).await?;
-    ensure_jump_rule(FORWARD_CHAIN, &["-i", bridge, "-j", &out_chain]).await?;
-    ensure_jump_rule(FORWARD_CHAIN, &["-o", bridge, "-j", &in_chain]).await?;
+    ensure_chain(family, &out_chain).await?;
+    ensure_chain(family, &in_chain).await?;
+    ensure_jump_rule(family, FORWARD_CHAIN, &["-i", bridge, "-j", &out_chain]).await?;
+    ensure_jump_rule(family, FORWARD_CHAIN, &["-o", bridge, "-j", &in_chain]).await?;
 
-    flush_chain(&out_chain).await?;
-    flush_chain(&in_chain).await?;
+    flush_chain(family, &out_chain).await?;
+    flush_chain(family, &in_chain).await?;
 
     for subnet in blocked_subnets {
-        run_cmd("iptables", &["-A", &out_chain, "-d", subnet, "-j", "DROP"]).await?;
-        run_cmd("iptables", &["-A", &in_chain, "-s", subnet, "-j", "DROP"]).await?;
+        run_cmd(program, &["-A", &out_chain, "-d", subnet, "-j", "DROP"]).await?;
+        run_cmd(program, &["-A", &in_chain, "-s", subnet, "-j", "DROP"]).await?;
     }
 
-    run_cmd("iptables", &["-A", &out_chain, "-j", "RETURN"]).await?;
-    run_cmd("iptables", &["-A", &in_chain, "-j", "RETURN"]).await?;
-    sync_nat_exemption_rules(bridge, local_subnet, nat_exempt_subnets).await?;
+    run_cmd(program, &["-A", &out_chain, "-j", "RETURN"]).await?;
+    run_cmd(program, &["-A", &in_chain, "-j", "RETURN"]).await?;
+    sync_nat_exemption_rules(family, bridge, local_subnet, nat_exempt_subnets).await?;
     Ok(())
 }
 
-pub async fn teardown_network_isolation(bridge: &str) -> Result<()> {
+pub async fn teardown_network_isolation(bridge: &str, dual_stack: bool) -> Result<()> {
     super::validate_iface_name(bridge)?;
+    teardown_network_isolation_family(IpFamily::V4, bridge).await?;
+    if dual_stack {
+        teardown_network_isolation_family(IpFamily::V6, bridge).await?;
+    }
+    Ok(())
+}
+
+async fn teardown_network_isolation_family(family: IpFamily, bridge: &str) -> Result<()> {
     let out_chain = format!("QXNETO{}", sanitize_suffix(bridge, 20));
     let in_chain = format!("QXNETI{}", sanitize_suffix(bridge, 20));
-    remove_forward_rules_referencing(&out_chain).await?;
-    remove_forward_rules_referencing(&in_chain).await?;
-    delete_chain_if_exists(&out_chain).await?;
-    delete_chain_if_exists(&in_chain).await?;
+    remove_forward_rules_referencing(family, &out_chain).await?;
+    remove_forward_rules_referencing(family, &in_chain).await?;
+    delete_chain_if_exists(family, &out_chain).await?;
+    delete_chain_if_exists(family, &in_chain).await?;
     Ok(())
 }
 
-pub async fn sync_vm_firewall(vm_id: &str, interfaces: &[VmFirewallInterface]) -> Result<()> {
-    if interfaces.is_empty() {
-        teardown_vm_firewall(vm_id).await?;
-        return Ok(());
+pub async fn sync_vm_firewall(
+    vm_id: &str,
+    interfaces: &[VmFirewallInterface],
+    log_denied: bool,
+) -> Result<()> {
+    let mut v4_interfaces = Vec::with_capacity(interfaces.len());
+    let mut v6_interfaces = Vec::new();
+    for iface in interfaces {
+        super::validate_iface_name(&iface.bridge_name)?;
+        super::validate_ipv4_address(&iface.ip)?;
+
+        let mut v4_rules = Vec::new();
+        let mut v6_rules = Vec::new();
+        for rule in &iface.rules {
+            if rule.cidr.is_empty() {
+                v4_rules.push(rule);
+                v6_rules.push(rule);
+                continue;
+            }
+            match IpFamily::of_cidr(&rule.cidr)? {
+                IpFamily::V4 => v4_rules.push(rule),
+                IpFamily::V6 => v6_rules.push(rule),
+            }
+        }
+
+        v4_interfaces.push(FamilyInterface {
+            bridge_name: &iface.bridge_name,
+            ip: &iface.ip,
+            rules: v4_rules,
+            restrict_egress: iface.restrict_egress,
+        });
+        if !iface.ip6.is_empty() {
+            super::validate_ipv6_address(&iface.ip6)?;
+            v6_interfaces.push(FamilyInterface {
+                bridge_name: &iface.bridge_name,
+                ip: &iface.ip6,
+                rules: v6_rules,
+                restrict_egress: iface.restrict_egress,
+            });
+        }
     }
 
-    let chains = VmFirewallChains::new(vm_id);
-    ensure_chain(&chains.out_parent).await?;
-    ensure_chain(&chains.in_parent).await?;
+    if v4_interfaces.is_empty() {
+        teardown_vm_firewall_family(IpFamily::V4, vm_id).await?;
+    } else {
+        sync_vm_firewall_family(IpFamily::V4, vm_id, &v4_interfaces, log_denied).await?;
+    }
 
-    let next_out_chain = next_child_chain(&chains.out_parent, &chains.out_a, &chains.out_b).await?;
-    let next_in_chain = next_child_chain(&chains.in_parent, &chains.in_a, &chains.in_b).await?;
+    if v6_interfaces.is_empty() {
+        // IPv4-only hosts may not ship ip6tables at all.
+        if let Err(e) = teardown_vm_firewall_family(IpFamily::V6, vm_id).await {
+            warn!("Skipping IPv6 firewall cleanup for VM {vm_id}: {e}");
+        }
+    } else {
+        sync_vm_firewall_family(IpFamily::V6, vm_id, &v6_interfaces, log_denied).await?;
+    }
 
-    ensure_chain(&next_out_chain).await?;
-    ensure_chain(&next_in_chain).await?;
-    flush_chain(&next_out_chain).await?;
-    flush_chain(&next_in_chain).await?;
+    Ok(())
+}
 
-    run_cmd(
-        "iptables",
-        &[
-            "-A",
-            &next_in_chain,
-            "-m",
-            "conntrack",
-            "--ctstate",
-            "ESTABLISHED,RELATED",
-            "-j",
-            "ACCEPT",
-        ],
-    )
-    .await?;
-    run_cmd(
-        "iptables",
-        &[
-            "-A",
-            &next_out_chain,
-            "-m",
-            "conntrack",
-            "--ctstate",
-            "ESTABLISHED,RELATED",
-            "-j",
-            "ACCEPT",
-        ],
-    )
-    .await?;
+async fn sync_vm_firewall_family(
+    family: IpFamily,
+    vm_id: &str,
+    interfaces: &[FamilyInterface<'_>],
+    log_denied: bool,
+) -> Result<()> {
+    let program = family.program();
+    let chains = VmFirewallChains::new(vm_id);
+    ensure_chain(family, &chains.out_parent).await?;
+    ensure_chain(family, &chains.in_parent).await?;
+
+    let next_out_chain =
+        next_child_chain(family, &chains.out_parent, &chains.out_a, &chains.out_b).await?;
+    let next_in_chain =
+        next_child_chain(family, &chains.in_parent, &chains.in_a, &chains.in_b).await?;
+
+    ensure_chain(family, &next_out_chain).await?;
+    ensure_chain(family, &next_in_chain).await?;
+    flush_chain(family, &next_out_chain).await?;
+    flush_chain(family, &next_in_chain).await?;
+
+    for chain in [&next_in_chain, &next_out_chain] {
+        run_cmd(
+            program,
+            &[
+                "-A",
+                chain,
+                "-m",
+                "conntrack",
+                "--ctstate",
+                "ESTABLISHED,RELATED",
+                "-j",
+                "ACCEPT",
+            ],
+        )
+        .await?;
+        if family == IpFamily::V6 {
+            for icmp_type in ICMPV6_DISCOVERY_TYPES {
+                run_cmd(
+                    program,
+                    &[
+                        "-A",
+                        chain,
+                        "-p",
+                        "ipv6-icmp",
+                        "--icmpv6-type",
+                        icmp_type,
+                        "-j",
+                        "ACCEPT",
+                    ],
+                )
+                .await?;
+            }
+        }
+    }
 
-    let mut has_egress_rules = false;
+    let mut has_egress_rules = interfaces.iter().any(|iface| iface.restrict_egress);
     let mut desired_in_jump_rules = Vec::with_capacity(interfaces.len());
     let mut desired_out_jump_rules = Vec::with_capacity(interfaces.len());
 
     for iface in interfaces {
-        super::validate_iface_name(&iface.bridge_name)?;
-        super::validate_ipv4_address(&iface.ip)?;
-
+        let host_cidr = format!("{}/{}", iface.ip, family.host_prefix());
         desired_in_jump_rules.push(vec![
             "-o".to_string(),
-            iface.bridge_name.clone(),
+            iface.bridge_name.to_string(),
             "-d".to_string(),
-            format!("{}/32", iface.ip),
+            host_cidr.clone(),
             "-j".to_string(),
             chains.in_parent.clone(),
         ]);
         desired_out_jump_rules.push(vec![
             "-i".to_string(),
-            iface.bridge_name.clone(),
+            iface.bridge_name.to_string(),
             "-s".to_string(),
-            format!("{}/32", iface.ip),
+            host_cidr,
             "-j".to_string(),
             chains.out_parent.clone(),
         ]);
@@ -236,9 +449,6 @@ pub async fn sync_vm_firewall(vm_id: &str, interfaces: &[VmFirewallInterface]) -
             let protocol =
                 FirewallProtocol::try_from(rule.protocol).unwrap_or(FirewallProtocol::Any);
             let cidr = (!rule.cidr.is_empty()).then_some(rule.cidr.as_str());
-            if let Some(cidr) = cidr {
-                super::validate_ipv4_cidr(cidr)?;
-            }
 
             let (chain, cidr_flag, ports_allowed) = match direction {
                 FirewallDirection::Ingress => (&next_in_chain, "-s", true),
@@ -266,7 +476,7 @@ pub async fn sync_vm_firewall(vm_id: &str, interfaces: &[VmFirewallInterface]) -
                 }
                 FirewallProtocol::Icmp => {
                     args.push("-p".to_string());
-                    args.push("icmp".to_string());
+                    args.push(family.icmp_protocol().to_string());
                 }
             }
 
@@ -283,25 +493,41 @@ pub async fn sync_vm_firewall(vm_id: &str, interfaces: &[VmFirewallInterface]) -
                 args.push(port_range);
             }
 
+            if let Some(comment) = rule_comment(&rule.rule_id)? {
+                args.extend(["-m", "comment", "--comment"].map(String::from));
+                args.push(comment);
+            }
+
             args.push("-j".to_string());
             args.push("ACCEPT".to_string());
             let refs: Vec<&str> = args.iter().map(String::as_str).collect();
-            run_cmd("iptables", &refs).await?;
+            run_cmd(program, &refs).await?;
         }
     }
 
-    run_cmd("iptables", &["-A", &next_in_chain, "-j", "DROP"]).await?;
+    let log_prefix = |direction| log_denied.then(|| firewall_log::log_prefix(vm_id, direction));
+    append_default_drop(
+        program,
+        &next_in_chain,
+        log_prefix(FirewallDirection::Ingress),
+    )
+    .await?;
 
     if has_egress_rules {
-        run_cmd("iptables", &["-A", &next_out_chain, "-j", "DROP"]).await?;
+        append_default_drop(
+            program,
+            &next_out_chain,
+            log_prefix(FirewallDirection::Egress),
+        )
+        .await?;
     } else {
-        run_cmd("iptables", &["-A", &next_out_chain, "-j", "ACCEPT"]).await?;
+        run_cmd(program, &["-A", &next_out_chain, "-j", "ACCEPT"]).await?;
     }
 
-    point_parent_to_child(&chains.in_parent, &next_in_chain).await?;
-    point_parent_to_child(&chains.out_parent, &next_out_chain).await?;
-    sync_forward_jump_rules(&chains.in_parent, &desired_in_jump_rules).await?;
-    sync_forward_jump_rules(&chains.out_parent, &desired_out_jump_rules).await?;
+    point_parent_to_child(family, &chains.in_parent, &next_in_chain).await?;
+    point_parent_to_child(family, &chains.out_parent, &next_out_chain).await?;
+    sync_forward_jump_rules(family, &chains.in_parent, &desired_in_jump_rules).await?;
+    sync_forward_jump_rules(family, &chains.out_parent, &desired_out_jump_rules).await?;
 
     let stale_in_chain = if next_in_chain == chains.in_a {
         chains.in_b.as_str()
@@ -313,59 +539,535 @@ pub async fn sync_vm_firewall(vm_id: &str, interfaces: &[VmFirewallInterface]) -
     } else {
         chains.out_a.as_str()
     };
-    delete_chain_if_exists(stale_in_chain).await?;
-    delete_chain_if_exists(stale_out_chain).await?;
+    delete_chain_if_exists(family, stale_in_chain).await?;
+    delete_chain_if_exists(family, stale_out_chain).await?;
 
     Ok(())
 }
 
+/// Close a VM chain with its tagged default drop, preceded by a rate-limited
+/// LOG rule when denied packets are being logged.
+async fn append_default_drop(program: &str, chain: &str, log_prefix: Option<String>) -> Result<()> {
+    if let Some(log_prefix) = log_prefix {
+        let rate = format!("{LOG_RATE_PER_MINUTE}/minute");
+        let burst = LOG_BURST.to_string();
+        run_cmd(
+            program,
+            &[
+                "-A",
+                chain,
+                "-m",
+                "limit",
+                "--limit",
+                &rate,
+                "--limit-burst",
+                &burst,
+                "-j",
+                "LOG",
+                "--log-prefix",
+                &log_prefix,
+            ],
+        )
+        .await?;
+    }
+    run_cmd(
+        program,
+        &[
+            "-A",
+            chain,
+            "-m",
+            "comment",
+            "--comment",
+            DEFAULT_DROP_COMMENT,
+            "-j",
+            "DROP",
+        ],
+    )
+    .await
+}
+
+/// Counters of the tagged rules in a VM's active chains, both families.
+pub async fn vm_firewall_counters(vm_id: &str) -> Result<Vec<FirewallRuleCounter>> {
+    let chains = VmFirewallChains::new(vm_id);
+    let mut counters = Vec::new();
+    for family in [IpFamily::V4, IpFamily::V6] {
+        for (parent, child_a, child_b, direction) in [
+            (
+                &chains.in_parent,
+                &chains.in_a,
+                &chains.in_b,
+                FirewallDirection::Ingress,
+            ),
+            (
+                &chains.out_parent,
+                &chains.out_a,
+                &chains.out_b,
+                FirewallDirection::Egress,
+            ),
+        ] {
+            // No chain for a family the VM has no address in, and no
+            // ip6tables at all on some hosts: nothing to count either way.
+            let Ok(parent_rules) = run_cmd_capture(family.program(), &["-S", parent]).await else {
+                continue;
+            };
+            let Some(child) = active_child_chain(&parent_rules, parent, child_a, child_b) else {
+                continue;
+            };
+            let listing = run_cmd_capture(family.program(), &["-S", child, "-v"]).await?;
+            counters.extend(rule_counters(&listing, direction));
+        }
+    }
+    Ok(counters)
+}
+
+/// The A/B child chain a VM parent chain currently jumps to.
+fn active_child_chain<'a>(
+    parent_rules: &str,
+    parent: &str,
+    child_a: &'a str,
+    child_b: &'a str,
+) -> Option<&'a str> {
+    let jump = format!("-A {parent} -j ");
+    parent_rules.lines().find_map(|line| {
+        let target = line.strip_prefix(&jump)?.trim();
+        [child_a, child_b]
+            .into_iter()
+            .find(|child| *child == target)
+    })
+}
+
+/// Counters of the qarax-tagged rules in an `iptables -S <chain> -v`
+/// listing, which prints them as `-c <packets> <bytes>`.
+fn rule_counters(listing: &str, direction: FirewallDirection) -> Vec<FirewallRuleCounter> {
+    listing
+        .lines()
+        .filter(|line| line.starts_with("-A "))
+        .filter_map(|line| {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let after = |flag: &str, offset: usize| {
+                let index = tokens.iter().position(|token| *token == flag)?;
+                tokens.get(index + offset).copied()
+            };
+            let comment = after("--comment", 1)?.trim_matches('"');
+            let packets = after("-c", 1)?.parse().ok()?;
+            let bytes = after("-c", 2)?.parse().ok()?;
+            counter_for_comment(comment, direction, packets, bytes)
+        })
+        .collect()
+}
+
 pub async fn teardown_vm_firewall(vm_id: &str) -> Result<()> {
+    teardown_vm_firewall_family(IpFamily::V4, vm_id).await?;
+    if let Err(e) = teardown_vm_firewall_family(IpFamily::V6, vm_id).await {
+        warn!("Skipping IPv6 firewall cleanup for VM {vm_id}: {e}");
+    }
+    Ok(())
+}
+
+async fn teardown_vm_firewall_family(family: IpFamily, vm_id: &str) -> Result<()> {
     let chains = VmFirewallChains::new(vm_id);
-    remove_forward_rules_referencing(&chains.out_parent).await?;
-    remove_forward_rules_referencing(&chains.in_parent).await?;
-    delete_chain_if_exists(&chains.out_parent).await?;
-    delete_chain_if_exists(&chains.in_parent).await?;
-    delete_chain_if_exists(&chains.out_a).await?;
-    delete_chain_if_exists(&chains.out_b).await?;
-    delete_chain_if_exists(&chains.in_a).await?;
-    delete_chain_if_exists(&chains.in_b).await?;
+    remove_forward_rules_referencing(family, &chains.out_parent).await?;
+    remove_forward_rules_referencing(family, &chains.in_parent).await?;
+    delete_chain_if_exists(family, &chains.out_parent).await?;
+    delete_chain_if_exists(family, &chains.in_parent).await?;
+    delete_chain_if_exists(family, &chains.out_a).await?;
+    delete_chain_if_exists(family, &chains.out_b).await?;
+    delete_chain_if_exists(family, &chains.in_a).await?;
+    delete_chain_if_exists(family, &chains.in_b).await?;
+    Ok(())
+}
+
+pub async fn sync_vm_port_security(vm_id: &str, ports: &[TapPortSecurity]) -> Result<()> {
+    let chains = VmPortSecurityChains::new(vm_id);
+    for chain in [&chains.parent, &chains.a, &chains.b] {
+        if !ebtables_chain_exists(chain).await {
+            ebtables(&["-N", chain]).await?;
+        }
+    }
+
+    let live = ebtables_rules(&chains.parent).await?;
+    let (child, stale) = if live.contains(&format!("-j {}", chains.a)) {
+        (&chains.b, &chains.a)
+    } else {
+        (&chains.a, &chains.b)
+    };
+    ebtables(&["-F", child]).await?;
+    for port in ports {
+        for rule in port_security_rules(port)? {
+            let mut args = vec!["-A", child.as_str()];
+            args.extend(rule.iter().map(String::as_str));
+            ebtables(&args).await?;
+        }
+    }
+
+    ebtables(&["-I", &chains.parent, "1", "-j", child]).await?;
+    while ebtables(&["-D", &chains.parent, "2"]).await.is_ok() {}
+    let taps: Vec<&str> = ports.iter().map(|port| port.tap.as_str()).collect();
+    sync_prerouting_jumps(&chains.parent, &taps).await?;
+    ebtables(&["-F", stale]).await
+}
+
+/// Remove a VM's anti-spoofing chains. Hosts without ebtables never had any.
+pub async fn teardown_vm_port_security(vm_id: &str) -> Result<()> {
+    let chains = VmPortSecurityChains::new(vm_id);
+    if !ebtables_chain_exists(&chains.parent).await {
+        return Ok(());
+    }
+    sync_prerouting_jumps(&chains.parent, &[]).await?;
+    for chain in [&chains.parent, &chains.a, &chains.b] {
+        let _ = ebtables(&["-F", chain]).await;
+    }
+    for chain in [&chains.parent, &chains.a, &chains.b] {
+        let _ = ebtables(&["-X", chain]).await;
+    }
+    Ok(())
+}
+
+/// ebtables rules, without the `-A <chain>`, accepting a TAP's frames only
+/// from a bound MAC and address. ebtables cannot match the target of a
+/// neighbor advertisement, so unlike nftables it leaves those unchecked.
+fn port_security_rules(port: &TapPortSecurity) -> Result<Vec<Vec<String>>> {
+    let tap = port.tap.as_str();
+    let mac = port.mac.as_str();
+    let mut rules: Vec<Vec<&str>> = vec![
+        vec![
+            "-p",
+            "IPv6",
+            "--ip6-proto",
+            "ipv6-icmp",
+            "--ip6-icmp-type",
+            "router-advertisement",
+            "-j",
+            "DROP",
+        ],
+        vec![
+            "-p",
+            "IPv6",
+            "--ip6-proto",
+            "ipv6-icmp",
+            "--ip6-icmp-type",
+            "redirect",
+            "-j",
+            "DROP",
+        ],
+    ];
+
+    let mut bindings6 = Vec::new();
+    for (bound_mac, cidr) in &port.bindings {
+        match IpFamily::of_cidr(cidr)? {
+            IpFamily::V4 => {
+                rules.push(vec![
+                    "-p",
+                    "ARP",
+                    "-s",
+                    bound_mac,
+                    "--arp-mac-src",
+                    bound_mac,
+                    "--arp-ip-src",
+                    cidr,
+                    "-j",
+                    "RETURN",
+                ]);
+                rules.push(vec![
+                    "-p", "IPv4", "-s", bound_mac, "--ip-src", cidr, "-j", "RETURN",
+                ]);
+            }
+            IpFamily::V6 => bindings6.push((bound_mac, cidr)),
+        }
+    }
+    // ARP probes and DHCP discovery, sent before the guest has an address.
+    rules.push(vec![
+        "-p",
+        "ARP",
+        "-s",
+        mac,
+        "--arp-mac-src",
+        mac,
+        "--arp-ip-src",
+        "0.0.0.0",
+        "-j",
+        "RETURN",
+    ]);
+    rules.push(vec![
+        "-p",
+        "IPv4",
+        "-s",
+        mac,
+        "--ip-src",
+        "0.0.0.0",
+        "--ip-proto",
+        "udp",
+        "--ip-dport",
+        "67",
+        "-j",
+        "RETURN",
+    ]);
+
+    for (bound_mac, cidr) in bindings6 {
+        rules.push(vec![
+            "-p",
+            "IPv6",
+            "-s",
+            bound_mac,
+            "--ip6-src",
+            cidr,
+            "-j",
+            "RETURN",
+        ]);
+    }
+    rules.push(vec![
+        "-p",
+        "IPv6",
+        "-s",
+        mac,
+        "--ip6-src",
+        "fe80::/10",
+        "-j",
+        "RETURN",
+    ]);
+    // Duplicate address detection.
+    rules.push(vec![
+        "-p",
+        "IPv6",
+        "-s",
+        mac,
+        "--ip6-src",
+        "::",
+        "--ip6-proto",
+        "ipv6-icmp",
+        "-j",
+        "RETURN",
+    ]);
+    rules.push(vec!["-j", "DROP"]);
+
+    Ok(rules
+        .into_iter()
+        .map(|rule| {
+            ["-i", tap]
+                .into_iter()
+                .chain(rule)
+                .map(str::to_string)
+                .collect()
+        })
+        .collect())
+}
+
+/// Point PREROUTING at `chain` for exactly the given TAPs.
+async fn sync_prerouting_jumps(chain: &str, taps: &[&str]) -> Result<()> {
+    let existing = ebtables_rules(EBTABLES_PREROUTING).await?;
+    let wanted: Vec<String> = taps
+        .iter()
+        .map(|tap| format!("-i {tap} -j {chain}"))
+        .collect();
+
+    for rule in existing
+        .iter()
+        .filter(|rule| rule.ends_with(&format!("-j {chain}")) && !wanted.contains(rule))
+    {
+        let mut args = vec!["-D", EBTABLES_PREROUTING];
+        args.extend(rule.split_whitespace());
+        ebtables(&args).await?;
+    }
+    for (tap, rule) in taps.iter().zip(&wanted) {
+        if !existing.contains(rule) {
+            ebtables(&["-A", EBTABLES_PREROUTING, "-i", tap, "-j", chain]).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Rules of an ebtables `nat` chain as `ebtables -L` prints them.
+async fn ebtables_rules(chain: &str) -> Result<Vec<String>> {
+    let listing = run_cmd_capture("ebtables", &["-t", "nat", "-L", chain]).await?;
+    Ok(listing
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with('-'))
+        .map(ToString::to_string)
+        .collect())
+}
+
+async fn ebtables_chain_exists(chain: &str) -> bool {
+    run_cmd_capture("ebtables", &["-t", "nat", "-L", chain])
+        .await
+        .is_ok()
+}
+
+async fn ebtables(args: &[&str]) -> Result<()> {
+    let mut nat_args = vec!["-t", "nat"];
+    nat_args.extend_from_slice(args);
+    run_cmd("ebtables", &nat_args).await
+}
+
+/// Rewrite a VM's floating IP and port forward rules. The SNAT jump sits
+/// ahead of the bridge masquerade rules so a VM with a floating IP leaves
+/// the uplink from that address.
+pub async fn sync_vm_nat(
+    vm_id: &str,
+    uplink: &str,
+    floating_ips: &[FloatingIpNat],
+    port_forwards: &[PortForwardNat],
+) -> Result<()> {
+    super::validate_iface_name(uplink)?;
+    if floating_ips.is_empty() && port_forwards.is_empty() {
+        return teardown_vm_nat(vm_id).await;
+    }
+
+    let chains = VmNatChains::new(vm_id);
+    for (parent, chain) in [("PREROUTING", &chains.dnat), ("POSTROUTING", &chains.snat)] {
+        match run_cmd("iptables", &["-t", "nat", "-N", chain]).await {
+            Ok(()) => {}
+            Err(error) if error.to_string().contains("Chain already exists") => {}
+            Err(error) => return Err(error),
+        }
+        if run_cmd("iptables", &["-t", "nat", "-C", parent, "-j", chain])
+            .await
+            .is_err()
+        {
+            run_cmd("iptables", &["-t", "nat", "-I", parent, "1", "-j", chain]).await?;
+        }
+        run_cmd("iptables", &["-t", "nat", "-F", chain]).await?;
+    }
+
+    for nat in floating_ips {
+        let floating_ip = format!("{}/32", nat.floating_ip);
+        let vm_ip = nat.vm_ip.to_string();
+        run_cmd(
+            "iptables",
+            &[
+                "-t",
+                "nat",
+                "-A",
+                &chains.dnat,
+                "-d",
+                &floating_ip,
+                "-j",
+                "DNAT",
+                "--to-destination",
+                &vm_ip,
+            ],
+        )
+        .await?;
+        run_cmd(
+            "iptables",
+            &[
+                "-t",
+                "nat",
+                "-A",
+                &chains.snat,
+                "-s",
+                &format!("{vm_ip}/32"),
+                "-o",
+                uplink,
+                "-j",
+                "SNAT",
+                "--to-source",
+                &nat.floating_ip.to_string(),
+            ],
+        )
+        .await?;
+    }
+
+    for forward in port_forwards {
+        run_cmd(
+            "iptables",
+            &[
+                "-t",
+                "nat",
+                "-A",
+                &chains.dnat,
+                "-p",
+                forward.protocol,
+                "-m",
+                "addrtype",
+                "--dst-type",
+                "LOCAL",
+                "--dport",
+                &forward.host_port.to_string(),
+                "-j",
+                "DNAT",
+                "--to-destination",
+                &format!("{}:{}", forward.vm_ip, forward.vm_port),
+            ],
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn teardown_vm_nat(vm_id: &str) -> Result<()> {
+    let chains = VmNatChains::new(vm_id);
+    let existing = run_cmd_capture("iptables", &["-t", "nat", "-S"]).await?;
+    for (parent, chain) in [("PREROUTING", &chains.dnat), ("POSTROUTING", &chains.snat)] {
+        if !existing.lines().any(|line| line == format!("-N {chain}")) {
+            continue;
+        }
+        while run_cmd("iptables", &["-t", "nat", "-D", parent, "-j", chain])
+            .await
+            .is_ok()
+        {}
+        let _ = run_cmd("iptables", &["-t", "nat", "-F", chain]).await;
+        let _ = run_cmd("iptables", &["-t", "nat", "-X", chain]).await;
+    }
     Ok(())
 }
 
-fn sanitize_suffix(input: &str, max_len: usize) -> String {
-    input
-        .chars()
-        .filter(|c| c.is_ascii_alphanumeric())
-        .take(max_len)
-        .collect::<String>()
+/// Floating IPs currently translated to a VM on this host.
+pub async fn vm_floating_ips(vm_id: &str) -> Result<Vec<Ipv4Addr>> {
+    let chains = VmNatChains::new(vm_id);
+    let existing = run_cmd_capture("iptables", &["-t", "nat", "-S"]).await?;
+    Ok(dnat_floating_ips(&existing, &chains.dnat))
+}
+
+/// Destinations of the whole-address DNAT rules in `chain`, parsed from
+/// `iptables -S` output. Port forwards match on `--dst-type` instead of
+/// `-d`, so they are not picked up.
+fn dnat_floating_ips(listing: &str, chain: &str) -> Vec<Ipv4Addr> {
+    let prefix = format!("-A {chain} ");
+    listing
+        .lines()
+        .filter(|line| line.starts_with(&prefix) && line.contains("-j DNAT"))
+        .filter_map(|line| {
+            let mut tokens = line.split_whitespace();
+            tokens.find(|token| *token == "-d")?;
+            let address = tokens.next()?;
+            address.strip_suffix("/32").unwrap_or(address).parse().ok()
+        })
+        .collect()
 }
 
-async fn ensure_chain(chain: &str) -> Result<()> {
-    match run_cmd("iptables", &["-N", chain]).await {
+async fn ensure_chain(family: IpFamily, chain: &str) -> Result<()> {
+    match run_cmd(family.program(), &["-N", chain]).await {
         Ok(()) => Ok(()),
         Err(error) if error.to_string().contains("Chain already exists") => Ok(()),
         Err(error) => Err(error),
     }
 }
 
-async fn flush_chain(chain: &str) -> Result<()> {
-    run_cmd("iptables", &["-F", chain]).await
+async fn flush_chain(family: IpFamily, chain: &str) -> Result<()> {
+    run_cmd(family.program(), &["-F", chain]).await
 }
 
-async fn ensure_jump_rule(parent_chain: &str, rule: &[&str]) -> Result<()> {
+async fn ensure_jump_rule(family: IpFamily, parent_chain: &str, rule: &[&str]) -> Result<()> {
     let mut check_args = vec!["-C", parent_chain];
     check_args.extend_from_slice(rule);
-    if run_cmd("iptables", &check_args).await.is_ok() {
+    if run_cmd(family.program(), &check_args).await.is_ok() {
         return Ok(());
     }
 
     let mut insert_args = vec!["-I", parent_chain, "1"];
     insert_args.extend_from_slice(rule);
-    run_cmd("iptables", &insert_args).await
+    run_cmd(family.program(), &insert_args).await
 }
 
-async fn next_child_chain(parent_chain: &str, child_a: &str, child_b: &str) -> Result<String> {
-    let rules = run_cmd_capture("iptables", &["-S", parent_chain]).await?;
+async fn next_child_chain(
+    family: IpFamily,
+    parent_chain: &str,
+    child_a: &str,
+    child_b: &str,
+) -> Result<String> {
+    let rules = run_cmd_capture(family.program(), &["-S", parent_chain]).await?;
     if rules
         .lines()
         .any(|line| line == format!("-A {parent_chain} -j {child_a}"))
@@ -376,30 +1078,36 @@ async fn next_child_chain(parent_chain: &str, child_a: &str, child_b: &str) -> R
     }
 }
 
-async fn point_parent_to_child(parent_chain: &str, child_chain: &str) -> Result<()> {
-    let rules = run_cmd_capture("iptables", &["-S", parent_chain]).await?;
+async fn point_parent_to_child(
+    family: IpFamily,
+    parent_chain: &str,
+    child_chain: &str,
+) -> Result<()> {
+    let program = family.program();
+    let rules = run_cmd_capture(program, &["-S", parent_chain]).await?;
     if rules
         .lines()
         .any(|line| line.starts_with(&format!("-A {parent_chain} ")))
     {
-        run_cmd("iptables", &["-R", parent_chain, "1", "-j", child_chain]).await?;
-        while run_cmd("iptables", &["-D", parent_chain, "2"])
-            .await
-            .is_ok()
-        {}
+        run_cmd(program, &["-R", parent_chain, "1", "-j", child_chain]).await?;
+        while run_cmd(program, &["-D", parent_chain, "2"]).await.is_ok() {}
     } else {
-        run_cmd("iptables", &["-A", parent_chain, "-j", child_chain]).await?;
+        run_cmd(program, &["-A", parent_chain, "-j", child_chain]).await?;
     }
     Ok(())
 }
 
-async fn sync_forward_jump_rules(parent_chain: &str, desired_rules: &[Vec<String>]) -> Result<()> {
+async fn sync_forward_jump_rules(
+    family: IpFamily,
+    parent_chain: &str,
+    desired_rules: &[Vec<String>],
+) -> Result<()> {
     for rule in desired_rules {
         let refs: Vec<&str> = rule.iter().map(String::as_str).collect();
-        ensure_jump_rule(FORWARD_CHAIN, &refs).await?;
+        ensure_jump_rule(family, FORWARD_CHAIN, &refs).await?;
     }
 
-    let rules = run_cmd_capture("iptables", &["-S", FORWARD_CHAIN]).await?;
+    let rules = run_cmd_capture(family.program(), &["-S", FORWARD_CHAIN]).await?;
     for line in rules
         .lines()
         .filter(|line| line.contains(&format!("-j {parent_chain}")))
@@ -415,7 +1123,7 @@ async fn sync_forward_jump_rules(parent_chain: &str, desired_rules: &[Vec<String
         if parts.first().is_some_and(|part| part == "-A") {
             parts[0] = "-D".to_string();
             let refs: Vec<&str> = parts.iter().map(String::as_str).collect();
-            run_cmd("iptables", &refs).await?;
+            run_cmd(family.program(), &refs).await?;
         }
     }
 
@@ -423,6 +1131,7 @@ async fn sync_forward_jump_rules(parent_chain: &str, desired_rules: &[Vec<String
 }
 
 async fn ensure_nat_exemption_rule(
+    family: IpFamily,
     bridge: &str,
     local_subnet: &str,
     exempt_subnet: &str,
@@ -441,22 +1150,23 @@ async fn ensure_nat_exemption_rule(
 
     let mut check_args = vec!["-t", "nat", "-C", "POSTROUTING"];
     check_args.extend_from_slice(&rule);
-    if run_cmd("iptables", &check_args).await.is_ok() {
+    if run_cmd(family.program(), &check_args).await.is_ok() {
         return Ok(());
     }
 
     let mut insert_args = vec!["-t", "nat", "-I", "POSTROUTING", "1"];
     insert_args.extend_from_slice(&rule);
-    run_cmd("iptables", &insert_args).await
+    run_cmd(family.program(), &insert_args).await
 }
 
 async fn sync_nat_exemption_rules(
+    family: IpFamily,
     bridge: &str,
     local_subnet: &str,
     nat_exempt_subnets: &[String],
 ) -> Result<()> {
     for subnet in nat_exempt_subnets {
-        ensure_nat_exemption_rule(bridge, local_subnet, subnet).await?;
+        ensure_nat_exemption_rule(family, bridge, local_subnet, subnet).await?;
     }
 
     let desired_rules: Vec<Vec<String>> = nat_exempt_subnets
@@ -476,7 +1186,7 @@ async fn sync_nat_exemption_rules(
         })
         .collect();
 
-    let rules = run_cmd_capture("iptables", &["-t", "nat", "-S", "POSTROUTING"]).await?;
+    let rules = run_cmd_capture(family.program(), &["-t", "nat", "-S", "POSTROUTING"]).await?;
     for line in rules.lines().filter(|line| {
         line.contains(&format!("-s {local_subnet}"))
             && line.contains(&format!("! -o {bridge}"))
@@ -495,7 +1205,7 @@ async fn sync_nat_exemption_rules(
             parts.insert(0, "nat".to_string());
             parts.insert(0, "-t".to_string());
             let refs: Vec<&str> = parts.iter().map(String::as_str).collect();
-            run_cmd("iptables", &refs).await?;
+            run_cmd(family.program(), &refs).await?;
         }
     }
 
@@ -509,8 +1219,12 @@ fn rule_matches(line: &str, desired_rule: &[String]) -> bool {
         .all(|token| parts.contains(token.as_str()))
 }
 
-async fn remove_nat_exemption_rules(bridge: &str, local_subnet: &str) -> Result<()> {
-    let rules = run_cmd_capture("iptables", &["-t", "nat", "-S", "POSTROUTING"]).await?;
+async fn remove_nat_exemption_rules(
+    family: IpFamily,
+    bridge: &str,
+    local_subnet: &str,
+) -> Result<()> {
+    let rules = run_cmd_capture(family.program(), &["-t", "nat", "-S", "POSTROUTING"]).await?;
     for line in rules.lines().filter(|line| {
         line.contains(&format!("-s {local_subnet}"))
             && line.contains(&format!("! -o {bridge}"))
@@ -522,14 +1236,15 @@ async fn remove_nat_exemption_rules(bridge: &str, local_subnet: &str) -> Result<
             parts.insert(0, "nat".to_string());
             parts.insert(0, "-t".to_string());
             let refs: Vec<&str> = parts.iter().map(String::as_str).collect();
-            let _ = run_cmd("iptables", &refs).await;
+            let _ = run_cmd(family.program(), &refs).await;
         }
     }
     Ok(())
 }
 
-async fn remove_forward_rules_referencing(chain: &str) -> Result<()> {
-    let rules = run_cmd_capture("iptables", &["-S", FORWARD_CHAIN]).await?;
+async fn remove_forward_rules_referencing(family: IpFamily, chain: &str) -> Result<()> {
+    let program = family.program();
+    let rules = run_cmd_capture(program, &["-S", FORWARD_CHAIN]).await?;
     for line in rules
         .lines()
         .filter(|line| line.contains(&format!("-j {chain}")))
@@ -538,27 +1253,27 @@ async fn remove_forward_rules_referencing(chain: &str) -> Result<()> {
         if parts.first().is_some_and(|part| part == "-A") {
             parts[0] = "-D".to_string();
             let refs: Vec<&str> = parts.iter().map(String::as_str).collect();
-            let _ = run_cmd("iptables", &refs).await;
+            let _ = run_cmd(program, &refs).await;
         }
     }
 
-    let _ = run_cmd("iptables", &["-F", chain]).await;
-    let _ = run_cmd("iptables", &["-X", chain]).await;
+    let _ = run_cmd(program, &["-F", chain]).await;
+    let _ = run_cmd(program, &["-X", chain]).await;
     Ok(())
 }
 
-async fn delete_chain_if_exists(chain: &str) -> Result<()> {
-    if !chain_exists(chain).await? {
+async fn delete_chain_if_exists(family: IpFamily, chain: &str) -> Result<()> {
+    if !chain_exists(family, chain).await? {
         return Ok(());
     }
 
-    let _ = run_cmd("iptables", &["-F", chain]).await;
-    let _ = run_cmd("iptables", &["-X", chain]).await;
+    let _ = run_cmd(family.program(), &["-F", chain]).await;
+    let _ = run_cmd(family.program(), &["-X", chain]).await;
     Ok(())
 }
 
-async fn chain_exists(chain: &str) -> Result<bool> {
-    let output = run_cmd_capture("iptables", &["-S"]).await?;
+async fn chain_exists(family: IpFamily, chain: &str) -> Result<bool> {
+    let output = run_cmd_capture(family.program(), &["-S"]).await?;
     Ok(output.lines().any(|line| line == format!("-N {chain}")))
 }
 
@@ -591,3 +1306,92 @@ async fn run_cmd_capture(program: &str, args: &[&str]) -> Result<String> {
         anyhow::bail!("{} {} failed: {}", program, args.join(" "), stderr.trim())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rule_counters_follow_the_active_child_chain() {
+        let parent_rules = "-N QXVMIabc\n-A QXVMIabc -j QXVIBabc\n";
+        assert_eq!(
+            active_child_chain(parent_rules, "QXVMIabc", "QXVIAabc", "QXVIBabc"),
+            Some("QXVIBabc")
+        );
+        assert_eq!(
+            active_child_chain("-N QXVMIabc\n", "QXVMIabc", "QXVIAabc", "QXVIBabc"),
+            None
+        );
+
+        let listing = "\
+-N QXVIBabc
+-A QXVIBabc -m conntrack --ctstate RELATED,ESTABLISHED -c 900 81000 -j ACCEPT
+-A QXVIBabc -s 10.0.0.9/32 -p tcp -m tcp --dport 5432 -m comment --comment \"qarax:r1\" -c 12 720 -j ACCEPT
+-A QXVIBabc -s 10.0.0.10/32 -p tcp -m tcp --dport 5432 -m comment --comment qarax:r1 -c 3 180 -j ACCEPT
+-A QXVIBabc -m limit --limit 10/min -c 4 240 -j LOG --log-prefix \"qxd:abc:in \"
+-A QXVIBabc -m comment --comment \"qarax:default-drop\" -c 4 240 -j DROP
+";
+        let counters = rule_counters(listing, FirewallDirection::Ingress);
+        let summary: Vec<(&str, u64, u64)> = counters
+            .iter()
+            .map(|counter| (counter.rule_id.as_str(), counter.packets, counter.bytes))
+            .collect();
+        assert_eq!(summary, [("r1", 12, 720), ("r1", 3, 180), ("", 4, 240)]);
+    }
+
+    #[test]
+    fn port_security_rules_bind_each_address_to_its_mac() {
+        let mac = "02:aa:00:00:00:05".to_string();
+        let port = TapPortSecurity {
+            tap: "qt24b6061en0".into(),
+            mac: mac.clone(),
+            bindings: vec![
+                (mac.clone(), "10.0.0.5/32".into()),
+                (mac.clone(), "fd00::a8:ff:fe00:5/128".into()),
+                ("00:00:5e:00:01:07".into(), "10.0.0.100/32".into()),
+            ],
+        };
+        let rules: Vec<String> = port_security_rules(&port)
+            .unwrap()
+            .iter()
+            .map(|rule| rule.join(" "))
+            .collect();
+
+        assert!(
+            rules
+                .iter()
+                .all(|rule| rule.starts_with("-i qt24b6061en0 "))
+        );
+        assert!(rules.contains(&format!(
+            "-i qt24b6061en0 -p ARP -s {mac} --arp-mac-src {mac} --arp-ip-src 10.0.0.5/32 -j RETURN"
+        )));
+        assert!(
+            rules.contains(
+                &"-i qt24b6061en0 -p IPv4 -s 00:00:5e:00:01:07 --ip-src 10.0.0.100/32 -j RETURN"
+                    .to_string()
+            )
+        );
+        assert!(rules.contains(&format!(
+            "-i qt24b6061en0 -p IPv6 -s {mac} --ip6-src fd00::a8:ff:fe00:5/128 -j RETURN"
+        )));
+        assert!(rules[0].contains("router-advertisement -j DROP"));
+        assert_eq!(rules.last().unwrap(), "-i qt24b6061en0 -j DROP");
+    }
+
+    #[test]
+    fn dnat_floating_ips_ignores_port_forwards_and_other_chains() {
+        let listing = "\
+-P PREROUTING ACCEPT
+-N QXVDNabc
+-A PREROUTING -j QXVDNabc
+-A QXVDNabc -d 203.0.113.5/32 -j DNAT --to-destination 10.0.0.5
+-A QXVDNabc -p tcp -m addrtype --dst-type LOCAL -m tcp --dport 8080 -j DNAT --to-destination 10.0.0.5:80
+-A QXVDNother -d 203.0.113.9/32 -j DNAT --to-destination 10.0.0.9
+";
+
+        assert_eq!(
+            dnat_floating_ips(listing, "QXVDNabc"),
+            vec![Ipv4Addr::new(203, 0, 113, 5)]
+        );
+    }
+}
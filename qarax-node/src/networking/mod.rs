@@ -1,6 +1,13 @@
 pub mod bridge;
 pub mod dhcp;
+pub mod dns;
+pub mod firewall;
+pub mod firewall_log;
+pub mod floating_ip;
 pub mod iptables;
+pub mod nftables;
+pub mod port_security;
+pub mod ra;
 pub mod vxlan;
 
 /// Validate a network interface name.
@@ -49,3 +56,50 @@ pub(super) fn validate_ipv4_address(ip: &str) -> anyhow::Result<()> {
         .map_err(|_| anyhow::anyhow!("Invalid IPv4 address {ip:?}"))?;
     Ok(())
 }
+
+/// Validate a colon-separated Ethernet MAC address (e.g. "02:00:0a:00:00:05").
+pub(super) fn validate_mac(mac: &str) -> anyhow::Result<()> {
+    let octets: Vec<&str> = mac.split(':').collect();
+    if octets.len() != 6
+        || octets
+            .iter()
+            .any(|octet| octet.len() != 2 || !octet.chars().all(|c| c.is_ascii_hexdigit()))
+    {
+        anyhow::bail!("Invalid MAC address {mac:?}");
+    }
+    Ok(())
+}
+
+/// Validate an IPv6 CIDR string (e.g. "fd00:10::/64").
+pub(super) fn validate_ipv6_cidr(cidr: &str) -> anyhow::Result<()> {
+    let (ip_str, prefix_str) = cidr
+        .split_once('/')
+        .ok_or_else(|| anyhow::anyhow!("Invalid CIDR {cidr:?}: missing '/'"))?;
+    let _: std::net::Ipv6Addr = ip_str
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid CIDR {cidr:?}: bad IPv6 address"))?;
+    let prefix: u8 = prefix_str
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid CIDR {cidr:?}: bad prefix length"))?;
+    if prefix > 128 {
+        anyhow::bail!("Invalid CIDR {cidr:?}: prefix length must be 0–128");
+    }
+    Ok(())
+}
+
+pub(super) fn validate_ipv6_address(ip: &str) -> anyhow::Result<()> {
+    let _: std::net::Ipv6Addr = ip
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid IPv6 address {ip:?}"))?;
+    Ok(())
+}
+
+/// Keep the ASCII alphanumerics of `input`, truncated to `max_len`, for use
+/// in firewall chain and set names.
+pub(super) fn sanitize_suffix(input: &str, max_len: usize) -> String {
+    input
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .take(max_len)
+        .collect::<String>()
+}
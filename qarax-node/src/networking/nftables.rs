@@ -0,0 +1,1023 @@
+use std::fmt::Write as _;
+use std::net::Ipv4Addr;
+use std::process::Stdio;
+
+use anyhow::{Context, Result};
+use tokio::io::AsyncWriteExt;
+use tracing::info;
+
+use super::firewall::{DEFAULT_DROP_COMMENT, counter_for_comment, rule_comment};
+use super::firewall_log::{self, LOG_BURST, LOG_RATE_PER_MINUTE};
+use super::floating_ip::{FloatingIpNat, PortForwardNat};
+use super::port_security::TapPortSecurity;
+use super::sanitize_suffix;
+use crate::rpc::node::{
+    FirewallDirection, FirewallProtocol, FirewallRuleCounter, VmFirewallInterface, VmFirewallRule,
+};
+
+/// Everything qarax programs lives in this one table; `inet` covers IPv4 and
+/// IPv6 with a single set of chains.
+const TABLE: &str = "inet qarax";
+/// Anti-spoofing runs at layer 2, before frames reach the `inet` hooks or
+/// the host, so it needs a table of its own.
+const BRIDGE_TABLE: &str = "bridge qarax";
+
+const FORWARD_FILTER_HOOK: &str = "type filter hook forward priority filter; policy accept;";
+const POSTROUTING_NAT_HOOK: &str = "type nat hook postrouting priority srcnat; policy accept;";
+const PREROUTING_DNAT_HOOK: &str = "type nat hook prerouting priority dstnat; policy accept;";
+/// Ahead of the bridge masquerade chains, so a floating IP wins over the
+/// host address.
+const POSTROUTING_SNAT_HOOK: &str =
+    "type nat hook postrouting priority srcnat - 10; policy accept;";
+const BRIDGE_PREROUTING_HOOK: &str = "type filter hook prerouting priority filter; policy accept;";
+const INTERVAL_SET4: &str = "type ipv4_addr; flags interval; auto-merge;";
+const INTERVAL_SET6: &str = "type ipv6_addr; flags interval; auto-merge;";
+
+const ICMPV6_DISCOVERY_TYPES: &str =
+    "{ nd-router-solicit, nd-router-advert, nd-neighbor-solicit, nd-neighbor-advert }";
+
+/// Chain and set names owned by one bridge.
+///
+/// NAT (`fwd_`, `nat_`, `exempt*`) and isolation (`iso_`, `blocked*`) are kept
+/// apart so `setup_nat` and `sync_network_isolation` never rewrite each
+/// other's state.
+struct BridgeObjects {
+    fwd: String,
+    nat: String,
+    iso: String,
+    exempt4: String,
+    exempt6: String,
+    blocked4: String,
+    blocked6: String,
+}
+
+impl BridgeObjects {
+    fn new(bridge: &str) -> Self {
+        let suffix = sanitize_suffix(bridge, 15);
+        Self {
+            fwd: format!("fwd_{suffix}"),
+            nat: format!("nat_{suffix}"),
+            iso: format!("iso_{suffix}"),
+            exempt4: format!("exempt4_{suffix}"),
+            exempt6: format!("exempt6_{suffix}"),
+            blocked4: format!("blocked4_{suffix}"),
+            blocked6: format!("blocked6_{suffix}"),
+        }
+    }
+}
+
+/// Chain and set names owned by one VM. The base chain dispatches on
+/// `bridge . address` sets, so matching a packet to its VM is a single set
+/// lookup no matter how many NICs the VM has.
+struct VmObjects {
+    base: String,
+    ingress: String,
+    egress: String,
+    addrs4: String,
+    addrs6: String,
+    dnat: String,
+    snat: String,
+    /// In `BRIDGE_TABLE`.
+    spoof: String,
+}
+
+impl VmObjects {
+    fn new(vm_id: &str) -> Self {
+        let suffix = sanitize_suffix(vm_id, 32);
+        Self {
+            base: format!("vm_{suffix}"),
+            ingress: format!("vm_{suffix}_in"),
+            egress: format!("vm_{suffix}_out"),
+            addrs4: format!("vm_{suffix}_addrs4"),
+            addrs6: format!("vm_{suffix}_addrs6"),
+            dnat: format!("vm_{suffix}_dnat"),
+            snat: format!("vm_{suffix}_snat"),
+            spoof: format!("vm_{suffix}_spoof"),
+        }
+    }
+}
+
+/// Fail early when the `nft` binary is missing, rather than on the first
+/// network attach.
+pub async fn check_available() -> Result<()> {
+    let output = tokio::process::Command::new("nft")
+        .arg("--version")
+        .output()
+        .await
+        .context("Failed to execute nft")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "nft --version failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+pub async fn setup_nat(bridge: &str, subnet: &str, subnet6: Option<&str>) -> Result<()> {
+    super::validate_iface_name(bridge)?;
+    super::validate_ipv4_cidr(subnet)?;
+    if let Some(subnet6) = subnet6 {
+        super::validate_ipv6_cidr(subnet6)?;
+    }
+    info!("Setting up NAT for bridge {} subnet {}", bridge, subnet);
+
+    tokio::fs::write("/proc/sys/net/ipv4/ip_forward", b"1")
+        .await
+        .context("Failed to enable ip_forward")?;
+    if subnet6.is_some() {
+        tokio::fs::write("/proc/sys/net/ipv6/conf/all/forwarding", b"1")
+            .await
+            .context("Failed to enable IPv6 forwarding")?;
+    }
+
+    apply(&nat_ruleset(bridge, subnet, subnet6)?).await
+}
+
+pub async fn teardown_nat(bridge: &str, subnet: &str, subnet6: Option<&str>) -> Result<()> {
+    super::validate_iface_name(bridge)?;
+    super::validate_ipv4_cidr(subnet)?;
+    if let Some(subnet6) = subnet6 {
+        super::validate_ipv6_cidr(subnet6)?;
+    }
+    info!("Tearing down NAT for bridge {} subnet {}", bridge, subnet);
+
+    let objects = BridgeObjects::new(bridge);
+    let mut script = table_preamble();
+    declare_chain(&mut script, &objects.fwd, Some(FORWARD_FILTER_HOOK));
+    declare_chain(&mut script, &objects.nat, Some(POSTROUTING_NAT_HOOK));
+    declare_set(&mut script, &objects.exempt4, INTERVAL_SET4);
+    declare_set(&mut script, &objects.exempt6, INTERVAL_SET6);
+    delete_objects(
+        &mut script,
+        &[&objects.fwd, &objects.nat],
+        &[&objects.exempt4, &objects.exempt6],
+    );
+    apply(&script).await
+}
+
+pub async fn sync_network_isolation(
+    bridge: &str,
+    local_subnet: &str,
+    local_subnet6: Option<&str>,
+    blocked_subnets: &[String],
+    nat_exempt_subnets: &[String],
+) -> Result<()> {
+    super::validate_iface_name(bridge)?;
+    super::validate_ipv4_cidr(local_subnet)?;
+    if let Some(local_subnet6) = local_subnet6 {
+        super::validate_ipv6_cidr(local_subnet6)?;
+    }
+
+    apply(&isolation_ruleset(
+        bridge,
+        local_subnet6.is_some(),
+        blocked_subnets,
+        nat_exempt_subnets,
+    )?)
+    .await
+}
+
+pub async fn teardown_network_isolation(bridge: &str) -> Result<()> {
+    super::validate_iface_name(bridge)?;
+    let objects = BridgeObjects::new(bridge);
+    let mut script = table_preamble();
+    declare_chain(&mut script, &objects.iso, Some(FORWARD_FILTER_HOOK));
+    declare_set(&mut script, &objects.blocked4, INTERVAL_SET4);
+    declare_set(&mut script, &objects.blocked6, INTERVAL_SET6);
+    delete_objects(
+        &mut script,
+        &[&objects.iso],
+        &[&objects.blocked4, &objects.blocked6],
+    );
+    apply(&script).await
+}
+
+pub async fn sync_vm_firewall(
+    vm_id: &str,
+    interfaces: &[VmFirewallInterface],
+    log_denied: bool,
+) -> Result<()> {
+    if interfaces.is_empty() {
+        return teardown_vm_firewall(vm_id).await;
+    }
+    apply(&vm_firewall_ruleset(vm_id, interfaces, log_denied)?).await
+}
+
+pub async fn teardown_vm_firewall(vm_id: &str) -> Result<()> {
+    let objects = VmObjects::new(vm_id);
+    let mut script = table_preamble();
+    declare_vm_objects(&mut script, &objects);
+    delete_objects(
+        &mut script,
+        &[&objects.base, &objects.ingress, &objects.egress],
+        &[&objects.addrs4, &objects.addrs6],
+    );
+    apply(&script).await
+}
+
+pub async fn sync_vm_nat(
+    vm_id: &str,
+    uplink: &str,
+    floating_ips: &[FloatingIpNat],
+    port_forwards: &[PortForwardNat],
+) -> Result<()> {
+    if floating_ips.is_empty() && port_forwards.is_empty() {
+        return teardown_vm_nat(vm_id).await;
+    }
+    apply(&vm_nat_ruleset(vm_id, uplink, floating_ips, port_forwards)?).await
+}
+
+pub async fn teardown_vm_nat(vm_id: &str) -> Result<()> {
+    let objects = VmObjects::new(vm_id);
+    let mut script = table_preamble();
+    declare_chain(&mut script, &objects.dnat, Some(PREROUTING_DNAT_HOOK));
+    declare_chain(&mut script, &objects.snat, Some(POSTROUTING_SNAT_HOOK));
+    delete_objects(&mut script, &[&objects.dnat, &objects.snat], &[]);
+    apply(&script).await
+}
+
+pub async fn sync_vm_port_security(vm_id: &str, ports: &[TapPortSecurity]) -> Result<()> {
+    apply(&vm_port_security_ruleset(vm_id, ports)?).await
+}
+
+pub async fn teardown_vm_port_security(vm_id: &str) -> Result<()> {
+    let chain = VmObjects::new(vm_id).spoof;
+    let script = format!(
+        "add table {BRIDGE_TABLE}\n\
+         add chain {BRIDGE_TABLE} {chain} {{ {BRIDGE_PREROUTING_HOOK} }}\n\
+         flush chain {BRIDGE_TABLE} {chain}\n\
+         delete chain {BRIDGE_TABLE} {chain}\n"
+    );
+    apply(&script).await
+}
+
+/// Counters of the tagged rules in a VM's ingress and egress chains.
+pub async fn vm_firewall_counters(vm_id: &str) -> Result<Vec<FirewallRuleCounter>> {
+    let objects = VmObjects::new(vm_id);
+    let mut counters = Vec::new();
+    for (chain, direction) in [
+        (&objects.ingress, FirewallDirection::Ingress),
+        (&objects.egress, FirewallDirection::Egress),
+    ] {
+        let output = tokio::process::Command::new("nft")
+            .args(["-j", "list", "chain", "inet", "qarax", chain])
+            .output()
+            .await
+            .context("Failed to execute nft")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains("No such file or directory") {
+                continue;
+            }
+            anyhow::bail!("nft list chain failed: {}", stderr.trim());
+        }
+        counters.extend(rule_counters(
+            &String::from_utf8_lossy(&output.stdout),
+            direction,
+        )?);
+    }
+    Ok(counters)
+}
+
+/// Counters of the qarax-tagged rules in an `nft -j list chain` listing.
+fn rule_counters(listing: &str, direction: FirewallDirection) -> Result<Vec<FirewallRuleCounter>> {
+    let listing: serde_json::Value =
+        serde_json::from_str(listing).context("Failed to parse nft JSON output")?;
+    let objects = listing["nftables"].as_array().cloned().unwrap_or_default();
+    Ok(objects
+        .iter()
+        .filter_map(|object| {
+            let rule = object.get("rule")?;
+            let comment = rule.get("comment")?.as_str()?;
+            let counter = rule
+                .get("expr")?
+                .as_array()?
+                .iter()
+                .find_map(|expr| expr.get("counter"))?;
+            counter_for_comment(
+                comment,
+                direction,
+                counter.get("packets")?.as_u64()?,
+                counter.get("bytes")?.as_u64()?,
+            )
+        })
+        .collect())
+}
+
+/// Floating IPs currently translated to a VM on this host.
+pub async fn vm_floating_ips(vm_id: &str) -> Result<Vec<Ipv4Addr>> {
+    let objects = VmObjects::new(vm_id);
+    let output = tokio::process::Command::new("nft")
+        .args(["list", "chain", "inet", "qarax", &objects.dnat])
+        .output()
+        .await
+        .context("Failed to execute nft")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("No such file or directory") {
+            return Ok(Vec::new());
+        }
+        anyhow::bail!("nft list chain failed: {}", stderr.trim());
+    }
+    Ok(dnat_floating_ips(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn vm_nat_ruleset(
+    vm_id: &str,
+    uplink: &str,
+    floating_ips: &[FloatingIpNat],
+    port_forwards: &[PortForwardNat],
+) -> Result<String> {
+    let objects = VmObjects::new(vm_id);
+    let uplink = quote_iface(uplink)?;
+    let mut script = table_preamble();
+    declare_chain(&mut script, &objects.dnat, Some(PREROUTING_DNAT_HOOK));
+    declare_chain(&mut script, &objects.snat, Some(POSTROUTING_SNAT_HOOK));
+    flush_chain(&mut script, &objects.dnat);
+    flush_chain(&mut script, &objects.snat);
+
+    for nat in floating_ips {
+        add_rule(
+            &mut script,
+            &objects.dnat,
+            &format!("ip daddr {} dnat ip to {}", nat.floating_ip, nat.vm_ip),
+        );
+        add_rule(
+            &mut script,
+            &objects.snat,
+            &format!(
+                "oifname {uplink} ip saddr {} snat ip to {}",
+                nat.vm_ip, nat.floating_ip
+            ),
+        );
+    }
+    for forward in port_forwards {
+        add_rule(
+            &mut script,
+            &objects.dnat,
+            &format!(
+                "fib daddr type local {} dport {} dnat ip to {}:{}",
+                forward.protocol, forward.host_port, forward.vm_ip, forward.vm_port
+            ),
+        );
+    }
+
+    Ok(script)
+}
+
+/// Destinations of the whole-address DNAT rules in an `nft list chain`
+/// listing; port forwards match on `fib daddr` and are skipped.
+fn dnat_floating_ips(listing: &str) -> Vec<Ipv4Addr> {
+    listing
+        .lines()
+        .filter(|line| line.contains(" dnat ") && !line.contains("fib daddr"))
+        .filter_map(|line| {
+            let mut tokens = line.split_whitespace();
+            tokens.find(|token| *token == "daddr")?;
+            tokens.next()?.parse().ok()
+        })
+        .collect()
+}
+
+fn nat_ruleset(bridge: &str, subnet: &str, subnet6: Option<&str>) -> Result<String> {
+    let objects = BridgeObjects::new(bridge);
+    let iface = quote_iface(bridge)?;
+    let mut script = table_preamble();
+
+    // The exemption sets are filled by sync_network_isolation; declaring them
+    // here lets the masquerade chain reference them before the first sync.
+    declare_set(&mut script, &objects.exempt4, INTERVAL_SET4);
+    declare_set(&mut script, &objects.exempt6, INTERVAL_SET6);
+
+    declare_chain(&mut script, &objects.fwd, Some(FORWARD_FILTER_HOOK));
+    flush_chain(&mut script, &objects.fwd);
+    add_rule(
+        &mut script,
+        &objects.fwd,
+        &format!("iifname {iface} accept"),
+    );
+    add_rule(
+        &mut script,
+        &objects.fwd,
+        &format!("oifname {iface} accept"),
+    );
+
+    declare_chain(&mut script, &objects.nat, Some(POSTROUTING_NAT_HOOK));
+    flush_chain(&mut script, &objects.nat);
+    let families = std::iter::once(("ip", subnet, &objects.exempt4))
+        .chain(subnet6.map(|subnet6| ("ip6", subnet6, &objects.exempt6)));
+    for (family, subnet, exempt) in families {
+        add_rule(
+            &mut script,
+            &objects.nat,
+            &format!("{family} saddr {subnet} {family} daddr @{exempt} oifname != {iface} return"),
+        );
+        add_rule(
+            &mut script,
+            &objects.nat,
+            &format!("{family} saddr {subnet} oifname != {iface} masquerade"),
+        );
+    }
+
+    Ok(script)
+}
+
+fn isolation_ruleset(
+    bridge: &str,
+    dual_stack: bool,
+    blocked_subnets: &[String],
+    nat_exempt_subnets: &[String],
+) -> Result<String> {
+    let objects = BridgeObjects::new(bridge);
+    let iface = quote_iface(bridge)?;
+    let (blocked4, blocked6) = split_by_family(blocked_subnets)?;
+    let (exempt4, exempt6) = split_by_family(nat_exempt_subnets)?;
+    // A bridge without an IPv6 prefix has no routable IPv6 guests to isolate.
+    let (blocked6, exempt6) = if dual_stack {
+        (blocked6, exempt6)
+    } else {
+        (Vec::new(), Vec::new())
+    };
+
+    let mut script = table_preamble();
+    for (set, spec, elements) in [
+        (&objects.blocked4, INTERVAL_SET4, &blocked4),
+        (&objects.blocked6, INTERVAL_SET6, &blocked6),
+        (&objects.exempt4, INTERVAL_SET4, &exempt4),
+        (&objects.exempt6, INTERVAL_SET6, &exempt6),
+    ] {
+        declare_set(&mut script, set, spec);
+        replace_elements(&mut script, set, elements);
+    }
+
+    declare_chain(&mut script, &objects.iso, Some(FORWARD_FILTER_HOOK));
+    flush_chain(&mut script, &objects.iso);
+    for (family, set) in [("ip", &objects.blocked4), ("ip6", &objects.blocked6)] {
+        add_rule(
+            &mut script,
+            &objects.iso,
+            &format!("iifname {iface} {family} daddr @{set} drop"),
+        );
+        add_rule(
+            &mut script,
+            &objects.iso,
+            &format!("oifname {iface} {family} saddr @{set} drop"),
+        );
+    }
+
+    Ok(script)
+}
+
+fn vm_firewall_ruleset(
+    vm_id: &str,
+    interfaces: &[VmFirewallInterface],
+    log_denied: bool,
+) -> Result<String> {
+    let objects = VmObjects::new(vm_id);
+    let mut addrs4 = Vec::with_capacity(interfaces.len());
+    let mut addrs6 = Vec::new();
+    for iface in interfaces {
+        super::validate_iface_name(&iface.bridge_name)?;
+        super::validate_ipv4_address(&iface.ip)?;
+        let name = quote_iface(&iface.bridge_name)?;
+        addrs4.push(format!("{name} . {}", iface.ip));
+        if !iface.ip6.is_empty() {
+            super::validate_ipv6_address(&iface.ip6)?;
+            addrs6.push(format!("{name} . {}", iface.ip6));
+        }
+    }
+
+    let mut script = table_preamble();
+    declare_vm_objects(&mut script, &objects);
+    for chain in [&objects.base, &objects.ingress, &objects.egress] {
+        flush_chain(&mut script, chain);
+    }
+    replace_elements(&mut script, &objects.addrs4, &addrs4);
+    replace_elements(&mut script, &objects.addrs6, &addrs6);
+
+    for (family, set) in [("ip", &objects.addrs4), ("ip6", &objects.addrs6)] {
+        add_rule(
+            &mut script,
+            &objects.base,
+            &format!("oifname . {family} daddr @{set} jump {}", objects.ingress),
+        );
+        add_rule(
+            &mut script,
+            &objects.base,
+            &format!("iifname . {family} saddr @{set} jump {}", objects.egress),
+        );
+    }
+
+    for chain in [&objects.ingress, &objects.egress] {
+        add_rule(&mut script, chain, "ct state established,related accept");
+        add_rule(
+            &mut script,
+            chain,
+            &format!("icmpv6 type {ICMPV6_DISCOVERY_TYPES} accept"),
+        );
+    }
+
+    let mut has_egress_rules = interfaces.iter().any(|iface| iface.restrict_egress);
+    for rule in interfaces.iter().flat_map(|iface| &iface.rules) {
+        let direction =
+            FirewallDirection::try_from(rule.direction).unwrap_or(FirewallDirection::Ingress);
+        let chain = match direction {
+            FirewallDirection::Ingress => &objects.ingress,
+            FirewallDirection::Egress => {
+                has_egress_rules = true;
+                &objects.egress
+            }
+        };
+        // Only tagged rules are counted; `vm_firewall_counters` reads them back.
+        let mut statement = rule_match(rule, direction)?;
+        match rule_comment(&rule.rule_id)? {
+            Some(comment) => write!(statement, " counter accept comment \"{comment}\"")
+                .expect("writing to a String cannot fail"),
+            None => statement.push_str(" accept"),
+        }
+        add_rule(&mut script, chain, &statement);
+    }
+
+    let mut default_drop = |chain: &str, direction| {
+        if log_denied {
+            add_rule(
+                &mut script,
+                chain,
+                &format!(
+                    "limit rate {LOG_RATE_PER_MINUTE}/minute burst {LOG_BURST} packets \
+                     log prefix \"{}\"",
+                    firewall_log::log_prefix(vm_id, direction)
+                ),
+            );
+        }
+        add_rule(
+            &mut script,
+            chain,
+            &format!("counter drop comment \"{DEFAULT_DROP_COMMENT}\""),
+        );
+    };
+    default_drop(&objects.ingress, FirewallDirection::Ingress);
+    if has_egress_rules {
+        default_drop(&objects.egress, FirewallDirection::Egress);
+    } else {
+        add_rule(&mut script, &objects.egress, "accept");
+    }
+
+    Ok(script)
+}
+
+/// One prerouting chain per VM in the bridge table. Each TAP's frames are
+/// accepted only from a bound MAC and address; anything else the guest sends,
+/// including other ethertypes, is dropped before the bridge forwards it.
+fn vm_port_security_ruleset(vm_id: &str, ports: &[TapPortSecurity]) -> Result<String> {
+    let objects = VmObjects::new(vm_id);
+    let chain = &objects.spoof;
+    let mut script = format!("add table {BRIDGE_TABLE}\n");
+    writeln!(
+        script,
+        "add chain {BRIDGE_TABLE} {chain} {{ {BRIDGE_PREROUTING_HOOK} }}"
+    )
+    .expect("writing to a String cannot fail");
+    writeln!(script, "flush chain {BRIDGE_TABLE} {chain}")
+        .expect("writing to a String cannot fail");
+
+    for port in ports {
+        let iif = quote_iface(&port.tap)?;
+        let mac = &port.mac;
+        let mut rule = |statement: &str| {
+            writeln!(
+                script,
+                "add rule {BRIDGE_TABLE} {chain} iifname {iif} {statement}"
+            )
+            .expect("writing to a String cannot fail");
+        };
+
+        let (bindings4, bindings6): (Vec<_>, Vec<_>) = port
+            .bindings
+            .iter()
+            .partition(|(_, cidr)| cidr_family(cidr).is_ok_and(|family| family == "ip"));
+
+        // Only the gateway may advertise routes, and a guest may only
+        // answer neighbor solicitations for its own addresses.
+        rule("icmpv6 type { nd-router-advert, nd-redirect } drop");
+        let targets: Vec<&str> = bindings6
+            .iter()
+            .map(|(_, cidr)| cidr.as_str())
+            .chain(["fe80::/10"])
+            .collect();
+        rule(&format!(
+            "icmpv6 type nd-neighbor-advert icmpv6 taddr != {{ {} }} drop",
+            targets.join(", ")
+        ));
+
+        for (bound_mac, cidr) in &bindings4 {
+            rule(&format!(
+                "ether saddr {bound_mac} arp saddr ether {bound_mac} arp saddr ip {cidr} accept"
+            ));
+            rule(&format!("ether saddr {bound_mac} ip saddr {cidr} accept"));
+        }
+        // ARP probes and DHCP discovery, sent before the guest has an address.
+        rule(&format!(
+            "ether saddr {mac} arp saddr ether {mac} arp saddr ip 0.0.0.0 accept"
+        ));
+        rule(&format!(
+            "ether saddr {mac} ip saddr 0.0.0.0 udp dport 67 accept"
+        ));
+
+        for (bound_mac, cidr) in &bindings6 {
+            rule(&format!("ether saddr {bound_mac} ip6 saddr {cidr} accept"));
+        }
+        rule(&format!("ether saddr {mac} ip6 saddr fe80::/10 accept"));
+        // Duplicate address detection.
+        rule(&format!(
+            "ether saddr {mac} ip6 saddr :: icmpv6 type {{ nd-neighbor-solicit, mld2-listener-report }} accept"
+        ));
+        rule("drop");
+    }
+
+    Ok(script)
+}
+
+/// Match expression for one security-group rule, without its verdict.
+fn rule_match(rule: &VmFirewallRule, direction: FirewallDirection) -> Result<String> {
+    let protocol = FirewallProtocol::try_from(rule.protocol).unwrap_or(FirewallProtocol::Any);
+    let mut parts = Vec::new();
+
+    let family = if rule.cidr.is_empty() {
+        None
+    } else {
+        let family = cidr_family(&rule.cidr)?;
+        let field = match direction {
+            FirewallDirection::Ingress => "saddr",
+            FirewallDirection::Egress => "daddr",
+        };
+        parts.push(format!("{family} {field} {}", rule.cidr));
+        Some(family)
+    };
+
+    let l4 = match protocol {
+        FirewallProtocol::Any => None,
+        FirewallProtocol::Tcp => Some("tcp"),
+        FirewallProtocol::Udp => Some("udp"),
+        FirewallProtocol::Icmp => {
+            parts.push(match family {
+                Some("ip") => "meta l4proto icmp".to_string(),
+                Some(_) => "meta l4proto ipv6-icmp".to_string(),
+                None => "meta l4proto { icmp, ipv6-icmp }".to_string(),
+            });
+            None
+        }
+    };
+
+    if let Some(l4) = l4 {
+        match (rule.port_start, rule.port_end) {
+            (Some(start), Some(end)) if start == end => parts.push(format!("{l4} dport {start}")),
+            (Some(start), Some(end)) => parts.push(format!("{l4} dport {start}-{end}")),
+            _ => parts.push(format!("meta l4proto {l4}")),
+        }
+    }
+
+    Ok(parts.join(" "))
+}
+
+fn declare_vm_objects(script: &mut String, objects: &VmObjects) {
+    declare_set(script, &objects.addrs4, "type ifname . ipv4_addr;");
+    declare_set(script, &objects.addrs6, "type ifname . ipv6_addr;");
+    declare_chain(script, &objects.ingress, None);
+    declare_chain(script, &objects.egress, None);
+    declare_chain(script, &objects.base, Some(FORWARD_FILTER_HOOK));
+}
+
+/// nft keyword (`ip` / `ip6`) for the family of a CIDR.
+fn cidr_family(cidr: &str) -> Result<&'static str> {
+    if super::validate_ipv4_cidr(cidr).is_ok() {
+        return Ok("ip");
+    }
+    super::validate_ipv6_cidr(cidr)?;
+    Ok("ip6")
+}
+
+fn split_by_family(subnets: &[String]) -> Result<(Vec<String>, Vec<String>)> {
+    let mut v4 = Vec::new();
+    let mut v6 = Vec::new();
+    for subnet in subnets {
+        match cidr_family(subnet)? {
+            "ip" => v4.push(subnet.clone()),
+            _ => v6.push(subnet.clone()),
+        }
+    }
+    Ok((v4, v6))
+}
+
+/// Quote an interface name for an nft script. Names are validated by
+/// `validate_iface_name`, which still allows quotes and backslashes.
+fn quote_iface(name: &str) -> Result<String> {
+    super::validate_iface_name(name)?;
+    if name.contains(['"', '\\']) {
+        anyhow::bail!("Interface name {name:?} contains illegal characters");
+    }
+    Ok(format!("\"{name}\""))
+}
+
+fn table_preamble() -> String {
+    format!("add table {TABLE}\n")
+}
+
+/// `add` is a no-op for objects that already exist, so every script declares
+/// what it touches before flushing or deleting it.
+fn declare_chain(script: &mut String, chain: &str, hook: Option<&str>) {
+    match hook {
+        Some(hook) => writeln!(script, "add chain {TABLE} {chain} {{ {hook} }}"),
+        None => writeln!(script, "add chain {TABLE} {chain}"),
+    }
+    .expect("writing to a String cannot fail");
+}
+
+fn declare_set(script: &mut String, set: &str, spec: &str) {
+    writeln!(script, "add set {TABLE} {set} {{ {spec} }}")
+        .expect("writing to a String cannot fail");
+}
+
+fn flush_chain(script: &mut String, chain: &str) {
+    writeln!(script, "flush chain {TABLE} {chain}").expect("writing to a String cannot fail");
+}
+
+fn replace_elements(script: &mut String, set: &str, elements: &[String]) {
+    writeln!(script, "flush set {TABLE} {set}").expect("writing to a String cannot fail");
+    if !elements.is_empty() {
+        writeln!(
+            script,
+            "add element {TABLE} {set} {{ {} }}",
+            elements.join(", ")
+        )
+        .expect("writing to a String cannot fail");
+    }
+}
+
+fn add_rule(script: &mut String, chain: &str, rule: &str) {
+    writeln!(script, "add rule {TABLE} {chain} {rule}").expect("writing to a String cannot fail");
+}
+
+/// Delete chains and then sets. Chains are flushed first: nft refuses to
+/// delete a chain that still has rules or a set that a rule still references.
+fn delete_objects(script: &mut String, chains: &[&str], sets: &[&str]) {
+    for chain in chains {
+        flush_chain(script, chain);
+    }
+    for chain in chains {
+        writeln!(script, "delete chain {TABLE} {chain}").expect("writing to a String cannot fail");
+    }
+    for set in sets {
+        writeln!(script, "delete set {TABLE} {set}").expect("writing to a String cannot fail");
+    }
+}
+
+/// Load a script with `nft -f -`. The kernel commits it as one transaction,
+/// so a failure part-way leaves the previous ruleset untouched.
+async fn apply(script: &str) -> Result<()> {
+    let mut child = tokio::process::Command::new("nft")
+        .args(["-f", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute nft")?;
+
+    let mut stdin = child.stdin.take().context("nft stdin unavailable")?;
+    stdin
+        .write_all(script.as_bytes())
+        .await
+        .context("Failed to write nft ruleset")?;
+    drop(stdin);
+
+    let output = child
+        .wait_with_output()
+        .await
+        .context("Failed to wait for nft")?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("nft -f failed: {}", stderr.trim())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(
+        direction: FirewallDirection,
+        protocol: FirewallProtocol,
+        cidr: &str,
+        ports: Option<(i32, i32)>,
+    ) -> VmFirewallRule {
+        VmFirewallRule {
+            direction: direction as i32,
+            protocol: protocol as i32,
+            cidr: cidr.to_string(),
+            port_start: ports.map(|(start, _)| start),
+            port_end: ports.map(|(_, end)| end),
+            rule_id: String::new(),
+        }
+    }
+
+    #[test]
+    fn vm_ruleset_dispatches_through_address_sets() {
+        let interfaces = vec![VmFirewallInterface {
+            bridge_name: "qbr0".into(),
+            ip: "10.0.0.5".into(),
+            ip6: "fd00::5".into(),
+            restrict_egress: false,
+            rules: vec![
+                rule(
+                    FirewallDirection::Ingress,
+                    FirewallProtocol::Tcp,
+                    "10.0.0.0/8",
+                    Some((22, 22)),
+                ),
+                rule(FirewallDirection::Ingress, FirewallProtocol::Icmp, "", None),
+            ],
+        }];
+
+        let script =
+            vm_firewall_ruleset("7f1c2a9e-0000-4000-8000-000000000001", &interfaces, false)
+                .unwrap();
+        let vm = "vm_7f1c2a9e000040008000000000000001";
+
+        assert!(script.contains(&format!(
+            "add element inet qarax {vm}_addrs4 {{ \"qbr0\" . 10.0.0.5 }}"
+        )));
+        assert!(script.contains(&format!(
+            "add rule inet qarax {vm} oifname . ip6 daddr @{vm}_addrs6 jump {vm}_in"
+        )));
+        assert!(script.contains(&format!(
+            "add rule inet qarax {vm}_in ip saddr 10.0.0.0/8 tcp dport 22 accept"
+        )));
+        assert!(script.contains(&format!(
+            "add rule inet qarax {vm}_in meta l4proto {{ icmp, ipv6-icmp }} accept"
+        )));
+        assert!(script.ends_with(&format!("add rule inet qarax {vm}_out accept\n")));
+    }
+
+    #[test]
+    fn denied_packets_are_logged_and_rules_tagged_for_counters() {
+        let mut tagged = rule(
+            FirewallDirection::Egress,
+            FirewallProtocol::Udp,
+            "10.0.0.9/32",
+            Some((53, 53)),
+        );
+        tagged.rule_id = "5f0e7c1a-0000-4000-8000-00000000000a".into();
+        let interfaces = vec![VmFirewallInterface {
+            bridge_name: "qbr0".into(),
+            ip: "10.0.0.5".into(),
+            ip6: String::new(),
+            restrict_egress: true,
+            rules: vec![tagged],
+        }];
+
+        let script =
+            vm_firewall_ruleset("7f1c2a9e-0000-4000-8000-000000000001", &interfaces, true).unwrap();
+        let vm = "vm_7f1c2a9e000040008000000000000001";
+
+        assert!(script.contains(&format!(
+            "add rule inet qarax {vm}_out ip daddr 10.0.0.9/32 udp dport 53 counter accept \
+             comment \"qarax:5f0e7c1a-0000-4000-8000-00000000000a\""
+        )));
+        assert!(script.contains(&format!(
+            "add rule inet qarax {vm}_in limit rate 10/minute burst 5 packets \
+             log prefix \"qxd:7f1c2a9e00004000:in \""
+        )));
+        assert!(script.ends_with(&format!(
+            "add rule inet qarax {vm}_out counter drop comment \"qarax:default-drop\"\n"
+        )));
+
+        let listing = r#"{"nftables": [
+            {"metainfo": {"json_schema_version": 1}},
+            {"chain": {"family": "inet", "table": "qarax", "name": "vm_x_out"}},
+            {"rule": {"chain": "vm_x_out", "expr": [
+                {"match": {"op": "==", "left": {"ct": {"key": "state"}}, "right": ["established"]}},
+                {"accept": null}]}},
+            {"rule": {"chain": "vm_x_out", "comment": "qarax:r1", "expr": [
+                {"counter": {"packets": 7, "bytes": 420}}, {"accept": null}]}},
+            {"rule": {"chain": "vm_x_out", "comment": "qarax:default-drop", "expr": [
+                {"counter": {"packets": 2, "bytes": 120}}, {"drop": null}]}}
+        ]}"#;
+        let counters = rule_counters(listing, FirewallDirection::Egress).unwrap();
+        let summary: Vec<(&str, u64)> = counters
+            .iter()
+            .map(|counter| (counter.rule_id.as_str(), counter.packets))
+            .collect();
+        assert_eq!(summary, [("r1", 7), ("", 2)]);
+    }
+
+    #[test]
+    fn port_security_pins_taps_to_bound_addresses() {
+        let mac = "02:aa:00:00:00:05".to_string();
+        let port = TapPortSecurity {
+            tap: "qt24b6061en0".into(),
+            mac: mac.clone(),
+            bindings: vec![
+                (mac.clone(), "10.0.0.5/32".into()),
+                (mac.clone(), "fd00::a8:ff:fe00:5/128".into()),
+                ("00:00:5e:00:01:07".into(), "10.0.0.100/32".into()),
+            ],
+        };
+        let script = vm_port_security_ruleset("vm-1", &[port]).unwrap();
+        let prefix = "add rule bridge qarax vm_vm1_spoof iifname \"qt24b6061en0\"";
+
+        assert!(script.contains(
+            "add chain bridge qarax vm_vm1_spoof { type filter hook prerouting priority filter; policy accept; }"
+        ));
+        assert!(script.contains(&format!(
+            "{prefix} ether saddr {mac} arp saddr ether {mac} arp saddr ip 10.0.0.5/32 accept"
+        )));
+        assert!(script.contains(&format!(
+            "{prefix} ether saddr 00:00:5e:00:01:07 ip saddr 10.0.0.100/32 accept"
+        )));
+        assert!(script.contains(&format!(
+            "{prefix} ether saddr {mac} ip6 saddr fd00::a8:ff:fe00:5/128 accept"
+        )));
+        assert!(script.contains(&format!(
+            "{prefix} icmpv6 type nd-neighbor-advert icmpv6 taddr != {{ fd00::a8:ff:fe00:5/128, fe80::/10 }} drop"
+        )));
+
+        // Router advertisements are dropped before any address is accepted,
+        // and everything unmatched is dropped last.
+        let lines: Vec<&str> = script.lines().collect();
+        let ra = lines
+            .iter()
+            .position(|line| line.contains("nd-router-advert"));
+        let first_accept = lines.iter().position(|line| line.ends_with("accept"));
+        assert!(ra < first_accept);
+        assert_eq!(lines.last().unwrap(), &format!("{prefix} drop"));
+    }
+
+    #[test]
+    fn isolation_ruleset_skips_ipv6_on_single_stack_bridges() {
+        let blocked = vec!["10.1.0.0/24".to_string(), "fd00:1::/64".to_string()];
+        let script = isolation_ruleset("qbr0", false, &blocked, &[]).unwrap();
+
+        assert!(script.contains("add element inet qarax blocked4_qbr0 { 10.1.0.0/24 }"));
+        assert!(!script.contains("fd00:1::/64"));
+        assert!(script.contains(
+            "add rule inet qarax iso_qbr0 iifname \"qbr0\" ip daddr @blocked4_qbr0 drop"
+        ));
+    }
+
+    #[test]
+    fn vm_nat_ruleset_translates_floating_ips_and_forwards() {
+        let vm_ip = Ipv4Addr::new(10, 0, 0, 5);
+        let floating_ips = [FloatingIpNat {
+            floating_ip: Ipv4Addr::new(203, 0, 113, 5),
+            vm_ip,
+        }];
+        let port_forwards = [PortForwardNat {
+            protocol: "tcp",
+            host_port: 8080,
+            vm_ip,
+            vm_port: 80,
+        }];
+
+        let script = vm_nat_ruleset(
+            "7f1c2a9e-0000-4000-8000-000000000001",
+            "eth0",
+            &floating_ips,
+            &port_forwards,
+        )
+        .unwrap();
+        let vm = "vm_7f1c2a9e000040008000000000000001";
+
+        assert!(script.contains(&format!(
+            "add rule inet qarax {vm}_dnat ip daddr 203.0.113.5 dnat ip to 10.0.0.5"
+        )));
+        assert!(script.contains(&format!(
+            "add rule inet qarax {vm}_snat oifname \"eth0\" ip saddr 10.0.0.5 snat ip to 203.0.113.5"
+        )));
+        assert!(script.contains(&format!(
+            "add rule inet qarax {vm}_dnat fib daddr type local tcp dport 8080 dnat ip to 10.0.0.5:80"
+        )));
+
+        let listing = "\
+table inet qarax {
+\tchain vm_x_dnat {
+\t\ttype nat hook prerouting priority dstnat; policy accept;
+\t\tip daddr 203.0.113.5 dnat ip to 10.0.0.5
+\t\tfib daddr type local tcp dport 8080 dnat ip to 10.0.0.5:80
+\t}
+}
+";
+        assert_eq!(
+            dnat_floating_ips(listing),
+            vec![Ipv4Addr::new(203, 0, 113, 5)]
+        );
+    }
+
+    #[test]
+    fn quote_iface_rejects_quotes() {
+        assert!(quote_iface("br\"0").is_err());
+        assert_eq!(quote_iface("qbr0").unwrap(), "\"qbr0\"");
+    }
+}
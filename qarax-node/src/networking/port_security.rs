@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use tracing::{info, warn};
+
+use super::firewall;
+use crate::rpc::node::VmPortSecurity;
+
+/// Anti-spoofing for one TAP: frames the guest sends through it must match
+/// one of `bindings` as their source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TapPortSecurity {
+    pub tap: String,
+    /// The NIC's own MAC, the only one allowed for DHCP discovery, ARP probes
+    /// and link-local IPv6.
+    pub mac: String,
+    /// `(mac, cidr)` source pairs: the NIC's addresses as host prefixes, then
+    /// its allowed address pairs.
+    pub bindings: Vec<(String, String)>,
+}
+
+/// Pin each of a VM's NICs to its MAC and addresses. `taps` maps a guest MAC
+/// (lowercase) to the TAP that carries it on this host; a NIC without one,
+/// such as a hotplugged NIC, is left unfiltered.
+pub async fn sync_vm_port_security(
+    vm_id: &str,
+    ports: &[VmPortSecurity],
+    taps: &HashMap<String, String>,
+) -> Result<()> {
+    let mut resolved = Vec::with_capacity(ports.len());
+    for port in ports {
+        let mac = port.mac.to_ascii_lowercase();
+        let Some(tap) = taps.get(&mac) else {
+            warn!("No TAP found for NIC {mac} of VM {vm_id}; it is not protected against spoofing");
+            continue;
+        };
+        resolved.push(tap_port_security(tap, mac, port)?);
+    }
+
+    if resolved.is_empty() {
+        return firewall::teardown_vm_port_security(vm_id).await;
+    }
+    firewall::sync_vm_port_security(vm_id, &resolved).await?;
+    info!(
+        "Synced anti-spoofing for VM {vm_id} on {} TAP(s)",
+        resolved.len()
+    );
+    Ok(())
+}
+
+fn tap_port_security(tap: &str, mac: String, port: &VmPortSecurity) -> Result<TapPortSecurity> {
+    super::validate_iface_name(tap)?;
+    super::validate_mac(&mac)?;
+
+    let mut bindings = Vec::new();
+    for address in &port.addresses {
+        let cidr = if address.contains(':') {
+            super::validate_ipv6_address(address)?;
+            format!("{address}/128")
+        } else {
+            super::validate_ipv4_address(address)?;
+            format!("{address}/32")
+        };
+        bindings.push((mac.clone(), cidr));
+    }
+    for pair in &port.allowed_address_pairs {
+        if pair.cidr.contains(':') {
+            super::validate_ipv6_cidr(&pair.cidr)?;
+        } else {
+            super::validate_ipv4_cidr(&pair.cidr)?;
+        }
+        let pair_mac = if pair.mac.is_empty() {
+            mac.clone()
+        } else {
+            let pair_mac = pair.mac.to_ascii_lowercase();
+            super::validate_mac(&pair_mac)?;
+            pair_mac
+        };
+        bindings.push((pair_mac, pair.cidr.clone()));
+    }
+
+    Ok(TapPortSecurity {
+        tap: tap.to_string(),
+        mac,
+        bindings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::node::AllowedAddressPair;
+
+    #[test]
+    fn ports_bind_addresses_and_pairs_to_macs() {
+        let port = VmPortSecurity {
+            mac: "02:AA:00:00:00:05".into(),
+            addresses: vec!["10.0.0.5".into(), "fd00::a8:ff:fe00:5".into()],
+            allowed_address_pairs: vec![
+                AllowedAddressPair {
+                    cidr: "10.0.0.100/32".into(),
+                    mac: String::new(),
+                },
+                AllowedAddressPair {
+                    cidr: "10.0.0.101/32".into(),
+                    mac: "00:00:5E:00:01:07".into(),
+                },
+            ],
+        };
+        let resolved =
+            tap_port_security("qt24b6061en0", port.mac.to_ascii_lowercase(), &port).unwrap();
+
+        assert_eq!(resolved.mac, "02:aa:00:00:00:05");
+        assert_eq!(
+            resolved.bindings,
+            vec![
+                ("02:aa:00:00:00:05".into(), "10.0.0.5/32".into()),
+                ("02:aa:00:00:00:05".into(), "fd00::a8:ff:fe00:5/128".into()),
+                ("02:aa:00:00:00:05".into(), "10.0.0.100/32".into()),
+                ("00:00:5e:00:01:07".into(), "10.0.0.101/32".into()),
+            ]
+        );
+
+        let bad = VmPortSecurity {
+            addresses: vec!["10.0.0.5; drop".into()],
+            ..port
+        };
+        assert!(tap_port_security("qt24b6061en0", "02:aa:00:00:00:05".into(), &bad).is_err());
+    }
+}
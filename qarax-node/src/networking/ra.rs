@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::net::{Ipv6Addr, SocketAddrV6};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+const ICMPV6_ROUTER_SOLICITATION: u8 = 133;
+const ICMPV6_ROUTER_ADVERTISEMENT: u8 = 134;
+const OPTION_PREFIX_INFORMATION: u8 = 3;
+/// Unsolicited advertisements go out a bit more often than RFC 4861's
+/// default MinRtrAdvInterval, so a guest that missed its solicitation still
+/// configures quickly.
+const ADVERTISE_INTERVAL: Duration = Duration::from_secs(180);
+const ROUTER_LIFETIME_SECS: u16 = 1800;
+const PREFIX_VALID_LIFETIME_SECS: u32 = 86400;
+const PREFIX_PREFERRED_LIFETIME_SECS: u32 = 14400;
+const ALL_NODES: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+const ALL_ROUTERS: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 2);
+
+static ADVERTISERS: LazyLock<Mutex<HashMap<String, JoinHandle<()>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Start sending router advertisements for `prefix` (a /64) on a bridge, so
+/// guests pick a SLAAC address and a default route through the bridge.
+pub async fn start_router_advertisements(bridge: &str, prefix: &str) -> Result<()> {
+    super::validate_iface_name(bridge)?;
+    super::validate_ipv6_cidr(prefix)?;
+    let (prefix_ip, prefix_len) = prefix
+        .split_once('/')
+        .map(|(ip, len)| (ip.parse::<Ipv6Addr>(), len.parse::<u8>()))
+        .ok_or_else(|| anyhow::anyhow!("Invalid prefix {prefix:?}"))?;
+    let (prefix_ip, prefix_len) = (prefix_ip?, prefix_len?);
+    if prefix_len != 64 {
+        anyhow::bail!("SLAAC needs a /64 prefix, got {prefix}");
+    }
+    info!("Starting router advertisements on {bridge} for {prefix}");
+
+    let ifindex = interface_index(bridge).await?;
+
+    // Same locking as the DHCP server: stop + bind + insert happen together.
+    let mut advertisers = ADVERTISERS.lock().unwrap();
+    if let Some(handle) = advertisers.remove(bridge) {
+        handle.abort();
+    }
+
+    let socket = create_icmpv6_socket(bridge, ifindex)?;
+    let packet = router_advertisement(prefix_ip, prefix_len);
+    let bridge_name = bridge.to_string();
+    let handle = tokio::spawn(async move {
+        if let Err(e) = run_advertiser(socket, ifindex, &packet, &bridge_name).await {
+            tracing::error!("Router advertiser for {bridge_name} exited with error: {e}");
+        }
+    });
+
+    advertisers.insert(bridge.to_string(), handle);
+    Ok(())
+}
+
+/// Stop sending router advertisements on a bridge.
+pub async fn stop_router_advertisements(bridge: &str) -> Result<()> {
+    if let Some(handle) = ADVERTISERS.lock().unwrap().remove(bridge) {
+        info!("Stopping router advertisements on {bridge}");
+        handle.abort();
+    }
+    Ok(())
+}
+
+async fn interface_index(name: &str) -> Result<u32> {
+    let raw = tokio::fs::read_to_string(format!("/sys/class/net/{name}/ifindex"))
+        .await
+        .with_context(|| format!("Failed to read ifindex of {name}"))?;
+    raw.trim()
+        .parse()
+        .with_context(|| format!("Invalid ifindex for {name}: {raw:?}"))
+}
+
+fn create_icmpv6_socket(bridge: &str, ifindex: u32) -> Result<UdpSocket> {
+    let socket = socket2::Socket::new(
+        socket2::Domain::IPV6,
+        socket2::Type::RAW,
+        Some(socket2::Protocol::ICMPV6),
+    )
+    .context("Failed to create ICMPv6 socket")?;
+
+    socket
+        .bind_device(Some(bridge.as_bytes()))
+        .context("Failed to bind socket to bridge interface")?;
+    // RFC 4861: hosts drop neighbour discovery messages with a hop limit below 255.
+    socket
+        .set_multicast_hops_v6(255)
+        .context("Failed to set multicast hop limit")?;
+    socket
+        .set_multicast_if_v6(ifindex)
+        .context("Failed to set multicast interface")?;
+    socket
+        .join_multicast_v6(&ALL_ROUTERS, ifindex)
+        .context("Failed to join all-routers group")?;
+    socket
+        .set_nonblocking(true)
+        .context("Failed to set socket non-blocking")?;
+
+    // tokio has no raw socket type; its UdpSocket only needs recvfrom/sendto
+    // on the descriptor, which work the same for ICMPv6.
+    UdpSocket::from_std(std::net::UdpSocket::from(socket)).context("Failed to create tokio socket")
+}
+
+async fn run_advertiser(
+    socket: UdpSocket,
+    ifindex: u32,
+    packet: &[u8],
+    bridge: &str,
+) -> Result<()> {
+    let all_nodes = SocketAddrV6::new(ALL_NODES, 0, 0, ifindex);
+    let mut ticker = tokio::time::interval(ADVERTISE_INTERVAL);
+    let mut buf = [0u8; 1500];
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            received = socket.recv_from(&mut buf) => {
+                let (len, src) = received?;
+                if len == 0 || buf[0] != ICMPV6_ROUTER_SOLICITATION {
+                    continue;
+                }
+                debug!("Router solicitation on {bridge} from {src}");
+            }
+        }
+
+        // The kernel fills in the ICMPv6 checksum on raw ICMPv6 sockets. Until
+        // the bridge has a link-local address sending fails; the next tick retries.
+        if let Err(e) = socket.send_to(packet, all_nodes).await {
+            warn!("Failed to send router advertisement on {bridge}: {e}");
+        }
+    }
+}
+
+/// Build a router advertisement carrying one autonomous, on-link prefix.
+fn router_advertisement(prefix: Ipv6Addr, prefix_len: u8) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(48);
+    packet.push(ICMPV6_ROUTER_ADVERTISEMENT);
+    packet.push(0); // code
+    packet.extend_from_slice(&[0, 0]); // checksum
+    packet.push(64); // current hop limit advertised to hosts
+    packet.push(0); // no DHCPv6 (M/O flags clear)
+    packet.extend_from_slice(&ROUTER_LIFETIME_SECS.to_be_bytes());
+    packet.extend_from_slice(&0u32.to_be_bytes()); // reachable time
+    packet.extend_from_slice(&0u32.to_be_bytes()); // retrans timer
+
+    packet.push(OPTION_PREFIX_INFORMATION);
+    packet.push(4); // length in units of 8 bytes
+    packet.push(prefix_len);
+    packet.push(0xc0); // on-link + autonomous
+    packet.extend_from_slice(&PREFIX_VALID_LIFETIME_SECS.to_be_bytes());
+    packet.extend_from_slice(&PREFIX_PREFERRED_LIFETIME_SECS.to_be_bytes());
+    packet.extend_from_slice(&0u32.to_be_bytes()); // reserved
+    packet.extend_from_slice(&prefix.octets());
+    packet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn router_advertisement_carries_the_prefix() {
+        let prefix: Ipv6Addr = "fd00:10::".parse().unwrap();
+        let packet = router_advertisement(prefix, 64);
+
+        assert_eq!(packet.len(), 48);
+        assert_eq!(packet[0], ICMPV6_ROUTER_ADVERTISEMENT);
+        assert_eq!(
+            u16::from_be_bytes([packet[6], packet[7]]),
+            ROUTER_LIFETIME_SECS
+        );
+        assert_eq!(&packet[16..19], &[OPTION_PREFIX_INFORMATION, 4, 64]);
+        assert_eq!(packet[19], 0xc0);
+        assert_eq!(&packet[32..48], &prefix.octets());
+    }
+}
@@ -2,9 +2,12 @@ use std::collections::{BTreeMap, BTreeSet, HashSet};
 
 use anyhow::{Context, Result};
 
-use crate::rpc::node::VpcOverlayConfig;
+use crate::rpc::node::{StretchedMacEntry, SyncStretchedNetworkRequest, VpcOverlayConfig};
 
 const QARAX_VXLAN_PREFIX: &str = "qvx";
+/// Stretched-network VXLAN ports; kept apart from `qvx` so VPC overlay sync
+/// never garbage-collects them.
+const STRETCHED_VXLAN_PREFIX: &str = "qst";
 const VXLAN_FDB_MAC: &str = "00:00:00:00:00:00";
 
 pub async fn sync_vpc_overlays(overlays: &[VpcOverlayConfig]) -> Result<()> {
@@ -39,9 +42,20 @@ async fn reconcile_overlay(overlay: &VpcOverlayConfig) -> Result<()> {
         super::validate_ipv4_address(&route.via_ip)?;
     }
 
-    if overlay_needs_recreate(overlay).await? {
+    if vxlan_needs_recreate(
+        &overlay.interface_name,
+        overlay.vni,
+        &overlay.local_underlay_ip,
+    )
+    .await?
+    {
         delete_interface(&overlay.interface_name).await?;
-        create_interface(overlay).await?;
+        create_interface(
+            &overlay.interface_name,
+            overlay.vni,
+            &overlay.local_underlay_ip,
+        )
+        .await?;
     }
 
     sync_interface_address(&overlay.interface_name, &overlay.local_overlay_cidr).await?;
@@ -56,6 +70,91 @@ async fn reconcile_overlay(overlay: &VpcOverlayConfig) -> Result<()> {
     Ok(())
 }
 
+/// Join a stretched network's bridge to its VXLAN segment and point remote VM
+/// MACs at the hosts they run on. Only the host picked by the control plane
+/// serves DHCP, so every guest on the segment leases from one table.
+pub async fn sync_stretched_network(req: &SyncStretchedNetworkRequest) -> Result<()> {
+    super::validate_iface_name(&req.bridge_name)?;
+    super::validate_iface_name(&req.interface_name)?;
+    if !req.interface_name.starts_with(STRETCHED_VXLAN_PREFIX) {
+        anyhow::bail!(
+            "Stretched network interface {:?} must start with {STRETCHED_VXLAN_PREFIX:?}",
+            req.interface_name
+        );
+    }
+    super::validate_ipv4_address(&req.local_underlay_ip)?;
+    for peer_ip in &req.peer_underlay_ips {
+        super::validate_ipv4_address(peer_ip)?;
+    }
+    for entry in &req.remote_macs {
+        super::validate_mac(&entry.mac)?;
+        super::validate_ipv4_address(&entry.underlay_ip)?;
+    }
+
+    if vxlan_needs_recreate(&req.interface_name, req.vni, &req.local_underlay_ip).await? {
+        delete_interface(&req.interface_name).await?;
+        create_interface(&req.interface_name, req.vni, &req.local_underlay_ip).await?;
+    }
+
+    run_cmd(
+        "ip",
+        &[
+            "link",
+            "set",
+            "dev",
+            &req.interface_name,
+            "master",
+            &req.bridge_name,
+        ],
+    )
+    .await
+    .with_context(|| {
+        format!(
+            "Failed to attach {} to bridge {}",
+            req.interface_name, req.bridge_name
+        )
+    })?;
+    run_cmd("ip", &["link", "set", "dev", &req.interface_name, "up"])
+        .await
+        .with_context(|| format!("Failed to bring up {}", req.interface_name))?;
+
+    sync_fdb_entries(&req.interface_name, &req.peer_underlay_ips).await?;
+    sync_mac_entries(&req.interface_name, &req.remote_macs).await?;
+
+    if !req.serve_dhcp {
+        return super::dhcp::stop_dhcp_server(&req.bridge_name).await;
+    }
+    // The network's DHCP settings do not change while it is attached, so
+    // leave a running server alone rather than rebinding on every sync.
+    if !super::dhcp::is_dhcp_server_running(&req.bridge_name) {
+        super::dhcp::start_dhcp_server(
+            &req.bridge_name,
+            &req.subnet,
+            &req.dhcp_range_start,
+            &req.dhcp_range_end,
+            &req.gateway,
+            &req.dns,
+            req.dhcp_options.as_ref(),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Delete the stretched-network VXLAN ports of a bridge before it is removed.
+pub async fn remove_stretched_ports(bridge: &str) -> Result<()> {
+    super::validate_iface_name(bridge)?;
+    let output = run_cmd_capture("ip", &["-o", "link", "show", "master", bridge]).await?;
+    for iface in output.lines().filter_map(|line| {
+        let (_, rest) = line.split_once(':')?;
+        let iface = rest.trim().split([':', '@']).next()?.trim();
+        iface.starts_with(STRETCHED_VXLAN_PREFIX).then_some(iface)
+    }) {
+        delete_interface(iface).await?;
+    }
+    Ok(())
+}
+
 async fn list_managed_interfaces() -> Result<Vec<String>> {
     let output = run_cmd_capture("ip", &["-o", "link", "show"]).await?;
     Ok(output
@@ -85,13 +184,8 @@ async fn delete_interface(name: &str) -> Result<()> {
     }
 }
 
-async fn overlay_needs_recreate(overlay: &VpcOverlayConfig) -> Result<bool> {
-    let output = match run_cmd_capture(
-        "ip",
-        &["-d", "link", "show", "dev", &overlay.interface_name],
-    )
-    .await
-    {
+async fn vxlan_needs_recreate(interface_name: &str, vni: u32, local_ip: &str) -> Result<bool> {
+    let output = match run_cmd_capture("ip", &["-d", "link", "show", "dev", interface_name]).await {
         Ok(output) => output,
         Err(error)
             if error.to_string().contains("Cannot find device")
@@ -102,40 +196,35 @@ async fn overlay_needs_recreate(overlay: &VpcOverlayConfig) -> Result<bool> {
         Err(error) => return Err(error),
     };
 
-    let expected_vni = format!("vxlan id {}", overlay.vni);
-    let expected_local = format!("local {}", overlay.local_underlay_ip);
+    let expected_vni = format!("vxlan id {vni}");
+    let expected_local = format!("local {local_ip}");
     Ok(!(output.contains(&expected_vni)
         && output.contains(&expected_local)
         && output.contains("dstport 4789")
         && output.contains("nolearning")))
 }
 
-async fn create_interface(overlay: &VpcOverlayConfig) -> Result<()> {
+async fn create_interface(interface_name: &str, vni: u32, local_ip: &str) -> Result<()> {
     run_cmd(
         "ip",
         &[
             "link",
             "add",
             "dev",
-            &overlay.interface_name,
+            interface_name,
             "type",
             "vxlan",
             "id",
-            &overlay.vni.to_string(),
+            &vni.to_string(),
             "dstport",
             "4789",
             "local",
-            &overlay.local_underlay_ip,
+            local_ip,
             "nolearning",
         ],
     )
     .await
-    .with_context(|| {
-        format!(
-            "Failed to create VXLAN interface {}",
-            overlay.interface_name
-        )
-    })
+    .with_context(|| format!("Failed to create VXLAN interface {interface_name}"))
 }
 
 async fn sync_interface_address(interface_name: &str, local_overlay_cidr: &str) -> Result<()> {
@@ -238,6 +327,68 @@ async fn sync_fdb_entries(interface_name: &str, peer_underlay_ips: &[String]) ->
     Ok(())
 }
 
+async fn sync_mac_entries(interface_name: &str, entries: &[StretchedMacEntry]) -> Result<()> {
+    let desired: BTreeMap<String, &str> = entries
+        .iter()
+        .map(|entry| (entry.mac.to_ascii_lowercase(), entry.underlay_ip.as_str()))
+        .collect();
+    let existing = list_fdb_mac_entries(interface_name).await?;
+
+    for mac in existing.keys().filter(|mac| !desired.contains_key(*mac)) {
+        run_cmd(
+            "bridge",
+            &["fdb", "del", mac, "dev", interface_name, "self"],
+        )
+        .await
+        .with_context(|| format!("Failed to remove MAC {mac} from {interface_name}"))?;
+    }
+
+    for (mac, underlay_ip) in &desired {
+        if existing.get(mac).map(String::as_str) == Some(*underlay_ip) {
+            continue;
+        }
+        run_cmd(
+            "bridge",
+            &[
+                "fdb",
+                "replace",
+                mac,
+                "dev",
+                interface_name,
+                "dst",
+                underlay_ip,
+                "self",
+                "permanent",
+            ],
+        )
+        .await
+        .with_context(|| {
+            format!("Failed to point MAC {mac} at {underlay_ip} on {interface_name}")
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Unicast MAC → remote VTEP entries on a VXLAN device (flood entries excluded).
+async fn list_fdb_mac_entries(interface_name: &str) -> Result<BTreeMap<String, String>> {
+    let output = run_cmd_capture("bridge", &["fdb", "show", "dev", interface_name]).await?;
+    Ok(output
+        .lines()
+        .filter(|line| !line.starts_with(VXLAN_FDB_MAC) && line.contains(" self"))
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let mac = parts.next()?.to_string();
+            while let Some(part) = parts.next() {
+                if part == "dst" {
+                    return parts.next().map(|dst| (mac, dst.to_string()));
+                }
+            }
+            None
+        })
+        .collect())
+}
+
 async fn list_fdb_peer_ips(interface_name: &str) -> Result<BTreeSet<String>> {
     let output = run_cmd_capture("bridge", &["fdb", "show", "dev", interface_name]).await?;
     Ok(output
@@ -0,0 +1,345 @@
+//! Local cache of OverlayBD image layers.
+//!
+//! Every layer gets a directory under `<cache_dir>/layers/`, which is handed
+//! to overlaybd-tcmu as the layer's `dir`. overlaybd reads the layer from
+//! `overlaybd.commit` there when present; otherwise it fetches blocks from the
+//! registry and completes the file in the background. Pre-warming writes the
+//! same file up front.
+//!
+//! An index (`cache-index.json`) remembers which images use which layers and
+//! when each image was last mounted or pre-warmed. When a size cap is set, the
+//! least recently used images whose layers no mounted VM needs are evicted.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::storage::dir_usage;
+
+/// File overlaybd-tcmu reads a fully downloaded layer from.
+pub const LAYER_COMMIT_FILE: &str = "overlaybd.commit";
+
+#[derive(Serialize, Deserialize, Default)]
+struct CacheIndex {
+    /// Keyed by image reference.
+    images: BTreeMap<String, CachedImage>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CachedImage {
+    registry_url: String,
+    layers: Vec<String>,
+    /// Unix seconds.
+    last_used: u64,
+}
+
+/// Cache footprint of one image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageUsage {
+    pub image_ref: String,
+    pub registry_url: String,
+    pub layers: usize,
+    pub cached_layers: usize,
+    pub cached_bytes: u64,
+    pub last_used: u64,
+    /// A mounted VM uses at least one of the image's layers.
+    pub in_use: bool,
+}
+
+/// Node-wide cache counters. Hits and misses count layers at mount time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub used_bytes: u64,
+    pub max_bytes: Option<u64>,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+pub struct LayerCache {
+    layers_dir: PathBuf,
+    index_path: PathBuf,
+    max_bytes: Option<u64>,
+    index: Mutex<CacheIndex>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+impl LayerCache {
+    /// Load the cache index under `cache_dir`. A missing or unreadable index
+    /// starts empty; layer files already on disk are picked up again the next
+    /// time an image that uses them is mounted.
+    pub fn new(cache_dir: &Path, max_bytes: Option<u64>) -> Self {
+        let index_path = cache_dir.join("cache-index.json");
+        let index = match std::fs::read(&index_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                warn!("Ignoring corrupt OverlayBD cache index: {}", e);
+                CacheIndex::default()
+            }),
+            Err(_) => CacheIndex::default(),
+        };
+        Self {
+            layers_dir: cache_dir.join("layers"),
+            index_path,
+            max_bytes,
+            index: Mutex::new(index),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    /// Directory holding one layer, e.g. `layers/sha256_<hex>`.
+    pub fn layer_dir(&self, digest: &str) -> PathBuf {
+        self.layers_dir.join(digest.replace(':', "_"))
+    }
+
+    pub async fn is_cached(&self, digest: &str) -> bool {
+        tokio::fs::try_exists(self.layer_dir(digest).join(LAYER_COMMIT_FILE))
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Create the layer directories overlaybd will read from and fill.
+    pub async fn prepare_layers(&self, digests: &[String]) -> std::io::Result<()> {
+        for digest in digests {
+            tokio::fs::create_dir_all(self.layer_dir(digest)).await?;
+        }
+        Ok(())
+    }
+
+    /// Record that a VM mounted `image_ref`, counting each layer already in
+    /// the cache as a hit and each one overlaybd still has to fetch as a miss.
+    pub async fn record_mount(&self, image_ref: &str, registry_url: &str, layers: &[String]) {
+        for digest in layers {
+            if self.is_cached(digest).await {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+            } else {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.touch(image_ref, registry_url, layers).await;
+    }
+
+    /// Mark `image_ref` as just used.
+    pub async fn touch(&self, image_ref: &str, registry_url: &str, layers: &[String]) {
+        let mut index = self.index.lock().await;
+        index.images.insert(
+            image_ref.to_string(),
+            CachedImage {
+                registry_url: registry_url.to_string(),
+                layers: layers.to_vec(),
+                last_used: now(),
+            },
+        );
+        self.save(&index).await;
+    }
+
+    pub async fn stats(&self) -> CacheStats {
+        CacheStats {
+            used_bytes: dir_usage(&self.layers_dir).await,
+            max_bytes: self.max_bytes,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Per-image footprint, most recently used first. Layers shared between
+    /// images count towards each of them.
+    pub async fn usage(&self, in_use_layers: &HashSet<String>) -> Vec<ImageUsage> {
+        let images: Vec<(String, CachedImage)> = {
+            let index = self.index.lock().await;
+            index
+                .images
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect()
+        };
+
+        let mut usage = Vec::with_capacity(images.len());
+        for (image_ref, image) in images {
+            let mut cached_layers = 0;
+            let mut cached_bytes = 0;
+            for digest in &image.layers {
+                if self.is_cached(digest).await {
+                    cached_layers += 1;
+                }
+                cached_bytes += dir_usage(&self.layer_dir(digest)).await;
+            }
+            usage.push(ImageUsage {
+                in_use: image.layers.iter().any(|l| in_use_layers.contains(l)),
+                image_ref,
+                registry_url: image.registry_url,
+                layers: image.layers.len(),
+                cached_layers,
+                cached_bytes,
+                last_used: image.last_used,
+            });
+        }
+        usage.sort_by(|a, b| b.last_used.cmp(&a.last_used));
+        usage
+    }
+
+    /// Evict least recently used images until the cache fits its cap. Images
+    /// with a layer in `in_use_layers` are kept, and so are the layers
+    /// themselves even when another evicted image shares them. Returns the
+    /// evicted image references.
+    pub async fn evict(&self, in_use_layers: &HashSet<String>) -> Vec<String> {
+        let Some(max_bytes) = self.max_bytes else {
+            return Vec::new();
+        };
+        let mut index = self.index.lock().await;
+        let mut used = dir_usage(&self.layers_dir).await;
+        if used <= max_bytes {
+            return Vec::new();
+        }
+
+        let mut candidates: Vec<(String, CachedImage)> = index
+            .images
+            .iter()
+            .filter(|(_, image)| !image.layers.iter().any(|l| in_use_layers.contains(l)))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        candidates.sort_by_key(|(_, image)| image.last_used);
+
+        let mut evicted = Vec::new();
+        for (image_ref, image) in candidates {
+            if used <= max_bytes {
+                break;
+            }
+            for digest in &image.layers {
+                if in_use_layers.contains(digest) {
+                    continue;
+                }
+                let dir = self.layer_dir(digest);
+                let size = dir_usage(&dir).await;
+                match tokio::fs::remove_dir_all(&dir).await {
+                    Ok(()) => used = used.saturating_sub(size),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => warn!("Failed to evict OverlayBD layer {}: {}", dir.display(), e),
+                }
+            }
+            index.images.remove(&image_ref);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+            info!("Evicted OverlayBD image {} from the cache", image_ref);
+            evicted.push(image_ref);
+        }
+
+        if used > max_bytes {
+            warn!(
+                "OverlayBD cache still holds {} bytes over its {} byte cap; the rest is in use",
+                used - max_bytes,
+                max_bytes
+            );
+        }
+        self.save(&index).await;
+        evicted
+    }
+
+    async fn save(&self, index: &CacheIndex) {
+        let result = match serde_json::to_vec_pretty(index) {
+            Ok(bytes) => {
+                let tmp = self.index_path.with_extension("json.tmp");
+                match tokio::fs::write(&tmp, bytes).await {
+                    Ok(()) => tokio::fs::rename(&tmp, &self.index_path).await,
+                    Err(e) => Err(e),
+                }
+            }
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            warn!("Failed to save OverlayBD cache index: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn cache_layer(cache: &LayerCache, digest: &str, bytes: usize) {
+        let dir = cache.layer_dir(digest);
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        tokio::fs::write(dir.join(LAYER_COMMIT_FILE), vec![1u8; bytes])
+            .await
+            .unwrap();
+    }
+
+    fn layers(digests: &[&str]) -> Vec<String> {
+        digests.iter().map(|d| d.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn counts_hits_and_misses_per_layer() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = LayerCache::new(dir.path(), None);
+        cache_layer(&cache, "sha256:aa", 4096).await;
+
+        cache
+            .record_mount(
+                "registry:5000/app:1",
+                "http://registry:5000",
+                &layers(&["sha256:aa", "sha256:bb"]),
+            )
+            .await;
+
+        let stats = cache.stats().await;
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        let usage = cache.usage(&HashSet::new()).await;
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].cached_layers, 1);
+        assert_eq!(usage[0].layers, 2);
+
+        // The index survives a restart.
+        let reopened = LayerCache::new(dir.path(), None);
+        assert_eq!(reopened.usage(&HashSet::new()).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn evicts_least_recently_used_images_not_in_use() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = LayerCache::new(dir.path(), Some(96 * 1024));
+        for (image, digest) in [
+            ("old", "sha256:01"),
+            ("busy", "sha256:02"),
+            ("new", "sha256:03"),
+        ] {
+            cache_layer(&cache, digest, 40 * 1024).await;
+            cache
+                .touch(image, "http://registry:5000", &layers(&[digest]))
+                .await;
+        }
+        {
+            // Make the order explicit rather than relying on the clock.
+            let mut index = cache.index.lock().await;
+            for (i, image) in ["old", "busy", "new"].iter().enumerate() {
+                index.images.get_mut(*image).unwrap().last_used = i as u64;
+            }
+        }
+
+        let in_use: HashSet<String> = layers(&["sha256:02"]).into_iter().collect();
+        let evicted = cache.evict(&in_use).await;
+
+        // Dropping "old" gets the cache under its cap, so "new" stays; "busy"
+        // is never a candidate because a VM has it mounted.
+        assert_eq!(evicted, vec!["old".to_string()]);
+        assert!(!cache.is_cached("sha256:01").await);
+        assert!(cache.is_cached("sha256:02").await);
+        assert!(cache.is_cached("sha256:03").await);
+        assert_eq!(cache.stats().await.evictions, 1);
+    }
+}
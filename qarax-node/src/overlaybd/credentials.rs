@@ -0,0 +1,128 @@
+//! Registry logins supplied by the control plane for a single import or
+//! preflight. Nothing is persisted on the node; the set lives for one request.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use oci_client::secrets::RegistryAuth;
+
+use crate::rpc::node::RegistryCredential;
+
+/// Logins keyed by normalized registry host.
+///
+/// `Debug` lists only the registries so a stray `{:?}` cannot leak a password.
+#[derive(Clone, Default)]
+pub struct RegistryCredentials {
+    logins: HashMap<String, (String, String)>,
+}
+
+impl fmt::Debug for RegistryCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut registries: Vec<&str> = self.logins.keys().map(String::as_str).collect();
+        registries.sort_unstable();
+        f.debug_struct("RegistryCredentials")
+            .field("registries", &registries)
+            .finish()
+    }
+}
+
+impl From<Vec<RegistryCredential>> for RegistryCredentials {
+    fn from(credentials: Vec<RegistryCredential>) -> Self {
+        let logins = credentials
+            .into_iter()
+            .filter(|c| !c.registry.trim().is_empty())
+            .map(|c| (normalize_registry(&c.registry), (c.username, c.password)))
+            .collect();
+        Self { logins }
+    }
+}
+
+impl RegistryCredentials {
+    /// Auth for requests to `registry`. oci-client answers a bearer challenge
+    /// by exchanging these for a token, so the same login covers both
+    /// basic-auth and token-auth registries.
+    pub fn auth_for(&self, registry: &str) -> RegistryAuth {
+        match self.logins.get(&normalize_registry(registry)) {
+            Some((username, password)) => RegistryAuth::Basic(username.clone(), password.clone()),
+            None => RegistryAuth::Anonymous,
+        }
+    }
+
+    /// Docker `config.json` holding the login for `registry`, for tools such
+    /// as the `convertor` that read credentials from `$DOCKER_CONFIG`.
+    pub fn docker_config(&self, registry: &str) -> Option<serde_json::Value> {
+        let registry = normalize_registry(registry);
+        self.logins.get(&registry).map(|(username, password)| {
+            let auth = openssl::base64::encode_block(format!("{username}:{password}").as_bytes());
+            serde_json::json!({ "auths": { registry: { "auth": auth } } })
+        })
+    }
+}
+
+/// Reduce a registry URL or host to the key logins are stored under. Docker
+/// Hub answers under several names; fold them into the one image refs use.
+pub fn normalize_registry(registry: &str) -> String {
+    let host = registry
+        .trim()
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .split('/')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match host.as_str() {
+        "index.docker.io" | "registry-1.docker.io" | "registry.hub.docker.com" => {
+            "docker.io".to_string()
+        }
+        _ => host,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credentials() -> RegistryCredentials {
+        RegistryCredentials::from(vec![
+            RegistryCredential {
+                registry: "https://GHCR.io/".into(),
+                username: "bot".into(),
+                password: "ghp_secret".into(),
+            },
+            RegistryCredential {
+                registry: "index.docker.io".into(),
+                username: "hub".into(),
+                password: "hub-token".into(),
+            },
+        ])
+    }
+
+    #[test]
+    fn matches_by_normalized_host() {
+        let creds = credentials();
+        assert!(matches!(
+            creds.auth_for("ghcr.io"),
+            RegistryAuth::Basic(user, pass) if user == "bot" && pass == "ghp_secret"
+        ));
+        assert!(matches!(
+            creds.auth_for("docker.io"),
+            RegistryAuth::Basic(user, _) if user == "hub"
+        ));
+        assert!(matches!(
+            creds.auth_for("registry:5000"),
+            RegistryAuth::Anonymous
+        ));
+        assert_eq!(
+            creds.docker_config("http://ghcr.io").unwrap()["auths"]["ghcr.io"]["auth"],
+            openssl::base64::encode_block(b"bot:ghp_secret")
+        );
+    }
+
+    #[test]
+    fn debug_output_omits_secrets() {
+        let rendered = format!("{:?}", credentials());
+        assert!(rendered.contains("ghcr.io"));
+        assert!(!rendered.contains("ghp_secret"));
+        assert!(!rendered.contains("hub-token"));
+    }
+}
@@ -0,0 +1,409 @@
+//! Garbage collection of converted images in an OverlayBD pool's registry.
+//!
+//! The control plane passes every image reference and digest it still needs.
+//! Manifests in the registry that none of them name are deleted through the
+//! registry API, by digest, which also drops every tag pointing at them.
+//!
+//! Deleting a manifest only unlinks its blobs; the registry frees the space on
+//! its next `garbage-collect` run. Reclaimed bytes therefore count the blobs of
+//! deleted manifests that no kept manifest shares.
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use super::manager::{OverlayBdError, build_target_ref, registry_host};
+
+const MANIFEST_ACCEPT: &str = "application/vnd.oci.image.manifest.v1+json, \
+     application/vnd.docker.distribution.manifest.v2+json";
+const CATALOG_PAGE_SIZE: usize = 1000;
+
+/// One manifest in the registry, with every tag that points at it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryImage {
+    pub repository: String,
+    pub tags: Vec<String>,
+    pub digest: String,
+    /// Config and layer blobs as `(digest, size)`.
+    pub blobs: Vec<(String, u64)>,
+}
+
+/// A manifest that nothing references any more.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GcCandidate {
+    pub repository: String,
+    pub tags: Vec<String>,
+    pub digest: String,
+    /// Bytes of blobs only unreferenced manifests use, counted once across
+    /// all candidates.
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GcReport {
+    pub candidates: Vec<GcCandidate>,
+    pub kept: usize,
+    pub reclaimed_bytes: u64,
+}
+
+/// Image references and digests the control plane still uses, normalised to
+/// `(repository, tag)` pairs in the pool registry.
+#[derive(Debug, Default)]
+pub struct KeepSet {
+    tags: HashSet<(String, String)>,
+    digests: HashSet<String>,
+}
+
+impl KeepSet {
+    pub fn new(registry_url: &str, refs: &[String], digests: &[String]) -> Self {
+        let mut keep = KeepSet {
+            tags: HashSet::new(),
+            digests: digests.iter().filter(|d| !d.is_empty()).cloned().collect(),
+        };
+        for image_ref in refs {
+            let (name, digest) = match image_ref.split_once('@') {
+                Some((name, digest)) => (name, Some(digest)),
+                None => (image_ref.as_str(), None),
+            };
+            if let Some(digest) = digest {
+                keep.digests.insert(digest.to_string());
+                continue;
+            }
+            if let Some(key) = repository_tag(name, registry_url) {
+                keep.tags.insert(key);
+            }
+        }
+        keep
+    }
+
+    fn keeps(&self, image: &RegistryImage) -> bool {
+        self.digests.contains(&image.digest)
+            || image
+                .tags
+                .iter()
+                .any(|tag| self.tags.contains(&(image.repository.clone(), tag.clone())))
+    }
+}
+
+/// Where `image_ref` lives in the pool registry, as `import_image` would have
+/// pushed it.
+fn repository_tag(image_ref: &str, registry_url: &str) -> Option<(String, String)> {
+    let target = build_target_ref(image_ref, registry_url).ok()?;
+    let path = target.strip_prefix(&format!("{}/", registry_host(registry_url)))?;
+    match path.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => {
+            Some((repository.to_string(), tag.to_string()))
+        }
+        _ => Some((path.to_string(), "latest".to_string())),
+    }
+}
+
+/// Split the registry's manifests into ones to delete and ones to keep.
+pub fn plan(images: &[RegistryImage], keep: &KeepSet) -> GcReport {
+    let (kept, unused): (Vec<_>, Vec<_>) = images.iter().partition(|image| keep.keeps(image));
+    let mut shared: HashSet<&str> = kept
+        .iter()
+        .flat_map(|image| image.blobs.iter().map(|(digest, _)| digest.as_str()))
+        .collect();
+
+    let mut report = GcReport {
+        kept: kept.len(),
+        ..Default::default()
+    };
+    for image in unused {
+        let mut size_bytes = 0;
+        for (digest, size) in &image.blobs {
+            if shared.insert(digest.as_str()) {
+                size_bytes += size;
+            }
+        }
+        report.reclaimed_bytes += size_bytes;
+        report.candidates.push(GcCandidate {
+            repository: image.repository.clone(),
+            tags: image.tags.clone(),
+            digest: image.digest.clone(),
+            size_bytes,
+        });
+    }
+    report
+}
+
+#[derive(Deserialize)]
+struct Catalog {
+    #[serde(default)]
+    repositories: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TagList {
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct Descriptor {
+    digest: String,
+    #[serde(default)]
+    size: u64,
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    config: Option<Descriptor>,
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+}
+
+/// Minimal registry v2 client for listing and deleting manifests.
+pub struct RegistryClient {
+    base: String,
+    http: reqwest::Client,
+}
+
+impl RegistryClient {
+    pub fn new(registry_url: &str) -> Self {
+        Self {
+            base: registry_url.trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    async fn get(
+        &self,
+        path: &str,
+        accept: Option<&str>,
+    ) -> Result<reqwest::Response, OverlayBdError> {
+        let url = format!("{}{}", self.base, path);
+        let mut request = self.http.get(&url);
+        if let Some(accept) = accept {
+            request = request.header(reqwest::header::ACCEPT, accept);
+        }
+        request
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| OverlayBdError::OciError(format!("GET {}: {}", url, e)))
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<T, OverlayBdError> {
+        let body =
+            self.get(path, None).await?.bytes().await.map_err(|e| {
+                OverlayBdError::OciError(format!("GET {}{}: {}", self.base, path, e))
+            })?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    async fn repositories(&self) -> Result<Vec<String>, OverlayBdError> {
+        let mut repositories: Vec<String> = Vec::new();
+        loop {
+            let mut path = format!("/v2/_catalog?n={}", CATALOG_PAGE_SIZE);
+            if let Some(last) = repositories.last() {
+                path.push_str(&format!("&last={}", last));
+            }
+            let page: Catalog = self.get_json(&path).await?;
+            let done = page.repositories.len() < CATALOG_PAGE_SIZE;
+            repositories.extend(page.repositories);
+            if done {
+                return Ok(repositories);
+            }
+        }
+    }
+
+    async fn tags(&self, repository: &str) -> Result<Vec<String>, OverlayBdError> {
+        let list: TagList = self
+            .get_json(&format!("/v2/{}/tags/list", repository))
+            .await?;
+        Ok(list.tags.unwrap_or_default())
+    }
+
+    /// Resolve a tag to its manifest digest and blobs.
+    async fn manifest(
+        &self,
+        repository: &str,
+        tag: &str,
+    ) -> Result<(String, Vec<(String, u64)>), OverlayBdError> {
+        let path = format!("/v2/{}/manifests/{}", repository, tag);
+        let response = self.get(&path, Some(MANIFEST_ACCEPT)).await?;
+        let header_digest = response
+            .headers()
+            .get("Docker-Content-Digest")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned);
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| OverlayBdError::OciError(format!("GET {}{}: {}", self.base, path, e)))?;
+        let digest = header_digest.unwrap_or_else(|| format!("sha256:{:x}", Sha256::digest(&body)));
+
+        // Image indexes have no layers of their own and count as empty.
+        let manifest: Manifest = serde_json::from_slice(&body)?;
+        let blobs = manifest
+            .config
+            .into_iter()
+            .chain(manifest.layers)
+            .map(|d| (d.digest, d.size))
+            .collect();
+        Ok((digest, blobs))
+    }
+
+    async fn delete_manifest(&self, repository: &str, digest: &str) -> Result<(), OverlayBdError> {
+        let url = format!("{}/v2/{}/manifests/{}", self.base, repository, digest);
+        let response = self
+            .http
+            .delete(&url)
+            .send()
+            .await
+            .map_err(|e| OverlayBdError::OciError(format!("DELETE {}: {}", url, e)))?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            reqwest::StatusCode::NOT_FOUND => Ok(()),
+            reqwest::StatusCode::METHOD_NOT_ALLOWED => Err(OverlayBdError::OciError(format!(
+                "registry {} does not allow deletes (set REGISTRY_STORAGE_DELETE_ENABLED=true)",
+                self.base
+            ))),
+            status => Err(OverlayBdError::OciError(format!(
+                "DELETE {}: HTTP {}",
+                url, status
+            ))),
+        }
+    }
+
+    /// Every tagged manifest in the registry, with tags grouped by digest.
+    pub async fn images(&self) -> Result<Vec<RegistryImage>, OverlayBdError> {
+        let mut images = Vec::new();
+        for repository in self.repositories().await? {
+            let mut by_digest: BTreeMap<String, RegistryImage> = BTreeMap::new();
+            for tag in self.tags(&repository).await? {
+                let (digest, blobs) = match self.manifest(&repository, &tag).await {
+                    Ok(manifest) => manifest,
+                    Err(e) => {
+                        warn!("Skipping {}:{} during GC: {}", repository, tag, e);
+                        continue;
+                    }
+                };
+                by_digest
+                    .entry(digest.clone())
+                    .or_insert_with(|| RegistryImage {
+                        repository: repository.clone(),
+                        tags: Vec::new(),
+                        digest,
+                        blobs,
+                    })
+                    .tags
+                    .push(tag);
+            }
+            images.extend(by_digest.into_values());
+        }
+        Ok(images)
+    }
+}
+
+/// Find the registry's unreferenced manifests and, unless `dry_run` is set,
+/// delete them.
+pub async fn collect(
+    registry_url: &str,
+    keep: &KeepSet,
+    dry_run: bool,
+) -> Result<GcReport, OverlayBdError> {
+    let client = RegistryClient::new(registry_url);
+    let images = client.images().await?;
+    let report = plan(&images, keep);
+    if !dry_run {
+        for candidate in &report.candidates {
+            client
+                .delete_manifest(&candidate.repository, &candidate.digest)
+                .await?;
+            info!(
+                "Deleted unreferenced OverlayBD image {}@{} ({} bytes)",
+                candidate.repository, candidate.digest, candidate.size_bytes
+            );
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGISTRY: &str = "http://registry:5000";
+
+    fn image(
+        repository: &str,
+        tags: &[&str],
+        digest: &str,
+        blobs: &[(&str, u64)],
+    ) -> RegistryImage {
+        RegistryImage {
+            repository: repository.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            digest: digest.to_string(),
+            blobs: blobs.iter().map(|(d, s)| (d.to_string(), *s)).collect(),
+        }
+    }
+
+    #[test]
+    fn keep_set_matches_source_and_imported_refs() {
+        let keep = KeepSet::new(
+            REGISTRY,
+            &[
+                "docker.io/library/ubuntu:22.04".to_string(),
+                "registry:5000/library/alpine".to_string(),
+                "registry:5000/tools/busybox@sha256:bb".to_string(),
+            ],
+            &["sha256:dd".to_string()],
+        );
+
+        assert!(keep.keeps(&image("library/ubuntu", &["22.04"], "sha256:aa", &[])));
+        assert!(keep.keeps(&image("library/alpine", &["latest"], "sha256:cc", &[])));
+        assert!(keep.keeps(&image("tools/busybox", &["1.36"], "sha256:bb", &[])));
+        assert!(keep.keeps(&image("tools/fedora", &["40"], "sha256:dd", &[])));
+        assert!(!keep.keeps(&image("library/ubuntu", &["24.04"], "sha256:ee", &[])));
+    }
+
+    #[test]
+    fn plan_counts_only_blobs_no_kept_image_shares() {
+        let images = vec![
+            image(
+                "library/ubuntu",
+                &["22.04"],
+                "sha256:kept",
+                &[("sha256:base", 100), ("sha256:cfg1", 1)],
+            ),
+            image(
+                "library/ubuntu",
+                &["23.04", "old"],
+                "sha256:gone1",
+                &[("sha256:base", 100), ("sha256:top", 50), ("sha256:cfg2", 1)],
+            ),
+            image(
+                "library/debian",
+                &["12"],
+                "sha256:gone2",
+                &[("sha256:top", 50), ("sha256:deb", 70)],
+            ),
+        ];
+        let keep = KeepSet::new(
+            REGISTRY,
+            &["docker.io/library/ubuntu:22.04".to_string()],
+            &[],
+        );
+
+        let report = plan(&images, &keep);
+
+        assert_eq!(report.kept, 1);
+        assert_eq!(report.reclaimed_bytes, 121);
+        let sizes: Vec<_> = report
+            .candidates
+            .iter()
+            .map(|c| (c.digest.as_str(), c.size_bytes))
+            .collect();
+        assert_eq!(sizes, vec![("sha256:gone1", 51), ("sha256:gone2", 70)]);
+        assert_eq!(report.candidates[0].tags, vec!["23.04", "old"]);
+    }
+}
@@ -16,11 +16,15 @@ use std::time::Duration;
 use oci_client::Reference;
 use oci_client::client::{Client, ClientConfig, ClientProtocol, Config, ImageLayer};
 use oci_client::secrets::RegistryAuth;
+use sha2::{Digest, Sha256};
 use thiserror::Error;
+use tokio::io::AsyncWriteExt;
 use tokio::sync::Mutex;
 use tracing::{info, warn};
 use uuid::Uuid;
 
+use super::cache::{CacheStats, ImageUsage, LAYER_COMMIT_FILE, LayerCache};
+use super::credentials::RegistryCredentials;
 use crate::image_preflight::{
     PreflightCheckResult, architecture_check, boot_mode_check, guest_command_check,
 };
@@ -29,9 +33,13 @@ use crate::oci_config::{OciImageConfig, OciImageConfigDetails};
 /// Path to the overlaybd-create binary installed by the overlaybd RPM.
 const OVERLAYBD_CREATE: &str = "/opt/overlaybd/bin/overlaybd-create";
 
-/// Virtual disk size presented to Cloud Hypervisor (sparse, so barely uses space).
-const DISK_SIZE_GB: u64 = 64;
-const DISK_SIZE_BYTES: u64 = DISK_SIZE_GB * 1024 * 1024 * 1024;
+/// Path to the overlaybd-commit binary installed by the overlaybd RPM.
+const OVERLAYBD_COMMIT: &str = "/opt/overlaybd/bin/overlaybd-commit";
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+/// Default virtual disk size presented to Cloud Hypervisor (sparse, so barely uses space).
+const DEFAULT_DISK_SIZE_BYTES: u64 = 64 * GIB;
 
 #[derive(Debug, Error)]
 pub enum OverlayBdError {
@@ -57,14 +65,40 @@ pub enum OverlayBdError {
     Json(#[from] serde_json::Error),
 }
 
+/// Outcome of `OverlayBdManager::resize_upper`.
+#[derive(Debug)]
+pub enum UpperResize {
+    /// The upper was never created; the next mount creates it at the new size.
+    NotCreated,
+    /// The writes were frozen and a fresh upper of the new size is in place.
+    Resized,
+    /// The writes were frozen but the old upper could not be replaced. The
+    /// frozen layer is still valid beneath it and must be recorded.
+    FrozenOnly(OverlayBdError),
+}
+
 /// Normalize a registry URL to a bare host[:port] string.
-fn registry_host(url: &str) -> String {
+pub(super) fn registry_host(url: &str) -> String {
     url.trim_start_matches("https://")
         .trim_start_matches("http://")
         .trim_end_matches('/')
         .to_string()
 }
 
+/// Writable upper layer stacked on an image when it is mounted.
+#[derive(Debug, Clone, Default)]
+pub struct UpperLayerSpec {
+    /// Persistent upper.data / upper.index paths. When unset, the upper is
+    /// ephemeral and lives in the per-VM config directory.
+    pub data_path: Option<String>,
+    pub index_path: Option<String>,
+    /// Virtual size of the device; defaults to 64 GiB.
+    pub size_bytes: Option<u64>,
+    /// Read-only local layers frozen from the upper by earlier resizes,
+    /// oldest first.
+    pub frozen_layers: Vec<String>,
+}
+
 /// Represents a successfully mounted OverlayBD block device.
 #[derive(Debug, Clone)]
 pub struct MountedDevice {
@@ -76,6 +110,8 @@ pub struct MountedDevice {
     tcmu_name: String,
     /// Loopback target WWN in configfs (derived from vm_id)
     wwn: String,
+    /// Digests of the image layers the device reads from
+    layers: Vec<String>,
 }
 
 /// Manages OverlayBD block devices for VMs.
@@ -86,50 +122,62 @@ pub struct OverlayBdManager {
     cache_dir: PathBuf,
     /// Currently mounted devices, keyed by VM ID
     mounts: Arc<Mutex<HashMap<String, MountedDevice>>>,
+    /// Layer blobs shared by all mounts
+    cache: LayerCache,
 }
 
 impl OverlayBdManager {
-    pub fn new(convertor_binary: impl Into<PathBuf>, cache_dir: impl Into<PathBuf>) -> Self {
+    pub fn new(
+        convertor_binary: impl Into<PathBuf>,
+        cache_dir: impl Into<PathBuf>,
+        cache_max_bytes: Option<u64>,
+    ) -> Self {
         let convertor_binary = convertor_binary.into();
         let cache_dir = cache_dir.into();
         info!(
-            "OverlayBdManager initialized: convertor={}, cache_dir={}",
+            "OverlayBdManager initialized: convertor={}, cache_dir={}, cache_max_bytes={:?}",
             convertor_binary.display(),
-            cache_dir.display()
+            cache_dir.display(),
+            cache_max_bytes
         );
         Self {
+            cache: LayerCache::new(&cache_dir, cache_max_bytes),
             convertor_binary,
             cache_dir,
             mounts: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
     /// Import an OCI image into the local OverlayBD storage pool:
     ///   1. Copy the source OCI image to the local registry using `oci-client`.
     ///   2. Convert the mirrored image to OverlayBD format using `convertor`.
     ///
-    /// Returns the image reference in the target registry.
-    /// Import an OCI image into the local registry in OverlayBD format.
-    ///
-    /// Returns `(target_ref, converted_size_bytes)` where `converted_size_bytes`
-    /// is the sum of the converted layer sizes stored in the local registry —
-    /// i.e. the actual disk space consumed by this image on the OverlayBD pool.
+    /// `size_bytes` of the result is the sum of the converted layer sizes
+    /// stored in the local registry — i.e. the actual disk space consumed by
+    /// this image on the OverlayBD pool. `content_size_bytes` is what the
+    /// source layers unpack to, which is what a disk holding the image needs.
     pub async fn import_image(
         &self,
         image_ref: &str,
         registry_url: &str,
-    ) -> Result<(String, i64), OverlayBdError> {
+        credentials: &RegistryCredentials,
+    ) -> Result<ImportedImage, OverlayBdError> {
         let target_ref = build_target_ref(image_ref, registry_url)?;
 
         info!("Copying OCI image {} → {}", image_ref, target_ref);
-        self.copy_image(image_ref, &target_ref, registry_url)
+        let content_size_bytes = self
+            .copy_image(image_ref, &target_ref, registry_url, credentials)
             .await?;
 
         info!("Converting {} to OverlayBD format", target_ref);
-        self.convert_to_overlaybd(&target_ref).await?;
+        self.convert_to_overlaybd(&target_ref, credentials).await?;
 
         let size_bytes = self
-            .fetch_image_size(&target_ref, registry_url)
+            .fetch_image_size(&target_ref, registry_url, credentials)
             .await
             .unwrap_or_else(|e| {
                 warn!(
@@ -140,10 +188,41 @@ impl OverlayBdManager {
             });
 
         info!(
-            "OverlayBD image ready: {} ({} bytes)",
-            target_ref, size_bytes
+            "OverlayBD image ready: {} ({} bytes, {} bytes unpacked)",
+            target_ref, size_bytes, content_size_bytes
         );
-        Ok((target_ref, size_bytes))
+        Ok(ImportedImage {
+            image_ref: target_ref,
+            size_bytes,
+            content_size_bytes,
+        })
+    }
+
+    /// Resolve the manifest digest `image_ref` currently points at upstream.
+    /// For multi-platform images this is the digest of the index, which is
+    /// what changes when the publisher pushes a new build.
+    pub async fn resolve_digest(
+        &self,
+        image_ref: &str,
+        credentials: &RegistryCredentials,
+    ) -> Result<String, OverlayBdError> {
+        let reference = Reference::try_from(image_ref)
+            .map_err(|e| OverlayBdError::InvalidImageRef(e.to_string()))?;
+
+        let host = reference.registry().to_string();
+        let mut excepts = Vec::new();
+        if host.starts_with("localhost") || host.starts_with("127.0.0.1") {
+            excepts.push(host);
+        }
+        let client = Client::new(ClientConfig {
+            protocol: ClientProtocol::HttpsExcept(excepts),
+            ..Default::default()
+        });
+
+        client
+            .fetch_manifest_digest(&reference, &credentials.auth_for(reference.registry()))
+            .await
+            .map_err(|e| OverlayBdError::OciError(e.to_string()))
     }
 
     /// Fetch the total compressed layer size of an image in the local registry.
@@ -154,6 +233,7 @@ impl OverlayBdManager {
         &self,
         image_ref: &str,
         registry_url: &str,
+        credentials: &RegistryCredentials,
     ) -> Result<i64, OverlayBdError> {
         let reference = Reference::try_from(image_ref)
             .map_err(|e| OverlayBdError::InvalidImageRef(e.to_string()))?;
@@ -164,7 +244,7 @@ impl OverlayBdManager {
         });
 
         let (manifest, _digest) = client
-            .pull_image_manifest(&reference, &RegistryAuth::Anonymous)
+            .pull_image_manifest(&reference, &credentials.auth_for(reference.registry()))
             .await
             .map_err(|e| OverlayBdError::OciError(e.to_string()))?;
 
@@ -173,12 +253,15 @@ impl OverlayBdManager {
     }
 
     /// Copy an OCI image from an arbitrary source registry to the local registry.
+    ///
+    /// Returns the bytes the image's layers unpack to.
     async fn copy_image(
         &self,
         source: &str,
         target: &str,
         registry_url: &str,
-    ) -> Result<(), OverlayBdError> {
+        credentials: &RegistryCredentials,
+    ) -> Result<i64, OverlayBdError> {
         let source_ref = Reference::try_from(source)
             .map_err(|e| OverlayBdError::InvalidImageRef(e.to_string()))?;
         let target_ref = Reference::try_from(target)
@@ -196,7 +279,7 @@ impl OverlayBdManager {
         });
 
         let (manifest, _digest) = client
-            .pull_image_manifest(&source_ref, &RegistryAuth::Anonymous)
+            .pull_image_manifest(&source_ref, &credentials.auth_for(source_ref.registry()))
             .await
             .map_err(|e| OverlayBdError::OciError(e.to_string()))?;
 
@@ -213,6 +296,7 @@ impl OverlayBdManager {
         };
 
         let mut image_layers: Vec<ImageLayer> = Vec::new();
+        let mut content_size: u64 = 0;
         for layer_desc in &manifest.layers {
             let mut buf: Vec<u8> = Vec::new();
             client
@@ -224,6 +308,7 @@ impl OverlayBdManager {
                 &layer_desc.digest[..std::cmp::min(16, layer_desc.digest.len())],
                 buf.len()
             );
+            content_size += layer_content_size(&layer_desc.media_type, &buf);
             image_layers.push(ImageLayer {
                 data: buf.into(),
                 media_type: layer_desc.media_type.clone(),
@@ -236,40 +321,42 @@ impl OverlayBdManager {
                 &target_ref,
                 &image_layers,
                 config,
-                &RegistryAuth::Anonymous,
+                &credentials.auth_for(target_ref.registry()),
                 Some(manifest),
             )
             .await
             .map_err(|e| OverlayBdError::OciError(e.to_string()))?;
 
         info!("Copied OCI image to {}", target);
-        Ok(())
+        Ok(content_size as i64)
     }
 
     /// Convert the image at `target_ref` to OverlayBD format in-place.
-    async fn convert_to_overlaybd(&self, target_ref: &str) -> Result<(), OverlayBdError> {
+    async fn convert_to_overlaybd(
+        &self,
+        target_ref: &str,
+        credentials: &RegistryCredentials,
+    ) -> Result<(), OverlayBdError> {
         let reference = Reference::try_from(target_ref)
             .map_err(|e| OverlayBdError::InvalidImageRef(e.to_string()))?;
 
-        let registry = reference.registry();
-        let repository = reference.repository();
-        let tag = reference.tag().unwrap_or("latest");
-
-        let output = tokio::process::Command::new(&self.convertor_binary)
-            .args([
-                "--repository",
-                &format!("{}/{}", registry, repository),
-                "--input-tag",
-                tag,
-                "--overlaybd",
-                tag,
-                "--plain",
-            ])
-            .output()
-            .await
-            .map_err(|e| {
-                OverlayBdError::ConvertorFailed(format!("failed to spawn convertor: {}", e))
-            })?;
+        // The login goes through a private Docker config rather than `-u`, which
+        // would put the password in the convertor's argv for anyone to read.
+        let auth_dir = match credentials.docker_config(reference.registry()) {
+            Some(config) => Some(write_docker_config(&self.cache_dir, &config).map_err(|e| {
+                OverlayBdError::ConvertorFailed(format!("failed to write registry login: {}", e))
+            })?),
+            None => None,
+        };
+        let mut command = tokio::process::Command::new(&self.convertor_binary);
+        command.args(convertor_args(&reference));
+        if let Some(dir) = &auth_dir {
+            command.env("DOCKER_CONFIG", dir.path());
+        }
+        let output = command.output().await.map_err(|e| {
+            OverlayBdError::ConvertorFailed(format!("failed to spawn convertor: {}", e))
+        })?;
+        drop(auth_dir);
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -297,9 +384,9 @@ impl OverlayBdManager {
         vm_id: &str,
         image_ref: &str,
         registry_url: &str,
-        upper_data_path: Option<&str>,
-        upper_index_path: Option<&str>,
+        upper: &UpperLayerSpec,
     ) -> Result<MountedDevice, OverlayBdError> {
+        let size_bytes = upper.size_bytes.unwrap_or(DEFAULT_DISK_SIZE_BYTES);
         let config_dir = self.cache_dir.join(vm_id);
         tokio::fs::create_dir_all(&config_dir).await.map_err(|e| {
             OverlayBdError::MountFailed(format!("create cache dir {}: {}", config_dir.display(), e))
@@ -307,10 +394,14 @@ impl OverlayBdManager {
 
         // Use caller-supplied persistent paths when provided; otherwise fall
         // back to ephemeral paths inside the config_dir (deleted on unmount).
-        let upper_index = upper_index_path
+        let upper_index = upper
+            .index_path
+            .as_deref()
             .map(PathBuf::from)
             .unwrap_or_else(|| config_dir.join("upper.index"));
-        let upper_data = upper_data_path
+        let upper_data = upper
+            .data_path
+            .as_deref()
             .map(PathBuf::from)
             .unwrap_or_else(|| config_dir.join("upper.data"));
         let config_file = config_dir.join("config.json");
@@ -337,32 +428,33 @@ impl OverlayBdManager {
                 })?;
             }
             info!(
-                "Creating OverlayBD upper layer for VM {} ({} GB sparse)",
-                vm_id, DISK_SIZE_GB
+                "Creating OverlayBD upper layer for VM {} ({} bytes sparse)",
+                vm_id, size_bytes
             );
-            let out = tokio::process::Command::new(OVERLAYBD_CREATE)
-                .args([
-                    upper_data.to_str().unwrap_or(""),
-                    upper_index.to_str().unwrap_or(""),
-                    &DISK_SIZE_GB.to_string(),
-                ])
-                .output()
-                .await
-                .map_err(|e| {
-                    OverlayBdError::MountFailed(format!("exec {}: {}", OVERLAYBD_CREATE, e))
-                })?;
-            if !out.status.success() {
-                return Err(OverlayBdError::MountFailed(format!(
-                    "overlaybd-create failed: {}",
-                    String::from_utf8_lossy(&out.stderr).trim()
-                )));
-            }
+            create_upper(&upper_data, &upper_index, size_bytes).await?;
         }
 
         // 2. Write the overlaybd TCMU config.json.
         //    Fetch the manifest to build the lowers array (OverlayBD layer descriptors).
         //    overlaybd-tcmu needs these to lazy-fetch image data from the registry.
-        let lowers = self.fetch_lowers(image_ref, registry_url).await?;
+        //    Each lower points at its directory in the layer cache, and
+        //    `download` has overlaybd complete the cached copy in the background.
+        //    Layers frozen from the upper by earlier resizes sit on top of them.
+        let mut lowers = self.fetch_lowers(image_ref, registry_url).await?;
+        let layers = lower_digests(&lowers);
+        self.cache
+            .prepare_layers(&layers)
+            .await
+            .map_err(|e| OverlayBdError::MountFailed(format!("create layer cache dirs: {}", e)))?;
+        self.cache
+            .record_mount(image_ref, registry_url, &layers)
+            .await;
+        lowers.extend(
+            upper
+                .frozen_layers
+                .iter()
+                .map(|file| serde_json::json!({ "file": file })),
+        );
         let config_json = serde_json::json!({
             "repoBlobUrl": repo_blob_url,
             "lowers": lowers,
@@ -370,7 +462,8 @@ impl OverlayBdManager {
                 "index": upper_index.to_string_lossy(),
                 "data":  upper_data.to_string_lossy()
             },
-            "resultFile": config_dir.join("result").to_string_lossy()
+            "resultFile": config_dir.join("result").to_string_lossy(),
+            "download": { "enable": true }
         });
         tokio::fs::write(&config_file, serde_json::to_string_pretty(&config_json)?)
             .await
@@ -426,7 +519,7 @@ impl OverlayBdManager {
         let dev_config = format!(
             "dev_config=overlaybd/{},dev_size={},dev_max_sectors=128",
             config_file.display(),
-            DISK_SIZE_BYTES
+            size_bytes
         );
         tokio::fs::write(tcmu_dir.join("control"), &dev_config)
             .await
@@ -473,6 +566,7 @@ impl OverlayBdManager {
             config_dir: config_dir.clone(),
             tcmu_name,
             wwn,
+            layers,
         };
 
         self.mounts
@@ -480,6 +574,8 @@ impl OverlayBdManager {
             .await
             .insert(vm_id.to_string(), mounted.clone());
 
+        self.evict_cache().await;
+
         Ok(mounted)
     }
 
@@ -490,20 +586,24 @@ impl OverlayBdManager {
         architecture: &str,
         boot_mode: &str,
         qarax_init_binary: Option<&Path>,
+        credentials: &RegistryCredentials,
     ) -> Result<(String, Vec<PreflightCheckResult>), OverlayBdError> {
         let mut checks = vec![boot_mode_check(boot_mode)];
 
-        let (imported_ref, size_bytes) = self.import_image(image_ref, registry_url).await?;
+        let imported = self
+            .import_image(image_ref, registry_url, credentials)
+            .await?;
         checks.push(PreflightCheckResult::ok(
             "overlaybd_import",
             format!(
                 "imported image into local registry as {} ({} bytes)",
-                imported_ref, size_bytes
+                imported.image_ref, imported.size_bytes
             ),
         ));
+        let imported_ref = imported.image_ref;
 
         let oci_config = self
-            .fetch_full_oci_config(&imported_ref, registry_url)
+            .fetch_full_oci_config(&imported_ref, registry_url, credentials)
             .await?;
         let manifest_architecture = oci_config
             .architecture
@@ -527,7 +627,12 @@ impl OverlayBdManager {
 
         let vm_id = format!("preflight-{}", Uuid::new_v4());
         let mount_result = self
-            .mount(&vm_id, &imported_ref, registry_url, None, None)
+            .mount(
+                &vm_id,
+                &imported_ref,
+                registry_url,
+                &UpperLayerSpec::default(),
+            )
             .await;
         let mounted = match mount_result {
             Ok(mounted) => mounted,
@@ -715,6 +820,13 @@ impl OverlayBdManager {
             }
 
             let (tcmu_name, wwn) = Self::vm_tcmu_names(&vm_id);
+            let layers = match tokio::fs::read(entry.path().join("config.json")).await {
+                Ok(bytes) => serde_json::from_slice::<serde_json::Value>(&bytes)
+                    .ok()
+                    .and_then(|config| config["lowers"].as_array().map(|l| lower_digests(l)))
+                    .unwrap_or_default(),
+                Err(_) => Vec::new(),
+            };
 
             info!(
                 "Recovered OverlayBD mount for VM {}: {}",
@@ -726,9 +838,170 @@ impl OverlayBdManager {
                 config_dir: entry.path(),
                 tcmu_name,
                 wwn,
+                layers,
             };
             self.mounts.lock().await.insert(vm_id, mounted);
         }
+
+        self.evict_cache().await;
+    }
+
+    /// Grow a persistent upper layer while no VM has it mounted. The writes so
+    /// far are committed to `frozen_layer`, which later mounts stack below a
+    /// fresh upper of `new_size_bytes`. The fresh upper is created under
+    /// temporary names and renamed into place, so a failure never leaves the
+    /// disk without its upper.
+    pub async fn resize_upper(
+        &self,
+        upper_data: &Path,
+        upper_index: &Path,
+        frozen_layer: &Path,
+        new_size_bytes: u64,
+    ) -> Result<UpperResize, OverlayBdError> {
+        if !tokio::fs::try_exists(upper_data).await? {
+            return Ok(UpperResize::NotCreated);
+        }
+        if tokio::fs::try_exists(frozen_layer).await? {
+            return Err(OverlayBdError::MountFailed(format!(
+                "frozen layer {} already exists",
+                frozen_layer.display()
+            )));
+        }
+
+        let out = tokio::process::Command::new(OVERLAYBD_COMMIT)
+            .arg(upper_data)
+            .arg(upper_index)
+            .arg(frozen_layer)
+            .output()
+            .await
+            .map_err(|e| {
+                OverlayBdError::MountFailed(format!("exec {}: {}", OVERLAYBD_COMMIT, e))
+            })?;
+        if !out.status.success() {
+            let _ = tokio::fs::remove_file(frozen_layer).await;
+            return Err(OverlayBdError::MountFailed(format!(
+                "overlaybd-commit failed: {}",
+                String::from_utf8_lossy(&out.stderr).trim()
+            )));
+        }
+
+        // From here on the frozen layer holds every write of the old upper,
+        // so it stays valid under either upper and is reported even on failure.
+        if let Err(e) = replace_upper(upper_data, upper_index, new_size_bytes).await {
+            return Ok(UpperResize::FrozenOnly(e));
+        }
+        info!(
+            "Resized OverlayBD upper layer {} to {} bytes (earlier writes frozen in {})",
+            upper_data.display(),
+            new_size_bytes,
+            frozen_layer.display()
+        );
+        Ok(UpperResize::Resized)
+    }
+
+    /// Evict least recently used images from the layer cache, keeping every
+    /// layer a mounted device reads from.
+    pub async fn evict_cache(&self) -> Vec<String> {
+        let in_use = self.in_use_layers().await;
+        self.cache.evict(&in_use).await
+    }
+
+    /// Cache counters and per-image usage, most recently used image first.
+    pub async fn cache_report(&self) -> (CacheStats, Vec<ImageUsage>) {
+        let in_use = self.in_use_layers().await;
+        (self.cache.stats().await, self.cache.usage(&in_use).await)
+    }
+
+    async fn in_use_layers(&self) -> HashSet<String> {
+        self.mounts
+            .lock()
+            .await
+            .values()
+            .flat_map(|m| m.layers.iter().cloned())
+            .collect()
+    }
+
+    /// Download every layer of `image_ref` into the layer cache so the first
+    /// VM started from it does not fetch blocks from the registry. Layers that
+    /// are already cached are skipped.
+    pub async fn prewarm(
+        &self,
+        image_ref: &str,
+        registry_url: &str,
+    ) -> Result<ImageUsage, OverlayBdError> {
+        let (repo_name, _tag) = parse_image_ref(image_ref)?;
+        let lowers = self.fetch_lowers(image_ref, registry_url).await?;
+        let layers = lower_digests(&lowers);
+        self.cache.prepare_layers(&layers).await?;
+
+        for digest in &layers {
+            if self.cache.is_cached(digest).await {
+                continue;
+            }
+            let blob_url = format!(
+                "{}/v2/{}/blobs/{}",
+                registry_url.trim_end_matches('/'),
+                repo_name,
+                digest
+            );
+            self.download_layer(&blob_url, digest).await?;
+        }
+        self.cache.touch(image_ref, registry_url, &layers).await;
+        info!(
+            "Pre-warmed {} layer(s) of OverlayBD image {}",
+            layers.len(),
+            image_ref
+        );
+        self.evict_cache().await;
+
+        let (_, usage) = self.cache_report().await;
+        usage
+            .into_iter()
+            .find(|u| u.image_ref == image_ref)
+            .ok_or_else(|| {
+                OverlayBdError::OciError(format!(
+                    "{} was evicted right after pre-warming; raise the cache size cap",
+                    image_ref
+                ))
+            })
+    }
+
+    /// Stream one layer blob into its cache directory. The blob is written
+    /// under a temporary name and only renamed into place once its digest
+    /// checks out, so overlaybd never sees a partial layer.
+    async fn download_layer(&self, blob_url: &str, digest: &str) -> Result<(), OverlayBdError> {
+        use futures::StreamExt;
+
+        let dir = self.cache.layer_dir(digest);
+        let partial = dir.join(format!("{}.download", LAYER_COMMIT_FILE));
+
+        let response = reqwest::get(blob_url)
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| OverlayBdError::OciError(format!("fetch {}: {}", blob_url, e)))?;
+
+        let mut file = tokio::fs::File::create(&partial).await?;
+        let mut hasher = Sha256::new();
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk
+                .map_err(|e| OverlayBdError::OciError(format!("fetch {}: {}", blob_url, e)))?;
+            hasher.update(&chunk);
+            file.write_all(&chunk).await?;
+        }
+        file.sync_all().await?;
+
+        let actual = format!("sha256:{:x}", hasher.finalize());
+        if digest.starts_with("sha256:") && actual != digest {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(OverlayBdError::OciError(format!(
+                "layer {} downloaded with digest {}",
+                digest, actual
+            )));
+        }
+
+        tokio::fs::rename(&partial, dir.join(LAYER_COMMIT_FILE)).await?;
+        Ok(())
     }
 
     /// Inject the qarax-init binary and config into a mounted OverlayBD block device.
@@ -847,7 +1120,7 @@ impl OverlayBdManager {
         registry_url: &str,
     ) -> Result<OciImageConfigDetails, OverlayBdError> {
         Ok(self
-            .fetch_full_oci_config(image_ref, registry_url)
+            .fetch_full_oci_config(image_ref, registry_url, &RegistryCredentials::default())
             .await?
             .config)
     }
@@ -856,6 +1129,7 @@ impl OverlayBdManager {
         &self,
         image_ref: &str,
         registry_url: &str,
+        credentials: &RegistryCredentials,
     ) -> Result<OciImageConfig, OverlayBdError> {
         let host = registry_host(registry_url);
 
@@ -874,7 +1148,7 @@ impl OverlayBdManager {
         });
 
         let (manifest, _digest) = client
-            .pull_image_manifest(&local_ref, &RegistryAuth::Anonymous)
+            .pull_image_manifest(&local_ref, &credentials.auth_for(local_ref.registry()))
             .await
             .map_err(|e| {
                 OverlayBdError::OciError(format!("fetch manifest for {}: {}", full_ref_str, e))
@@ -942,7 +1216,7 @@ impl OverlayBdManager {
                 serde_json::json!({
                     "digest": layer.digest,
                     "size": layer.size,
-                    "dir": ""
+                    "dir": self.cache.layer_dir(&layer.digest).to_string_lossy()
                 })
             })
             .collect();
@@ -1205,7 +1479,15 @@ impl OverlayBdManager {
 /// e.g. image_ref = "public.ecr.aws/docker/library/ubuntu:22.04",
 ///      registry_url = "http://registry:5000"
 /// ->   "registry:5000/docker/library/ubuntu:22.04"
-fn build_target_ref(image_ref: &str, registry_url: &str) -> Result<String, OverlayBdError> {
+///
+/// A source pinned by digest keeps its own tag in the local registry, so
+/// importing `ubuntu:22.04@sha256:0123456789ab...` yields
+/// `registry:5000/ubuntu:22.04-0123456789ab` and does not replace whatever
+/// `ubuntu:22.04` currently points at.
+pub(super) fn build_target_ref(
+    image_ref: &str,
+    registry_url: &str,
+) -> Result<String, OverlayBdError> {
     let host_owned = registry_host(registry_url);
     let host = host_owned.as_str();
 
@@ -1216,6 +1498,11 @@ fn build_target_ref(image_ref: &str, registry_url: &str) -> Result<String, Overl
         )));
     }
 
+    let (image_ref, pinned) = match image_ref.split_once('@') {
+        Some((name, digest)) => (name, Some(digest)),
+        None => (image_ref, None),
+    };
+
     let bare = if image_ref.contains('/') {
         // If the first component is a registry hostname (contains . or :), strip it.
         // Otherwise, keep the whole string (it's a namespace/repo like "library/ubuntu")
@@ -1229,7 +1516,58 @@ fn build_target_ref(image_ref: &str, registry_url: &str) -> Result<String, Overl
         image_ref
     };
 
-    Ok(format!("{}/{}", host, bare))
+    let Some(digest) = pinned else {
+        return Ok(format!("{}/{}", host, bare));
+    };
+    let (repository, tag) = match bare.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => (repository, tag),
+        _ => (bare, "latest"),
+    };
+    let hex = digest.rsplit(':').next().unwrap_or(digest);
+    Ok(format!(
+        "{}/{}:{}-{}",
+        host,
+        repository,
+        tag,
+        &hex[..hex.len().min(12)]
+    ))
+}
+
+/// Arguments for an in-place `convertor` run against the local registry. The
+/// login (if any) is passed through `$DOCKER_CONFIG`, see `write_docker_config`.
+fn convertor_args(reference: &Reference) -> Vec<String> {
+    let tag = reference.tag().unwrap_or("latest");
+    vec![
+        "--repository".to_string(),
+        format!("{}/{}", reference.registry(), reference.repository()),
+        "--input-tag".to_string(),
+        tag.to_string(),
+        "--overlaybd".to_string(),
+        tag.to_string(),
+        "--plain".to_string(),
+    ]
+}
+
+/// Write `config` as `config.json` in a fresh 0700 directory under `parent`,
+/// readable only by the node. The directory is removed when dropped.
+fn write_docker_config(
+    parent: &std::path::Path,
+    config: &serde_json::Value,
+) -> std::io::Result<tempfile::TempDir> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    std::fs::create_dir_all(parent)?;
+    let dir = tempfile::Builder::new()
+        .prefix(".convertor-auth-")
+        .tempdir_in(parent)?;
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(dir.path().join("config.json"))?;
+    file.write_all(config.to_string().as_bytes())?;
+    Ok(dir)
 }
 
 /// Parse an image reference into (repo_name, tag).
@@ -1260,13 +1598,123 @@ fn parse_image_ref(image_ref: &str) -> Result<(String, String), OverlayBdError>
     Ok((repo, tag))
 }
 
+/// Create an empty sparse upper layer. overlaybd-create takes whole GiB, so
+/// the size is rounded up.
+async fn create_upper(data: &Path, index: &Path, size_bytes: u64) -> Result<(), OverlayBdError> {
+    let out = tokio::process::Command::new(OVERLAYBD_CREATE)
+        .arg(data)
+        .arg(index)
+        .arg(size_bytes.div_ceil(GIB).to_string())
+        .output()
+        .await
+        .map_err(|e| OverlayBdError::MountFailed(format!("exec {}: {}", OVERLAYBD_CREATE, e)))?;
+    if !out.status.success() {
+        return Err(OverlayBdError::MountFailed(format!(
+            "overlaybd-create failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Swap a persistent upper for an empty one of `size_bytes`. The new files are
+/// created next to the old ones and renamed over them; if only the data file
+/// made it, the old data is linked back so data and index always match.
+async fn replace_upper(data: &Path, index: &Path, size_bytes: u64) -> Result<(), OverlayBdError> {
+    let new_data = with_suffix(data, ".resize-new");
+    let new_index = with_suffix(index, ".resize-new");
+    let old_data = with_suffix(data, ".resize-old");
+    for path in [&new_data, &new_index, &old_data] {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+
+    let result = async {
+        create_upper(&new_data, &new_index, size_bytes).await?;
+        tokio::fs::hard_link(data, &old_data).await?;
+        tokio::fs::rename(&new_data, data).await?;
+        if let Err(e) = tokio::fs::rename(&new_index, index).await {
+            tokio::fs::rename(&old_data, data).await?;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+    .await;
+
+    for path in [&new_data, &new_index, &old_data] {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+    result
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Layer digests of a TCMU config's `lowers` array, in order.
+fn lower_digests(lowers: &[serde_json::Value]) -> Vec<String> {
+    lowers
+        .iter()
+        .filter_map(|l| l["digest"].as_str().map(str::to_string))
+        .collect()
+}
+
+/// An image imported into a pool registry in OverlayBD format.
+#[derive(Debug, Clone)]
+pub struct ImportedImage {
+    /// Reference of the converted image in the pool registry.
+    pub image_ref: String,
+    /// Converted layer bytes stored in the registry.
+    pub size_bytes: i64,
+    /// Bytes the source image's layers unpack to.
+    pub content_size_bytes: i64,
+}
+
+/// Bytes a layer blob unpacks to. Gzip layers are inflated to count them;
+/// anything else counts at its blob size.
+fn layer_content_size(media_type: &str, blob: &[u8]) -> u64 {
+    if media_type.ends_with("gzip") {
+        let mut decoder = flate2::read::MultiGzDecoder::new(blob);
+        match std::io::copy(&mut decoder, &mut std::io::sink()) {
+            Ok(size) => return size,
+            Err(e) => warn!("Could not inflate {} layer: {}", media_type, e),
+        }
+    }
+    blob.len() as u64
+}
+
 fn looks_like_device_path(path: &str) -> bool {
     path.starts_with("/dev/") && path.len() > "/dev/".len()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::looks_like_device_path;
+    use super::{
+        Reference, RegistryCredentials, build_target_ref, convertor_args, layer_content_size,
+        looks_like_device_path, write_docker_config,
+    };
+    use crate::rpc::node::RegistryCredential;
+
+    #[test]
+    fn gzip_layers_count_at_their_unpacked_size() {
+        use std::io::Write;
+
+        let content = vec![0u8; 8 * 1024 * 1024];
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&content).unwrap();
+        let blob = encoder.finish().unwrap();
+        assert!(blob.len() < content.len() / 100);
+
+        assert_eq!(
+            layer_content_size("application/vnd.oci.image.layer.v1.tar+gzip", &blob),
+            content.len() as u64
+        );
+        assert_eq!(
+            layer_content_size("application/vnd.oci.image.layer.v1.tar", &content),
+            content.len() as u64
+        );
+    }
 
     #[test]
     fn accepts_dev_paths_for_recovery() {
@@ -1279,4 +1727,61 @@ mod tests {
         assert!(!looks_like_device_path("success"));
         assert!(!looks_like_device_path("sdb"));
     }
+
+    #[test]
+    fn pinned_sources_get_a_digest_suffixed_tag() {
+        assert_eq!(
+            build_target_ref("docker.io/library/ubuntu:24.04", "http://registry:5000").unwrap(),
+            "registry:5000/library/ubuntu:24.04"
+        );
+        assert_eq!(
+            build_target_ref(
+                "docker.io/library/ubuntu:24.04@sha256:0123456789abcdef",
+                "http://registry:5000"
+            )
+            .unwrap(),
+            "registry:5000/library/ubuntu:24.04-0123456789ab"
+        );
+        assert_eq!(
+            build_target_ref(
+                "localhost:5000/alpine@sha256:fedcba9876543210",
+                "registry:5000"
+            )
+            .unwrap(),
+            "registry:5000/alpine:latest-fedcba987654"
+        );
+    }
+
+    #[test]
+    fn convertor_login_goes_to_a_private_docker_config() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let reference = Reference::try_from("registry:5000/library/alpine:3.20").unwrap();
+        let args = convertor_args(&reference);
+        assert_eq!(args[1], "registry:5000/library/alpine");
+        assert!(!args.contains(&"-u".to_string()));
+
+        let credentials = RegistryCredentials::from(vec![RegistryCredential {
+            registry: "http://registry:5000".into(),
+            username: "qarax".into(),
+            password: "s3cret".into(),
+        }]);
+        assert!(credentials.docker_config("other:5000").is_none());
+        let config = credentials.docker_config(reference.registry()).unwrap();
+        // base64("qarax:s3cret")
+        assert_eq!(config["auths"]["registry:5000"]["auth"], "cWFyYXg6czNjcmV0");
+
+        let parent = tempfile::tempdir().unwrap();
+        let dir = write_docker_config(parent.path(), &config).unwrap();
+        let file = dir.path().join("config.json");
+        let mode = std::fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&file).unwrap()).unwrap();
+        assert_eq!(written, config);
+
+        let path = dir.path().to_path_buf();
+        drop(dir);
+        assert!(!path.exists());
+    }
 }
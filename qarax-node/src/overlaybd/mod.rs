@@ -1,3 +1,10 @@
+pub mod cache;
+pub mod credentials;
+pub mod gc;
 pub mod manager;
 
-pub use manager::{MountedDevice, OverlayBdError, OverlayBdManager};
+pub use cache::{CacheStats, ImageUsage};
+pub use credentials::RegistryCredentials;
+pub use manager::{
+    ImportedImage, MountedDevice, OverlayBdError, OverlayBdManager, UpperLayerSpec, UpperResize,
+};
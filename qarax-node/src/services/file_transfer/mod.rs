@@ -7,12 +7,22 @@ use tokio::sync::mpsc;
 use tonic::{Request, Response, Status};
 use tracing::{debug, error, info, warn};
 
-use crate::overlaybd::manager::OverlayBdManager;
+use common::checksum::{Checksum, ChecksumAlgorithm};
+
+use crate::overlaybd::manager::{OverlayBdManager, UpperLayerSpec};
 use crate::rpc::node::{
-    CopyFileRequest, CreateDiskRequest, DownloadFileRequest, OverlayBdDiskSource, TransferResponse,
-    create_disk_request::Source, file_transfer_service_server::FileTransferService,
+    ArchiveMember, ChecksumFilesRequest, ChecksumFilesResponse, CopyFileRequest, CreateDiskRequest,
+    DeletePathRequest, DeletePathResponse, DirectoryEntry, DownloadFileRequest,
+    ExtractArchiveMemberRequest, FileChecksum, ListDirectoryRequest, ListDirectoryResponse,
+    OverlayBdDiskSource, PackArchiveRequest, PackArchiveResponse, ReadArchiveFileRequest,
+    ReadFileRequest, ReadFileResponse, TransferResponse, UploadFileHeader, UploadFileRequest,
+    WriteFileRequest, create_disk_request::Source,
+    file_transfer_service_server::FileTransferService, upload_file_request::Payload,
 };
 
+/// Upper bound for `ReadFile`; it is meant for manifests, not disk images.
+const MAX_READ_FILE_BYTES: u64 = 16 * 1024 * 1024;
+
 /// Implementation of the FileTransferService gRPC service.
 ///
 /// Handles file downloads (HTTP(S) → disk), local copies, and disk creation on the node.
@@ -46,15 +56,29 @@ impl FileTransferService for FileTransferServiceImpl {
             "Starting file download"
         );
 
-        match do_download(&req.source_url, &req.destination_path).await {
-            Ok(bytes_written) => {
-                info!(transfer_id = %req.transfer_id, bytes_written, "Download completed");
+        let options = DownloadOptions::from_request(
+            req.expected_checksum.as_deref(),
+            req.bandwidth_limit_bytes_per_sec,
+        )
+        .map_err(Status::invalid_argument)?;
+
+        match do_download(&req.source_url, &req.destination_path, &options).await {
+            Ok(outcome) => {
+                info!(
+                    transfer_id = %req.transfer_id,
+                    bytes_written = outcome.bytes_written,
+                    checksum = outcome.checksum.as_deref().unwrap_or("-"),
+                    "Download completed"
+                );
+                let checksum_verified = outcome.checksum.is_some();
                 Ok(Response::new(TransferResponse {
                     transfer_id: req.transfer_id,
                     success: true,
-                    bytes_written,
+                    bytes_written: outcome.bytes_written,
                     error: String::new(),
                     is_final: true,
+                    checksum: outcome.checksum.unwrap_or_default(),
+                    checksum_verified,
                 }))
             }
             Err(e) => {
@@ -65,6 +89,8 @@ impl FileTransferService for FileTransferServiceImpl {
                     bytes_written: 0,
                     error: e.to_string(),
                     is_final: true,
+                    checksum: mismatched_checksum(&e),
+                    checksum_verified: false,
                 }))
             }
         }
@@ -93,6 +119,8 @@ impl FileTransferService for FileTransferServiceImpl {
                 bytes_written: 0,
                 error: format!("Failed to create directory: {e}"),
                 is_final: true,
+                checksum: String::new(),
+                checksum_verified: false,
             }));
         }
 
@@ -105,6 +133,8 @@ impl FileTransferService for FileTransferServiceImpl {
                     bytes_written: bytes_written as i64,
                     error: String::new(),
                     is_final: true,
+                    checksum: String::new(),
+                    checksum_verified: false,
                 }))
             }
             Err(e) => {
@@ -115,6 +145,8 @@ impl FileTransferService for FileTransferServiceImpl {
                     bytes_written: 0,
                     error: e.to_string(),
                     is_final: true,
+                    checksum: String::new(),
+                    checksum_verified: false,
                 }))
             }
         }
@@ -135,10 +167,22 @@ impl FileTransferService for FileTransferServiceImpl {
             "Creating disk"
         );
 
+        let url_options = match req.source {
+            Some(Source::Url(ref source)) => Some(
+                DownloadOptions::from_request(
+                    source.expected_checksum.as_deref(),
+                    source.bandwidth_limit_bytes_per_sec,
+                )
+                .map_err(Status::invalid_argument)?,
+            ),
+            _ => None,
+        };
+
         let (tx, rx) = mpsc::channel(16);
         let overlaybd_manager = self.overlaybd_manager.clone();
 
         tokio::spawn(async move {
+            let mut checksum = None;
             let result = match req.source {
                 Some(Source::Overlaybd(ref source)) => {
                     do_create_from_overlaybd(
@@ -150,7 +194,15 @@ impl FileTransferService for FileTransferServiceImpl {
                     )
                     .await
                 }
-                Some(Source::Url(ref source)) => do_download(&source.url, &req.path).await,
+                Some(Source::Url(ref source)) => {
+                    let options = url_options.unwrap_or_default();
+                    do_download(&source.url, &req.path, &options)
+                        .await
+                        .map(|outcome| {
+                            checksum = outcome.checksum;
+                            outcome.bytes_written
+                        })
+                }
                 Some(Source::Blank(ref source)) => {
                     do_create_blank(&req.path, req.size_bytes, source.preallocate).await
                 }
@@ -160,12 +212,15 @@ impl FileTransferService for FileTransferServiceImpl {
             let final_msg = match result {
                 Ok(bytes_written) => {
                     info!(dest = %req.path, bytes_written, "Disk created");
+                    let checksum_verified = checksum.is_some();
                     TransferResponse {
                         transfer_id: String::new(),
                         success: true,
                         bytes_written,
                         error: String::new(),
                         is_final: true,
+                        checksum: checksum.unwrap_or_default(),
+                        checksum_verified,
                     }
                 }
                 Err(e) => {
@@ -177,6 +232,8 @@ impl FileTransferService for FileTransferServiceImpl {
                         bytes_written: 0,
                         error: e.to_string(),
                         is_final: true,
+                        checksum: mismatched_checksum(&e),
+                        checksum_verified: false,
                     }
                 }
             };
@@ -187,6 +244,660 @@ impl FileTransferService for FileTransferServiceImpl {
             tokio_stream::wrappers::ReceiverStream::new(rx),
         )))
     }
+
+    async fn write_file(
+        &self,
+        request: Request<WriteFileRequest>,
+    ) -> Result<Response<TransferResponse>, Status> {
+        let req = request.into_inner();
+        debug!(path = %req.path, bytes = req.content.len(), "Writing file");
+
+        let result: anyhow::Result<i64> = async {
+            let dest = std::path::Path::new(&req.path);
+            if let Some(parent) = dest.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(dest, &req.content).await?;
+            Ok(req.content.len() as i64)
+        }
+        .await;
+
+        match result {
+            Ok(bytes_written) => Ok(Response::new(TransferResponse {
+                transfer_id: String::new(),
+                success: true,
+                bytes_written,
+                error: String::new(),
+                is_final: true,
+                checksum: String::new(),
+                checksum_verified: false,
+            })),
+            Err(e) => {
+                error!(path = %req.path, error = %e, "Write failed");
+                Ok(Response::new(TransferResponse {
+                    transfer_id: String::new(),
+                    success: false,
+                    bytes_written: 0,
+                    error: e.to_string(),
+                    is_final: true,
+                    checksum: String::new(),
+                    checksum_verified: false,
+                }))
+            }
+        }
+    }
+
+    async fn read_file(
+        &self,
+        request: Request<ReadFileRequest>,
+    ) -> Result<Response<ReadFileResponse>, Status> {
+        let req = request.into_inner();
+
+        let metadata = tokio::fs::metadata(&req.path)
+            .await
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => {
+                    Status::not_found(format!("{}: not found", req.path))
+                }
+                _ => Status::internal(format!("Failed to stat {}: {e}", req.path)),
+            })?;
+        if !metadata.is_file() {
+            return Err(Status::invalid_argument(format!(
+                "{} is not a regular file",
+                req.path
+            )));
+        }
+        if metadata.len() > MAX_READ_FILE_BYTES {
+            return Err(Status::invalid_argument(format!(
+                "{} is larger than {} bytes",
+                req.path, MAX_READ_FILE_BYTES
+            )));
+        }
+
+        let content = tokio::fs::read(&req.path)
+            .await
+            .map_err(|e| Status::internal(format!("Failed to read {}: {e}", req.path)))?;
+        Ok(Response::new(ReadFileResponse { content }))
+    }
+
+    async fn checksum_files(
+        &self,
+        request: Request<ChecksumFilesRequest>,
+    ) -> Result<Response<ChecksumFilesResponse>, Status> {
+        let req = request.into_inner();
+        let path = std::path::PathBuf::from(&req.path);
+
+        let files = tokio::task::spawn_blocking(move || checksum_files(&path))
+            .await
+            .map_err(|e| Status::internal(format!("checksum task panicked: {e}")))?
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => {
+                    Status::not_found(format!("{}: not found", req.path))
+                }
+                _ => Status::internal(format!("Failed to checksum {}: {e}", req.path)),
+            })?;
+
+        Ok(Response::new(ChecksumFilesResponse { files }))
+    }
+
+    async fn delete_path(
+        &self,
+        request: Request<DeletePathRequest>,
+    ) -> Result<Response<DeletePathResponse>, Status> {
+        let req = request.into_inner();
+        let path = std::path::PathBuf::from(&req.path);
+        validate_deletable_path(&path).map_err(Status::invalid_argument)?;
+
+        info!(path = %req.path, recursive = req.recursive, "Deleting path");
+        let recursive = req.recursive;
+        let bytes_freed = tokio::task::spawn_blocking(move || delete_path(&path, recursive))
+            .await
+            .map_err(|e| Status::internal(format!("delete task panicked: {e}")))?
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::IsADirectory => Status::failed_precondition(format!(
+                    "{} is a directory; recursive delete required",
+                    req.path
+                )),
+                _ => Status::internal(format!("Failed to delete {}: {e}", req.path)),
+            })?;
+
+        Ok(Response::new(DeletePathResponse { bytes_freed }))
+    }
+
+    async fn list_directory(
+        &self,
+        request: Request<ListDirectoryRequest>,
+    ) -> Result<Response<ListDirectoryResponse>, Status> {
+        let req = request.into_inner();
+        let path = std::path::PathBuf::from(&req.path);
+
+        let entries = tokio::task::spawn_blocking(move || list_directory(&path))
+            .await
+            .map_err(|e| Status::internal(format!("list task panicked: {e}")))?
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => {
+                    Status::not_found(format!("{}: not found", req.path))
+                }
+                _ => Status::internal(format!("Failed to list {}: {e}", req.path)),
+            })?;
+
+        Ok(Response::new(ListDirectoryResponse { entries }))
+    }
+
+    async fn pack_archive(
+        &self,
+        request: Request<PackArchiveRequest>,
+    ) -> Result<Response<PackArchiveResponse>, Status> {
+        let req = request.into_inner();
+        let archive = std::path::PathBuf::from(&req.archive_path);
+        validate_deletable_path(&archive).map_err(Status::invalid_argument)?;
+
+        info!(archive = %req.archive_path, members = req.members.len(), "Packing archive");
+        let members = req.members;
+        let size_bytes = tokio::task::spawn_blocking(move || pack_archive(&archive, &members))
+            .await
+            .map_err(|e| Status::internal(format!("pack task panicked: {e}")))?
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => Status::not_found(e.to_string()),
+                std::io::ErrorKind::InvalidInput => Status::invalid_argument(e.to_string()),
+                _ => Status::internal(format!("Failed to pack {}: {e}", req.archive_path)),
+            })?;
+
+        Ok(Response::new(PackArchiveResponse { size_bytes }))
+    }
+
+    async fn extract_archive_member(
+        &self,
+        request: Request<ExtractArchiveMemberRequest>,
+    ) -> Result<Response<FileChecksum>, Status> {
+        let req = request.into_inner();
+        let archive = std::path::PathBuf::from(&req.archive_path);
+        let member = std::path::PathBuf::from(&req.member);
+        let dest = std::path::PathBuf::from(&req.destination_path);
+        validate_deletable_path(&dest).map_err(Status::invalid_argument)?;
+
+        info!(archive = %req.archive_path, member = %req.member, dest = %req.destination_path, "Extracting archive member");
+        let checksum =
+            tokio::task::spawn_blocking(move || extract_archive_member(&archive, &member, &dest))
+                .await
+                .map_err(|e| Status::internal(format!("extract task panicked: {e}")))?
+                .map_err(|e| match e.kind() {
+                    std::io::ErrorKind::NotFound => {
+                        Status::not_found(format!("{}: {} not found", req.archive_path, req.member))
+                    }
+                    std::io::ErrorKind::InvalidData => {
+                        Status::invalid_argument(format!("{}: {e}", req.archive_path))
+                    }
+                    _ => Status::internal(format!(
+                        "Failed to extract {} from {}: {e}",
+                        req.member, req.archive_path
+                    )),
+                })?;
+
+        Ok(Response::new(checksum))
+    }
+
+    async fn read_archive_file(
+        &self,
+        request: Request<ReadArchiveFileRequest>,
+    ) -> Result<Response<ReadFileResponse>, Status> {
+        let req = request.into_inner();
+        let archive = std::path::PathBuf::from(&req.archive_path);
+        let member = std::path::PathBuf::from(&req.member);
+
+        let content = tokio::task::spawn_blocking(move || read_archive_file(&archive, &member))
+            .await
+            .map_err(|e| Status::internal(format!("read task panicked: {e}")))?
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => {
+                    Status::not_found(format!("{}: {} not found", req.archive_path, req.member))
+                }
+                std::io::ErrorKind::InvalidData => {
+                    Status::invalid_argument(format!("{}: {e}", req.archive_path))
+                }
+                _ => Status::internal(format!("Failed to read {}: {e}", req.archive_path)),
+            })?;
+
+        Ok(Response::new(ReadFileResponse { content }))
+    }
+
+    async fn upload_file(
+        &self,
+        request: Request<tonic::Streaming<UploadFileRequest>>,
+    ) -> Result<Response<TransferResponse>, Status> {
+        let mut messages = request.into_inner();
+        let header = match messages.message().await? {
+            Some(UploadFileRequest {
+                payload: Some(Payload::Header(header)),
+            }) => header,
+            _ => {
+                return Err(Status::invalid_argument(
+                    "the first upload message must be a header",
+                ));
+            }
+        };
+        validate_deletable_path(std::path::Path::new(&header.destination_path))
+            .map_err(Status::invalid_argument)?;
+
+        info!(
+            transfer_id = %header.transfer_id,
+            dest = %header.destination_path,
+            offset = header.offset,
+            end_offset = header.end_offset,
+            finalize = header.finalize,
+            "Receiving upload"
+        );
+
+        let bytes_written = receive_upload(&header, messages).await.inspect_err(|e| {
+            warn!(transfer_id = %header.transfer_id, error = %e.message(), "Upload stream failed");
+        })?;
+
+        Ok(Response::new(TransferResponse {
+            transfer_id: header.transfer_id,
+            success: true,
+            bytes_written,
+            error: String::new(),
+            is_final: true,
+            checksum: String::new(),
+            checksum_verified: false,
+        }))
+    }
+}
+
+/// Reject paths that could never belong to a storage object: relative paths,
+/// `..` components and the filesystem root.
+fn validate_deletable_path(path: &std::path::Path) -> Result<(), String> {
+    use std::path::Component;
+
+    if !path.is_absolute() {
+        return Err(format!("{} is not an absolute path", path.display()));
+    }
+    if path.components().any(|c| matches!(c, Component::ParentDir)) {
+        return Err(format!("{} must not contain '..'", path.display()));
+    }
+    if path.parent().is_none() {
+        return Err("refusing to delete the filesystem root".to_string());
+    }
+    Ok(())
+}
+
+/// Remove `path` and return the number of bytes it occupied. A missing path is
+/// not an error so callers can retry deletes safely.
+fn delete_path(path: &std::path::Path, recursive: bool) -> std::io::Result<i64> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+
+    if metadata.is_dir() {
+        if !recursive {
+            return Err(std::io::Error::from(std::io::ErrorKind::IsADirectory));
+        }
+        let size = tree_size(path)?;
+        std::fs::remove_dir_all(path)?;
+        Ok(size)
+    } else {
+        std::fs::remove_file(path)?;
+        Ok(metadata.len() as i64)
+    }
+}
+
+fn tree_size(path: &std::path::Path) -> std::io::Result<i64> {
+    let mut total = 0;
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            total += tree_size(&entry.path())?;
+        } else {
+            total += entry.metadata()?.len() as i64;
+        }
+    }
+    Ok(total)
+}
+
+fn list_directory(path: &std::path::Path) -> std::io::Result<Vec<DirectoryEntry>> {
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let modified_unix = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+        entries.push(DirectoryEntry {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            is_dir: metadata.is_dir(),
+            size_bytes: if metadata.is_dir() {
+                0
+            } else {
+                metadata.len() as i64
+            },
+            modified_unix,
+        });
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+/// Write `members` into a tar archive at `archive`, in order.
+///
+/// Files are streamed straight from their source paths, with holes kept as
+/// GNU sparse members. The archive is built next to its destination and
+/// renamed into place, so a failed pack never leaves a truncated archive
+/// behind. Sources marked `remove_source` are deleted either way.
+fn pack_archive(archive: &std::path::Path, members: &[ArchiveMember]) -> std::io::Result<i64> {
+    let mut partial = archive.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = std::path::PathBuf::from(partial);
+
+    let result = (|| {
+        let mut builder = tar::Builder::new(std::fs::File::create(&partial)?);
+        builder.follow_symlinks(false);
+        builder.sparse(true);
+        for member in members {
+            if !is_safe_member_name(std::path::Path::new(&member.name)) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("invalid archive member name {}", member.name),
+                ));
+            }
+            if member.source_path.is_empty() {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(member.content.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(
+                    std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or_default(),
+                );
+                builder.append_data(&mut header, &member.name, member.content.as_slice())?;
+                continue;
+            }
+            let source = std::path::Path::new(&member.source_path);
+            let metadata = std::fs::metadata(source).map_err(|e| {
+                std::io::Error::new(e.kind(), format!("{}: {e}", member.source_path))
+            })?;
+            if metadata.is_dir() {
+                builder.append_dir_all(&member.name, source)?;
+            } else {
+                builder.append_path_with_name(source, &member.name)?;
+            }
+        }
+        builder.into_inner()?.sync_all()
+    })();
+
+    for member in members.iter().filter(|m| m.remove_source) {
+        let source = std::path::Path::new(&member.source_path);
+        if validate_deletable_path(source).is_err() {
+            continue;
+        }
+        let removed = if source.is_dir() {
+            std::fs::remove_dir_all(source)
+        } else {
+            std::fs::remove_file(source)
+        };
+        if let Err(e) = removed
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            warn!(path = %member.source_path, error = %e, "Failed to remove packed source");
+        }
+    }
+
+    match result {
+        Ok(()) => {
+            std::fs::rename(&partial, archive)?;
+            Ok(std::fs::metadata(archive)?.len() as i64)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+/// Relative, `..`-free member names only, so extraction never has to trust them.
+fn is_safe_member_name(name: &std::path::Path) -> bool {
+    use std::path::Component;
+
+    name.components().next().is_some()
+        && name
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+fn same_member(path: &std::path::Path, wanted: &std::path::Path) -> bool {
+    use std::path::Component;
+
+    let normalize = |p: &std::path::Path| -> Vec<_> {
+        p.components()
+            .filter(|c| !matches!(c, Component::CurDir))
+            .collect()
+    };
+    normalize(path) == normalize(wanted)
+}
+
+/// Write one regular-file member of `archive` to `dest` and return the
+/// digest of what landed on disk. Holes in sparse members are seeked over.
+fn extract_archive_member(
+    archive: &std::path::Path,
+    member: &std::path::Path,
+    dest: &std::path::Path,
+) -> std::io::Result<FileChecksum> {
+    let mut reader = tar::Archive::new(std::fs::File::open(archive)?);
+    for entry in reader.entries_with_seek()? {
+        let mut entry = entry?;
+        if !same_member(&entry.path()?, member) {
+            continue;
+        }
+        let entry_type = entry.header().entry_type();
+        if !(entry_type.is_file() || entry_type.is_gnu_sparse()) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{} is not a regular file", member.display()),
+            ));
+        }
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        entry.unpack(dest)?;
+        let (size_bytes, sha256) = sha256_file(dest)?;
+        return Ok(FileChecksum {
+            name: member.to_string_lossy().into_owned(),
+            size_bytes,
+            sha256,
+        });
+    }
+    Err(std::io::Error::from(std::io::ErrorKind::NotFound))
+}
+
+/// Return the contents of one regular-file member of a tar archive.
+fn read_archive_file(
+    archive: &std::path::Path,
+    member: &std::path::Path,
+) -> std::io::Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut reader = tar::Archive::new(std::fs::File::open(archive)?);
+    for entry in reader.entries_with_seek()? {
+        let mut entry = entry?;
+        if !same_member(&entry.path()?, member) {
+            continue;
+        }
+        if !entry.header().entry_type().is_file() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{} is not a regular file", member.display()),
+            ));
+        }
+        if entry.size() > MAX_READ_FILE_BYTES {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "{} is larger than {MAX_READ_FILE_BYTES} bytes",
+                    member.display()
+                ),
+            ));
+        }
+        let mut content = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut content)?;
+        return Ok(content);
+    }
+    Err(std::io::Error::from(std::io::ErrorKind::NotFound))
+}
+
+/// Sibling file that holds an upload until it is finalized.
+fn upload_partial_path(destination: &std::path::Path) -> std::path::PathBuf {
+    let mut partial = destination.as_os_str().to_owned();
+    partial.push(".upload");
+    std::path::PathBuf::from(partial)
+}
+
+/// Write one upload stream into the partial file and return its size afterwards.
+///
+/// Anything past `header.offset` is discarded first, so a stream that died half
+/// way through can be replayed from the last offset the caller acknowledged.
+async fn receive_upload<S>(header: &UploadFileHeader, mut messages: S) -> Result<i64, Status>
+where
+    S: futures::Stream<Item = Result<UploadFileRequest, Status>> + Unpin,
+{
+    use tokio::io::AsyncSeekExt;
+
+    if header.offset < 0 || header.end_offset < header.offset {
+        return Err(Status::invalid_argument(format!(
+            "invalid upload range {}..{}",
+            header.offset, header.end_offset
+        )));
+    }
+
+    let destination = std::path::Path::new(&header.destination_path);
+    let partial = upload_partial_path(destination);
+    let io_error =
+        |e: std::io::Error| Status::internal(format!("Failed to write {}: {e}", partial.display()));
+
+    // Never write over an existing file; a new upload starts at offset 0.
+    if header.offset == 0 && tokio::fs::try_exists(destination).await.unwrap_or(false) {
+        return Err(already_exists(destination));
+    }
+    if let Some(parent) = destination.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(io_error)?;
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&partial)
+        .await
+        .map_err(io_error)?;
+
+    let existing = file.metadata().await.map_err(io_error)?.len() as i64;
+    if existing < header.offset {
+        return Err(Status::failed_precondition(format!(
+            "partial upload has {existing} bytes; cannot resume at offset {}",
+            header.offset
+        )));
+    }
+    file.set_len(header.offset as u64).await.map_err(io_error)?;
+    file.seek(std::io::SeekFrom::Start(header.offset as u64))
+        .await
+        .map_err(io_error)?;
+
+    let mut written = header.offset;
+    while let Some(message) = messages.next().await {
+        match message?.payload {
+            Some(Payload::Data(data)) => {
+                written += data.len() as i64;
+                if written > header.end_offset {
+                    return Err(Status::invalid_argument(format!(
+                        "upload stream is longer than the declared end offset {}",
+                        header.end_offset
+                    )));
+                }
+                file.write_all(&data).await.map_err(io_error)?;
+            }
+            Some(Payload::Header(_)) => {
+                return Err(Status::invalid_argument("upload header sent twice"));
+            }
+            None => {}
+        }
+    }
+    file.sync_all().await.map_err(io_error)?;
+
+    if written != header.end_offset {
+        return Err(Status::aborted(format!(
+            "upload stream ended at {written} bytes, expected {}",
+            header.end_offset
+        )));
+    }
+    if header.finalize {
+        // A hard link fails instead of replacing a file that appeared at the
+        // destination in the meantime, which a rename would silently do.
+        match tokio::fs::hard_link(&partial, destination).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                return Err(already_exists(destination));
+            }
+            Err(e) => return Err(io_error(e)),
+        }
+        tokio::fs::remove_file(&partial).await.map_err(io_error)?;
+    }
+    Ok(written)
+}
+
+fn already_exists(destination: &std::path::Path) -> Status {
+    Status::already_exists(format!("{} already exists", destination.display()))
+}
+
+/// SHA-256 every regular file at `path` (a single file, or the direct children
+/// of a directory). Entries are sorted by name so the result is stable.
+fn checksum_files(path: &std::path::Path) -> std::io::Result<Vec<FileChecksum>> {
+    if path.is_dir() {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            let (size_bytes, sha256) = sha256_file(&entry.path())?;
+            files.push(FileChecksum {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                size_bytes,
+                sha256,
+            });
+        }
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(files)
+    } else {
+        let (size_bytes, sha256) = sha256_file(path)?;
+        Ok(vec![FileChecksum {
+            name: String::new(),
+            size_bytes,
+            sha256,
+        }])
+    }
+}
+
+fn sha256_file(path: &std::path::Path) -> std::io::Result<(i64, String)> {
+    use sha2::{Digest, Sha256};
+    use std::io::Read;
+
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    let mut size: i64 = 0;
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        size += n as i64;
+    }
+    Ok((size, format!("{:x}", hasher.finalize())))
 }
 
 /// Mount an OverlayBD TCMU device and copy its contents to a raw disk file.
@@ -212,8 +923,12 @@ async fn do_create_from_overlaybd(
             &mount_id,
             &source.image_ref,
             &source.registry_url,
-            source.upper_data.as_deref(),
-            source.upper_index.as_deref(),
+            &UpperLayerSpec {
+                data_path: source.upper_data.clone(),
+                index_path: source.upper_index.clone(),
+                size_bytes: source.size_bytes,
+                frozen_layers: source.frozen_layers.clone(),
+            },
         )
         .await
         .map_err(|e| anyhow::anyhow!("OverlayBD mount failed: {}", e))?;
@@ -289,6 +1004,8 @@ async fn copy_block_device(
                     bytes_written: bytes_written as i64,
                     error: String::new(),
                     is_final: false,
+                    checksum: String::new(),
+                    checksum_verified: false,
                 }))
                 .await;
         }
@@ -331,8 +1048,16 @@ async fn do_create_blank(path: &str, size_bytes: i64, preallocate: bool) -> anyh
 /// Download a file from `source_url` to `destination_path`, streaming chunks to disk.
 ///
 /// Writes to a `.tmp` sibling file and atomically renames on success.
-/// On failure the partial `.tmp` file is cleaned up.
-async fn do_download(source_url: &str, destination_path: &str) -> anyhow::Result<i64> {
+/// Dropped connections and 5xx responses are retried with an HTTP `Range`
+/// request that picks up after the last byte written; `If-Range` makes the
+/// server send the full body instead if the resource changed in between.
+/// When an expected checksum is given the data is hashed as it streams and a
+/// mismatch fails the download. On failure the partial `.tmp` file is cleaned up.
+async fn do_download(
+    source_url: &str,
+    destination_path: &str,
+    options: &DownloadOptions,
+) -> anyhow::Result<DownloadOutcome> {
     let dest = std::path::Path::new(destination_path);
     let tmp_path = dest.with_extension("tmp");
 
@@ -340,32 +1065,74 @@ async fn do_download(source_url: &str, destination_path: &str) -> anyhow::Result
         tokio::fs::create_dir_all(parent).await?;
     }
 
-    let response = reqwest::get(source_url).await?;
-    let status = response.status();
-    if !status.is_success() {
-        anyhow::bail!("HTTP {status} from {source_url}");
-    }
-
-    let mut stream = response.bytes_stream();
+    let client = reqwest::Client::new();
     let mut file = tokio::fs::File::create(&tmp_path).await?;
-    let mut bytes_written: i64 = 0;
+    let mut state = DownloadState {
+        written: 0,
+        validator: None,
+        digest: options
+            .expected_checksum
+            .as_ref()
+            .map(|c| StreamingDigest::new(c.algorithm)),
+        throttle: options.bandwidth_limit_bytes_per_sec.map(Throttle::new),
+    };
 
-    let result: anyhow::Result<i64> = async {
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-            file.write_all(&chunk).await?;
-            bytes_written += chunk.len() as i64;
+    let result: anyhow::Result<DownloadOutcome> = async {
+        let mut attempt = 1;
+        loop {
+            match download_attempt(&client, source_url, &mut file, &mut state).await {
+                Ok(()) => break,
+                Err(AttemptError::Retryable(e)) if attempt < DOWNLOAD_MAX_ATTEMPTS => {
+                    let delay = DOWNLOAD_RETRY_BASE_DELAY * 2u32.pow(attempt - 1);
+                    warn!(
+                        source = %source_url,
+                        attempt,
+                        resume_from = state.written,
+                        error = %e,
+                        "Download interrupted, retrying in {:?}",
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(AttemptError::Retryable(e) | AttemptError::Fatal(e)) => return Err(e),
+            }
         }
         file.flush().await?;
-        Ok(bytes_written)
+
+        let checksum = match (&options.expected_checksum, state.digest.take()) {
+            (Some(expected), Some(digest)) => {
+                let actual = Checksum {
+                    algorithm: expected.algorithm,
+                    digest: digest.finalize_hex(),
+                };
+                if actual != *expected {
+                    return Err(ChecksumMismatch {
+                        expected: expected.to_string(),
+                        actual: actual.to_string(),
+                    }
+                    .into());
+                }
+                Some(actual.to_string())
+            }
+            _ => None,
+        };
+
+        Ok(DownloadOutcome {
+            bytes_written: state.written as i64,
+            checksum,
+        })
     }
     .await;
 
     match result {
-        Ok(n) => {
+        Ok(outcome) => {
             tokio::fs::rename(&tmp_path, dest).await?;
-            debug!(bytes = n, "Download written to {destination_path}");
-            Ok(n)
+            debug!(
+                bytes = outcome.bytes_written,
+                "Download written to {destination_path}"
+            );
+            Ok(outcome)
         }
         Err(e) => {
             let _ = tokio::fs::remove_file(&tmp_path).await;
@@ -373,3 +1140,549 @@ async fn do_download(source_url: &str, destination_path: &str) -> anyhow::Result
         }
     }
 }
+
+const DOWNLOAD_MAX_ATTEMPTS: u32 = 5;
+const DOWNLOAD_RETRY_BASE_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Per-download knobs carried on `DownloadFileRequest` / `UrlDiskSource`.
+#[derive(Debug, Default)]
+struct DownloadOptions {
+    expected_checksum: Option<Checksum>,
+    bandwidth_limit_bytes_per_sec: Option<u64>,
+}
+
+impl DownloadOptions {
+    fn from_request(
+        expected_checksum: Option<&str>,
+        bandwidth_limit_bytes_per_sec: Option<i64>,
+    ) -> Result<Self, String> {
+        let expected_checksum = expected_checksum
+            .filter(|c| !c.is_empty())
+            .map(Checksum::parse)
+            .transpose()?;
+        let bandwidth_limit_bytes_per_sec = match bandwidth_limit_bytes_per_sec {
+            Some(limit) if limit <= 0 => {
+                return Err("bandwidth_limit_bytes_per_sec must be greater than 0".to_string());
+            }
+            limit => limit.map(|l| l as u64),
+        };
+        Ok(Self {
+            expected_checksum,
+            bandwidth_limit_bytes_per_sec,
+        })
+    }
+}
+
+struct DownloadOutcome {
+    bytes_written: i64,
+    /// Verified `<algorithm>:<hex>` digest, present when one was requested.
+    checksum: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("checksum mismatch: expected {expected}, got {actual}")]
+struct ChecksumMismatch {
+    expected: String,
+    actual: String,
+}
+
+/// The computed digest to report back when `err` is a checksum mismatch.
+fn mismatched_checksum(err: &anyhow::Error) -> String {
+    err.downcast_ref::<ChecksumMismatch>()
+        .map(|m| m.actual.clone())
+        .unwrap_or_default()
+}
+
+/// Progress carried across retries of a single download.
+struct DownloadState {
+    written: u64,
+    /// `ETag` (preferred) or `Last-Modified` of the first full response, sent
+    /// as `If-Range` so a changed resource restarts from scratch.
+    validator: Option<String>,
+    digest: Option<StreamingDigest>,
+    throttle: Option<Throttle>,
+}
+
+enum AttemptError {
+    Retryable(anyhow::Error),
+    Fatal(anyhow::Error),
+}
+
+async fn download_attempt(
+    client: &reqwest::Client,
+    source_url: &str,
+    file: &mut tokio::fs::File,
+    state: &mut DownloadState,
+) -> Result<(), AttemptError> {
+    use reqwest::{StatusCode, header};
+    use tokio::io::AsyncSeekExt;
+
+    let mut request = client.get(source_url);
+    if state.written > 0 {
+        request = request.header(header::RANGE, format!("bytes={}-", state.written));
+        if let Some(validator) = &state.validator {
+            request = request.header(header::IF_RANGE, validator);
+        }
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| AttemptError::Retryable(e.into()))?;
+    let status = response.status();
+
+    if status == StatusCode::PARTIAL_CONTENT && state.written > 0 {
+        let content_range = response
+            .headers()
+            .get(header::CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        if range_start(content_range) != Some(state.written) {
+            return Err(AttemptError::Fatal(anyhow::anyhow!(
+                "server resumed {source_url} at an unexpected offset ({content_range})"
+            )));
+        }
+    } else if status.is_success() {
+        if state.written > 0 {
+            // Server ignored the range or the resource changed: start over.
+            file.set_len(0)
+                .await
+                .map_err(|e| AttemptError::Fatal(e.into()))?;
+            file.seek(std::io::SeekFrom::Start(0))
+                .await
+                .map_err(|e| AttemptError::Fatal(e.into()))?;
+            state.written = 0;
+            if let Some(digest) = state.digest.as_mut() {
+                digest.reset();
+            }
+        }
+        state.validator = response
+            .headers()
+            .get(header::ETAG)
+            .or_else(|| response.headers().get(header::LAST_MODIFIED))
+            .and_then(|v| v.to_str().ok())
+            .map(ToOwned::to_owned);
+    } else if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+        return Err(AttemptError::Retryable(anyhow::anyhow!(
+            "HTTP {status} from {source_url}"
+        )));
+    } else {
+        return Err(AttemptError::Fatal(anyhow::anyhow!(
+            "HTTP {status} from {source_url}"
+        )));
+    }
+
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| AttemptError::Retryable(e.into()))?;
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| AttemptError::Fatal(e.into()))?;
+        state.written += chunk.len() as u64;
+        if let Some(digest) = state.digest.as_mut() {
+            digest.update(&chunk);
+        }
+        if let Some(throttle) = state.throttle.as_mut() {
+            throttle.consume(chunk.len() as u64).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse the first byte position out of a `Content-Range: bytes <start>-<end>/<len>` value.
+fn range_start(content_range: &str) -> Option<u64> {
+    content_range
+        .strip_prefix("bytes ")?
+        .split_once('-')?
+        .0
+        .trim()
+        .parse()
+        .ok()
+}
+
+enum StreamingDigest {
+    Sha256(sha2::Sha256),
+    Sha512(sha2::Sha512),
+}
+
+impl StreamingDigest {
+    fn new(algorithm: ChecksumAlgorithm) -> Self {
+        use sha2::Digest;
+
+        match algorithm {
+            ChecksumAlgorithm::Sha256 => Self::Sha256(sha2::Sha256::new()),
+            ChecksumAlgorithm::Sha512 => Self::Sha512(sha2::Sha512::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        use sha2::Digest;
+
+        match self {
+            Self::Sha256(h) => h.update(data),
+            Self::Sha512(h) => h.update(data),
+        }
+    }
+
+    fn reset(&mut self) {
+        use sha2::Digest;
+
+        match self {
+            Self::Sha256(h) => Digest::reset(h),
+            Self::Sha512(h) => Digest::reset(h),
+        }
+    }
+
+    fn finalize_hex(self) -> String {
+        use sha2::Digest;
+
+        match self {
+            Self::Sha256(h) => format!("{:x}", h.finalize()),
+            Self::Sha512(h) => format!("{:x}", h.finalize()),
+        }
+    }
+}
+
+/// Average-rate limiter: sleeps whenever the bytes seen so far are ahead of
+/// what `bytes_per_sec` allows for the elapsed time.
+struct Throttle {
+    bytes_per_sec: u64,
+    started: std::time::Instant,
+    bytes: u64,
+}
+
+impl Throttle {
+    fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec,
+            started: std::time::Instant::now(),
+            bytes: 0,
+        }
+    }
+
+    async fn consume(&mut self, bytes: u64) {
+        self.bytes += bytes;
+        let delay = throttle_delay(self.bytes, self.started.elapsed(), self.bytes_per_sec);
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+fn throttle_delay(
+    bytes: u64,
+    elapsed: std::time::Duration,
+    bytes_per_sec: u64,
+) -> std::time::Duration {
+    let allowed_at = std::time::Duration::from_secs_f64(bytes as f64 / bytes_per_sec as f64);
+    allowed_at.saturating_sub(elapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum_files_hashes_directory_entries_in_name_order() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b"), b"").unwrap();
+        std::fs::write(dir.path().join("a"), b"abc").unwrap();
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
+
+        let files = checksum_files(dir.path()).unwrap();
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].name, "a");
+        assert_eq!(files[0].size_bytes, 3);
+        assert_eq!(
+            files[0].sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(files[1].name, "b");
+        assert_eq!(
+            files[1].sha256,
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    fn upload_messages(
+        chunks: &[&[u8]],
+    ) -> impl futures::Stream<Item = Result<UploadFileRequest, Status>> + Unpin {
+        let messages: Vec<_> = chunks
+            .iter()
+            .map(|chunk| {
+                Ok(UploadFileRequest {
+                    payload: Some(Payload::Data(chunk.to_vec())),
+                })
+            })
+            .collect();
+        futures::stream::iter(messages)
+    }
+
+    fn upload_header(dest: &std::path::Path, offset: i64, end_offset: i64) -> UploadFileHeader {
+        UploadFileHeader {
+            transfer_id: "t".to_string(),
+            destination_path: dest.to_string_lossy().into_owned(),
+            offset,
+            end_offset,
+            finalize: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn receive_upload_resumes_from_offset_and_finalizes() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("disk.img");
+
+        let header = upload_header(&dest, 0, 6);
+        let written = receive_upload(&header, upload_messages(&[b"abc", b"def"]))
+            .await
+            .unwrap();
+        assert_eq!(written, 6);
+        assert!(!dest.exists());
+
+        // Replaying from offset 4 drops the unacknowledged tail "ef".
+        let mut header = upload_header(&dest, 4, 7);
+        header.finalize = true;
+        let written = receive_upload(&header, upload_messages(&[b"XYZ"]))
+            .await
+            .unwrap();
+        assert_eq!(written, 7);
+        assert_eq!(std::fs::read(&dest).unwrap(), b"abcdXYZ");
+        assert!(!upload_partial_path(&dest).exists());
+    }
+
+    #[tokio::test]
+    async fn receive_upload_rejects_gaps_and_short_streams() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("disk.img");
+
+        let gap = receive_upload(&upload_header(&dest, 10, 12), upload_messages(&[b"ab"]))
+            .await
+            .unwrap_err();
+        assert_eq!(gap.code(), tonic::Code::FailedPrecondition);
+
+        let mut header = upload_header(&dest, 0, 5);
+        header.finalize = true;
+        let short = receive_upload(&header, upload_messages(&[b"abc"]))
+            .await
+            .unwrap_err();
+        assert_eq!(short.code(), tonic::Code::Aborted);
+        assert!(!dest.exists());
+
+        let long = receive_upload(&upload_header(&dest, 0, 2), upload_messages(&[b"abc"]))
+            .await
+            .unwrap_err();
+        assert_eq!(long.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn receive_upload_never_replaces_an_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("disk.img");
+        std::fs::write(&dest, b"keep").unwrap();
+
+        let exists = receive_upload(&upload_header(&dest, 0, 3), upload_messages(&[b"abc"]))
+            .await
+            .unwrap_err();
+        assert_eq!(exists.code(), tonic::Code::AlreadyExists);
+        assert!(!upload_partial_path(&dest).exists());
+
+        // A file that shows up between chunks is not overwritten on finalize.
+        std::fs::remove_file(&dest).unwrap();
+        receive_upload(&upload_header(&dest, 0, 3), upload_messages(&[b"abc"]))
+            .await
+            .unwrap();
+        std::fs::write(&dest, b"keep").unwrap();
+        let mut header = upload_header(&dest, 3, 6);
+        header.finalize = true;
+        let raced = receive_upload(&header, upload_messages(&[b"def"]))
+            .await
+            .unwrap_err();
+        assert_eq!(raced.code(), tonic::Code::AlreadyExists);
+        assert_eq!(std::fs::read(&dest).unwrap(), b"keep");
+    }
+
+    #[test]
+    fn checksum_files_single_file_has_empty_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.raw");
+        std::fs::write(&path, b"abc").unwrap();
+
+        let files = checksum_files(&path).unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "");
+        assert_eq!(files[0].size_bytes, 3);
+    }
+
+    #[test]
+    fn delete_path_reports_freed_bytes_and_tolerates_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let snap = dir.path().join("snap");
+        std::fs::create_dir_all(snap.join("nested")).unwrap();
+        std::fs::write(snap.join("memory-ranges"), vec![0u8; 10]).unwrap();
+        std::fs::write(snap.join("nested").join("state.json"), b"{}").unwrap();
+
+        assert_eq!(
+            delete_path(&snap, false).unwrap_err().kind(),
+            std::io::ErrorKind::IsADirectory
+        );
+        assert_eq!(delete_path(&snap, true).unwrap(), 12);
+        assert!(!snap.exists());
+        assert_eq!(delete_path(&snap, true).unwrap(), 0);
+    }
+
+    fn file_member(name: &str, source: &std::path::Path) -> ArchiveMember {
+        ArchiveMember {
+            name: name.to_string(),
+            source_path: source.display().to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn archive_round_trips_and_reads_single_members() {
+        let dir = tempfile::tempdir().unwrap();
+        let disk = dir.path().join("root.raw");
+        std::fs::write(&disk, vec![7u8; 4096]).unwrap();
+        let memory = dir.path().join("memory");
+        std::fs::create_dir_all(&memory).unwrap();
+        std::fs::write(memory.join("state.json"), b"[]").unwrap();
+        let archive = dir.path().join("bundle.tar");
+        let members = vec![
+            ArchiveMember {
+                name: "manifest.json".to_string(),
+                content: b"{}".to_vec(),
+                ..Default::default()
+            },
+            file_member("disks/root.raw", &disk),
+            ArchiveMember {
+                remove_source: true,
+                ..file_member("memory", &memory)
+            },
+        ];
+
+        assert!(pack_archive(&archive, &members).unwrap() > 4096);
+        assert!(disk.exists());
+        assert!(!memory.exists());
+        assert_eq!(
+            read_archive_file(&archive, std::path::Path::new("manifest.json")).unwrap(),
+            b"{}"
+        );
+        assert_eq!(
+            read_archive_file(&archive, std::path::Path::new("missing.json"))
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::NotFound
+        );
+
+        let dest = dir.path().join("out").join("disk.raw");
+        let checksum =
+            extract_archive_member(&archive, std::path::Path::new("disks/root.raw"), &dest)
+                .unwrap();
+        assert_eq!(checksum.size_bytes, 4096);
+        assert_eq!(checksum.sha256, sha256_file(&disk).unwrap().1);
+        let state = dir.path().join("out").join("state.json");
+        extract_archive_member(&archive, std::path::Path::new("memory/state.json"), &state)
+            .unwrap();
+        assert_eq!(std::fs::read(state).unwrap(), b"[]");
+    }
+
+    #[test]
+    fn pack_archive_rejects_escaping_member_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("bundle.tar");
+        let members = vec![ArchiveMember {
+            name: "../manifest.json".to_string(),
+            content: b"{}".to_vec(),
+            ..Default::default()
+        }];
+
+        let err = pack_archive(&archive, &members).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(!archive.exists());
+    }
+
+    #[test]
+    fn extract_archive_member_rejects_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("evil.tar");
+        let mut builder = tar::Builder::new(std::fs::File::create(&archive).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "manifest.json", "/etc/shadow")
+            .unwrap();
+        builder.finish().unwrap();
+        drop(builder);
+
+        let err = extract_archive_member(
+            &archive,
+            std::path::Path::new("manifest.json"),
+            &dir.path().join("out"),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn validate_deletable_path_rejects_unsafe_paths() {
+        use std::path::Path;
+
+        assert!(validate_deletable_path(Path::new("/var/lib/qarax/pools/x")).is_ok());
+        assert!(validate_deletable_path(Path::new("relative/dir")).is_err());
+        assert!(validate_deletable_path(Path::new("/var/lib/../../etc")).is_err());
+        assert!(validate_deletable_path(Path::new("/")).is_err());
+    }
+
+    #[test]
+    fn streaming_digest_matches_one_shot_and_resets() {
+        let mut digest = StreamingDigest::new(ChecksumAlgorithm::Sha256);
+        digest.update(b"stale");
+        digest.reset();
+        digest.update(b"a");
+        digest.update(b"bc");
+        assert_eq!(
+            digest.finalize_hex(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let mut digest = StreamingDigest::new(ChecksumAlgorithm::Sha512);
+        digest.update(b"abc");
+        assert!(digest.finalize_hex().starts_with("ddaf35a193617aba"));
+    }
+
+    #[test]
+    fn range_start_parses_content_range() {
+        assert_eq!(range_start("bytes 1024-2047/4096"), Some(1024));
+        assert_eq!(range_start("bytes */4096"), None);
+        assert_eq!(range_start(""), None);
+    }
+
+    #[test]
+    fn throttle_delay_only_when_ahead_of_budget() {
+        use std::time::Duration;
+
+        assert_eq!(
+            throttle_delay(2_000, Duration::from_millis(500), 1_000),
+            Duration::from_millis(1_500)
+        );
+        assert_eq!(
+            throttle_delay(500, Duration::from_secs(1), 1_000),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn download_options_validate_inputs() {
+        assert!(DownloadOptions::from_request(Some("md5:abc"), None).is_err());
+        assert!(DownloadOptions::from_request(None, Some(0)).is_err());
+
+        let options = DownloadOptions::from_request(Some(""), Some(1_000)).unwrap();
+        assert!(options.expected_checksum.is_none());
+        assert_eq!(options.bandwidth_limit_bytes_per_sec, Some(1_000));
+    }
+}
@@ -1,4 +1,5 @@
 use futures::Stream;
+use std::collections::HashMap;
 use std::path::Path;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -8,17 +9,25 @@ use tracing::{debug, error, info, instrument, warn};
 
 use crate::cloud_hypervisor::VmManager;
 use crate::firecracker::FirecrackerManager;
+use crate::overlaybd::{ImageUsage, RegistryCredentials, UpperResize, gc};
 use crate::rpc::node::{
     AddDeviceRequest, AddDiskDeviceRequest, AddNetworkDeviceRequest, AttachNetworkRequest,
     AttachNetworkResponse, AttachStoragePoolRequest, AttachStoragePoolResponse, ConsoleInput,
     ConsoleLogResponse, ConsoleOutput, ConsolePtyPathResponse, DetachNetworkRequest,
-    DetachNetworkResponse, DetachStoragePoolRequest, DeviceCounters, ExecVmRequest, ExecVmResponse,
-    GpuInfo, HypervisorType, ImportOverlayBdRequest, ImportOverlayBdResponse, NodeInfo, NumaNode,
-    PreflightCheck, PreflightImageRequest, PreflightImageResponse, ReceiveMigrationRequest,
-    ReceiveMigrationResponse, RemoveDeviceRequest, ResizeDiskRequest, ResizeVmRequest,
-    RestoreVmRequest, SendMigrationRequest, SnapshotVmRequest, StoragePoolKind,
-    SyncNetworkIsolationRequest, SyncVmFirewallRequest, SyncVpcOverlaysRequest, VmConfig,
-    VmCounters, VmId, VmList, VmState, vm_service_server::VmService,
+    DetachNetworkResponse, DetachStoragePoolRequest, DeviceCounters, DiscoverLunsRequest,
+    DiscoverLunsResponse, DiscoveredLun, ExecVmRequest, ExecVmResponse, GpuInfo, HypervisorType,
+    ImportOverlayBdRequest, ImportOverlayBdResponse, ManageStorageImageRequest, NodeInfo, NumaNode,
+    OverlayBdCacheInfo, OverlayBdCachedImage, OverlayBdGcImage, OverlayBdGcRequest,
+    OverlayBdGcResponse, PreflightCheck, PreflightImageRequest, PreflightImageResponse,
+    PrewarmOverlayBdImageRequest, ReceiveMigrationRequest, ReceiveMigrationResponse,
+    RemoveDeviceRequest, ResizeDiskRequest, ResizeOverlayBdUpperRequest,
+    ResizeOverlayBdUpperResponse, ResizeVmRequest, ResolveImageDigestRequest,
+    ResolveImageDigestResponse, RestoreVmRequest, SendMigrationRequest, SnapshotVmRequest,
+    StorageImageOperation, StoragePathHealth, StoragePoolCapacity, StoragePoolHealthRequest,
+    StoragePoolHealthResponse, StoragePoolKind, SyncDhcpReservationsRequest, SyncNetworkDnsRequest,
+    SyncNetworkIsolationRequest, SyncStretchedNetworkRequest, SyncVmFirewallRequest,
+    SyncVmNatRequest, SyncVpcOverlaysRequest, VmConfig, VmCounters, VmFirewallCounters,
+    VmFirewallDenied, VmId, VmList, VmState, vm_service_server::VmService,
 };
 use crate::vmm::{VmmError, VmmManager};
 use common::cpu_list::expand_cpu_list;
@@ -81,6 +90,23 @@ impl VmServiceImpl {
         None
     }
 
+    /// TAP device of each of a VM's NICs, keyed by lowercase guest MAC.
+    async fn vm_taps(&self, vm_id: &str) -> HashMap<String, String> {
+        let Some(manager) = self.find_manager(vm_id).await else {
+            return HashMap::new();
+        };
+        let Ok(state) = manager.get_vm_info(vm_id).await else {
+            return HashMap::new();
+        };
+        state
+            .config
+            .map(|config| config.networks)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|net| Some((net.mac?.to_ascii_lowercase(), net.tap?)))
+            .collect()
+    }
+
     /// Run a unit-returning VM operation, logging start/success/failure uniformly.
     async fn run_vm_op<F, Fut>(
         &self,
@@ -231,7 +257,16 @@ impl VmService for VmServiceImpl {
         let req = request.into_inner();
         info!("Restoring VM: {}", req.vm_id);
         let manager = self.manager_for_create(req.hypervisor)?;
-        match manager.restore_vm(&req.vm_id, &req.source_url).await {
+        match manager
+            .restore_vm(
+                &req.vm_id,
+                &req.source_url,
+                req.config,
+                req.disks,
+                &req.upper_copies,
+            )
+            .await
+        {
             Ok(()) => {
                 info!("VM {} restored successfully", req.vm_id);
                 Ok(Response::new(()))
@@ -566,6 +601,29 @@ impl VmService for VmServiceImpl {
         }
     }
 
+    async fn resolve_image_digest(
+        &self,
+        request: Request<ResolveImageDigestRequest>,
+    ) -> Result<Response<ResolveImageDigestResponse>, Status> {
+        let req = request.into_inner();
+        let manager = self
+            .ch_manager
+            .overlaybd_manager()
+            .ok_or_else(|| Status::unimplemented("OverlayBD not configured on this node"))?;
+
+        let credentials = RegistryCredentials::from(req.registry_credentials);
+        match manager.resolve_digest(&req.image_ref, &credentials).await {
+            Ok(digest) => Ok(Response::new(ResolveImageDigestResponse { digest })),
+            Err(e) => {
+                error!("Failed to resolve digest of {}: {}", req.image_ref, e);
+                Err(Status::unavailable(format!(
+                    "Failed to resolve digest of {}: {}",
+                    req.image_ref, e
+                )))
+            }
+        }
+    }
+
     async fn preflight_image(
         &self,
         request: Request<PreflightImageRequest>,
@@ -605,6 +663,7 @@ impl VmService for VmServiceImpl {
             .overlaybd_manager()
             .ok_or_else(|| Status::unimplemented("OverlayBD not configured on this node"))?;
 
+        let credentials = RegistryCredentials::from(req.registry_credentials);
         let response = match manager
             .preflight_boot(
                 &req.image_ref,
@@ -612,6 +671,7 @@ impl VmService for VmServiceImpl {
                 &architecture,
                 boot_mode,
                 self.ch_manager.qarax_init_binary(),
+                &credentials,
             )
             .await
         {
@@ -646,20 +706,22 @@ impl VmService for VmServiceImpl {
             .overlaybd_manager()
             .ok_or_else(|| Status::unimplemented("OverlayBD not configured on this node"))?;
 
+        let credentials = RegistryCredentials::from(req.registry_credentials);
         match obd_manager
-            .import_image(&req.image_ref, &req.registry_url)
+            .import_image(&req.image_ref, &req.registry_url, &credentials)
             .await
         {
-            Ok((target_ref, size_bytes)) => {
+            Ok(imported) => {
                 info!(
                     "OverlayBD image imported: {} ({} bytes)",
-                    target_ref, size_bytes
+                    imported.image_ref, imported.size_bytes
                 );
                 Ok(Response::new(ImportOverlayBdResponse {
-                    image_ref: target_ref,
+                    image_ref: imported.image_ref,
                     digest: String::new(), // digest resolved by node at mount time
                     available: true,
-                    size_bytes,
+                    size_bytes: imported.size_bytes,
+                    content_size_bytes: imported.content_size_bytes,
                 }))
             }
             Err(e) => {
@@ -669,6 +731,149 @@ impl VmService for VmServiceImpl {
         }
     }
 
+    async fn get_overlay_bd_cache(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<OverlayBdCacheInfo>, Status> {
+        let obd_manager = self
+            .ch_manager
+            .overlaybd_manager()
+            .ok_or_else(|| Status::unimplemented("OverlayBD not configured on this node"))?;
+
+        let (stats, images) = obd_manager.cache_report().await;
+        Ok(Response::new(OverlayBdCacheInfo {
+            used_bytes: stats.used_bytes,
+            max_bytes: stats.max_bytes.unwrap_or(0),
+            hits: stats.hits,
+            misses: stats.misses,
+            evictions: stats.evictions,
+            images: images.into_iter().map(cached_image).collect(),
+        }))
+    }
+
+    async fn prewarm_overlay_bd_image(
+        &self,
+        request: Request<PrewarmOverlayBdImageRequest>,
+    ) -> Result<Response<OverlayBdCachedImage>, Status> {
+        let req = request.into_inner();
+        info!(
+            "Pre-warming OverlayBD image {} from {}",
+            req.image_ref, req.registry_url
+        );
+
+        let obd_manager = self
+            .ch_manager
+            .overlaybd_manager()
+            .ok_or_else(|| Status::unimplemented("OverlayBD not configured on this node"))?;
+
+        match obd_manager.prewarm(&req.image_ref, &req.registry_url).await {
+            Ok(usage) => Ok(Response::new(cached_image(usage))),
+            Err(e) => {
+                error!(
+                    "Failed to pre-warm OverlayBD image {}: {}",
+                    req.image_ref, e
+                );
+                Err(Status::internal(format!(
+                    "OverlayBD pre-warm failed: {}",
+                    e
+                )))
+            }
+        }
+    }
+
+    async fn garbage_collect_overlay_bd_images(
+        &self,
+        request: Request<OverlayBdGcRequest>,
+    ) -> Result<Response<OverlayBdGcResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "Collecting unreferenced OverlayBD images in {} (keeping {} ref(s), dry_run={})",
+            req.registry_url,
+            req.keep_refs.len() + req.keep_digests.len(),
+            req.dry_run
+        );
+
+        let keep = gc::KeepSet::new(&req.registry_url, &req.keep_refs, &req.keep_digests);
+        match gc::collect(&req.registry_url, &keep, req.dry_run).await {
+            Ok(report) => Ok(Response::new(OverlayBdGcResponse {
+                images: report
+                    .candidates
+                    .into_iter()
+                    .map(|c| OverlayBdGcImage {
+                        repository: c.repository,
+                        tags: c.tags,
+                        digest: c.digest,
+                        size_bytes: c.size_bytes,
+                    })
+                    .collect(),
+                kept: report.kept as u32,
+                reclaimed_bytes: report.reclaimed_bytes,
+            })),
+            Err(e) => {
+                error!("OverlayBD image GC in {} failed: {}", req.registry_url, e);
+                Err(Status::internal(format!(
+                    "OverlayBD image GC failed: {}",
+                    e
+                )))
+            }
+        }
+    }
+
+    async fn resize_overlay_bd_upper(
+        &self,
+        request: Request<ResizeOverlayBdUpperRequest>,
+    ) -> Result<Response<ResizeOverlayBdUpperResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "Resizing OverlayBD upper layer {} to {} bytes",
+            req.upper_data, req.new_size
+        );
+
+        let obd_manager = self
+            .ch_manager
+            .overlaybd_manager()
+            .ok_or_else(|| Status::unimplemented("OverlayBD not configured on this node"))?;
+
+        match obd_manager
+            .resize_upper(
+                Path::new(&req.upper_data),
+                Path::new(&req.upper_index),
+                Path::new(&req.frozen_layer),
+                req.new_size,
+            )
+            .await
+        {
+            Ok(UpperResize::NotCreated) => Ok(Response::new(ResizeOverlayBdUpperResponse {
+                frozen: false,
+                error: String::new(),
+            })),
+            Ok(UpperResize::Resized) => Ok(Response::new(ResizeOverlayBdUpperResponse {
+                frozen: true,
+                error: String::new(),
+            })),
+            Ok(UpperResize::FrozenOnly(e)) => {
+                error!(
+                    "Froze OverlayBD upper layer {} into {} but could not replace it: {}",
+                    req.upper_data, req.frozen_layer, e
+                );
+                Ok(Response::new(ResizeOverlayBdUpperResponse {
+                    frozen: true,
+                    error: e.to_string(),
+                }))
+            }
+            Err(e) => {
+                error!(
+                    "Failed to resize OverlayBD upper layer {}: {}",
+                    req.upper_data, e
+                );
+                Err(Status::internal(format!(
+                    "OverlayBD upper resize failed: {}",
+                    e
+                )))
+            }
+        }
+    }
+
     async fn read_console_log(
         &self,
         request: Request<VmId>,
@@ -980,6 +1185,7 @@ impl VmService for VmServiceImpl {
                 Ok(Response::new(AttachStoragePoolResponse {
                     success: true,
                     message: msg,
+                    capacity: pool_capacity(backend.as_ref(), pool_id, &req.config_json).await,
                 }))
             }
             Err(e) => {
@@ -987,6 +1193,7 @@ impl VmService for VmServiceImpl {
                 Ok(Response::new(AttachStoragePoolResponse {
                     success: false,
                     message: e.to_string(),
+                    capacity: None,
                 }))
             }
         }
@@ -1000,8 +1207,25 @@ impl VmService for VmServiceImpl {
         info!("Attaching network bridge: {}", req.bridge_name);
 
         let bridged = !req.parent_interface.is_empty();
+        if req.vlan_id != 0 && !bridged {
+            return Err(Status::invalid_argument(
+                "vlan_id requires parent_interface",
+            ));
+        }
 
-        if bridged {
+        if req.vlan_id != 0 {
+            // VLAN mode: bridge a tagged sub-interface; the parent is untouched
+            let vlan_id = u16::try_from(req.vlan_id).map_err(|_| {
+                Status::invalid_argument(format!("Invalid VLAN id {}", req.vlan_id))
+            })?;
+            crate::networking::bridge::create_vlan_bridge(
+                &req.bridge_name,
+                &req.parent_interface,
+                vlan_id,
+            )
+            .await
+            .map_err(|e| Status::internal(format!("Failed to bridge VLAN: {}", e)))?;
+        } else if bridged {
             // Bridged mode: bridge an existing NIC (its IP moves to the bridge)
             crate::networking::bridge::bridge_interface(&req.bridge_name, &req.parent_interface)
                 .await
@@ -1015,30 +1239,63 @@ impl VmService for VmServiceImpl {
                 .await
                 .map_err(|e| Status::internal(format!("Failed to create bridge: {}", e)))?;
 
+            if !req.gateway_mac.is_empty() {
+                crate::networking::bridge::set_anycast_gateway(&req.bridge_name, &req.gateway_mac)
+                    .await
+                    .map_err(|e| {
+                        Status::internal(format!("Failed to set anycast gateway: {}", e))
+                    })?;
+            }
+
             crate::networking::bridge::set_bridge_ip(&req.bridge_name, &gateway_cidr)
                 .await
                 .map_err(|e| Status::internal(format!("Failed to set bridge IP: {}", e)))?;
+
+            // Dual-stack: the bridge routes the /64 and advertises it to guests.
+            // Bridged networks leave that to the upstream router.
+            if !req.subnet6.is_empty() {
+                crate::networking::bridge::set_bridge_ip(
+                    &req.bridge_name,
+                    &format!("{}/64", req.gateway6),
+                )
+                .await
+                .map_err(|e| Status::internal(format!("Failed to set bridge IPv6: {}", e)))?;
+                crate::networking::ra::start_router_advertisements(&req.bridge_name, &req.subnet6)
+                    .await
+                    .map_err(|e| {
+                        Status::internal(format!("Failed to start router advertisements: {}", e))
+                    })?;
+            }
         }
 
-        // Start DHCP server (both modes need DHCP for guest VMs)
+        // Start DHCP server (both modes need DHCP for guest VMs). Stretched
+        // networks send no range: SyncStretchedNetwork picks the one host that
+        // serves DHCP for the whole segment.
         let dns = if req.dns.is_empty() {
             &req.gateway
         } else {
             &req.dns
         };
-        crate::networking::dhcp::start_dhcp_server(
-            &req.bridge_name,
-            &req.dhcp_range_start,
-            &req.dhcp_range_end,
-            &req.gateway,
-            dns,
-        )
-        .await
-        .map_err(|e| Status::internal(format!("Failed to start DHCP server: {}", e)))?;
+        crate::networking::dhcp::set_dhcp_reservations(&req.bridge_name, &req.dhcp_reservations)
+            .map_err(|e| Status::invalid_argument(format!("Invalid DHCP reservations: {e}")))?;
+        if !req.dhcp_range_start.is_empty() {
+            crate::networking::dhcp::start_dhcp_server(
+                &req.bridge_name,
+                &req.subnet,
+                &req.dhcp_range_start,
+                &req.dhcp_range_end,
+                &req.gateway,
+                dns,
+                req.dhcp_options.as_ref(),
+            )
+            .await
+            .map_err(|e| Status::internal(format!("Failed to start DHCP server: {}", e)))?;
+        }
 
         // NAT is only needed in isolated mode — bridged mode shares the upstream network
         if !bridged {
-            crate::networking::iptables::setup_nat(&req.bridge_name, &req.subnet)
+            let subnet6 = (!req.subnet6.is_empty()).then_some(req.subnet6.as_str());
+            crate::networking::firewall::setup_nat(&req.bridge_name, &req.subnet, subnet6)
                 .await
                 .map_err(|e| Status::internal(format!("Failed to setup NAT: {}", e)))?;
         }
@@ -1061,9 +1318,25 @@ impl VmService for VmServiceImpl {
         if let Err(e) = crate::networking::dhcp::stop_dhcp_server(&req.bridge_name).await {
             warn!("Failed to stop DHCP server for {}: {}", req.bridge_name, e);
         }
+        if let Err(e) = crate::networking::ra::stop_router_advertisements(&req.bridge_name).await {
+            warn!(
+                "Failed to stop router advertisements for {}: {}",
+                req.bridge_name, e
+            );
+        }
+        if let Err(e) = crate::networking::dns::stop_network_dns(&req.bridge_name).await {
+            warn!("Failed to stop DNS resolver for {}: {}", req.bridge_name, e);
+        }
+        if let Err(e) = crate::networking::dhcp::forget_dhcp_state(&req.bridge_name).await {
+            warn!("Failed to clear DHCP leases for {}: {}", req.bridge_name, e);
+        }
 
-        if let Err(e) =
-            crate::networking::iptables::teardown_network_isolation(&req.bridge_name).await
+        let subnet6 = (!req.subnet6.is_empty()).then_some(req.subnet6.as_str());
+        if let Err(e) = crate::networking::firewall::teardown_network_isolation(
+            &req.bridge_name,
+            subnet6.is_some(),
+        )
+        .await
         {
             warn!(
                 "Failed to tear down network isolation rules for {}: {}",
@@ -1073,12 +1346,25 @@ impl VmService for VmServiceImpl {
 
         if !req.subnet.is_empty()
             && let Err(e) =
-                crate::networking::iptables::teardown_nat(&req.bridge_name, &req.subnet).await
+                crate::networking::firewall::teardown_nat(&req.bridge_name, &req.subnet, subnet6)
+                    .await
         {
             warn!("Failed to tear down NAT for {}: {}", req.bridge_name, e);
         }
 
-        if crate::networking::bridge::is_bridged_interface(&req.bridge_name).await {
+        if let Err(e) = crate::networking::vxlan::remove_stretched_ports(&req.bridge_name).await {
+            warn!(
+                "Failed to remove stretched network ports of {}: {}",
+                req.bridge_name, e
+            );
+        }
+
+        if crate::networking::bridge::is_vlan_bridge(&req.bridge_name).await {
+            // VLAN mode: delete the sub-interface and bridge
+            if let Err(e) = crate::networking::bridge::delete_vlan_bridge(&req.bridge_name).await {
+                warn!("Failed to delete VLAN bridge {}: {}", req.bridge_name, e);
+            }
+        } else if crate::networking::bridge::is_bridged_interface(&req.bridge_name).await {
             // Bridged mode: move IP back to parent NIC and delete bridge
             if let Err(e) = crate::networking::bridge::unbridge_interface(&req.bridge_name).await {
                 warn!("Failed to unbridge {}: {}", req.bridge_name, e);
@@ -1099,9 +1385,10 @@ impl VmService for VmServiceImpl {
         request: Request<SyncNetworkIsolationRequest>,
     ) -> Result<Response<()>, Status> {
         let req = request.into_inner();
-        crate::networking::iptables::sync_network_isolation(
+        crate::networking::firewall::sync_network_isolation(
             &req.bridge_name,
             &req.local_subnet,
+            (!req.local_subnet6.is_empty()).then_some(req.local_subnet6.as_str()),
             &req.blocked_subnets,
             &req.nat_exempt_subnets,
         )
@@ -1121,17 +1408,85 @@ impl VmService for VmServiceImpl {
         Ok(Response::new(()))
     }
 
+    async fn sync_stretched_network(
+        &self,
+        request: Request<SyncStretchedNetworkRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.into_inner();
+        crate::networking::vxlan::sync_stretched_network(&req)
+            .await
+            .map_err(|e| Status::internal(format!("Failed to sync stretched network: {e}")))?;
+        Ok(Response::new(()))
+    }
+
+    async fn sync_vm_nat(
+        &self,
+        request: Request<SyncVmNatRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.into_inner();
+        crate::networking::floating_ip::sync_vm_nat(&req)
+            .await
+            .map_err(|e| Status::internal(format!("Failed to sync VM NAT: {e}")))?;
+        Ok(Response::new(()))
+    }
+
+    async fn sync_network_dns(
+        &self,
+        request: Request<SyncNetworkDnsRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.into_inner();
+        crate::networking::dns::sync_network_dns(&req)
+            .await
+            .map_err(|e| Status::internal(format!("Failed to sync network DNS: {e}")))?;
+        Ok(Response::new(()))
+    }
+
+    async fn sync_dhcp_reservations(
+        &self,
+        request: Request<SyncDhcpReservationsRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.into_inner();
+        crate::networking::dhcp::set_dhcp_reservations(&req.bridge_name, &req.reservations)
+            .map_err(|e| Status::internal(format!("Failed to sync DHCP reservations: {e}")))?;
+        Ok(Response::new(()))
+    }
+
     async fn sync_vm_firewall(
         &self,
         request: Request<SyncVmFirewallRequest>,
     ) -> Result<Response<()>, Status> {
         let req = request.into_inner();
-        crate::networking::iptables::sync_vm_firewall(&req.vm_id, &req.interfaces)
+        crate::networking::firewall::sync_vm_firewall(&req.vm_id, &req.interfaces, req.log_denied)
             .await
             .map_err(|e| Status::internal(format!("Failed to sync VM firewall: {e}")))?;
+        let taps = self.vm_taps(&req.vm_id).await;
+        crate::networking::port_security::sync_vm_port_security(&req.vm_id, &req.ports, &taps)
+            .await
+            .map_err(|e| Status::internal(format!("Failed to sync VM anti-spoofing: {e}")))?;
         Ok(Response::new(()))
     }
 
+    async fn get_vm_firewall_counters(
+        &self,
+        request: Request<VmId>,
+    ) -> Result<Response<VmFirewallCounters>, Status> {
+        let vm_id = request.into_inner().id;
+        let counters = crate::networking::firewall::vm_firewall_counters(&vm_id)
+            .await
+            .map_err(|e| Status::internal(format!("Failed to read VM firewall counters: {e}")))?;
+        Ok(Response::new(VmFirewallCounters { counters }))
+    }
+
+    async fn get_vm_firewall_denied(
+        &self,
+        request: Request<VmId>,
+    ) -> Result<Response<VmFirewallDenied>, Status> {
+        let vm_id = request.into_inner().id;
+        Ok(Response::new(VmFirewallDenied {
+            packets: crate::networking::firewall_log::denied_packets(&vm_id),
+        }))
+    }
+
     async fn detach_storage_pool(
         &self,
         request: Request<DetachStoragePoolRequest>,
@@ -1156,6 +1511,108 @@ impl VmService for VmServiceImpl {
         Ok(Response::new(()))
     }
 
+    async fn manage_storage_image(
+        &self,
+        request: Request<ManageStorageImageRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.into_inner();
+        let kind = StoragePoolKind::try_from(req.pool_kind)
+            .map_err(|_| Status::invalid_argument("unknown storage pool kind"))?;
+        let operation = StorageImageOperation::try_from(req.operation)
+            .map_err(|_| Status::invalid_argument("unknown storage image operation"))?;
+        let config: serde_json::Value = serde_json::from_str(&req.config_json)
+            .map_err(|e| Status::invalid_argument(format!("invalid config_json: {e}")))?;
+        info!("Storage image {:?} (kind={:?})", operation, kind);
+
+        let backend = self.ch_manager.storage_backend(kind).ok_or_else(|| {
+            Status::unimplemented(format!("{:?} storage backend not configured", kind))
+        })?;
+
+        let result = match operation {
+            StorageImageOperation::Create => backend.create_image(&config, req.size_bytes).await,
+            StorageImageOperation::Resize => backend.resize_image(&config, req.size_bytes).await,
+            StorageImageOperation::Snapshot => backend.snapshot_image(&config, &req.snapshot).await,
+            StorageImageOperation::Clone => {
+                backend
+                    .clone_image(&config, &req.source_image, &req.snapshot)
+                    .await
+            }
+            StorageImageOperation::Remove => backend.remove_image(&config).await,
+        };
+        result.map_err(|e| {
+            error!("Storage image {:?} failed: {}", operation, e);
+            Status::internal(format!("{operation:?} failed: {e}"))
+        })?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn get_storage_pool_health(
+        &self,
+        request: Request<StoragePoolHealthRequest>,
+    ) -> Result<Response<StoragePoolHealthResponse>, Status> {
+        let req = request.into_inner();
+        let kind = StoragePoolKind::try_from(req.pool_kind)
+            .map_err(|_| Status::invalid_argument("unknown storage pool kind"))?;
+
+        let backend = self.ch_manager.storage_backend(kind).ok_or_else(|| {
+            Status::unimplemented(format!("{:?} storage backend not configured", kind))
+        })?;
+
+        let paths = backend.path_health(&req.config_json).await.map_err(|e| {
+            error!(
+                "Failed to check health of storage pool {}: {}",
+                req.pool_id, e
+            );
+            Status::internal(format!("Health check failed: {}", e))
+        })?;
+
+        Ok(Response::new(StoragePoolHealthResponse {
+            paths: paths
+                .into_iter()
+                .map(|p| StoragePathHealth {
+                    path: p.path,
+                    state: p.state,
+                    healthy: p.healthy,
+                })
+                .collect(),
+            capacity: pool_capacity(backend.as_ref(), &req.pool_id, &req.config_json).await,
+        }))
+    }
+
+    async fn discover_luns(
+        &self,
+        request: Request<DiscoverLunsRequest>,
+    ) -> Result<Response<DiscoverLunsResponse>, Status> {
+        let req = request.into_inner();
+        let kind = StoragePoolKind::try_from(req.pool_kind)
+            .map_err(|_| Status::invalid_argument("unknown storage pool kind"))?;
+
+        let backend = self.ch_manager.storage_backend(kind).ok_or_else(|| {
+            Status::unimplemented(format!("{:?} storage backend not configured", kind))
+        })?;
+
+        let luns = backend.discover_luns(&req.config_json).await.map_err(|e| {
+            error!(
+                "Failed to discover LUNs of storage pool {}: {}",
+                req.pool_id, e
+            );
+            Status::internal(format!("LUN discovery failed: {}", e))
+        })?;
+
+        Ok(Response::new(DiscoverLunsResponse {
+            luns: luns
+                .into_iter()
+                .map(|l| DiscoveredLun {
+                    lun: l.lun,
+                    size_bytes: l.size_bytes,
+                    wwn: l.wwn,
+                    serial: l.serial,
+                })
+                .collect(),
+        }))
+    }
+
     async fn receive_migration(
         &self,
         request: Request<ReceiveMigrationRequest>,
@@ -1480,6 +1937,41 @@ async fn parse_loadavg() -> f64 {
         .unwrap_or(0.0)
 }
 
+/// Measure a pool's capacity for an RPC response. Failures are logged and
+/// reported as unknown rather than failing the surrounding call.
+async fn pool_capacity(
+    backend: &dyn crate::storage::StorageBackend,
+    pool_id: &str,
+    config_json: &str,
+) -> Option<StoragePoolCapacity> {
+    match backend.capacity(pool_id, config_json).await {
+        Ok(capacity) => capacity.map(|c| StoragePoolCapacity {
+            total_bytes: c.total_bytes,
+            used_bytes: c.used_bytes,
+            free_bytes: c.free_bytes,
+        }),
+        Err(e) => {
+            warn!(
+                "Failed to measure capacity of storage pool {}: {}",
+                pool_id, e
+            );
+            None
+        }
+    }
+}
+
+fn cached_image(usage: ImageUsage) -> OverlayBdCachedImage {
+    OverlayBdCachedImage {
+        image_ref: usage.image_ref,
+        registry_url: usage.registry_url,
+        layers: usage.layers as u32,
+        cached_layers: usage.cached_layers as u32,
+        cached_bytes: usage.cached_bytes,
+        last_used_unix: usage.last_used as i64,
+        in_use: usage.in_use,
+    }
+}
+
 /// Get total and available disk bytes for a path via statvfs.
 fn disk_usage(path: &std::path::Path) -> (i64, i64) {
     match nix::sys::statvfs::statvfs(path) {
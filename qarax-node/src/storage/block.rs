@@ -2,24 +2,64 @@ use std::path::PathBuf;
 use std::time::Duration;
 
 use tokio::process::Command;
-use tracing::{debug, warn};
+use tracing::{debug, info, warn};
 
-use super::{MappedDisk, StorageBackend};
+use std::collections::BTreeMap;
+
+use super::{LunInfo, MappedDisk, PathHealth, PoolCapacity, StorageBackend};
 
 /// Block (iSCSI) storage backend.
 ///
-/// Pool config JSON: `{"portal": "host:port", "iqn": "iqn.2024-01.qarax:target0"}`.
-/// Disk object config JSON: `{"lun": <u32>}`.
+/// Pool config JSON:
+/// `{"portals": ["10.0.0.5:3260", "10.0.1.5:3260"], "iqn": "iqn.2024-01.qarax:target0",
+///   "chap": {"username": "u", "secret": "s"}, "mutual_chap": {"username": "t", "secret": "x"}}`.
+/// A single `"portal"` is still accepted. `chap` authenticates the initiator;
+/// `mutual_chap` additionally makes the target authenticate itself and
+/// requires `chap`.
+/// Disk object config JSON: the portals and iqn plus `{"lun": <u32>}`.
 ///
-/// `attach` runs `iscsiadm` discovery + login so that the kernel creates
-/// `/dev/disk/by-path/ip-<portal>-iscsi-<iqn>-lun-<lun>` for every LUN
-/// exported by the target. `map` resolves that symlink for a given disk.
-pub struct BlockBackend;
+/// `attach` runs `iscsiadm` discovery + login against every portal so that
+/// the kernel creates `/dev/disk/by-path/ip-<portal>-iscsi-<iqn>-lun-<lun>`
+/// per path. `map` resolves those symlinks and, when more than one path
+/// exists, returns the dm-multipath device that holds them. `discover_luns`
+/// walks the same symlinks to list every LUN the target exports.
+pub struct BlockBackend {
+    iscsiadm: PathBuf,
+    /// Filesystem root for `/dev` and `/sys` lookups (a scratch dir in tests).
+    root: PathBuf,
+}
+
+impl Default for BlockBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 #[derive(serde::Deserialize, Debug)]
 struct BlockPoolConfig {
-    portal: String,
+    #[serde(default)]
+    portal: Option<String>,
+    #[serde(default)]
+    portals: Vec<String>,
     iqn: String,
+    #[serde(default)]
+    chap: Option<ChapCredentials>,
+    #[serde(default)]
+    mutual_chap: Option<ChapCredentials>,
+}
+
+#[derive(serde::Deserialize)]
+struct ChapCredentials {
+    username: String,
+    secret: String,
+}
+
+impl std::fmt::Debug for ChapCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChapCredentials")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
 }
 
 #[derive(serde::Deserialize, Debug)]
@@ -28,6 +68,38 @@ struct BlockDiskConfig {
     lun: u32,
 }
 
+impl BlockPoolConfig {
+    fn parse(config: &serde_json::Value) -> anyhow::Result<Self> {
+        let cfg: BlockPoolConfig = serde_json::from_value(config.clone())
+            .map_err(|e| anyhow::anyhow!("Invalid BLOCK config: {e}"))?;
+        for portal in cfg.all_portals() {
+            validate_portal(&portal)?;
+        }
+        if cfg.all_portals().is_empty() {
+            anyhow::bail!("BLOCK config needs at least one portal");
+        }
+        validate_iqn(&cfg.iqn)?;
+        for chap in [&cfg.chap, &cfg.mutual_chap].into_iter().flatten() {
+            validate_chap(chap)?;
+        }
+        if cfg.mutual_chap.is_some() && cfg.chap.is_none() {
+            anyhow::bail!("mutual_chap requires chap");
+        }
+        Ok(cfg)
+    }
+
+    /// `portal` followed by `portals`, without duplicates.
+    fn all_portals(&self) -> Vec<String> {
+        let mut portals: Vec<String> = Vec::new();
+        for portal in self.portal.iter().chain(&self.portals) {
+            if !portals.contains(portal) {
+                portals.push(portal.clone());
+            }
+        }
+        portals
+    }
+}
+
 fn validate_portal(portal: &str) -> anyhow::Result<()> {
     if portal.is_empty() {
         anyhow::bail!("portal is empty");
@@ -56,109 +128,384 @@ fn validate_iqn(iqn: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn run_iscsiadm(args: &[&str]) -> anyhow::Result<String> {
-    let output = Command::new("iscsiadm").args(args).output().await?;
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("iscsiadm {args:?} failed: {}", stderr.trim());
+fn validate_chap(chap: &ChapCredentials) -> anyhow::Result<()> {
+    if chap.username.is_empty() || chap.username.chars().any(char::is_control) {
+        anyhow::bail!("CHAP username is empty or contains control characters");
     }
+    // Never echo the secret back in the error.
+    if chap.secret.is_empty() || chap.secret.chars().any(char::is_control) {
+        anyhow::bail!(
+            "CHAP secret for {:?} is empty or contains control characters",
+            chap.username
+        );
+    }
+    Ok(())
 }
 
-fn by_path_symlink(portal: &str, iqn: &str, lun: u32) -> PathBuf {
-    PathBuf::from(format!(
-        "/dev/disk/by-path/ip-{portal}-iscsi-{iqn}-lun-{lun}"
-    ))
+/// One line of `iscsiadm -m session`:
+/// `tcp: [3] 10.0.0.5:3260,1 iqn.2024-01.qarax:target0 (non-flash)`.
+#[derive(Debug, PartialEq, Eq)]
+struct Session {
+    id: u32,
+    portal: String,
+    iqn: String,
 }
 
-#[tonic::async_trait]
-impl StorageBackend for BlockBackend {
-    async fn attach(&self, _pool_id: &str, config_json: &str) -> anyhow::Result<String> {
-        let cfg: BlockPoolConfig = serde_json::from_str(config_json)
-            .map_err(|e| anyhow::anyhow!("Invalid BLOCK pool config JSON: {e}"))?;
+fn parse_sessions(output: &str) -> Vec<Session> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().skip(1);
+            let id = fields
+                .next()?
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse()
+                .ok()?;
+            // Drop the ",<tpgt>" suffix from the portal.
+            let portal = fields.next()?.rsplit_once(',').map(|(p, _)| p)?;
+            let iqn = fields.next()?;
+            Some(Session {
+                id,
+                portal: portal.to_string(),
+                iqn: iqn.to_string(),
+            })
+        })
+        .collect()
+}
 
-        validate_portal(&cfg.portal)?;
-        validate_iqn(&cfg.iqn)?;
+impl BlockBackend {
+    pub fn new() -> Self {
+        Self::with_paths("iscsiadm", "/")
+    }
 
-        // Discover targets on the portal. This is idempotent.
-        if let Err(e) =
-            run_iscsiadm(&["-m", "discovery", "-t", "sendtargets", "-p", &cfg.portal]).await
-        {
-            warn!(error = %e, portal = %cfg.portal, "iscsiadm discovery failed");
-            return Err(e);
+    pub fn with_paths(iscsiadm: impl Into<PathBuf>, root: impl Into<PathBuf>) -> Self {
+        Self {
+            iscsiadm: iscsiadm.into(),
+            root: root.into(),
         }
+    }
 
-        // Log in to the target. Already-logged-in returns non-zero on some
-        // versions, so check for an existing session first.
-        let sessions = run_iscsiadm(&["-m", "session"]).await.unwrap_or_default();
-        let already_logged_in = sessions
-            .lines()
-            .any(|line| line.split_whitespace().any(|token| token == cfg.iqn));
-
-        if !already_logged_in {
-            run_iscsiadm(&["-m", "node", "-T", &cfg.iqn, "-p", &cfg.portal, "--login"]).await?;
+    async fn run_iscsiadm(&self, args: &[&str]) -> anyhow::Result<String> {
+        let output = Command::new(&self.iscsiadm).args(args).output().await?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("iscsiadm {args:?} failed: {}", stderr.trim());
         }
-
-        Ok(format!(
-            "iSCSI target {} @ {} attached",
-            cfg.iqn, cfg.portal
-        ))
     }
 
-    async fn detach(&self, _pool_id: &str, config_json: &str) -> anyhow::Result<()> {
-        let cfg: BlockPoolConfig = serde_json::from_str(config_json)
-            .map_err(|e| anyhow::anyhow!("Invalid BLOCK pool config JSON: {e}"))?;
+    /// `iscsiadm --op update` for one node or discovery record setting. The
+    /// value is left out of errors because it may be a CHAP secret.
+    async fn set_param(&self, record: &[&str], name: &str, value: &str) -> anyhow::Result<()> {
+        let mut args = record.to_vec();
+        args.extend(["--op", "update", "-n", name, "-v", value]);
+        let output = Command::new(&self.iscsiadm).args(&args).output().await?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "iscsiadm failed to set {name}: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
 
-        validate_portal(&cfg.portal)?;
-        validate_iqn(&cfg.iqn)?;
+    async fn sessions(&self) -> Vec<Session> {
+        // iscsiadm exits non-zero when there are no sessions at all.
+        parse_sessions(
+            &self
+                .run_iscsiadm(&["-m", "session"])
+                .await
+                .unwrap_or_default(),
+        )
+    }
 
-        let sessions = run_iscsiadm(&["-m", "session"]).await.unwrap_or_default();
-        let logged_in = sessions
-            .lines()
-            .any(|line| line.split_whitespace().any(|token| token == cfg.iqn));
+    /// Discover targets on a portal, authenticating with CHAP when configured.
+    async fn discover(&self, cfg: &BlockPoolConfig, portal: &str) -> anyhow::Result<()> {
+        let Some(chap) = &cfg.chap else {
+            self.run_iscsiadm(&["-m", "discovery", "-t", "sendtargets", "-p", portal])
+                .await?;
+            return Ok(());
+        };
 
-        if logged_in {
-            run_iscsiadm(&["-m", "node", "-T", &cfg.iqn, "-p", &cfg.portal, "--logout"]).await?;
+        let record = ["-m", "discoverydb", "-t", "sendtargets", "-p", portal];
+        let mut new = record.to_vec();
+        new.extend(["--op", "new"]);
+        self.run_iscsiadm(&new).await?;
+        self.set_param(&record, "discovery.sendtargets.auth.authmethod", "CHAP")
+            .await?;
+        self.set_param(
+            &record,
+            "discovery.sendtargets.auth.username",
+            &chap.username,
+        )
+        .await?;
+        self.set_param(&record, "discovery.sendtargets.auth.password", &chap.secret)
+            .await?;
+        if let Some(mutual) = &cfg.mutual_chap {
+            self.set_param(
+                &record,
+                "discovery.sendtargets.auth.username_in",
+                &mutual.username,
+            )
+            .await?;
+            self.set_param(
+                &record,
+                "discovery.sendtargets.auth.password_in",
+                &mutual.secret,
+            )
+            .await?;
         }
+        let mut discover = record.to_vec();
+        discover.push("--discover");
+        self.run_iscsiadm(&discover).await?;
+        Ok(())
+    }
 
+    /// Store session CHAP settings on the node record for `portal`.
+    async fn configure_session_auth(
+        &self,
+        cfg: &BlockPoolConfig,
+        portal: &str,
+    ) -> anyhow::Result<()> {
+        let record = ["-m", "node", "-T", cfg.iqn.as_str(), "-p", portal];
+        let Some(chap) = &cfg.chap else {
+            return self
+                .set_param(&record, "node.session.auth.authmethod", "None")
+                .await;
+        };
+        self.set_param(&record, "node.session.auth.authmethod", "CHAP")
+            .await?;
+        self.set_param(&record, "node.session.auth.username", &chap.username)
+            .await?;
+        self.set_param(&record, "node.session.auth.password", &chap.secret)
+            .await?;
+        if let Some(mutual) = &cfg.mutual_chap {
+            self.set_param(&record, "node.session.auth.username_in", &mutual.username)
+                .await?;
+            self.set_param(&record, "node.session.auth.password_in", &mutual.secret)
+                .await?;
+        }
         Ok(())
     }
 
-    async fn map(&self, _vm_id: &str, config: &serde_json::Value) -> anyhow::Result<MappedDisk> {
-        let portal = config
-            .get("portal")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow::anyhow!("block disk config missing 'portal'"))?;
-        let iqn = config
-            .get("iqn")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow::anyhow!("block disk config missing 'iqn'"))?;
-        let disk: BlockDiskConfig = serde_json::from_value(config.clone())
-            .map_err(|e| anyhow::anyhow!("Invalid BLOCK disk config: {e}"))?;
+    async fn login(&self, cfg: &BlockPoolConfig, portal: &str) -> anyhow::Result<()> {
+        self.discover(cfg, portal).await?;
+        self.configure_session_auth(cfg, portal).await?;
+        self.run_iscsiadm(&["-m", "node", "-T", &cfg.iqn, "-p", portal, "--login"])
+            .await?;
+        Ok(())
+    }
 
-        validate_portal(portal)?;
-        validate_iqn(iqn)?;
+    fn by_path_symlink(&self, portal: &str, iqn: &str, lun: u32) -> PathBuf {
+        self.root.join(format!(
+            "dev/disk/by-path/ip-{portal}-iscsi-{iqn}-lun-{lun}"
+        ))
+    }
 
-        let symlink = by_path_symlink(portal, iqn, disk.lun);
+    /// The dm-multipath map holding `device` (e.g. `sdb`), if any.
+    async fn multipath_holder(&self, device: &str) -> Option<PathBuf> {
+        let holders = self.root.join("sys/block").join(device).join("holders");
+        let mut entries = tokio::fs::read_dir(&holders).await.ok()?;
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let dm = entry.file_name();
+            let dm_dir = self.root.join("sys/block").join(&dm).join("dm");
+            let uuid = tokio::fs::read_to_string(dm_dir.join("uuid"))
+                .await
+                .unwrap_or_default();
+            if !uuid.starts_with("mpath-") {
+                continue;
+            }
+            let name = tokio::fs::read_to_string(dm_dir.join("name")).await.ok()?;
+            return Some(self.root.join("dev/mapper").join(name.trim()));
+        }
+        None
+    }
 
-        // The udev symlink may appear a moment after login; retry briefly.
+    /// Resolve a LUN to a device: the multipath map when the paths have one,
+    /// otherwise the single path device.
+    async fn resolve_device(
+        &self,
+        portals: &[String],
+        iqn: &str,
+        lun: u32,
+    ) -> anyhow::Result<PathBuf> {
+        // udev symlinks and the multipath map may appear a moment after login; retry briefly.
         for _ in 0..20 {
-            if tokio::fs::metadata(&symlink).await.is_ok() {
-                let resolved = tokio::fs::canonicalize(&symlink).await?;
+            let mut paths = Vec::new();
+            for portal in portals {
+                let symlink = self.by_path_symlink(portal, iqn, lun);
+                if let Ok(resolved) = tokio::fs::canonicalize(&symlink).await {
+                    paths.push(resolved);
+                }
+            }
+
+            for path in &paths {
+                let Some(device) = path.file_name().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                if let Some(mpath) = self.multipath_holder(device).await {
+                    debug!(
+                        "BLOCK map resolved LUN {lun} of {iqn} to multipath device {}",
+                        mpath.display()
+                    );
+                    return Ok(mpath);
+                }
+            }
+
+            if paths.len() == 1 && portals.len() == 1 {
                 debug!(
-                    "BLOCK map resolved {} -> {}",
-                    symlink.display(),
-                    resolved.display()
+                    "BLOCK map resolved LUN {lun} of {iqn} to {}",
+                    paths[0].display()
                 );
-                return Ok(MappedDisk {
-                    device_path: resolved.to_string_lossy().into_owned(),
-                });
+                return Ok(paths.remove(0));
             }
             tokio::time::sleep(Duration::from_millis(250)).await;
         }
 
-        anyhow::bail!("iSCSI device {} did not appear in time", symlink.display());
+        // Several portals but no multipath map: fall back to one path rather
+        // than handing the VM two devices for the same LUN.
+        for portal in portals {
+            let symlink = self.by_path_symlink(portal, iqn, lun);
+            if let Ok(resolved) = tokio::fs::canonicalize(&symlink).await {
+                warn!(
+                    "No dm-multipath device for LUN {lun} of {iqn}; using single path {} (is multipathd running?)",
+                    resolved.display()
+                );
+                return Ok(resolved);
+            }
+        }
+
+        anyhow::bail!("iSCSI device for LUN {lun} of {iqn} did not appear in time");
+    }
+
+    /// Size, WWID and serial of a SCSI disk such as `sdb`.
+    async fn lun_info(&self, lun: u32, device: &str) -> anyhow::Result<LunInfo> {
+        let sys = self.root.join("sys/block").join(device);
+        let sectors: i64 = tokio::fs::read_to_string(sys.join("size"))
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read size of {device}: {e}"))?
+            .trim()
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid size for {device}: {e}"))?;
+        let wwn = tokio::fs::read_to_string(sys.join("device/wwid"))
+            .await
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default();
+        let serial = tokio::fs::read(sys.join("device/vpd_pg80"))
+            .await
+            .map(|page| parse_vpd_serial(&page))
+            .unwrap_or_default();
+        Ok(LunInfo {
+            lun,
+            // sysfs reports 512-byte sectors regardless of the logical block size
+            size_bytes: sectors * 512,
+            wwn,
+            serial,
+        })
+    }
+
+    async fn session_state(&self, session_id: u32) -> String {
+        let path = self
+            .root
+            .join(format!("sys/class/iscsi_session/session{session_id}/state"));
+        tokio::fs::read_to_string(&path)
+            .await
+            .map(|s| s.trim().to_string())
+            .unwrap_or_else(|_| "UNKNOWN".to_string())
+    }
+}
+
+/// Extract the unit serial number from a raw VPD page 0x80: a 4-byte header
+/// whose last two bytes hold the length, followed by ASCII padded with spaces.
+fn parse_vpd_serial(page: &[u8]) -> String {
+    if page.len() < 4 || page[1] != 0x80 {
+        return String::new();
+    }
+    let len = u16::from_be_bytes([page[2], page[3]]) as usize;
+    let end = (4 + len).min(page.len());
+    String::from_utf8_lossy(&page[4..end])
+        .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+        .to_string()
+}
+
+fn session_for<'a>(sessions: &'a [Session], portal: &str, iqn: &str) -> Option<&'a Session> {
+    sessions.iter().find(|s| s.iqn == iqn && s.portal == portal)
+}
+
+#[tonic::async_trait]
+impl StorageBackend for BlockBackend {
+    async fn attach(&self, _pool_id: &str, config_json: &str) -> anyhow::Result<String> {
+        let value: serde_json::Value = serde_json::from_str(config_json)
+            .map_err(|e| anyhow::anyhow!("Invalid BLOCK pool config JSON: {e}"))?;
+        let cfg = BlockPoolConfig::parse(&value)?;
+        let portals = cfg.all_portals();
+
+        // Log in to every portal. Already-logged-in returns non-zero on some
+        // versions, so check for an existing session first. One reachable
+        // portal is enough; the rest show up as unhealthy paths.
+        let sessions = self.sessions().await;
+        let mut failed = Vec::new();
+        for portal in &portals {
+            if session_for(&sessions, portal, &cfg.iqn).is_some() {
+                continue;
+            }
+            if let Err(e) = self.login(&cfg, portal).await {
+                warn!(error = %e, portal = %portal, iqn = %cfg.iqn, "iSCSI login failed");
+                failed.push(format!("{portal}: {e}"));
+            }
+        }
+
+        if failed.len() == portals.len() {
+            anyhow::bail!(
+                "iSCSI login to {} failed on every portal: {}",
+                cfg.iqn,
+                failed.join("; ")
+            );
+        }
+
+        let connected = portals.len() - failed.len();
+        info!(
+            "iSCSI target {} attached via {}/{} portals",
+            cfg.iqn,
+            connected,
+            portals.len()
+        );
+        Ok(format!(
+            "iSCSI target {} attached via {connected}/{} portals",
+            cfg.iqn,
+            portals.len()
+        ))
+    }
+
+    async fn detach(&self, _pool_id: &str, config_json: &str) -> anyhow::Result<()> {
+        let value: serde_json::Value = serde_json::from_str(config_json)
+            .map_err(|e| anyhow::anyhow!("Invalid BLOCK pool config JSON: {e}"))?;
+        let cfg = BlockPoolConfig::parse(&value)?;
+
+        let sessions = self.sessions().await;
+        for portal in cfg.all_portals() {
+            if session_for(&sessions, &portal, &cfg.iqn).is_some() {
+                self.run_iscsiadm(&["-m", "node", "-T", &cfg.iqn, "-p", &portal, "--logout"])
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn map(&self, _vm_id: &str, config: &serde_json::Value) -> anyhow::Result<MappedDisk> {
+        let cfg = BlockPoolConfig::parse(config)?;
+        let disk: BlockDiskConfig = serde_json::from_value(config.clone())
+            .map_err(|e| anyhow::anyhow!("Invalid BLOCK disk config: {e}"))?;
+
+        let device = self
+            .resolve_device(&cfg.all_portals(), &cfg.iqn, disk.lun)
+            .await?;
+        Ok(MappedDisk {
+            device_path: device.to_string_lossy().into_owned(),
+        })
     }
 
     async fn unmap(&self, _vm_id: &str) -> anyhow::Result<()> {
@@ -169,4 +516,361 @@ impl StorageBackend for BlockBackend {
         // Kernel restores sessions from node.startup=automatic records on boot.
         Ok(())
     }
+
+    async fn path_health(&self, config_json: &str) -> anyhow::Result<Vec<PathHealth>> {
+        let value: serde_json::Value = serde_json::from_str(config_json)
+            .map_err(|e| anyhow::anyhow!("Invalid BLOCK pool config JSON: {e}"))?;
+        let cfg = BlockPoolConfig::parse(&value)?;
+
+        let sessions = self.sessions().await;
+        let mut paths = Vec::new();
+        for portal in cfg.all_portals() {
+            let state = match session_for(&sessions, &portal, &cfg.iqn) {
+                Some(session) => self.session_state(session.id).await,
+                None => "NOT_CONNECTED".to_string(),
+            };
+            paths.push(PathHealth {
+                healthy: state == "LOGGED_IN",
+                path: portal,
+                state,
+            });
+        }
+        Ok(paths)
+    }
+
+    async fn discover_luns(&self, config_json: &str) -> anyhow::Result<Vec<LunInfo>> {
+        let value: serde_json::Value = serde_json::from_str(config_json)
+            .map_err(|e| anyhow::anyhow!("Invalid BLOCK pool config JSON: {e}"))?;
+        let cfg = BlockPoolConfig::parse(&value)?;
+
+        let sessions = self.sessions().await;
+        let portals = cfg.all_portals();
+        if !portals
+            .iter()
+            .any(|portal| session_for(&sessions, portal, &cfg.iqn).is_some())
+        {
+            anyhow::bail!("No iSCSI session to {} on this host", cfg.iqn);
+        }
+
+        // Every path to a LUN has its own symlink; the first one that resolves
+        // is enough to read the LUN's attributes.
+        let prefixes: Vec<String> = portals
+            .iter()
+            .map(|portal| format!("ip-{portal}-iscsi-{}-lun-", cfg.iqn))
+            .collect();
+        let mut luns = BTreeMap::new();
+        let by_path = self.root.join("dev/disk/by-path");
+        let mut entries = tokio::fs::read_dir(&by_path)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", by_path.display()))?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // Partition symlinks end in -partN and fail to parse as a LUN.
+            let Some(lun) = prefixes
+                .iter()
+                .find_map(|prefix| name.strip_prefix(prefix.as_str()))
+                .and_then(|suffix| suffix.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            if luns.contains_key(&lun) {
+                continue;
+            }
+            let Ok(resolved) = tokio::fs::canonicalize(entry.path()).await else {
+                continue;
+            };
+            let Some(device) = resolved.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            luns.insert(lun, self.lun_info(lun, device).await?);
+        }
+
+        debug!("Discovered {} LUNs on {}", luns.len(), cfg.iqn);
+        Ok(luns.into_values().collect())
+    }
+
+    /// LUNs are provisioned on the target and qarax cannot carve new ones,
+    /// so everything exported counts as used.
+    async fn capacity(
+        &self,
+        _pool_id: &str,
+        config_json: &str,
+    ) -> anyhow::Result<Option<PoolCapacity>> {
+        let luns = self.discover_luns(config_json).await?;
+        let total_bytes = luns.iter().map(|l| l.size_bytes).sum();
+        Ok(Some(PoolCapacity {
+            total_bytes,
+            used_bytes: total_bytes,
+            free_bytes: 0,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    const IQN: &str = "iqn.2024-01.qarax:target0";
+
+    /// Write a fake `iscsiadm` that records its arguments and reports one
+    /// logged-in session on the first portal.
+    fn mock_iscsiadm(dir: &Path) -> (PathBuf, PathBuf) {
+        let log = dir.join("iscsiadm.log");
+        let script = dir.join("iscsiadm");
+        std::fs::write(
+            &script,
+            format!(
+                r#"#!/bin/sh
+echo "$@" >> {log}
+if [ "$1 $2" = "-m session" ]; then
+  echo "tcp: [3] 10.0.0.5:3260,1 {IQN} (non-flash)"
+fi
+exit 0
+"#,
+                log = log.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        (script, log)
+    }
+
+    fn write(path: &Path, contents: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    fn pool_config() -> String {
+        serde_json::json!({
+            "portals": ["10.0.0.5:3260", "10.0.1.5:3260"],
+            "iqn": IQN,
+            "chap": {"username": "initiator", "secret": "initiator-secret"},
+            "mutual_chap": {"username": "target", "secret": "target-secret"},
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn parses_iscsiadm_sessions() {
+        let sessions = parse_sessions(
+            "tcp: [3] 10.0.0.5:3260,1 iqn.2024-01.qarax:target0 (non-flash)\n\
+             tcp: [12] [fd00::5]:3260,1 iqn.2024-01.qarax:target1 (non-flash)\n",
+        );
+        assert_eq!(
+            sessions,
+            vec![
+                Session {
+                    id: 3,
+                    portal: "10.0.0.5:3260".into(),
+                    iqn: "iqn.2024-01.qarax:target0".into(),
+                },
+                Session {
+                    id: 12,
+                    portal: "[fd00::5]:3260".into(),
+                    iqn: "iqn.2024-01.qarax:target1".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn validates_pool_config() {
+        let parse = |v: serde_json::Value| BlockPoolConfig::parse(&v);
+        let cfg = parse(serde_json::json!({
+            "portal": "10.0.0.5:3260",
+            "portals": ["10.0.0.5:3260", "10.0.1.5:3260"],
+            "iqn": IQN,
+        }))
+        .unwrap();
+        assert_eq!(cfg.all_portals(), vec!["10.0.0.5:3260", "10.0.1.5:3260"]);
+
+        assert!(parse(serde_json::json!({ "iqn": IQN })).is_err());
+        let err = parse(serde_json::json!({
+            "portal": "10.0.0.5:3260",
+            "iqn": IQN,
+            "mutual_chap": {"username": "target", "secret": "target-secret"},
+        }))
+        .unwrap_err();
+        assert!(err.to_string().contains("mutual_chap requires chap"));
+    }
+
+    #[tokio::test]
+    async fn attach_configures_chap_and_logs_in_missing_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let (iscsiadm, log) = mock_iscsiadm(dir.path());
+        let backend = BlockBackend::with_paths(&iscsiadm, dir.path());
+
+        let message = backend.attach("p", &pool_config()).await.unwrap();
+        assert_eq!(
+            message,
+            format!("iSCSI target {IQN} attached via 2/2 portals")
+        );
+
+        let lines: Vec<String> = std::fs::read_to_string(&log)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        let node = format!("-m node -T {IQN} -p 10.0.1.5:3260");
+        let discovery = "-m discoverydb -t sendtargets -p 10.0.1.5:3260";
+        assert_eq!(
+            lines,
+            vec![
+                "-m session".to_string(),
+                format!("{discovery} --op new"),
+                format!("{discovery} --op update -n discovery.sendtargets.auth.authmethod -v CHAP"),
+                format!(
+                    "{discovery} --op update -n discovery.sendtargets.auth.username -v initiator"
+                ),
+                format!(
+                    "{discovery} --op update -n discovery.sendtargets.auth.password -v initiator-secret"
+                ),
+                format!(
+                    "{discovery} --op update -n discovery.sendtargets.auth.username_in -v target"
+                ),
+                format!(
+                    "{discovery} --op update -n discovery.sendtargets.auth.password_in -v target-secret"
+                ),
+                format!("{discovery} --discover"),
+                format!("{node} --op update -n node.session.auth.authmethod -v CHAP"),
+                format!("{node} --op update -n node.session.auth.username -v initiator"),
+                format!("{node} --op update -n node.session.auth.password -v initiator-secret"),
+                format!("{node} --op update -n node.session.auth.username_in -v target"),
+                format!("{node} --op update -n node.session.auth.password_in -v target-secret"),
+                format!("{node} --login"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn path_health_reports_each_portal() {
+        let dir = tempfile::tempdir().unwrap();
+        let (iscsiadm, _log) = mock_iscsiadm(dir.path());
+        write(
+            &dir.path().join("sys/class/iscsi_session/session3/state"),
+            "LOGGED_IN\n",
+        );
+        let backend = BlockBackend::with_paths(&iscsiadm, dir.path());
+
+        let health = backend.path_health(&pool_config()).await.unwrap();
+        assert_eq!(
+            health,
+            vec![
+                PathHealth {
+                    path: "10.0.0.5:3260".into(),
+                    state: "LOGGED_IN".into(),
+                    healthy: true,
+                },
+                PathHealth {
+                    path: "10.0.1.5:3260".into(),
+                    state: "NOT_CONNECTED".into(),
+                    healthy: false,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn discover_luns_reads_size_wwn_and_serial() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let (iscsiadm, _log) = mock_iscsiadm(root);
+        let by_path = root.join("dev/disk/by-path");
+        std::fs::create_dir_all(&by_path).unwrap();
+        for (portal, lun, device) in [
+            ("10.0.0.5:3260", 1, "sdb"),
+            ("10.0.1.5:3260", 1, "sdc"),
+            ("10.0.0.5:3260", 2, "sdd"),
+        ] {
+            write(&root.join("dev").join(device), "");
+            std::os::unix::fs::symlink(
+                root.join("dev").join(device),
+                by_path.join(format!("ip-{portal}-iscsi-{IQN}-lun-{lun}")),
+            )
+            .unwrap();
+            write(&root.join(format!("sys/block/{device}/size")), "2097152\n");
+        }
+        std::os::unix::fs::symlink(
+            root.join("dev/sdd"),
+            by_path.join(format!("ip-10.0.0.5:3260-iscsi-{IQN}-lun-2-part1")),
+        )
+        .unwrap();
+        // Both paths of LUN 1 report the same identifiers.
+        let mut page = vec![0x00, 0x80, 0x00, 0x08];
+        page.extend_from_slice(b"SN-0001 ");
+        for device in ["sdb", "sdc"] {
+            write(
+                &root.join(format!("sys/block/{device}/device/wwid")),
+                "naa.6001405abcdef\n",
+            );
+            std::fs::write(
+                root.join(format!("sys/block/{device}/device/vpd_pg80")),
+                &page,
+            )
+            .unwrap();
+        }
+
+        let backend = BlockBackend::with_paths(&iscsiadm, root);
+        let luns = backend.discover_luns(&pool_config()).await.unwrap();
+        let capacity = backend.capacity("p", &pool_config()).await.unwrap();
+        assert_eq!(
+            capacity,
+            Some(PoolCapacity {
+                total_bytes: 2 * 1024 * 1024 * 1024,
+                used_bytes: 2 * 1024 * 1024 * 1024,
+                free_bytes: 0,
+            })
+        );
+        assert_eq!(
+            luns,
+            vec![
+                LunInfo {
+                    lun: 1,
+                    size_bytes: 1024 * 1024 * 1024,
+                    wwn: "naa.6001405abcdef".into(),
+                    serial: "SN-0001".into(),
+                },
+                LunInfo {
+                    lun: 2,
+                    size_bytes: 1024 * 1024 * 1024,
+                    wwn: String::new(),
+                    serial: String::new(),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn map_prefers_the_multipath_device() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let by_path = root.join("dev/disk/by-path");
+        std::fs::create_dir_all(&by_path).unwrap();
+        for (portal, device) in [("10.0.0.5:3260", "sdb"), ("10.0.1.5:3260", "sdc")] {
+            write(&root.join("dev").join(device), "");
+            std::os::unix::fs::symlink(
+                root.join("dev").join(device),
+                by_path.join(format!("ip-{portal}-iscsi-{IQN}-lun-1")),
+            )
+            .unwrap();
+            write(&root.join(format!("sys/block/{device}/holders/dm-0")), "");
+        }
+        write(
+            &root.join("sys/block/dm-0/dm/uuid"),
+            "mpath-36001405abcdef\n",
+        );
+        write(&root.join("sys/block/dm-0/dm/name"), "mpatha\n");
+
+        let backend = BlockBackend::with_paths("iscsiadm", root);
+        let mut config: serde_json::Value = serde_json::from_str(&pool_config()).unwrap();
+        config["lun"] = serde_json::json!(1);
+
+        let mapped = backend.map("vm-1", &config).await.unwrap();
+        assert_eq!(
+            mapped.device_path,
+            root.join("dev/mapper/mpatha").to_string_lossy()
+        );
+    }
 }
@@ -1,25 +1,38 @@
-use super::{MappedDisk, StorageBackend};
+use super::{MappedDisk, PoolCapacity, StorageBackend};
 
 pub struct LocalBackend;
 
-#[tonic::async_trait]
-impl StorageBackend for LocalBackend {
-    async fn attach(&self, pool_id: &str, config_json: &str) -> anyhow::Result<String> {
-        let cfg: serde_json::Value =
-            serde_json::from_str(config_json).unwrap_or_else(|_| serde_json::json!({}));
+fn pool_dir(pool_id: &str, config_json: &str) -> std::path::PathBuf {
+    let cfg: serde_json::Value =
+        serde_json::from_str(config_json).unwrap_or_else(|_| serde_json::json!({}));
 
-        let path_str = cfg.get("path").and_then(|v| v.as_str()).unwrap_or_default();
+    let path_str = cfg.get("path").and_then(|v| v.as_str()).unwrap_or_default();
 
-        let dir = if path_str.is_empty() {
-            std::path::PathBuf::from(format!("/var/lib/qarax/pools/{}", pool_id))
-        } else {
-            std::path::PathBuf::from(path_str)
-        };
+    if path_str.is_empty() {
+        std::path::PathBuf::from(format!("/var/lib/qarax/pools/{}", pool_id))
+    } else {
+        std::path::PathBuf::from(path_str)
+    }
+}
 
+#[tonic::async_trait]
+impl StorageBackend for LocalBackend {
+    async fn attach(&self, pool_id: &str, config_json: &str) -> anyhow::Result<String> {
+        let dir = pool_dir(pool_id, config_json);
         tokio::fs::create_dir_all(&dir).await?;
         Ok(format!("local dir {} ready", dir.display()))
     }
 
+    async fn capacity(
+        &self,
+        pool_id: &str,
+        config_json: &str,
+    ) -> anyhow::Result<Option<PoolCapacity>> {
+        PoolCapacity::of_filesystem(pool_dir(pool_id, config_json))
+            .await
+            .map(Some)
+    }
+
     async fn detach(&self, _pool_id: &str, _config_json: &str) -> anyhow::Result<()> {
         Ok(())
     }
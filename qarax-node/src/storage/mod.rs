@@ -2,9 +2,12 @@ pub mod block;
 pub mod local;
 pub mod nfs;
 pub mod overlaybd;
+pub mod rbd;
 
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::rpc::node::StoragePoolKind;
 
@@ -15,6 +18,79 @@ pub struct MappedDisk {
     pub device_path: String,
 }
 
+/// Health of one path to a pool's storage (an iSCSI session per portal).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathHealth {
+    pub path: String,
+    pub state: String,
+    pub healthy: bool,
+}
+
+/// Space of a pool as seen from this host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolCapacity {
+    pub total_bytes: i64,
+    pub used_bytes: i64,
+    pub free_bytes: i64,
+}
+
+impl PoolCapacity {
+    /// statvfs of the filesystem holding `path`. Runs off the async runtime
+    /// and gives up after a few seconds, since a dead NFS server makes
+    /// statvfs block indefinitely.
+    pub async fn of_filesystem(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let display = path.display().to_string();
+        let stat = tokio::time::timeout(
+            Duration::from_secs(5),
+            tokio::task::spawn_blocking(move || nix::sys::statvfs::statvfs(&path)),
+        )
+        .await
+        .map_err(|_| anyhow::anyhow!("statvfs of {display} timed out"))??
+        .map_err(|e| anyhow::anyhow!("statvfs of {display} failed: {e}"))?;
+
+        let fragment = stat.fragment_size() as i64;
+        let total_bytes = stat.blocks() as i64 * fragment;
+        Ok(Self {
+            total_bytes,
+            used_bytes: total_bytes - stat.blocks_free() as i64 * fragment,
+            free_bytes: stat.blocks_available() as i64 * fragment,
+        })
+    }
+}
+
+/// Bytes allocated on disk by the files under `dir` (sparse files count only
+/// their written blocks). Missing directories count as empty.
+pub async fn dir_usage(dir: &Path) -> u64 {
+    let mut total = 0;
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(mut entries) = tokio::fs::read_dir(&dir).await else {
+            continue;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let Ok(meta) = entry.metadata().await else {
+                continue;
+            };
+            if meta.is_dir() {
+                pending.push(entry.path());
+            } else if meta.is_file() {
+                total += std::os::unix::fs::MetadataExt::blocks(&meta) * 512;
+            }
+        }
+    }
+    total
+}
+
+/// A LUN visible on the host through an attached pool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LunInfo {
+    pub lun: u32,
+    pub size_bytes: i64,
+    pub wwn: String,
+    pub serial: String,
+}
+
 /// Abstraction over storage backends (Local, NFS, OverlayBD, etc).
 ///
 /// Each backend knows how to make storage accessible on a host (attach/detach)
@@ -33,10 +109,85 @@ pub trait StorageBackend: Send + Sync {
     /// Release the mapping created by map().
     async fn unmap(&self, vm_id: &str) -> anyhow::Result<()>;
 
+    /// Release one mapping created by map() while the VM keeps the rest, e.g.
+    /// after the VMM refused a hotplugged disk. Backends that hold a single
+    /// mapping per VM release it through unmap().
+    async fn unmap_device(&self, vm_id: &str, _device_path: &str) -> anyhow::Result<()> {
+        self.unmap(vm_id).await
+    }
+
     /// Rebuild internal state after a restart.
     async fn recover(&self) -> anyhow::Result<()> {
         Ok(())
     }
+
+    /// Report the state of every path to an attached pool. Backends with a
+    /// single implicit path return nothing.
+    async fn path_health(&self, _config_json: &str) -> anyhow::Result<Vec<PathHealth>> {
+        Ok(Vec::new())
+    }
+
+    /// Total, used and free space of an attached pool on this host, or `None`
+    /// when the backend has no way to measure it.
+    async fn capacity(
+        &self,
+        _pool_id: &str,
+        _config_json: &str,
+    ) -> anyhow::Result<Option<PoolCapacity>> {
+        Ok(None)
+    }
+
+    /// List the LUNs an attached pool exposes on this host.
+    async fn discover_luns(&self, _config_json: &str) -> anyhow::Result<Vec<LunInfo>> {
+        anyhow::bail!("LUN discovery is not supported by this backend")
+    }
+
+    // Image management for backends that own their images (e.g. RBD) rather
+    // than exposing files. `config` is the storage object config, the same
+    // value `map()` receives.
+
+    /// Create a new, empty image.
+    async fn create_image(
+        &self,
+        _config: &serde_json::Value,
+        _size_bytes: i64,
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("image creation is not supported by this storage backend")
+    }
+
+    /// Grow an existing image.
+    async fn resize_image(
+        &self,
+        _config: &serde_json::Value,
+        _size_bytes: i64,
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("image resize is not supported by this storage backend")
+    }
+
+    /// Take a named point-in-time snapshot of an image.
+    async fn snapshot_image(
+        &self,
+        _config: &serde_json::Value,
+        _snapshot: &str,
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("image snapshots are not supported by this storage backend")
+    }
+
+    /// Create the image described by `config` as a copy-on-write clone of
+    /// `source_image@snapshot` in the same pool.
+    async fn clone_image(
+        &self,
+        _config: &serde_json::Value,
+        _source_image: &str,
+        _snapshot: &str,
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("image clones are not supported by this storage backend")
+    }
+
+    /// Delete an image.
+    async fn remove_image(&self, _config: &serde_json::Value) -> anyhow::Result<()> {
+        anyhow::bail!("image removal is not supported by this storage backend")
+    }
 }
 
 /// Registry of storage backends keyed by pool kind.
@@ -1,4 +1,6 @@
-use super::{MappedDisk, StorageBackend};
+use std::os::unix::fs::MetadataExt;
+
+use super::{MappedDisk, PoolCapacity, StorageBackend};
 
 pub struct NfsBackend;
 
@@ -71,6 +73,23 @@ impl StorageBackend for NfsBackend {
         }
     }
 
+    async fn capacity(
+        &self,
+        pool_id: &str,
+        _config_json: &str,
+    ) -> anyhow::Result<Option<PoolCapacity>> {
+        let mount_point = format!("/var/lib/qarax/pools/{}", pool_id);
+
+        // An unmounted mount point would report the local root filesystem.
+        let dev = tokio::fs::metadata(&mount_point).await?.dev();
+        let parent_dev = tokio::fs::metadata("/var/lib/qarax/pools").await?.dev();
+        if dev == parent_dev {
+            anyhow::bail!("NFS export is not mounted at {}", mount_point);
+        }
+
+        PoolCapacity::of_filesystem(mount_point).await.map(Some)
+    }
+
     async fn map(&self, _vm_id: &str, config: &serde_json::Value) -> anyhow::Result<MappedDisk> {
         let path = config
             .get("path")
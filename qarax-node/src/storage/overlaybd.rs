@@ -3,8 +3,8 @@ use std::sync::Arc;
 
 use tracing::info;
 
-use super::{MappedDisk, StorageBackend};
-use crate::overlaybd::OverlayBdManager;
+use super::{MappedDisk, PoolCapacity, StorageBackend, dir_usage};
+use crate::overlaybd::{OverlayBdManager, UpperLayerSpec};
 
 pub struct OverlayBdBackend {
     manager: Arc<OverlayBdManager>,
@@ -64,6 +64,21 @@ impl StorageBackend for OverlayBdBackend {
         Ok(())
     }
 
+    /// Images live in the registry; what the pool costs this host is the
+    /// local cache, measured against the filesystem that holds it.
+    async fn capacity(
+        &self,
+        _pool_id: &str,
+        _config_json: &str,
+    ) -> anyhow::Result<Option<PoolCapacity>> {
+        let cache_dir = self.manager.cache_dir();
+        let fs = PoolCapacity::of_filesystem(cache_dir).await?;
+        Ok(Some(PoolCapacity {
+            used_bytes: dir_usage(cache_dir).await as i64,
+            ..fs
+        }))
+    }
+
     async fn map(&self, vm_id: &str, config: &serde_json::Value) -> anyhow::Result<MappedDisk> {
         let image_ref = config
             .get("image_ref")
@@ -74,18 +89,26 @@ impl StorageBackend for OverlayBdBackend {
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow::anyhow!("OverlayBD disk config missing 'registry_url'"))?;
 
-        let upper_data_path = config.get("upper_data_path").and_then(|v| v.as_str());
-        let upper_index_path = config.get("upper_index_path").and_then(|v| v.as_str());
+        let str_field = |key: &str| config.get(key).and_then(|v| v.as_str()).map(String::from);
+        let upper = UpperLayerSpec {
+            data_path: str_field("upper_data_path"),
+            index_path: str_field("upper_index_path"),
+            size_bytes: config.get("size_bytes").and_then(|v| v.as_u64()),
+            frozen_layers: config
+                .get("frozen_layers")
+                .and_then(|v| v.as_array())
+                .map(|layers| {
+                    layers
+                        .iter()
+                        .filter_map(|l| l.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
 
         let mounted = self
             .manager
-            .mount(
-                vm_id,
-                image_ref,
-                registry_url,
-                upper_data_path,
-                upper_index_path,
-            )
+            .mount(vm_id, image_ref, registry_url, &upper)
             .await?;
         let device_path = mounted.device_path.clone();
 
@@ -0,0 +1,517 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use tokio::process::Command;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use super::{MappedDisk, StorageBackend};
+
+/// Ceph RBD storage backend.
+///
+/// Pool config JSON:
+/// `{"pool": "rbd", "monitors": "10.0.0.1:6789,10.0.0.2:6789", "user": "qarax",
+///   "keyring": "/etc/ceph/ceph.client.qarax.keyring", "map_type": "krbd"}`.
+/// Only `pool` is required; the rest fall back to the host's `/etc/ceph` setup.
+/// Disk object config JSON is the pool config plus `{"image": "<name>"}`.
+///
+/// Everything goes through the `rbd` CLI. `map` runs `rbd device map`, which
+/// uses the kernel client by default or the `rbd-nbd` helper with
+/// `"map_type": "nbd"` (for images whose features krbd does not support).
+/// Devices are tracked per VM and released by `unmap`.
+pub struct RbdBackend {
+    rbd_binary: PathBuf,
+    mapped: Mutex<HashMap<String, Vec<MappedImage>>>,
+}
+
+#[derive(Debug)]
+struct MappedImage {
+    device: String,
+    map_type: MapType,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum MapType {
+    #[default]
+    Krbd,
+    Nbd,
+}
+
+impl MapType {
+    fn as_str(self) -> &'static str {
+        match self {
+            MapType::Krbd => "krbd",
+            MapType::Nbd => "nbd",
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct RbdConfig {
+    pool: String,
+    #[serde(default)]
+    monitors: Option<String>,
+    #[serde(default)]
+    user: Option<String>,
+    #[serde(default)]
+    keyring: Option<String>,
+    #[serde(default)]
+    map_type: MapType,
+}
+
+impl RbdConfig {
+    fn parse(config: &serde_json::Value) -> anyhow::Result<Self> {
+        let cfg: RbdConfig = serde_json::from_value(config.clone())
+            .map_err(|e| anyhow::anyhow!("Invalid RBD config: {e}"))?;
+        validate_name("pool", &cfg.pool)?;
+        if let Some(user) = &cfg.user {
+            validate_name("user", user)?;
+        }
+        if let Some(monitors) = &cfg.monitors {
+            validate_monitors(monitors)?;
+        }
+        if let Some(keyring) = &cfg.keyring
+            && (!keyring.starts_with('/') || keyring.chars().any(char::is_control))
+        {
+            anyhow::bail!("keyring must be an absolute path: {keyring:?}");
+        }
+        Ok(cfg)
+    }
+
+    /// `--id`/`-m`/`--keyring` arguments for every rbd invocation.
+    fn connection_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(user) = &self.user {
+            args.extend(["--id".to_string(), user.clone()]);
+        }
+        if let Some(monitors) = &self.monitors {
+            args.extend(["-m".to_string(), monitors.clone()]);
+        }
+        if let Some(keyring) = &self.keyring {
+            args.extend(["--keyring".to_string(), keyring.clone()]);
+        }
+        args
+    }
+
+    fn image_spec(&self, image: &str) -> anyhow::Result<String> {
+        validate_name("image", image)?;
+        Ok(format!("{}/{}", self.pool, image))
+    }
+}
+
+/// Pool, image, snapshot and user names end up as rbd arguments; keep them to a
+/// conservative character set and never let them start like an option.
+fn validate_name(field: &str, value: &str) -> anyhow::Result<()> {
+    if value.is_empty() {
+        anyhow::bail!("RBD {field} is empty");
+    }
+    if value.starts_with('-') || value.starts_with('.') {
+        anyhow::bail!("RBD {field} must not start with '-' or '.': {value:?}");
+    }
+    if value
+        .chars()
+        .any(|c| !c.is_ascii_alphanumeric() && !matches!(c, '-' | '_' | '.'))
+    {
+        anyhow::bail!("RBD {field} contains illegal characters: {value:?}");
+    }
+    Ok(())
+}
+
+fn validate_monitors(monitors: &str) -> anyhow::Result<()> {
+    if monitors.is_empty()
+        || monitors.starts_with('-')
+        || monitors
+            .chars()
+            .any(|c| !c.is_alphanumeric() && !matches!(c, '.' | ':' | ',' | '-' | '[' | ']'))
+    {
+        anyhow::bail!("RBD monitors contain illegal characters: {monitors:?}");
+    }
+    Ok(())
+}
+
+/// rbd sizes default to MiB; round up so the image is never smaller than asked.
+fn size_arg(size_bytes: i64) -> anyhow::Result<String> {
+    if size_bytes <= 0 {
+        anyhow::bail!("RBD image size must be greater than 0");
+    }
+    const MIB: i64 = 1024 * 1024;
+    Ok(format!("{}M", (size_bytes + MIB - 1) / MIB))
+}
+
+fn required_str<'a>(config: &'a serde_json::Value, field: &str) -> anyhow::Result<&'a str> {
+    config
+        .get(field)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("RBD disk config missing '{field}'"))
+}
+
+impl Default for RbdBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RbdBackend {
+    pub fn new() -> Self {
+        Self::with_binary("rbd")
+    }
+
+    pub fn with_binary(rbd_binary: impl Into<PathBuf>) -> Self {
+        Self {
+            rbd_binary: rbd_binary.into(),
+            mapped: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn run(&self, cfg: &RbdConfig, args: &[&str]) -> anyhow::Result<String> {
+        let output = Command::new(&self.rbd_binary)
+            .args(args)
+            .args(cfg.connection_args())
+            .output()
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to run {}: {e}", self.rbd_binary.display()))?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("rbd {} failed: {}", args.join(" "), stderr.trim());
+        }
+    }
+
+    async fn unmap_image(&self, vm_id: &str, image: &MappedImage) -> anyhow::Result<()> {
+        let output = Command::new(&self.rbd_binary)
+            .args([
+                "device",
+                "unmap",
+                "-t",
+                image.map_type.as_str(),
+                &image.device,
+            ])
+            .output()
+            .await;
+        match output {
+            Ok(output) if output.status.success() => {
+                debug!(vm_id, device = %image.device, "RBD device unmapped");
+                Ok(())
+            }
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+                warn!(vm_id, device = %image.device, error = %stderr, "rbd device unmap failed");
+                Err(anyhow::anyhow!(
+                    "rbd device unmap {}: {stderr}",
+                    image.device
+                ))
+            }
+            Err(e) => {
+                warn!(vm_id, device = %image.device, error = %e, "rbd device unmap failed");
+                Err(e.into())
+            }
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl StorageBackend for RbdBackend {
+    async fn attach(&self, _pool_id: &str, config_json: &str) -> anyhow::Result<String> {
+        let config: serde_json::Value = serde_json::from_str(config_json)
+            .map_err(|e| anyhow::anyhow!("Invalid RBD pool config JSON: {e}"))?;
+        let cfg = RbdConfig::parse(&config)?;
+
+        // Listing the pool proves both that the cluster is reachable and that
+        // the configured user may read it. Nothing is held open afterwards.
+        let listing = self
+            .run(&cfg, &["ls", "--format", "json", &cfg.pool])
+            .await?;
+        let images = serde_json::from_str::<Vec<String>>(&listing)
+            .map(|images| images.len())
+            .unwrap_or_default();
+        Ok(format!("RBD pool {} reachable ({images} images)", cfg.pool))
+    }
+
+    async fn detach(&self, _pool_id: &str, _config_json: &str) -> anyhow::Result<()> {
+        // Mappings belong to VMs and are released by unmap().
+        Ok(())
+    }
+
+    async fn map(&self, vm_id: &str, config: &serde_json::Value) -> anyhow::Result<MappedDisk> {
+        let cfg = RbdConfig::parse(config)?;
+        let spec = cfg.image_spec(required_str(config, "image")?)?;
+
+        let device = self
+            .run(&cfg, &["device", "map", "-t", cfg.map_type.as_str(), &spec])
+            .await?;
+        if !device.starts_with("/dev/") {
+            anyhow::bail!("rbd device map returned unexpected output: {device:?}");
+        }
+        debug!(vm_id, spec = %spec, device = %device, "RBD image mapped");
+
+        self.mapped
+            .lock()
+            .await
+            .entry(vm_id.to_string())
+            .or_default()
+            .push(MappedImage {
+                device: device.clone(),
+                map_type: cfg.map_type,
+            });
+        Ok(MappedDisk {
+            device_path: device,
+        })
+    }
+
+    async fn unmap(&self, vm_id: &str) -> anyhow::Result<()> {
+        let Some(images) = self.mapped.lock().await.remove(vm_id) else {
+            return Ok(());
+        };
+
+        let mut result = Ok(());
+        for image in images {
+            if let Err(e) = self.unmap_image(vm_id, &image).await {
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    async fn unmap_device(&self, vm_id: &str, device_path: &str) -> anyhow::Result<()> {
+        let image = {
+            let mut mapped = self.mapped.lock().await;
+            let Some(images) = mapped.get_mut(vm_id) else {
+                return Ok(());
+            };
+            let Some(index) = images.iter().position(|i| i.device == device_path) else {
+                return Ok(());
+            };
+            let image = images.remove(index);
+            if images.is_empty() {
+                mapped.remove(vm_id);
+            }
+            image
+        };
+        self.unmap_image(vm_id, &image).await
+    }
+
+    async fn create_image(
+        &self,
+        config: &serde_json::Value,
+        size_bytes: i64,
+    ) -> anyhow::Result<()> {
+        let cfg = RbdConfig::parse(config)?;
+        let spec = cfg.image_spec(required_str(config, "image")?)?;
+        let size = size_arg(size_bytes)?;
+        self.run(&cfg, &["create", "--size", &size, &spec]).await?;
+        info!(spec = %spec, size = %size, "RBD image created");
+        Ok(())
+    }
+
+    async fn resize_image(
+        &self,
+        config: &serde_json::Value,
+        size_bytes: i64,
+    ) -> anyhow::Result<()> {
+        let cfg = RbdConfig::parse(config)?;
+        let spec = cfg.image_spec(required_str(config, "image")?)?;
+        let size = size_arg(size_bytes)?;
+        // No --allow-shrink: shrinking would silently destroy guest data.
+        self.run(&cfg, &["resize", "--size", &size, &spec]).await?;
+        info!(spec = %spec, size = %size, "RBD image resized");
+        Ok(())
+    }
+
+    async fn snapshot_image(
+        &self,
+        config: &serde_json::Value,
+        snapshot: &str,
+    ) -> anyhow::Result<()> {
+        let cfg = RbdConfig::parse(config)?;
+        let spec = cfg.image_spec(required_str(config, "image")?)?;
+        validate_name("snapshot", snapshot)?;
+        self.run(&cfg, &["snap", "create", &format!("{spec}@{snapshot}")])
+            .await?;
+        Ok(())
+    }
+
+    async fn clone_image(
+        &self,
+        config: &serde_json::Value,
+        source_image: &str,
+        snapshot: &str,
+    ) -> anyhow::Result<()> {
+        let cfg = RbdConfig::parse(config)?;
+        let target = cfg.image_spec(required_str(config, "image")?)?;
+        validate_name("snapshot", snapshot)?;
+        let parent = format!("{}@{snapshot}", cfg.image_spec(source_image)?);
+
+        // v1 clones need a protected parent snapshot; protecting twice is an
+        // error rbd reports, not a problem.
+        if let Err(e) = self.run(&cfg, &["snap", "protect", &parent]).await
+            && !e.to_string().contains("already protected")
+        {
+            return Err(e);
+        }
+        self.run(&cfg, &["clone", &parent, &target]).await?;
+        info!(parent = %parent, target = %target, "RBD image cloned");
+        Ok(())
+    }
+
+    async fn remove_image(&self, config: &serde_json::Value) -> anyhow::Result<()> {
+        let cfg = RbdConfig::parse(config)?;
+        let spec = cfg.image_spec(required_str(config, "image")?)?;
+        self.run(&cfg, &["rm", "--no-progress", &spec]).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Write a fake `rbd` that records its arguments and answers the few
+    /// subcommands the backend relies on.
+    fn mock_rbd(dir: &std::path::Path) -> (PathBuf, PathBuf) {
+        let log = dir.join("rbd.log");
+        let script = dir.join("rbd");
+        std::fs::write(
+            &script,
+            format!(
+                r#"#!/bin/sh
+echo "$@" >> {log}
+case "$1 $2" in
+  "device map") case "$5" in */disk-2) echo /dev/rbd8 ;; *) echo /dev/rbd7 ;; esac ;;
+  "ls --format") echo '["a","b"]' ;;
+  "snap protect") echo "rbd: snap is already protected" >&2; exit 16 ;;
+  "rm --no-progress") echo "rbd: error opening image missing: (2) No such file or directory" >&2; exit 2 ;;
+esac
+exit 0
+"#,
+                log = log.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        (script, log)
+    }
+
+    fn log_lines(log: &std::path::Path) -> Vec<String> {
+        std::fs::read_to_string(log)
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn map_and_unmap_go_through_rbd_device() {
+        let dir = tempfile::tempdir().unwrap();
+        let (rbd, log) = mock_rbd(dir.path());
+        let backend = RbdBackend::with_binary(&rbd);
+        let config = serde_json::json!({
+            "pool": "vms",
+            "monitors": "10.0.0.1:6789",
+            "user": "qarax",
+            "map_type": "nbd",
+            "image": "disk-1",
+        });
+
+        let mapped = backend.map("vm-1", &config).await.unwrap();
+        assert_eq!(mapped.device_path, "/dev/rbd7");
+        backend.unmap("vm-1").await.unwrap();
+        // Nothing left to release the second time.
+        backend.unmap("vm-1").await.unwrap();
+
+        assert_eq!(
+            log_lines(&log),
+            vec![
+                "device map -t nbd vms/disk-1 --id qarax -m 10.0.0.1:6789",
+                "device unmap -t nbd /dev/rbd7",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn unmap_device_releases_only_that_mapping() {
+        let dir = tempfile::tempdir().unwrap();
+        let (rbd, log) = mock_rbd(dir.path());
+        let backend = RbdBackend::with_binary(&rbd);
+        let config = |image: &str| serde_json::json!({ "pool": "vms", "image": image });
+
+        backend.map("vm-1", &config("disk-1")).await.unwrap();
+        let second = backend.map("vm-1", &config("disk-2")).await.unwrap();
+        assert_eq!(second.device_path, "/dev/rbd8");
+
+        backend.unmap_device("vm-1", "/dev/rbd8").await.unwrap();
+        backend.unmap_device("vm-1", "/dev/rbd8").await.unwrap();
+        backend.unmap("vm-1").await.unwrap();
+
+        let unmaps: Vec<_> = log_lines(&log)
+            .into_iter()
+            .filter(|l| l.starts_with("device unmap"))
+            .collect();
+        assert_eq!(
+            unmaps,
+            vec![
+                "device unmap -t krbd /dev/rbd8",
+                "device unmap -t krbd /dev/rbd7"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn image_operations_build_rbd_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let (rbd, log) = mock_rbd(dir.path());
+        let backend = RbdBackend::with_binary(&rbd);
+        let config = serde_json::json!({ "pool": "vms", "image": "disk-2" });
+
+        assert_eq!(
+            backend.attach("p", r#"{"pool": "vms"}"#).await.unwrap(),
+            "RBD pool vms reachable (2 images)"
+        );
+        backend
+            .create_image(&config, 1024 * 1024 + 1)
+            .await
+            .unwrap();
+        backend.resize_image(&config, 4 << 30).await.unwrap();
+        backend.snapshot_image(&config, "base").await.unwrap();
+        backend
+            .clone_image(&config, "golden", "base")
+            .await
+            .unwrap();
+        let missing = backend
+            .remove_image(&serde_json::json!({ "pool": "vms", "image": "missing" }))
+            .await
+            .unwrap_err();
+        assert!(missing.to_string().contains("No such file"));
+
+        assert_eq!(
+            log_lines(&log),
+            vec![
+                "ls --format json vms",
+                "create --size 2M vms/disk-2",
+                "resize --size 4096M vms/disk-2",
+                "snap create vms/disk-2@base",
+                "snap protect vms/golden@base",
+                "clone vms/golden@base vms/disk-2",
+                "rm --no-progress vms/missing",
+            ]
+        );
+    }
+
+    #[test]
+    fn config_validation_rejects_option_injection() {
+        for bad in [
+            serde_json::json!({ "pool": "-c/etc/passwd" }),
+            serde_json::json!({ "pool": "vms", "user": "a b" }),
+            serde_json::json!({ "pool": "vms", "monitors": "--keyring=x" }),
+            serde_json::json!({ "pool": "vms", "keyring": "relative/path" }),
+            serde_json::json!({ "pool": "vms", "map_type": "ggate" }),
+        ] {
+            assert!(RbdConfig::parse(&bad).is_err(), "{bad}");
+        }
+        let cfg = RbdConfig::parse(&serde_json::json!({ "pool": "vms" })).unwrap();
+        assert!(cfg.image_spec("../x").is_err());
+        assert_eq!(cfg.image_spec("disk.raw").unwrap(), "vms/disk.raw");
+    }
+}
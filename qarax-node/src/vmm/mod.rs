@@ -10,7 +10,7 @@ use std::path::Path;
 use async_trait::async_trait;
 
 use crate::rpc::node::{
-    DiskConfig as ProtoDiskConfig, ExecVmResponse, NetConfig as ProtoNetConfig,
+    DiskConfig as ProtoDiskConfig, ExecVmResponse, NetConfig as ProtoNetConfig, UpperLayerCopy,
     VfioDeviceConfig as ProtoVfioDeviceConfig, VmConfig as ProtoVmConfig, VmState,
 };
 
@@ -103,7 +103,18 @@ pub trait VmmManager: Send + Sync + 'static {
     // ── Snapshots ───────────────────────────────────────────────────────────
 
     async fn snapshot_vm(&self, vm_id: &str, destination_url: &str) -> Result<(), VmmError>;
-    async fn restore_vm(&self, vm_id: &str, source_url: &str) -> Result<(), VmmError>;
+    /// Restore from a snapshot. `disks` are storage-backed disks to map again
+    /// before restoring, after rolling the listed upper layers back. `config`,
+    /// when given, is the VM's current config whose devices replace the
+    /// host-local ones recorded in the snapshot.
+    async fn restore_vm(
+        &self,
+        vm_id: &str,
+        source_url: &str,
+        config: Option<ProtoVmConfig>,
+        disks: Vec<ProtoDiskConfig>,
+        upper_copies: &[UpperLayerCopy],
+    ) -> Result<(), VmmError>;
 
     // ── Recovery ────────────────────────────────────────────────────────────
 
@@ -93,6 +93,14 @@ fn default_otlp_endpoint() -> String {
     "http://localhost:4318".to_string()
 }
 
+#[derive(serde::Deserialize, Debug, Default)]
+pub struct SecretsSettings {
+    /// AES-256 key (64 hex characters) that encrypts secrets stored in the
+    /// database, such as registry passwords. Overridden by QARAX_ENCRYPTION_KEY.
+    #[serde(default)]
+    pub encryption_key: Option<Secret<String>>,
+}
+
 #[derive(serde::Deserialize, Debug)]
 pub struct Settings {
     pub database: DatabaseSettings,
@@ -102,6 +110,8 @@ pub struct Settings {
     pub scheduling: SchedulingSettings,
     #[serde(default)]
     pub telemetry: TelemetrySettings,
+    #[serde(default)]
+    pub secrets: SecretsSettings,
 }
 
 #[derive(serde::Deserialize, Debug, Clone)]
@@ -220,6 +230,12 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
                 .ok()
                 .filter(|s| !s.is_empty()),
         )?
+        .set_override_option(
+            "secrets.encryption_key",
+            std::env::var("QARAX_ENCRYPTION_KEY")
+                .ok()
+                .filter(|s| !s.is_empty()),
+        )?
         .build()?;
     settings.try_deserialize::<Settings>()
 }
@@ -52,6 +52,21 @@ fn to_singular_title(s: &str) -> String {
     }
 }
 
+impl From<crate::secrets::SecretsError> for Error {
+    fn from(err: crate::secrets::SecretsError) -> Self {
+        use crate::secrets::SecretsError;
+        match err {
+            SecretsError::NotConfigured | SecretsError::InvalidKey => {
+                Error::UnprocessableEntity(err.to_string())
+            }
+            SecretsError::Encrypt | SecretsError::Decrypt => {
+                tracing::error!(error = %err, "secret encryption failure");
+                Error::InternalServerError
+            }
+        }
+    }
+}
+
 impl From<sqlx::Error> for Error {
     fn from(err: sqlx::Error) -> Self {
         match err {
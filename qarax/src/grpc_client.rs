@@ -7,6 +7,9 @@ use tracing::{debug, instrument, warn};
 use uuid::Uuid;
 
 use crate::model::network_interfaces::NetworkInterface;
+use crate::model::registry_credentials::RegistryLogin;
+use crate::model::storage_pools::PoolCapacity;
+use secrecy::ExposeSecret;
 
 // Include the generated proto code
 pub mod node {
@@ -74,20 +77,98 @@ type FileTransferClient =
     node::file_transfer_service_client::FileTransferServiceClient<tonic::transport::Channel>;
 
 use node::{
-    AddDiskDeviceRequest, AddNetworkDeviceRequest, AttachNetworkRequest, AttachStoragePoolRequest,
-    BlankDiskSource, CloudInitConfig, ConsoleConfig, ConsoleInput, ConsoleLogResponse,
-    CopyFileRequest, CpusConfig, CreateDiskRequest, DetachNetworkRequest, DetachStoragePoolRequest,
-    DiskConfig, DownloadFileRequest, ExecVmRequest, ExecVmResponse, HypervisorType,
-    ImportOverlayBdRequest, ImportOverlayBdResponse, MemoryConfig, NetConfig, NodeInfo,
-    NumaPlacement, OverlayBdDiskSource, PayloadConfig, PreflightImageRequest,
-    PreflightImageResponse, ReceiveMigrationRequest, RemoveDeviceRequest, ResizeDiskRequest,
-    ResizeVmRequest, RestoreVmRequest, SendMigrationRequest, SnapshotVmRequest, StoragePoolKind,
-    SyncNetworkIsolationRequest, SyncVmFirewallRequest, SyncVpcOverlaysRequest, TransferResponse,
-    UrlDiskSource, VfioDeviceConfig, VmConfig, VmCounters, VmFirewallInterface, VmId, VmState,
-    VpcOverlayConfig, VsockConfig, file_transfer_service_client::FileTransferServiceClient,
+    AddDiskDeviceRequest, AddNetworkDeviceRequest, ArchiveMember, AttachNetworkRequest,
+    AttachStoragePoolRequest, BlankDiskSource, ChecksumFilesRequest, CloudInitConfig,
+    ConsoleConfig, ConsoleInput, ConsoleLogResponse, CopyFileRequest, CpusConfig,
+    CreateDiskRequest, DeletePathRequest, DetachNetworkRequest, DetachStoragePoolRequest,
+    DhcpOptions, DhcpReservation, DirectoryEntry, DiscoverLunsRequest, DiscoveredLun, DiskConfig,
+    DownloadFileRequest, ExecVmRequest, ExecVmResponse, ExtractArchiveMemberRequest, FileChecksum,
+    HypervisorType, ImportOverlayBdRequest, ImportOverlayBdResponse, ListDirectoryRequest,
+    ManageStorageImageRequest, MemoryConfig, NetConfig, NodeInfo, NumaPlacement,
+    OverlayBdCacheInfo, OverlayBdCachedImage, OverlayBdDiskSource, OverlayBdGcRequest,
+    OverlayBdGcResponse, PackArchiveRequest, PayloadConfig, PreflightImageRequest,
+    PreflightImageResponse, PrewarmOverlayBdImageRequest, ReadArchiveFileRequest, ReadFileRequest,
+    ReceiveMigrationRequest, RegistryCredential, RemoveDeviceRequest, ResizeDiskRequest,
+    ResizeOverlayBdUpperRequest, ResizeOverlayBdUpperResponse, ResizeVmRequest,
+    ResolveImageDigestRequest, RestoreVmRequest, SendMigrationRequest, SnapshotVmRequest,
+    StorageImageOperation, StoragePoolCapacity, StoragePoolHealthRequest,
+    StoragePoolHealthResponse, StoragePoolKind, SyncDhcpReservationsRequest, SyncNetworkDnsRequest,
+    SyncNetworkIsolationRequest, SyncStretchedNetworkRequest, SyncVmFirewallRequest,
+    SyncVmNatRequest, SyncVpcOverlaysRequest, TransferResponse, UploadFileHeader,
+    UploadFileRequest, UpperLayerCopy, UrlDiskSource, VfioDeviceConfig, VmConfig, VmCounters,
+    VmFirewallCounters, VmFirewallDenied, VmFirewallInterface, VmId, VmPortSecurity, VmState,
+    VpcOverlayConfig, VsockConfig, WriteFileRequest,
+    file_transfer_service_client::FileTransferServiceClient, upload_file_request,
     vm_service_client::VmServiceClient,
 };
 
+fn registry_credentials(logins: &[RegistryLogin]) -> Vec<RegistryCredential> {
+    logins
+        .iter()
+        .map(|login| RegistryCredential {
+            registry: login.registry.clone(),
+            username: login.username.clone(),
+            password: login.password.expose_secret().clone(),
+        })
+        .collect()
+}
+
+/// Bracket IPv6 literals so a host address can be used in a URL authority.
+pub(crate) fn host_for_url(host: &str) -> String {
+    if host.parse::<std::net::Ipv6Addr>().is_ok() {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    }
+}
+
+fn storage_pool_kind(pool_type: &crate::model::storage_pools::StoragePoolType) -> StoragePoolKind {
+    use crate::model::storage_pools::StoragePoolType;
+
+    match pool_type {
+        StoragePoolType::Local => StoragePoolKind::Local,
+        StoragePoolType::Nfs => StoragePoolKind::Nfs,
+        StoragePoolType::OverlayBd => StoragePoolKind::Overlaybd,
+        StoragePoolType::Block => StoragePoolKind::Block,
+        StoragePoolType::Rbd => StoragePoolKind::Rbd,
+    }
+}
+
+impl From<StoragePoolCapacity> for PoolCapacity {
+    fn from(c: StoragePoolCapacity) -> Self {
+        PoolCapacity {
+            total_bytes: c.total_bytes,
+            used_bytes: c.used_bytes,
+            free_bytes: c.free_bytes,
+        }
+    }
+}
+
+/// Optional integrity and rate settings for a node-side download.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadOptions {
+    /// Expected digest in `<algorithm>:<hex>` form (sha256 or sha512).
+    pub expected_checksum: Option<String>,
+    pub bandwidth_limit_bytes_per_sec: Option<i64>,
+}
+
+/// Outcome of a completed node-side download.
+#[derive(Debug)]
+pub struct DownloadResult {
+    pub bytes_written: i64,
+    /// Verified digest, present only when an expected checksum was supplied.
+    pub checksum: Option<String>,
+}
+
+/// The node finished a download but its content did not match the expected checksum.
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub struct ChecksumMismatch {
+    /// Digest the node actually computed, in `<algorithm>:<hex>` form.
+    pub actual: String,
+    pub message: String,
+}
+
 /// Client for communicating with qarax-node via gRPC.
 /// The VM service channel is lazily connected and cached so that multiple
 /// RPCs in the same monitor tick share a single TCP connection.
@@ -138,6 +219,125 @@ pub struct CreateVmRequest {
     pub hypervisor: crate::model::vms::Hypervisor,
 }
 
+impl CreateVmRequest {
+    /// The node-side config for this VM, as sent to `CreateVM`.
+    pub fn into_vm_config(self) -> VmConfig {
+        let CreateVmRequest {
+            vm_id,
+            boot_vcpus,
+            max_vcpus,
+            memory_size,
+            networks,
+            kernel,
+            firmware,
+            initramfs,
+            cmdline,
+            memory_shared,
+            memory_hotplug_size,
+            memory_hugepages,
+            disks: extra_disks,
+            cloud_init_user_data,
+            cloud_init_meta_data,
+            cloud_init_network_config,
+            devices,
+            vsock,
+            numa_placement,
+            rng,
+            serial,
+            console,
+            hypervisor,
+        } = self;
+
+        // Check if a production rootfs is configured via environment variable
+        let mut disks = vec![];
+        if let Ok(rootfs_path) = std::env::var("VM_ROOTFS")
+            && !rootfs_path.is_empty()
+        {
+            debug!("Adding rootfs disk: {}", rootfs_path);
+            disks.push(DiskConfig {
+                id: "rootfs".to_string(),
+                path: Some(rootfs_path),
+                readonly: Some(false),
+                direct: None,
+                vhost_user: None,
+                vhost_socket: None,
+                num_queues: None,
+                queue_size: None,
+                rate_limiter: None,
+                rate_limit_group: None,
+                pci_segment: None,
+                serial: None,
+                oci_image_ref: None,
+                registry_url: None,
+                upper_data_path: None,
+                upper_index_path: None,
+                rbd_config_json: None,
+                block_config_json: None,
+                overlaybd_size_bytes: None,
+                overlaybd_frozen_layers: Vec::new(),
+            });
+        }
+
+        // Append disks resolved from vm_disks + storage objects
+        disks.extend(extra_disks);
+
+        // Default to PTY serial console if not overridden from vm_consoles.
+        let serial_cfg = serial.unwrap_or(ConsoleConfig {
+            mode: node::ConsoleMode::Pty as i32,
+            file: None,
+            socket: None,
+            iommu: None,
+        });
+
+        let proto_hypervisor = match hypervisor {
+            crate::model::vms::Hypervisor::Firecracker => HypervisorType::Firecracker as i32,
+            _ => HypervisorType::CloudHv as i32,
+        };
+
+        VmConfig {
+            vm_id: vm_id.to_string(),
+            hypervisor: proto_hypervisor,
+            cpus: Some(CpusConfig {
+                boot_vcpus,
+                max_vcpus,
+                topology: None,
+                kvm_hyperv: None,
+                max_phys_bits: None,
+            }),
+            memory: Some(MemoryConfig {
+                size: memory_size,
+                hotplug_size: memory_hotplug_size,
+                mergeable: None,
+                shared: if memory_shared { Some(true) } else { None },
+                hugepages: if memory_hugepages { Some(true) } else { None },
+                hugepage_size: None,
+                prefault: None,
+                thp: None,
+            }),
+            payload: Some(PayloadConfig {
+                kernel: kernel.filter(|s| !s.is_empty()),
+                cmdline: cmdline.filter(|s| !s.is_empty()),
+                initramfs: initramfs.filter(|s| !s.trim().is_empty()),
+                firmware: firmware.filter(|s| !s.is_empty()),
+            }),
+            disks,
+            networks,
+            rng,
+            serial: Some(serial_cfg),
+            console,
+            rate_limit_groups: vec![],
+            cloud_init: cloud_init_user_data.map(|user_data| CloudInitConfig {
+                user_data,
+                meta_data: cloud_init_meta_data.unwrap_or_default(),
+                network_config: cloud_init_network_config.unwrap_or_default(),
+            }),
+            devices,
+            vsock,
+            numa_placement,
+        }
+    }
+}
+
 /// Convert DB network interfaces to proto NetConfig for the node.
 pub fn net_configs_from_db(networks: &[NetworkInterface]) -> Vec<NetConfig> {
     fn normalize_ip(value: &Option<String>) -> Option<String> {
@@ -194,7 +394,7 @@ impl NodeClient {
     /// Create a new client for the specified qarax-node address
     pub fn new(host: &str, port: u16) -> Self {
         Self {
-            address: format!("http://{}:{}", host, port),
+            address: format!("http://{}:{}", host_for_url(host), port),
             vm_channel: tokio::sync::OnceCell::new(),
         }
     }
@@ -290,118 +490,11 @@ impl NodeClient {
     /// Create a VM on the qarax-node
     #[instrument(skip(self))]
     pub async fn create_vm(&self, req: CreateVmRequest) -> Result<()> {
-        let CreateVmRequest {
-            vm_id,
-            boot_vcpus,
-            max_vcpus,
-            memory_size,
-            networks,
-            kernel,
-            firmware,
-            initramfs,
-            cmdline,
-            memory_shared,
-            memory_hotplug_size,
-            memory_hugepages,
-            disks: extra_disks,
-            cloud_init_user_data,
-            cloud_init_meta_data,
-            cloud_init_network_config,
-            devices,
-            vsock,
-            numa_placement,
-            rng,
-            serial,
-            console,
-            hypervisor,
-        } = req;
+        let vm_id = req.vm_id;
         debug!("Creating VM {} on node {}", vm_id, self.address);
 
         let mut client = self.connect_vm_service().await?;
-
-        // Check if a production rootfs is configured via environment variable
-        let mut disks = vec![];
-        if let Ok(rootfs_path) = std::env::var("VM_ROOTFS")
-            && !rootfs_path.is_empty()
-        {
-            debug!("Adding rootfs disk: {}", rootfs_path);
-            disks.push(DiskConfig {
-                id: "rootfs".to_string(),
-                path: Some(rootfs_path),
-                readonly: Some(false),
-                direct: None,
-                vhost_user: None,
-                vhost_socket: None,
-                num_queues: None,
-                queue_size: None,
-                rate_limiter: None,
-                rate_limit_group: None,
-                pci_segment: None,
-                serial: None,
-                oci_image_ref: None,
-                registry_url: None,
-                upper_data_path: None,
-                upper_index_path: None,
-            });
-        }
-
-        // Append disks resolved from vm_disks + storage objects
-        disks.extend(extra_disks);
-
-        // Default to PTY serial console if not overridden from vm_consoles.
-        let serial_cfg = serial.unwrap_or(ConsoleConfig {
-            mode: node::ConsoleMode::Pty as i32,
-            file: None,
-            socket: None,
-            iommu: None,
-        });
-
-        let proto_hypervisor = match hypervisor {
-            crate::model::vms::Hypervisor::Firecracker => HypervisorType::Firecracker as i32,
-            _ => HypervisorType::CloudHv as i32,
-        };
-
-        let config = VmConfig {
-            vm_id: vm_id.to_string(),
-            hypervisor: proto_hypervisor,
-            cpus: Some(CpusConfig {
-                boot_vcpus,
-                max_vcpus,
-                topology: None,
-                kvm_hyperv: None,
-                max_phys_bits: None,
-            }),
-            memory: Some(MemoryConfig {
-                size: memory_size,
-                hotplug_size: memory_hotplug_size,
-                mergeable: None,
-                shared: if memory_shared { Some(true) } else { None },
-                hugepages: if memory_hugepages { Some(true) } else { None },
-                hugepage_size: None,
-                prefault: None,
-                thp: None,
-            }),
-            payload: Some(PayloadConfig {
-                kernel: kernel.filter(|s| !s.is_empty()),
-                cmdline: cmdline.filter(|s| !s.is_empty()),
-                initramfs: initramfs.filter(|s| !s.trim().is_empty()),
-                firmware: firmware.filter(|s| !s.is_empty()),
-            }),
-            disks,
-            networks,
-            rng,
-            serial: Some(serial_cfg),
-            console,
-            rate_limit_groups: vec![],
-            cloud_init: cloud_init_user_data.map(|user_data| CloudInitConfig {
-                user_data,
-                meta_data: cloud_init_meta_data.unwrap_or_default(),
-                network_config: cloud_init_network_config.unwrap_or_default(),
-            }),
-            devices,
-            vsock,
-            numa_placement,
-        };
+        let config = req.into_vm_config();
 
         client
             .create_vm(config)
@@ -543,13 +636,17 @@ impl NodeClient {
         Ok(())
     }
 
-    /// Restore a VM on the qarax-node from a snapshot
-    #[instrument(skip(self))]
+    /// Restore a VM on the qarax-node from a snapshot. `disks` are remapped
+    /// on the node after `upper_copies` roll their upper layers back.
+    #[instrument(skip(self, disks))]
     pub async fn restore_vm(
         &self,
         vm_id: Uuid,
         source_url: &str,
         hypervisor: &crate::model::vms::Hypervisor,
+        config: Option<VmConfig>,
+        disks: Vec<DiskConfig>,
+        upper_copies: Vec<UpperLayerCopy>,
     ) -> Result<()> {
         let proto_hypervisor = match hypervisor {
             crate::model::vms::Hypervisor::Firecracker => HypervisorType::Firecracker as i32,
@@ -561,6 +658,9 @@ impl NodeClient {
                 vm_id: vm_id.to_string(),
                 source_url: source_url.to_string(),
                 hypervisor: proto_hypervisor,
+                disks,
+                upper_copies,
+                config,
             })
             .await
             .context("Failed to restore VM on qarax-node")?;
@@ -613,7 +713,8 @@ impl NodeClient {
         transfer_id: &str,
         source_url: &str,
         destination_path: &str,
-    ) -> Result<i64> {
+        options: &DownloadOptions,
+    ) -> Result<DownloadResult> {
         debug!(
             "Requesting file download on node {}: {} -> {}",
             self.address, source_url, destination_path
@@ -626,6 +727,8 @@ impl NodeClient {
                 transfer_id: transfer_id.to_string(),
                 source_url: source_url.to_string(),
                 destination_path: destination_path.to_string(),
+                expected_checksum: options.expected_checksum.clone(),
+                bandwidth_limit_bytes_per_sec: options.bandwidth_limit_bytes_per_sec,
             })
             .await
             .map_err(|s| {
@@ -637,86 +740,350 @@ impl NodeClient {
             })?
             .into_inner();
 
+        download_result(response, "Download")
+    }
+
+    /// Copy a file locally on the node from source to destination
+    #[instrument(skip(self))]
+    pub async fn copy_file(
+        &self,
+        transfer_id: &str,
+        source_path: &str,
+        destination_path: &str,
+    ) -> Result<i64> {
+        debug!(
+            "Requesting file copy on node {}: {} -> {}",
+            self.address, source_path, destination_path
+        );
+
+        let mut client = self.connect_file_transfer_service().await?;
+
+        let response = client
+            .copy_file(CopyFileRequest {
+                transfer_id: transfer_id.to_string(),
+                source_path: source_path.to_string(),
+                destination_path: destination_path.to_string(),
+            })
+            .await
+            .map_err(|s| {
+                anyhow::anyhow!(
+                    "gRPC copy_file failed: code={:?} message={}",
+                    s.code(),
+                    s.message()
+                )
+            })?
+            .into_inner();
+
         if response.success {
             Ok(response.bytes_written)
         } else {
-            anyhow::bail!("Download failed: {}", response.error)
+            anyhow::bail!("Copy failed: {}", response.error)
         }
     }
 
-    /// Copy a file locally on the node from source to destination
+    /// Stream one piece of an upload to the node, starting at `offset` and ending
+    /// at `end_offset`. With `finalize` the node moves the finished file into
+    /// place. Returns the size of the node's copy afterwards.
+    #[instrument(skip(self, data))]
+    pub async fn upload_file<S>(
+        &self,
+        transfer_id: &str,
+        destination_path: &str,
+        offset: i64,
+        end_offset: i64,
+        finalize: bool,
+        data: S,
+    ) -> Result<i64>
+    where
+        S: futures::Stream<Item = Vec<u8>> + Send + 'static,
+    {
+        use futures::StreamExt;
+
+        debug!(
+            "Uploading bytes {}..{} of {} to node {}",
+            offset, end_offset, destination_path, self.address
+        );
+
+        let mut client = self.connect_file_transfer_service().await?;
+
+        let header = UploadFileRequest {
+            payload: Some(upload_file_request::Payload::Header(UploadFileHeader {
+                transfer_id: transfer_id.to_string(),
+                destination_path: destination_path.to_string(),
+                offset,
+                end_offset,
+                finalize,
+            })),
+        };
+        let messages = futures::stream::once(async move { header }).chain(data.map(|chunk| {
+            UploadFileRequest {
+                payload: Some(upload_file_request::Payload::Data(chunk)),
+            }
+        }));
+
+        let response = client
+            .upload_file(messages)
+            .await
+            .map_err(|s| match s.code() {
+                tonic::Code::AlreadyExists => {
+                    crate::errors::Error::Conflict(s.message().to_string()).into()
+                }
+                _ => anyhow::anyhow!(
+                    "gRPC upload_file failed: code={:?} message={}",
+                    s.code(),
+                    s.message()
+                ),
+            })?
+            .into_inner();
+
+        if response.success {
+            Ok(response.bytes_written)
+        } else {
+            anyhow::bail!("Upload failed: {}", response.error)
+        }
+    }
+
+    /// Write a small file (such as a bundle manifest) on the node.
+    #[instrument(skip(self, content))]
+    pub async fn write_file(&self, path: &str, content: Vec<u8>) -> Result<()> {
+        debug!(
+            "Writing {} bytes to {} on node {}",
+            content.len(),
+            path,
+            self.address
+        );
+
+        let mut client = self.connect_file_transfer_service().await?;
+
+        let response = client
+            .write_file(WriteFileRequest {
+                path: path.to_string(),
+                content,
+            })
+            .await
+            .context("Failed to write file on qarax-node")?
+            .into_inner();
+
+        if response.success {
+            Ok(())
+        } else {
+            anyhow::bail!("Write failed: {}", response.error)
+        }
+    }
+
+    /// Read a small file (such as a bundle manifest) from the node.
     #[instrument(skip(self))]
-    pub async fn copy_file(
+    pub async fn read_file(&self, path: &str) -> Result<Vec<u8>> {
+        let mut client = self.connect_file_transfer_service().await?;
+
+        let response = client
+            .read_file(ReadFileRequest {
+                path: path.to_string(),
+            })
+            .await
+            .map_err(|s| match s.code() {
+                tonic::Code::NotFound => crate::errors::Error::NotFound.into(),
+                _ => anyhow::anyhow!("Failed to read {} on qarax-node: {}", path, s.message()),
+            })?;
+
+        Ok(response.into_inner().content)
+    }
+
+    /// SHA-256 a file, or every regular file directly inside a directory, on the node.
+    #[instrument(skip(self))]
+    pub async fn checksum_files(&self, path: &str) -> Result<Vec<FileChecksum>> {
+        let mut client = self.connect_file_transfer_service().await?;
+
+        let response = client
+            .checksum_files(ChecksumFilesRequest {
+                path: path.to_string(),
+            })
+            .await
+            .map_err(|s| {
+                anyhow::anyhow!("Failed to checksum {} on qarax-node: {}", path, s.message())
+            })?;
+
+        Ok(response.into_inner().files)
+    }
+
+    /// Remove a file (or, with `recursive`, a directory tree) on the node.
+    /// Returns the number of bytes freed; a path that is already gone frees 0.
+    #[instrument(skip(self))]
+    pub async fn delete_path(&self, path: &str, recursive: bool) -> Result<i64> {
+        let mut client = self.connect_file_transfer_service().await?;
+
+        let response = client
+            .delete_path(DeletePathRequest {
+                path: path.to_string(),
+                recursive,
+            })
+            .await
+            .map_err(|s| {
+                anyhow::anyhow!("Failed to delete {} on qarax-node: {}", path, s.message())
+            })?;
+
+        Ok(response.into_inner().bytes_freed)
+    }
+
+    /// List the direct children of a directory on the node.
+    #[instrument(skip(self))]
+    pub async fn list_directory(&self, path: &str) -> Result<Vec<DirectoryEntry>> {
+        let mut client = self.connect_file_transfer_service().await?;
+
+        let response = client
+            .list_directory(ListDirectoryRequest {
+                path: path.to_string(),
+            })
+            .await
+            .map_err(|s| match s.code() {
+                tonic::Code::NotFound => crate::errors::Error::NotFound.into(),
+                _ => anyhow::anyhow!("Failed to list {} on qarax-node: {}", path, s.message()),
+            })?;
+
+        Ok(response.into_inner().entries)
+    }
+
+    /// Pack files and inline data on the node into a single tar archive. Returns the archive size.
+    #[instrument(skip(self, members))]
+    pub async fn pack_archive(
+        &self,
+        archive_path: &str,
+        members: Vec<ArchiveMember>,
+    ) -> Result<i64> {
+        let mut client = self.connect_file_transfer_service().await?;
+
+        let response = client
+            .pack_archive(PackArchiveRequest {
+                archive_path: archive_path.to_string(),
+                members,
+            })
+            .await
+            .map_err(|s| {
+                anyhow::anyhow!(
+                    "Failed to pack {} on qarax-node: {}",
+                    archive_path,
+                    s.message()
+                )
+            })?;
+
+        Ok(response.into_inner().size_bytes)
+    }
+
+    /// Write one member of a tar archive on the node to `destination_path`.
+    /// Returns the digest of the extracted file.
+    #[instrument(skip(self))]
+    pub async fn extract_archive_member(
         &self,
-        transfer_id: &str,
-        source_path: &str,
+        archive_path: &str,
+        member: &str,
         destination_path: &str,
-    ) -> Result<i64> {
-        debug!(
-            "Requesting file copy on node {}: {} -> {}",
-            self.address, source_path, destination_path
-        );
-
+    ) -> Result<FileChecksum> {
         let mut client = self.connect_file_transfer_service().await?;
 
         let response = client
-            .copy_file(CopyFileRequest {
-                transfer_id: transfer_id.to_string(),
-                source_path: source_path.to_string(),
+            .extract_archive_member(ExtractArchiveMemberRequest {
+                archive_path: archive_path.to_string(),
+                member: member.to_string(),
                 destination_path: destination_path.to_string(),
             })
             .await
             .map_err(|s| {
                 anyhow::anyhow!(
-                    "gRPC copy_file failed: code={:?} message={}",
-                    s.code(),
+                    "Failed to extract {} from {} on qarax-node: {}",
+                    member,
+                    archive_path,
                     s.message()
                 )
-            })?
-            .into_inner();
+            })?;
 
-        if response.success {
-            Ok(response.bytes_written)
-        } else {
-            anyhow::bail!("Copy failed: {}", response.error)
-        }
+        Ok(response.into_inner())
+    }
+
+    /// Read a single small member (such as a bundle manifest) out of a tar archive on the node.
+    #[instrument(skip(self))]
+    pub async fn read_archive_file(&self, archive_path: &str, member: &str) -> Result<Vec<u8>> {
+        let mut client = self.connect_file_transfer_service().await?;
+
+        let response = client
+            .read_archive_file(ReadArchiveFileRequest {
+                archive_path: archive_path.to_string(),
+                member: member.to_string(),
+            })
+            .await
+            .map_err(|s| match s.code() {
+                tonic::Code::NotFound => crate::errors::Error::NotFound.into(),
+                tonic::Code::InvalidArgument => {
+                    crate::errors::Error::UnprocessableEntity(s.message().to_string()).into()
+                }
+                _ => anyhow::anyhow!(
+                    "Failed to read {} from {} on qarax-node: {}",
+                    member,
+                    archive_path,
+                    s.message()
+                ),
+            })?;
+
+        Ok(response.into_inner().content)
+    }
+
+    /// Create a blank disk file on the node, sparse or preallocated.
+    #[instrument(skip(self))]
+    pub async fn create_disk(&self, path: &str, size_bytes: i64, preallocate: bool) -> Result<i64> {
+        debug!(
+            "Creating disk on node {}: path={path} size={size_bytes}",
+            self.address
+        );
+
+        let mut stream = self
+            .send_create_disk_request(
+                CreateDiskRequest {
+                    path: path.to_string(),
+                    size_bytes,
+                    source: Some(node::create_disk_request::Source::Blank(BlankDiskSource {
+                        preallocate,
+                    })),
+                },
+                "create_disk",
+            )
+            .await?;
+
+        consume_create_disk_stream(&mut stream, "create_disk", |_| async {})
+            .await
+            .map(|r| r.bytes_written)
     }
 
-    /// Create a disk file on the node: blank (sparse or preallocated) or populated from source_url.
+    /// Create a disk file on the node populated from `source_url`, optionally
+    /// verifying a checksum and throttling the download.
     #[instrument(skip(self))]
-    pub async fn create_disk(
+    pub async fn create_disk_from_url(
         &self,
         path: &str,
         size_bytes: i64,
-        source_url: Option<&str>,
-        preallocate: bool,
-    ) -> Result<i64> {
+        source_url: &str,
+        options: &DownloadOptions,
+    ) -> Result<DownloadResult> {
         debug!(
-            "Creating disk on node {}: path={path} size={size_bytes}",
+            "Creating disk on node {} from {source_url}: path={path}",
             self.address
         );
 
-        let source = match source_url {
-            Some(url) => Some(node::create_disk_request::Source::Url(UrlDiskSource {
-                url: url.to_string(),
-            })),
-            None => Some(node::create_disk_request::Source::Blank(BlankDiskSource {
-                preallocate,
-            })),
-        };
-
         let mut stream = self
             .send_create_disk_request(
                 CreateDiskRequest {
                     path: path.to_string(),
                     size_bytes,
-                    source,
+                    source: Some(node::create_disk_request::Source::Url(UrlDiskSource {
+                        url: source_url.to_string(),
+                        expected_checksum: options.expected_checksum.clone(),
+                        bandwidth_limit_bytes_per_sec: options.bandwidth_limit_bytes_per_sec,
+                    })),
                 },
                 "create_disk",
             )
             .await?;
 
-        consume_create_disk_stream(&mut stream, "create_disk", |_| async {}).await
+        let response = consume_create_disk_stream(&mut stream, "create_disk", |_| async {}).await?;
+        download_result(response, "create_disk")
     }
 
     /// Create a disk from an OverlayBD TCMU block device.
@@ -751,7 +1118,10 @@ impl NodeClient {
             )
             .await?;
 
-        consume_create_disk_stream(&mut stream, "create_disk (overlaybd)", &mut on_progress).await
+        consume_create_disk_stream(&mut stream, "create_disk (overlaybd)", &mut on_progress)
+            .await
+            .and_then(|r| download_result(r, "create_disk (overlaybd)"))
+            .map(|r| r.bytes_written)
     }
 
     async fn send_create_disk_request(
@@ -774,11 +1144,37 @@ impl NodeClient {
     }
 
     /// Import (convert + push) an OCI image for OverlayBD lazy loading
-    #[instrument(skip(self))]
+    #[instrument(skip(self, logins))]
+    /// Digest the upstream `image_ref` currently points at.
+    pub async fn resolve_image_digest(
+        &self,
+        image_ref: &str,
+        logins: &[RegistryLogin],
+    ) -> Result<String> {
+        let mut client = self.connect_vm_service().await?;
+
+        let response = client
+            .resolve_image_digest(ResolveImageDigestRequest {
+                image_ref: image_ref.to_string(),
+                registry_credentials: registry_credentials(logins),
+            })
+            .await
+            .map_err(|s| {
+                anyhow::anyhow!(
+                    "gRPC resolve_image_digest failed: code={:?} message={}",
+                    s.code(),
+                    s.message()
+                )
+            })?;
+
+        Ok(response.into_inner().digest)
+    }
+
     pub async fn import_overlaybd_image(
         &self,
         image_ref: &str,
         registry_url: &str,
+        logins: &[RegistryLogin],
     ) -> Result<ImportOverlayBdResponse> {
         debug!(
             "Importing OverlayBD image {} to {} on node {}",
@@ -791,6 +1187,7 @@ impl NodeClient {
             .import_overlay_bd_image(ImportOverlayBdRequest {
                 image_ref: image_ref.to_string(),
                 registry_url: registry_url.to_string(),
+                registry_credentials: registry_credentials(logins),
             })
             .await
             .map_err(|s| {
@@ -804,13 +1201,14 @@ impl NodeClient {
         Ok(response.into_inner())
     }
 
-    #[instrument(skip(self))]
+    #[instrument(skip(self, logins))]
     pub async fn preflight_image(
         &self,
         image_ref: &str,
         registry_url: Option<&str>,
         architecture: &str,
         boot_mode: &str,
+        logins: &[RegistryLogin],
     ) -> Result<PreflightImageResponse> {
         debug!("Preflighting image {} on node {}", image_ref, self.address);
 
@@ -822,6 +1220,7 @@ impl NodeClient {
                 registry_url: registry_url.unwrap_or_default().to_string(),
                 architecture: architecture.to_string(),
                 boot_mode: boot_mode.to_string(),
+                registry_credentials: registry_credentials(logins),
             })
             .await
             .map_err(|s| {
@@ -855,22 +1254,15 @@ impl NodeClient {
     pub async fn attach_storage_pool(
         &self,
         pool: &crate::model::storage_pools::StoragePool,
-    ) -> Result<()> {
-        use crate::model::storage_pools::StoragePoolType;
-
+    ) -> Result<Option<PoolCapacity>> {
         debug!(
             "Attaching storage pool {} ({}) on node {}",
             pool.id, pool.pool_type, self.address
         );
 
-        let pool_kind = match pool.pool_type {
-            StoragePoolType::Local => StoragePoolKind::Local,
-            StoragePoolType::Nfs => StoragePoolKind::Nfs,
-            StoragePoolType::OverlayBd => StoragePoolKind::Overlaybd,
-            StoragePoolType::Block => StoragePoolKind::Block,
-        };
+        let pool_kind = storage_pool_kind(&pool.pool_type);
 
-        let config_json = pool.config.to_string();
+        let config_json = pool.node_config()?.to_string();
 
         let mut client = self.connect_vm_service().await?;
 
@@ -892,7 +1284,7 @@ impl NodeClient {
 
         if response.success {
             debug!("Storage pool {} attached: {}", pool.id, response.message);
-            Ok(())
+            Ok(response.capacity.map(PoolCapacity::from))
         } else {
             anyhow::bail!("attach_storage_pool failed: {}", response.message)
         }
@@ -904,19 +1296,12 @@ impl NodeClient {
         &self,
         pool: &crate::model::storage_pools::StoragePool,
     ) -> Result<()> {
-        use crate::model::storage_pools::StoragePoolType;
-
         debug!(
             "Detaching storage pool {} ({}) on node {}",
             pool.id, pool.pool_type, self.address
         );
 
-        let pool_kind = match pool.pool_type {
-            StoragePoolType::Local => StoragePoolKind::Local,
-            StoragePoolType::Nfs => StoragePoolKind::Nfs,
-            StoragePoolType::OverlayBd => StoragePoolKind::Overlaybd,
-            StoragePoolType::Block => StoragePoolKind::Block,
-        };
+        let pool_kind = storage_pool_kind(&pool.pool_type);
 
         let mut client = self.connect_vm_service().await?;
 
@@ -924,7 +1309,7 @@ impl NodeClient {
             .detach_storage_pool(DetachStoragePoolRequest {
                 pool_id: pool.id.to_string(),
                 pool_kind: pool_kind as i32,
-                config_json: pool.config.to_string(),
+                config_json: pool.node_config()?.to_string(),
             })
             .await
             .map_err(|s| {
@@ -939,9 +1324,182 @@ impl NodeClient {
         Ok(())
     }
 
+    /// Per-path health and measured capacity of a pool attached to the node.
+    /// `paths` is empty for backends without multiple paths.
+    #[instrument(skip(self))]
+    pub async fn storage_pool_health(
+        &self,
+        pool: &crate::model::storage_pools::StoragePool,
+    ) -> Result<StoragePoolHealthResponse> {
+        let mut client = self.connect_vm_service().await?;
+
+        let response = client
+            .get_storage_pool_health(StoragePoolHealthRequest {
+                pool_id: pool.id.to_string(),
+                pool_kind: storage_pool_kind(&pool.pool_type) as i32,
+                config_json: pool.node_config()?.to_string(),
+            })
+            .await
+            .map_err(|s| {
+                anyhow::anyhow!(
+                    "gRPC get_storage_pool_health failed: code={:?} message={}",
+                    s.code(),
+                    s.message()
+                )
+            })?
+            .into_inner();
+
+        Ok(response)
+    }
+
+    /// List the LUNs a BLOCK pool's target exports, as seen by this node.
+    #[instrument(skip(self))]
+    pub async fn discover_luns(
+        &self,
+        pool: &crate::model::storage_pools::StoragePool,
+    ) -> Result<Vec<DiscoveredLun>> {
+        let mut client = self.connect_vm_service().await?;
+
+        let response = client
+            .discover_luns(DiscoverLunsRequest {
+                pool_id: pool.id.to_string(),
+                pool_kind: storage_pool_kind(&pool.pool_type) as i32,
+                config_json: pool.node_config()?.to_string(),
+            })
+            .await
+            .map_err(|s| {
+                anyhow::anyhow!(
+                    "gRPC discover_luns failed: code={:?} message={}",
+                    s.code(),
+                    s.message()
+                )
+            })?
+            .into_inner();
+
+        Ok(response.luns)
+    }
+
+    /// Layer cache usage and hit/miss counters of the node's OverlayBD manager.
+    #[instrument(skip(self))]
+    pub async fn overlaybd_cache(&self) -> Result<OverlayBdCacheInfo> {
+        let mut client = self.connect_vm_service().await?;
+
+        let response = client.get_overlay_bd_cache(()).await.map_err(|s| {
+            anyhow::anyhow!(
+                "gRPC get_overlay_bd_cache failed: code={:?} message={}",
+                s.code(),
+                s.message()
+            )
+        })?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Download every layer of a converted image into the node's OverlayBD
+    /// cache ahead of the first VM start.
+    #[instrument(skip(self))]
+    pub async fn prewarm_overlaybd_image(
+        &self,
+        image_ref: &str,
+        registry_url: &str,
+    ) -> Result<OverlayBdCachedImage> {
+        let mut client = self.connect_vm_service().await?;
+
+        let response = client
+            .prewarm_overlay_bd_image(PrewarmOverlayBdImageRequest {
+                image_ref: image_ref.to_string(),
+                registry_url: registry_url.to_string(),
+            })
+            .await
+            .map_err(|s| {
+                anyhow::anyhow!(
+                    "gRPC prewarm_overlay_bd_image failed: code={:?} message={}",
+                    s.code(),
+                    s.message()
+                )
+            })?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Delete manifests in an OverlayBD pool registry that none of `keep_refs`
+    /// or `keep_digests` name. With `dry_run` only the candidates are reported.
+    #[instrument(skip(self, keep_refs, keep_digests))]
+    pub async fn garbage_collect_overlaybd_images(
+        &self,
+        registry_url: &str,
+        keep_refs: Vec<String>,
+        keep_digests: Vec<String>,
+        dry_run: bool,
+    ) -> Result<OverlayBdGcResponse> {
+        let mut client = self.connect_vm_service().await?;
+
+        let response = client
+            .garbage_collect_overlay_bd_images(OverlayBdGcRequest {
+                registry_url: registry_url.to_string(),
+                keep_refs,
+                keep_digests,
+                dry_run,
+            })
+            .await
+            .map_err(|s| {
+                anyhow::anyhow!(
+                    "gRPC garbage_collect_overlay_bd_images failed: code={:?} message={}",
+                    s.code(),
+                    s.message()
+                )
+            })?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Create, resize, snapshot, clone or remove an image in a pool whose node
+    /// backend owns the images (RBD). `config` is the storage object config.
+    #[instrument(skip(self, config))]
+    pub async fn manage_storage_image(
+        &self,
+        pool: &crate::model::storage_pools::StoragePool,
+        operation: StorageImageOperation,
+        config: &serde_json::Value,
+        size_bytes: i64,
+        snapshot: &str,
+        source_image: &str,
+    ) -> Result<()> {
+        debug!(
+            "Storage image {:?} in pool {} on node {}",
+            operation, pool.id, self.address
+        );
+
+        let mut client = self.connect_vm_service().await?;
+
+        client
+            .manage_storage_image(ManageStorageImageRequest {
+                pool_kind: storage_pool_kind(&pool.pool_type) as i32,
+                operation: operation as i32,
+                config_json: config.to_string(),
+                size_bytes,
+                snapshot: snapshot.to_string(),
+                source_image: source_image.to_string(),
+            })
+            .await
+            .map_err(|s| {
+                anyhow::anyhow!(
+                    "gRPC manage_storage_image failed: code={:?} message={}",
+                    s.code(),
+                    s.message()
+                )
+            })?;
+
+        Ok(())
+    }
+
     /// Attach a network (create bridge, start DHCP server, setup NAT) on the node.
     /// If `parent_interface` is non-empty, bridges that NIC instead of creating
-    /// an isolated bridge (skips NAT).
+    /// an isolated bridge (skips NAT). `ipv6` is the `(subnet6, gateway6)` pair
+    /// of a dual-stack network, which the node advertises to guests.
+    /// `gateway_mac` is the anycast gateway MAC of a stretched network.
+    /// `vlan_id` makes the node bridge the `parent_interface.VID` sub-interface.
+    /// `dhcp_options` are handed out by the node's DHCP server.
     #[instrument(skip(self))]
     #[allow(clippy::too_many_arguments)]
     pub async fn attach_network(
@@ -953,6 +1511,11 @@ impl NodeClient {
         dhcp_range_start: &str,
         dhcp_range_end: &str,
         parent_interface: &str,
+        ipv6: Option<(&str, &str)>,
+        gateway_mac: Option<&str>,
+        vlan_id: Option<u16>,
+        dhcp_options: DhcpOptions,
+        dhcp_reservations: Vec<DhcpReservation>,
     ) -> Result<()> {
         debug!(
             "Attaching network bridge {} on node {}",
@@ -970,6 +1533,16 @@ impl NodeClient {
                 dhcp_range_start: dhcp_range_start.to_string(),
                 dhcp_range_end: dhcp_range_end.to_string(),
                 parent_interface: parent_interface.to_string(),
+                subnet6: ipv6
+                    .map(|(subnet6, _)| subnet6.to_string())
+                    .unwrap_or_default(),
+                gateway6: ipv6
+                    .map(|(_, gateway6)| gateway6.to_string())
+                    .unwrap_or_default(),
+                gateway_mac: gateway_mac.unwrap_or_default().to_string(),
+                vlan_id: vlan_id.map(u32::from).unwrap_or_default(),
+                dhcp_options: Some(dhcp_options),
+                dhcp_reservations,
             })
             .await
             .map_err(|s| {
@@ -986,7 +1559,12 @@ impl NodeClient {
 
     /// Detach a network (stop DHCP server, teardown NAT, delete bridge) on the node.
     #[instrument(skip(self))]
-    pub async fn detach_network(&self, bridge_name: &str, subnet: &str) -> Result<()> {
+    pub async fn detach_network(
+        &self,
+        bridge_name: &str,
+        subnet: &str,
+        subnet6: Option<&str>,
+    ) -> Result<()> {
         debug!(
             "Detaching network bridge {} on node {}",
             bridge_name, self.address
@@ -998,6 +1576,7 @@ impl NodeClient {
             .detach_network(DetachNetworkRequest {
                 bridge_name: bridge_name.to_string(),
                 subnet: subnet.to_string(),
+                subnet6: subnet6.unwrap_or_default().to_string(),
             })
             .await
             .map_err(|s| {
@@ -1017,6 +1596,7 @@ impl NodeClient {
         &self,
         bridge_name: &str,
         local_subnet: &str,
+        local_subnet6: Option<&str>,
         blocked_subnets: &[String],
         nat_exempt_subnets: &[String],
     ) -> Result<()> {
@@ -1027,6 +1607,7 @@ impl NodeClient {
                 blocked_subnets: blocked_subnets.to_vec(),
                 local_subnet: local_subnet.to_string(),
                 nat_exempt_subnets: nat_exempt_subnets.to_vec(),
+                local_subnet6: local_subnet6.unwrap_or_default().to_string(),
             })
             .await
             .map_err(|s| {
@@ -1057,17 +1638,73 @@ impl NodeClient {
         Ok(())
     }
 
-    #[instrument(skip(self, interfaces))]
+    #[instrument(skip(self, request))]
+    pub async fn sync_stretched_network(&self, request: SyncStretchedNetworkRequest) -> Result<()> {
+        let mut client = self.connect_vm_service().await?;
+        client.sync_stretched_network(request).await.map_err(|s| {
+            anyhow::anyhow!(
+                "gRPC sync_stretched_network failed: code={:?} message={}",
+                s.code(),
+                s.message()
+            )
+        })?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, request))]
+    pub async fn sync_vm_nat(&self, request: SyncVmNatRequest) -> Result<()> {
+        let mut client = self.connect_vm_service().await?;
+        client.sync_vm_nat(request).await.map_err(|s| {
+            anyhow::anyhow!(
+                "gRPC sync_vm_nat failed: code={:?} message={}",
+                s.code(),
+                s.message()
+            )
+        })?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, request))]
+    pub async fn sync_dhcp_reservations(&self, request: SyncDhcpReservationsRequest) -> Result<()> {
+        let mut client = self.connect_vm_service().await?;
+        client.sync_dhcp_reservations(request).await.map_err(|s| {
+            anyhow::anyhow!(
+                "gRPC sync_dhcp_reservations failed: code={:?} message={}",
+                s.code(),
+                s.message()
+            )
+        })?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, request))]
+    pub async fn sync_network_dns(&self, request: SyncNetworkDnsRequest) -> Result<()> {
+        let mut client = self.connect_vm_service().await?;
+        client.sync_network_dns(request).await.map_err(|s| {
+            anyhow::anyhow!(
+                "gRPC sync_network_dns failed: code={:?} message={}",
+                s.code(),
+                s.message()
+            )
+        })?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, interfaces, ports))]
     pub async fn sync_vm_firewall(
         &self,
         vm_id: Uuid,
         interfaces: &[VmFirewallInterface],
+        ports: &[VmPortSecurity],
+        log_denied: bool,
     ) -> Result<()> {
         let mut client = self.connect_vm_service().await?;
         client
             .sync_vm_firewall(SyncVmFirewallRequest {
                 vm_id: vm_id.to_string(),
                 interfaces: interfaces.to_vec(),
+                log_denied,
+                ports: ports.to_vec(),
             })
             .await
             .map_err(|s| {
@@ -1080,6 +1717,32 @@ impl NodeClient {
         Ok(())
     }
 
+    /// Per-rule security-group counters of a VM since its rules were last synced
+    #[instrument(skip(self))]
+    pub async fn get_vm_firewall_counters(&self, vm_id: Uuid) -> Result<VmFirewallCounters> {
+        let mut client = self.connect_vm_service().await?;
+        let response = client
+            .get_vm_firewall_counters(VmId {
+                id: vm_id.to_string(),
+            })
+            .await
+            .context("Failed to get VM firewall counters from qarax-node")?;
+        Ok(response.into_inner())
+    }
+
+    /// Recently denied packets logged for a VM
+    #[instrument(skip(self))]
+    pub async fn get_vm_firewall_denied(&self, vm_id: Uuid) -> Result<VmFirewallDenied> {
+        let mut client = self.connect_vm_service().await?;
+        let response = client
+            .get_vm_firewall_denied(VmId {
+                id: vm_id.to_string(),
+            })
+            .await
+            .context("Failed to get denied packets from qarax-node")?;
+        Ok(response.into_inner())
+    }
+
     /// Read the console log for a VM on the qarax-node
     #[instrument(skip(self))]
     pub async fn read_console_log(&self, vm_id: Uuid) -> Result<ConsoleLogResponse> {
@@ -1342,11 +2005,37 @@ impl NodeClient {
         Ok(())
     }
 
+    /// Recreate a persistent OverlayBD upper layer at `new_size` bytes, first
+    /// committing its writes to `frozen_layer`. `frozen` is false when the
+    /// upper was never created; a non-empty `error` means the layer was
+    /// frozen but the upper was not replaced.
+    #[instrument(skip(self))]
+    pub async fn resize_overlaybd_upper(
+        &self,
+        upper_data: &str,
+        upper_index: &str,
+        frozen_layer: &str,
+        new_size: u64,
+    ) -> Result<ResizeOverlayBdUpperResponse> {
+        let mut client = self.connect_vm_service().await?;
+        let response = client
+            .resize_overlay_bd_upper(ResizeOverlayBdUpperRequest {
+                upper_data: upper_data.to_string(),
+                upper_index: upper_index.to_string(),
+                frozen_layer: frozen_layer.to_string(),
+                new_size,
+            })
+            .await
+            .context("Failed to resize OverlayBD upper layer on qarax-node")?;
+        Ok(response.into_inner())
+    }
+
     /// Prepare the destination node to receive a live migration.
     ///
     /// Returns the `receiver_url` that Cloud Hypervisor is listening on
     /// (e.g. `"tcp://0.0.0.0:49152"`).  Callers must replace `0.0.0.0` with
-    /// the destination host's real IP before passing it to `send_migration`.
+    /// the destination host's real IP (bracketed if IPv6, see `host_for_url`)
+    /// before passing it to `send_migration`.
     #[instrument(skip(self, config))]
     pub async fn receive_migration(
         &self,
@@ -1398,12 +2087,11 @@ async fn consume_create_disk_stream<F, Fut>(
     stream: &mut tonic::Streaming<TransferResponse>,
     label: &str,
     mut on_progress: F,
-) -> Result<i64>
+) -> Result<TransferResponse>
 where
     F: FnMut(i64) -> Fut,
     Fut: std::future::Future<Output = ()>,
 {
-    let mut final_response: Option<TransferResponse> = None;
     while let Some(msg) = stream.message().await.map_err(|s| {
         anyhow::anyhow!(
             "gRPC {label} stream error: code={:?} message={}",
@@ -1412,17 +2100,33 @@ where
         )
     })? {
         if msg.is_final {
-            final_response = Some(msg);
-            break;
+            return Ok(msg);
         }
         on_progress(msg.bytes_written).await;
     }
 
-    match final_response {
-        Some(r) if r.success => Ok(r.bytes_written),
-        Some(r) => anyhow::bail!("{label} failed: {}", r.error),
-        None => anyhow::bail!("gRPC {label} stream ended without final response"),
+    anyhow::bail!("gRPC {label} stream ended without final response")
+}
+
+/// Turn a terminal `TransferResponse` into a result, surfacing checksum
+/// mismatches as [`ChecksumMismatch`] so callers can record the digest.
+fn download_result(response: TransferResponse, label: &str) -> Result<DownloadResult> {
+    if response.success {
+        return Ok(DownloadResult {
+            bytes_written: response.bytes_written,
+            checksum: response.checksum_verified.then_some(response.checksum),
+        });
+    }
+
+    if !response.checksum.is_empty() {
+        return Err(ChecksumMismatch {
+            actual: response.checksum,
+            message: format!("{label} failed: {}", response.error),
+        }
+        .into());
     }
+
+    anyhow::bail!("{label} failed: {}", response.error)
 }
 
 /// Type alias for console channel used in attach_console
@@ -14,11 +14,14 @@ use crate::{
     App,
     handlers::{
         audit::{AuditEvent, AuditEventExt},
-        vm::handler::{CreateSnapshotRequest, create_vm_snapshot, restore_vm_from_snapshot},
+        vm::handler::{
+            CreateSnapshotRequest, create_vm_snapshot, delete_vm_snapshot, restore_vm_from_snapshot,
+        },
     },
     model::{
         audit_log::{AuditAction, AuditResourceType},
         backups::{self, Backup, BackupStatus, BackupType, NewBackup},
+        snapshots,
         storage_objects::{self, NewStorageObject, StorageObjectType},
         storage_pools,
     },
@@ -147,6 +150,24 @@ async fn cleanup_dump_file(dump_path: &str) {
     let _ = fs::remove_file(dump_path).await;
 }
 
+async fn delete_database_backup(env: &App, backup: &Backup) -> Result<()> {
+    let storage_object = storage_objects::get(env.pool(), backup.storage_object_id).await?;
+    let references = storage_objects::count_references(env.pool(), storage_object.id).await?;
+    if references > 0 {
+        return Err(crate::errors::Error::Conflict(format!(
+            "backup storage object {} is still referenced by {} other resource(s)",
+            storage_object.id, references
+        )));
+    }
+
+    if let Some(dump_path) = storage_objects::get_path_from_config(&storage_object.config) {
+        cleanup_dump_file(&dump_path).await;
+    }
+
+    storage_objects::delete(env.pool(), storage_object.id).await?;
+    Ok(())
+}
+
 async fn terminate_database_sessions(env: &App) -> Result<()> {
     let maintenance_url = format!("{}/postgres", env.database().connection_string_without_db());
     let mut connection = PgConnection::connect(&maintenance_url)
@@ -436,3 +457,54 @@ pub async fn restore(
         metadata: None,
     }))
 }
+
+#[utoipa::path(
+    delete,
+    path = "/backups/{backup_id}",
+    params(
+        ("backup_id" = uuid::Uuid, Path, description = "Backup unique identifier")
+    ),
+    responses(
+        (status = 204, description = "Backup and its files deleted"),
+        (status = 404, description = "Backup not found"),
+        (status = 409, description = "Backup is still being created or its storage is referenced elsewhere"),
+        (status = 422, description = "No UP host is attached to the backup's storage pool"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "backups"
+)]
+#[instrument(skip(env))]
+pub async fn delete(
+    Extension(env): Extension<App>,
+    Path(backup_id): Path<Uuid>,
+) -> Result<axum::response::Response> {
+    let backup = backups::get(env.pool(), backup_id).await?;
+    if backup.status == BackupStatus::Creating {
+        return Err(crate::errors::Error::Conflict(
+            "backup is still being created".into(),
+        ));
+    }
+
+    match backup.backup_type {
+        BackupType::Vm => {
+            let snapshot_id = backup
+                .snapshot_id
+                .ok_or(crate::errors::Error::InternalServerError)?;
+            let snapshot = snapshots::get(env.pool(), snapshot_id).await?;
+            delete_vm_snapshot(&env, &snapshot).await?;
+        }
+        BackupType::Database => delete_database_backup(&env, &backup).await?,
+    }
+
+    Ok(ApiResponse {
+        data: (),
+        code: StatusCode::NO_CONTENT,
+    }
+    .with_audit_event(AuditEvent {
+        action: AuditAction::Delete,
+        resource_type: AuditResourceType::Backup,
+        resource_id: backup.id,
+        resource_name: Some(backup.name),
+        metadata: None,
+    }))
+}
@@ -0,0 +1,206 @@
+use std::net::Ipv4Addr;
+
+use super::*;
+use crate::{
+    App,
+    model::{
+        floating_ips::{self, FloatingIp, NewFloatingIp},
+        hosts,
+    },
+    network_policy,
+};
+use axum::{Extension, Json, extract::Path};
+use http::StatusCode;
+use serde::Deserialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AssociateFloatingIpRequest {
+    pub vm_id: Uuid,
+    /// Defaults to the VM's first interface with an address on a managed network.
+    #[serde(default)]
+    pub network_interface_id: Option<Uuid>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/floating-ips",
+    responses(
+        (status = 200, description = "List floating IPs", body = Vec<FloatingIp>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "networks"
+)]
+#[instrument(skip(env))]
+pub async fn list(Extension(env): Extension<App>) -> Result<ApiResponse<Vec<FloatingIp>>> {
+    let floating_ips = floating_ips::list(env.pool()).await?;
+    Ok(ApiResponse {
+        data: floating_ips,
+        code: StatusCode::OK,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/floating-ips/{floating_ip_id}",
+    params(
+        ("floating_ip_id" = uuid::Uuid, Path, description = "Floating IP unique identifier")
+    ),
+    responses(
+        (status = 200, description = "Floating IP found", body = FloatingIp),
+        (status = 404, description = "Floating IP not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "networks"
+)]
+#[instrument(skip(env))]
+pub async fn get(
+    Extension(env): Extension<App>,
+    Path(floating_ip_id): Path<Uuid>,
+) -> Result<ApiResponse<FloatingIp>> {
+    let floating_ip = floating_ips::get(env.pool(), floating_ip_id).await?;
+    Ok(ApiResponse {
+        data: floating_ip,
+        code: StatusCode::OK,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/floating-ips",
+    request_body = NewFloatingIp,
+    responses(
+        (status = 201, description = "Floating IP created successfully", body = String),
+        (status = 409, description = "Floating IP already exists"),
+        (status = 422, description = "Invalid input"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "networks"
+)]
+#[instrument(skip(env))]
+pub async fn create(
+    Extension(env): Extension<App>,
+    Json(new_floating_ip): Json<NewFloatingIp>,
+) -> Result<(StatusCode, String)> {
+    let address: Ipv4Addr = new_floating_ip.address.parse().map_err(|_| {
+        crate::errors::Error::UnprocessableEntity(format!(
+            "invalid floating IP {:?}: expected an IPv4 address",
+            new_floating_ip.address
+        ))
+    })?;
+    // The node removes a floating IP from its uplink on release, which would
+    // cut a host off if the address were its own.
+    if hosts::list(env.pool(), None, None)
+        .await?
+        .iter()
+        .any(|host| host.address.parse::<Ipv4Addr>().ok() == Some(address))
+    {
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "{address} is a host address"
+        )));
+    }
+
+    let id = floating_ips::create(env.pool(), new_floating_ip).await?;
+    Ok((StatusCode::CREATED, id.to_string()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/floating-ips/{floating_ip_id}",
+    params(
+        ("floating_ip_id" = uuid::Uuid, Path, description = "Floating IP unique identifier")
+    ),
+    responses(
+        (status = 204, description = "Floating IP deleted successfully"),
+        (status = 404, description = "Floating IP not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "networks"
+)]
+#[instrument(skip(env))]
+pub async fn delete(
+    Extension(env): Extension<App>,
+    Path(floating_ip_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let floating_ip = floating_ips::get(env.pool(), floating_ip_id).await?;
+    floating_ips::delete(env.pool(), floating_ip_id).await?;
+    if let Some(vm_id) = floating_ip.vm_id {
+        network_policy::sync_vm_nat(env.pool(), vm_id).await?;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/floating-ips/{floating_ip_id}/associate",
+    params(
+        ("floating_ip_id" = uuid::Uuid, Path, description = "Floating IP unique identifier")
+    ),
+    request_body = AssociateFloatingIpRequest,
+    responses(
+        (status = 200, description = "Floating IP associated", body = FloatingIp),
+        (status = 404, description = "Floating IP or VM not found"),
+        (status = 409, description = "Interface already has a floating IP"),
+        (status = 422, description = "VM has no suitable interface, or the node rejected the change"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "networks"
+)]
+#[instrument(skip(env))]
+pub async fn associate(
+    Extension(env): Extension<App>,
+    Path(floating_ip_id): Path<Uuid>,
+    Json(body): Json<AssociateFloatingIpRequest>,
+) -> Result<ApiResponse<FloatingIp>> {
+    let previous = floating_ips::get(env.pool(), floating_ip_id).await?;
+    let nic =
+        network_policy::nat_target_interface(env.pool(), body.vm_id, body.network_interface_id)
+            .await?;
+
+    floating_ips::set_interface(env.pool(), floating_ip_id, Some(nic.id)).await?;
+    // Release the address on the old VM's host before claiming it again.
+    if let Some(old_vm_id) = previous.vm_id
+        && old_vm_id != body.vm_id
+    {
+        network_policy::sync_vm_nat(env.pool(), old_vm_id).await?;
+    }
+    network_policy::sync_vm_nat(env.pool(), body.vm_id).await?;
+
+    Ok(ApiResponse {
+        data: floating_ips::get(env.pool(), floating_ip_id).await?,
+        code: StatusCode::OK,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/floating-ips/{floating_ip_id}/disassociate",
+    params(
+        ("floating_ip_id" = uuid::Uuid, Path, description = "Floating IP unique identifier")
+    ),
+    responses(
+        (status = 200, description = "Floating IP released", body = FloatingIp),
+        (status = 404, description = "Floating IP not found"),
+        (status = 422, description = "The node rejected the change"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "networks"
+)]
+#[instrument(skip(env))]
+pub async fn disassociate(
+    Extension(env): Extension<App>,
+    Path(floating_ip_id): Path<Uuid>,
+) -> Result<ApiResponse<FloatingIp>> {
+    let previous = floating_ips::get(env.pool(), floating_ip_id).await?;
+    floating_ips::set_interface(env.pool(), floating_ip_id, None).await?;
+    if let Some(vm_id) = previous.vm_id {
+        network_policy::sync_vm_nat(env.pool(), vm_id).await?;
+    }
+
+    Ok(ApiResponse {
+        data: floating_ips::get(env.pool(), floating_ip_id).await?,
+        code: StatusCode::OK,
+    })
+}
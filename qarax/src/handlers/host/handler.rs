@@ -79,6 +79,50 @@ pub struct HostEvacuateResponse {
     pub job_id: Uuid,
 }
 
+/// OverlayBD layer cache of one host, read live from its node. Counters start
+/// from zero whenever qarax-node restarts.
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct OverlayBdCacheReport {
+    pub host_id: Uuid,
+    pub used_bytes: u64,
+    /// Size cap set with `--overlaybd-cache-max-bytes`; absent when unlimited.
+    pub max_bytes: Option<u64>,
+    /// Image layers already cached when a VM mounted them.
+    pub hits: u64,
+    /// Image layers overlaybd had to fetch from the registry at mount.
+    pub misses: u64,
+    /// Images evicted to stay under the size cap.
+    pub evictions: u64,
+    /// Most recently used first.
+    pub images: Vec<OverlayBdCachedImage>,
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct OverlayBdCachedImage {
+    pub image_ref: String,
+    pub registry_url: String,
+    pub layers: u32,
+    pub cached_layers: u32,
+    pub cached_bytes: u64,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// A VM on the host is running from this image, so it is never evicted.
+    pub in_use: bool,
+}
+
+impl From<crate::grpc_client::node::OverlayBdCachedImage> for OverlayBdCachedImage {
+    fn from(image: crate::grpc_client::node::OverlayBdCachedImage) -> Self {
+        Self {
+            image_ref: image.image_ref,
+            registry_url: image.registry_url,
+            layers: image.layers,
+            cached_layers: image.cached_layers,
+            cached_bytes: image.cached_bytes,
+            last_used_at: chrono::DateTime::from_timestamp(image.last_used_unix, 0),
+            in_use: image.in_use,
+        }
+    }
+}
+
 fn persisted_vm_architecture(vm: &Vm) -> Option<String> {
     vm.config
         .get("architecture")
@@ -670,20 +714,36 @@ pub async fn init(
 
         // Re-attach existing pools via gRPC (e.g. recreate local dirs after reboot)
         for pool in &existing_pools {
-            if let Err(e) = client.attach_storage_pool(pool).await {
-                warn!(
-                    host_id = %host_id,
-                    pool_id = %pool.id,
-                    error = %e,
-                    "Failed to re-attach storage pool to host via gRPC"
-                );
+            match client.attach_storage_pool(pool).await {
+                Ok(capacity) => {
+                    if let Some(capacity) = capacity
+                        && let Err(e) =
+                            storage_pools::record_capacity(&db_pool, pool.id, host_id, &capacity)
+                                .await
+                    {
+                        warn!(
+                            host_id = %host_id,
+                            pool_id = %pool.id,
+                            error = %e,
+                            "Failed to record pool capacity"
+                        );
+                    }
+                }
+                Err(e) => {
+                    warn!(
+                        host_id = %host_id,
+                        pool_id = %pool.id,
+                        error = %e,
+                        "Failed to re-attach storage pool to host via gRPC"
+                    );
+                }
             }
         }
 
         // Attach new shared pools
         for pool in &new_shared {
             match client.attach_storage_pool(pool).await {
-                Ok(()) => {
+                Ok(capacity) => {
                     if let Err(e) = storage_pools::attach_host(&db_pool, pool.id, host_id).await {
                         warn!(
                             host_id = %host_id,
@@ -691,6 +751,17 @@ pub async fn init(
                             error = %e,
                             "Failed to record pool attachment in DB"
                         );
+                    } else if let Some(capacity) = capacity
+                        && let Err(e) =
+                            storage_pools::record_capacity(&db_pool, pool.id, host_id, &capacity)
+                                .await
+                    {
+                        warn!(
+                            host_id = %host_id,
+                            pool_id = %pool.id,
+                            error = %e,
+                            "Failed to record pool capacity"
+                        );
                     }
                 }
                 Err(e) => {
@@ -1028,3 +1099,52 @@ pub async fn resources(
         code: StatusCode::OK,
     })
 }
+
+#[utoipa::path(
+    get,
+    path = "/hosts/{host_id}/overlaybd-cache",
+    params(
+        ("host_id" = uuid::Uuid, Path, description = "Host unique identifier")
+    ),
+    responses(
+        (status = 200, description = "OverlayBD layer cache usage and counters", body = OverlayBdCacheReport),
+        (status = 404, description = "Host not found"),
+        (status = 422, description = "Host is not up"),
+        (status = 500, description = "Node did not report its cache")
+    ),
+    tag = "hosts"
+)]
+#[instrument(skip(env))]
+pub async fn overlaybd_cache(
+    Extension(env): Extension<App>,
+    Path(host_id): Path<Uuid>,
+) -> Result<ApiResponse<OverlayBdCacheReport>> {
+    let host = hosts::require_by_id(env.pool(), host_id).await?;
+    if host.status != HostStatus::Up {
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "host {} must be up to report its OverlayBD cache (current status: {})",
+            host.name, host.status
+        )));
+    }
+
+    let cache = NodeClient::new(&host.address, host.port as u16)
+        .overlaybd_cache()
+        .await
+        .map_err(|e| {
+            error!(host_id = %host_id, "Failed to read OverlayBD cache: {}", e);
+            crate::errors::Error::InternalServerError
+        })?;
+
+    Ok(ApiResponse {
+        data: OverlayBdCacheReport {
+            host_id,
+            used_bytes: cache.used_bytes,
+            max_bytes: (cache.max_bytes > 0).then_some(cache.max_bytes),
+            hits: cache.hits,
+            misses: cache.misses,
+            evictions: cache.evictions,
+            images: cache.images.into_iter().map(Into::into).collect(),
+        },
+        code: StatusCode::OK,
+    })
+}
@@ -0,0 +1,238 @@
+use super::*;
+use crate::{
+    App,
+    model::{
+        image_catalogs::{
+            self, ImageCatalog, ImageCatalogVersion, NewImageCatalog, UpdateImageCatalog,
+        },
+        jobs::{self, JobType, NewJob},
+    },
+};
+use axum::{Extension, Json, extract::Path};
+use http::StatusCode;
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Serialize, ToSchema)]
+pub struct ImageCatalogRefreshResponse {
+    pub job_id: Uuid,
+}
+
+#[utoipa::path(
+    get,
+    path = "/image-catalogs",
+    params(crate::handlers::NameQuery),
+    responses(
+        (status = 200, description = "List image catalogs", body = Vec<ImageCatalog>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "image-catalogs"
+)]
+#[instrument(skip(env))]
+pub async fn list(
+    Extension(env): Extension<App>,
+    axum::extract::Query(query): axum::extract::Query<crate::handlers::NameQuery>,
+) -> Result<ApiResponse<Vec<ImageCatalog>>> {
+    let catalogs = image_catalogs::list(env.pool(), query.name.as_deref()).await?;
+    Ok(ApiResponse {
+        data: catalogs,
+        code: StatusCode::OK,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/image-catalogs/{catalog_id}",
+    params(
+        ("catalog_id" = uuid::Uuid, Path, description = "Image catalog unique identifier")
+    ),
+    responses(
+        (status = 200, description = "Image catalog found", body = ImageCatalog),
+        (status = 404, description = "Image catalog not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "image-catalogs"
+)]
+#[instrument(skip(env))]
+pub async fn get(
+    Extension(env): Extension<App>,
+    Path(catalog_id): Path<Uuid>,
+) -> Result<ApiResponse<ImageCatalog>> {
+    let catalog = image_catalogs::get(env.pool(), catalog_id).await?;
+    Ok(ApiResponse {
+        data: catalog,
+        code: StatusCode::OK,
+    })
+}
+
+/// Start tracking an upstream image. The first version is imported by the
+/// next refresh pass, or immediately via `POST /image-catalogs/{id}/refresh`.
+#[utoipa::path(
+    post,
+    path = "/image-catalogs",
+    request_body = NewImageCatalog,
+    responses(
+        (status = 201, description = "Image catalog created successfully", body = String),
+        (status = 404, description = "Storage pool not found"),
+        (status = 409, description = "An image catalog with this name already exists"),
+        (status = 422, description = "Invalid input or not an OverlayBD pool"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "image-catalogs"
+)]
+#[instrument(skip(env))]
+pub async fn create(
+    Extension(env): Extension<App>,
+    Json(new_catalog): Json<NewImageCatalog>,
+) -> Result<(StatusCode, String)> {
+    let id = image_catalogs::create(env.pool(), &new_catalog).await?;
+    Ok((StatusCode::CREATED, id.to_string()))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/image-catalogs/{catalog_id}",
+    params(
+        ("catalog_id" = uuid::Uuid, Path, description = "Image catalog unique identifier")
+    ),
+    request_body = UpdateImageCatalog,
+    responses(
+        (status = 200, description = "Image catalog updated", body = ImageCatalog),
+        (status = 404, description = "Image catalog not found"),
+        (status = 422, description = "Invalid input"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "image-catalogs"
+)]
+#[instrument(skip(env))]
+pub async fn update(
+    Extension(env): Extension<App>,
+    Path(catalog_id): Path<Uuid>,
+    Json(update): Json<UpdateImageCatalog>,
+) -> Result<ApiResponse<ImageCatalog>> {
+    let catalog = image_catalogs::update(env.pool(), catalog_id, &update).await?;
+    Ok(ApiResponse {
+        data: catalog,
+        code: StatusCode::OK,
+    })
+}
+
+/// Stop tracking an image. Imported versions stay in the pool as ordinary
+/// OCI_IMAGE storage objects.
+#[utoipa::path(
+    delete,
+    path = "/image-catalogs/{catalog_id}",
+    params(
+        ("catalog_id" = uuid::Uuid, Path, description = "Image catalog unique identifier")
+    ),
+    responses(
+        (status = 204, description = "Image catalog deleted successfully"),
+        (status = 404, description = "Image catalog not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "image-catalogs"
+)]
+#[instrument(skip(env))]
+pub async fn delete(
+    Extension(env): Extension<App>,
+    Path(catalog_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    image_catalogs::delete(env.pool(), catalog_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/image-catalogs/{catalog_id}/versions",
+    params(
+        ("catalog_id" = uuid::Uuid, Path, description = "Image catalog unique identifier")
+    ),
+    responses(
+        (status = 200, description = "Imported versions, newest first", body = Vec<ImageCatalogVersion>),
+        (status = 404, description = "Image catalog not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "image-catalogs"
+)]
+#[instrument(skip(env))]
+pub async fn list_versions(
+    Extension(env): Extension<App>,
+    Path(catalog_id): Path<Uuid>,
+) -> Result<ApiResponse<Vec<ImageCatalogVersion>>> {
+    image_catalogs::get(env.pool(), catalog_id).await?;
+    let versions = image_catalogs::list_versions(env.pool(), catalog_id).await?;
+    Ok(ApiResponse {
+        data: versions,
+        code: StatusCode::OK,
+    })
+}
+
+/// Check the upstream digest now instead of waiting for the refresh interval.
+#[utoipa::path(
+    post,
+    path = "/image-catalogs/{catalog_id}/refresh",
+    params(
+        ("catalog_id" = uuid::Uuid, Path, description = "Image catalog unique identifier")
+    ),
+    responses(
+        (status = 202, description = "Refresh job accepted", body = ImageCatalogRefreshResponse),
+        (status = 404, description = "Image catalog not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "image-catalogs"
+)]
+#[instrument(skip(env))]
+pub async fn refresh(
+    Extension(env): Extension<App>,
+    Path(catalog_id): Path<Uuid>,
+) -> Result<axum::response::Response> {
+    use axum::response::IntoResponse as _;
+
+    let catalog = image_catalogs::get(env.pool(), catalog_id).await?;
+    let job = jobs::create(
+        env.pool(),
+        NewJob {
+            job_type: JobType::ImageCatalogRefresh,
+            description: Some(format!(
+                "Refreshing image catalog {} ({})",
+                catalog.name, catalog.source_ref
+            )),
+            resource_id: Some(catalog_id),
+            resource_type: Some(jobs::resource_types::IMAGE_CATALOG.to_string()),
+        },
+    )
+    .await?;
+    let job_id = job.id;
+
+    tokio::spawn(async move {
+        if let Err(e) = jobs::mark_running(env.pool(), job_id).await {
+            tracing::error!(job_id = %job_id, error = %e, "Failed to mark catalog refresh job running");
+            return;
+        }
+
+        match crate::image_catalog_refresh::refresh(&env, &catalog).await {
+            Ok(outcome) => {
+                let _ = jobs::mark_completed(env.pool(), job_id, Some(outcome.to_json())).await;
+                tracing::info!(
+                    catalog_id = %catalog_id,
+                    job_id = %job_id,
+                    imported = outcome.imported,
+                    "Image catalog refresh job completed"
+                );
+            }
+            Err(e) => {
+                let msg = format!("Image catalog refresh failed: {:#}", e);
+                tracing::error!(catalog_id = %catalog_id, error = %msg);
+                let _ = jobs::mark_failed(env.pool(), job_id, &msg).await;
+            }
+        }
+    });
+
+    Ok(ApiResponse {
+        data: ImageCatalogRefreshResponse { job_id },
+        code: StatusCode::ACCEPTED,
+    }
+    .into_response())
+}
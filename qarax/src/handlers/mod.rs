@@ -29,11 +29,15 @@ mod audit_log;
 mod backup;
 mod boot_source;
 mod events;
+mod floating_ip;
 mod host;
+mod image_catalog;
 mod instance_type;
 mod job;
 mod lifecycle_hook;
 mod network;
+mod port_forward;
+mod registry_credential;
 mod sandbox;
 mod scheduling;
 mod security_group;
@@ -94,6 +98,7 @@ pub struct BackupListQuery {
         backup::handler::get,
         backup::handler::create,
         backup::handler::restore,
+        backup::handler::delete,
         host::handler::list,
         host::handler::add,
         host::handler::update,
@@ -105,6 +110,7 @@ pub struct BackupListQuery {
         host::handler::list_gpus,
         host::handler::list_numa_nodes,
         host::handler::resources,
+        host::handler::overlaybd_cache,
         instance_type::handler::list,
         instance_type::handler::get,
         instance_type::handler::create,
@@ -121,6 +127,7 @@ pub struct BackupListQuery {
         vm::handler::resume,
         vm::handler::list_snapshots,
         vm::handler::create_snapshot,
+        vm::handler::delete_snapshot,
         vm::handler::restore,
         vm::handler::migrate,
         vm::handler::delete,
@@ -136,9 +143,15 @@ pub struct BackupListQuery {
         vm::handler::list_security_groups,
         vm::handler::attach_security_group,
         vm::handler::detach_security_group,
+        vm::handler::list_allowed_address_pairs,
+        vm::handler::update_allowed_address_pairs,
+        vm::handler::get_firewall_log,
+        vm::handler::update_firewall_log,
         vm::handler::resize_vm,
         vm::handler::resize_disk,
         vm::handler::commit,
+        vm::bundle::export,
+        vm::bundle::import,
         storage_object::handler::list,
         storage_object::handler::get,
         storage_object::handler::create,
@@ -149,9 +162,13 @@ pub struct BackupListQuery {
         storage_pool::handler::delete,
         storage_pool::handler::attach_host,
         storage_pool::handler::detach_host,
+        storage_pool::handler::list_paths,
         storage_pool::handler::import_to_pool,
+        storage_pool::handler::prewarm,
+        storage_pool::handler::gc_images,
         storage_pool::handler::create_disk,
         storage_pool::handler::register_lun,
+        storage_pool::handler::discover_luns,
         boot_source::handler::list,
         boot_source::handler::get,
         boot_source::handler::create,
@@ -163,6 +180,9 @@ pub struct BackupListQuery {
         transfer::handler::create,
         transfer::handler::list,
         transfer::handler::get,
+        transfer::upload::upload,
+        transfer::upload::get,
+        transfer::upload::cancel,
         job::handler::get,
         network::handler::list,
         network::handler::get,
@@ -171,6 +191,16 @@ pub struct BackupListQuery {
         network::handler::attach_host,
         network::handler::detach_host,
         network::handler::list_ips,
+        floating_ip::handler::list,
+        floating_ip::handler::get,
+        floating_ip::handler::create,
+        floating_ip::handler::delete,
+        floating_ip::handler::associate,
+        floating_ip::handler::disassociate,
+        port_forward::handler::list,
+        port_forward::handler::get,
+        port_forward::handler::create,
+        port_forward::handler::delete,
         security_group::handler::list,
         security_group::handler::get,
         security_group::handler::create,
@@ -178,12 +208,25 @@ pub struct BackupListQuery {
         security_group::handler::list_rules,
         security_group::handler::create_rule,
         security_group::handler::delete_rule,
+        security_group::handler::stats,
         lifecycle_hook::handler::list,
         lifecycle_hook::handler::get,
         lifecycle_hook::handler::create,
         lifecycle_hook::handler::update,
         lifecycle_hook::handler::delete,
         lifecycle_hook::handler::list_executions,
+        registry_credential::handler::list,
+        registry_credential::handler::get,
+        registry_credential::handler::create,
+        registry_credential::handler::update,
+        registry_credential::handler::delete,
+        image_catalog::handler::list,
+        image_catalog::handler::get,
+        image_catalog::handler::create,
+        image_catalog::handler::update,
+        image_catalog::handler::delete,
+        image_catalog::handler::list_versions,
+        image_catalog::handler::refresh,
         sandbox::handler::create,
         sandbox::handler::list,
         sandbox::handler::get,
@@ -203,6 +246,8 @@ pub struct BackupListQuery {
             crate::model::hosts::UpdateHostPlacementRequest,
             crate::model::hosts::DeployHostRequest,
             crate::handlers::host::handler::HostEvacuateResponse,
+            crate::handlers::host::handler::OverlayBdCacheReport,
+            crate::handlers::host::handler::OverlayBdCachedImage,
             crate::model::hosts::HostStatus,
             crate::model::host_gpus::HostGpu,
             crate::model::host_gpus::AcceleratorConfig,
@@ -226,6 +271,10 @@ pub struct BackupListQuery {
             crate::model::storage_pools::NewStoragePool,
             crate::model::storage_pools::StoragePoolType,
             crate::model::storage_pools::StoragePoolStatus,
+            crate::model::storage_pools::HostPathHealth,
+            crate::model::storage_pools::HostPoolCapacity,
+            crate::model::storage_pools::PoolCapacity,
+            crate::model::storage_pools::StoragePathHealth,
             crate::handlers::storage_pool::handler::AttachPoolHostRequest,
             crate::model::boot_sources::BootSource,
             crate::model::boot_sources::NewBootSource,
@@ -233,6 +282,7 @@ pub struct BackupListQuery {
             crate::model::vm_templates::NewVmTemplate,
             crate::model::network_interfaces::NetworkInterface,
             crate::model::network_interfaces::RateLimiterConfig,
+            crate::model::network_interfaces::AllowedAddressPair,
             crate::model::network_interfaces::TokenBucket,
             crate::model::network_interfaces::InterfaceType,
             crate::model::network_interfaces::VhostMode,
@@ -265,22 +315,46 @@ pub struct BackupListQuery {
             crate::handlers::vm::handler::DiskResizeRequest,
             crate::handlers::vm::handler::CommitVmRequest,
             crate::handlers::vm::handler::CommitVmResponse,
+            crate::handlers::vm::bundle::ExportVmRequest,
+            crate::handlers::vm::bundle::ExportVmResponse,
+            crate::handlers::vm::bundle::ImportVmRequest,
+            crate::handlers::vm::bundle::ImportVmResponse,
             crate::handlers::storage_pool::handler::ImportToPoolRequest,
             crate::handlers::storage_pool::handler::ImportToPoolResponse,
+            crate::handlers::storage_pool::handler::PrewarmRequest,
+            crate::handlers::storage_pool::handler::PrewarmResponse,
+            crate::handlers::storage_pool::handler::ImageGcRequest,
+            crate::handlers::storage_pool::handler::ImageGcResponse,
             crate::handlers::storage_pool::handler::CreateDiskRequest,
             crate::handlers::storage_pool::handler::CreateDiskResponse,
             crate::handlers::storage_pool::handler::RegisterLunRequest,
+            crate::handlers::storage_pool::handler::LunSyncEntry,
+            crate::handlers::storage_pool::handler::LunDiscoveryReport,
             crate::model::networks::Network,
             crate::model::networks::NewNetwork,
+            crate::model::networks::DhcpOptions,
+            crate::model::networks::DhcpStaticRoute,
             crate::model::networks::NetworkStatus,
             crate::model::networks::IpAllocation,
             crate::handlers::network::handler::AttachHostRequest,
+            crate::model::floating_ips::FloatingIp,
+            crate::model::floating_ips::NewFloatingIp,
+            crate::handlers::floating_ip::handler::AssociateFloatingIpRequest,
+            crate::model::port_forwards::PortForward,
+            crate::model::port_forwards::NewPortForward,
+            crate::model::port_forwards::PortForwardProtocol,
             crate::model::security_groups::SecurityGroup,
             crate::model::security_groups::NewSecurityGroup,
             crate::model::security_groups::SecurityGroupRule,
             crate::model::security_groups::NewSecurityGroupRule,
             crate::model::security_groups::SecurityGroupDirection,
             crate::model::security_groups::SecurityGroupProtocol,
+            crate::model::security_groups::SecurityGroupStats,
+            crate::model::security_groups::SecurityGroupRuleStats,
+            crate::model::security_groups::SecurityGroupMemberStats,
+            crate::model::security_groups::VmFirewallLog,
+            crate::model::security_groups::UpdateVmFirewallLog,
+            crate::model::security_groups::DeniedPacket,
             crate::handlers::vm::handler::AttachSecurityGroupRequest,
             crate::model::lifecycle_hooks::LifecycleHook,
             crate::model::lifecycle_hooks::NewLifecycleHook,
@@ -288,6 +362,14 @@ pub struct BackupListQuery {
             crate::model::lifecycle_hooks::HookExecution,
             crate::model::lifecycle_hooks::HookScope,
             crate::model::lifecycle_hooks::HookExecutionStatus,
+            crate::model::registry_credentials::RegistryCredential,
+            crate::model::registry_credentials::NewRegistryCredential,
+            crate::model::registry_credentials::UpdateRegistryCredential,
+            crate::model::image_catalogs::ImageCatalog,
+            crate::model::image_catalogs::ImageCatalogVersion,
+            crate::model::image_catalogs::NewImageCatalog,
+            crate::model::image_catalogs::UpdateImageCatalog,
+            crate::handlers::image_catalog::handler::ImageCatalogRefreshResponse,
             crate::model::sandboxes::Sandbox,
             crate::model::sandboxes::NewSandbox,
             crate::model::sandboxes::SandboxStatus,
@@ -316,6 +398,8 @@ pub struct BackupListQuery {
         (name = "networks", description = "Network management endpoints"),
         (name = "security-groups", description = "Security group management endpoints"),
         (name = "hooks", description = "Lifecycle hook management endpoints"),
+        (name = "registry-credentials", description = "Private OCI registry login management endpoints"),
+        (name = "image-catalogs", description = "Tracked upstream images with automatic refresh"),
         (name = "sandboxes", description = "Ephemeral sandbox environments for AI agents"),
         (name = "sandbox-pools", description = "Prewarmed sandbox pool management endpoints"),
         (name = "scheduling", description = "Scheduling observability endpoints"),
@@ -391,7 +475,11 @@ pub fn app(env: App) -> Router {
         .merge(transfers())
         .merge(jobs())
         .merge(networks())
+        .merge(floating_ips())
+        .merge(port_forwards())
         .merge(hooks())
+        .merge(registry_credentials())
+        .merge(image_catalogs())
         .merge(sandboxes())
         .merge(scheduling())
         .merge(security_groups())
@@ -453,6 +541,10 @@ fn hosts() -> Router {
         .route("/hosts/{host_id}/gpus", get(host::handler::list_gpus))
         .route("/hosts/{host_id}/numa", get(host::handler::list_numa_nodes))
         .route("/hosts/{host_id}/resources", get(host::handler::resources))
+        .route(
+            "/hosts/{host_id}/overlaybd-cache",
+            get(host::handler::overlaybd_cache),
+        )
 }
 
 fn backups() -> Router {
@@ -461,7 +553,10 @@ fn backups() -> Router {
             "/backups",
             get(backup::handler::list).post(backup::handler::create),
         )
-        .route("/backups/{backup_id}", get(backup::handler::get))
+        .route(
+            "/backups/{backup_id}",
+            get(backup::handler::get).delete(backup::handler::delete),
+        )
         .route(
             "/backups/{backup_id}/restore",
             post(backup::handler::restore),
@@ -472,6 +567,7 @@ fn vms() -> Router {
     Router::new()
         .route("/vms", get(vm::handler::list).post(vm::handler::create))
         .route("/vms/preflight", post(vm::handler::preflight_image))
+        .route("/vms/import", post(vm::bundle::import))
         .route(
             "/vms/{vm_id}",
             get(vm::handler::get).delete(vm::handler::delete),
@@ -505,6 +601,11 @@ fn vms() -> Router {
             "/vms/{vm_id}/nics/{device_id}",
             axum::routing::delete(vm::handler::remove_nic),
         )
+        .route(
+            "/vms/{vm_id}/nics/{device_id}/allowed-address-pairs",
+            get(vm::handler::list_allowed_address_pairs)
+                .put(vm::handler::update_allowed_address_pairs),
+        )
         .route(
             "/vms/{vm_id}/security-groups",
             get(vm::handler::list_security_groups).post(vm::handler::attach_security_group),
@@ -513,10 +614,18 @@ fn vms() -> Router {
             "/vms/{vm_id}/security-groups/{security_group_id}",
             axum::routing::delete(vm::handler::detach_security_group),
         )
+        .route(
+            "/vms/{vm_id}/firewall-log",
+            get(vm::handler::get_firewall_log).put(vm::handler::update_firewall_log),
+        )
         .route(
             "/vms/{vm_id}/snapshots",
             get(vm::handler::list_snapshots).post(vm::handler::create_snapshot),
         )
+        .route(
+            "/vms/{vm_id}/snapshots/{snapshot_id}",
+            axum::routing::delete(vm::handler::delete_snapshot),
+        )
         .route("/vms/{vm_id}/restore", post(vm::handler::restore))
         .route("/vms/{vm_id}/migrate", post(vm::handler::migrate))
         .route(
@@ -528,6 +637,7 @@ fn vms() -> Router {
             axum::routing::put(vm::handler::resize_disk),
         )
         .route("/vms/{vm_id}/commit", post(vm::handler::commit))
+        .route("/vms/{vm_id}/export", post(vm::bundle::export))
 }
 
 fn instance_types() -> Router {
@@ -584,10 +694,22 @@ fn storage_pools() -> Router {
             "/storage-pools/{pool_id}/hosts/{host_id}",
             axum::routing::delete(storage_pool::handler::detach_host),
         )
+        .route(
+            "/storage-pools/{pool_id}/paths",
+            get(storage_pool::handler::list_paths),
+        )
         .route(
             "/storage-pools/{pool_id}/import",
             post(storage_pool::handler::import_to_pool),
         )
+        .route(
+            "/storage-pools/{pool_id}/prewarm",
+            post(storage_pool::handler::prewarm),
+        )
+        .route(
+            "/storage-pools/{pool_id}/gc",
+            post(storage_pool::handler::gc_images),
+        )
         .route(
             "/storage-pools/{pool_id}/disks",
             post(storage_pool::handler::create_disk),
@@ -596,6 +718,10 @@ fn storage_pools() -> Router {
             "/storage-pools/{pool_id}/luns",
             post(storage_pool::handler::register_lun),
         )
+        .route(
+            "/storage-pools/{pool_id}/luns/discover",
+            post(storage_pool::handler::discover_luns),
+        )
 }
 
 fn transfers() -> Router {
@@ -608,6 +734,12 @@ fn transfers() -> Router {
             "/storage-pools/{pool_id}/transfers/{transfer_id}",
             get(transfer::handler::get),
         )
+        .route(
+            "/storage-pools/{pool_id}/uploads/{name}",
+            get(transfer::upload::get)
+                .put(transfer::upload::upload)
+                .delete(transfer::upload::cancel),
+        )
 }
 
 fn jobs() -> Router {
@@ -638,6 +770,38 @@ fn networks() -> Router {
         )
 }
 
+fn floating_ips() -> Router {
+    Router::new()
+        .route(
+            "/floating-ips",
+            get(floating_ip::handler::list).post(floating_ip::handler::create),
+        )
+        .route(
+            "/floating-ips/{floating_ip_id}",
+            get(floating_ip::handler::get).delete(floating_ip::handler::delete),
+        )
+        .route(
+            "/floating-ips/{floating_ip_id}/associate",
+            post(floating_ip::handler::associate),
+        )
+        .route(
+            "/floating-ips/{floating_ip_id}/disassociate",
+            post(floating_ip::handler::disassociate),
+        )
+}
+
+fn port_forwards() -> Router {
+    Router::new()
+        .route(
+            "/port-forwards",
+            get(port_forward::handler::list).post(port_forward::handler::create),
+        )
+        .route(
+            "/port-forwards/{port_forward_id}",
+            get(port_forward::handler::get).delete(port_forward::handler::delete),
+        )
+}
+
 fn hooks() -> Router {
     Router::new()
         .route(
@@ -656,6 +820,42 @@ fn hooks() -> Router {
         )
 }
 
+fn registry_credentials() -> Router {
+    Router::new()
+        .route(
+            "/registry-credentials",
+            get(registry_credential::handler::list).post(registry_credential::handler::create),
+        )
+        .route(
+            "/registry-credentials/{credential_id}",
+            get(registry_credential::handler::get)
+                .patch(registry_credential::handler::update)
+                .delete(registry_credential::handler::delete),
+        )
+}
+
+fn image_catalogs() -> Router {
+    Router::new()
+        .route(
+            "/image-catalogs",
+            get(image_catalog::handler::list).post(image_catalog::handler::create),
+        )
+        .route(
+            "/image-catalogs/{catalog_id}",
+            get(image_catalog::handler::get)
+                .patch(image_catalog::handler::update)
+                .delete(image_catalog::handler::delete),
+        )
+        .route(
+            "/image-catalogs/{catalog_id}/versions",
+            get(image_catalog::handler::list_versions),
+        )
+        .route(
+            "/image-catalogs/{catalog_id}/refresh",
+            post(image_catalog::handler::refresh),
+        )
+}
+
 fn security_groups() -> Router {
     Router::new()
         .route(
@@ -674,6 +874,10 @@ fn security_groups() -> Router {
             "/security-groups/{security_group_id}/rules/{rule_id}",
             axum::routing::delete(security_group::handler::delete_rule),
         )
+        .route(
+            "/security-groups/{security_group_id}/stats",
+            get(security_group::handler::stats),
+        )
 }
 
 fn boot_sources() -> Router {
@@ -78,6 +78,14 @@ pub async fn create(
     Extension(env): Extension<App>,
     Json(new_net): Json<NewNetwork>,
 ) -> Result<(StatusCode, String)> {
+    networks::validate_ipv6(new_net.subnet6.as_deref(), new_net.gateway6.as_deref())
+        .map_err(crate::errors::Error::UnprocessableEntity)?;
+    networks::validate_vlan(new_net.network_type.as_deref(), new_net.vlan_id)
+        .map_err(crate::errors::Error::UnprocessableEntity)?;
+    if let Some(options) = &new_net.dhcp_options {
+        networks::validate_dhcp_options(&new_net.subnet, options)
+            .map_err(crate::errors::Error::UnprocessableEntity)?;
+    }
     let id = networks::create(env.pool(), new_net).await?;
     Ok((StatusCode::CREATED, id.to_string()))
 }
@@ -135,44 +143,92 @@ pub async fn attach_host(
 ) -> Result<StatusCode> {
     let network = networks::get(env.pool(), network_id).await?;
     let host = hosts::require_by_id(env.pool(), body.host_id).await?;
+    attach_network_to_host(
+        &env,
+        &network,
+        &host,
+        &body.bridge_name,
+        body.parent_interface.as_deref(),
+    )
+    .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Provision a network's bridge on a host and record the attachment.
+pub(crate) async fn attach_network_to_host(
+    env: &App,
+    network: &Network,
+    host: &hosts::Host,
+    bridge_name: &str,
+    parent_interface: Option<&str>,
+) -> Result<()> {
+    let network_id = network.id;
 
     // passt-backed networks don't require bridge/DHCP/NAT provisioning.
     if network.network_type.as_deref() == Some("passt") {
-        networks::attach_host(env.pool(), network_id, body.host_id, &body.bridge_name).await?;
-        network_policy::sync_cluster_vpc_state(&env, &network, &[body.host_id]).await?;
-        return Ok(StatusCode::NO_CONTENT);
+        networks::attach_host(env.pool(), network_id, host.id, bridge_name).await?;
+        network_policy::sync_cluster_vpc_state(env, network, &[host.id]).await?;
+        return Ok(());
     }
 
-    let parent_interface = body.parent_interface.clone().unwrap_or_default();
+    let stretched = network.is_stretched();
+    if stretched && parent_interface.is_some() {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "stretched networks use a qarax-managed bridge; parent_interface is not supported"
+                .into(),
+        ));
+    }
+    if network.is_vlan() && parent_interface.is_none() {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "vlan networks need parent_interface, the uplink that carries the tagged traffic"
+                .into(),
+        ));
+    }
+    let parent_interface = parent_interface.unwrap_or_default();
 
     // Both isolated and bridged modes need DHCP range (for the DHCP server to serve VMs).
-    // Bridged mode skips NAT but still needs DHCP.
-    let (dhcp_start, dhcp_end) = compute_dhcp_range(&network.subnet, network.gateway.as_deref());
+    // Bridged mode skips NAT but still needs DHCP. Stretched networks get
+    // their single DHCP server from sync_stretched_network instead. A VLAN is
+    // one segment shared by every host on the uplink, so a server per host
+    // would compete; guests there rely on the addressing pinned at boot.
+    let (dhcp_start, dhcp_end) = if stretched || network.is_vlan() {
+        (String::new(), String::new())
+    } else {
+        networks::compute_dhcp_range(&network.subnet, network.gateway.as_deref())
+    };
 
-    let gateway = network
-        .gateway
-        .clone()
-        .unwrap_or_else(|| default_gateway(&network.subnet));
+    let gateway = network.ipv4_gateway();
 
     let dns = network.dns.clone().unwrap_or_else(|| gateway.clone());
+    let gateway6 = network.ipv6_gateway();
+    let ipv6 = network.subnet6.as_deref().zip(gateway6.as_deref());
+    let gateway_mac = stretched.then(|| network.anycast_gateway_mac());
+    // Reservations go with the attach so the DHCP server never starts
+    // without them, including after the node restarted.
+    let dhcp_reservations = network_policy::dhcp_reservations(env.pool(), network).await?;
 
     // Call gRPC to set up bridge on the node
     let client = NodeClient::new(&host.address, host.port as u16);
     client
         .attach_network(
-            &body.bridge_name,
+            bridge_name,
             &network.subnet,
             &gateway,
             &dns,
             &dhcp_start,
             &dhcp_end,
-            &parent_interface,
+            parent_interface,
+            ipv6,
+            gateway_mac.as_deref(),
+            network.vlan_id.and_then(|id| u16::try_from(id).ok()),
+            network_policy::dhcp_options(network),
+            dhcp_reservations,
         )
         .await
         .map_err(|e| {
             tracing::error!(
                 network_id = %network_id,
-                host_id = %body.host_id,
+                host_id = %host.id,
                 error = %e,
                 "gRPC attach_network failed"
             );
@@ -182,9 +238,15 @@ pub async fn attach_host(
         })?;
 
     // Record the attachment in the DB
-    networks::attach_host(env.pool(), network_id, body.host_id, &body.bridge_name).await?;
-    network_policy::sync_cluster_vpc_state(&env, &network, &[body.host_id]).await?;
-    Ok(StatusCode::NO_CONTENT)
+    networks::attach_host(env.pool(), network_id, host.id, bridge_name).await?;
+    network_policy::sync_cluster_vpc_state(env, network, &[host.id]).await?;
+    if stretched {
+        network_policy::sync_stretched_network(env.pool(), network).await?;
+    }
+    if let Err(e) = network_policy::sync_network_dns(env.pool(), network_id).await {
+        tracing::warn!(network_id = %network_id, error = %e, "Failed to sync network DNS records");
+    }
+    Ok(())
 }
 
 #[utoipa::path(
@@ -217,7 +279,7 @@ pub async fn detach_host(
     // Call gRPC to tear down bridge on the node
     let client = NodeClient::new(&host.address, host.port as u16);
     client
-        .detach_network(&bridge_name, &network.subnet)
+        .detach_network(&bridge_name, &network.subnet, network.subnet6.as_deref())
         .await
         .map_err(|e| {
             tracing::error!(
@@ -234,6 +296,9 @@ pub async fn detach_host(
     // Remove the DB record
     networks::detach_host(env.pool(), network_id, host_id).await?;
     network_policy::sync_cluster_vpc_state(&env, &network, &[host_id]).await?;
+    if network.is_stretched() {
+        network_policy::sync_stretched_network(env.pool(), &network).await?;
+    }
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -260,74 +325,3 @@ pub async fn list_ips(
         code: StatusCode::OK,
     })
 }
-
-/// Compute a default gateway from a CIDR (first usable address).
-fn default_gateway(subnet: &str) -> String {
-    if let Some((base, _prefix)) = subnet.split_once('/') {
-        let octets: Vec<u8> = base.split('.').filter_map(|o| o.parse().ok()).collect();
-        if octets.len() == 4 {
-            return format!(
-                "{}.{}.{}.{}",
-                octets[0],
-                octets[1],
-                octets[2],
-                octets[3] + 1
-            );
-        }
-    }
-    "10.0.0.1".to_string()
-}
-
-/// Compute DHCP range: start at the first usable host after the gateway, end at broadcast-1.
-/// The start must align with `next_available_ip` (which also starts at network_addr+1 and
-/// skips the gateway) so the API-allocated IP matches what the DHCP server actually hands out.
-fn compute_dhcp_range(subnet: &str, gateway: Option<&str>) -> (String, String) {
-    if let Some((base, prefix_str)) = subnet.split_once('/') {
-        let octets: Vec<u8> = base.split('.').filter_map(|o| o.parse().ok()).collect();
-        let prefix_len: u32 = prefix_str.parse().unwrap_or(24);
-        if octets.len() == 4 {
-            let base_u32 = (octets[0] as u32) << 24
-                | (octets[1] as u32) << 16
-                | (octets[2] as u32) << 8
-                | octets[3] as u32;
-            let host_bits = 32 - prefix_len;
-            let network_addr = base_u32 & (u32::MAX << host_bits);
-            let broadcast_addr = network_addr | ((1u32 << host_bits) - 1);
-
-            // Parse the gateway IP so we can skip it when selecting the range start.
-            let gw_u32: Option<u32> = gateway.and_then(|gw| {
-                let parts: Vec<u8> = gw.split('.').filter_map(|o| o.parse().ok()).collect();
-                if parts.len() == 4 {
-                    Some(
-                        (parts[0] as u32) << 24
-                            | (parts[1] as u32) << 16
-                            | (parts[2] as u32) << 8
-                            | parts[3] as u32,
-                    )
-                } else {
-                    None
-                }
-            });
-
-            // Start at network_addr+1, skip the gateway — mirrors next_available_ip logic.
-            let mut start = network_addr + 1;
-            if gw_u32 == Some(start) {
-                start += 1;
-            }
-            let end = broadcast_addr - 1; // .254 for /24
-
-            let fmt = |addr: u32| {
-                format!(
-                    "{}.{}.{}.{}",
-                    (addr >> 24) & 0xFF,
-                    (addr >> 16) & 0xFF,
-                    (addr >> 8) & 0xFF,
-                    addr & 0xFF
-                )
-            };
-
-            return (fmt(start), fmt(end));
-        }
-    }
-    ("10.0.0.10".to_string(), "10.0.0.254".to_string())
-}
@@ -0,0 +1,129 @@
+use super::*;
+use crate::{
+    App,
+    model::port_forwards::{self, NewPortForward, PortForward},
+    network_policy,
+};
+use axum::{Extension, Json, extract::Path};
+use http::StatusCode;
+use serde::Deserialize;
+use tracing::instrument;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PortForwardListQuery {
+    /// Only list forwards to this VM
+    pub vm_id: Option<Uuid>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/port-forwards",
+    params(PortForwardListQuery),
+    responses(
+        (status = 200, description = "List port forwards", body = Vec<PortForward>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "networks"
+)]
+#[instrument(skip(env))]
+pub async fn list(
+    Extension(env): Extension<App>,
+    axum::extract::Query(query): axum::extract::Query<PortForwardListQuery>,
+) -> Result<ApiResponse<Vec<PortForward>>> {
+    let forwards = port_forwards::list(env.pool(), query.vm_id).await?;
+    Ok(ApiResponse {
+        data: forwards,
+        code: StatusCode::OK,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/port-forwards/{port_forward_id}",
+    params(
+        ("port_forward_id" = uuid::Uuid, Path, description = "Port forward unique identifier")
+    ),
+    responses(
+        (status = 200, description = "Port forward found", body = PortForward),
+        (status = 404, description = "Port forward not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "networks"
+)]
+#[instrument(skip(env))]
+pub async fn get(
+    Extension(env): Extension<App>,
+    Path(port_forward_id): Path<Uuid>,
+) -> Result<ApiResponse<PortForward>> {
+    let forward = port_forwards::get(env.pool(), port_forward_id).await?;
+    Ok(ApiResponse {
+        data: forward,
+        code: StatusCode::OK,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/port-forwards",
+    request_body = NewPortForward,
+    responses(
+        (status = 201, description = "Port forward created successfully", body = String),
+        (status = 404, description = "VM not found"),
+        (status = 409, description = "Host port already forwarded"),
+        (status = 422, description = "Invalid input, or the node rejected the change"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "networks"
+)]
+#[instrument(skip(env))]
+pub async fn create(
+    Extension(env): Extension<App>,
+    Json(new_forward): Json<NewPortForward>,
+) -> Result<(StatusCode, String)> {
+    let vm_port = new_forward.vm_port.unwrap_or(new_forward.host_port);
+    for port in [new_forward.host_port, vm_port] {
+        if !(1..=65535).contains(&port) {
+            return Err(crate::errors::Error::UnprocessableEntity(format!(
+                "port {port} is outside 1-65535"
+            )));
+        }
+    }
+    crate::model::vms::get(env.pool(), new_forward.vm_id).await?;
+    let nic = network_policy::nat_target_interface(
+        env.pool(),
+        new_forward.vm_id,
+        new_forward.network_interface_id,
+    )
+    .await?;
+
+    let id = port_forwards::create(env.pool(), nic.id, &new_forward, vm_port).await?;
+    network_policy::sync_vm_nat(env.pool(), new_forward.vm_id).await?;
+    Ok((StatusCode::CREATED, id.to_string()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/port-forwards/{port_forward_id}",
+    params(
+        ("port_forward_id" = uuid::Uuid, Path, description = "Port forward unique identifier")
+    ),
+    responses(
+        (status = 204, description = "Port forward deleted successfully"),
+        (status = 404, description = "Port forward not found"),
+        (status = 422, description = "The node rejected the change"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "networks"
+)]
+#[instrument(skip(env))]
+pub async fn delete(
+    Extension(env): Extension<App>,
+    Path(port_forward_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let forward = port_forwards::get(env.pool(), port_forward_id).await?;
+    port_forwards::delete(env.pool(), port_forward_id).await?;
+    network_policy::sync_vm_nat(env.pool(), forward.vm_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
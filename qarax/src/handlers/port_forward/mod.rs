@@ -0,0 +1,3 @@
+use super::{ApiResponse, Result};
+
+pub mod handler;
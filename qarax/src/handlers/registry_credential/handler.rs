@@ -0,0 +1,167 @@
+use super::*;
+use crate::{
+    App,
+    handlers::audit::{AuditEvent, AuditEventExt},
+    model::{
+        audit_log::{AuditAction, AuditResourceType},
+        registry_credentials::{
+            self, NewRegistryCredential, RegistryCredential, UpdateRegistryCredential,
+        },
+    },
+};
+use axum::{Extension, Json, extract::Path};
+use http::StatusCode;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Audit metadata for a credential. Only identifies the login; the password
+/// never reaches the audit log.
+fn audit_metadata(credential: &RegistryCredential) -> serde_json::Value {
+    serde_json::json!({
+        "registry": credential.registry,
+        "username": credential.username,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/registry-credentials",
+    params(crate::handlers::NameQuery),
+    responses(
+        (status = 200, description = "List registry credentials (passwords are never returned)", body = Vec<RegistryCredential>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "registry-credentials"
+)]
+#[instrument(skip(env))]
+pub async fn list(
+    Extension(env): Extension<App>,
+    axum::extract::Query(query): axum::extract::Query<crate::handlers::NameQuery>,
+) -> Result<ApiResponse<Vec<RegistryCredential>>> {
+    let credentials = registry_credentials::list(env.pool(), query.name.as_deref()).await?;
+    Ok(ApiResponse {
+        data: credentials,
+        code: StatusCode::OK,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/registry-credentials/{credential_id}",
+    params(
+        ("credential_id" = uuid::Uuid, Path, description = "Registry credential unique identifier")
+    ),
+    responses(
+        (status = 200, description = "Registry credential found", body = RegistryCredential),
+        (status = 404, description = "Registry credential not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "registry-credentials"
+)]
+#[instrument(skip(env))]
+pub async fn get(
+    Extension(env): Extension<App>,
+    Path(credential_id): Path<Uuid>,
+) -> Result<ApiResponse<RegistryCredential>> {
+    let credential = registry_credentials::get(env.pool(), credential_id).await?;
+    Ok(ApiResponse {
+        data: credential,
+        code: StatusCode::OK,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/registry-credentials",
+    request_body = NewRegistryCredential,
+    responses(
+        (status = 201, description = "Registry credential created successfully", body = String),
+        (status = 409, description = "A credential with this name or registry already exists"),
+        (status = 422, description = "Invalid input or no encryption key configured"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "registry-credentials"
+)]
+#[instrument(skip(env, new_credential))]
+pub async fn create(
+    Extension(env): Extension<App>,
+    Json(new_credential): Json<NewRegistryCredential>,
+) -> Result<axum::response::Response> {
+    let id = registry_credentials::create(env.pool(), &new_credential).await?;
+    let credential = registry_credentials::get(env.pool(), id).await?;
+    Ok(
+        (StatusCode::CREATED, id.to_string()).with_audit_event(AuditEvent {
+            action: AuditAction::Create,
+            resource_type: AuditResourceType::RegistryCredential,
+            resource_id: id,
+            resource_name: Some(credential.name.clone()),
+            metadata: Some(audit_metadata(&credential)),
+        }),
+    )
+}
+
+#[utoipa::path(
+    patch,
+    path = "/registry-credentials/{credential_id}",
+    params(
+        ("credential_id" = uuid::Uuid, Path, description = "Registry credential unique identifier")
+    ),
+    request_body = UpdateRegistryCredential,
+    responses(
+        (status = 200, description = "Registry credential updated", body = RegistryCredential),
+        (status = 404, description = "Registry credential not found"),
+        (status = 422, description = "Invalid input or no encryption key configured"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "registry-credentials"
+)]
+#[instrument(skip(env, update))]
+pub async fn update(
+    Extension(env): Extension<App>,
+    Path(credential_id): Path<Uuid>,
+    Json(update): Json<UpdateRegistryCredential>,
+) -> Result<axum::response::Response> {
+    let credential = registry_credentials::update(env.pool(), credential_id, &update).await?;
+    let mut metadata = audit_metadata(&credential);
+    metadata["password_rotated"] = serde_json::Value::Bool(update.password.is_some());
+    Ok(ApiResponse {
+        data: credential.clone(),
+        code: StatusCode::OK,
+    }
+    .with_audit_event(AuditEvent {
+        action: AuditAction::Update,
+        resource_type: AuditResourceType::RegistryCredential,
+        resource_id: credential_id,
+        resource_name: Some(credential.name),
+        metadata: Some(metadata),
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/registry-credentials/{credential_id}",
+    params(
+        ("credential_id" = uuid::Uuid, Path, description = "Registry credential unique identifier")
+    ),
+    responses(
+        (status = 204, description = "Registry credential deleted successfully"),
+        (status = 404, description = "Registry credential not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "registry-credentials"
+)]
+#[instrument(skip(env))]
+pub async fn delete(
+    Extension(env): Extension<App>,
+    Path(credential_id): Path<Uuid>,
+) -> Result<axum::response::Response> {
+    let credential = registry_credentials::get(env.pool(), credential_id).await?;
+    registry_credentials::delete(env.pool(), credential_id).await?;
+    Ok(StatusCode::NO_CONTENT.with_audit_event(AuditEvent {
+        action: AuditAction::Delete,
+        resource_type: AuditResourceType::RegistryCredential,
+        resource_id: credential_id,
+        resource_name: Some(credential.name.clone()),
+        metadata: Some(audit_metadata(&credential)),
+    }))
+}
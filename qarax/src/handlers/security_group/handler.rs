@@ -6,6 +6,7 @@ use crate::{
         audit_log::{AuditAction, AuditResourceType},
         security_groups::{
             self, NewSecurityGroup, NewSecurityGroupRule, SecurityGroup, SecurityGroupRule,
+            SecurityGroupStats,
         },
     },
     network_policy,
@@ -100,6 +101,7 @@ pub async fn create(
     responses(
         (status = 204, description = "Security group deleted successfully"),
         (status = 404, description = "Security group not found"),
+        (status = 409, description = "Security group is the peer of another group's rules"),
         (status = 500, description = "Internal server error")
     ),
     tag = "security-groups"
@@ -110,6 +112,15 @@ pub async fn delete(
     Path(security_group_id): Path<Uuid>,
 ) -> Result<axum::response::Response> {
     let group = security_groups::get(env.pool(), security_group_id).await?;
+    let referencing =
+        security_groups::list_referencing_group_names(env.pool(), security_group_id).await?;
+    if !referencing.is_empty() {
+        return Err(crate::errors::Error::Conflict(format!(
+            "Security group {} is used as a peer by rules in: {}",
+            group.name,
+            referencing.join(", ")
+        )));
+    }
     let vm_ids = security_groups::list_vm_ids(env.pool(), security_group_id).await?;
     security_groups::delete(env.pool(), security_group_id).await?;
     for vm_id in vm_ids {
@@ -150,6 +161,32 @@ pub async fn list_rules(
     })
 }
 
+#[utoipa::path(
+    get,
+    path = "/security-groups/{security_group_id}/stats",
+    params(
+        ("security_group_id" = uuid::Uuid, Path, description = "Security group unique identifier")
+    ),
+    responses(
+        (status = 200, description = "Packet counters for the group's rules and members", body = SecurityGroupStats),
+        (status = 404, description = "Security group not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "security-groups"
+)]
+#[instrument(skip(env))]
+pub async fn stats(
+    Extension(env): Extension<App>,
+    Path(security_group_id): Path<Uuid>,
+) -> Result<ApiResponse<SecurityGroupStats>> {
+    security_groups::get(env.pool(), security_group_id).await?;
+    let stats = network_policy::security_group_stats(&env, security_group_id).await?;
+    Ok(ApiResponse {
+        data: stats,
+        code: StatusCode::OK,
+    })
+}
+
 #[utoipa::path(
     post,
     path = "/security-groups/{security_group_id}/rules",
@@ -159,7 +196,7 @@ pub async fn list_rules(
     request_body = NewSecurityGroupRule,
     responses(
         (status = 201, description = "Security-group rule created successfully", body = String),
-        (status = 404, description = "Security group not found"),
+        (status = 404, description = "Security group or peer security group not found"),
         (status = 422, description = "Invalid input"),
         (status = 500, description = "Internal server error")
     ),
@@ -172,6 +209,14 @@ pub async fn create_rule(
     Json(rule): Json<NewSecurityGroupRule>,
 ) -> Result<(StatusCode, String)> {
     security_groups::get(env.pool(), security_group_id).await?;
+    if let Some(remote_security_group_id) = rule.remote_security_group_id {
+        if rule.cidr.is_some() {
+            return Err(crate::errors::Error::UnprocessableEntity(
+                "cidr and remote_security_group_id are mutually exclusive".into(),
+            ));
+        }
+        security_groups::get(env.pool(), remote_security_group_id).await?;
+    }
     let id = security_groups::create_rule(env.pool(), security_group_id, rule).await?;
     network_policy::sync_security_group_members(&env, security_group_id).await?;
     Ok((StatusCode::CREATED, id.to_string()))
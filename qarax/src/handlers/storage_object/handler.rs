@@ -1,7 +1,14 @@
 use super::*;
 use crate::{
     App,
-    model::storage_objects::{self, NewStorageObject, StorageObject},
+    grpc_client::{NodeClient, node::StorageImageOperation},
+    handlers::{
+        storage_pool::handler::require_up_host_for_pool, vm::handler::release_frozen_layers,
+    },
+    model::{
+        storage_objects::{self, NewStorageObject, StorageObject, StorageObjectType},
+        storage_pools::{self, StoragePool, StoragePoolType},
+    },
 };
 use axum::{Extension, Json, extract::Path};
 use http::StatusCode;
@@ -90,6 +97,8 @@ pub async fn create(
     responses(
         (status = 204, description = "Storage object deleted successfully"),
         (status = 404, description = "Storage object not found"),
+        (status = 409, description = "RBD image or OverlayBD upper layer is still referenced"),
+        (status = 422, description = "RBD image could not be removed, or no UP host is attached to the pool"),
         (status = 500, description = "Internal server error")
     ),
     tag = "storage-objects"
@@ -99,6 +108,63 @@ pub async fn delete(
     Extension(env): Extension<App>,
     Path(object_id): Path<Uuid>,
 ) -> Result<StatusCode> {
+    let object = storage_objects::get(env.pool(), object_id).await?;
+    let pool = storage_pools::get(env.pool(), object.storage_pool_id).await?;
+    if pool.pool_type == StoragePoolType::Rbd {
+        remove_rbd_image(&env, &pool, &object).await?;
+    } else if object.object_type == StorageObjectType::OverlaybdUpper {
+        remove_overlaybd_upper(&env, &object).await?;
+    }
     storage_objects::delete(env.pool(), object_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// A persistent OverlayBD upper outlives its VM, so deleting the object is
+/// what frees its files and any frozen layers nothing else stacks on.
+async fn remove_overlaybd_upper(env: &App, object: &StorageObject) -> Result<()> {
+    let Some(config) = storage_objects::OverlaybdUpperConfig::from_value(&object.config) else {
+        return Ok(());
+    };
+    let references = storage_objects::count_references(env.pool(), object.id).await?;
+    if references > 0 {
+        return Err(crate::errors::Error::Conflict(format!(
+            "storage object {} is still referenced by {} other resource(s)",
+            object.id, references
+        )));
+    }
+    let host = require_up_host_for_pool(env, object.storage_pool_id).await?;
+    let node_client = NodeClient::new(&host.address, host.port as u16);
+    for path in [&config.upper_data, &config.upper_index] {
+        node_client.delete_path(path, false).await.map_err(|e| {
+            tracing::error!(path = %path, "Failed to remove OverlayBD upper layer: {:#}", e);
+            crate::errors::Error::InternalServerError
+        })?;
+    }
+    release_frozen_layers(env, &node_client, object.id, &config.frozen_layers).await
+}
+
+/// RBD images live in the Ceph pool rather than on a node's filesystem, so
+/// they are removed through an attached node before the row goes away.
+async fn remove_rbd_image(env: &App, pool: &StoragePool, object: &StorageObject) -> Result<()> {
+    let references = storage_objects::count_references(env.pool(), object.id).await?;
+    if references > 0 {
+        return Err(crate::errors::Error::Conflict(format!(
+            "storage object {} is still referenced by {} other resource(s)",
+            object.id, references
+        )));
+    }
+    let host = require_up_host_for_pool(env, pool.id).await?;
+    NodeClient::new(&host.address, host.port as u16)
+        .manage_storage_image(
+            pool,
+            StorageImageOperation::Remove,
+            &object.config,
+            0,
+            "",
+            "",
+        )
+        .await
+        .map_err(|e| {
+            crate::errors::Error::UnprocessableEntity(format!("Failed to remove RBD image: {e}"))
+        })
+}
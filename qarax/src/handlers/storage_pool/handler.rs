@@ -1,15 +1,20 @@
 use super::*;
 use crate::{
     App,
-    grpc_client::NodeClient,
+    grpc_client::{
+        DownloadOptions, DownloadResult, NodeClient,
+        node::{DiscoveredLun, StorageImageOperation},
+    },
     model::{
         hosts,
         jobs::{self, JobType, NewJob},
+        registry_credentials,
         storage_objects::{self, NewStorageObject, StorageObjectType},
-        storage_pools::{self, NewStoragePool, StoragePool},
+        storage_pools::{self, BlockPoolConfig, HostPathHealth, NewStoragePool, StoragePool},
     },
 };
 use axum::{Extension, Json, extract::Path};
+use common::checksum::Checksum;
 use http::StatusCode;
 use serde::{Deserialize, Serialize};
 use tracing::{instrument, warn};
@@ -56,7 +61,8 @@ pub async fn get(
     Extension(env): Extension<App>,
     Path(pool_id): Path<Uuid>,
 ) -> Result<ApiResponse<StoragePool>> {
-    let pool = storage_pools::get(env.pool(), pool_id).await?;
+    let mut pool = storage_pools::get(env.pool(), pool_id).await?;
+    pool.host_capacity = storage_pools::list_capacity(env.pool(), pool_id).await?;
     Ok(ApiResponse {
         data: pool,
         code: StatusCode::OK,
@@ -74,14 +80,39 @@ pub async fn get(
     ),
     tag = "storage-pools"
 )]
-#[instrument(skip(env))]
+#[instrument(skip(env, new_pool), fields(name = %new_pool.name, pool_type = %new_pool.pool_type))]
 pub async fn create(
     Extension(env): Extension<App>,
-    Json(new_pool): Json<NewStoragePool>,
+    Json(mut new_pool): Json<NewStoragePool>,
 ) -> Result<(StatusCode, String)> {
+    if new_pool.pool_type == storage_pools::StoragePoolType::Rbd
+        && storage_pools::RbdPoolConfig::from_value(&new_pool.config).is_none()
+    {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "RBD pools require a config with a 'pool' name".into(),
+        ));
+    }
+
+    if new_pool.pool_type == storage_pools::StoragePoolType::Block {
+        let mut block = BlockPoolConfig::from_value(&new_pool.config).ok_or_else(|| {
+            crate::errors::Error::UnprocessableEntity(
+                "BLOCK pools require a config with 'portal' or 'portals' and 'iqn'".into(),
+            )
+        })?;
+        block
+            .validate()
+            .map_err(crate::errors::Error::UnprocessableEntity)?;
+        // CHAP secrets are only ever stored encrypted.
+        block.seal_secrets()?;
+        new_pool.config = serde_json::to_value(&block).map_err(|e| {
+            tracing::error!("Failed to serialize BLOCK pool config: {}", e);
+            crate::errors::Error::InternalServerError
+        })?;
+    }
+
     let id = storage_pools::create(env.pool(), new_pool.clone()).await?;
 
-    // For shared pool types (NFS, OverlayBD), auto-attach every UP host.
+    // For shared pool types (NFS, OverlayBD, Block, RBD), auto-attach every UP host.
     // Local pools are host-specific and must be attached explicitly.
     if new_pool.pool_type.is_shared() {
         let db_pool = env.pool_arc();
@@ -105,9 +136,15 @@ pub async fn create(
             for host in up_hosts {
                 let client = NodeClient::new(&host.address, host.port as u16);
                 match client.attach_storage_pool(&pool).await {
-                    Ok(()) => {
+                    Ok(capacity) => {
                         if let Err(e) = storage_pools::attach_host(&db_pool, id, host.id).await {
                             warn!(pool_id = %id, host_id = %host.id, error = %e, "Failed to record pool attachment in DB");
+                        } else if let Some(capacity) = capacity
+                            && let Err(e) =
+                                storage_pools::record_capacity(&db_pool, id, host.id, &capacity)
+                                    .await
+                        {
+                            warn!(pool_id = %id, host_id = %host.id, error = %e, "Failed to record pool capacity");
                         }
                     }
                     Err(e) => {
@@ -143,6 +180,32 @@ pub async fn delete(
     Ok(StatusCode::NO_CONTENT)
 }
 
+#[utoipa::path(
+    get,
+    path = "/storage-pools/{pool_id}/paths",
+    params(
+        ("pool_id" = uuid::Uuid, Path, description = "Storage pool unique identifier")
+    ),
+    responses(
+        (status = 200, description = "Last reported path health per attached host", body = Vec<HostPathHealth>),
+        (status = 404, description = "Storage pool not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "storage-pools"
+)]
+#[instrument(skip(env))]
+pub async fn list_paths(
+    Extension(env): Extension<App>,
+    Path(pool_id): Path<Uuid>,
+) -> Result<ApiResponse<Vec<HostPathHealth>>> {
+    storage_pools::get(env.pool(), pool_id).await?;
+    let paths = storage_pools::list_path_health(env.pool(), pool_id).await?;
+    Ok(ApiResponse {
+        data: paths,
+        code: StatusCode::OK,
+    })
+}
+
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct AttachPoolHostRequest {
     pub host_id: Uuid,
@@ -173,7 +236,7 @@ pub async fn attach_host(
 
     // Perform the real attachment on the node first.
     let client = NodeClient::new(&host.address, host.port as u16);
-    client.attach_storage_pool(&pool).await.map_err(|e| {
+    let capacity = client.attach_storage_pool(&pool).await.map_err(|e| {
         tracing::error!(
             pool_id = %pool_id,
             host_id = %body.host_id,
@@ -185,6 +248,9 @@ pub async fn attach_host(
 
     // Record the attachment in the DB.
     storage_pools::attach_host(env.pool(), pool_id, body.host_id).await?;
+    if let Some(capacity) = capacity {
+        storage_pools::record_capacity(env.pool(), pool_id, body.host_id, &capacity).await?;
+    }
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -252,9 +318,20 @@ pub struct CreateDiskRequest {
     /// Optional URL to populate the disk from (e.g. a cloud image). When set the
     /// operation becomes async and returns 202 with a job_id.
     pub source_url: Option<String>,
+    /// Expected digest of the downloaded image as `sha256:<hex>` or `sha512:<hex>`.
+    /// Only valid together with source_url.
+    #[serde(default)]
+    pub expected_checksum: Option<String>,
+    /// Cap on the download rate in bytes per second. Only valid together with source_url.
+    #[serde(default)]
+    pub bandwidth_limit_bytes_per_sec: Option<i64>,
     /// If true, use fallocate to reserve blocks upfront (default: sparse).
     #[serde(default)]
     pub preallocate: bool,
+    /// RBD disk in the same pool to clone. The clone is a copy-on-write child
+    /// of a snapshot of that disk and has its size.
+    #[serde(default)]
+    pub source_storage_object_id: Option<Uuid>,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -294,10 +371,37 @@ pub async fn create_disk(
         name,
         size_bytes,
         source_url,
+        expected_checksum,
+        bandwidth_limit_bytes_per_sec,
         preallocate,
+        source_storage_object_id,
     } = req;
 
+    if source_url.is_some() && source_storage_object_id.is_some() {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "source_url and source_storage_object_id are mutually exclusive".into(),
+        ));
+    }
     let is_blank_disk = source_url.is_none();
+    if is_blank_disk && (expected_checksum.is_some() || bandwidth_limit_bytes_per_sec.is_some()) {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "expected_checksum and bandwidth_limit_bytes_per_sec require source_url".into(),
+        ));
+    }
+    let expected_checksum = expected_checksum
+        .map(|c| Checksum::parse(&c).map(|c| c.to_string()))
+        .transpose()
+        .map_err(crate::errors::Error::UnprocessableEntity)?;
+    if bandwidth_limit_bytes_per_sec.is_some_and(|limit| limit <= 0) {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "bandwidth_limit_bytes_per_sec must be greater than 0".into(),
+        ));
+    }
+    if source_storage_object_id.is_some() && size_bytes.is_some() {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "a clone has the size of its source; omit size_bytes".into(),
+        ));
+    }
     let size_bytes = match (size_bytes, source_url.as_ref()) {
         (Some(size_bytes), _) if size_bytes <= 0 => {
             return Err(crate::errors::Error::UnprocessableEntity(
@@ -306,6 +410,7 @@ pub async fn create_disk(
         }
         (Some(size_bytes), _) => size_bytes,
         (None, Some(_)) => 0,
+        (None, None) if source_storage_object_id.is_some() => 0,
         (None, None) => {
             return Err(crate::errors::Error::UnprocessableEntity(
                 "size_bytes is required when source_url is not provided".into(),
@@ -315,36 +420,44 @@ pub async fn create_disk(
 
     let pool = storage_pools::get(env.pool(), pool_id).await?;
 
-    if !matches!(
-        pool.pool_type,
+    match pool.pool_type {
         storage_pools::StoragePoolType::Local | storage_pools::StoragePoolType::Nfs
-    ) {
-        return Err(crate::errors::Error::UnprocessableEntity(
-            "Disks can only be created in Local or NFS pools".into(),
-        ));
+            if source_storage_object_id.is_some() =>
+        {
+            return Err(crate::errors::Error::UnprocessableEntity(
+                "source_storage_object_id is only supported for RBD pools".into(),
+            ));
+        }
+        storage_pools::StoragePoolType::Local | storage_pools::StoragePoolType::Nfs => {}
+        storage_pools::StoragePoolType::Rbd if is_blank_disk => {}
+        storage_pools::StoragePoolType::Rbd => {
+            return Err(crate::errors::Error::UnprocessableEntity(
+                "RBD pools only support blank disks; source_url is not supported".into(),
+            ));
+        }
+        _ => {
+            return Err(crate::errors::Error::UnprocessableEntity(
+                "Disks can only be created in Local, NFS or RBD pools".into(),
+            ));
+        }
     }
 
-    if pool.status != storage_pools::StoragePoolStatus::Active {
+    if !pool.status.is_usable() {
         return Err(crate::errors::Error::UnprocessableEntity(
             "Storage pool is not active".into(),
         ));
     }
 
-    // Capacity check for blank disks (source_url size is unknown until download).
-    if is_blank_disk
-        && let (Some(capacity), Some(allocated)) = (pool.capacity_bytes, pool.allocated_bytes)
-    {
-        let available = capacity - allocated;
-        if size_bytes > available {
-            return Err(crate::errors::Error::UnprocessableEntity(format!(
-                "Insufficient pool capacity: requested {} bytes but only {} available",
-                size_bytes, available
-            )));
+    let host = require_up_host_for_pool(&env, pool_id).await?;
+    admit_pool_allocation(env.pool(), &pool, &host, size_bytes).await?;
+
+    if pool.pool_type == storage_pools::StoragePoolType::Rbd {
+        if let Some(source_id) = source_storage_object_id {
+            return clone_rbd_disk(&env, &pool, &host, name, source_id).await;
         }
+        return create_rbd_disk(&env, &pool, &host, name, size_bytes).await;
     }
 
-    let host = require_up_host_for_pool(&env, pool_id).await?;
-
     // Create the StorageObject record — path is auto-derived from pool config.
     let storage_object_id = storage_objects::create(
         env.pool(),
@@ -388,6 +501,10 @@ pub async fn create_disk(
         let job_id = job.id;
 
         let db_pool = env.pool_arc();
+        let options = DownloadOptions {
+            expected_checksum,
+            bandwidth_limit_bytes_per_sec,
+        };
 
         tokio::spawn(async move {
             if let Err(e) = jobs::mark_running(&db_pool, job_id).await {
@@ -396,10 +513,13 @@ pub async fn create_disk(
             }
 
             match node_client
-                .create_disk(&dest_path, size_bytes, Some(&source_url), preallocate)
+                .create_disk_from_url(&dest_path, size_bytes, &source_url, &options)
                 .await
             {
-                Ok(bytes_written) => {
+                Ok(DownloadResult {
+                    bytes_written,
+                    checksum,
+                }) => {
                     let _ = storage_objects::update_size_bytes(
                         &db_pool,
                         storage_object_id,
@@ -409,7 +529,11 @@ pub async fn create_disk(
                     let _ = jobs::mark_completed(
                         &db_pool,
                         job_id,
-                        Some(serde_json::json!({ "storage_object_id": storage_object_id, "bytes_written": bytes_written })),
+                        Some(serde_json::json!({
+                            "storage_object_id": storage_object_id,
+                            "bytes_written": bytes_written,
+                            "checksum": checksum,
+                        })),
                     )
                     .await;
                 }
@@ -433,7 +557,7 @@ pub async fn create_disk(
     } else {
         // Sync path: creating a blank disk is fast.
         match node_client
-            .create_disk(&dest_path, size_bytes, None, preallocate)
+            .create_disk(&dest_path, size_bytes, preallocate)
             .await
         {
             Ok(_) => Ok(ApiResponse {
@@ -454,6 +578,141 @@ pub async fn create_disk(
     }
 }
 
+/// Create a blank image in an RBD pool through an attached node. The image is
+/// named after a fresh UUID so user-supplied names never reach the rbd CLI.
+async fn create_rbd_disk(
+    env: &App,
+    pool: &StoragePool,
+    host: &hosts::Host,
+    name: String,
+    size_bytes: i64,
+) -> Result<axum::response::Response> {
+    use axum::response::IntoResponse as _;
+
+    let image = format!("qarax-{}", Uuid::new_v4());
+    let storage_object_id = storage_objects::create(
+        env.pool(),
+        NewStorageObject {
+            name,
+            storage_pool_id: Some(pool.id),
+            object_type: StorageObjectType::Disk,
+            size_bytes,
+            config: serde_json::json!({ "image": image }),
+            parent_id: None,
+        },
+    )
+    .await?;
+    let so = storage_objects::get(env.pool(), storage_object_id).await?;
+
+    if let Err(e) = NodeClient::new(&host.address, host.port as u16)
+        .manage_storage_image(
+            pool,
+            StorageImageOperation::Create,
+            &so.config,
+            size_bytes,
+            "",
+            "",
+        )
+        .await
+    {
+        let _ = storage_objects::delete(env.pool(), storage_object_id).await;
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "Failed to create RBD image: {e}"
+        )));
+    }
+
+    Ok(ApiResponse {
+        data: CreateDiskResponse {
+            storage_object_id,
+            job_id: None,
+        },
+        code: StatusCode::CREATED,
+    }
+    .into_response())
+}
+
+/// Clone an RBD disk within its pool: snapshot the source under the new
+/// image's name, then create a copy-on-write child of that snapshot. The clone
+/// records the source as its parent, so the source cannot be deleted first.
+async fn clone_rbd_disk(
+    env: &App,
+    pool: &StoragePool,
+    host: &hosts::Host,
+    name: String,
+    source_id: Uuid,
+) -> Result<axum::response::Response> {
+    use axum::response::IntoResponse as _;
+
+    let source = storage_objects::get(env.pool(), source_id).await?;
+    let source_image = source
+        .config
+        .get("image")
+        .and_then(|v| v.as_str())
+        .filter(|_| source.storage_pool_id == pool.id)
+        .filter(|_| source.object_type == StorageObjectType::Disk)
+        .ok_or_else(|| {
+            crate::errors::Error::UnprocessableEntity(
+                "source_storage_object_id must be an RBD disk in this pool".into(),
+            )
+        })?
+        .to_string();
+
+    let image = format!("qarax-{}", Uuid::new_v4());
+    let storage_object_id = storage_objects::create(
+        env.pool(),
+        NewStorageObject {
+            name,
+            storage_pool_id: Some(pool.id),
+            object_type: StorageObjectType::Disk,
+            size_bytes: source.size_bytes,
+            config: serde_json::json!({ "image": image }),
+            parent_id: Some(source.id),
+        },
+    )
+    .await?;
+    let so = storage_objects::get(env.pool(), storage_object_id).await?;
+
+    let client = NodeClient::new(&host.address, host.port as u16);
+    let cloned = async {
+        client
+            .manage_storage_image(
+                pool,
+                StorageImageOperation::Snapshot,
+                &source.config,
+                0,
+                &image,
+                "",
+            )
+            .await?;
+        client
+            .manage_storage_image(
+                pool,
+                StorageImageOperation::Clone,
+                &so.config,
+                0,
+                &image,
+                &source_image,
+            )
+            .await
+    }
+    .await;
+    if let Err(e) = cloned {
+        let _ = storage_objects::delete(env.pool(), storage_object_id).await;
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "Failed to clone RBD image: {e}"
+        )));
+    }
+
+    Ok(ApiResponse {
+        data: CreateDiskResponse {
+            storage_object_id,
+            job_id: None,
+        },
+        code: StatusCode::CREATED,
+    }
+    .into_response())
+}
+
 /// Import an OCI image into the pool, converting it to OverlayBD format.
 #[utoipa::path(
     post,
@@ -532,8 +791,21 @@ pub async fn import_to_pool(
                 }
             };
 
+        let logins =
+            match registry_credentials::logins_for_image(&db_pool, &image_ref, Some(&registry_url))
+                .await
+            {
+                Ok(logins) => logins,
+                Err(e) => {
+                    let msg = format!("Failed to load registry credentials: {}", e);
+                    tracing::error!(pool_id = %pool_id, error = %msg);
+                    let _ = jobs::mark_failed(&db_pool, job_id, &msg).await;
+                    return;
+                }
+            };
+
         match node_client
-            .import_overlaybd_image(&image_ref, &registry_url)
+            .import_overlaybd_image(&image_ref, &registry_url, &logins)
             .await
         {
             Ok(result) => {
@@ -572,6 +844,322 @@ pub async fn import_to_pool(
     .into_response())
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PrewarmRequest {
+    /// Converted image reference in the pool registry, as stored on the
+    /// image's storage object after import.
+    pub image_ref: String,
+    /// Hosts to pre-warm. Defaults to every UP host attached to the pool.
+    #[serde(default)]
+    pub host_ids: Option<Vec<Uuid>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PrewarmResponse {
+    pub job_id: Uuid,
+    pub host_ids: Vec<Uuid>,
+}
+
+/// Download every layer of an OverlayBD image into the layer cache of the
+/// selected hosts, so VMs started from it there do not fetch blocks lazily.
+#[utoipa::path(
+    post,
+    path = "/storage-pools/{pool_id}/prewarm",
+    params(
+        ("pool_id" = Uuid, Path, description = "Storage pool ID")
+    ),
+    request_body = PrewarmRequest,
+    responses(
+        (status = 202, description = "Pre-warm job accepted", body = PrewarmResponse),
+        (status = 404, description = "Pool or host not found"),
+        (status = 422, description = "Not an OverlayBD pool, or a host is not UP and attached to it"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "storage-pools"
+)]
+#[instrument(skip(env))]
+pub async fn prewarm(
+    Extension(env): Extension<App>,
+    Path(pool_id): Path<Uuid>,
+    Json(req): Json<PrewarmRequest>,
+) -> Result<axum::response::Response> {
+    use axum::response::IntoResponse as _;
+
+    let pool = storage_pools::get(env.pool(), pool_id).await?;
+    if pool.pool_type != storage_pools::StoragePoolType::OverlayBd {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "Pre-warming is only valid for OVERLAYBD pools".into(),
+        ));
+    }
+    let registry_url = crate::model::storage_pools::OverlayBdPoolConfig::from_value(&pool.config)
+        .map(|cfg| cfg.url)
+        .ok_or_else(|| {
+            crate::errors::Error::UnprocessableEntity(
+                "OverlayBD pool config missing 'url' field".into(),
+            )
+        })?;
+    if req.image_ref.trim().is_empty() {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "image_ref must not be empty".into(),
+        ));
+    }
+
+    let targets = match &req.host_ids {
+        Some(host_ids) => {
+            let mut targets = Vec::with_capacity(host_ids.len());
+            for host_id in host_ids {
+                let host = hosts::require_by_id(env.pool(), *host_id).await?;
+                if !storage_pools::host_has_pool(env.pool(), host.id, pool_id).await? {
+                    return Err(crate::errors::Error::UnprocessableEntity(format!(
+                        "host {} is not attached to storage pool {}",
+                        host.name, pool.name
+                    )));
+                }
+                if host.status != hosts::HostStatus::Up {
+                    return Err(crate::errors::Error::UnprocessableEntity(format!(
+                        "host {} must be up to pre-warm (current status: {})",
+                        host.name, host.status
+                    )));
+                }
+                targets.push(host);
+            }
+            targets
+        }
+        None => {
+            let mut targets = Vec::new();
+            for host_id in storage_pools::list_up_host_ids(env.pool(), pool_id).await? {
+                targets.push(hosts::require_by_id(env.pool(), host_id).await?);
+            }
+            targets
+        }
+    };
+    if targets.is_empty() {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "No UP host attached to this storage pool".into(),
+        ));
+    }
+
+    let job = jobs::create(
+        env.pool(),
+        NewJob {
+            job_type: JobType::ImagePrewarm,
+            description: Some(format!(
+                "Pre-warming {} on {} host(s) of pool {}",
+                req.image_ref,
+                targets.len(),
+                pool.name
+            )),
+            resource_id: Some(pool_id),
+            resource_type: Some("storage_pool".to_string()),
+        },
+    )
+    .await?;
+    let job_id = job.id;
+    let host_ids: Vec<Uuid> = targets.iter().map(|h| h.id).collect();
+
+    let db_pool = env.pool_arc();
+    let image_ref = req.image_ref.clone();
+    tokio::spawn(async move {
+        if let Err(e) = jobs::mark_running(&db_pool, job_id).await {
+            tracing::error!(job_id = %job_id, error = %e, "Failed to mark pre-warm job running");
+            return;
+        }
+
+        let total = targets.len();
+        let mut join_set = tokio::task::JoinSet::new();
+        for host in targets {
+            let image_ref = image_ref.clone();
+            let registry_url = registry_url.clone();
+            join_set.spawn(async move {
+                let result = NodeClient::new(&host.address, host.port as u16)
+                    .prewarm_overlaybd_image(&image_ref, &registry_url)
+                    .await;
+                (host, result)
+            });
+        }
+
+        let mut outcomes = Vec::with_capacity(total);
+        let mut failed = 0;
+        while let Some(joined) = join_set.join_next().await {
+            let Ok((host, result)) = joined else {
+                continue;
+            };
+            match result {
+                Ok(image) => outcomes.push(serde_json::json!({
+                    "host_id": host.id,
+                    "host_name": host.name,
+                    "layers": image.layers,
+                    "cached_layers": image.cached_layers,
+                    "cached_bytes": image.cached_bytes,
+                })),
+                Err(e) => {
+                    failed += 1;
+                    tracing::warn!(job_id = %job_id, host_id = %host.id, error = %e, "Pre-warm failed");
+                    outcomes.push(serde_json::json!({
+                        "host_id": host.id,
+                        "host_name": host.name,
+                        "error": e.to_string(),
+                    }));
+                }
+            }
+            let progress = (outcomes.len() * 100 / total) as i32;
+            let _ = jobs::update_progress(&db_pool, job_id, progress).await;
+        }
+
+        let result = serde_json::json!({ "image_ref": image_ref, "hosts": outcomes });
+        if failed > 0 {
+            let msg = format!("Pre-warming failed on {} of {} host(s)", failed, total);
+            let _ = jobs::mark_failed_with_result(&db_pool, job_id, &msg, Some(result)).await;
+        } else {
+            let _ = jobs::mark_completed(&db_pool, job_id, Some(result)).await;
+            tracing::info!(pool_id = %pool_id, job_id = %job_id, "Pre-warm job completed");
+        }
+    });
+
+    Ok(ApiResponse {
+        data: PrewarmResponse { job_id, host_ids },
+        code: StatusCode::ACCEPTED,
+    }
+    .into_response())
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ImageGcRequest {
+    /// Report what would be deleted without deleting anything.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ImageGcResponse {
+    pub job_id: Uuid,
+}
+
+/// Delete images from an OverlayBD pool's registry that no VM, VM template or
+/// storage object references. The job result lists the deleted (or, in a dry
+/// run, deletable) manifests and the bytes their unshared blobs take up.
+#[utoipa::path(
+    post,
+    path = "/storage-pools/{pool_id}/gc",
+    params(
+        ("pool_id" = Uuid, Path, description = "Storage pool ID")
+    ),
+    request_body = ImageGcRequest,
+    responses(
+        (status = 202, description = "Image GC job accepted", body = ImageGcResponse),
+        (status = 404, description = "Pool not found"),
+        (status = 422, description = "Not an OverlayBD pool, or no UP host attached to it"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "storage-pools"
+)]
+#[instrument(skip(env))]
+pub async fn gc_images(
+    Extension(env): Extension<App>,
+    Path(pool_id): Path<Uuid>,
+    Json(req): Json<ImageGcRequest>,
+) -> Result<axum::response::Response> {
+    use axum::response::IntoResponse as _;
+
+    let pool = storage_pools::get(env.pool(), pool_id).await?;
+    if pool.pool_type != storage_pools::StoragePoolType::OverlayBd {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "Image GC is only valid for OVERLAYBD pools".into(),
+        ));
+    }
+    let registry_url = crate::model::storage_pools::OverlayBdPoolConfig::from_value(&pool.config)
+        .map(|cfg| cfg.url)
+        .ok_or_else(|| {
+            crate::errors::Error::UnprocessableEntity(
+                "OverlayBD pool config missing 'url' field".into(),
+            )
+        })?;
+    let host = require_up_host_for_pool(&env, pool_id).await?;
+
+    let job = jobs::create(
+        env.pool(),
+        NewJob {
+            job_type: JobType::ImageGc,
+            description: Some(format!(
+                "{} unreferenced images in pool {}",
+                if req.dry_run { "Listing" } else { "Deleting" },
+                pool.name
+            )),
+            resource_id: Some(pool_id),
+            resource_type: Some("storage_pool".to_string()),
+        },
+    )
+    .await?;
+    let job_id = job.id;
+
+    let db_pool = env.pool_arc();
+    let dry_run = req.dry_run;
+    tokio::spawn(async move {
+        if let Err(e) = jobs::mark_running(&db_pool, job_id).await {
+            tracing::error!(job_id = %job_id, error = %e, "Failed to mark image GC job running");
+            return;
+        }
+
+        // Collect references inside the job so images imported while it was
+        // queued are still kept.
+        let (keep_refs, keep_digests) = match storage_objects::referenced_images(&db_pool).await {
+            Ok(refs) => refs,
+            Err(e) => {
+                let msg = format!("Failed to collect image references: {}", e);
+                tracing::error!(pool_id = %pool_id, error = %msg);
+                let _ = jobs::mark_failed(&db_pool, job_id, &msg).await;
+                return;
+            }
+        };
+
+        match NodeClient::new(&host.address, host.port as u16)
+            .garbage_collect_overlaybd_images(&registry_url, keep_refs, keep_digests, dry_run)
+            .await
+        {
+            Ok(report) => {
+                let images: Vec<_> = report
+                    .images
+                    .iter()
+                    .map(|image| {
+                        serde_json::json!({
+                            "repository": image.repository,
+                            "tags": image.tags,
+                            "digest": image.digest,
+                            "size_bytes": image.size_bytes,
+                        })
+                    })
+                    .collect();
+                let result = serde_json::json!({
+                    "dry_run": dry_run,
+                    "images": images,
+                    "kept": report.kept,
+                    "reclaimed_bytes": report.reclaimed_bytes,
+                });
+                let _ = jobs::mark_completed(&db_pool, job_id, Some(result)).await;
+                tracing::info!(
+                    pool_id = %pool_id,
+                    job_id = %job_id,
+                    images = report.images.len(),
+                    reclaimed_bytes = report.reclaimed_bytes,
+                    dry_run,
+                    "Image GC job completed"
+                );
+            }
+            Err(e) => {
+                let msg = format!("Image GC failed: {}", e);
+                tracing::error!(pool_id = %pool_id, error = %msg);
+                let _ = jobs::mark_failed(&db_pool, job_id, &msg).await;
+            }
+        }
+    });
+
+    Ok(ApiResponse {
+        data: ImageGcResponse { job_id },
+        code: StatusCode::ACCEPTED,
+    }
+    .into_response())
+}
+
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct RegisterLunRequest {
     /// Human-readable name for the resulting storage object.
@@ -659,7 +1247,294 @@ pub async fn register_lun(
     })
 }
 
-async fn require_up_host_for_pool(env: &App, pool_id: Uuid) -> Result<hosts::Host> {
+/// One LUN in a discovery report.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct LunSyncEntry {
+    pub storage_object_id: Uuid,
+    pub name: String,
+    pub lun: u32,
+    pub size_bytes: i64,
+    /// Size recorded before this discovery; set only for resized LUNs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_size_bytes: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wwn: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+}
+
+/// Outcome of syncing a BLOCK pool's storage objects with the LUNs its target exports.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct LunDiscoveryReport {
+    /// LUNs seen for the first time; a disk object was registered for each.
+    pub new: Vec<LunSyncEntry>,
+    /// Known LUNs whose size changed on the target.
+    pub resized: Vec<LunSyncEntry>,
+    /// Registered LUNs the target no longer exports. They are flagged
+    /// `"missing": true` in their config and cannot be attached to VMs.
+    pub vanished: Vec<LunSyncEntry>,
+    /// Number of known LUNs that did not change.
+    pub unchanged: usize,
+}
+
+fn object_lun(obj: &storage_objects::StorageObject) -> Option<u32> {
+    obj.config
+        .get("lun")
+        .and_then(|v| v.as_u64())
+        .and_then(|l| u32::try_from(l).ok())
+}
+
+fn object_wwn(obj: &storage_objects::StorageObject) -> Option<&str> {
+    obj.config
+        .get("wwn")
+        .and_then(|v| v.as_str())
+        .filter(|w| !w.is_empty())
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+#[derive(Default)]
+struct LunSyncPlan<'a> {
+    new: Vec<&'a DiscoveredLun>,
+    matched: Vec<(&'a storage_objects::StorageObject, &'a DiscoveredLun)>,
+    vanished: Vec<&'a storage_objects::StorageObject>,
+}
+
+/// Pair discovered LUNs with registered objects. The WWN identifies a LUN even
+/// if the target renumbers it; the LUN number is only trusted when neither
+/// side contradicts it with a different WWN (a LUN replaced on the target).
+fn plan_lun_sync<'a>(
+    existing: &'a [storage_objects::StorageObject],
+    discovered: &'a [DiscoveredLun],
+) -> LunSyncPlan<'a> {
+    let registered: Vec<_> = existing
+        .iter()
+        .filter(|obj| object_lun(obj).is_some())
+        .collect();
+    let mut claimed = vec![false; registered.len()];
+    let mut found: Vec<Option<usize>> = vec![None; discovered.len()];
+
+    for (d, lun) in discovered.iter().enumerate() {
+        if lun.wwn.is_empty() {
+            continue;
+        }
+        if let Some(i) = (0..registered.len())
+            .find(|&i| !claimed[i] && object_wwn(registered[i]) == Some(lun.wwn.as_str()))
+        {
+            claimed[i] = true;
+            found[d] = Some(i);
+        }
+    }
+    for (d, lun) in discovered.iter().enumerate() {
+        if found[d].is_some() {
+            continue;
+        }
+        if let Some(i) = (0..registered.len()).find(|&i| {
+            !claimed[i]
+                && object_lun(registered[i]) == Some(lun.lun)
+                && (lun.wwn.is_empty()
+                    || object_wwn(registered[i]).is_none_or(|wwn| wwn == lun.wwn))
+        }) {
+            claimed[i] = true;
+            found[d] = Some(i);
+        }
+    }
+
+    let mut plan = LunSyncPlan::default();
+    for (lun, slot) in discovered.iter().zip(found) {
+        match slot {
+            Some(i) => plan.matched.push((registered[i], lun)),
+            None => plan.new.push(lun),
+        }
+    }
+    plan.vanished = registered
+        .iter()
+        .zip(claimed)
+        .filter(|(_, claimed)| !claimed)
+        .map(|(obj, _)| *obj)
+        .collect();
+    plan
+}
+
+/// Discover the LUNs exported by a BLOCK pool's target and sync them into storage objects.
+///
+/// Asks an UP host attached to the pool to list every LUN it sees after
+/// login. Unknown LUNs are registered as disk objects, size changes are
+/// recorded, and registered LUNs that disappeared are flagged as missing
+/// rather than deleted, since VMs may still reference them.
+#[utoipa::path(
+    post,
+    path = "/storage-pools/{pool_id}/luns/discover",
+    params(
+        ("pool_id" = Uuid, Path, description = "Storage pool ID")
+    ),
+    responses(
+        (status = 200, description = "LUNs synced", body = LunDiscoveryReport),
+        (status = 404, description = "Pool not found"),
+        (status = 422, description = "Pool is not BLOCK or has no UP host"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "storage-pools"
+)]
+#[instrument(skip(env))]
+pub async fn discover_luns(
+    Extension(env): Extension<App>,
+    Path(pool_id): Path<Uuid>,
+) -> Result<ApiResponse<LunDiscoveryReport>> {
+    let pool = storage_pools::get(env.pool(), pool_id).await?;
+
+    if pool.pool_type != storage_pools::StoragePoolType::Block {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "LUN discovery is only valid for BLOCK pools".into(),
+        ));
+    }
+
+    let host = require_up_host_for_pool(&env, pool_id).await?;
+    let discovered = NodeClient::new(&host.address, host.port as u16)
+        .discover_luns(&pool)
+        .await
+        .map_err(|e| {
+            tracing::error!(pool_id = %pool_id, host_id = %host.id, "LUN discovery failed: {}", e);
+            crate::errors::Error::InternalServerError
+        })?;
+
+    let existing = storage_objects::list(env.pool(), None, Some(pool_id), None).await?;
+    let plan = plan_lun_sync(&existing, &discovered);
+    let mut report = LunDiscoveryReport {
+        new: Vec::new(),
+        resized: Vec::new(),
+        vanished: Vec::new(),
+        unchanged: 0,
+    };
+
+    for lun in plan.new {
+        let mut config = serde_json::json!({ "lun": lun.lun });
+        if !lun.wwn.is_empty() {
+            config["wwn"] = serde_json::json!(lun.wwn);
+        }
+        if !lun.serial.is_empty() {
+            config["serial"] = serde_json::json!(lun.serial);
+        }
+        let name = format!("{}-lun-{}", pool.name, lun.lun);
+        let storage_object_id = storage_objects::create(
+            env.pool(),
+            NewStorageObject {
+                name: name.clone(),
+                storage_pool_id: Some(pool_id),
+                object_type: StorageObjectType::Disk,
+                size_bytes: lun.size_bytes,
+                config,
+                parent_id: None,
+            },
+        )
+        .await?;
+        report.new.push(LunSyncEntry {
+            storage_object_id,
+            name,
+            lun: lun.lun,
+            size_bytes: lun.size_bytes,
+            previous_size_bytes: None,
+            wwn: non_empty(&lun.wwn),
+            serial: non_empty(&lun.serial),
+        });
+    }
+
+    for (obj, lun) in plan.matched {
+        let mut config = obj.config.clone();
+        config["lun"] = serde_json::json!(lun.lun);
+        if !lun.wwn.is_empty() {
+            config["wwn"] = serde_json::json!(lun.wwn);
+        }
+        if !lun.serial.is_empty() {
+            config["serial"] = serde_json::json!(lun.serial);
+        }
+        if let Some(map) = config.as_object_mut() {
+            map.remove("missing");
+        }
+        if config != obj.config {
+            storage_objects::update_config(env.pool(), obj.id, &config).await?;
+        }
+
+        if obj.size_bytes == lun.size_bytes {
+            report.unchanged += 1;
+            continue;
+        }
+        storage_objects::update_size_bytes(env.pool(), obj.id, lun.size_bytes).await?;
+        report.resized.push(LunSyncEntry {
+            storage_object_id: obj.id,
+            name: obj.name.clone(),
+            lun: lun.lun,
+            size_bytes: lun.size_bytes,
+            previous_size_bytes: Some(obj.size_bytes),
+            wwn: non_empty(&lun.wwn),
+            serial: non_empty(&lun.serial),
+        });
+    }
+
+    for obj in plan.vanished {
+        if obj.config.get("missing").and_then(|v| v.as_bool()) != Some(true) {
+            let mut config = obj.config.clone();
+            config["missing"] = serde_json::json!(true);
+            storage_objects::update_config(env.pool(), obj.id, &config).await?;
+            warn!(pool_id = %pool_id, storage_object_id = %obj.id, "LUN no longer exported by target");
+        }
+        report.vanished.push(LunSyncEntry {
+            storage_object_id: obj.id,
+            name: obj.name.clone(),
+            lun: object_lun(obj).unwrap_or_default(),
+            size_bytes: obj.size_bytes,
+            previous_size_bytes: None,
+            wwn: object_wwn(obj).map(str::to_string),
+            serial: obj
+                .config
+                .get("serial")
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+        });
+    }
+
+    Ok(ApiResponse {
+        data: report,
+        code: StatusCode::OK,
+    })
+}
+
+/// Refuse to allocate `size_bytes` in `pool` from `host` when that would go
+/// past the pool's configured capacity, or past the free space the host last
+/// measured, which catches exports filled outside qarax. A size of 0 stands
+/// for one not known up front (a download, a clone), which is only refused
+/// once the pool is full.
+pub(crate) async fn admit_pool_allocation(
+    db: &sqlx::PgPool,
+    pool: &StoragePool,
+    host: &hosts::Host,
+    size_bytes: i64,
+) -> Result<()> {
+    if let (Some(capacity), Some(allocated)) = (pool.capacity_bytes, pool.allocated_bytes) {
+        let available = capacity - allocated;
+        if available <= 0 || size_bytes > available {
+            return Err(crate::errors::Error::UnprocessableEntity(format!(
+                "Insufficient pool capacity: requested {} bytes but only {} available",
+                size_bytes,
+                available.max(0)
+            )));
+        }
+    }
+
+    if let Some(free) = storage_pools::measured_free_bytes(db, pool.id, host.id).await?
+        && (free <= 0 || size_bytes > free)
+    {
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "Insufficient pool space: requested {} bytes but host {} reports {} free",
+            size_bytes, host.name, free
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) async fn require_up_host_for_pool(env: &App, pool_id: Uuid) -> Result<hosts::Host> {
     let host_id = storage_pools::find_host_for_pool(env.pool(), pool_id)
         .await
         .map_err(|e| {
@@ -680,3 +1555,70 @@ async fn require_up_host_for_pool(env: &App, pool_id: Uuid) -> Result<hosts::Hos
 
     Ok(host)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_object(
+        lun: u32,
+        wwn: Option<&str>,
+        size_bytes: i64,
+    ) -> storage_objects::StorageObject {
+        let mut config = serde_json::json!({ "iqn": "iqn.2024-01.qarax:target0", "lun": lun });
+        if let Some(wwn) = wwn {
+            config["wwn"] = serde_json::json!(wwn);
+        }
+        storage_objects::StorageObject {
+            id: Uuid::new_v4(),
+            name: format!("lun-{lun}"),
+            storage_pool_id: Uuid::nil(),
+            object_type: StorageObjectType::Disk,
+            size_bytes,
+            config,
+            parent_id: None,
+        }
+    }
+
+    fn discovered(lun: u32, wwn: &str, size_bytes: i64) -> DiscoveredLun {
+        DiscoveredLun {
+            lun,
+            size_bytes,
+            wwn: wwn.to_string(),
+            serial: String::new(),
+        }
+    }
+
+    #[test]
+    fn lun_sync_matches_by_wwn_then_lun_number() {
+        let existing = vec![
+            // Renumbered on the target: still the same LUN.
+            block_object(1, Some("naa.aaa"), 10),
+            // Registered by hand without a WWN.
+            block_object(2, None, 20),
+            // LUN 3 now holds a different disk.
+            block_object(3, Some("naa.ccc"), 30),
+            // Gone from the target.
+            block_object(4, None, 40),
+        ];
+        let luns = vec![
+            discovered(5, "naa.aaa", 10),
+            discovered(2, "naa.bbb", 25),
+            discovered(3, "naa.ddd", 30),
+        ];
+
+        let plan = plan_lun_sync(&existing, &luns);
+
+        let matched: Vec<(Uuid, u32)> = plan
+            .matched
+            .iter()
+            .map(|(obj, lun)| (obj.id, lun.lun))
+            .collect();
+        assert_eq!(matched, vec![(existing[0].id, 5), (existing[1].id, 2)]);
+        assert_eq!(plan.new.iter().map(|l| l.lun).collect::<Vec<_>>(), vec![3]);
+        assert_eq!(
+            plan.vanished.iter().map(|o| o.id).collect::<Vec<_>>(),
+            vec![existing[2].id, existing[3].id]
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use axum::{Extension, Json, extract::Path};
+use common::checksum::Checksum;
 use http::StatusCode;
 use tracing::{error, info, instrument};
 use uuid::Uuid;
@@ -10,7 +11,7 @@ use crate::{
         storage_pools,
         transfers::{self, NewTransfer, Transfer, TransferType},
     },
-    transfer_executor::executor_for_pool,
+    transfer_executor::{TransferError, executor_for_pool},
 };
 
 use super::{ApiResponse, Result};
@@ -24,6 +25,35 @@ fn infer_transfer_type(source: &str) -> TransferType {
     }
 }
 
+/// Checksum and bandwidth options only make sense for URL downloads. The
+/// checksum is normalized in place so the stored value is always lowercase.
+fn validate_download_options(
+    new_transfer: &mut NewTransfer,
+    transfer_type: &TransferType,
+) -> Result<()> {
+    let has_options = new_transfer.expected_checksum.is_some()
+        || new_transfer.bandwidth_limit_bytes_per_sec.is_some();
+    if has_options && *transfer_type != TransferType::Download {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "expected_checksum and bandwidth_limit_bytes_per_sec require an http(s) source".into(),
+        ));
+    }
+    if let Some(checksum) = &new_transfer.expected_checksum {
+        let checksum =
+            Checksum::parse(checksum).map_err(crate::errors::Error::UnprocessableEntity)?;
+        new_transfer.expected_checksum = Some(checksum.to_string());
+    }
+    if new_transfer
+        .bandwidth_limit_bytes_per_sec
+        .is_some_and(|limit| limit <= 0)
+    {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "bandwidth_limit_bytes_per_sec must be greater than 0".into(),
+        ));
+    }
+    Ok(())
+}
+
 #[utoipa::path(
     post,
     path = "/storage-pools/{pool_id}/transfers",
@@ -34,7 +64,7 @@ fn infer_transfer_type(source: &str) -> TransferType {
     responses(
         (status = 202, description = "Transfer accepted and started", body = Transfer),
         (status = 404, description = "Storage pool not found"),
-        (status = 422, description = "Invalid input"),
+        (status = 422, description = "Invalid input or checksum/bandwidth options on a non-URL source"),
         (status = 500, description = "Internal server error")
     ),
     tag = "transfers"
@@ -43,12 +73,13 @@ fn infer_transfer_type(source: &str) -> TransferType {
 pub async fn create(
     Extension(env): Extension<App>,
     Path(pool_id): Path<Uuid>,
-    Json(new_transfer): Json<NewTransfer>,
+    Json(mut new_transfer): Json<NewTransfer>,
 ) -> Result<ApiResponse<Transfer>> {
     // Validate pool exists
     let pool = storage_pools::get(env.pool(), pool_id).await?;
 
     let transfer_type = infer_transfer_type(&new_transfer.source);
+    validate_download_options(&mut new_transfer, &transfer_type)?;
 
     // Insert transfer record
     let transfer = transfers::create(env.pool(), pool_id, &new_transfer, transfer_type).await?;
@@ -74,6 +105,13 @@ pub async fn create(
         // Execute the transfer
         match executor.execute(&bg_transfer, &bg_pool, &db_pool).await {
             Ok(result) => {
+                if let Some(checksum) = &result.checksum
+                    && let Err(e) =
+                        transfers::record_checksum(&db_pool, transfer_id, checksum, true).await
+                {
+                    error!(transfer_id = %transfer_id, error = %e, "Failed to record transfer checksum");
+                }
+
                 // Create the storage object
                 let new_object = NewStorageObject {
                     name: bg_transfer.name.clone(),
@@ -116,6 +154,9 @@ pub async fn create(
                 }
             }
             Err(e) => {
+                if let TransferError::ChecksumMismatch { actual, .. } = &e {
+                    let _ = transfers::record_checksum(&db_pool, transfer_id, actual, false).await;
+                }
                 let msg = e.to_string();
                 error!(transfer_id = %transfer_id, error = %msg, "Transfer failed");
                 let _ = transfers::mark_failed(&db_pool, transfer_id, &msg).await;
@@ -0,0 +1,419 @@
+//! Client uploads into Local and NFS pools.
+//!
+//! An upload is a `PUT` of the raw bytes, optionally split into several
+//! requests with `Content-Range: bytes <start>-<end>/<total>`. Each request is
+//! streamed straight to a node attached to the pool; the node keeps a
+//! `<name>.upload` partial file until the last byte arrives. Progress is
+//! tracked as an `UPLOAD` transfer whose `transferred_bytes` is the offset the
+//! next request has to start at, so an interrupted upload can be resumed.
+
+use axum::{
+    Extension,
+    body::Body,
+    extract::{Path, Query},
+};
+use http::{HeaderMap, StatusCode, header};
+use serde::Deserialize;
+use tokio_stream::StreamExt as _;
+use tracing::{error, info, instrument, warn};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::{
+    App,
+    grpc_client::NodeClient,
+    handlers::storage_pool::handler::{admit_pool_allocation, require_up_host_for_pool},
+    model::{
+        storage_objects::{self, NewStorageObject, StorageObjectType},
+        storage_pools::{self, StoragePool, StoragePoolType},
+        transfers::{self, Transfer},
+    },
+};
+
+use super::{ApiResponse, Result};
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct UploadQuery {
+    /// Type of the resulting storage object (default: disk)
+    pub object_type: Option<StorageObjectType>,
+}
+
+/// Byte range carried by a single upload request; `end` is exclusive.
+#[derive(Debug, PartialEq, Eq)]
+struct UploadRange {
+    start: i64,
+    end: i64,
+    total: i64,
+}
+
+/// Work out which part of the file a request carries. Without `Content-Range`
+/// the body is the whole file, so `Content-Length` is required either way: the
+/// node needs to know where the stream is supposed to end.
+fn upload_range(headers: &HeaderMap) -> Result<UploadRange> {
+    let invalid = |msg: &str| crate::errors::Error::UnprocessableEntity(msg.to_string());
+
+    let content_length = headers
+        .get(header::CONTENT_LENGTH)
+        .map(|v| {
+            v.to_str()
+                .ok()
+                .and_then(|v| v.parse::<i64>().ok())
+                .ok_or_else(|| invalid("invalid Content-Length header"))
+        })
+        .transpose()?;
+
+    let range = match headers.get(header::CONTENT_RANGE) {
+        Some(value) => {
+            let value = value
+                .to_str()
+                .map_err(|_| invalid("invalid Content-Range header"))?;
+            parse_content_range(value).ok_or_else(|| {
+                invalid("Content-Range must look like 'bytes <start>-<end>/<total>'")
+            })?
+        }
+        None => {
+            let length = content_length.ok_or_else(|| {
+                invalid("uploads require a Content-Length or Content-Range header")
+            })?;
+            UploadRange {
+                start: 0,
+                end: length,
+                total: length,
+            }
+        }
+    };
+
+    if range.total == 0 {
+        return Err(invalid("upload must not be empty"));
+    }
+    if let Some(length) = content_length
+        && length != range.end - range.start
+    {
+        return Err(invalid("Content-Length does not match Content-Range"));
+    }
+    Ok(range)
+}
+
+fn parse_content_range(value: &str) -> Option<UploadRange> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, last) = range.split_once('-')?;
+    let (start, last, total) = (
+        start.trim().parse::<i64>().ok()?,
+        last.trim().parse::<i64>().ok()?,
+        total.trim().parse::<i64>().ok()?,
+    );
+    (0 <= start && start <= last && last < total).then_some(UploadRange {
+        start,
+        end: last + 1,
+        total,
+    })
+}
+
+/// Names become file names on the node, so keep them to a single path component.
+fn validate_upload_name(name: &str) -> Result<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') || name.contains('\0') {
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "invalid upload name '{name}'"
+        )));
+    }
+    Ok(())
+}
+
+fn upload_destination(pool: &StoragePool, name: &str) -> Result<String> {
+    if !matches!(
+        pool.pool_type,
+        StoragePoolType::Local | StoragePoolType::Nfs
+    ) {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "Uploads are only supported for Local and NFS pools".into(),
+        ));
+    }
+    if !pool.status.is_usable() {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "Storage pool is not active".into(),
+        ));
+    }
+    let base = pool.node_base_path().ok_or_else(|| {
+        crate::errors::Error::UnprocessableEntity("Storage pool has no path configured".into())
+    })?;
+    Ok(format!("{base}/{name}"))
+}
+
+#[utoipa::path(
+    put,
+    path = "/storage-pools/{pool_id}/uploads/{name}",
+    params(
+        ("pool_id" = uuid::Uuid, Path, description = "Storage pool unique identifier"),
+        ("name" = String, Path, description = "Name of the resulting storage object and file"),
+        ("Content-Range" = Option<String>, Header, description = "bytes <start>-<end>/<total> when uploading in chunks"),
+        UploadQuery
+    ),
+    request_body(content = String, content_type = "application/octet-stream", description = "Raw file bytes"),
+    responses(
+        (status = 201, description = "Upload complete; storage object created", body = Transfer),
+        (status = 202, description = "Chunk stored; more data expected", body = Transfer),
+        (status = 404, description = "Storage pool not found"),
+        (status = 409, description = "Range does not continue the upload in progress, or the name is already taken"),
+        (status = 422, description = "Invalid range, pool, or the node rejected the data"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "transfers"
+)]
+#[instrument(skip(env, body))]
+pub async fn upload(
+    Extension(env): Extension<App>,
+    Path((pool_id, name)): Path<(Uuid, String)>,
+    Query(query): Query<UploadQuery>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<ApiResponse<Transfer>> {
+    validate_upload_name(&name)?;
+    let range = upload_range(&headers)?;
+    let pool = storage_pools::get(env.pool(), pool_id).await?;
+    let destination = upload_destination(&pool, &name)?;
+    let host = require_up_host_for_pool(&env, pool_id).await?;
+
+    let transfer = match transfers::find_active_upload(env.pool(), pool_id, &name).await? {
+        Some(transfer) => {
+            if transfer.total_bytes != Some(range.total) {
+                return Err(crate::errors::Error::Conflict(format!(
+                    "upload '{name}' is in progress with a different size; cancel it first"
+                )));
+            }
+            if range.start != 0 && range.start != transfer.transferred_bytes {
+                return Err(crate::errors::Error::Conflict(format!(
+                    "upload '{name}' is at offset {}",
+                    transfer.transferred_bytes
+                )));
+            }
+            transfer
+        }
+        None => {
+            if range.start != 0 {
+                return Err(crate::errors::Error::Conflict(format!(
+                    "no upload in progress for '{name}'; start at offset 0"
+                )));
+            }
+            if storage_objects::path_in_use(env.pool(), pool_id, &destination).await? {
+                return Err(crate::errors::Error::Conflict(format!(
+                    "a storage object already uses '{name}' in this pool"
+                )));
+            }
+            admit_pool_allocation(env.pool(), &pool, &host, range.total).await?;
+            let object_type = query.object_type.unwrap_or(StorageObjectType::Disk);
+            transfers::create_upload(env.pool(), pool_id, &name, &object_type, range.total).await?
+        }
+    };
+
+    let finalize = range.end == range.total;
+    // A broken client connection just ends the stream early; the node then
+    // sees fewer bytes than `range.end` and refuses to commit the chunk.
+    let data = body
+        .into_data_stream()
+        .map_while(|chunk| chunk.ok())
+        .map(Vec::from);
+
+    let node_client = NodeClient::new(&host.address, host.port as u16);
+    let bytes_written = node_client
+        .upload_file(
+            &transfer.id.to_string(),
+            &destination,
+            range.start,
+            range.end,
+            finalize,
+            data,
+        )
+        .await
+        .map_err(|e| {
+            warn!(transfer_id = %transfer.id, error = %e, "Upload chunk failed");
+            match e.downcast::<crate::errors::Error>() {
+                Ok(err) => err,
+                Err(e) => crate::errors::Error::UnprocessableEntity(format!(
+                    "Upload to node failed: {e:#}"
+                )),
+            }
+        })?;
+
+    if !transfers::advance_upload(
+        env.pool(),
+        transfer.id,
+        transfer.transferred_bytes,
+        bytes_written,
+    )
+    .await?
+    {
+        return Err(crate::errors::Error::Conflict(format!(
+            "upload '{name}' was modified by a concurrent request"
+        )));
+    }
+
+    if !finalize {
+        return Ok(ApiResponse {
+            data: transfers::get(env.pool(), transfer.id).await?,
+            code: StatusCode::ACCEPTED,
+        });
+    }
+
+    let object_id = match storage_objects::create(
+        env.pool(),
+        NewStorageObject {
+            name: name.clone(),
+            storage_pool_id: Some(pool_id),
+            object_type: transfer.object_type.clone(),
+            size_bytes: bytes_written,
+            config: serde_json::json!({ "path": destination }),
+            parent_id: None,
+        },
+    )
+    .await
+    {
+        Ok(object_id) => object_id,
+        Err(e) => {
+            let msg = format!("Failed to create storage object: {e}");
+            error!(transfer_id = %transfer.id, error = %msg);
+            let _ = transfers::mark_failed(env.pool(), transfer.id, &msg).await;
+            return Err(e.into());
+        }
+    };
+    transfers::mark_completed(env.pool(), transfer.id, object_id, bytes_written).await?;
+    info!(
+        transfer_id = %transfer.id,
+        storage_object_id = %object_id,
+        bytes = bytes_written,
+        "Upload completed"
+    );
+
+    Ok(ApiResponse {
+        data: transfers::get(env.pool(), transfer.id).await?,
+        code: StatusCode::CREATED,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/storage-pools/{pool_id}/uploads/{name}",
+    params(
+        ("pool_id" = uuid::Uuid, Path, description = "Storage pool unique identifier"),
+        ("name" = String, Path, description = "Upload name")
+    ),
+    responses(
+        (status = 200, description = "Upload in progress; transferred_bytes is the resume offset", body = Transfer),
+        (status = 404, description = "No upload in progress with this name"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "transfers"
+)]
+#[instrument(skip(env))]
+pub async fn get(
+    Extension(env): Extension<App>,
+    Path((pool_id, name)): Path<(Uuid, String)>,
+) -> Result<ApiResponse<Transfer>> {
+    let transfer = transfers::find_active_upload(env.pool(), pool_id, &name)
+        .await?
+        .ok_or(crate::errors::Error::NotFound)?;
+    Ok(ApiResponse {
+        data: transfer,
+        code: StatusCode::OK,
+    })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/storage-pools/{pool_id}/uploads/{name}",
+    params(
+        ("pool_id" = uuid::Uuid, Path, description = "Storage pool unique identifier"),
+        ("name" = String, Path, description = "Upload name")
+    ),
+    responses(
+        (status = 204, description = "Upload cancelled and partial data removed"),
+        (status = 404, description = "No upload in progress with this name"),
+        (status = 422, description = "No UP host is attached to the pool"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "transfers"
+)]
+#[instrument(skip(env))]
+pub async fn cancel(
+    Extension(env): Extension<App>,
+    Path((pool_id, name)): Path<(Uuid, String)>,
+) -> Result<ApiResponse<()>> {
+    let transfer = transfers::find_active_upload(env.pool(), pool_id, &name)
+        .await?
+        .ok_or(crate::errors::Error::NotFound)?;
+    let pool = storage_pools::get(env.pool(), pool_id).await?;
+    let destination = upload_destination(&pool, &name)?;
+    let host = require_up_host_for_pool(&env, pool_id).await?;
+
+    NodeClient::new(&host.address, host.port as u16)
+        .delete_path(&format!("{destination}.upload"), false)
+        .await
+        .map_err(|e| {
+            crate::errors::Error::UnprocessableEntity(format!(
+                "Failed to remove partial upload: {e:#}"
+            ))
+        })?;
+    transfers::mark_failed(env.pool(), transfer.id, "upload cancelled").await?;
+
+    Ok(ApiResponse {
+        data: (),
+        code: StatusCode::NO_CONTENT,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(header::HeaderName, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.clone(), value.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn upload_range_uses_content_range_or_whole_body() {
+        let whole = upload_range(&headers(&[(header::CONTENT_LENGTH, "10")])).unwrap();
+        assert_eq!(
+            whole,
+            UploadRange {
+                start: 0,
+                end: 10,
+                total: 10
+            }
+        );
+
+        let chunk = upload_range(&headers(&[
+            (header::CONTENT_RANGE, "bytes 4-7/10"),
+            (header::CONTENT_LENGTH, "4"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            chunk,
+            UploadRange {
+                start: 4,
+                end: 8,
+                total: 10
+            }
+        );
+    }
+
+    #[test]
+    fn upload_range_rejects_bad_headers() {
+        assert!(upload_range(&HeaderMap::new()).is_err());
+        assert!(upload_range(&headers(&[(header::CONTENT_LENGTH, "0")])).is_err());
+        for bad in ["bytes 5-4/10", "bytes 0-10/10", "bytes */10", "items 0-1/2"] {
+            assert!(
+                upload_range(&headers(&[(header::CONTENT_RANGE, bad)])).is_err(),
+                "{bad}"
+            );
+        }
+        assert!(
+            upload_range(&headers(&[
+                (header::CONTENT_RANGE, "bytes 0-3/10"),
+                (header::CONTENT_LENGTH, "5"),
+            ]))
+            .is_err()
+        );
+    }
+}
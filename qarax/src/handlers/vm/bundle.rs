@@ -0,0 +1,1279 @@
+//! Portable VM export/import bundles.
+//!
+//! A bundle is a single uncompressed tar archive on a Local or NFS pool:
+//!
+//! ```text
+//! manifest.json     VM spec, NICs, disks and per-file SHA-256 digests
+//! SHA256SUMS        `sha256sum -c` compatible list of every file
+//! disks/<name>.raw  one raw image per exported disk
+//! memory/           optional hypervisor memory snapshot
+//! ```
+//!
+//! Only the archive is needed to recreate the VM elsewhere; network and
+//! storage pool references are remapped on import. Disks are streamed into
+//! the archive from their storage objects and back out into new ones, so
+//! neither side needs a second copy.
+
+use std::collections::HashMap;
+
+use axum::{Extension, Json, extract::Path};
+use chrono::{DateTime, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{error, info, instrument, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    App,
+    grpc_client::{
+        NodeClient,
+        node::{ArchiveMember, FileChecksum},
+    },
+    handlers::audit::{AuditEvent, AuditEventExt},
+    model::{
+        audit_log::{AuditAction, AuditResourceType},
+        hosts::{self, Host},
+        jobs::{self, JobType, NewJob},
+        network_interfaces::{self, NetworkInterface},
+        networks, snapshots,
+        snapshots::{NewSnapshot, SnapshotStatus},
+        storage_objects::{self, NewStorageObject, StorageObject, StorageObjectType},
+        storage_pools::{self, StoragePool, StoragePoolType},
+        vm_disks::{self, NewVmDisk, VmDisk},
+        vms::{self, NewVm, NewVmNetwork, Vm, VmStatus},
+    },
+};
+
+use super::handler::{create_vm_internal, host_for_vm, record_ready_vm_backup};
+use super::{ApiResponse, Result};
+
+/// Bumped whenever the manifest layout changes incompatibly.
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const CHECKSUMS_FILE: &str = "SHA256SUMS";
+const DISKS_DIR: &str = "disks";
+const MEMORY_DIR: &str = "memory";
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub source_vm_id: Uuid,
+    /// The exported `vms` row. `id`, `host_id` and `status` are informational only.
+    pub vm: Vm,
+    pub nics: Vec<BundleNic>,
+    pub disks: Vec<BundleDisk>,
+    pub memory_snapshot: Option<BundleMemorySnapshot>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BundleNic {
+    #[serde(flatten)]
+    pub interface: NetworkInterface,
+    /// Name of the managed network on the exporting installation, used as a
+    /// fallback key when remapping networks on import.
+    pub network_name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BundleDisk {
+    #[serde(flatten)]
+    pub disk: VmDisk,
+    /// Path of the image relative to the bundle root.
+    pub file: String,
+    /// Logical (virtual) size of the disk, as tracked by the storage object.
+    pub size_bytes: i64,
+    pub sha256: String,
+    pub source_storage_pool_id: Uuid,
+    pub source_storage_pool_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BundleMemorySnapshot {
+    /// Directory relative to the bundle root.
+    pub dir: String,
+    pub files: Vec<BundleFile>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BundleFile {
+    pub name: String,
+    pub size_bytes: i64,
+    pub sha256: String,
+}
+
+impl From<FileChecksum> for BundleFile {
+    fn from(f: FileChecksum) -> Self {
+        BundleFile {
+            name: f.name,
+            size_bytes: f.size_bytes,
+            sha256: f.sha256,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ExportVmRequest {
+    /// Name of the bundle storage object (auto-generated if omitted).
+    pub name: Option<String>,
+    /// Local or NFS pool to write the bundle to. Defaults to the pool of the
+    /// VM's first disk.
+    pub storage_pool_id: Option<Uuid>,
+    /// Include a memory snapshot. Requires the VM to be running or paused;
+    /// otherwise the VM must be stopped.
+    #[serde(default)]
+    pub include_memory: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ExportVmResponse {
+    pub job_id: Uuid,
+    pub storage_object_id: Uuid,
+    /// Bundle archive on the pool's host(s).
+    pub path: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ImportVmRequest {
+    /// A `vm_bundle` storage object known to this installation. Mutually
+    /// exclusive with `storage_pool_id` + `path`.
+    pub storage_object_id: Option<Uuid>,
+    /// Pool holding a bundle copied in from elsewhere.
+    pub storage_pool_id: Option<Uuid>,
+    /// Absolute path of the bundle archive inside `storage_pool_id`.
+    pub path: Option<String>,
+    /// Name for the new VM (defaults to the exported name).
+    pub name: Option<String>,
+    /// Source network ID or name → network on this installation. Networks
+    /// without an entry are matched by name.
+    #[serde(default)]
+    #[schema(value_type = HashMap<String, String>)]
+    pub network_map: HashMap<String, Uuid>,
+    /// Source storage pool ID or name → Local/NFS pool on this installation.
+    #[serde(default)]
+    #[schema(value_type = HashMap<String, String>)]
+    pub storage_pool_map: HashMap<String, Uuid>,
+    /// Pool for disks not covered by `storage_pool_map` (defaults to the bundle's pool).
+    pub default_storage_pool_id: Option<Uuid>,
+    /// Boot source to use; boot sources are not carried in the bundle.
+    pub boot_source_id: Option<Uuid>,
+    /// Keep the exported IP addresses when they are free on the target network.
+    #[serde(default)]
+    pub preserve_ips: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ImportVmResponse {
+    pub vm_id: Uuid,
+    pub job_id: Uuid,
+}
+
+/// Directory next to a bundle archive that holds the memory snapshot until
+/// it is packed.
+fn memory_staging_dir(bundle_path: &str) -> String {
+    format!("{bundle_path}.{MEMORY_DIR}")
+}
+
+fn bundle_disk_file(logical_name: &str) -> String {
+    format!("{DISKS_DIR}/{logical_name}.raw")
+}
+
+/// Reject absolute paths and `..` components so a manifest cannot point the
+/// node outside of the bundle directory.
+fn validate_relative_path(path: &str) -> Result<()> {
+    let p = std::path::Path::new(path);
+    let ok = !path.is_empty()
+        && p.components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)));
+    if ok {
+        Ok(())
+    } else {
+        Err(crate::errors::Error::UnprocessableEntity(format!(
+            "invalid path in bundle manifest: {path}"
+        )))
+    }
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Render a `sha256sum -c` compatible checksum list for the bundle.
+fn render_checksums(manifest: &BundleManifest, manifest_sha256: &str) -> String {
+    let mut lines = vec![format!("{manifest_sha256}  {MANIFEST_FILE}")];
+    for disk in &manifest.disks {
+        lines.push(format!("{}  {}", disk.sha256, disk.file));
+    }
+    if let Some(memory) = &manifest.memory_snapshot {
+        for file in &memory.files {
+            lines.push(format!("{}  {}/{}", file.sha256, memory.dir, file.name));
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+fn ensure_bundle_pool(pool: &StoragePool) -> Result<()> {
+    if !matches!(
+        pool.pool_type,
+        StoragePoolType::Local | StoragePoolType::Nfs
+    ) {
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "storage pool '{}' must be Local or NFS to hold VM bundles",
+            pool.name
+        )));
+    }
+    if !pool.status.is_usable() {
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "storage pool '{}' is not active",
+            pool.name
+        )));
+    }
+    Ok(())
+}
+
+/// Check that a caller-supplied bundle path names a file inside the pool's
+/// directory, so an import can't read arbitrary files on the host.
+fn bundle_path_in_pool(pool: &StoragePool, path: &str) -> Result<String> {
+    let base = pool.node_base_path().ok_or_else(|| {
+        crate::errors::Error::UnprocessableEntity(format!(
+            "storage pool '{}' has no path configured",
+            pool.name
+        ))
+    })?;
+    let inside = path
+        .strip_prefix(&base)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .is_some_and(|rest| {
+            rest.split('/')
+                .all(|c| !c.is_empty() && c != "." && c != "..")
+        });
+    if !inside {
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "path must be a bundle archive under {base}"
+        )));
+    }
+    Ok(path.to_string())
+}
+
+/// A disk to be exported, resolved to its on-host path.
+struct ExportDisk {
+    disk: VmDisk,
+    object: StorageObject,
+    pool: StoragePool,
+    path: String,
+}
+
+async fn resolve_export_disks(env: &App, vm_id: Uuid) -> Result<Vec<ExportDisk>> {
+    let mut resolved = Vec::new();
+    for disk in vm_disks::list_by_vm(env.pool(), vm_id).await? {
+        let Some(object_id) = disk.storage_object_id else {
+            return Err(crate::errors::Error::UnprocessableEntity(format!(
+                "disk {} has no backing storage object and cannot be exported",
+                disk.logical_name
+            )));
+        };
+        let object = storage_objects::get(env.pool(), object_id).await?;
+        let pool = storage_pools::get(env.pool(), object.storage_pool_id).await?;
+        match pool.pool_type {
+            StoragePoolType::Local | StoragePoolType::Nfs => {}
+            StoragePoolType::OverlayBd => {
+                return Err(crate::errors::Error::UnprocessableEntity(format!(
+                    "disk {} is backed by an OCI image; commit the VM to a raw disk before exporting",
+                    disk.logical_name
+                )));
+            }
+            StoragePoolType::Block | StoragePoolType::Rbd => {
+                return Err(crate::errors::Error::UnprocessableEntity(format!(
+                    "disk {} is on a {} pool; only Local and NFS disks can be exported",
+                    disk.logical_name, pool.pool_type
+                )));
+            }
+        }
+        let path = storage_objects::get_path_from_config(&object.config).ok_or_else(|| {
+            crate::errors::Error::UnprocessableEntity(format!(
+                "disk {} has no resolvable host path",
+                disk.logical_name
+            ))
+        })?;
+        resolved.push(ExportDisk {
+            disk,
+            object,
+            pool,
+            path,
+        });
+    }
+    Ok(resolved)
+}
+
+#[utoipa::path(
+    post,
+    path = "/vms/{vm_id}/export",
+    params(
+        ("vm_id" = uuid::Uuid, Path, description = "VM unique identifier")
+    ),
+    request_body = ExportVmRequest,
+    responses(
+        (status = 202, description = "Export job accepted", body = ExportVmResponse),
+        (status = 404, description = "VM not found"),
+        (status = 422, description = "VM or its disks cannot be exported"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "vms"
+)]
+#[instrument(skip(env))]
+pub async fn export(
+    Extension(env): Extension<App>,
+    Path(vm_id): Path<Uuid>,
+    Json(req): Json<ExportVmRequest>,
+) -> Result<axum::response::Response> {
+    let vm = vms::get(env.pool(), vm_id).await?;
+    let allowed = if req.include_memory {
+        matches!(vm.status, VmStatus::Running | VmStatus::Paused)
+    } else {
+        matches!(vm.status, VmStatus::Created | VmStatus::Shutdown)
+    };
+    if !allowed {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            if req.include_memory {
+                "VM must be running or paused to export with a memory snapshot".into()
+            } else {
+                "VM must be stopped (Created or Shutdown) to export".into()
+            },
+        ));
+    }
+
+    let host = host_for_vm(&env, vm_id).await?;
+    let disks = resolve_export_disks(&env, vm_id).await?;
+
+    let preferred_pool_id = req
+        .storage_pool_id
+        .or_else(|| disks.first().map(|d| d.pool.id));
+    let pool_id = storage_pools::pick_active_non_overlaybd(env.pool(), preferred_pool_id)
+        .await?
+        .ok_or_else(|| {
+            crate::errors::Error::UnprocessableEntity(
+                "no suitable storage pool available for the bundle".into(),
+            )
+        })?;
+    let bundle_pool = storage_pools::get(env.pool(), pool_id).await?;
+    ensure_bundle_pool(&bundle_pool)?;
+    if !storage_pools::host_has_pool(env.pool(), host.id, bundle_pool.id).await? {
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "storage pool '{}' is not attached to the VM's host",
+            bundle_pool.name
+        )));
+    }
+
+    let name = req
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("{}-export-{}", vm.name, &Uuid::new_v4().to_string()[..8]));
+    let bundle = storage_objects::create_returning(
+        env.pool(),
+        NewStorageObject {
+            name: name.clone(),
+            storage_pool_id: Some(bundle_pool.id),
+            object_type: StorageObjectType::VmBundle,
+            size_bytes: 0,
+            config: serde_json::Value::Null,
+            parent_id: None,
+        },
+    )
+    .await?;
+    let bundle_path = storage_objects::get_path_from_config(&bundle.config)
+        .ok_or(crate::errors::Error::InternalServerError)?;
+
+    let job = jobs::create(
+        env.pool(),
+        NewJob {
+            job_type: JobType::VmExport,
+            description: Some(format!("Exporting VM {} to bundle {}", vm.name, name)),
+            resource_id: Some(vm_id),
+            resource_type: Some(jobs::resource_types::VM.to_string()),
+        },
+    )
+    .await?;
+    let job_id = job.id;
+
+    let db_pool = env.pool_arc();
+    let params = ExportParams {
+        job_id,
+        bundle_object_id: bundle.id,
+        bundle_path: bundle_path.clone(),
+        include_memory: req.include_memory,
+    };
+    let vm_name = vm.name.clone();
+    tokio::spawn(async move {
+        run_vm_export(&db_pool, &host, vm, disks, params).await;
+    });
+
+    Ok(ApiResponse {
+        data: ExportVmResponse {
+            job_id,
+            storage_object_id: bundle.id,
+            path: bundle_path,
+        },
+        code: StatusCode::ACCEPTED,
+    }
+    .with_audit_event(AuditEvent {
+        action: AuditAction::Export,
+        resource_type: AuditResourceType::Vm,
+        resource_id: vm_id,
+        resource_name: Some(vm_name),
+        metadata: Some(serde_json::json!({
+            "storage_object_id": bundle.id,
+            "include_memory": req.include_memory,
+        })),
+    }))
+}
+
+struct ExportParams {
+    job_id: Uuid,
+    bundle_object_id: Uuid,
+    bundle_path: String,
+    include_memory: bool,
+}
+
+async fn run_vm_export(
+    db_pool: &sqlx::PgPool,
+    host: &Host,
+    vm: Vm,
+    disks: Vec<ExportDisk>,
+    params: ExportParams,
+) {
+    let job_id = params.job_id;
+    if let Err(e) = jobs::mark_running(db_pool, job_id).await {
+        error!(job_id = %job_id, error = %e, "Failed to mark export job as running");
+        return;
+    }
+
+    let node_client = NodeClient::new(&host.address, host.port as u16);
+
+    // With a memory snapshot the VM stays paused while the disks are copied so
+    // the disk images match the captured memory state.
+    let resume_after = params.include_memory && vm.status == VmStatus::Running;
+    if resume_after && let Err(e) = node_client.pause_vm(vm.id).await {
+        let _ = jobs::mark_failed(db_pool, job_id, &format!("Failed to pause VM: {e:#}")).await;
+        let _ = storage_objects::delete(db_pool, params.bundle_object_id).await;
+        return;
+    }
+
+    let result = write_bundle(db_pool, &node_client, &vm, &disks, &params).await;
+
+    if resume_after && let Err(e) = node_client.resume_vm(vm.id).await {
+        error!(vm_id = %vm.id, error = %e, "Failed to resume VM after export");
+    }
+
+    match result {
+        Ok((manifest_sha256, size_bytes)) => {
+            let _ =
+                storage_objects::update_size_bytes(db_pool, params.bundle_object_id, size_bytes)
+                    .await;
+            let result = serde_json::json!({
+                "storage_object_id": params.bundle_object_id,
+                "path": params.bundle_path,
+                "manifest_sha256": manifest_sha256,
+                "size_bytes": size_bytes,
+            });
+            let _ = jobs::mark_completed(db_pool, job_id, Some(result)).await;
+            info!(vm_id = %vm.id, job_id = %job_id, "VM export completed");
+        }
+        Err(e) => {
+            error!(vm_id = %vm.id, job_id = %job_id, error = %e, "VM export failed");
+            // Failing before the pack leaves the memory snapshot staged next
+            // to the bundle; the pack itself never leaves a partial archive.
+            if params.include_memory {
+                let staging = memory_staging_dir(&params.bundle_path);
+                if let Err(e) = node_client.delete_path(&staging, true).await {
+                    warn!(path = %staging, error = %e, "Failed to remove staged memory snapshot");
+                }
+            }
+            let _ = storage_objects::delete(db_pool, params.bundle_object_id).await;
+            let _ = jobs::mark_failed(db_pool, job_id, &format!("{e:#}")).await;
+        }
+    }
+}
+
+/// Snapshot memory if requested, digest the disks in place and stream
+/// everything into the bundle archive, manifest first. Returns the manifest
+/// digest and the archive size.
+async fn write_bundle(
+    db_pool: &sqlx::PgPool,
+    node_client: &NodeClient,
+    vm: &Vm,
+    disks: &[ExportDisk],
+    params: &ExportParams,
+) -> anyhow::Result<(String, i64)> {
+    use anyhow::Context;
+
+    let job_id = params.job_id;
+    let bundle = &params.bundle_path;
+    let mut members = Vec::new();
+
+    let memory_snapshot = if params.include_memory {
+        let memory_path = memory_staging_dir(bundle);
+        node_client
+            .snapshot_vm(vm.id, &format!("file://{memory_path}"))
+            .await
+            .context("Failed to snapshot VM memory")?;
+        let files: Vec<BundleFile> = node_client
+            .checksum_files(&memory_path)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        members.push(ArchiveMember {
+            name: MEMORY_DIR.to_string(),
+            source_path: memory_path,
+            remove_source: true,
+            ..Default::default()
+        });
+        Some(BundleMemorySnapshot {
+            dir: MEMORY_DIR.to_string(),
+            files,
+        })
+    } else {
+        None
+    };
+    let _ = jobs::update_progress(db_pool, job_id, 10).await;
+
+    let mut bundle_disks = Vec::with_capacity(disks.len());
+    for (i, d) in disks.iter().enumerate() {
+        let file = bundle_disk_file(&d.disk.logical_name);
+        let checksum = node_client
+            .checksum_files(&d.path)
+            .await
+            .with_context(|| format!("Failed to digest disk {}", d.disk.logical_name))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("node returned no checksum for {}", d.path))?;
+        members.push(ArchiveMember {
+            name: file.clone(),
+            source_path: d.path.clone(),
+            ..Default::default()
+        });
+        bundle_disks.push(BundleDisk {
+            disk: d.disk.clone(),
+            file,
+            size_bytes: d.object.size_bytes,
+            sha256: checksum.sha256,
+            source_storage_pool_id: d.pool.id,
+            source_storage_pool_name: d.pool.name.clone(),
+        });
+        let pct = 10 + ((i + 1) * 40 / disks.len()) as i32;
+        let _ = jobs::update_progress(db_pool, job_id, pct).await;
+    }
+
+    let mut nics = Vec::new();
+    for interface in network_interfaces::list_by_vm(db_pool, vm.id).await? {
+        let network_name = match interface.network_id {
+            Some(network_id) => Some(networks::get(db_pool, network_id).await?.name),
+            None => None,
+        };
+        nics.push(BundleNic {
+            interface,
+            network_name,
+        });
+    }
+
+    let manifest = BundleManifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        exported_at: Utc::now(),
+        source_vm_id: vm.id,
+        vm: vm.clone(),
+        nics,
+        disks: bundle_disks,
+        memory_snapshot,
+    };
+    let manifest_bytes = serde_json::to_vec_pretty(&manifest)?;
+    let manifest_sha256 = sha256_hex(&manifest_bytes);
+    let checksums = render_checksums(&manifest, &manifest_sha256).into_bytes();
+
+    // The manifest goes first so imports can read it without scanning past
+    // the disk images.
+    members.splice(
+        0..0,
+        [
+            ArchiveMember {
+                name: MANIFEST_FILE.to_string(),
+                content: manifest_bytes,
+                ..Default::default()
+            },
+            ArchiveMember {
+                name: CHECKSUMS_FILE.to_string(),
+                content: checksums,
+                ..Default::default()
+            },
+        ],
+    );
+    let size_bytes = node_client
+        .pack_archive(bundle, members)
+        .await
+        .context("Failed to pack bundle")?;
+
+    Ok((manifest_sha256, size_bytes))
+}
+
+/// Look up a mapping by source ID first, then by source name.
+fn lookup_mapping(map: &HashMap<String, Uuid>, id: Uuid, name: Option<&str>) -> Option<Uuid> {
+    map.get(&id.to_string())
+        .or_else(|| name.and_then(|n| map.get(n)))
+        .copied()
+}
+
+async fn resolve_target_network(
+    env: &App,
+    req: &ImportVmRequest,
+    nic: &BundleNic,
+    source_network_id: Uuid,
+) -> Result<Uuid> {
+    if let Some(id) = lookup_mapping(
+        &req.network_map,
+        source_network_id,
+        nic.network_name.as_deref(),
+    ) {
+        return Ok(networks::get(env.pool(), id).await?.id);
+    }
+    if let Some(name) = nic.network_name.as_deref()
+        && let [network] = networks::list(env.pool(), Some(name)).await?.as_slice()
+    {
+        return Ok(network.id);
+    }
+    Err(crate::errors::Error::UnprocessableEntity(format!(
+        "no target network for NIC {} (source network {} '{}'); add it to network_map",
+        nic.interface.device_id,
+        source_network_id,
+        nic.network_name.as_deref().unwrap_or_default()
+    )))
+}
+
+async fn resolve_target_pool(
+    env: &App,
+    req: &ImportVmRequest,
+    disk: &BundleDisk,
+    bundle_pool: &StoragePool,
+    host: &Host,
+) -> Result<StoragePool> {
+    let pool_id = lookup_mapping(
+        &req.storage_pool_map,
+        disk.source_storage_pool_id,
+        Some(&disk.source_storage_pool_name),
+    )
+    .or(req.default_storage_pool_id)
+    .unwrap_or(bundle_pool.id);
+    let pool = storage_pools::get(env.pool(), pool_id).await?;
+    ensure_bundle_pool(&pool)?;
+    if !storage_pools::host_has_pool(env.pool(), host.id, pool.id).await? {
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "storage pool '{}' for disk {} is not attached to host {}, which reads the bundle",
+            pool.name, disk.disk.logical_name, host.name
+        )));
+    }
+    Ok(pool)
+}
+
+/// Pool for the imported memory snapshot: the first disk's pool, else the
+/// explicit default or bundle pool, else any active non-OverlayBD pool, as
+/// for a regular snapshot. It must be attached to the host reading the bundle.
+async fn resolve_memory_pool(
+    env: &App,
+    req: &ImportVmRequest,
+    target_pools: &[StoragePool],
+    bundle_pool: &StoragePool,
+    host: &Host,
+) -> Result<Uuid> {
+    let preferred = target_pools
+        .first()
+        .map(|p| p.id)
+        .or(req.default_storage_pool_id)
+        .unwrap_or(bundle_pool.id);
+    let pool_id = storage_pools::pick_active_non_overlaybd(env.pool(), Some(preferred))
+        .await?
+        .ok_or_else(|| {
+            crate::errors::Error::UnprocessableEntity(
+                "no suitable storage pool available for the memory snapshot".into(),
+            )
+        })?;
+    if !storage_pools::host_has_pool(env.pool(), host.id, pool_id).await? {
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "storage pool {pool_id} for the memory snapshot is not attached to host {}, which reads the bundle",
+            host.name
+        )));
+    }
+    Ok(pool_id)
+}
+
+/// Build the `NewVm` for an import from the manifest's VM row.
+fn new_vm_from_manifest(
+    manifest: &BundleManifest,
+    name: String,
+    boot_source_id: Option<Uuid>,
+    networks: Vec<NewVmNetwork>,
+) -> NewVm {
+    let vm = &manifest.vm;
+    NewVm {
+        name,
+        tags: Some(vm.tags.clone()),
+        vm_template_id: None,
+        instance_type_id: None,
+        hypervisor: Some(vm.hypervisor.clone()),
+        architecture: vm
+            .config
+            .get("architecture")
+            .and_then(|v| v.as_str())
+            .map(str::to_string),
+        boot_vcpus: Some(vm.boot_vcpus),
+        max_vcpus: Some(vm.max_vcpus),
+        cpu_topology: vm.cpu_topology.clone(),
+        kvm_hyperv: Some(vm.kvm_hyperv),
+        memory_size: Some(vm.memory_size),
+        memory_hotplug_size: vm.memory_hotplug_size,
+        memory_mergeable: Some(vm.memory_mergeable),
+        memory_shared: Some(vm.memory_shared),
+        memory_hugepages: Some(vm.memory_hugepages),
+        memory_hugepage_size: vm.memory_hugepage_size,
+        memory_prefault: Some(vm.memory_prefault),
+        memory_thp: Some(vm.memory_thp),
+        boot_source_id,
+        root_disk_object_id: None,
+        boot_mode: Some(vm.boot_mode.clone()),
+        description: vm.description.clone(),
+        image_ref: None,
+        cloud_init_user_data: vm.cloud_init_user_data.clone(),
+        cloud_init_meta_data: vm.cloud_init_meta_data.clone(),
+        cloud_init_network_config: vm.cloud_init_network_config.clone(),
+        network_id: None,
+        networks: (!networks.is_empty()).then_some(networks),
+        security_group_ids: None,
+        accelerator_config: None,
+        numa_config: None,
+        persistent_upper_pool_id: None,
+        overlaybd_disk_size_bytes: None,
+        root_disk_mode: None,
+        root_disk_pool_id: None,
+        placement_policy: vm.placement_policy.clone(),
+        guest_agent: Some(vm.guest_agent),
+        config: vm.config.clone(),
+    }
+}
+
+fn new_vm_network(
+    nic: &NetworkInterface,
+    network_id: Option<Uuid>,
+    ip: Option<String>,
+) -> NewVmNetwork {
+    NewVmNetwork {
+        id: nic.device_id.clone(),
+        network_id,
+        mac: nic.mac_address.clone(),
+        // TAP names are host-local; let the node pick a fresh one.
+        tap: None,
+        ip,
+        mtu: Some(nic.mtu),
+        interface_type: Some(nic.interface_type.clone()),
+        num_queues: Some(nic.num_queues),
+        queue_size: Some(nic.queue_size),
+        offload_tso: Some(nic.offload_tso),
+        offload_ufo: Some(nic.offload_ufo),
+        offload_csum: Some(nic.offload_csum),
+        pci_segment: Some(nic.pci_segment),
+        iommu: Some(nic.iommu),
+        ..Default::default()
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/vms/import",
+    request_body = ImportVmRequest,
+    responses(
+        (status = 202, description = "Import job accepted", body = ImportVmResponse),
+        (status = 404, description = "Bundle, pool, network or host not found"),
+        (status = 409, description = "A VM with this name already exists"),
+        (status = 422, description = "Invalid bundle or unmapped networks/pools"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "vms"
+)]
+#[instrument(skip(env))]
+pub async fn import(
+    Extension(env): Extension<App>,
+    Json(req): Json<ImportVmRequest>,
+) -> Result<axum::response::Response> {
+    // Locate the bundle and a host that can read it.
+    let (bundle_pool, bundle_path) = match (req.storage_object_id, req.storage_pool_id, &req.path) {
+        (Some(object_id), None, None) => {
+            let object = storage_objects::get(env.pool(), object_id).await?;
+            if object.object_type != StorageObjectType::VmBundle {
+                return Err(crate::errors::Error::UnprocessableEntity(format!(
+                    "storage object {object_id} is not a VM bundle"
+                )));
+            }
+            let path = storage_objects::get_path_from_config(&object.config)
+                .ok_or(crate::errors::Error::InternalServerError)?;
+            (
+                storage_pools::get(env.pool(), object.storage_pool_id).await?,
+                path,
+            )
+        }
+        (None, Some(pool_id), Some(path)) => {
+            let pool = storage_pools::get(env.pool(), pool_id).await?;
+            ensure_bundle_pool(&pool)?;
+            let path = bundle_path_in_pool(&pool, path)?;
+            (pool, path)
+        }
+        _ => {
+            return Err(crate::errors::Error::UnprocessableEntity(
+                "specify either storage_object_id or storage_pool_id and path".into(),
+            ));
+        }
+    };
+    ensure_bundle_pool(&bundle_pool)?;
+    let host_id = storage_pools::find_host_for_pool(env.pool(), bundle_pool.id)
+        .await?
+        .ok_or_else(|| {
+            crate::errors::Error::UnprocessableEntity(format!(
+                "storage pool '{}' is not attached to any host",
+                bundle_pool.name
+            ))
+        })?;
+    let host = hosts::require_by_id(env.pool(), host_id).await?;
+    let node_client = NodeClient::new(&host.address, host.port as u16);
+
+    let manifest_bytes = node_client
+        .read_archive_file(&bundle_path, MANIFEST_FILE)
+        .await
+        .map_err(|e| match e.downcast::<crate::errors::Error>() {
+            Ok(err) => err,
+            Err(err) => {
+                error!(error = %err, "Failed to read bundle manifest");
+                crate::errors::Error::InternalServerError
+            }
+        })?;
+    let manifest: BundleManifest = serde_json::from_slice(&manifest_bytes).map_err(|e| {
+        crate::errors::Error::UnprocessableEntity(format!("invalid bundle manifest: {e}"))
+    })?;
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "bundle format version {} is newer than supported version {}",
+            manifest.format_version, BUNDLE_FORMAT_VERSION
+        )));
+    }
+    for disk in &manifest.disks {
+        validate_relative_path(&disk.file)?;
+    }
+    if let Some(memory) = &manifest.memory_snapshot {
+        validate_relative_path(&memory.dir)?;
+        for file in &memory.files {
+            validate_relative_path(&file.name)?;
+        }
+    }
+
+    // Remap networks and pick addresses.
+    let mut new_networks = Vec::with_capacity(manifest.nics.len());
+    for nic in &manifest.nics {
+        let Some(source_network_id) = nic.interface.network_id else {
+            new_networks.push(new_vm_network(&nic.interface, None, None));
+            continue;
+        };
+        let network_id = resolve_target_network(&env, &req, nic, source_network_id).await?;
+        let preserved_ip = match nic.interface.ip_address.as_deref() {
+            Some(ip) if req.preserve_ips => networks::get_allocation(env.pool(), network_id, ip)
+                .await?
+                .is_none()
+                .then(|| ip.to_string()),
+            _ => None,
+        };
+        let ip = match preserved_ip {
+            Some(ip) => ip,
+            None => networks::next_available_ip(env.pool(), network_id)
+                .await?
+                .ok_or_else(|| {
+                    crate::errors::Error::UnprocessableEntity(format!(
+                        "no available IPs in network {network_id}"
+                    ))
+                })?,
+        };
+        new_networks.push(new_vm_network(&nic.interface, Some(network_id), Some(ip)));
+    }
+
+    // Remap storage pools before creating anything.
+    let mut target_pools = Vec::with_capacity(manifest.disks.len());
+    for disk in &manifest.disks {
+        target_pools.push(resolve_target_pool(&env, &req, disk, &bundle_pool, &host).await?);
+    }
+    let memory_pool_id = match manifest.memory_snapshot {
+        Some(_) => Some(resolve_memory_pool(&env, &req, &target_pools, &bundle_pool, &host).await?),
+        None => None,
+    };
+
+    let name = req.name.clone().unwrap_or_else(|| manifest.vm.name.clone());
+    let resolved = vms::resolve_create_request(
+        env.pool(),
+        new_vm_from_manifest(&manifest, name.clone(), req.boot_source_id, new_networks),
+    )
+    .await?;
+    let vm_id = create_vm_internal(&env, resolved).await?;
+    // Disks are copied on the host that reads the bundle, so the VM must live there.
+    vms::update_host_id(env.pool(), vm_id, host.id).await?;
+    vms::update_status(env.pool(), vm_id, VmStatus::Pending).await?;
+
+    let mut objects = Vec::new();
+    let created = create_import_records(
+        &env,
+        vm_id,
+        &manifest,
+        &target_pools,
+        memory_pool_id,
+        &mut objects,
+    )
+    .await;
+    let (disks, memory) = match created {
+        Ok(created) => created,
+        Err(e) => {
+            discard_import(env.pool(), &node_client, vm_id, &objects).await;
+            return Err(e);
+        }
+    };
+
+    let job = jobs::create(
+        env.pool(),
+        NewJob {
+            job_type: JobType::VmImport,
+            description: Some(format!("Importing VM {name} from {bundle_path}")),
+            resource_id: Some(vm_id),
+            resource_type: Some(jobs::resource_types::VM.to_string()),
+        },
+    )
+    .await;
+    let job_id = match job {
+        Ok(job) => job.id,
+        Err(e) => {
+            discard_import(env.pool(), &node_client, vm_id, &objects).await;
+            return Err(e.into());
+        }
+    };
+
+    let db_pool = env.pool_arc();
+    let params = ImportParams {
+        vm_id,
+        job_id,
+        bundle_path: bundle_path.clone(),
+        disks,
+        memory,
+        objects,
+    };
+    tokio::spawn(async move {
+        run_vm_import(&db_pool, &node_client, &params).await;
+    });
+
+    Ok(ApiResponse {
+        data: ImportVmResponse { vm_id, job_id },
+        code: StatusCode::ACCEPTED,
+    }
+    .with_audit_event(AuditEvent {
+        action: AuditAction::Import,
+        resource_type: AuditResourceType::Vm,
+        resource_id: vm_id,
+        resource_name: Some(name),
+        metadata: Some(serde_json::json!({
+            "bundle_path": bundle_path,
+            "source_vm_id": manifest.source_vm_id,
+        })),
+    }))
+}
+
+/// A bundle file to copy into a storage object during import.
+struct ImportCopy {
+    source: String,
+    dest: String,
+    sha256: String,
+}
+
+struct ImportMemory {
+    snapshot_id: Uuid,
+    copies: Vec<ImportCopy>,
+}
+
+/// A storage object created for an import and the node path its data goes to.
+struct ImportedObject {
+    id: Uuid,
+    path: String,
+    /// The memory snapshot is a directory; disks are single files.
+    is_dir: bool,
+}
+
+struct ImportParams {
+    vm_id: Uuid,
+    job_id: Uuid,
+    bundle_path: String,
+    disks: Vec<ImportCopy>,
+    memory: Option<ImportMemory>,
+    objects: Vec<ImportedObject>,
+}
+
+/// Create the storage objects, `vm_disks` rows and (optionally) the snapshot
+/// row that the import job will fill in. Every storage object is recorded in
+/// `objects` as soon as it exists, so a failure part way can be rolled back.
+async fn create_import_records(
+    env: &App,
+    vm_id: Uuid,
+    manifest: &BundleManifest,
+    target_pools: &[StoragePool],
+    memory_pool_id: Option<Uuid>,
+    objects: &mut Vec<ImportedObject>,
+) -> Result<(Vec<ImportCopy>, Option<ImportMemory>)> {
+    let mut disks = Vec::with_capacity(manifest.disks.len());
+    for (disk, pool) in manifest.disks.iter().zip(target_pools) {
+        let object = storage_objects::create_returning(
+            env.pool(),
+            NewStorageObject {
+                name: format!("{}-{}", manifest.vm.name, disk.disk.logical_name),
+                storage_pool_id: Some(pool.id),
+                object_type: StorageObjectType::Disk,
+                size_bytes: disk.size_bytes,
+                config: serde_json::Value::Null,
+                parent_id: None,
+            },
+        )
+        .await?;
+        let dest = storage_objects::get_path_from_config(&object.config)
+            .ok_or(crate::errors::Error::InternalServerError)?;
+        objects.push(ImportedObject {
+            id: object.id,
+            path: dest.clone(),
+            is_dir: false,
+        });
+        let d = &disk.disk;
+        vm_disks::create(
+            env.pool(),
+            &NewVmDisk {
+                vm_id,
+                storage_object_id: Some(object.id),
+                logical_name: d.logical_name.clone(),
+                device_path: d.device_path.clone(),
+                boot_order: d.boot_order,
+                read_only: Some(d.read_only),
+                direct: Some(d.direct),
+                num_queues: Some(d.num_queues),
+                queue_size: Some(d.queue_size),
+                rate_limiter: d.rate_limiter.clone(),
+                rate_limit_group: d.rate_limit_group.clone(),
+                pci_segment: Some(d.pci_segment),
+                serial_number: d.serial_number.clone(),
+                config: d.config.clone(),
+                ..Default::default()
+            },
+        )
+        .await?;
+        disks.push(ImportCopy {
+            source: disk.file.clone(),
+            dest,
+            sha256: disk.sha256.clone(),
+        });
+    }
+
+    let memory = match &manifest.memory_snapshot {
+        Some(memory) => {
+            let object = storage_objects::create_returning(
+                env.pool(),
+                NewStorageObject {
+                    name: format!("{}-imported-memory", manifest.vm.name),
+                    storage_pool_id: memory_pool_id,
+                    object_type: StorageObjectType::Snapshot,
+                    size_bytes: memory.files.iter().map(|f| f.size_bytes).sum(),
+                    config: serde_json::Value::Null,
+                    parent_id: None,
+                },
+            )
+            .await?;
+            let dir = storage_objects::get_path_from_config(&object.config)
+                .ok_or(crate::errors::Error::InternalServerError)?;
+            objects.push(ImportedObject {
+                id: object.id,
+                path: dir.clone(),
+                is_dir: true,
+            });
+            let snapshot_id = snapshots::create(
+                env.pool(),
+                &NewSnapshot {
+                    vm_id,
+                    storage_object_id: object.id,
+                    name: format!("imported-{}", &manifest.source_vm_id.to_string()[..8]),
+                    upper_storage_object_id: None,
+                },
+            )
+            .await?;
+            Some(ImportMemory {
+                snapshot_id,
+                copies: memory
+                    .files
+                    .iter()
+                    .map(|f| ImportCopy {
+                        source: format!("{}/{}", memory.dir, f.name),
+                        dest: format!("{dir}/{}", f.name),
+                        sha256: f.sha256.clone(),
+                    })
+                    .collect(),
+            })
+        }
+        None => None,
+    };
+
+    Ok((disks, memory))
+}
+
+async fn run_vm_import(db_pool: &sqlx::PgPool, node_client: &NodeClient, params: &ImportParams) {
+    let (vm_id, job_id) = (params.vm_id, params.job_id);
+    if let Err(e) = jobs::mark_running(db_pool, job_id).await {
+        error!(job_id = %job_id, error = %e, "Failed to mark import job as running");
+        return;
+    }
+
+    match copy_import_files(db_pool, node_client, params).await {
+        Ok(()) => {
+            if let Some(memory) = &params.memory {
+                let _ =
+                    snapshots::update_status(db_pool, memory.snapshot_id, SnapshotStatus::Ready)
+                        .await;
+                match snapshots::get(db_pool, memory.snapshot_id).await {
+                    Ok(snapshot) => {
+                        if let Err(e) = record_ready_vm_backup(db_pool, &snapshot).await {
+                            warn!(vm_id = %vm_id, error = ?e, "Failed to record imported memory snapshot as a backup");
+                        }
+                    }
+                    Err(e) => {
+                        warn!(vm_id = %vm_id, error = %e, "Failed to load imported snapshot");
+                    }
+                }
+            }
+            let _ = vms::update_status(db_pool, vm_id, VmStatus::Created).await;
+            let result = serde_json::json!({
+                "vm_id": vm_id,
+                "disks": params.disks.len(),
+                "snapshot_id": params.memory.as_ref().map(|m| m.snapshot_id),
+            });
+            let _ = jobs::mark_completed(db_pool, job_id, Some(result)).await;
+            info!(vm_id = %vm_id, job_id = %job_id, "VM import completed");
+        }
+        Err(e) => {
+            error!(vm_id = %vm_id, job_id = %job_id, error = %e, "VM import failed");
+            let _ = jobs::mark_failed(db_pool, job_id, &format!("{e:#}")).await;
+            discard_import(db_pool, node_client, vm_id, &params.objects).await;
+        }
+    }
+}
+
+/// Roll back a failed import: drop the VM (its `vm_disks` and snapshot rows
+/// cascade with it), then each storage object along with whatever was
+/// already extracted into it. An object whose data can't be removed is kept
+/// so the leftover stays visible.
+async fn discard_import(
+    db_pool: &sqlx::PgPool,
+    node_client: &NodeClient,
+    vm_id: Uuid,
+    objects: &[ImportedObject],
+) {
+    let _ = vms::delete(db_pool, vm_id).await;
+    for object in objects {
+        if let Err(e) = node_client.delete_path(&object.path, object.is_dir).await {
+            warn!(
+                storage_object_id = %object.id,
+                path = %object.path,
+                error = %e,
+                "Failed to remove imported data; keeping its storage object"
+            );
+            continue;
+        }
+        let _ = storage_objects::delete(db_pool, object.id).await;
+    }
+}
+
+/// Extract each bundle file into place and verify it against the manifest digest.
+async fn copy_import_files(
+    db_pool: &sqlx::PgPool,
+    node_client: &NodeClient,
+    params: &ImportParams,
+) -> anyhow::Result<()> {
+    use anyhow::Context;
+
+    let copies: Vec<&ImportCopy> = params
+        .disks
+        .iter()
+        .chain(params.memory.iter().flat_map(|m| m.copies.iter()))
+        .collect();
+
+    for (i, copy) in copies.iter().enumerate() {
+        let actual = node_client
+            .extract_archive_member(&params.bundle_path, &copy.source, &copy.dest)
+            .await
+            .with_context(|| format!("Failed to extract {}", copy.source))?;
+        if actual.sha256 != copy.sha256 {
+            anyhow::bail!(
+                "checksum mismatch for {}: manifest {} != actual {}",
+                copy.source,
+                copy.sha256,
+                actual.sha256
+            );
+        }
+        let pct = ((i + 1) * 100 / copies.len()) as i32;
+        let _ = jobs::update_progress(db_pool, params.job_id, pct.min(99)).await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_relative_path_rejects_escapes() {
+        assert!(validate_relative_path("disks/disk0.raw").is_ok());
+        assert!(validate_relative_path("/etc/shadow").is_err());
+        assert!(validate_relative_path("disks/../../etc/shadow").is_err());
+        assert!(validate_relative_path("").is_err());
+    }
+
+    #[test]
+    fn lookup_mapping_prefers_id_over_name() {
+        let by_id = Uuid::new_v4();
+        let by_name = Uuid::new_v4();
+        let source = Uuid::new_v4();
+        let map = HashMap::from([
+            (source.to_string(), by_id),
+            ("default".to_string(), by_name),
+        ]);
+
+        assert_eq!(lookup_mapping(&map, source, Some("default")), Some(by_id));
+        assert_eq!(
+            lookup_mapping(&map, Uuid::new_v4(), Some("default")),
+            Some(by_name)
+        );
+        assert_eq!(lookup_mapping(&map, Uuid::new_v4(), None), None);
+    }
+
+    #[test]
+    fn bundle_path_in_pool_rejects_paths_outside_the_pool() {
+        let pool = StoragePool {
+            id: Uuid::new_v4(),
+            name: "bundles".to_string(),
+            pool_type: StoragePoolType::Local,
+            status: storage_pools::StoragePoolStatus::Active,
+            config: serde_json::json!({"path": "/srv/qarax/"}),
+            capacity_bytes: None,
+            allocated_bytes: None,
+            host_capacity: Vec::new(),
+        };
+
+        assert!(bundle_path_in_pool(&pool, "/srv/qarax/web.tar").is_ok());
+        assert!(bundle_path_in_pool(&pool, "/srv/qarax/in/web.tar").is_ok());
+        assert!(bundle_path_in_pool(&pool, "/etc/shadow").is_err());
+        assert!(bundle_path_in_pool(&pool, "/srv/qarax").is_err());
+        assert!(bundle_path_in_pool(&pool, "/srv/qarax-other/web.tar").is_err());
+        assert!(bundle_path_in_pool(&pool, "/srv/qarax/../etc/shadow").is_err());
+        assert!(bundle_path_in_pool(&pool, "/srv/qarax//web.tar").is_err());
+    }
+}
@@ -22,11 +22,14 @@ use crate::{
         CreateVmRequest, NodeClient, net_configs_from_db,
         node::{
             ConsoleConfig as ProtoConsoleConfig, ConsoleMode as ProtoConsoleMode, CpuPinning,
-            DiskConfig, NetConfig, NumaPlacement, RngConfig as ProtoRngConfig, VhostMode,
-            VsockConfig,
+            DiskConfig, NetConfig, NumaPlacement, RngConfig as ProtoRngConfig,
+            StorageImageOperation, UpperLayerCopy, VhostMode, VsockConfig,
         },
     },
-    handlers::audit::{AuditEvent, AuditEventExt},
+    handlers::{
+        audit::{AuditEvent, AuditEventExt},
+        storage_pool::handler::admit_pool_allocation,
+    },
     model::{
         audit_log::{AuditAction, AuditResourceType},
         backups,
@@ -36,7 +39,7 @@ use crate::{
         jobs::{self, JobType, NewJob},
         lifecycle_hooks,
         network_interfaces::{self, NetworkInterface},
-        networks,
+        networks, registry_credentials,
         sandboxes::{self, SandboxStatus},
         security_groups, snapshots,
         snapshots::{NewSnapshot, Snapshot, SnapshotStatus},
@@ -48,7 +51,7 @@ use crate::{
         vm_templates::{self, CreateVmTemplateFromVmRequest},
         vms::{
             self, BootMode, ExecVmRequest, ExecVmResponse, Hypervisor, NewVm, NewVmNetwork,
-            ResolvedNewVm, Vm, VmStatus,
+            ResolvedNewVm, RootDiskMode, Vm, VmStatus,
         },
     },
     network_policy,
@@ -157,7 +160,8 @@ async fn root_disk_scheduling_requirements(
     let storage_pool_id = match pool.pool_type {
         storage_pools::StoragePoolType::Local
         | storage_pools::StoragePoolType::Nfs
-        | storage_pools::StoragePoolType::Block => Some(pool.id),
+        | storage_pools::StoragePoolType::Block
+        | storage_pools::StoragePoolType::Rbd => Some(pool.id),
         storage_pools::StoragePoolType::OverlayBd => None,
     };
 
@@ -405,7 +409,9 @@ fn storage_object_requires_exclusive_attachment(
 ) -> bool {
     matches!(
         storage_pool.pool_type,
-        storage_pools::StoragePoolType::Local | storage_pools::StoragePoolType::Nfs
+        storage_pools::StoragePoolType::Local
+            | storage_pools::StoragePoolType::Nfs
+            | storage_pools::StoragePoolType::Rbd
     ) && matches!(
         object.object_type,
         StorageObjectType::Disk | StorageObjectType::Snapshot
@@ -573,7 +579,7 @@ async fn delete_nic_and_release_ip(pool: &sqlx::PgPool, nic: &NetworkInterface)
 }
 
 /// Resolve the host that a VM is assigned to, for routing subsequent operations.
-async fn host_for_vm(env: &App, vm_id: Uuid) -> Result<Host> {
+pub(super) async fn host_for_vm(env: &App, vm_id: Uuid) -> Result<Host> {
     let vm = vms::get(env.pool(), vm_id).await?;
     let host_id = vm.host_id.ok_or_else(|| {
         crate::errors::Error::UnprocessableEntity("VM has no assigned host".into())
@@ -679,6 +685,10 @@ pub async fn preflight_image(
         }
     };
 
+    let logins =
+        registry_credentials::logins_for_image(env.pool(), &body.image_ref, Some(&registry_url))
+            .await?;
+
     let node_client = NodeClient::new(&host.address, host.port as u16);
     let response = node_client
         .preflight_image(
@@ -686,6 +696,7 @@ pub async fn preflight_image(
             Some(&registry_url),
             &architecture,
             &boot_mode.to_string(),
+            &logins,
         )
         .await
         .map_err(|e| {
@@ -792,6 +803,8 @@ pub async fn create(
     Json(vm): Json<NewVm>,
 ) -> Result<axum::response::Response> {
     let vm = vms::resolve_create_request(env.pool(), vm).await?;
+    validate_overlaybd_disk_size(&vm)?;
+    validate_root_disk_mode(&vm)?;
 
     // If an OCI image_ref is provided, use the async job path
     if vm.image_ref.is_some() {
@@ -855,7 +868,7 @@ pub(crate) async fn create_vm_internal(env: &App, vm: ResolvedNewVm) -> Result<U
     }
 
     for net in vm.networks.as_deref().unwrap_or(&[]) {
-        network_interfaces::create(&mut tx, id, net).await?;
+        create_network_interface_tx(&mut tx, env.pool(), id, net).await?;
     }
     if let Some(network_id) = vm.network_id {
         create_managed_network_interface_tx(&mut tx, env.pool(), id, network_id).await?;
@@ -873,6 +886,116 @@ pub(crate) async fn create_vm_internal(env: &App, vm: ResolvedNewVm) -> Result<U
     Ok(id)
 }
 
+/// Virtual size of an OverlayBD disk whose upper layer was created without an
+/// explicit size.
+const OVERLAYBD_DEFAULT_DISK_SIZE_BYTES: i64 = 64 * GIB;
+const GIB: i64 = 1024 * 1024 * 1024;
+
+/// The upper layer is created by `overlaybd-create`, which sizes it in whole GiB.
+fn validate_overlaybd_disk_size(vm: &ResolvedNewVm) -> Result<()> {
+    let Some(size) = vm.overlaybd_disk_size_bytes else {
+        return Ok(());
+    };
+    if vm.image_ref.is_none() {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "overlaybd_disk_size_bytes requires image_ref".into(),
+        ));
+    }
+    if size <= 0 || size % GIB != 0 {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "overlaybd_disk_size_bytes must be a positive multiple of 1 GiB".into(),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_root_disk_mode(vm: &ResolvedNewVm) -> Result<()> {
+    let invalid = |msg: &str| Err(crate::errors::Error::UnprocessableEntity(msg.into()));
+    match vm.root_disk_mode {
+        RootDiskMode::Overlaybd if vm.root_disk_pool_id.is_some() => {
+            invalid("root_disk_pool_id requires root_disk_mode=full_clone")
+        }
+        RootDiskMode::Overlaybd => Ok(()),
+        RootDiskMode::FullClone if vm.image_ref.is_none() => {
+            invalid("root_disk_mode=full_clone requires image_ref")
+        }
+        RootDiskMode::FullClone if vm.root_disk_pool_id.is_none() => {
+            invalid("root_disk_mode=full_clone requires root_disk_pool_id")
+        }
+        RootDiskMode::FullClone if vm.persistent_upper_pool_id.is_some() => {
+            invalid("persistent_upper_pool_id cannot be combined with root_disk_mode=full_clone")
+        }
+        RootDiskMode::FullClone => Ok(()),
+    }
+}
+
+/// Bytes a full-clone root disk must hold: what the image unpacks to. The
+/// converted size stands in for nodes that do not report it, and is never
+/// more than the unpacked content anyway.
+fn full_clone_content_size(import: &crate::grpc_client::node::ImportOverlayBdResponse) -> i64 {
+    import.content_size_bytes.max(import.size_bytes)
+}
+
+/// Size of a full-clone root disk when the caller gives none: twice the
+/// image's unpacked content, at least 4 GiB, rounded up to whole GiB.
+fn full_clone_default_size(content_size_bytes: i64) -> i64 {
+    let size = content_size_bytes.saturating_mul(2).max(4 * GIB);
+    (size + GIB - 1) / GIB * GIB
+}
+
+/// Check that a pool a VM writes files to from its host is Local or NFS,
+/// usable, and attached to that host. `field` names the request field in errors.
+async fn validate_host_file_pool(
+    env: &App,
+    host_id: Uuid,
+    pool_id: Uuid,
+    field: &str,
+) -> Result<storage_pools::StoragePool> {
+    let pool = storage_pools::get(env.pool(), pool_id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => crate::errors::Error::UnprocessableEntity(format!(
+                "{} {} not found",
+                field, pool_id
+            )),
+            _ => {
+                tracing::error!("Failed to get storage pool {}: {}", pool_id, e);
+                crate::errors::Error::InternalServerError
+            }
+        })?;
+
+    if !pool.status.is_usable() {
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "{} {} is not active",
+            field, pool_id
+        )));
+    }
+
+    match pool.pool_type {
+        storage_pools::StoragePoolType::Local | storage_pools::StoragePoolType::Nfs => {}
+        _ => {
+            return Err(crate::errors::Error::UnprocessableEntity(format!(
+                "{} must be a Local or NFS pool",
+                field
+            )));
+        }
+    }
+
+    let attached = storage_pools::host_has_pool(env.pool(), host_id, pool_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to check pool attachment: {}", e);
+            crate::errors::Error::InternalServerError
+        })?;
+    if !attached {
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "{} {} is not attached to the selected host",
+            field, pool_id
+        )));
+    }
+    Ok(pool)
+}
+
 /// Async path: pull OCI image and create VM in a background job, return 202 immediately.
 async fn create_with_image(env: App, vm: ResolvedNewVm) -> Result<axum::response::Response> {
     let image_ref = vm
@@ -976,51 +1099,29 @@ async fn create_with_image(env: App, vm: ResolvedNewVm) -> Result<axum::response
         }
     }
 
-    // Validate persistent_upper_pool_id if provided: must be Local/NFS, Active,
-    // and already attached to the selected host.
+    // Pools the VM keeps files on (a persistent upper layer or a full-clone
+    // root disk) must be Local/NFS, Active, attached to the selected host and
+    // have room left. A full clone without an explicit size is admitted again
+    // once the image's size is known.
     let persistent_upper_pool_id = vm.persistent_upper_pool_id;
+    let overlaybd_disk_size_bytes = vm.overlaybd_disk_size_bytes;
+    let root_disk_mode = vm.root_disk_mode;
+    let root_disk_pool_id = vm.root_disk_pool_id;
     if let Some(upper_pool_id) = persistent_upper_pool_id {
-        let upper_pool = storage_pools::get(env.pool(), upper_pool_id)
-            .await
-            .map_err(|e| match e {
-                sqlx::Error::RowNotFound => crate::errors::Error::UnprocessableEntity(format!(
-                    "persistent_upper_pool_id {} not found",
-                    upper_pool_id
-                )),
-                _ => {
-                    tracing::error!("Failed to get storage pool {}: {}", upper_pool_id, e);
-                    crate::errors::Error::InternalServerError
-                }
-            })?;
-
-        if upper_pool.status != storage_pools::StoragePoolStatus::Active {
-            return Err(crate::errors::Error::UnprocessableEntity(format!(
-                "persistent_upper_pool_id {} is not active",
-                upper_pool_id
-            )));
-        }
-
-        match upper_pool.pool_type {
-            storage_pools::StoragePoolType::Local | storage_pools::StoragePoolType::Nfs => {}
-            _ => {
-                return Err(crate::errors::Error::UnprocessableEntity(
-                    "persistent_upper_pool_id must be a Local or NFS pool".into(),
-                ));
-            }
-        }
-
-        let attached = storage_pools::host_has_pool(env.pool(), host.id, upper_pool_id)
-            .await
-            .map_err(|e| {
-                tracing::error!("Failed to check pool attachment: {}", e);
-                crate::errors::Error::InternalServerError
-            })?;
-        if !attached {
-            return Err(crate::errors::Error::UnprocessableEntity(format!(
-                "persistent_upper_pool_id {} is not attached to the selected host",
-                upper_pool_id
-            )));
-        }
+        let pool =
+            validate_host_file_pool(&env, host.id, upper_pool_id, "persistent_upper_pool_id")
+                .await?;
+        admit_pool_allocation(env.pool(), &pool, &host, 0).await?;
+    }
+    if let Some(pool_id) = root_disk_pool_id {
+        let pool = validate_host_file_pool(&env, host.id, pool_id, "root_disk_pool_id").await?;
+        admit_pool_allocation(
+            env.pool(),
+            &pool,
+            &host,
+            overlaybd_disk_size_bytes.unwrap_or(0),
+        )
+        .await?;
     }
 
     // Create VM row with PENDING status
@@ -1029,7 +1130,7 @@ async fn create_with_image(env: App, vm: ResolvedNewVm) -> Result<axum::response
 
     // Store network interfaces in the transaction
     for net in vm.networks.as_deref().unwrap_or(&[]) {
-        network_interfaces::create(&mut tx, vm_id, net).await?;
+        create_network_interface_tx(&mut tx, env.pool(), vm_id, net).await?;
     }
     if let Some(network_id) = vm.network_id {
         create_managed_network_interface_tx(&mut tx, env.pool(), vm_id, network_id).await?;
@@ -1053,7 +1154,10 @@ async fn create_with_image(env: App, vm: ResolvedNewVm) -> Result<axum::response
         env.pool(),
         NewJob {
             job_type: JobType::ImagePull,
-            description: Some(format!("Pulling image {}", image_ref)),
+            description: Some(match root_disk_mode {
+                RootDiskMode::Overlaybd => format!("Pulling image {}", image_ref),
+                RootDiskMode::FullClone => format!("Cloning image {} to raw disk", image_ref),
+            }),
             resource_id: Some(vm_id),
             resource_type: Some(jobs::resource_types::VM.to_string()),
         },
@@ -1086,17 +1190,36 @@ async fn create_with_image(env: App, vm: ResolvedNewVm) -> Result<axum::response
 
         let node_client = NodeClient::new(&host.address, host.port as u16);
 
-        run_overlaybd_create(
-            &node_client,
-            &db_pool,
-            vm_id,
-            job_id,
-            &image_ref,
-            &overlaybd_pool.config,
-            overlaybd_pool.id,
-            persistent_upper_pool_id,
-        )
-        .await;
+        match (root_disk_mode, root_disk_pool_id) {
+            (RootDiskMode::FullClone, Some(root_disk_pool_id)) => {
+                run_full_clone_create(
+                    &node_client,
+                    &db_pool,
+                    &host,
+                    vm_id,
+                    job_id,
+                    &image_ref,
+                    &overlaybd_pool.config,
+                    root_disk_pool_id,
+                    overlaybd_disk_size_bytes,
+                )
+                .await
+            }
+            _ => {
+                run_overlaybd_create(
+                    &node_client,
+                    &db_pool,
+                    vm_id,
+                    job_id,
+                    &image_ref,
+                    &overlaybd_pool.config,
+                    overlaybd_pool.id,
+                    persistent_upper_pool_id,
+                    overlaybd_disk_size_bytes,
+                )
+                .await
+            }
+        }
     });
 
     Ok(response)
@@ -1116,11 +1239,67 @@ async fn create_managed_network_interface_tx(
         ip: Some(ip),
         ..Default::default()
     };
-    network_interfaces::create(tx, vm_id, &net).await?;
+    create_network_interface_tx(tx, pool, vm_id, &net).await?;
 
     Ok(())
 }
 
+/// Create a network interface record. NICs on managed networks get a MAC
+/// that is fixed up front: the DHCP server reserves the allocated address for
+/// it, dual-stack networks derive the IPv6 address the guest will
+/// autoconfigure from it, and stretched networks tell remote hosts where it
+/// lives.
+async fn create_network_interface_tx(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    pool: &sqlx::PgPool,
+    vm_id: Uuid,
+    net: &NewVmNetwork,
+) -> Result<Uuid> {
+    let network = match net.network_id {
+        Some(network_id) => Some(networks::get(pool, network_id).await?),
+        None => None,
+    };
+    let Some(network) = network.filter(|network| network.network_type.as_deref() != Some("passt"))
+    else {
+        if !net.allowed_address_pairs.is_empty() {
+            return Err(crate::errors::Error::UnprocessableEntity(
+                "allowed_address_pairs need a managed network".into(),
+            ));
+        }
+        return Ok(network_interfaces::create(tx, vm_id, net).await?);
+    };
+
+    let net = NewVmNetwork {
+        mac: Some(
+            net.mac
+                .clone()
+                .unwrap_or_else(network_interfaces::random_mac),
+        ),
+        allowed_address_pairs: network_interfaces::normalize_allowed_address_pairs(
+            &net.allowed_address_pairs,
+        )
+        .map_err(crate::errors::Error::UnprocessableEntity)?,
+        ..net.clone()
+    };
+    let Some(subnet6) = network.subnet6.as_deref() else {
+        return Ok(network_interfaces::create(tx, vm_id, &net).await?);
+    };
+    let mac = net.mac.as_deref().unwrap_or_default();
+    let ip6 = networks::eui64_address(subnet6, mac).ok_or_else(|| {
+        crate::errors::Error::UnprocessableEntity(format!("invalid MAC address {mac:?}"))
+    })?;
+    let nic_id = network_interfaces::create(tx, vm_id, &net).await?;
+    let network_id = net.network_id.unwrap_or_default();
+    networks::try_allocate_ipv6_tx(tx, network_id, &ip6.to_string(), vm_id, nic_id)
+        .await?
+        .ok_or_else(|| {
+            crate::errors::Error::Conflict(format!(
+                "IPv6 address {ip6} is already allocated on network {network_id}"
+            ))
+        })?;
+    Ok(nic_id)
+}
+
 /// Helper to register explicitly provided static IPs in IPAM during VM creation.
 async fn register_static_ips_tx(
     tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
@@ -1136,44 +1315,218 @@ async fn register_static_ips_tx(
     Ok(())
 }
 
-/// Background task for the OverlayBD lazy block loading path.
+/// Convert and push `image_ref` into the OverlayBD pool's registry, returning
+/// the registry URL alongside the import result.
+async fn import_image_for_vm(
+    node_client: &NodeClient,
+    db_pool: &sqlx::PgPool,
+    image_ref: &str,
+    pool_config: &serde_json::Value,
+) -> std::result::Result<(String, crate::grpc_client::node::ImportOverlayBdResponse), String> {
+    let registry_url = OverlayBdPoolConfig::from_value(pool_config)
+        .map(|cfg| cfg.url)
+        .ok_or_else(|| "OverlayBD pool config missing 'url' field".to_string())?;
+
+    // Private registries need a stored login; the error text never carries it.
+    let logins = registry_credentials::logins_for_image(db_pool, image_ref, Some(&registry_url))
+        .await
+        .map_err(|e| format!("Failed to load registry credentials: {}", e))?;
+
+    let import_result = node_client
+        .import_overlaybd_image(image_ref, &registry_url, &logins)
+        .await
+        .map_err(|e| format!("Failed to import OverlayBD image: {}", e))?;
+    Ok((registry_url, import_result))
+}
+
+/// Background task for `root_disk_mode=full_clone`: import the image, copy it
+/// into a raw disk on a Local/NFS pool and attach that as the root disk.
 #[allow(clippy::too_many_arguments)]
-async fn run_overlaybd_create(
+async fn run_full_clone_create(
     node_client: &NodeClient,
     db_pool: &sqlx::PgPool,
+    host: &Host,
     vm_id: uuid::Uuid,
     job_id: uuid::Uuid,
     image_ref: &str,
     pool_config: &serde_json::Value,
-    storage_pool_id: uuid::Uuid,
-    persistent_upper_pool_id: Option<uuid::Uuid>,
+    root_disk_pool_id: uuid::Uuid,
+    disk_size_bytes: Option<i64>,
 ) {
-    // Extract registry URL from pool config
-    let registry_url = match OverlayBdPoolConfig::from_value(pool_config) {
-        Some(cfg) => cfg.url,
-        None => {
-            let msg = "OverlayBD pool config missing 'url' field".to_string();
+    let result = async {
+        let (registry_url, import_result) =
+            import_image_for_vm(node_client, db_pool, image_ref, pool_config)
+                .await
+                .map_err(anyhow::Error::msg)?;
+        let _ = jobs::update_progress(db_pool, job_id, 20).await;
+
+        let content_size = full_clone_content_size(&import_result);
+        let size_bytes = disk_size_bytes.unwrap_or_else(|| full_clone_default_size(content_size));
+        if size_bytes < content_size {
+            anyhow::bail!(
+                "disk_size_bytes {} is smaller than the image's unpacked size of {} bytes",
+                size_bytes,
+                content_size
+            );
+        }
+        let pool = storage_pools::get(db_pool, root_disk_pool_id).await?;
+        admit_pool_allocation(db_pool, &pool, host, size_bytes)
+            .await
+            .map_err(|e| anyhow::anyhow!("{e}"))?;
+        let (disk_so_id, disk_path) = clone_image_to_root_disk(
+            db_pool,
+            vm_id,
+            job_id,
+            node_client,
+            crate::grpc_client::node::OverlayBdDiskSource {
+                image_ref: import_result.image_ref.clone(),
+                registry_url,
+                upper_data: None,
+                upper_index: None,
+                size_bytes: Some(size_bytes as u64),
+                frozen_layers: Vec::new(),
+            },
+            root_disk_pool_id,
+            size_bytes,
+        )
+        .await?;
+        anyhow::Ok((disk_so_id, disk_path, import_result.digest))
+    }
+    .await;
+
+    match result {
+        Ok((disk_so_id, disk_path, digest)) => {
+            // The VM no longer refers to the image, so it cannot be committed.
+            if let Err(e) = vms::clear_image_ref(db_pool, vm_id).await {
+                warn!(vm_id = %vm_id, error = %e, "Failed to clear image_ref on VM");
+            }
+            let _ = vms::update_status(db_pool, vm_id, VmStatus::Created).await;
+            let result = serde_json::json!({
+                "storage_object_id": disk_so_id,
+                "path": disk_path,
+                "digest": digest,
+            });
+            let _ = jobs::mark_completed(db_pool, job_id, Some(result)).await;
+            tracing::info!(vm_id = %vm_id, job_id = %job_id, "VM creation job completed (full clone)");
+        }
+        Err(e) => {
+            let msg = format!("{:#}", e);
             tracing::error!(vm_id = %vm_id, job_id = %job_id, error = %msg);
-            let _ = jobs::mark_failed(db_pool, job_id, &msg).await;
+            // The job finishes last so whoever waits on it sees the VM's final state.
             let _ = vms::update_status(db_pool, vm_id, VmStatus::Unknown).await;
-            return;
+            let _ = jobs::mark_failed(db_pool, job_id, &msg).await;
         }
-    };
+    }
+}
 
-    // Step 1: Import (convert + push) image to local registry
-    let import_result = match node_client
-        .import_overlaybd_image(image_ref, &registry_url)
+/// Create a raw Disk storage object, fill it from the OverlayBD image and
+/// attach it to the VM as its boot disk. The storage object is removed again
+/// if any step fails.
+#[allow(clippy::too_many_arguments)]
+async fn clone_image_to_root_disk(
+    db_pool: &sqlx::PgPool,
+    vm_id: Uuid,
+    job_id: Uuid,
+    node_client: &NodeClient,
+    source: crate::grpc_client::node::OverlayBdDiskSource,
+    root_disk_pool_id: Uuid,
+    size_bytes: i64,
+) -> anyhow::Result<(Uuid, String)> {
+    use anyhow::Context;
+
+    let disk_so = storage_objects::create_returning(
+        db_pool,
+        NewStorageObject {
+            name: format!("rootfs-{}", vm_id),
+            storage_pool_id: Some(root_disk_pool_id),
+            object_type: StorageObjectType::Disk,
+            size_bytes,
+            config: serde_json::Value::Object(serde_json::Map::new()),
+            parent_id: None,
+        },
+    )
+    .await
+    .context("Failed to create root disk storage object")?;
+
+    let result = async {
+        let disk_path = storage_objects::get_path_from_config(&disk_so.config)
+            .ok_or_else(|| anyhow::anyhow!("Root disk storage object has no path in config"))?;
+
+        let mut last_pct = 20i32;
+        node_client
+            .create_disk_from_overlaybd(&disk_path, size_bytes, source, |bytes_written| {
+                let pct = 20 + ((bytes_written as f64 / size_bytes as f64) * 70.0) as i32;
+                let should_update = pct != last_pct;
+                if should_update {
+                    last_pct = pct;
+                }
+                let pool = db_pool.clone();
+                async move {
+                    if should_update {
+                        let _ = jobs::update_progress(&pool, job_id, pct).await;
+                    }
+                }
+            })
+            .await
+            .context("Failed to copy OverlayBD image to raw disk")?;
+
+        let existing_disks = vm_disks::list_by_vm(db_pool, vm_id)
+            .await
+            .context("Failed to list existing disks")?;
+        let logical_name = next_disk_id(&existing_disks);
+        vm_disks::create(
+            db_pool,
+            &NewVmDisk {
+                vm_id,
+                storage_object_id: Some(disk_so.id),
+                logical_name: logical_name.clone(),
+                device_path: format!("/dev/{}", logical_name),
+                boot_order: Some(0),
+                read_only: Some(false),
+                ..Default::default()
+            },
+        )
         .await
-    {
-        Ok(r) => r,
+        .context("Failed to persist root disk record")?;
+        anyhow::Ok(disk_path)
+    }
+    .await;
+
+    match result {
+        Ok(disk_path) => Ok((disk_so.id, disk_path)),
         Err(e) => {
-            let msg = format!("Failed to import OverlayBD image: {}", e);
-            tracing::error!(vm_id = %vm_id, job_id = %job_id, error = %msg);
-            let _ = jobs::mark_failed(db_pool, job_id, &msg).await;
-            let _ = vms::update_status(db_pool, vm_id, VmStatus::Unknown).await;
-            return;
+            if let Err(cleanup_err) = storage_objects::delete(db_pool, disk_so.id).await {
+                warn!(vm_id = %vm_id, storage_object_id = %disk_so.id, error = %cleanup_err, "Failed to clean up orphaned root disk storage object");
+            }
+            Err(e)
         }
-    };
+    }
+}
+
+/// Background task for the OverlayBD lazy block loading path.
+#[allow(clippy::too_many_arguments)]
+async fn run_overlaybd_create(
+    node_client: &NodeClient,
+    db_pool: &sqlx::PgPool,
+    vm_id: uuid::Uuid,
+    job_id: uuid::Uuid,
+    image_ref: &str,
+    pool_config: &serde_json::Value,
+    storage_pool_id: uuid::Uuid,
+    persistent_upper_pool_id: Option<uuid::Uuid>,
+    disk_size_bytes: Option<i64>,
+) {
+    // Step 1: Import (convert + push) image to local registry
+    let (registry_url, import_result) =
+        match import_image_for_vm(node_client, db_pool, image_ref, pool_config).await {
+            Ok(imported) => imported,
+            Err(msg) => {
+                tracing::error!(vm_id = %vm_id, job_id = %job_id, error = %msg);
+                let _ = jobs::mark_failed(db_pool, job_id, &msg).await;
+                let _ = vms::update_status(db_pool, vm_id, VmStatus::Unknown).await;
+                return;
+            }
+        };
 
     let _ = jobs::update_progress(db_pool, job_id, 50).await;
 
@@ -1228,7 +1581,7 @@ async fn run_overlaybd_create(
                 name: format!("overlaybd-upper-{}", vm_id),
                 storage_pool_id: Some(upper_pool_id),
                 object_type: storage_objects::StorageObjectType::OverlaybdUpper,
-                size_bytes: 0,
+                size_bytes: disk_size_bytes.unwrap_or(OVERLAYBD_DEFAULT_DISK_SIZE_BYTES),
                 config: serde_json::Value::Object(serde_json::Map::new()),
                 parent_id: None,
             },
@@ -1258,6 +1611,11 @@ async fn run_overlaybd_create(
         device_path: format!("/dev/{}", logical_name),
         boot_order: Some(0),
         read_only: Some(false),
+        // A persistent upper records its size on its storage object instead.
+        config: match (upper_so_id, disk_size_bytes) {
+            (None, Some(size)) => serde_json::json!({ "overlaybd_size_bytes": size }),
+            _ => serde_json::Value::Null,
+        },
         upper_storage_object_id: upper_so_id,
         ..Default::default()
     };
@@ -1477,6 +1835,12 @@ pub(crate) async fn start_vm_internal(env: &App, vm_id: Uuid) -> Result<Uuid> {
             return;
         }
 
+        // Before the guest boots, so its first DHCP request gets the
+        // allocated address.
+        if let Err(e) = network_policy::sync_network_dhcp_for_vm(&db_pool, vm_id).await {
+            tracing::warn!(vm_id = %vm_id, error = %e, "Failed to sync DHCP reservations");
+        }
+
         if let Err(e) = node_client.start_vm(vm_id).await {
             let msg = format!("start_vm failed: {:#}", e);
             tracing::error!(vm_id = %vm_id, job_id = %job_id, error = %msg);
@@ -1500,6 +1864,26 @@ pub(crate) async fn start_vm_internal(env: &App, vm_id: Uuid) -> Result<Uuid> {
             return;
         }
 
+        if let Err(e) = network_policy::sync_vm_nat_on_host(&db_pool, vm_id, host.id).await {
+            tracing::warn!(vm_id = %vm_id, error = %e, "Failed to sync floating IPs and port forwards");
+        }
+        if let Err(e) = network_policy::sync_network_dns_for_vm(&db_pool, vm_id).await {
+            tracing::warn!(vm_id = %vm_id, error = %e, "Failed to sync network DNS");
+        }
+        // Rules elsewhere that name this VM's security groups as their peer
+        // need its addresses before it can reach them.
+        if let Err(e) =
+            network_policy::sync_security_group_peers_for_vm(&env_for_start, vm_id).await
+        {
+            tracing::warn!(vm_id = %vm_id, error = %e, "Failed to sync security-group peers");
+        }
+
+        // The VM's NICs are now reachable on this host; remote hosts on a
+        // stretched network need to know.
+        if let Err(e) = network_policy::sync_stretched_networks_for_vm(&db_pool, vm_id).await {
+            tracing::warn!(vm_id = %vm_id, error = %e, "Failed to sync stretched networks");
+        }
+
         let _ = vms::update_status(&db_pool, vm_id, VmStatus::Running).await;
         let _ = jobs::mark_completed(&db_pool, job_id, None).await;
         #[cfg(feature = "otel")]
@@ -1765,7 +2149,7 @@ pub struct CreateSnapshotRequest {
     pub storage_pool_id: Option<Uuid>,
 }
 
-async fn record_ready_vm_backup(pool: &PgPool, snapshot: &Snapshot) -> Result<Backup> {
+pub(super) async fn record_ready_vm_backup(pool: &PgPool, snapshot: &Snapshot) -> Result<Backup> {
     backups::create(
         pool,
         &NewBackup {
@@ -1787,12 +2171,31 @@ pub(crate) async fn create_vm_snapshot(
     body: &CreateSnapshotRequest,
 ) -> Result<Snapshot> {
     let host = host_for_vm(env, vm_id).await?;
+    let disks = vm_disks::list_by_vm(env.pool(), vm_id).await?;
+
+    // A persistent OverlayBD upper layer is copied alongside the memory state.
+    // A snapshot records a single copy, so a VM with several is refused
+    // rather than snapshotted with some of its disks' writes missing.
+    let upper_ids: Vec<Uuid> = disks
+        .iter()
+        .filter_map(|d| d.upper_storage_object_id)
+        .collect();
+    if upper_ids.len() > 1 {
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "VM has {} persistent OverlayBD upper layers; snapshots support at most one",
+            upper_ids.len()
+        )));
+    }
+    let upper = match upper_ids.first() {
+        Some(upper_id) => Some(storage_objects::get(env.pool(), *upper_id).await?),
+        None => None,
+    };
 
-    // Resolve the storage pool: explicit → VM's primary disk's pool → any active non-OverlayBD.
+    // Resolve the storage pool: explicit → VM's primary disk's pool → the
+    // upper layer's pool → any active non-OverlayBD.
     let preferred_pool_id = if body.storage_pool_id.is_some() {
         body.storage_pool_id
     } else {
-        let disks = vm_disks::list_by_vm(env.pool(), vm_id).await?;
         let mut found = None;
         for disk in &disks {
             if let Some(so_id) = disk.storage_object_id
@@ -1807,7 +2210,7 @@ pub(crate) async fn create_vm_snapshot(
                 }
             }
         }
-        found
+        found.or(upper.as_ref().map(|u| u.storage_pool_id))
     };
 
     let pool_id = storage_pools::pick_active_non_overlaybd(env.pool(), preferred_pool_id)
@@ -1843,12 +2246,18 @@ pub(crate) async fn create_vm_snapshot(
         .ok_or(crate::errors::Error::InternalServerError)?;
     let snapshot_url = format!("file://{}", dir_path);
 
+    let upper_copy = match &upper {
+        Some(upper) => Some(create_upper_layer_copy(env, upper, &name).await?),
+        None => None,
+    };
+
     let id = snapshots::create(
         env.pool(),
         &NewSnapshot {
             vm_id,
             storage_object_id: so_id,
             name,
+            upper_storage_object_id: upper_copy.as_ref().map(|(copy, _, _)| copy.id),
         },
     )
     .await?;
@@ -1859,11 +2268,17 @@ pub(crate) async fn create_vm_snapshot(
     if let Err(e) = node_client.pause_vm(vm_id).await {
         error!("Failed to pause VM before snapshot: {}", e);
         let _ = snapshots::update_status(env.pool(), id, SnapshotStatus::Failed).await;
+        discard_upper_layer_copy(env, id, upper_copy.as_ref().map(|(copy, _, _)| copy.id)).await;
         return Err(crate::errors::Error::InternalServerError);
     }
 
-    // Take the snapshot
-    let snap_result = node_client.snapshot_vm(vm_id, &snapshot_url).await;
+    // Take the snapshot, copying the upper layer while the guest cannot write to it
+    let mut snap_result = node_client.snapshot_vm(vm_id, &snapshot_url).await;
+    if snap_result.is_ok()
+        && let Some((_, from, to)) = &upper_copy
+    {
+        snap_result = copy_upper_layer_files(&node_client, id, from, to).await;
+    }
 
     // Always attempt to resume, but capture the result — a failed resume means
     // the VM is stuck Paused and the client must be informed even if the
@@ -1886,6 +2301,8 @@ pub(crate) async fn create_vm_snapshot(
         Err(e) => {
             error!("Failed to snapshot VM: {}", e);
             let _ = snapshots::update_status(env.pool(), id, SnapshotStatus::Failed).await;
+            discard_upper_layer_copy(env, id, upper_copy.as_ref().map(|(copy, _, _)| copy.id))
+                .await;
             return Err(crate::errors::Error::InternalServerError);
         }
     }
@@ -1893,7 +2310,77 @@ pub(crate) async fn create_vm_snapshot(
     snapshots::get(env.pool(), id).await.map_err(Into::into)
 }
 
-#[utoipa::path(
+/// Remove the upper-layer copy of a snapshot that failed, along with any
+/// partially copied files. The failed snapshot row itself is kept.
+async fn discard_upper_layer_copy(env: &App, snapshot_id: Uuid, copy_id: Option<Uuid>) {
+    let Some(copy_id) = copy_id else {
+        return;
+    };
+    if let Err(e) = delete_upper_layer_copy(env, snapshot_id, copy_id).await {
+        warn!(
+            "Failed to remove upper layer copy {} of failed snapshot {}: {}",
+            copy_id, snapshot_id, e
+        );
+    }
+}
+
+/// Create the `OVERLAYBD_UPPER` object a snapshot copies `upper` into, next to
+/// it on the same pool. Layers frozen by earlier resizes are never written
+/// again, so the copy refers to the same files.
+async fn create_upper_layer_copy(
+    env: &App,
+    upper: &StorageObject,
+    snapshot_name: &str,
+) -> Result<(
+    StorageObject,
+    storage_objects::OverlaybdUpperConfig,
+    storage_objects::OverlaybdUpperConfig,
+)> {
+    let from =
+        storage_objects::OverlaybdUpperConfig::from_value(&upper.config).ok_or_else(|| {
+            crate::errors::Error::UnprocessableEntity(
+                "OverlayBD upper layer has no resolvable host path".into(),
+            )
+        })?;
+    let copy_id = storage_objects::create(
+        env.pool(),
+        NewStorageObject {
+            name: format!("{}-upper", snapshot_name),
+            storage_pool_id: Some(upper.storage_pool_id),
+            object_type: StorageObjectType::OverlaybdUpper,
+            size_bytes: upper.size_bytes,
+            config: serde_json::Value::Object(serde_json::Map::new()),
+            parent_id: Some(upper.id),
+        },
+    )
+    .await?;
+    let mut copy = storage_objects::get(env.pool(), copy_id).await?;
+    if !from.frozen_layers.is_empty() {
+        copy.config["frozen_layers"] = serde_json::json!(from.frozen_layers);
+        storage_objects::update_config(env.pool(), copy.id, &copy.config).await?;
+    }
+    let to = storage_objects::OverlaybdUpperConfig::from_value(&copy.config)
+        .ok_or(crate::errors::Error::InternalServerError)?;
+    Ok((copy, from, to))
+}
+
+async fn copy_upper_layer_files(
+    node_client: &NodeClient,
+    snapshot_id: Uuid,
+    from: &storage_objects::OverlaybdUpperConfig,
+    to: &storage_objects::OverlaybdUpperConfig,
+) -> anyhow::Result<()> {
+    let transfer_id = snapshot_id.to_string();
+    node_client
+        .copy_file(&transfer_id, &from.upper_data, &to.upper_data)
+        .await?;
+    node_client
+        .copy_file(&transfer_id, &from.upper_index, &to.upper_index)
+        .await?;
+    Ok(())
+}
+
+#[utoipa::path(
     post,
     path = "/vms/{vm_id}/snapshots",
     params(
@@ -1903,6 +2390,7 @@ pub(crate) async fn create_vm_snapshot(
     responses(
         (status = 201, description = "Snapshot created", body = Snapshot),
         (status = 404, description = "VM not found"),
+        (status = 422, description = "No suitable storage pool, or the VM has more than one persistent OverlayBD upper layer"),
         (status = 500, description = "Internal server error")
     ),
     tag = "vms"
@@ -1922,6 +2410,158 @@ pub async fn create_snapshot(
     })
 }
 
+/// Remove a snapshot's files from its pool and drop the storage object, which
+/// cascades to the `vm_snapshots` row and any backup recorded for it.
+pub(crate) async fn delete_vm_snapshot(env: &App, snapshot: &Snapshot) -> Result<()> {
+    if snapshot.status == SnapshotStatus::Creating {
+        return Err(crate::errors::Error::Conflict(
+            "snapshot is still being created".into(),
+        ));
+    }
+
+    let so = storage_objects::get(env.pool(), snapshot.storage_object_id).await?;
+    let references = storage_objects::count_references(env.pool(), so.id).await?;
+    if references > 0 {
+        return Err(crate::errors::Error::Conflict(format!(
+            "snapshot storage object {} is still referenced by {} other resource(s)",
+            so.id, references
+        )));
+    }
+
+    if let Some(copy_id) = snapshot.upper_storage_object_id {
+        delete_upper_layer_copy(env, snapshot.id, copy_id).await?;
+    }
+
+    let mut bytes_freed = 0;
+    if let Some(dir_path) = storage_objects::get_path_from_config(&so.config) {
+        let host = crate::handlers::storage_pool::handler::require_up_host_for_pool(
+            env,
+            so.storage_pool_id,
+        )
+        .await?;
+        let node_client = NodeClient::new(&host.address, host.port as u16);
+        bytes_freed = node_client
+            .delete_path(&dir_path, true)
+            .await
+            .map_err(|e| {
+                error!(snapshot_id = %snapshot.id, path = %dir_path, "Failed to remove snapshot files: {:#}", e);
+                crate::errors::Error::InternalServerError
+            })?;
+    }
+
+    storage_objects::delete(env.pool(), so.id).await?;
+
+    info!(
+        snapshot_id = %snapshot.id,
+        storage_object_id = %so.id,
+        bytes_freed,
+        "Deleted snapshot"
+    );
+    Ok(())
+}
+
+/// Remove a snapshot's copy of an OverlayBD upper layer, along with the frozen
+/// layers no other upper (the live disk or another copy) still stacks on.
+async fn delete_upper_layer_copy(env: &App, snapshot_id: Uuid, copy_id: Uuid) -> Result<()> {
+    let copy = match storage_objects::get(env.pool(), copy_id).await {
+        Ok(copy) => copy,
+        Err(sqlx::Error::RowNotFound) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    if let Some(config) = storage_objects::OverlaybdUpperConfig::from_value(&copy.config) {
+        let host = crate::handlers::storage_pool::handler::require_up_host_for_pool(
+            env,
+            copy.storage_pool_id,
+        )
+        .await?;
+        let node_client = NodeClient::new(&host.address, host.port as u16);
+        for path in [&config.upper_data, &config.upper_index] {
+            node_client.delete_path(path, false).await.map_err(|e| {
+                error!(snapshot_id = %snapshot_id, path = %path, "Failed to remove snapshot upper layer: {:#}", e);
+                crate::errors::Error::InternalServerError
+            })?;
+        }
+        release_frozen_layers(env, &node_client, copy.id, &config.frozen_layers).await?;
+    }
+
+    storage_objects::delete(env.pool(), copy.id).await?;
+    Ok(())
+}
+
+/// Delete the frozen layers in `layers` that no OverlayBD upper other than
+/// `owner_id` stacks on. Layer files are named per resize, so once the last
+/// upper lets go of one nothing can read it again.
+pub(crate) async fn release_frozen_layers(
+    env: &App,
+    node_client: &NodeClient,
+    owner_id: Uuid,
+    layers: &[String],
+) -> Result<()> {
+    for layer in layers {
+        if storage_objects::frozen_layer_in_use(env.pool(), layer, owner_id).await? {
+            continue;
+        }
+        node_client.delete_path(layer, false).await.map_err(|e| {
+            error!(path = %layer, "Failed to remove frozen OverlayBD layer: {:#}", e);
+            crate::errors::Error::InternalServerError
+        })?;
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/vms/{vm_id}/snapshots/{snapshot_id}",
+    params(
+        ("vm_id" = uuid::Uuid, Path, description = "VM unique identifier"),
+        ("snapshot_id" = uuid::Uuid, Path, description = "Snapshot unique identifier")
+    ),
+    responses(
+        (status = 204, description = "Snapshot deleted"),
+        (status = 404, description = "VM or snapshot not found"),
+        (status = 409, description = "Snapshot is still referenced by a backup or another resource"),
+        (status = 422, description = "No UP host is attached to the snapshot's storage pool"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "vms"
+)]
+#[instrument(skip(env))]
+pub async fn delete_snapshot(
+    Extension(env): Extension<App>,
+    Path((vm_id, snapshot_id)): Path<(Uuid, Uuid)>,
+) -> Result<axum::response::Response> {
+    vms::get(env.pool(), vm_id).await?;
+    let snapshot = snapshots::get(env.pool(), snapshot_id).await?;
+    if snapshot.vm_id != vm_id {
+        return Err(crate::errors::Error::NotFound);
+    }
+
+    if let Some(backup) = backups::find_by_snapshot(env.pool(), snapshot_id).await? {
+        return Err(crate::errors::Error::Conflict(format!(
+            "snapshot is referenced by backup '{}' ({}); delete the backup instead",
+            backup.name, backup.id
+        )));
+    }
+
+    delete_vm_snapshot(&env, &snapshot).await?;
+
+    Ok(ApiResponse {
+        data: (),
+        code: StatusCode::NO_CONTENT,
+    }
+    .with_audit_event(AuditEvent {
+        action: AuditAction::DeleteSnapshot,
+        resource_type: AuditResourceType::Vm,
+        resource_id: vm_id,
+        resource_name: None,
+        metadata: Some(serde_json::json!({
+            "snapshot_id": snapshot.id,
+            "snapshot_name": snapshot.name,
+        })),
+    }))
+}
+
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct RestoreRequest {
     pub snapshot_id: Uuid,
@@ -1963,6 +2603,32 @@ pub(crate) async fn restore_vm_from_snapshot(
     let host = host_for_vm(env, vm_id).await?;
     let node_client = NodeClient::new(&host.address, host.port as u16);
 
+    let plan = match snapshot.upper_storage_object_id {
+        Some(copy_id) => Some(upper_layer_restore_plan(env, vm_id, copy_id).await?),
+        None => None,
+    };
+    let (disks, upper_copies) = plan
+        .as_ref()
+        .map(|p| (p.disks.clone(), p.copies.clone()))
+        .unwrap_or_default();
+
+    // A snapshot records host-local disk paths and TAP names, which differ
+    // once the VM has been re-created elsewhere (an imported bundle). Cloud
+    // Hypervisor restores are therefore always handed the VM's current
+    // devices, with the upper-layer remap folded in.
+    let (config, disks) = match vm.hypervisor {
+        Hypervisor::Firecracker => (None, disks),
+        _ => {
+            let mut config = build_create_vm_request(env, &vm).await?.into_vm_config();
+            for disk in &mut config.disks {
+                if let Some(remapped) = disks.iter().find(|d| d.id == disk.id) {
+                    *disk = remapped.clone();
+                }
+            }
+            (Some(config), Vec::new())
+        }
+    };
+
     vms::update_status(env.pool(), vm_id, VmStatus::Pending).await?;
 
     let so = storage_objects::get(env.pool(), snapshot.storage_object_id).await?;
@@ -1973,7 +2639,14 @@ pub(crate) async fn restore_vm_from_snapshot(
     // The node handles the full restore flow: kills any existing CH process,
     // spawns a fresh one, and calls vm.restore directly (no vm.create needed).
     if let Err(e) = node_client
-        .restore_vm(vm_id, &snapshot_url, &vm.hypervisor)
+        .restore_vm(
+            vm_id,
+            &snapshot_url,
+            &vm.hypervisor,
+            config,
+            disks,
+            upper_copies,
+        )
         .await
     {
         let msg = format!("restore_vm failed: {:#}", e);
@@ -1982,11 +2655,122 @@ pub(crate) async fn restore_vm_from_snapshot(
         return Err(crate::errors::Error::InternalServerError);
     }
 
+    // The disk's upper only takes on the copy's size and frozen layers once
+    // the node has actually put the copy in place.
+    if let Some(plan) = &plan {
+        storage_objects::update_config(env.pool(), plan.upper.id, &plan.upper.config).await?;
+        storage_objects::update_size_bytes(env.pool(), plan.upper.id, plan.upper.size_bytes)
+            .await?;
+        if let Err(e) =
+            release_frozen_layers(env, &node_client, plan.upper.id, &plan.dropped_layers).await
+        {
+            tracing::warn!(vm_id = %vm_id, error = %e, "Failed to remove frozen layers dropped by the restore");
+        }
+    }
+
+    // The restored NICs sit on fresh TAPs, which need the VM's firewall and
+    // anti-spoofing rules just as after a start.
+    if let Err(e) = network_policy::sync_vm_firewall_on_host(env, vm_id, host.id).await {
+        tracing::error!(vm_id = %vm_id, error = %e, "sync_vm_firewall failed after restore");
+        let _ = node_client.force_stop_vm(vm_id).await;
+        let _ = vms::update_status(env.pool(), vm_id, VmStatus::Unknown).await;
+        return Err(e);
+    }
+    if let Err(e) = network_policy::sync_vm_nat_on_host(env.pool(), vm_id, host.id).await {
+        tracing::warn!(vm_id = %vm_id, error = %e, "Failed to sync floating IPs and port forwards");
+    }
+    if let Err(e) = network_policy::sync_network_dns_for_vm(env.pool(), vm_id).await {
+        tracing::warn!(vm_id = %vm_id, error = %e, "Failed to sync network DNS");
+    }
+    if let Err(e) = network_policy::sync_stretched_networks_for_vm(env.pool(), vm_id).await {
+        tracing::warn!(vm_id = %vm_id, error = %e, "Failed to sync stretched networks");
+    }
+
     vms::update_status(env.pool(), vm_id, VmStatus::Running).await?;
 
     vms::get(env.pool(), vm_id).await.map_err(Into::into)
 }
 
+/// How a restore puts a snapshot's copy of an OverlayBD upper layer back.
+struct UpperRestorePlan {
+    disks: Vec<DiskConfig>,
+    copies: Vec<UpperLayerCopy>,
+    /// The disk's upper as it should be recorded once the restore succeeds.
+    upper: storage_objects::StorageObject,
+    /// Frozen layers the disk's upper stacks on now but the copy does not.
+    dropped_layers: Vec<String>,
+}
+
+/// Work out how to put a snapshot's copy of an OverlayBD upper layer back
+/// under the disk it was taken from: the copy is written over the disk's
+/// current upper, which takes on the copy's size and frozen layers, and the
+/// returned disk config tells the node how to remount it. Nothing is written
+/// to the database here; the caller records the plan after the restore.
+async fn upper_layer_restore_plan(
+    env: &App,
+    vm_id: Uuid,
+    copy_id: Uuid,
+) -> Result<UpperRestorePlan> {
+    let copy = storage_objects::get(env.pool(), copy_id).await?;
+    let disk = vm_disks::list_by_vm(env.pool(), vm_id)
+        .await?
+        .into_iter()
+        .find(|d| {
+            d.upper_storage_object_id.is_some() && d.upper_storage_object_id == copy.parent_id
+        })
+        .ok_or_else(|| {
+            crate::errors::Error::UnprocessableEntity(
+                "snapshot's OverlayBD upper layer no longer belongs to a disk of this VM".into(),
+            )
+        })?;
+    let (Some(upper_id), Some(image_id)) = (disk.upper_storage_object_id, disk.storage_object_id)
+    else {
+        return Err(crate::errors::Error::InternalServerError);
+    };
+    let upper = storage_objects::get(env.pool(), upper_id).await?;
+    let image = storage_objects::get(env.pool(), image_id).await?;
+    plan_upper_restore(&disk, &image, upper, &copy)
+}
+
+/// The database-free half of `upper_layer_restore_plan`.
+fn plan_upper_restore(
+    disk: &vm_disks::VmDisk,
+    image: &StorageObject,
+    mut upper: StorageObject,
+    copy: &StorageObject,
+) -> Result<UpperRestorePlan> {
+    let unresolvable = || {
+        crate::errors::Error::UnprocessableEntity(
+            "OverlayBD upper layer has no resolvable host path".into(),
+        )
+    };
+    let source =
+        storage_objects::OverlaybdUpperConfig::from_value(&copy.config).ok_or_else(unresolvable)?;
+    let dest = storage_objects::OverlaybdUpperConfig::from_value(&upper.config)
+        .ok_or_else(unresolvable)?;
+
+    let dropped_layers = dest
+        .frozen_layers
+        .iter()
+        .filter(|layer| !source.frozen_layers.contains(layer))
+        .cloned()
+        .collect();
+    upper.config["frozen_layers"] = serde_json::json!(source.frozen_layers);
+    upper.size_bytes = copy.size_bytes;
+
+    Ok(UpperRestorePlan {
+        disks: vec![overlaybd_disk_config(disk, image, Some(&upper))],
+        copies: vec![UpperLayerCopy {
+            source_data: source.upper_data,
+            source_index: source.upper_index,
+            dest_data: dest.upper_data,
+            dest_index: dest.upper_index,
+        }],
+        upper,
+        dropped_layers,
+    })
+}
+
 #[utoipa::path(
     post,
     path = "/vms/{vm_id}/restore",
@@ -2123,8 +2907,44 @@ pub async fn delete(
         tracing::warn!("failed to enqueue delete hooks for VM {}: {}", vm_id, e);
     }
 
+    // Snapshot rows go with the VM; their copies of OverlayBD upper layers
+    // would otherwise be left behind, except where a backup still needs one.
+    for snapshot in snapshots::list_for_vm(env.pool(), vm_id, None).await? {
+        let Some(copy_id) = snapshot.upper_storage_object_id else {
+            continue;
+        };
+        if backups::find_by_snapshot(env.pool(), snapshot.id)
+            .await?
+            .is_some()
+        {
+            continue;
+        }
+        if let Err(e) = delete_upper_layer_copy(&env, snapshot.id, copy_id).await {
+            warn!(
+                "Failed to remove upper layer copy of snapshot {} of deleted VM {}: {}",
+                snapshot.id, vm_id, e
+            );
+        }
+    }
+
     let vm_name = vm.name.clone();
+    let network_ids = network_policy::vm_network_ids(env.pool(), vm_id).await?;
+    let security_group_ids = network_policy::vm_security_group_ids(env.pool(), vm_id).await?;
     vms::delete(env.pool(), vm_id).await?;
+    if let Err(e) = network_policy::sync_security_group_peers(&env, &security_group_ids).await {
+        warn!(
+            "Failed to sync security-group peers of deleted VM {}: {}",
+            vm_id, e
+        );
+    }
+    for network_id in network_ids {
+        if let Err(e) = network_policy::sync_network_records(env.pool(), network_id).await {
+            warn!(
+                "Failed to sync DHCP and DNS records for network {}: {}",
+                network_id, e
+            );
+        }
+    }
 
     Ok(ApiResponse {
         data: (),
@@ -2369,6 +3189,51 @@ async fn handle_console_websocket(ws: WebSocket, vm_id: Uuid, host: crate::model
     info!("WebSocket console session ended for VM: {}", vm_id);
 }
 
+/// Static addressing of one guest NIC, matched by MAC in a generated
+/// cloud-init network-config.
+struct GuestNicAddressing {
+    mac: String,
+    /// Addresses in CIDR form, IPv4 first.
+    addresses: Vec<String>,
+    gateway: Option<String>,
+    gateway6: Option<String>,
+    dns: Option<String>,
+}
+
+/// Render a cloud-init network-config (version 2) that pins every NIC's
+/// addresses and default routes.
+fn dual_stack_network_config(nics: &[GuestNicAddressing]) -> String {
+    let mut config = String::from("version: 2\nethernets:\n");
+    for (i, nic) in nics.iter().enumerate() {
+        config.push_str(&format!(
+            "  nic{i}:\n    match:\n      macaddress: \"{}\"\n    addresses:\n",
+            nic.mac
+        ));
+        for address in &nic.addresses {
+            config.push_str(&format!("      - \"{address}\"\n"));
+        }
+        let routes: Vec<(&str, &str)> = [
+            nic.gateway.as_deref().map(|gw| ("0.0.0.0/0", gw)),
+            nic.gateway6.as_deref().map(|gw| ("::/0", gw)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !routes.is_empty() {
+            config.push_str("    routes:\n");
+            for (to, via) in routes {
+                config.push_str(&format!("      - to: \"{to}\"\n        via: \"{via}\"\n"));
+            }
+        }
+        if let Some(dns) = &nic.dns {
+            config.push_str(&format!(
+                "    nameservers:\n      addresses:\n        - \"{dns}\"\n"
+            ));
+        }
+    }
+    config
+}
+
 fn subnet_mask_from_cidr(subnet: &str) -> Option<String> {
     let prefix = subnet
         .split_once('/')
@@ -2442,6 +3307,8 @@ async fn build_create_vm_request(env: &App, vm: &Vm) -> Result<CreateVmRequest>
     let mut networks = net_configs_from_db(&db_networks);
 
     let mut ip_params = String::new();
+    let ipv6_addresses = networks::ipv6_addresses_for_vm(env.pool(), vm_id).await?;
+    let mut guest_nics = Vec::new();
 
     // Managed networking stores IPs in DB as inet (often /32); provide mask from network CIDR.
     // For passt networks, switch to vhost-user passt backend and let guest networking be dynamic.
@@ -2464,20 +3331,52 @@ async fn build_create_vm_request(env: &App, vm: &Vm) -> Result<CreateVmRequest>
                 }
 
                 let ty = network.network_type.as_deref();
-                if ty == Some("bridge") || ty == Some("isolated") {
+                if ty == Some("bridge")
+                    || ty == Some("isolated")
+                    || ty == Some(networks::STRETCHED_NETWORK_TYPE)
+                    || ty == Some(networks::VLAN_NETWORK_TYPE)
+                {
                     let ip = net_config.ip.as_ref().unwrap();
                     let mask = net_config.mask.as_deref().unwrap_or("");
                     let gw = network.gateway.as_deref().unwrap_or("");
-                    let dns = network.dns.as_deref().unwrap_or("");
+                    // Same default as DHCP: the resolver the node runs on the
+                    // bridge gateway. VLAN networks have no such resolver.
+                    let dns = network.dns.clone().or_else(|| {
+                        (ty != Some(networks::VLAN_NETWORK_TYPE)).then(|| network.ipv4_gateway())
+                    });
+                    let dns = dns.as_deref().unwrap_or("");
                     ip_params.push_str(&format!(
                         " ip={}::{}:{}::eth{}:off:{}",
                         ip, gw, mask, i, dns
                     ));
+
+                    if let Some(mac) = db_net.mac_address.clone() {
+                        let prefix_len = network.subnet.split_once('/').map_or("32", |(_, p)| p);
+                        let mut addresses = vec![format!("{ip}/{prefix_len}")];
+                        let ip6 = ipv6_addresses.get(&db_net.id);
+                        if let Some(ip6) = ip6 {
+                            addresses.push(format!("{ip6}/64"));
+                        }
+                        guest_nics.push(GuestNicAddressing {
+                            mac,
+                            addresses,
+                            gateway: network.gateway.clone(),
+                            gateway6: ip6.and_then(|_| network.ipv6_gateway()),
+                            dns: (!dns.is_empty()).then(|| dns.to_string()),
+                        });
+                    }
                 }
             }
         }
     }
 
+    // The kernel ip= parameter cannot carry an IPv6 address, so dual-stack
+    // guests that run cloud-init get a generated network-config instead.
+    let generated_network_config = (vm.cloud_init_network_config.is_none()
+        && vm.cloud_init_user_data.is_some()
+        && guest_nics.iter().any(|nic| nic.gateway6.is_some()))
+    .then(|| dual_stack_network_config(&guest_nics));
+
     // Populate bridge field from host_networks if the VM has a host and network interfaces.
     // Return an error if a managed network is not attached to the scheduled host.
     if let Some(host_id) = vm.host_id {
@@ -2558,32 +3457,10 @@ async fn build_create_vm_request(env: &App, vm: &Vm) -> Result<CreateVmRequest>
 
             match pool.pool_type {
                 storage_pools::StoragePoolType::OverlayBd => {
-                    let image_ref = obj
-                        .config
-                        .get("image_ref")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or_default()
-                        .to_string();
-                    let registry_url = obj
-                        .config
-                        .get("registry_url")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or_default()
-                        .to_string();
-
                     let upper_object = disk
                         .upper_storage_object_id
                         .and_then(|uid| objects_map.get(&uid));
-                    let (upper_data_path, upper_index_path) = overlaybd_upper_paths(upper_object);
-
-                    resolved_disks.push(disk_to_disk_config(
-                        disk,
-                        None,
-                        Some(image_ref),
-                        Some(registry_url),
-                        upper_data_path,
-                        upper_index_path,
-                    ));
+                    resolved_disks.push(overlaybd_disk_config(disk, obj, upper_object));
                     has_overlaybd_boot = true;
                 }
                 storage_pools::StoragePoolType::Local | storage_pools::StoragePoolType::Nfs => {
@@ -2591,27 +3468,17 @@ async fn build_create_vm_request(env: &App, vm: &Vm) -> Result<CreateVmRequest>
                     resolved_disks.push(disk_to_disk_config(disk, path, None, None, None, None));
                 }
                 storage_pools::StoragePoolType::Block => {
-                    let path = iscsi_by_path(&obj.config).ok_or_else(|| {
-                        crate::errors::Error::UnprocessableEntity(format!(
-                            "Storage object {} has invalid iSCSI config",
-                            obj.id
-                        ))
-                    })?;
-                    resolved_disks.push(disk_to_disk_config(
-                        disk,
-                        Some(path),
-                        None,
-                        None,
-                        None,
-                        None,
-                    ));
+                    resolved_disks.push(block_disk_config(disk, obj)?);
+                }
+                storage_pools::StoragePoolType::Rbd => {
+                    resolved_disks.push(rbd_disk_config(disk, obj));
                 }
             }
         }
     }
 
     // Suppress kernel ip= params if cloud-init will configure networking instead.
-    if vm.cloud_init_network_config.is_some() {
+    if vm.cloud_init_network_config.is_some() || generated_network_config.is_some() {
         ip_params.clear();
     }
 
@@ -2712,7 +3579,10 @@ async fn build_create_vm_request(env: &App, vm: &Vm) -> Result<CreateVmRequest>
         disks: resolved_disks,
         cloud_init_user_data: vm.cloud_init_user_data.clone(),
         cloud_init_meta_data,
-        cloud_init_network_config: vm.cloud_init_network_config.clone(),
+        cloud_init_network_config: vm
+            .cloud_init_network_config
+            .clone()
+            .or(generated_network_config),
         devices,
         vsock: vms::guest_agent_enabled(&vm.config).then_some(VsockConfig {
             cid: None,
@@ -2975,60 +3845,89 @@ fn disk_config_for_hotplug(
 ) -> Result<DiskConfig> {
     match pool.pool_type {
         storage_pools::StoragePoolType::OverlayBd => {
-            let image_ref = obj
-                .config
-                .get("image_ref")
-                .and_then(|v| v.as_str())
-                .unwrap_or_default()
-                .to_string();
-            let registry_url = obj
-                .config
-                .get("registry_url")
-                .and_then(|v| v.as_str())
-                .unwrap_or_default()
-                .to_string();
-            let (upper_data_path, upper_index_path) = overlaybd_upper_paths(upper_object);
-            Ok(disk_to_disk_config(
-                disk,
-                None,
-                Some(image_ref),
-                Some(registry_url),
-                upper_data_path,
-                upper_index_path,
-            ))
+            Ok(overlaybd_disk_config(disk, obj, upper_object))
         }
         storage_pools::StoragePoolType::Local | storage_pools::StoragePoolType::Nfs => {
             let path = storage_objects::get_path_from_config(&obj.config);
             Ok(disk_to_disk_config(disk, path, None, None, None, None))
         }
-        storage_pools::StoragePoolType::Block => {
-            let path = iscsi_by_path(&obj.config).ok_or_else(|| {
-                crate::errors::Error::UnprocessableEntity(format!(
-                    "Storage object {} has invalid iSCSI config",
-                    obj.id
-                ))
-            })?;
-            Ok(disk_to_disk_config(
-                disk,
-                Some(path),
-                None,
-                None,
-                None,
-                None,
-            ))
-        }
+        storage_pools::StoragePoolType::Block => block_disk_config(disk, obj),
+        storage_pools::StoragePoolType::Rbd => Ok(rbd_disk_config(disk, obj)),
     }
 }
 
-/// Build `/dev/disk/by-path/ip-<portal>-iscsi-<iqn>-lun-<N>` from a BLOCK
-/// storage object's config. Returns `None` if any field is missing.
-fn iscsi_by_path(config: &serde_json::Value) -> Option<String> {
-    let portal = config.get("portal")?.as_str()?;
-    let iqn = config.get("iqn")?.as_str()?;
-    let lun = config.get("lun")?.as_i64()?;
-    Some(format!(
-        "/dev/disk/by-path/ip-{portal}-iscsi-{iqn}-lun-{lun}"
-    ))
+/// Build a `DiskConfig` for an OverlayBD image disk, carrying the upper
+/// layer's paths, size and any layers frozen by earlier resizes.
+fn overlaybd_disk_config(
+    disk: &vm_disks::VmDisk,
+    obj: &storage_objects::StorageObject,
+    upper_object: Option<&storage_objects::StorageObject>,
+) -> DiskConfig {
+    let image_ref = obj
+        .config
+        .get("image_ref")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let registry_url = obj
+        .config
+        .get("registry_url")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let (upper_data_path, upper_index_path) = overlaybd_upper_paths(upper_object);
+    let (size_bytes, frozen_layers) = overlaybd_upper_layout(disk, upper_object);
+    DiskConfig {
+        overlaybd_size_bytes: size_bytes.map(|s| s as u64),
+        overlaybd_frozen_layers: frozen_layers,
+        ..disk_to_disk_config(
+            disk,
+            None,
+            Some(image_ref),
+            Some(registry_url),
+            upper_data_path,
+            upper_index_path,
+        )
+    }
+}
+
+/// Virtual size and frozen layers of an OverlayBD disk. A persistent upper
+/// keeps both on its storage object; an ephemeral one only has the size the
+/// VM was created with, in the disk config.
+fn overlaybd_upper_layout(
+    disk: &vm_disks::VmDisk,
+    upper_object: Option<&storage_objects::StorageObject>,
+) -> (Option<i64>, Vec<String>) {
+    match upper_object {
+        Some(upper) => (
+            (upper.size_bytes > 0).then_some(upper.size_bytes),
+            storage_objects::OverlaybdUpperConfig::from_value(&upper.config)
+                .map(|c| c.frozen_layers)
+                .unwrap_or_default(),
+        ),
+        None => (
+            disk.config
+                .get("overlaybd_size_bytes")
+                .and_then(|v| v.as_i64()),
+            Vec::new(),
+        ),
+    }
+}
+
+/// Path the next resize of a persistent upper commits its writes to, next to
+/// the upper's own files. The random suffix keeps a resize after a snapshot
+/// restore from overwriting a layer that a later snapshot still stacks.
+fn next_frozen_layer_path(upper: &storage_objects::OverlaybdUpperConfig) -> String {
+    let base = upper
+        .upper_data
+        .strip_suffix(".upper.data")
+        .unwrap_or(&upper.upper_data);
+    format!(
+        "{}.layer{}-{}.commit",
+        base,
+        upper.frozen_layers.len(),
+        &Uuid::new_v4().simple().to_string()[..8]
+    )
 }
 
 fn overlaybd_upper_paths(
@@ -3048,13 +3947,25 @@ fn overlaybd_upper_paths(
 #[derive(Debug)]
 struct DiskResizeTarget {
     storage_object_id: Uuid,
-    path: String,
+    current_size_bytes: i64,
+    method: DiskResizeMethod,
+}
+
+#[derive(Debug)]
+enum DiskResizeMethod {
+    /// Grow a file on the host's filesystem.
+    File(String),
+    /// Grow an image owned by the pool's node backend (RBD).
+    Image,
+    /// Recreate a persistent OverlayBD upper layer at the new size.
+    OverlaybdUpper(storage_objects::OverlaybdUpperConfig),
 }
 
 fn resolve_disk_resize_target(
     disk: &vm_disks::VmDisk,
     object: &storage_objects::StorageObject,
     pool: &storage_pools::StoragePool,
+    upper_object: Option<&storage_objects::StorageObject>,
 ) -> Result<DiskResizeTarget> {
     match (&pool.pool_type, &object.object_type) {
         (
@@ -3066,16 +3977,41 @@ fn resolve_disk_resize_target(
             })?;
             Ok(DiskResizeTarget {
                 storage_object_id: object.id,
-                path,
+                current_size_bytes: object.size_bytes,
+                method: DiskResizeMethod::File(path),
             })
         }
+        (storage_pools::StoragePoolType::Rbd, StorageObjectType::Disk) => Ok(DiskResizeTarget {
+            storage_object_id: object.id,
+            current_size_bytes: object.size_bytes,
+            method: DiskResizeMethod::Image,
+        }),
+        (storage_pools::StoragePoolType::Rbd, _) => Err(crate::errors::Error::UnprocessableEntity(
+            "disk resize is only supported for RBD disk storage objects".into(),
+        )),
         (storage_pools::StoragePoolType::OverlayBd, StorageObjectType::OciImage) => {
-            let reason = if disk.upper_storage_object_id.is_some() {
-                "persistent OverlayBD disk resize is not supported yet"
-            } else {
-                "ephemeral OverlayBD disks cannot be resized"
-            };
-            Err(crate::errors::Error::UnprocessableEntity(reason.into()))
+            if disk.upper_storage_object_id.is_none() {
+                return Err(crate::errors::Error::UnprocessableEntity(
+                    "ephemeral OverlayBD disks cannot be resized".into(),
+                ));
+            }
+            let upper = upper_object.ok_or(crate::errors::Error::NotFound)?;
+            let config =
+                storage_objects::OverlaybdUpperConfig::from_value(&upper.config).ok_or_else(|| {
+                    crate::errors::Error::UnprocessableEntity(
+                        "OverlayBD upper layer has no resolvable host path".into(),
+                    )
+                })?;
+            Ok(DiskResizeTarget {
+                storage_object_id: upper.id,
+                // Uppers created before sizes were recorded use the default.
+                current_size_bytes: if upper.size_bytes > 0 {
+                    upper.size_bytes
+                } else {
+                    OVERLAYBD_DEFAULT_DISK_SIZE_BYTES
+                },
+                method: DiskResizeMethod::OverlaybdUpper(config),
+            })
         }
         (storage_pools::StoragePoolType::OverlayBd, _) => {
             Err(crate::errors::Error::UnprocessableEntity(
@@ -3095,6 +4031,36 @@ fn resolve_disk_resize_target(
     }
 }
 
+/// Build a `DiskConfig` for an iSCSI LUN; the node resolves it to the
+/// multipath device (or the single path) before use.
+fn block_disk_config(
+    disk: &vm_disks::VmDisk,
+    obj: &storage_objects::StorageObject,
+) -> Result<DiskConfig> {
+    let has_portal = obj.config.get("portal").is_some()
+        || obj
+            .config
+            .get("portals")
+            .and_then(|v| v.as_array())
+            .is_some_and(|portals| !portals.is_empty());
+    if !has_portal || obj.config.get("iqn").is_none() || obj.config.get("lun").is_none() {
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "Storage object {} has invalid iSCSI config",
+            obj.id
+        )));
+    }
+    if obj.config.get("missing").and_then(|v| v.as_bool()) == Some(true) {
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "Storage object {} refers to a LUN the target no longer exports",
+            obj.id
+        )));
+    }
+    Ok(DiskConfig {
+        block_config_json: Some(obj.config.to_string()),
+        ..disk_to_disk_config(disk, None, None, None, None, None)
+    })
+}
+
 /// Build a `DiskConfig` proto from a `VmDisk` plus pre-resolved path/image info.
 fn disk_to_disk_config(
     disk: &vm_disks::VmDisk,
@@ -3121,6 +4087,18 @@ fn disk_to_disk_config(
         registry_url,
         upper_data_path,
         upper_index_path,
+        rbd_config_json: None,
+        block_config_json: None,
+        overlaybd_size_bytes: None,
+        overlaybd_frozen_layers: Vec::new(),
+    }
+}
+
+/// Build a `DiskConfig` for an RBD image; the node maps it before use.
+fn rbd_disk_config(disk: &vm_disks::VmDisk, obj: &storage_objects::StorageObject) -> DiskConfig {
+    DiskConfig {
+        rbd_config_json: Some(obj.config.to_string()),
+        ..disk_to_disk_config(disk, None, None, None, None, None)
     }
 }
 
@@ -3300,7 +4278,7 @@ pub async fn add_nic(
         } else {
             req
         };
-        let nic_id = network_interfaces::create(&mut tx, vm_id, &req).await?;
+        let nic_id = create_network_interface_tx(&mut tx, env.pool(), vm_id, &req).await?;
         tx.commit().await?;
         nic_id
     };
@@ -3326,6 +4304,16 @@ pub async fn add_nic(
     }
 
     network_policy::sync_vm_firewall(&env, vm_id).await?;
+    network_policy::sync_security_group_peers_for_vm(&env, vm_id).await?;
+    network_policy::sync_stretched_networks_for_vm(env.pool(), vm_id).await?;
+    if let Some(network_id) = nic.network_id
+        && let Err(e) = network_policy::sync_network_records(env.pool(), network_id).await
+    {
+        warn!(
+            "Failed to sync DHCP and DNS records for network {}: {}",
+            network_id, e
+        );
+    }
 
     Ok(ApiResponse {
         data: nic,
@@ -3360,6 +4348,82 @@ pub async fn list_nics(
     })
 }
 
+#[utoipa::path(
+    get,
+    path = "/vms/{vm_id}/nics/{device_id}/allowed-address-pairs",
+    params(
+        ("vm_id" = uuid::Uuid, Path, description = "VM unique identifier"),
+        ("device_id" = String, Path, description = "NIC device ID (e.g. \"net0\")")
+    ),
+    responses(
+        (status = 200, description = "Extra addresses the NIC may send from", body = Vec<network_interfaces::AllowedAddressPair>),
+        (status = 404, description = "VM or NIC not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "vms"
+)]
+#[instrument(skip(env))]
+pub async fn list_allowed_address_pairs(
+    Extension(env): Extension<App>,
+    Path((vm_id, device_id)): Path<(Uuid, String)>,
+) -> Result<ApiResponse<Vec<network_interfaces::AllowedAddressPair>>> {
+    let nic = network_interfaces::get_by_device_id(env.pool(), vm_id, &device_id)
+        .await?
+        .ok_or(crate::errors::Error::NotFound)?;
+    let pairs = network_interfaces::allowed_address_pairs(env.pool(), nic.id).await?;
+    Ok(ApiResponse {
+        data: pairs,
+        code: StatusCode::OK,
+    })
+}
+
+#[utoipa::path(
+    put,
+    path = "/vms/{vm_id}/nics/{device_id}/allowed-address-pairs",
+    params(
+        ("vm_id" = uuid::Uuid, Path, description = "VM unique identifier"),
+        ("device_id" = String, Path, description = "NIC device ID (e.g. \"net0\")")
+    ),
+    request_body = Vec<network_interfaces::AllowedAddressPair>,
+    responses(
+        (status = 200, description = "Allowed address pairs replaced", body = Vec<network_interfaces::AllowedAddressPair>),
+        (status = 404, description = "VM or NIC not found"),
+        (status = 422, description = "Invalid address pair, or the NIC is not on a managed network"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "vms"
+)]
+#[instrument(skip(env))]
+pub async fn update_allowed_address_pairs(
+    Extension(env): Extension<App>,
+    Path((vm_id, device_id)): Path<(Uuid, String)>,
+    Json(pairs): Json<Vec<network_interfaces::AllowedAddressPair>>,
+) -> Result<ApiResponse<Vec<network_interfaces::AllowedAddressPair>>> {
+    let nic = network_interfaces::get_by_device_id(env.pool(), vm_id, &device_id)
+        .await?
+        .ok_or(crate::errors::Error::NotFound)?;
+    let network = match nic.network_id {
+        Some(network_id) => Some(networks::get(env.pool(), network_id).await?),
+        None => None,
+    };
+    if !pairs.is_empty()
+        && network.is_none_or(|network| network.network_type.as_deref() == Some("passt"))
+    {
+        return Err(crate::errors::Error::UnprocessableEntity(
+            "allowed_address_pairs need a managed network".into(),
+        ));
+    }
+
+    let pairs = network_interfaces::normalize_allowed_address_pairs(&pairs)
+        .map_err(crate::errors::Error::UnprocessableEntity)?;
+    network_interfaces::update_allowed_address_pairs(env.pool(), nic.id, &pairs).await?;
+    network_policy::sync_vm_firewall(&env, vm_id).await?;
+    Ok(ApiResponse {
+        data: pairs,
+        code: StatusCode::OK,
+    })
+}
+
 #[utoipa::path(
     delete,
     path = "/vms/{vm_id}/nics/{device_id}",
@@ -3410,6 +4474,27 @@ pub async fn remove_nic(
 
     delete_nic_and_release_ip(env.pool(), &nic).await?;
     network_policy::sync_vm_firewall(&env, vm_id).await?;
+    network_policy::sync_security_group_peers_for_vm(&env, vm_id).await?;
+    network_policy::sync_vm_nat(env.pool(), vm_id).await?;
+    if let Some(network_id) = nic.network_id {
+        let network = networks::get(env.pool(), network_id).await?;
+        // The NIC is already gone; a host that misses this keeps a stale MAC
+        // entry until the next sync rather than failing the removal.
+        if network.is_stretched()
+            && let Err(e) = network_policy::sync_stretched_network(env.pool(), &network).await
+        {
+            warn!(
+                "Failed to sync stretched network {} after removing a NIC: {}",
+                network_id, e
+            );
+        }
+        if let Err(e) = network_policy::sync_network_records(env.pool(), network_id).await {
+            warn!(
+                "Failed to sync DHCP and DNS records for network {}: {}",
+                network_id, e
+            );
+        }
+    }
 
     Ok(ApiResponse {
         data: (),
@@ -3476,6 +4561,7 @@ pub async fn attach_security_group(
     tx.commit().await?;
 
     network_policy::sync_vm_firewall(&env, vm_id).await?;
+    network_policy::sync_security_group_peers(&env, &[body.security_group_id]).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -3506,9 +4592,66 @@ pub async fn detach_security_group(
     tx.commit().await?;
 
     network_policy::sync_vm_firewall(&env, vm_id).await?;
+    network_policy::sync_security_group_peers(&env, &[security_group_id]).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
+#[utoipa::path(
+    get,
+    path = "/vms/{vm_id}/firewall-log",
+    params(
+        ("vm_id" = uuid::Uuid, Path, description = "VM unique identifier")
+    ),
+    responses(
+        (status = 200, description = "Denied-packet logging state and recent denied packets", body = security_groups::VmFirewallLog),
+        (status = 404, description = "VM not found"),
+        (status = 422, description = "Host could not be reached"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "vms"
+)]
+#[instrument(skip(env))]
+pub async fn get_firewall_log(
+    Extension(env): Extension<App>,
+    Path(vm_id): Path<Uuid>,
+) -> Result<ApiResponse<security_groups::VmFirewallLog>> {
+    let enabled = vms::firewall_log_denied(env.pool(), vm_id).await?;
+    let denied = network_policy::vm_denied_packets(env.pool(), vm_id).await?;
+    Ok(ApiResponse {
+        data: security_groups::VmFirewallLog { enabled, denied },
+        code: StatusCode::OK,
+    })
+}
+
+#[utoipa::path(
+    put,
+    path = "/vms/{vm_id}/firewall-log",
+    params(
+        ("vm_id" = uuid::Uuid, Path, description = "VM unique identifier")
+    ),
+    request_body = security_groups::UpdateVmFirewallLog,
+    responses(
+        (status = 200, description = "Denied-packet logging updated"),
+        (status = 404, description = "VM not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "vms"
+)]
+#[instrument(skip(env))]
+pub async fn update_firewall_log(
+    Extension(env): Extension<App>,
+    Path(vm_id): Path<Uuid>,
+    Json(body): Json<security_groups::UpdateVmFirewallLog>,
+) -> Result<ApiResponse<()>> {
+    vms::get(env.pool(), vm_id).await?;
+    vms::update_firewall_log_denied(env.pool(), vm_id, body.enabled).await?;
+    network_policy::sync_vm_firewall(&env, vm_id).await?;
+    Ok(ApiResponse {
+        data: (),
+        code: StatusCode::OK,
+    })
+}
+
 /// Request body for `POST /vms/{vm_id}/migrate`.
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct VmMigrateRequest {
@@ -3638,6 +4781,8 @@ pub(crate) async fn plan_vm_migration(
         }
     }
 
+    attach_stretched_networks(env, vm_id, &source_host, &target_host).await?;
+
     let mut target_vm = vm.clone();
     target_vm.host_id = Some(target_host.id);
     let create_req = build_create_vm_request(env, &target_vm).await?;
@@ -3652,6 +4797,48 @@ pub(crate) async fn plan_vm_migration(
     })
 }
 
+/// Attach the VM's stretched networks to the migration target, reusing the
+/// source host's bridge names. The subnet, gateway and DHCP server already
+/// live on the segment, so the VM keeps its addressing on arrival.
+async fn attach_stretched_networks(
+    env: &App,
+    vm_id: Uuid,
+    source_host: &Host,
+    target_host: &Host,
+) -> Result<()> {
+    let network_ids: std::collections::BTreeSet<Uuid> =
+        network_interfaces::list_by_vm(env.pool(), vm_id)
+            .await?
+            .into_iter()
+            .filter_map(|nic| nic.network_id)
+            .collect();
+    for network_id in network_ids {
+        let network = networks::get(env.pool(), network_id).await?;
+        if !network.is_stretched()
+            || networks::host_has_network(env.pool(), target_host.id, network_id).await?
+        {
+            continue;
+        }
+        let bridge_name = networks::get_host_bridge(env.pool(), source_host.id, network_id)
+            .await?
+            .ok_or_else(|| {
+                crate::errors::Error::UnprocessableEntity(format!(
+                    "Network {network_id} is not attached to source host {}",
+                    source_host.id
+                ))
+            })?;
+        crate::handlers::network::handler::attach_network_to_host(
+            env,
+            &network,
+            target_host,
+            &bridge_name,
+            None,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
 pub(crate) async fn execute_planned_vm_migration(
     db_pool: &PgPool,
     plan: PlannedVmMigration,
@@ -3739,7 +4926,10 @@ pub(crate) async fn execute_planned_vm_migration(
         let _ = jobs::update_progress(db_pool, job_id, 25).await;
     }
 
-    let actual_receiver_url = receiver_url.replace("0.0.0.0", &target_host.address);
+    let actual_receiver_url = receiver_url.replace(
+        "0.0.0.0",
+        &crate::grpc_client::host_for_url(&target_host.address),
+    );
 
     source_client
         .send_migration(vm_id, &actual_receiver_url)
@@ -3759,6 +4949,14 @@ pub(crate) async fn execute_planned_vm_migration(
     }
     let _ = vms::update_status(db_pool, vm_id, original_status).await;
 
+    if let Err(e) = crate::network_policy::sync_stretched_networks_for_vm(db_pool, vm_id).await {
+        tracing::warn!(
+            vm_id = %vm_id,
+            error = %e,
+            "Failed to move stretched network MACs to the destination host"
+        );
+    }
+
     if let Err(e) = source_client.delete_vm(vm_id).await {
         tracing::warn!(
             vm_id = %vm_id,
@@ -3767,6 +4965,16 @@ pub(crate) async fn execute_planned_vm_migration(
         );
     }
 
+    // Deleting the source VM released its floating IPs there; claim them on
+    // the destination.
+    if let Err(e) = crate::network_policy::sync_vm_nat(db_pool, vm_id).await {
+        tracing::warn!(
+            vm_id = %vm_id,
+            error = %e,
+            "Failed to move floating IPs and port forwards to the destination host"
+        );
+    }
+
     Ok(())
 }
 
@@ -4029,12 +5237,16 @@ pub async fn resize_disk(
 
     let obj = storage_objects::get(env.pool(), so_id).await?;
     let pool_record = storage_pools::get(env.pool(), obj.storage_pool_id).await?;
-    let target = resolve_disk_resize_target(&disk, &obj, &pool_record)?;
+    let upper_obj = match disk.upper_storage_object_id {
+        Some(upper_id) => Some(storage_objects::get(env.pool(), upper_id).await?),
+        None => None,
+    };
+    let target = resolve_disk_resize_target(&disk, &obj, &pool_record, upper_obj.as_ref())?;
 
-    if req.new_size_bytes <= obj.size_bytes {
+    if req.new_size_bytes <= target.current_size_bytes {
         return Err(crate::errors::Error::UnprocessableEntity(format!(
             "new_size_bytes {} must be greater than current size {}",
-            req.new_size_bytes, obj.size_bytes
+            req.new_size_bytes, target.current_size_bytes
         )));
     }
     if req.new_size_bytes % MIB != 0 {
@@ -4043,15 +5255,72 @@ pub async fn resize_disk(
             req.new_size_bytes, MIB
         )));
     }
+    if matches!(target.method, DiskResizeMethod::OverlaybdUpper(_)) && req.new_size_bytes % GIB != 0
+    {
+        return Err(crate::errors::Error::UnprocessableEntity(format!(
+            "new_size_bytes {} must be a multiple of 1 GiB ({}) for OverlayBD disks",
+            req.new_size_bytes, GIB
+        )));
+    }
 
     let host = host_for_vm(&env, vm_id).await?;
-    NodeClient::new(&host.address, host.port as u16)
-        .resize_disk(vm_id, &disk.logical_name, &target.path, req.new_size_bytes)
-        .await
-        .map_err(|e| {
-            error!("Failed to resize disk {} for VM {}: {}", disk_id, vm_id, e);
-            crate::errors::Error::InternalServerError
-        })?;
+    let client = NodeClient::new(&host.address, host.port as u16);
+    let resized = match &target.method {
+        DiskResizeMethod::File(path) => {
+            client
+                .resize_disk(vm_id, &disk.logical_name, path, req.new_size_bytes)
+                .await
+        }
+        DiskResizeMethod::Image => {
+            client
+                .manage_storage_image(
+                    &pool_record,
+                    StorageImageOperation::Resize,
+                    &obj.config,
+                    req.new_size_bytes,
+                    "",
+                    "",
+                )
+                .await
+        }
+        DiskResizeMethod::OverlaybdUpper(upper) => {
+            let frozen_layer = next_frozen_layer_path(upper);
+            match client
+                .resize_overlaybd_upper(
+                    &upper.upper_data,
+                    &upper.upper_index,
+                    &frozen_layer,
+                    req.new_size_bytes as u64,
+                )
+                .await
+            {
+                Ok(response) if response.frozen => {
+                    // Record the layer even when the upper was not replaced:
+                    // the node's upper reads correctly stacked on it either way.
+                    let mut frozen_layers = upper.frozen_layers.clone();
+                    frozen_layers.push(frozen_layer);
+                    let mut config = upper_obj
+                        .as_ref()
+                        .map(|o| o.config.clone())
+                        .unwrap_or_default();
+                    config["frozen_layers"] = serde_json::json!(frozen_layers);
+                    storage_objects::update_config(env.pool(), target.storage_object_id, &config)
+                        .await?;
+                    if response.error.is_empty() {
+                        Ok(())
+                    } else {
+                        Err(anyhow::anyhow!(response.error))
+                    }
+                }
+                Ok(_) => Ok(()),
+                Err(e) => Err(e),
+            }
+        }
+    };
+    resized.map_err(|e| {
+        error!("Failed to resize disk {} for VM {}: {}", disk_id, vm_id, e);
+        crate::errors::Error::InternalServerError
+    })?;
 
     storage_objects::update_size_bytes(env.pool(), target.storage_object_id, req.new_size_bytes)
         .await?;
@@ -4143,7 +5412,7 @@ pub async fn commit(
             ));
         }
     }
-    if target_pool.status != storage_pools::StoragePoolStatus::Active {
+    if !target_pool.status.is_usable() {
         return Err(crate::errors::Error::UnprocessableEntity(
             "target storage pool is not active".into(),
         ));
@@ -4160,6 +5429,10 @@ pub async fn commit(
             req.storage_pool_id
         )));
     }
+    if let Err(e) = admit_pool_allocation(env.pool(), &target_pool, &host, req.size_bytes).await {
+        let _ = vms::update_status(env.pool(), vm_id, vm.status.clone()).await;
+        return Err(e);
+    }
 
     // Create the job
     let job = jobs::create(
@@ -4202,6 +5475,8 @@ struct OciDiskInfo {
     registry_url: String,
     upper_data: Option<String>,
     upper_index: Option<String>,
+    size_bytes: Option<i64>,
+    frozen_layers: Vec<String>,
     oci_storage_object_id: Uuid,
     upper_storage_object_id: Option<Uuid>,
 }
@@ -4222,12 +5497,19 @@ async fn find_oci_disk(pool: &sqlx::PgPool, vm_id: Uuid) -> Result<OciDiskInfo>
             )
         })?;
 
-    let (upper_data, upper_index) = row
+    let (upper_data, upper_index, frozen_layers) = row
         .upper_config
         .as_ref()
         .and_then(|c| storage_objects::OverlaybdUpperConfig::from_value(c))
-        .map(|c| (Some(c.upper_data), Some(c.upper_index)))
-        .unwrap_or((None, None));
+        .map(|c| (Some(c.upper_data), Some(c.upper_index), c.frozen_layers))
+        .unwrap_or_default();
+    let size_bytes = match row.upper_size_bytes {
+        Some(size) => (size > 0).then_some(size),
+        None => row
+            .disk_config
+            .get("overlaybd_size_bytes")
+            .and_then(|v| v.as_i64()),
+    };
 
     Ok(OciDiskInfo {
         disk_id: row.disk_id,
@@ -4235,6 +5517,8 @@ async fn find_oci_disk(pool: &sqlx::PgPool, vm_id: Uuid) -> Result<OciDiskInfo>
         registry_url: oci_config.registry_url,
         upper_data,
         upper_index,
+        size_bytes,
+        frozen_layers,
         oci_storage_object_id: row.oci_storage_object_id,
         upper_storage_object_id: row.upper_storage_object_id,
     })
@@ -4381,6 +5665,8 @@ async fn run_vm_commit_copy_and_swap(
                 registry_url: oci_disk.registry_url.clone(),
                 upper_data: oci_disk.upper_data.clone(),
                 upper_index: oci_disk.upper_index.clone(),
+                size_bytes: oci_disk.size_bytes.map(|s| s as u64),
+                frozen_layers: oci_disk.frozen_layers.clone(),
             },
             |bytes_written| {
                 let pct = if size_bytes > 0 {
@@ -4496,9 +5782,82 @@ mod tests {
             config: serde_json::json!({}),
             capacity_bytes: None,
             allocated_bytes: None,
+            host_capacity: Vec::new(),
         }
     }
 
+    #[test]
+    fn upper_restore_plan_takes_on_the_copy_and_drops_newer_layers() {
+        let shared = "/pools/vm.layer0-aaaaaaaa.commit";
+        let newer = "/pools/vm.layer1-bbbbbbbb.commit";
+        let mut upper = make_storage_object(
+            StorageObjectType::OverlaybdUpper,
+            serde_json::json!({
+                "upper_data": "/pools/vm.upper.data",
+                "upper_index": "/pools/vm.upper.index",
+                "frozen_layers": [shared, newer],
+            }),
+        );
+        upper.size_bytes = 4 * GIB;
+        let mut copy = make_storage_object(
+            StorageObjectType::OverlaybdUpper,
+            serde_json::json!({
+                "upper_data": "/pools/snap.upper.data",
+                "upper_index": "/pools/snap.upper.index",
+                "frozen_layers": [shared],
+            }),
+        );
+        copy.size_bytes = 2 * GIB;
+        copy.parent_id = Some(upper.id);
+        let image = make_storage_object(
+            StorageObjectType::OciImage,
+            serde_json::json!({ "image_ref": "registry:5000/alpine:obd" }),
+        );
+        let mut disk = make_disk("disk0");
+        disk.storage_object_id = Some(image.id);
+        disk.upper_storage_object_id = Some(upper.id);
+
+        let plan = plan_upper_restore(&disk, &image, upper, &copy).unwrap();
+
+        assert_eq!(plan.dropped_layers, vec![newer.to_string()]);
+        assert_eq!(plan.upper.size_bytes, 2 * GIB);
+        assert_eq!(
+            plan.upper.config["frozen_layers"],
+            serde_json::json!([shared])
+        );
+        assert_eq!(plan.copies.len(), 1);
+        assert_eq!(plan.copies[0].source_data, "/pools/snap.upper.data");
+        assert_eq!(plan.copies[0].dest_index, "/pools/vm.upper.index");
+        let disk_config = &plan.disks[0];
+        assert_eq!(disk_config.overlaybd_size_bytes, Some(2 * GIB as u64));
+        assert_eq!(
+            disk_config.overlaybd_frozen_layers,
+            vec![shared.to_string()]
+        );
+        assert_eq!(
+            disk_config.upper_data_path.as_deref(),
+            Some("/pools/vm.upper.data")
+        );
+    }
+
+    #[test]
+    fn upper_restore_plan_needs_resolvable_paths() {
+        let upper = make_storage_object(StorageObjectType::OverlaybdUpper, serde_json::json!({}));
+        let copy = make_storage_object(
+            StorageObjectType::OverlaybdUpper,
+            serde_json::json!({
+                "upper_data": "/pools/snap.upper.data",
+                "upper_index": "/pools/snap.upper.index",
+            }),
+        );
+        let image = make_storage_object(StorageObjectType::OciImage, serde_json::json!({}));
+
+        let err = plan_upper_restore(&make_disk("disk0"), &image, upper, &copy)
+            .err()
+            .unwrap();
+        assert!(matches!(err, crate::errors::Error::UnprocessableEntity(_)));
+    }
+
     #[test]
     fn next_disk_id_empty() {
         assert_eq!(next_disk_id(&[]), "disk0");
@@ -4542,6 +5901,23 @@ mod tests {
         assert_eq!(parsed, BootMode::Kernel);
     }
 
+    #[test]
+    fn dual_stack_network_config_routes_both_families() {
+        let config = dual_stack_network_config(&[GuestNicAddressing {
+            mac: "02:00:00:00:00:01".into(),
+            addresses: vec!["10.0.0.2/24".into(), "fd00::ff:fe00:1/64".into()],
+            gateway: Some("10.0.0.1".into()),
+            gateway6: Some("fd00::1".into()),
+            dns: None,
+        }]);
+
+        assert!(config.contains("macaddress: \"02:00:00:00:00:01\""));
+        assert!(config.contains("- \"fd00::ff:fe00:1/64\""));
+        assert!(config.contains("- to: \"0.0.0.0/0\"\n        via: \"10.0.0.1\""));
+        assert!(config.contains("- to: \"::/0\"\n        via: \"fd00::1\""));
+        assert!(!config.contains("nameservers"));
+    }
+
     #[test]
     fn overlaybd_upper_paths_reads_config() {
         let upper_object = make_storage_object(
@@ -4573,16 +5949,27 @@ mod tests {
         );
         let pool = make_storage_pool(StoragePoolType::Local);
 
-        let target = resolve_disk_resize_target(&disk, &object, &pool).unwrap();
+        let target = resolve_disk_resize_target(&disk, &object, &pool, None).unwrap();
 
         assert_eq!(target.storage_object_id, object.id);
-        assert_eq!(target.path, "/var/lib/qarax/disk.raw");
+        assert!(
+            matches!(target.method, DiskResizeMethod::File(ref path) if path == "/var/lib/qarax/disk.raw")
+        );
     }
 
     #[test]
-    fn resolve_disk_resize_target_rejects_persistent_overlaybd() {
+    fn resolve_disk_resize_target_resizes_persistent_overlaybd_upper() {
+        let mut upper = make_storage_object(
+            StorageObjectType::OverlaybdUpper,
+            serde_json::json!({
+                "upper_data": "/var/lib/qarax/pools/u.upper.data",
+                "upper_index": "/var/lib/qarax/pools/u.upper.index",
+                "frozen_layers": ["/var/lib/qarax/pools/u.layer0.commit"]
+            }),
+        );
+        upper.size_bytes = 0;
         let mut disk = make_disk("disk0");
-        disk.upper_storage_object_id = Some(Uuid::new_v4());
+        disk.upper_storage_object_id = Some(upper.id);
         let object = make_storage_object(
             StorageObjectType::OciImage,
             serde_json::json!({
@@ -4592,10 +5979,52 @@ mod tests {
         );
         let pool = make_storage_pool(StoragePoolType::OverlayBd);
 
-        let err = resolve_disk_resize_target(&disk, &object, &pool).unwrap_err();
+        let target = resolve_disk_resize_target(&disk, &object, &pool, Some(&upper)).unwrap();
 
+        assert_eq!(target.storage_object_id, upper.id);
+        assert_eq!(target.current_size_bytes, OVERLAYBD_DEFAULT_DISK_SIZE_BYTES);
+        let DiskResizeMethod::OverlaybdUpper(config) = target.method else {
+            panic!("expected an OverlayBD upper resize");
+        };
+        let layer = next_frozen_layer_path(&config);
+        assert!(layer.starts_with("/var/lib/qarax/pools/u.layer1-"));
+        assert!(layer.ends_with(".commit"));
+        assert_ne!(layer, next_frozen_layer_path(&config));
+
+        disk.upper_storage_object_id = None;
+        let err = resolve_disk_resize_target(&disk, &object, &pool, None).unwrap_err();
         assert!(
-            matches!(err, crate::errors::Error::UnprocessableEntity(message) if message == "persistent OverlayBD disk resize is not supported yet")
+            matches!(err, crate::errors::Error::UnprocessableEntity(message) if message == "ephemeral OverlayBD disks cannot be resized")
         );
     }
+
+    #[test]
+    fn full_clone_default_size_doubles_image_with_floor() {
+        assert_eq!(full_clone_default_size(0), 4 * GIB);
+        assert_eq!(full_clone_default_size(GIB), 4 * GIB);
+        assert_eq!(full_clone_default_size(3 * GIB), 6 * GIB);
+        assert_eq!(full_clone_default_size(3 * GIB + 1), 7 * GIB);
+    }
+
+    #[test]
+    fn full_clone_is_sized_from_the_unpacked_image() {
+        // A well-compressed image: 300 MiB in the registry, 5 GiB unpacked.
+        let import = crate::grpc_client::node::ImportOverlayBdResponse {
+            size_bytes: 300 * 1024 * 1024,
+            content_size_bytes: 5 * GIB,
+            ..Default::default()
+        };
+        let content_size = full_clone_content_size(&import);
+        assert_eq!(content_size, 5 * GIB);
+        assert_eq!(full_clone_default_size(content_size), 10 * GIB);
+        assert!(full_clone_default_size(content_size) >= import.content_size_bytes);
+
+        // Nodes that do not report the unpacked size fall back to the
+        // converted one.
+        let import = crate::grpc_client::node::ImportOverlayBdResponse {
+            size_bytes: 3 * GIB,
+            ..Default::default()
+        };
+        assert_eq!(full_clone_content_size(&import), 3 * GIB);
+    }
 }
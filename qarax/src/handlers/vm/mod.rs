@@ -1,3 +1,4 @@
 use super::{ApiResponse, Result};
 
+pub mod bundle;
 pub mod handler;
@@ -1,7 +1,11 @@
 use super::*;
 use crate::{
     App,
-    model::vm_templates::{self, NewVmTemplate, VmTemplate},
+    errors::Error,
+    model::{
+        image_catalogs,
+        vm_templates::{self, NewVmTemplate, VmTemplate},
+    },
 };
 use axum::{Extension, Json, extract::Path};
 use http::StatusCode;
@@ -72,6 +76,21 @@ pub async fn create(
     Extension(env): Extension<App>,
     Json(new_vm_template): Json<NewVmTemplate>,
 ) -> Result<(StatusCode, String)> {
+    if let Some(catalog_id) = new_vm_template.image_catalog_id {
+        if new_vm_template.image_ref.is_some() {
+            return Err(Error::UnprocessableEntity(
+                "image_ref and image_catalog_id are mutually exclusive".into(),
+            ));
+        }
+        image_catalogs::get(env.pool(), catalog_id)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => {
+                    Error::UnprocessableEntity(format!("image catalog {catalog_id} not found"))
+                }
+                e => e.into(),
+            })?;
+    }
     let id = vm_templates::create(env.pool(), new_vm_template).await?;
     Ok((StatusCode::CREATED, id.to_string()))
 }
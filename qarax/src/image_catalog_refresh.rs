@@ -0,0 +1,219 @@
+/// Background task that re-checks image catalogs whose refresh interval has
+/// elapsed, imports new upstream digests and prunes old versions.
+use anyhow::{Context, Result, anyhow};
+use tokio::time::{Duration, interval};
+use tracing::{info, warn};
+
+use crate::{
+    App,
+    grpc_client::NodeClient,
+    model::{
+        hosts::{self, HostStatus},
+        image_catalogs::{self, ImageCatalog, ImageCatalogVersion},
+        registry_credentials,
+        storage_objects::{self, NewStorageObject, StorageObjectType},
+        storage_pools::{self, OverlayBdPoolConfig},
+    },
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Result of one refresh.
+#[derive(Debug)]
+pub struct RefreshOutcome {
+    /// Newest version after the refresh.
+    pub version: ImageCatalogVersion,
+    /// Whether the upstream digest was new and got imported.
+    pub imported: bool,
+    pub pruned: usize,
+}
+
+impl RefreshOutcome {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "version_id": self.version.id,
+            "digest": self.version.source_digest,
+            "image_ref": self.version.image_ref,
+            "storage_object_id": self.version.storage_object_id,
+            "imported": self.imported,
+            "pruned": self.pruned,
+        })
+    }
+}
+
+pub async fn start_image_catalog_refresh(env: App) {
+    let mut ticker = interval(POLL_INTERVAL);
+
+    loop {
+        ticker.tick().await;
+
+        if env.maintenance_mode() {
+            continue;
+        }
+
+        let due = match image_catalogs::list_due(env.pool()).await {
+            Ok(due) => due,
+            Err(e) => {
+                warn!("Image catalog refresh: failed to query catalogs: {}", e);
+                continue;
+            }
+        };
+
+        for catalog in due {
+            match refresh(&env, &catalog).await {
+                Ok(outcome) if outcome.imported => info!(
+                    catalog = %catalog.name,
+                    digest = %outcome.version.source_digest,
+                    pruned = outcome.pruned,
+                    "Image catalog refresh: imported new version"
+                ),
+                Ok(_) => {}
+                Err(e) => warn!(
+                    catalog = %catalog.name,
+                    "Image catalog refresh failed: {:#}",
+                    e
+                ),
+            }
+        }
+    }
+}
+
+/// Check the catalog's upstream digest and import it if it is new. The
+/// outcome (or error) is recorded on the catalog either way.
+pub async fn refresh(env: &App, catalog: &ImageCatalog) -> Result<RefreshOutcome> {
+    let result = refresh_inner(env, catalog).await;
+    let error = result.as_ref().err().map(|e| format!("{:#}", e));
+    if let Err(e) = image_catalogs::record_check(env.pool(), catalog.id, error.as_deref()).await {
+        warn!(catalog = %catalog.name, "Failed to record image catalog check: {}", e);
+    }
+    result
+}
+
+async fn refresh_inner(env: &App, catalog: &ImageCatalog) -> Result<RefreshOutcome> {
+    let pool = storage_pools::get(env.pool(), catalog.storage_pool_id).await?;
+    let registry_url = OverlayBdPoolConfig::from_value(&pool.config)
+        .map(|cfg| cfg.url)
+        .ok_or_else(|| anyhow!("OverlayBD pool config missing 'url' field"))?;
+    let host_id = storage_pools::find_host_for_pool(env.pool(), pool.id)
+        .await?
+        .ok_or_else(|| anyhow!("no host attached to storage pool '{}'", pool.name))?;
+    let host = hosts::require_by_id(env.pool(), host_id).await?;
+    if host.status != HostStatus::Up {
+        return Err(anyhow!(
+            "no UP host attached to storage pool '{}'",
+            pool.name
+        ));
+    }
+
+    let client = NodeClient::new(&host.address, host.port as u16);
+    let logins = registry_credentials::logins_for_image(
+        env.pool(),
+        &catalog.source_ref,
+        Some(&registry_url),
+    )
+    .await
+    .context("failed to load registry credentials")?;
+    let digest = client
+        .resolve_image_digest(&catalog.source_ref, &logins)
+        .await?;
+
+    if let Some(version) = image_catalogs::find_version(env.pool(), catalog.id, &digest).await? {
+        return Ok(RefreshOutcome {
+            version,
+            imported: false,
+            pruned: 0,
+        });
+    }
+
+    let image_ref = image_catalogs::pinned_ref(&catalog.source_ref, &digest);
+    // The object name doubles as a guard: a second refresh of the same digest
+    // racing this one fails here instead of importing twice.
+    let short_digest = digest.rsplit(':').next().unwrap_or(&digest);
+    let storage_object_id = storage_objects::create(
+        env.pool(),
+        NewStorageObject {
+            name: format!(
+                "{}-{}",
+                catalog.name,
+                &short_digest[..short_digest.len().min(12)]
+            ),
+            storage_pool_id: Some(pool.id),
+            object_type: StorageObjectType::OciImage,
+            size_bytes: 0,
+            config: serde_json::json!({ "image_ref": image_ref }),
+            parent_id: None,
+        },
+    )
+    .await?;
+
+    let imported = match client
+        .import_overlaybd_image(&image_ref, &registry_url, &logins)
+        .await
+    {
+        Ok(imported) => imported,
+        Err(e) => {
+            let _ = storage_objects::delete(env.pool(), storage_object_id).await;
+            return Err(e.context(format!("failed to import {}", image_ref)));
+        }
+    };
+    let config = serde_json::json!({
+        "image_ref": imported.image_ref,
+        "digest": digest,
+        "registry_url": registry_url,
+    });
+    storage_objects::update_config(env.pool(), storage_object_id, &config).await?;
+    let version = image_catalogs::create_version(
+        env.pool(),
+        catalog.id,
+        &digest,
+        &image_ref,
+        storage_object_id,
+    )
+    .await?;
+
+    let pruned = prune(env, catalog).await;
+    Ok(RefreshOutcome {
+        version,
+        imported: true,
+        pruned,
+    })
+}
+
+/// Drop versions beyond `keep_versions`. Their storage objects go too, which
+/// leaves the converted images to the pool's garbage collection once no VM
+/// uses them.
+async fn prune(env: &App, catalog: &ImageCatalog) -> usize {
+    let stale = match image_catalogs::versions_to_prune(
+        env.pool(),
+        catalog.id,
+        catalog.keep_versions,
+    )
+    .await
+    {
+        Ok(stale) => stale,
+        Err(e) => {
+            warn!(catalog = %catalog.name, "Failed to list versions to prune: {}", e);
+            return 0;
+        }
+    };
+
+    let mut pruned = 0;
+    for version in stale {
+        if let Some(object_id) = version.storage_object_id
+            && let Err(e) = storage_objects::delete(env.pool(), object_id).await
+        {
+            warn!(
+                catalog = %catalog.name,
+                storage_object_id = %object_id,
+                "Keeping image catalog version whose storage object cannot be deleted: {}",
+                e
+            );
+            continue;
+        }
+        match image_catalogs::delete_version(env.pool(), version.id).await {
+            Ok(()) => pruned += 1,
+            Err(e) => warn!(catalog = %catalog.name, "Failed to prune version: {}", e),
+        }
+    }
+    pruned
+}
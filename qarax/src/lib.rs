@@ -5,12 +5,15 @@ pub mod grpc_client;
 pub mod handlers;
 pub mod hook_executor;
 pub mod host_deployer;
+pub mod image_catalog_refresh;
 pub mod model;
 pub mod network_policy;
 pub mod resource_monitor;
 pub mod sandbox_pool_manager;
 pub mod sandbox_reaper;
 pub mod sandbox_runtime;
+pub mod secrets;
+pub mod snapshot_gc;
 pub mod startup;
 pub mod transfer_executor;
 pub mod vm_monitor;
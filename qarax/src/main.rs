@@ -3,9 +3,10 @@ use tokio::net::TcpListener;
 use common::telemtry::{get_subscriber, init_subscriber};
 use qarax::{
     configuration::{default_control_plane_architecture, get_configuration},
-    database,
+    database, secrets,
     startup::run,
 };
+use secrecy::ExposeSecret;
 use sqlx::PgPool;
 
 #[cfg(feature = "dhat-heap")]
@@ -46,6 +47,15 @@ async fn main() -> std::io::Result<()> {
     let subscriber = get_subscriber("qarax".into(), "info".into(), std::io::stdout);
     init_subscriber(subscriber);
 
+    match &configuration.secrets.encryption_key {
+        Some(key) => {
+            secrets::init(key.expose_secret()).expect("Invalid secrets.encryption_key");
+        }
+        None => tracing::warn!(
+            "No secrets.encryption_key configured; registry credentials and CHAP secrets cannot be stored or used"
+        ),
+    }
+
     database::run_migrations(&configuration.database.connection_string())
         .await
         .expect("Failed to run migrations");
@@ -29,9 +29,12 @@ pub enum AuditAction {
     Commit,
     CreateSnapshot,
     RestoreSnapshot,
+    DeleteSnapshot,
     CreateTemplate,
     NodeUpgrade,
     Evacuate,
+    Export,
+    Import,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Display, EnumString, ToSchema, PartialEq, Eq)]
@@ -51,6 +54,7 @@ pub enum AuditResourceType {
     Transfer,
     Sandbox,
     Backup,
+    RegistryCredential,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
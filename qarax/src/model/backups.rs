@@ -169,6 +169,33 @@ ORDER BY created_at DESC
     Ok(rows.into_iter().map(Into::into).collect())
 }
 
+pub async fn find_by_snapshot(
+    pool: &PgPool,
+    snapshot_id: Uuid,
+) -> Result<Option<Backup>, sqlx::Error> {
+    let row = sqlx::query_as::<_, BackupRow>(
+        r#"
+SELECT id,
+       name,
+       backup_type,
+       status,
+       vm_id,
+       snapshot_id,
+       storage_object_id,
+       error_message,
+       created_at,
+       updated_at
+FROM backups
+WHERE snapshot_id = $1
+        "#,
+    )
+    .bind(snapshot_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(Into::into))
+}
+
 pub async fn update_status(
     pool: &PgPool,
     backup_id: Uuid,
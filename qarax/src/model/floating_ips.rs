@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct FloatingIp {
+    pub id: Uuid,
+    pub address: String,
+    pub description: Option<String>,
+    pub network_interface_id: Option<Uuid>,
+    /// VM owning the associated interface.
+    pub vm_id: Option<Uuid>,
+    /// Address of the associated interface that traffic is translated to.
+    pub vm_ip: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct FloatingIpRow {
+    id: Uuid,
+    address: String,
+    description: Option<String>,
+    network_interface_id: Option<Uuid>,
+    vm_id: Option<Uuid>,
+    vm_ip: Option<String>,
+}
+
+impl From<FloatingIpRow> for FloatingIp {
+    fn from(row: FloatingIpRow) -> Self {
+        Self {
+            id: row.id,
+            address: row.address,
+            description: row.description,
+            network_interface_id: row.network_interface_id,
+            vm_id: row.vm_id,
+            vm_ip: row.vm_ip,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct NewFloatingIp {
+    /// IPv4 address reachable on the hosts' uplink network.
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+const SELECT_FLOATING_IPS: &str = r#"
+SELECT f.id,
+       host(f.address) AS address,
+       f.description,
+       f.network_interface_id,
+       ni.vm_id,
+       host(ni.ip_address) AS vm_ip
+FROM floating_ips f
+LEFT JOIN network_interfaces ni ON ni.id = f.network_interface_id
+"#;
+
+pub async fn list(pool: &PgPool) -> Result<Vec<FloatingIp>, sqlx::Error> {
+    let rows: Vec<FloatingIpRow> =
+        sqlx::query_as(&format!("{SELECT_FLOATING_IPS} ORDER BY f.address"))
+            .fetch_all(pool)
+            .await?;
+
+    Ok(rows.into_iter().map(Into::into).collect())
+}
+
+pub async fn get(pool: &PgPool, floating_ip_id: Uuid) -> Result<FloatingIp, sqlx::Error> {
+    let row: FloatingIpRow = sqlx::query_as(&format!("{SELECT_FLOATING_IPS} WHERE f.id = $1"))
+        .bind(floating_ip_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(row.into())
+}
+
+/// Floating IPs associated with any of a VM's interfaces.
+pub async fn list_by_vm(pool: &PgPool, vm_id: Uuid) -> Result<Vec<FloatingIp>, sqlx::Error> {
+    let rows: Vec<FloatingIpRow> = sqlx::query_as(&format!(
+        "{SELECT_FLOATING_IPS} WHERE ni.vm_id = $1 ORDER BY f.address"
+    ))
+    .bind(vm_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(Into::into).collect())
+}
+
+pub async fn create(pool: &PgPool, new: NewFloatingIp) -> Result<Uuid, sqlx::Error> {
+    let id = Uuid::new_v4();
+
+    sqlx::query(
+        r#"
+INSERT INTO floating_ips (id, address, description)
+VALUES ($1, $2::inet, $3)
+        "#,
+    )
+    .bind(id)
+    .bind(&new.address)
+    .bind(&new.description)
+    .execute(pool)
+    .await?;
+
+    Ok(id)
+}
+
+pub async fn delete(pool: &PgPool, floating_ip_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM floating_ips WHERE id = $1")
+        .bind(floating_ip_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Point a floating IP at an interface, or release it with `None`.
+pub async fn set_interface(
+    pool: &PgPool,
+    floating_ip_id: Uuid,
+    network_interface_id: Option<Uuid>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE floating_ips SET network_interface_id = $2 WHERE id = $1")
+        .bind(floating_ip_id)
+        .bind(network_interface_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
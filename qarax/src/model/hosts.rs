@@ -816,9 +816,13 @@ mod tests {
                     name: name.to_string(),
                     subnet: subnet.to_string(),
                     gateway: Some(gateway.to_string()),
+                    subnet6: None,
+                    gateway6: None,
                     dns: None,
                     vpc_name: None,
                     network_type: Some("isolated".to_string()),
+                    vlan_id: None,
+                    dhcp_options: None,
                 },
             )
             .await
@@ -910,6 +914,9 @@ mod tests {
             accelerator_config: None,
             numa_config: None,
             persistent_upper_pool_id: None,
+            overlaybd_disk_size_bytes: None,
+            root_disk_mode: Default::default(),
+            root_disk_pool_id: None,
             placement_policy: None,
             config: serde_json::json!({}),
         }
@@ -0,0 +1,336 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::errors::Error;
+use crate::model::storage_pools::{self, StoragePoolType};
+
+const DEFAULT_REFRESH_INTERVAL_SECS: i32 = 86_400;
+const MIN_REFRESH_INTERVAL_SECS: i32 = 60;
+const DEFAULT_KEEP_VERSIONS: i32 = 3;
+
+/// An upstream image reference that is re-checked periodically. Every new
+/// digest is imported into the catalog's OverlayBD pool as a new version.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, sqlx::FromRow)]
+pub struct ImageCatalog {
+    pub id: Uuid,
+    pub name: String,
+    /// Upstream reference to follow, e.g. `docker.io/library/ubuntu:24.04`.
+    pub source_ref: String,
+    /// OverlayBD pool new versions are imported into.
+    pub storage_pool_id: Uuid,
+    pub refresh_interval_secs: i32,
+    /// Number of versions retained; older ones are pruned after an import.
+    pub keep_versions: i32,
+    pub last_checked_at: Option<DateTime<Utc>>,
+    /// Error from the last refresh, cleared by the next successful one.
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One imported digest of a catalog.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, sqlx::FromRow)]
+pub struct ImageCatalogVersion {
+    pub id: Uuid,
+    pub catalog_id: Uuid,
+    pub source_digest: String,
+    /// The catalog's source reference pinned to `source_digest`. VMs created
+    /// from this version use it as their `image_ref`.
+    pub image_ref: String,
+    /// OCI_IMAGE storage object holding the converted image.
+    pub storage_object_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct NewImageCatalog {
+    pub name: String,
+    pub source_ref: String,
+    pub storage_pool_id: Uuid,
+    /// Seconds between digest checks. Defaults to one day; minimum 60.
+    pub refresh_interval_secs: Option<i32>,
+    /// Versions to retain. Defaults to 3.
+    pub keep_versions: Option<i32>,
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct UpdateImageCatalog {
+    pub refresh_interval_secs: Option<i32>,
+    pub keep_versions: Option<i32>,
+}
+
+const COLUMNS: &str = "id, name, source_ref, storage_pool_id, refresh_interval_secs, \
+                       keep_versions, last_checked_at, last_error, created_at, updated_at";
+
+const VERSION_COLUMNS: &str =
+    "id, catalog_id, source_digest, image_ref, storage_object_id, created_at";
+
+/// `source_ref` pinned to `digest`. A digest already present on the source
+/// is replaced.
+pub fn pinned_ref(source_ref: &str, digest: &str) -> String {
+    let name = source_ref
+        .split_once('@')
+        .map_or(source_ref, |(name, _)| name);
+    format!("{name}@{digest}")
+}
+
+fn validate_settings(
+    refresh_interval_secs: Option<i32>,
+    keep_versions: Option<i32>,
+) -> Result<(), Error> {
+    if refresh_interval_secs.is_some_and(|secs| secs < MIN_REFRESH_INTERVAL_SECS) {
+        return Err(Error::UnprocessableEntity(format!(
+            "refresh_interval_secs must be at least {MIN_REFRESH_INTERVAL_SECS}"
+        )));
+    }
+    if keep_versions.is_some_and(|keep| keep < 1) {
+        return Err(Error::UnprocessableEntity(
+            "keep_versions must be at least 1".into(),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_source_ref(source_ref: &str) -> Result<(), Error> {
+    if source_ref.trim().is_empty() || source_ref.chars().any(char::is_whitespace) {
+        return Err(Error::UnprocessableEntity(format!(
+            "invalid source_ref '{source_ref}'"
+        )));
+    }
+    if source_ref.contains('@') {
+        return Err(Error::UnprocessableEntity(
+            "source_ref must be a tag, not a digest; a pinned reference never changes".into(),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn create(pool: &PgPool, new: &NewImageCatalog) -> Result<Uuid, Error> {
+    validate_source_ref(&new.source_ref)?;
+    validate_settings(new.refresh_interval_secs, new.keep_versions)?;
+    let storage_pool = storage_pools::get(pool, new.storage_pool_id).await?;
+    if storage_pool.pool_type != StoragePoolType::OverlayBd {
+        return Err(Error::UnprocessableEntity(format!(
+            "storage pool '{}' is not an OverlayBD pool",
+            storage_pool.name
+        )));
+    }
+
+    let id = Uuid::new_v4();
+    sqlx::query(
+        r#"
+INSERT INTO image_catalogs (id, name, source_ref, storage_pool_id, refresh_interval_secs, keep_versions)
+VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(id)
+    .bind(&new.name)
+    .bind(new.source_ref.trim())
+    .bind(new.storage_pool_id)
+    .bind(
+        new.refresh_interval_secs
+            .unwrap_or(DEFAULT_REFRESH_INTERVAL_SECS),
+    )
+    .bind(new.keep_versions.unwrap_or(DEFAULT_KEEP_VERSIONS))
+    .execute(pool)
+    .await?;
+    Ok(id)
+}
+
+pub async fn get(pool: &PgPool, id: Uuid) -> Result<ImageCatalog, sqlx::Error> {
+    sqlx::query_as::<_, ImageCatalog>(&format!(
+        "SELECT {COLUMNS} FROM image_catalogs WHERE id = $1"
+    ))
+    .bind(id)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn list(
+    pool: &PgPool,
+    name_filter: Option<&str>,
+) -> Result<Vec<ImageCatalog>, sqlx::Error> {
+    sqlx::query_as::<_, ImageCatalog>(&format!(
+        "SELECT {COLUMNS} FROM image_catalogs \
+         WHERE ($1::text IS NULL OR name = $1) ORDER BY name"
+    ))
+    .bind(name_filter)
+    .fetch_all(pool)
+    .await
+}
+
+/// Catalogs whose refresh interval has elapsed since the last check.
+pub async fn list_due(pool: &PgPool) -> Result<Vec<ImageCatalog>, sqlx::Error> {
+    sqlx::query_as::<_, ImageCatalog>(&format!(
+        "SELECT {COLUMNS} FROM image_catalogs \
+         WHERE last_checked_at IS NULL \
+            OR last_checked_at + make_interval(secs => refresh_interval_secs) <= NOW() \
+         ORDER BY last_checked_at NULLS FIRST"
+    ))
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn update(
+    pool: &PgPool,
+    id: Uuid,
+    req: &UpdateImageCatalog,
+) -> Result<ImageCatalog, Error> {
+    validate_settings(req.refresh_interval_secs, req.keep_versions)?;
+    let updated = sqlx::query_as::<_, ImageCatalog>(&format!(
+        r#"
+UPDATE image_catalogs
+SET refresh_interval_secs = COALESCE($2, refresh_interval_secs),
+    keep_versions         = COALESCE($3, keep_versions),
+    updated_at            = NOW()
+WHERE id = $1
+RETURNING {COLUMNS}
+        "#
+    ))
+    .bind(id)
+    .bind(req.refresh_interval_secs)
+    .bind(req.keep_versions)
+    .fetch_one(pool)
+    .await?;
+    Ok(updated)
+}
+
+/// Record the outcome of a refresh. `error` is `None` on success.
+pub async fn record_check(pool: &PgPool, id: Uuid, error: Option<&str>) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE image_catalogs SET last_checked_at = NOW(), last_error = $2 WHERE id = $1")
+        .bind(id)
+        .bind(error)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn delete(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+    let result = sqlx::query("DELETE FROM image_catalogs WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+    Ok(())
+}
+
+/// Versions of a catalog, newest first.
+pub async fn list_versions(
+    pool: &PgPool,
+    catalog_id: Uuid,
+) -> Result<Vec<ImageCatalogVersion>, sqlx::Error> {
+    sqlx::query_as::<_, ImageCatalogVersion>(&format!(
+        "SELECT {VERSION_COLUMNS} FROM image_catalog_versions \
+         WHERE catalog_id = $1 ORDER BY created_at DESC"
+    ))
+    .bind(catalog_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn latest_version(
+    pool: &PgPool,
+    catalog_id: Uuid,
+) -> Result<Option<ImageCatalogVersion>, sqlx::Error> {
+    sqlx::query_as::<_, ImageCatalogVersion>(&format!(
+        "SELECT {VERSION_COLUMNS} FROM image_catalog_versions \
+         WHERE catalog_id = $1 ORDER BY created_at DESC LIMIT 1"
+    ))
+    .bind(catalog_id)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn find_version(
+    pool: &PgPool,
+    catalog_id: Uuid,
+    source_digest: &str,
+) -> Result<Option<ImageCatalogVersion>, sqlx::Error> {
+    sqlx::query_as::<_, ImageCatalogVersion>(&format!(
+        "SELECT {VERSION_COLUMNS} FROM image_catalog_versions \
+         WHERE catalog_id = $1 AND source_digest = $2"
+    ))
+    .bind(catalog_id)
+    .bind(source_digest)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn create_version(
+    pool: &PgPool,
+    catalog_id: Uuid,
+    source_digest: &str,
+    image_ref: &str,
+    storage_object_id: Uuid,
+) -> Result<ImageCatalogVersion, sqlx::Error> {
+    sqlx::query_as::<_, ImageCatalogVersion>(&format!(
+        r#"
+INSERT INTO image_catalog_versions (id, catalog_id, source_digest, image_ref, storage_object_id)
+VALUES ($1, $2, $3, $4, $5)
+RETURNING {VERSION_COLUMNS}
+        "#
+    ))
+    .bind(Uuid::new_v4())
+    .bind(catalog_id)
+    .bind(source_digest)
+    .bind(image_ref)
+    .bind(storage_object_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// Versions older than the newest `keep`.
+pub async fn versions_to_prune(
+    pool: &PgPool,
+    catalog_id: Uuid,
+    keep: i32,
+) -> Result<Vec<ImageCatalogVersion>, sqlx::Error> {
+    sqlx::query_as::<_, ImageCatalogVersion>(&format!(
+        "SELECT {VERSION_COLUMNS} FROM image_catalog_versions \
+         WHERE catalog_id = $1 ORDER BY created_at DESC OFFSET $2"
+    ))
+    .bind(catalog_id)
+    .bind(i64::from(keep.max(1)))
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn delete_version(pool: &PgPool, version_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM image_catalog_versions WHERE id = $1")
+        .bind(version_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinned_ref_replaces_an_existing_digest() {
+        assert_eq!(
+            pinned_ref("docker.io/library/ubuntu:24.04", "sha256:abc"),
+            "docker.io/library/ubuntu:24.04@sha256:abc"
+        );
+        assert_eq!(
+            pinned_ref("ubuntu:24.04@sha256:old", "sha256:new"),
+            "ubuntu:24.04@sha256:new"
+        );
+    }
+
+    #[test]
+    fn source_ref_must_be_a_tag() {
+        assert!(validate_source_ref("ghcr.io/acme/base:stable").is_ok());
+        assert!(validate_source_ref("ubuntu@sha256:abc").is_err());
+        assert!(validate_source_ref("  ").is_err());
+        assert!(validate_settings(Some(59), None).is_err());
+        assert!(validate_settings(None, Some(0)).is_err());
+        assert!(validate_settings(Some(3600), Some(1)).is_ok());
+    }
+}
@@ -37,6 +37,11 @@ pub enum JobType {
     HostEvacuate,
     DiskCreate,
     VmCommit,
+    VmExport,
+    VmImport,
+    ImagePrewarm,
+    ImageGc,
+    ImageCatalogRefresh,
 }
 
 #[derive(
@@ -56,6 +61,7 @@ pub enum JobStatus {
 /// Well-known resource type values for the `resource_type` column.
 pub mod resource_types {
     pub const HOST: &str = "host";
+    pub const IMAGE_CATALOG: &str = "image_catalog";
     pub const SANDBOX: &str = "sandbox";
     pub const VM: &str = "vm";
 }
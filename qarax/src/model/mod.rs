@@ -2,14 +2,18 @@ pub mod audit_log;
 pub mod backups;
 pub mod boot_sources;
 pub mod events;
+pub mod floating_ips;
 pub mod host_gpus;
 pub mod host_numa;
 pub mod hosts;
+pub mod image_catalogs;
 pub mod instance_types;
 pub mod jobs;
 pub mod lifecycle_hooks;
 pub mod network_interfaces;
 pub mod networks;
+pub mod port_forwards;
+pub mod registry_credentials;
 pub mod sandbox_pool_members;
 pub mod sandbox_pools;
 pub mod sandboxes;
@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+
 use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Postgres, Transaction, Type, types::Json};
 use strum_macros::{Display, EnumString};
@@ -8,6 +11,77 @@ use crate::model::vms::NewVmNetwork;
 
 pub type PgTransaction<'a> = Transaction<'a, Postgres>;
 
+/// Most allowed address pairs a NIC may carry.
+pub const MAX_ALLOWED_ADDRESS_PAIRS: usize = 10;
+
+/// An extra source a NIC may send from past anti-spoofing, such as a virtual
+/// IP that moves between VRRP or keepalived peers.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct AllowedAddressPair {
+    /// IPv4 or IPv6 address or CIDR
+    pub ip_address: String,
+    /// MAC the address is sent from; the NIC's own MAC when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mac_address: Option<String>,
+}
+
+impl AllowedAddressPair {
+    /// The pair with its address written as a CIDR and its MAC in lowercase.
+    fn normalized(&self) -> Result<Self, String> {
+        let (ip, prefix_len) = match self.ip_address.split_once('/') {
+            Some((ip, prefix_len)) => (ip, Some(prefix_len)),
+            None => (self.ip_address.as_str(), None),
+        };
+        let ip: IpAddr = ip
+            .parse()
+            .map_err(|_| format!("invalid address {:?}", self.ip_address))?;
+        let max_len = if ip.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len
+                .parse::<u32>()
+                .ok()
+                .filter(|prefix_len| *prefix_len <= max_len)
+                .ok_or_else(|| format!("invalid prefix length in {:?}", self.ip_address))?,
+            None => max_len,
+        };
+        let host_bits = match ip {
+            IpAddr::V4(ip) => {
+                u128::from(u32::from(ip) & u32::MAX.checked_shr(prefix_len).unwrap_or(0))
+            }
+            IpAddr::V6(ip) => u128::from(ip) & u128::MAX.checked_shr(prefix_len).unwrap_or(0),
+        };
+        if host_bits != 0 {
+            return Err(format!("{} has host bits set", self.ip_address));
+        }
+
+        let mac_address = self
+            .mac_address
+            .as_deref()
+            .map(|mac| {
+                mac.parse::<macaddr::MacAddr6>()
+                    .map(|mac| mac.to_string().to_ascii_lowercase())
+                    .map_err(|_| format!("invalid MAC address {mac:?}"))
+            })
+            .transpose()?;
+        Ok(Self {
+            ip_address: format!("{ip}/{prefix_len}"),
+            mac_address,
+        })
+    }
+}
+
+/// Check a NIC's allowed address pairs, returning them normalized.
+pub fn normalize_allowed_address_pairs(
+    pairs: &[AllowedAddressPair],
+) -> Result<Vec<AllowedAddressPair>, String> {
+    if pairs.len() > MAX_ALLOWED_ADDRESS_PAIRS {
+        return Err(format!(
+            "a NIC can have at most {MAX_ALLOWED_ADDRESS_PAIRS} allowed address pairs"
+        ));
+    }
+    pairs.iter().map(AllowedAddressPair::normalized).collect()
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct RateLimiterConfig {
     pub bandwidth: Option<TokenBucket>,
@@ -237,14 +311,14 @@ INSERT INTO network_interfaces (
     interface_type, vhost_user, vhost_socket, vhost_mode,
     num_queues, queue_size, rate_limiter,
     offload_tso, offload_ufo, offload_csum,
-    pci_segment, iommu
+    pci_segment, iommu, allowed_address_pairs
 ) VALUES (
     $1, $2, $3, $4,
     $5, $6::macaddr, $7::macaddr, $8::inet, $9,
     $10, $11, $12, $13,
     $14, $15, $16,
     $17, $18, $19,
-    $20, $21
+    $20, $21, $22
 )
         "#,
     )
@@ -269,12 +343,61 @@ INSERT INTO network_interfaces (
     .bind(n.offload_csum.unwrap_or(true))
     .bind(n.pci_segment.unwrap_or(0))
     .bind(n.iommu.unwrap_or(false))
+    .bind(Json(&n.allowed_address_pairs))
     .execute(tx.as_mut())
     .await?;
 
     Ok(id)
 }
 
+pub async fn allowed_address_pairs(
+    pool: &PgPool,
+    interface_id: Uuid,
+) -> Result<Vec<AllowedAddressPair>, sqlx::Error> {
+    let (pairs,): (Json<Vec<AllowedAddressPair>>,) =
+        sqlx::query_as("SELECT allowed_address_pairs FROM network_interfaces WHERE id = $1")
+            .bind(interface_id)
+            .fetch_one(pool)
+            .await?;
+    Ok(pairs.0)
+}
+
+/// Allowed address pairs of every NIC of a VM, keyed by interface id.
+pub async fn allowed_address_pairs_by_vm(
+    pool: &PgPool,
+    vm_id: Uuid,
+) -> Result<HashMap<Uuid, Vec<AllowedAddressPair>>, sqlx::Error> {
+    let rows: Vec<(Uuid, Json<Vec<AllowedAddressPair>>)> =
+        sqlx::query_as("SELECT id, allowed_address_pairs FROM network_interfaces WHERE vm_id = $1")
+            .bind(vm_id)
+            .fetch_all(pool)
+            .await?;
+    Ok(rows.into_iter().map(|(id, pairs)| (id, pairs.0)).collect())
+}
+
+pub async fn update_allowed_address_pairs(
+    pool: &PgPool,
+    interface_id: Uuid,
+    pairs: &[AllowedAddressPair],
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE network_interfaces SET allowed_address_pairs = $1 WHERE id = $2")
+        .bind(Json(pairs))
+        .bind(interface_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Random locally administered unicast MAC, for interfaces whose address has
+/// to be known before the VM boots.
+pub fn random_mac() -> String {
+    let bytes = Uuid::new_v4().into_bytes();
+    format!(
+        "02:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+        bytes[0], bytes[1], bytes[2], bytes[3], bytes[4]
+    )
+}
+
 pub async fn delete(pool: &PgPool, interface_id: Uuid) -> Result<(), sqlx::Error> {
     sqlx::query("DELETE FROM network_interfaces WHERE id = $1")
         .bind(interface_id)
@@ -330,3 +453,37 @@ WHERE vm_id = $1 AND device_id = $2
     .await?;
     Ok(row.map(|r| r.into()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(ip_address: &str, mac_address: Option<&str>) -> AllowedAddressPair {
+        AllowedAddressPair {
+            ip_address: ip_address.to_string(),
+            mac_address: mac_address.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn allowed_address_pairs_normalize_to_cidrs() {
+        let pairs = normalize_allowed_address_pairs(&[
+            pair("10.0.0.100", None),
+            pair("fd00::/64", Some("02:AB:CD:00:00:01")),
+        ])
+        .unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                pair("10.0.0.100/32", None),
+                pair("fd00::/64", Some("02:ab:cd:00:00:01")),
+            ]
+        );
+
+        assert!(normalize_allowed_address_pairs(&[pair("10.0.0.5/24", None)]).is_err());
+        assert!(normalize_allowed_address_pairs(&[pair("10.0.0.0/33", None)]).is_err());
+        assert!(normalize_allowed_address_pairs(&[pair("vip", None)]).is_err());
+        assert!(normalize_allowed_address_pairs(&[pair("10.0.0.9", Some("02:00"))]).is_err());
+        assert!(normalize_allowed_address_pairs(&vec![pair("10.0.0.9", None); 11]).is_err());
+    }
+}
@@ -1,5 +1,8 @@
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
 use serde::{Deserialize, Serialize};
-use sqlx::{PgPool, Postgres, Transaction, Type};
+use sqlx::{PgPool, Postgres, Transaction, Type, types::Json};
 use strum_macros::{Display, EnumString};
 use utoipa::ToSchema;
 use uuid::Uuid;
@@ -12,6 +15,10 @@ type HostNetworkListRow = (
     Option<String>,
     Option<String>,
     Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<i32>,
+    Json<DhcpOptions>,
     NetworkStatus,
     String,
 );
@@ -24,6 +31,10 @@ type AttachedNetworkRow = (
     Option<String>,
     Option<String>,
     Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<i32>,
+    Json<DhcpOptions>,
     NetworkStatus,
     Uuid,
     String,
@@ -37,23 +48,103 @@ pub struct Network {
     pub name: String,
     pub subnet: String,
     pub gateway: Option<String>,
+    /// IPv6 /64 of a dual-stack network.
+    pub subnet6: Option<String>,
+    pub gateway6: Option<String>,
     pub dns: Option<String>,
     pub vpc_name: Option<String>,
     #[serde(rename = "type")]
     pub network_type: Option<String>,
+    /// 802.1Q tag of a `vlan` network.
+    pub vlan_id: Option<i32>,
+    pub dhcp_options: DhcpOptions,
     pub status: NetworkStatus,
 }
 
+/// Options the network's DHCP server hands out next to the address, gateway
+/// and nameserver.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, ToSchema)]
+pub struct DhcpOptions {
+    /// DNS search domain (option 15). Defaults to the network's built-in
+    /// `<network>.internal` zone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain_name: Option<String>,
+    /// IPv4 NTP servers (option 42).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ntp_servers: Vec<String>,
+    /// Interface MTU (option 26).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtu: Option<u16>,
+    /// Classless static routes (option 121), in addition to the default route.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub static_routes: Vec<DhcpStaticRoute>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct DhcpStaticRoute {
+    /// IPv4 CIDR, e.g. `10.50.0.0/16`.
+    pub destination: String,
+    /// Next hop, which must be on the network's subnet.
+    pub gateway: String,
+}
+
+/// `type` of a network whose subnet spans every attached host over VXLAN.
+pub const STRETCHED_NETWORK_TYPE: &str = "stretched";
+
+/// `type` of a provider network carried as a tagged VLAN on a host uplink.
+pub const VLAN_NETWORK_TYPE: &str = "vlan";
+
+impl Network {
+    /// Whether one subnet spans every attached host over a VXLAN segment,
+    /// with the same gateway on each host.
+    pub fn is_stretched(&self) -> bool {
+        self.network_type.as_deref() == Some(STRETCHED_NETWORK_TYPE)
+    }
+
+    /// Whether the network is an 802.1Q VLAN on the hosts' uplink.
+    pub fn is_vlan(&self) -> bool {
+        self.network_type.as_deref() == Some(VLAN_NETWORK_TYPE)
+    }
+
+    /// IPv4 address of the bridge, defaulting to the first usable one in `subnet`.
+    pub fn ipv4_gateway(&self) -> String {
+        self.gateway
+            .clone()
+            .unwrap_or_else(|| default_gateway(&self.subnet))
+    }
+
+    /// MAC every host gives a stretched network's bridge, so a migrated
+    /// guest's neighbour entry for the gateway stays valid on the new host.
+    pub fn anycast_gateway_mac(&self) -> String {
+        let id = self.id.as_bytes();
+        format!(
+            "02:71:{:02x}:{:02x}:{:02x}:{:02x}",
+            id[0], id[1], id[2], id[3]
+        )
+    }
+
+    /// IPv6 address of the bridge, defaulting to the first one in `subnet6`.
+    pub fn ipv6_gateway(&self) -> Option<String> {
+        self.gateway6
+            .clone()
+            .or_else(|| self.subnet6.as_deref().and_then(default_gateway6))
+    }
+}
+
 #[derive(sqlx::FromRow)]
 struct NetworkRow {
     id: Uuid,
     name: String,
     subnet: String,
     gateway: Option<String>,
+    subnet6: Option<String>,
+    gateway6: Option<String>,
     dns: Option<String>,
     vpc_name: Option<String>,
     #[sqlx(rename = "type")]
     network_type: Option<String>,
+    vlan_id: Option<i32>,
+    dhcp_options: Json<DhcpOptions>,
     status: NetworkStatus,
 }
 
@@ -68,9 +159,13 @@ impl From<NetworkRow> for Network {
             name: row.name,
             subnet: row.subnet,
             gateway: normalize_inet(row.gateway),
+            subnet6: row.subnet6,
+            gateway6: normalize_inet(row.gateway6),
             dns: normalize_inet(row.dns),
             vpc_name: row.vpc_name,
             network_type: row.network_type,
+            vlan_id: row.vlan_id,
+            dhcp_options: row.dhcp_options.0,
             status: row.status,
         }
     }
@@ -94,12 +189,23 @@ pub struct NewNetwork {
     pub subnet: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub gateway: Option<String>,
+    /// Optional IPv6 /64 for a dual-stack network.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subnet6: Option<String>,
+    /// Defaults to the first address of `subnet6`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gateway6: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dns: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub vpc_name: Option<String>,
     #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub network_type: Option<String>,
+    /// 802.1Q tag (1-4094), required for `vlan` networks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vlan_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dhcp_options: Option<DhcpOptions>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -108,6 +214,8 @@ pub struct IpAllocation {
     pub network_id: Uuid,
     pub ip_address: String,
     pub vm_id: Option<Uuid>,
+    /// Set for IPv6 addresses, which belong to a single interface.
+    pub interface_id: Option<Uuid>,
     pub allocated_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -126,6 +234,7 @@ struct IpAllocationRow {
     network_id: Uuid,
     ip_address: String,
     vm_id: Option<Uuid>,
+    interface_id: Option<Uuid>,
     allocated_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -136,6 +245,7 @@ impl From<IpAllocationRow> for IpAllocation {
             network_id: row.network_id,
             ip_address: row.ip_address,
             vm_id: row.vm_id,
+            interface_id: row.interface_id,
             allocated_at: row.allocated_at,
         }
     }
@@ -148,7 +258,7 @@ pub type PgTransaction<'a> = Transaction<'a, Postgres>;
 pub async fn list(pool: &PgPool, name_filter: Option<&str>) -> Result<Vec<Network>, sqlx::Error> {
     let rows: Vec<NetworkRow> = sqlx::query_as::<_, NetworkRow>(
         r#"
-SELECT id, name, subnet::text, gateway::text, dns::text, vpc_name, type, status
+SELECT id, name, subnet::text, gateway::text, subnet6::text, gateway6::text, dns::text, vpc_name, type, vlan_id, dhcp_options, status
 FROM networks
 WHERE ($1::text IS NULL OR name = $1)
         "#,
@@ -163,7 +273,7 @@ WHERE ($1::text IS NULL OR name = $1)
 pub async fn get(pool: &PgPool, network_id: Uuid) -> Result<Network, sqlx::Error> {
     let row: NetworkRow = sqlx::query_as::<_, NetworkRow>(
         r#"
-SELECT id, name, subnet::text, gateway::text, dns::text, vpc_name, type, status
+SELECT id, name, subnet::text, gateway::text, subnet6::text, gateway6::text, dns::text, vpc_name, type, vlan_id, dhcp_options, status
 FROM networks
 WHERE id = $1
         "#,
@@ -180,8 +290,8 @@ pub async fn create(pool: &PgPool, new: NewNetwork) -> Result<Uuid, sqlx::Error>
 
     sqlx::query(
         r#"
-INSERT INTO networks (id, name, subnet, gateway, dns, vpc_name, type, status)
-VALUES ($1, $2, $3::cidr, $4::inet, $5::inet, $6, $7, $8)
+INSERT INTO networks (id, name, subnet, gateway, dns, vpc_name, type, status, subnet6, gateway6, vlan_id, dhcp_options)
+VALUES ($1, $2, $3::cidr, $4::inet, $5::inet, $6, $7, $8, $9::cidr, $10::inet, $11, $12)
         "#,
     )
     .bind(id)
@@ -192,6 +302,10 @@ VALUES ($1, $2, $3::cidr, $4::inet, $5::inet, $6, $7, $8)
     .bind(&new.vpc_name)
     .bind(&new.network_type)
     .bind(NetworkStatus::Active)
+    .bind(&new.subnet6)
+    .bind(&new.gateway6)
+    .bind(new.vlan_id)
+    .bind(Json(new.dhcp_options.unwrap_or_default()))
     .execute(pool)
     .await?;
 
@@ -288,9 +402,13 @@ SELECT n.id,
        n.name,
        n.subnet::text,
        n.gateway::text,
+       n.subnet6::text,
+       n.gateway6::text,
        n.dns::text,
        n.vpc_name,
        n.type,
+       n.vlan_id,
+       n.dhcp_options,
        n.status,
        hn.bridge_name
 FROM networks n
@@ -306,16 +424,34 @@ ORDER BY n.name
     Ok(rows
         .into_iter()
         .map(
-            |(id, name, subnet, gateway, dns, vpc_name, network_type, status, bridge_name)| {
+            |(
+                id,
+                name,
+                subnet,
+                gateway,
+                subnet6,
+                gateway6,
+                dns,
+                vpc_name,
+                network_type,
+                vlan_id,
+                dhcp_options,
+                status,
+                bridge_name,
+            )| {
                 (
                     Network {
                         id,
                         name,
                         subnet,
                         gateway: gateway.map(|v| v.split('/').next().unwrap_or(&v).to_string()),
+                        subnet6,
+                        gateway6: gateway6.map(|v| v.split('/').next().unwrap_or(&v).to_string()),
                         dns: dns.map(|v| v.split('/').next().unwrap_or(&v).to_string()),
                         vpc_name,
                         network_type,
+                        vlan_id,
+                        dhcp_options: dhcp_options.0,
                         status,
                     },
                     bridge_name,
@@ -332,9 +468,13 @@ SELECT n.id,
        n.name,
        n.subnet::text,
        n.gateway::text,
+       n.subnet6::text,
+       n.gateway6::text,
        n.dns::text,
        n.vpc_name,
        n.type,
+       n.vlan_id,
+       n.dhcp_options,
        n.status,
        h.id,
        h.address,
@@ -357,9 +497,13 @@ ORDER BY h.id, n.name
                 name,
                 subnet,
                 gateway,
+                subnet6,
+                gateway6,
                 dns,
                 vpc_name,
                 network_type,
+                vlan_id,
+                dhcp_options,
                 status,
                 host_id,
                 host_address,
@@ -375,9 +519,13 @@ ORDER BY h.id, n.name
                     name,
                     subnet,
                     gateway: gateway.map(|v| v.split('/').next().unwrap_or(&v).to_string()),
+                    subnet6,
+                    gateway6: gateway6.map(|v| v.split('/').next().unwrap_or(&v).to_string()),
                     dns: dns.map(|v| v.split('/').next().unwrap_or(&v).to_string()),
                     vpc_name,
                     network_type,
+                    vlan_id,
+                    dhcp_options: dhcp_options.0,
                     status,
                 },
             },
@@ -403,6 +551,220 @@ ORDER BY hn.host_id
 
 // IPAM
 
+/// Compute a default gateway from a CIDR (first usable address).
+pub fn default_gateway(subnet: &str) -> String {
+    if let Some((base, _prefix)) = subnet.split_once('/') {
+        let octets: Vec<u8> = base.split('.').filter_map(|o| o.parse().ok()).collect();
+        if octets.len() == 4 {
+            return format!(
+                "{}.{}.{}.{}",
+                octets[0],
+                octets[1],
+                octets[2],
+                octets[3] + 1
+            );
+        }
+    }
+    "10.0.0.1".to_string()
+}
+
+/// Compute DHCP range: start at the first usable host after the gateway, end at broadcast-1.
+/// The start must align with `next_available_ip` (which also starts at network_addr+1 and
+/// skips the gateway) so the API-allocated IP matches what the DHCP server actually hands out.
+pub fn compute_dhcp_range(subnet: &str, gateway: Option<&str>) -> (String, String) {
+    if let Some((base, prefix_str)) = subnet.split_once('/') {
+        let octets: Vec<u8> = base.split('.').filter_map(|o| o.parse().ok()).collect();
+        let prefix_len: u32 = prefix_str.parse().unwrap_or(24);
+        if octets.len() == 4 {
+            let base_u32 = (octets[0] as u32) << 24
+                | (octets[1] as u32) << 16
+                | (octets[2] as u32) << 8
+                | octets[3] as u32;
+            let host_bits = 32 - prefix_len;
+            let network_addr = base_u32 & (u32::MAX << host_bits);
+            let broadcast_addr = network_addr | ((1u32 << host_bits) - 1);
+
+            // Parse the gateway IP so we can skip it when selecting the range start.
+            let gw_u32: Option<u32> = gateway.and_then(|gw| {
+                let parts: Vec<u8> = gw.split('.').filter_map(|o| o.parse().ok()).collect();
+                if parts.len() == 4 {
+                    Some(
+                        (parts[0] as u32) << 24
+                            | (parts[1] as u32) << 16
+                            | (parts[2] as u32) << 8
+                            | parts[3] as u32,
+                    )
+                } else {
+                    None
+                }
+            });
+
+            // Start at network_addr+1, skip the gateway — mirrors next_available_ip logic.
+            let mut start = network_addr + 1;
+            if gw_u32 == Some(start) {
+                start += 1;
+            }
+            let end = broadcast_addr - 1; // .254 for /24
+
+            let fmt = |addr: u32| {
+                format!(
+                    "{}.{}.{}.{}",
+                    (addr >> 24) & 0xFF,
+                    (addr >> 16) & 0xFF,
+                    (addr >> 8) & 0xFF,
+                    addr & 0xFF
+                )
+            };
+
+            return (fmt(start), fmt(end));
+        }
+    }
+    ("10.0.0.10".to_string(), "10.0.0.254".to_string())
+}
+
+/// Check the IPv6 half of a new network. The prefix has to be a /64 because
+/// guests autoconfigure their address from the node's router advertisements.
+pub fn validate_ipv6(subnet6: Option<&str>, gateway6: Option<&str>) -> Result<(), String> {
+    let Some(subnet6) = subnet6 else {
+        return match gateway6 {
+            Some(_) => Err("gateway6 requires subnet6".to_string()),
+            None => Ok(()),
+        };
+    };
+    let prefix = parse_prefix64(subnet6)?;
+    if let Some(gateway6) = gateway6 {
+        let gateway: Ipv6Addr = gateway6
+            .parse()
+            .map_err(|_| format!("invalid gateway6 {gateway6:?}"))?;
+        if u128::from(gateway) >> 64 != u128::from(prefix) >> 64 {
+            return Err(format!("gateway6 {gateway6} is outside {subnet6}"));
+        }
+    }
+    Ok(())
+}
+
+/// Check that a VLAN id comes with, and only with, a `vlan` network type.
+pub fn validate_vlan(network_type: Option<&str>, vlan_id: Option<i32>) -> Result<(), String> {
+    match (network_type == Some(VLAN_NETWORK_TYPE), vlan_id) {
+        (true, None) => Err("vlan networks require vlan_id".to_string()),
+        (true, Some(id)) if !(1..=4094).contains(&id) => {
+            Err(format!("vlan_id {id} is outside 1-4094"))
+        }
+        (false, Some(_)) => Err("vlan_id is only valid for vlan networks".to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Check the extra DHCP options of a new network. Static routes must point
+/// at a next hop the guest can reach directly.
+pub fn validate_dhcp_options(subnet: &str, options: &DhcpOptions) -> Result<(), String> {
+    if let Some(domain) = options.domain_name.as_deref() {
+        let valid = domain.len() <= 253
+            && domain.split('.').all(|label| {
+                !label.is_empty()
+                    && label.len() <= 63
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+            });
+        if !valid {
+            return Err(format!("invalid domain_name {domain:?}"));
+        }
+    }
+    for server in &options.ntp_servers {
+        server
+            .parse::<Ipv4Addr>()
+            .map_err(|_| format!("invalid NTP server {server:?}: expected an IPv4 address"))?;
+    }
+    if let Some(mtu) = options.mtu
+        && !(576..=9216).contains(&mtu)
+    {
+        return Err(format!("mtu {mtu} is outside 576-9216"));
+    }
+
+    let (network, prefix_len) =
+        parse_ipv4_cidr(subnet).ok_or_else(|| format!("invalid subnet {subnet:?}"))?;
+    for route in &options.static_routes {
+        parse_ipv4_cidr(&route.destination)
+            .ok_or_else(|| format!("invalid route destination {:?}", route.destination))?;
+        let gateway: Ipv4Addr = route
+            .gateway
+            .parse()
+            .map_err(|_| format!("invalid route gateway {:?}", route.gateway))?;
+        if !ipv4_in_subnet(gateway, network, prefix_len) {
+            return Err(format!(
+                "route gateway {} is outside {subnet}",
+                route.gateway
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn parse_ipv4_cidr(cidr: &str) -> Option<(Ipv4Addr, u8)> {
+    let (ip, prefix_len) = cidr.split_once('/')?;
+    let prefix_len: u8 = prefix_len.parse().ok()?;
+    (prefix_len <= 32).then_some((ip.parse().ok()?, prefix_len))
+}
+
+fn ipv4_in_subnet(ip: Ipv4Addr, network: Ipv4Addr, prefix_len: u8) -> bool {
+    let mask = u32::MAX
+        .checked_shl(32 - u32::from(prefix_len))
+        .unwrap_or(0);
+    u32::from(ip) & mask == u32::from(network) & mask
+}
+
+fn parse_prefix64(subnet6: &str) -> Result<Ipv6Addr, String> {
+    let (ip, prefix_len) = subnet6
+        .split_once('/')
+        .ok_or_else(|| format!("invalid subnet6 {subnet6:?}: missing prefix length"))?;
+    let ip: Ipv6Addr = ip
+        .parse()
+        .map_err(|_| format!("invalid subnet6 {subnet6:?}: bad IPv6 address"))?;
+    if prefix_len != "64" {
+        return Err(format!("subnet6 {subnet6} must be a /64"));
+    }
+    if u128::from(ip) & u128::from(u64::MAX) != 0 {
+        return Err(format!("subnet6 {subnet6} has host bits set"));
+    }
+    Ok(ip)
+}
+
+/// First address of the prefix, used for the bridge when no `gateway6` is set.
+pub fn default_gateway6(subnet6: &str) -> Option<String> {
+    let prefix = parse_prefix64(subnet6).ok()?;
+    Some(Ipv6Addr::from(u128::from(prefix) | 1).to_string())
+}
+
+/// The SLAAC address a guest with `mac` picks in `subnet6` (modified EUI-64,
+/// RFC 4291 appendix A).
+pub fn eui64_address(subnet6: &str, mac: &str) -> Option<Ipv6Addr> {
+    let prefix = parse_prefix64(subnet6).ok()?;
+    let mac: Vec<u8> = mac
+        .split(':')
+        .map(|octet| u8::from_str_radix(octet, 16))
+        .collect::<Result<_, _>>()
+        .ok()?;
+    if mac.len() != 6 {
+        return None;
+    }
+    let interface_id = u64::from_be_bytes([
+        mac[0] ^ 0x02,
+        mac[1],
+        mac[2],
+        0xff,
+        0xfe,
+        mac[3],
+        mac[4],
+        mac[5],
+    ]);
+    Some(Ipv6Addr::from(
+        u128::from(prefix) | u128::from(interface_id),
+    ))
+}
+
 pub async fn allocate_ip(
     pool: &PgPool,
     network_id: Uuid,
@@ -415,7 +777,7 @@ INSERT INTO ip_allocations (network_id, ip_address, vm_id)
 VALUES ($1, $2::inet, $3)
 ON CONFLICT (network_id, ip_address) DO UPDATE
     SET vm_id = EXCLUDED.vm_id, allocated_at = now()
-RETURNING id, network_id, ip_address::text, vm_id, allocated_at
+RETURNING id, network_id, ip_address::text, vm_id, interface_id, allocated_at
         "#,
     )
     .bind(network_id)
@@ -438,7 +800,7 @@ pub async fn try_allocate_ip_tx(
 INSERT INTO ip_allocations (network_id, ip_address, vm_id)
 VALUES ($1, $2::inet, $3)
 ON CONFLICT (network_id, ip_address) DO NOTHING
-RETURNING id, network_id, ip_address::text, vm_id, allocated_at
+RETURNING id, network_id, ip_address::text, vm_id, interface_id, allocated_at
         "#,
     )
     .bind(network_id)
@@ -456,7 +818,7 @@ pub async fn list_allocations_by_vm(
 ) -> Result<Vec<IpAllocation>, sqlx::Error> {
     let rows = sqlx::query_as::<_, IpAllocationRow>(
         r#"
-SELECT id, network_id, ip_address::text, vm_id, allocated_at
+SELECT id, network_id, ip_address::text, vm_id, interface_id, allocated_at
 FROM ip_allocations
 WHERE vm_id = $1
         "#,
@@ -475,7 +837,7 @@ pub async fn get_allocation(
 ) -> Result<Option<IpAllocation>, sqlx::Error> {
     let row = sqlx::query_as::<_, IpAllocationRow>(
         r#"
-SELECT id, network_id, ip_address::text, vm_id, allocated_at
+SELECT id, network_id, ip_address::text, vm_id, interface_id, allocated_at
 FROM ip_allocations
 WHERE network_id = $1 AND ip_address = $2::inet
         "#,
@@ -533,7 +895,7 @@ pub async fn list_allocations(
 ) -> Result<Vec<IpAllocation>, sqlx::Error> {
     let rows = sqlx::query_as::<_, IpAllocationRow>(
         r#"
-SELECT id, network_id, ip_address::text, vm_id, allocated_at
+SELECT id, network_id, ip_address::text, vm_id, interface_id, allocated_at
 FROM ip_allocations
 WHERE network_id = $1
 ORDER BY ip_address
@@ -546,6 +908,117 @@ ORDER BY ip_address
     Ok(rows.into_iter().map(|r| r.into()).collect())
 }
 
+pub async fn try_allocate_ipv6_tx(
+    tx: &mut PgTransaction<'_>,
+    network_id: Uuid,
+    ip_address: &str,
+    vm_id: Uuid,
+    interface_id: Uuid,
+) -> Result<Option<IpAllocation>, sqlx::Error> {
+    let row = sqlx::query_as::<_, IpAllocationRow>(
+        r#"
+INSERT INTO ip_allocations (network_id, ip_address, vm_id, interface_id)
+VALUES ($1, $2::inet, $3, $4)
+ON CONFLICT (network_id, ip_address) DO NOTHING
+RETURNING id, network_id, ip_address::text, vm_id, interface_id, allocated_at
+        "#,
+    )
+    .bind(network_id)
+    .bind(ip_address)
+    .bind(vm_id)
+    .bind(interface_id)
+    .fetch_optional(tx.as_mut())
+    .await?;
+
+    Ok(row.map(Into::into))
+}
+
+/// IPv6 addresses of a VM's interfaces, keyed by interface id.
+pub async fn ipv6_addresses_for_vm(
+    pool: &PgPool,
+    vm_id: Uuid,
+) -> Result<HashMap<Uuid, String>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (Uuid, String)>(
+        r#"
+SELECT interface_id, host(ip_address)
+FROM ip_allocations
+WHERE vm_id = $1 AND interface_id IS NOT NULL AND family(ip_address) = 6
+        "#,
+    )
+    .bind(vm_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().collect())
+}
+
+/// MAC of every NIC on a network whose VM is placed on a host, with that host.
+pub async fn nic_locations(
+    pool: &PgPool,
+    network_id: Uuid,
+) -> Result<Vec<(String, Uuid)>, sqlx::Error> {
+    sqlx::query_as(
+        r#"
+SELECT ni.mac_address::text, v.host_id
+FROM network_interfaces ni
+JOIN vms v ON v.id = ni.vm_id
+WHERE ni.network_id = $1
+  AND ni.mac_address IS NOT NULL
+  AND v.host_id IS NOT NULL
+        "#,
+    )
+    .bind(network_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Name, IPv4 and IPv6 address of every addressed NIC on a network, for the
+/// network's DNS zone.
+pub async fn dns_records(
+    pool: &PgPool,
+    network_id: Uuid,
+) -> Result<Vec<(String, Option<String>, Option<String>)>, sqlx::Error> {
+    sqlx::query_as(
+        r#"
+SELECT v.name,
+       host(ni.ip_address),
+       (SELECT host(a.ip_address)
+        FROM ip_allocations a
+        WHERE a.interface_id = ni.id AND family(a.ip_address) = 6
+        LIMIT 1)
+FROM network_interfaces ni
+JOIN vms v ON v.id = ni.vm_id
+WHERE ni.network_id = $1
+ORDER BY v.name, ni.device_id
+        "#,
+    )
+    .bind(network_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// MAC, IPv4 address and VM name of every NIC on a network that the DHCP
+/// server should pin.
+pub async fn dhcp_reservations(
+    pool: &PgPool,
+    network_id: Uuid,
+) -> Result<Vec<(String, String, String)>, sqlx::Error> {
+    sqlx::query_as(
+        r#"
+SELECT ni.mac_address::text, host(ni.ip_address), v.name
+FROM network_interfaces ni
+JOIN vms v ON v.id = ni.vm_id
+WHERE ni.network_id = $1
+  AND ni.mac_address IS NOT NULL
+  AND family(ni.ip_address) = 4
+ORDER BY ni.mac_address
+        "#,
+    )
+    .bind(network_id)
+    .fetch_all(pool)
+    .await
+}
+
 /// Find the next available IP in the network's subnet.
 /// Skips .0 (network), the gateway, and the broadcast address.
 pub async fn next_available_ip(
@@ -590,7 +1063,7 @@ pub async fn next_available_ip(
 
     // Get already-allocated IPs
     let allocated: Vec<String> = sqlx::query_as::<_, (String,)>(
-        "SELECT ip_address::text FROM ip_allocations WHERE network_id = $1",
+        "SELECT ip_address::text FROM ip_allocations WHERE network_id = $1 AND family(ip_address) = 4",
     )
     .bind(network_id)
     .fetch_all(pool)
@@ -624,3 +1097,68 @@ pub async fn next_available_ip(
 
     Ok(None)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eui64_address_matches_slaac() {
+        assert_eq!(
+            eui64_address("fd00:1:2:3::/64", "52:54:00:12:34:56"),
+            Some("fd00:1:2:3:5054:ff:fe12:3456".parse().unwrap())
+        );
+        assert_eq!(eui64_address("fd00:1:2:3::/64", "not-a-mac"), None);
+    }
+
+    #[test]
+    fn ipv6_settings_require_a_slash_64() {
+        assert!(validate_ipv6(None, None).is_ok());
+        assert!(validate_ipv6(Some("fd00:1::/64"), Some("fd00:1::1")).is_ok());
+        assert!(validate_ipv6(Some("fd00:1::/48"), None).is_err());
+        assert!(validate_ipv6(Some("fd00:1::5/64"), None).is_err());
+        assert!(validate_ipv6(Some("fd00:1::/64"), Some("fd00:2::1")).is_err());
+        assert!(validate_ipv6(None, Some("fd00:1::1")).is_err());
+        assert_eq!(
+            default_gateway6("fd00:1::/64").as_deref(),
+            Some("fd00:1::1")
+        );
+    }
+
+    #[test]
+    fn vlan_id_goes_with_the_vlan_type() {
+        assert!(validate_vlan(Some("vlan"), Some(100)).is_ok());
+        assert!(validate_vlan(Some("bridge"), None).is_ok());
+        assert!(validate_vlan(Some("vlan"), None).is_err());
+        assert!(validate_vlan(Some("vlan"), Some(4095)).is_err());
+        assert!(validate_vlan(Some("isolated"), Some(100)).is_err());
+    }
+
+    #[test]
+    fn dhcp_route_gateways_must_be_on_link() {
+        let mut options = DhcpOptions {
+            domain_name: Some("corp.example".into()),
+            ntp_servers: vec!["10.0.0.2".into()],
+            mtu: Some(1450),
+            static_routes: vec![DhcpStaticRoute {
+                destination: "10.50.0.0/16".into(),
+                gateway: "10.0.0.254".into(),
+            }],
+        };
+        assert!(validate_dhcp_options("10.0.0.0/24", &options).is_ok());
+
+        options.static_routes[0].gateway = "10.0.1.254".into();
+        assert!(validate_dhcp_options("10.0.0.0/24", &options).is_err());
+
+        let options = DhcpOptions {
+            mtu: Some(100),
+            ..DhcpOptions::default()
+        };
+        assert!(validate_dhcp_options("10.0.0.0/24", &options).is_err());
+        let options = DhcpOptions {
+            domain_name: Some("-bad.example".into()),
+            ..DhcpOptions::default()
+        };
+        assert!(validate_dhcp_options("10.0.0.0/24", &options).is_err());
+    }
+}
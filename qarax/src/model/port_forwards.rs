@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Type};
+use strum_macros::{Display, EnumString};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq, Type, EnumString, Display, ToSchema,
+)]
+#[sqlx(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(type_name = "port_forward_protocol")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum PortForwardProtocol {
+    Tcp,
+    Udp,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct PortForward {
+    pub id: Uuid,
+    pub network_interface_id: Uuid,
+    pub vm_id: Uuid,
+    pub protocol: PortForwardProtocol,
+    /// Port on every local address of the host running the VM.
+    pub host_port: i32,
+    pub vm_port: i32,
+    /// Address of the interface that traffic is forwarded to.
+    pub vm_ip: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct PortForwardRow {
+    id: Uuid,
+    network_interface_id: Uuid,
+    vm_id: Uuid,
+    protocol: PortForwardProtocol,
+    host_port: i32,
+    vm_port: i32,
+    vm_ip: Option<String>,
+    description: Option<String>,
+}
+
+impl From<PortForwardRow> for PortForward {
+    fn from(row: PortForwardRow) -> Self {
+        Self {
+            id: row.id,
+            network_interface_id: row.network_interface_id,
+            vm_id: row.vm_id,
+            protocol: row.protocol,
+            host_port: row.host_port,
+            vm_port: row.vm_port,
+            vm_ip: row.vm_ip,
+            description: row.description,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct NewPortForward {
+    pub vm_id: Uuid,
+    /// Defaults to the VM's first interface with an address on a managed network.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_interface_id: Option<Uuid>,
+    pub protocol: PortForwardProtocol,
+    pub host_port: i32,
+    /// Defaults to `host_port`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vm_port: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+const SELECT_PORT_FORWARDS: &str = r#"
+SELECT pf.id,
+       pf.network_interface_id,
+       ni.vm_id,
+       pf.protocol,
+       pf.host_port,
+       pf.vm_port,
+       host(ni.ip_address) AS vm_ip,
+       pf.description
+FROM port_forwards pf
+JOIN network_interfaces ni ON ni.id = pf.network_interface_id
+"#;
+
+pub async fn list(pool: &PgPool, vm_id: Option<Uuid>) -> Result<Vec<PortForward>, sqlx::Error> {
+    let rows: Vec<PortForwardRow> = sqlx::query_as(&format!(
+        "{SELECT_PORT_FORWARDS} WHERE ($1::uuid IS NULL OR ni.vm_id = $1) \
+         ORDER BY pf.protocol, pf.host_port"
+    ))
+    .bind(vm_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(Into::into).collect())
+}
+
+pub async fn get(pool: &PgPool, port_forward_id: Uuid) -> Result<PortForward, sqlx::Error> {
+    let row: PortForwardRow = sqlx::query_as(&format!("{SELECT_PORT_FORWARDS} WHERE pf.id = $1"))
+        .bind(port_forward_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(row.into())
+}
+
+pub async fn create(
+    pool: &PgPool,
+    network_interface_id: Uuid,
+    new: &NewPortForward,
+    vm_port: i32,
+) -> Result<Uuid, sqlx::Error> {
+    let id = Uuid::new_v4();
+
+    sqlx::query(
+        r#"
+INSERT INTO port_forwards (id, network_interface_id, protocol, host_port, vm_port, description)
+VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(id)
+    .bind(network_interface_id)
+    .bind(new.protocol)
+    .bind(new.host_port)
+    .bind(vm_port)
+    .bind(&new.description)
+    .execute(pool)
+    .await?;
+
+    Ok(id)
+}
+
+pub async fn delete(pool: &PgPool, port_forward_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM port_forwards WHERE id = $1")
+        .bind(port_forward_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,276 @@
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::errors::Error;
+use crate::secrets;
+
+/// A stored registry login. The password is write-only and never returned.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, sqlx::FromRow)]
+pub struct RegistryCredential {
+    pub id: Uuid,
+    pub name: String,
+    /// Registry host[:port] the login applies to, e.g. `ghcr.io`.
+    pub registry: String,
+    pub username: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct NewRegistryCredential {
+    pub name: String,
+    /// Registry host[:port]; a URL is accepted and reduced to its host.
+    pub registry: String,
+    pub username: String,
+    /// Password or access token. Stored encrypted.
+    #[schema(value_type = String, format = Password)]
+    pub password: Secret<String>,
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct UpdateRegistryCredential {
+    pub username: Option<String>,
+    #[schema(value_type = Option<String>, format = Password)]
+    pub password: Option<Secret<String>>,
+}
+
+/// A decrypted login ready to hand to a node. `Debug` redacts the password.
+#[derive(Debug, Clone)]
+pub struct RegistryLogin {
+    pub registry: String,
+    pub username: String,
+    pub password: Secret<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct RegistryLoginRow {
+    id: Uuid,
+    registry: String,
+    username: String,
+    password_encrypted: Vec<u8>,
+}
+
+/// Reduce a registry URL or host to the form logins are matched on. Docker
+/// Hub is reachable under several names; they all fold into `docker.io`.
+pub fn normalize_registry(registry: &str) -> Result<String, String> {
+    let trimmed = registry.trim();
+    let host = trimmed
+        .strip_prefix("https://")
+        .or_else(|| trimmed.strip_prefix("http://"))
+        .unwrap_or(trimmed)
+        .trim_end_matches('/')
+        .to_ascii_lowercase();
+    if host.is_empty()
+        || host.contains('/')
+        || host.contains('@')
+        || host.chars().any(char::is_whitespace)
+    {
+        return Err(format!(
+            "invalid registry '{registry}'; expected host[:port]"
+        ));
+    }
+    Ok(match host.as_str() {
+        "index.docker.io" | "registry-1.docker.io" | "registry.hub.docker.com" => {
+            "docker.io".to_string()
+        }
+        _ => host,
+    })
+}
+
+/// Registry host an image reference is pulled from (`docker.io` when the
+/// reference names no registry).
+pub fn registry_of_image(image_ref: &str) -> String {
+    match image_ref.split_once('/') {
+        Some((first, _)) if first.contains('.') || first.contains(':') || first == "localhost" => {
+            normalize_registry(first).unwrap_or_else(|_| first.to_string())
+        }
+        _ => "docker.io".to_string(),
+    }
+}
+
+fn seal(id: Uuid, password: &Secret<String>) -> Result<Vec<u8>, Error> {
+    Ok(secrets::cipher()?.encrypt(password.expose_secret().as_bytes(), id.as_bytes())?)
+}
+
+const COLUMNS: &str = "id, name, registry, username, created_at, updated_at";
+
+pub async fn create(pool: &PgPool, new: &NewRegistryCredential) -> Result<Uuid, Error> {
+    let registry = normalize_registry(&new.registry).map_err(Error::UnprocessableEntity)?;
+    if new.username.trim().is_empty() {
+        return Err(Error::UnprocessableEntity(
+            "username must not be empty".into(),
+        ));
+    }
+
+    let id = Uuid::new_v4();
+    let password_encrypted = seal(id, &new.password)?;
+    sqlx::query(
+        r#"
+INSERT INTO registry_credentials (id, name, registry, username, password_encrypted)
+VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(id)
+    .bind(&new.name)
+    .bind(&registry)
+    .bind(&new.username)
+    .bind(&password_encrypted)
+    .execute(pool)
+    .await?;
+    Ok(id)
+}
+
+pub async fn get(pool: &PgPool, id: Uuid) -> Result<RegistryCredential, sqlx::Error> {
+    sqlx::query_as::<_, RegistryCredential>(&format!(
+        "SELECT {COLUMNS} FROM registry_credentials WHERE id = $1"
+    ))
+    .bind(id)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn list(
+    pool: &PgPool,
+    name_filter: Option<&str>,
+) -> Result<Vec<RegistryCredential>, sqlx::Error> {
+    sqlx::query_as::<_, RegistryCredential>(&format!(
+        "SELECT {COLUMNS} FROM registry_credentials \
+         WHERE ($1::text IS NULL OR name = $1) ORDER BY name"
+    ))
+    .bind(name_filter)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn update(
+    pool: &PgPool,
+    id: Uuid,
+    req: &UpdateRegistryCredential,
+) -> Result<RegistryCredential, Error> {
+    if req
+        .username
+        .as_deref()
+        .is_some_and(|username| username.trim().is_empty())
+    {
+        return Err(Error::UnprocessableEntity(
+            "username must not be empty".into(),
+        ));
+    }
+    let password_encrypted = req
+        .password
+        .as_ref()
+        .map(|password| seal(id, password))
+        .transpose()?;
+
+    let updated = sqlx::query_as::<_, RegistryCredential>(&format!(
+        r#"
+UPDATE registry_credentials
+SET username           = COALESCE($2, username),
+    password_encrypted = COALESCE($3, password_encrypted),
+    updated_at         = NOW()
+WHERE id = $1
+RETURNING {COLUMNS}
+        "#
+    ))
+    .bind(id)
+    .bind(&req.username)
+    .bind(&password_encrypted)
+    .fetch_one(pool)
+    .await?;
+    Ok(updated)
+}
+
+pub async fn delete(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+    let result = sqlx::query("DELETE FROM registry_credentials WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+    Ok(())
+}
+
+/// Decrypted logins for the given registries (hosts or URLs). Registries
+/// without a stored login are skipped, so public registries stay anonymous.
+pub async fn logins_for(pool: &PgPool, registries: &[&str]) -> Result<Vec<RegistryLogin>, Error> {
+    let mut hosts: Vec<String> = registries
+        .iter()
+        .filter_map(|registry| normalize_registry(registry).ok())
+        .collect();
+    hosts.sort();
+    hosts.dedup();
+    if hosts.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let rows = sqlx::query_as::<_, RegistryLoginRow>(
+        r#"
+SELECT id, registry, username, password_encrypted
+FROM registry_credentials
+WHERE registry = ANY($1)
+        "#,
+    )
+    .bind(&hosts)
+    .fetch_all(pool)
+    .await?;
+    if rows.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let cipher = secrets::cipher()?;
+    rows.into_iter()
+        .map(|row| {
+            let password = cipher.decrypt(&row.password_encrypted, row.id.as_bytes())?;
+            let password = String::from_utf8(password)
+                .map_err(|_| Error::from(secrets::SecretsError::Decrypt))?;
+            Ok(RegistryLogin {
+                registry: row.registry,
+                username: row.username,
+                password: Secret::new(password),
+            })
+        })
+        .collect()
+}
+
+/// Logins needed to pull `image_ref` and write it into the registry at
+/// `registry_url` (when there is one).
+pub async fn logins_for_image(
+    pool: &PgPool,
+    image_ref: &str,
+    registry_url: Option<&str>,
+) -> Result<Vec<RegistryLogin>, Error> {
+    let source = registry_of_image(image_ref);
+    let mut registries = vec![source.as_str()];
+    registries.extend(registry_url);
+    logins_for(pool, &registries).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_registry_hosts() {
+        assert_eq!(normalize_registry("https://GHCR.io/").unwrap(), "ghcr.io");
+        assert_eq!(
+            normalize_registry("harbor.example.com:8443").unwrap(),
+            "harbor.example.com:8443"
+        );
+        assert_eq!(normalize_registry("index.docker.io").unwrap(), "docker.io");
+        assert!(normalize_registry("ghcr.io/org").is_err());
+        assert!(normalize_registry("  ").is_err());
+    }
+
+    #[test]
+    fn finds_registry_of_image_refs() {
+        assert_eq!(registry_of_image("ghcr.io/org/app:1.0"), "ghcr.io");
+        assert_eq!(registry_of_image("registry:5000/app"), "registry:5000");
+        assert_eq!(registry_of_image("library/ubuntu:22.04"), "docker.io");
+        assert_eq!(registry_of_image("ubuntu"), "docker.io");
+    }
+}
@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Postgres, Transaction, Type};
 use strum_macros::{Display, EnumString};
@@ -63,6 +66,8 @@ pub struct SecurityGroupRule {
     pub direction: SecurityGroupDirection,
     pub protocol: SecurityGroupProtocol,
     pub cidr: Option<String>,
+    /// Peer security group: the rule matches the addresses of its member VMs.
+    pub remote_security_group_id: Option<Uuid>,
     pub port_start: Option<i32>,
     pub port_end: Option<i32>,
     pub description: Option<String>,
@@ -75,6 +80,7 @@ struct SecurityGroupRuleRow {
     direction: SecurityGroupDirection,
     protocol: SecurityGroupProtocol,
     cidr: Option<String>,
+    remote_security_group_id: Option<Uuid>,
     port_start: Option<i32>,
     port_end: Option<i32>,
     description: Option<String>,
@@ -88,6 +94,7 @@ impl From<SecurityGroupRuleRow> for SecurityGroupRule {
             direction: row.direction,
             protocol: row.protocol,
             cidr: row.cidr,
+            remote_security_group_id: row.remote_security_group_id,
             port_start: row.port_start,
             port_end: row.port_end,
             description: row.description,
@@ -108,6 +115,9 @@ pub struct NewSecurityGroupRule {
     pub protocol: SecurityGroupProtocol,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cidr: Option<String>,
+    /// Match the member VMs of this security group instead of a CIDR.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_security_group_id: Option<Uuid>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub port_start: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -116,6 +126,60 @@ pub struct NewSecurityGroupRule {
     pub description: Option<String>,
 }
 
+/// Rule hit counters of a security group, read from the hosts of its running
+/// members. Counters restart whenever a member's rules are resynced.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct SecurityGroupStats {
+    pub security_group_id: Uuid,
+    /// Every rule of the group, summed over its members.
+    pub rules: Vec<SecurityGroupRuleStats>,
+    pub members: Vec<SecurityGroupMemberStats>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct SecurityGroupRuleStats {
+    pub rule_id: Uuid,
+    pub direction: SecurityGroupDirection,
+    pub packets: u64,
+    pub bytes: u64,
+}
+
+/// Packets a member dropped because none of its security groups' rules
+/// allowed them. The drop covers all of the VM's groups, not just this one.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct SecurityGroupMemberStats {
+    pub vm_id: Uuid,
+    pub dropped_ingress_packets: u64,
+    pub dropped_egress_packets: u64,
+}
+
+/// A packet dropped by a VM's security groups, as logged on its host.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DeniedPacket {
+    pub timestamp: DateTime<Utc>,
+    pub direction: SecurityGroupDirection,
+    pub protocol: String,
+    pub source: String,
+    pub destination: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_port: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destination_port: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct VmFirewallLog {
+    pub enabled: bool,
+    /// Most recent denied packets, oldest first. Empty unless the VM is
+    /// running with logging enabled.
+    pub denied: Vec<DeniedPacket>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct UpdateVmFirewallLog {
+    pub enabled: bool,
+}
+
 pub async fn list(
     pool: &PgPool,
     name_filter: Option<&str>,
@@ -187,6 +251,7 @@ SELECT id,
        direction,
        protocol,
        cidr::text,
+       remote_security_group_id,
        port_start,
        port_end,
        description
@@ -212,9 +277,10 @@ pub async fn create_rule(
     sqlx::query(
         r#"
 INSERT INTO security_group_rules (
-    id, security_group_id, direction, protocol, cidr, port_start, port_end, description
+    id, security_group_id, direction, protocol, cidr, remote_security_group_id,
+    port_start, port_end, description
 )
-VALUES ($1, $2, $3, $4, $5::cidr, $6, $7, $8)
+VALUES ($1, $2, $3, $4, $5::cidr, $6, $7, $8, $9)
         "#,
     )
     .bind(id)
@@ -222,6 +288,7 @@ VALUES ($1, $2, $3, $4, $5::cidr, $6, $7, $8)
     .bind(rule.direction)
     .bind(rule.protocol)
     .bind(rule.cidr)
+    .bind(rule.remote_security_group_id)
     .bind(rule.port_start)
     .bind(rule.port_end)
     .bind(rule.description)
@@ -272,6 +339,7 @@ SELECT sgr.id,
        sgr.direction,
        sgr.protocol,
        sgr.cidr::text,
+       sgr.remote_security_group_id,
        sgr.port_start,
        sgr.port_end,
        sgr.description
@@ -332,3 +400,77 @@ pub async fn list_vm_ids(pool: &PgPool, security_group_id: Uuid) -> Result<Vec<U
 
     Ok(rows.into_iter().map(|(vm_id,)| vm_id).collect())
 }
+
+/// VMs with a rule whose peer is this security group.
+pub async fn list_peer_vm_ids(
+    pool: &PgPool,
+    security_group_id: Uuid,
+) -> Result<Vec<Uuid>, sqlx::Error> {
+    let rows: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+SELECT DISTINCT vsg.vm_id
+FROM security_group_rules sgr
+JOIN vm_security_groups vsg ON vsg.security_group_id = sgr.security_group_id
+WHERE sgr.remote_security_group_id = $1
+ORDER BY vsg.vm_id
+        "#,
+    )
+    .bind(security_group_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|(vm_id,)| vm_id).collect())
+}
+
+/// Names of the other security groups with a rule whose peer is this one.
+pub async fn list_referencing_group_names(
+    pool: &PgPool,
+    security_group_id: Uuid,
+) -> Result<Vec<String>, sqlx::Error> {
+    let rows: Vec<(String,)> = sqlx::query_as(
+        r#"
+SELECT DISTINCT sg.name
+FROM security_group_rules sgr
+JOIN security_groups sg ON sg.id = sgr.security_group_id
+WHERE sgr.remote_security_group_id = $1
+  AND sgr.security_group_id <> $1
+ORDER BY sg.name
+        "#,
+    )
+    .bind(security_group_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|(name,)| name).collect())
+}
+
+/// Host addresses of every managed NIC of the member VMs of each group, both
+/// families, keyed by group.
+pub async fn member_addresses(
+    pool: &PgPool,
+    security_group_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<String>>, sqlx::Error> {
+    let rows: Vec<(Uuid, String)> = sqlx::query_as(
+        r#"
+SELECT DISTINCT vsg.security_group_id, host(ia.ip_address)
+FROM vm_security_groups vsg
+JOIN ip_allocations ia ON ia.vm_id = vsg.vm_id
+JOIN networks n ON n.id = ia.network_id
+WHERE vsg.security_group_id = ANY($1)
+  AND n.type IS DISTINCT FROM 'passt'
+ORDER BY 1, 2
+        "#,
+    )
+    .bind(security_group_ids)
+    .fetch_all(pool)
+    .await?;
+
+    let mut addresses: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for (security_group_id, address) in rows {
+        addresses
+            .entry(security_group_id)
+            .or_default()
+            .push(address);
+    }
+    Ok(addresses)
+}
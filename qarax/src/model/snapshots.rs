@@ -26,26 +26,31 @@ pub struct Snapshot {
     pub name: String,
     pub status: SnapshotStatus,
     pub created_at: DateTime<Utc>,
+    /// Copy of the VM's persistent OverlayBD upper layer taken with the
+    /// snapshot, if it has one.
+    pub upper_storage_object_id: Option<Uuid>,
 }
 
 pub struct NewSnapshot {
     pub vm_id: Uuid,
     pub storage_object_id: Uuid,
     pub name: String,
+    pub upper_storage_object_id: Option<Uuid>,
 }
 
 pub async fn create(pool: &PgPool, new: &NewSnapshot) -> Result<Uuid, sqlx::Error> {
     let id = Uuid::new_v4();
     sqlx::query(
         r#"
-INSERT INTO vm_snapshots (id, vm_id, storage_object_id, name)
-VALUES ($1, $2, $3, $4)
+INSERT INTO vm_snapshots (id, vm_id, storage_object_id, name, upper_storage_object_id)
+VALUES ($1, $2, $3, $4, $5)
         "#,
     )
     .bind(id)
     .bind(new.vm_id)
     .bind(new.storage_object_id)
     .bind(&new.name)
+    .bind(new.upper_storage_object_id)
     .execute(pool)
     .await?;
 
@@ -56,7 +61,8 @@ pub async fn get(pool: &PgPool, snapshot_id: Uuid) -> Result<Snapshot, sqlx::Err
     sqlx::query_as!(
         Snapshot,
         r#"
-SELECT id, vm_id, storage_object_id, name, status as "status: _", created_at
+SELECT id, vm_id, storage_object_id, name, status as "status: _", created_at,
+       upper_storage_object_id
 FROM vm_snapshots
 WHERE id = $1
         "#,
@@ -74,7 +80,8 @@ pub async fn list_for_vm(
     sqlx::query_as!(
         Snapshot,
         r#"
-SELECT id, vm_id, storage_object_id, name, status as "status: _", created_at
+SELECT id, vm_id, storage_object_id, name, status as "status: _", created_at,
+       upper_storage_object_id
 FROM vm_snapshots
 WHERE vm_id = $1 AND ($2::text IS NULL OR name = $2)
 ORDER BY created_at ASC
@@ -61,8 +61,12 @@ pub enum StorageObjectType {
     OciImage,
     /// Persistent writable upper layer (upper.data + upper.index) for a
     /// linked-persistent OverlayBD VM. Stored on a Local or NFS pool.
-    /// Config JSON: {"upper_data": "/path/to/uuid.upper.data", "upper_index": "/path/to/uuid.upper.index"}
+    /// Config JSON: {"upper_data": "/path/to/uuid.upper.data", "upper_index": "/path/to/uuid.upper.index"},
+    /// plus "frozen_layers" once the upper has been resized.
     OverlaybdUpper,
+    /// Portable VM export bundle: a directory holding `manifest.json`, the
+    /// VM's disk images and an optional memory snapshot.
+    VmBundle,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -230,9 +234,11 @@ async fn resolve_new_object(
         }
     } else if (new_object.object_type == StorageObjectType::Disk
         || new_object.object_type == StorageObjectType::Snapshot
-        || new_object.object_type == StorageObjectType::DatabaseBackup)
+        || new_object.object_type == StorageObjectType::DatabaseBackup
+        || new_object.object_type == StorageObjectType::VmBundle)
         && new_object.config.get("path").is_none()
         && new_object.config.get("lun").is_none()
+        && new_object.config.get("image").is_none()
     {
         if let Ok(storage_pool) = storage_pools::get(pool, pool_id).await {
             let derived_path = match storage_pool.pool_type {
@@ -240,20 +246,20 @@ async fn resolve_new_object(
                     .config
                     .get("path")
                     .and_then(|v| v.as_str())
-                    .map(|base| {
-                        if new_object.object_type == StorageObjectType::DatabaseBackup {
-                            format!("{}/{}.dump", base, id)
-                        } else {
-                            format!("{}/{}", base, id)
-                        }
+                    .map(|base| match new_object.object_type {
+                        StorageObjectType::DatabaseBackup => format!("{}/{}.dump", base, id),
+                        StorageObjectType::VmBundle => format!("{}/{}.tar", base, id),
+                        _ => format!("{}/{}", base, id),
                     }),
-                storage_pools::StoragePoolType::Nfs => Some(
-                    if new_object.object_type == StorageObjectType::DatabaseBackup {
+                storage_pools::StoragePoolType::Nfs => Some(match new_object.object_type {
+                    StorageObjectType::DatabaseBackup => {
                         format!("/var/lib/qarax/pools/{}/{}.dump", pool_id, id)
-                    } else {
-                        format!("/var/lib/qarax/pools/{}/{}", pool_id, id)
-                    },
-                ),
+                    }
+                    StorageObjectType::VmBundle => {
+                        format!("/var/lib/qarax/pools/{}/{}.tar", pool_id, id)
+                    }
+                    _ => format!("/var/lib/qarax/pools/{}/{}", pool_id, id),
+                }),
                 _ => None,
             };
             if let Some(path) = derived_path {
@@ -265,30 +271,39 @@ async fn resolve_new_object(
             new_object.config.clone()
         }
     } else if new_object.object_type == StorageObjectType::Disk
-        && new_object.config.get("lun").is_some()
+        && new_object.config.get("image").is_some()
     {
-        // Block disks: caller supplies {"lun": N}; we enrich with pool portal/iqn
-        // so the node backend has everything it needs without a pool lookup.
+        // RBD images: caller supplies {"image": name}; we enrich with the pool's
+        // cluster settings so the node can map it without a pool lookup.
         match storage_pools::get(pool, pool_id).await {
-            Ok(storage_pool) if storage_pool.pool_type == storage_pools::StoragePoolType::Block => {
-                let portal = storage_pool
-                    .config
-                    .get("portal")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or_default()
-                    .to_string();
-                let iqn = storage_pool
+            Ok(storage_pool) if storage_pool.pool_type == storage_pools::StoragePoolType::Rbd => {
+                let image = new_object
                     .config
-                    .get("iqn")
+                    .get("image")
                     .and_then(|v| v.as_str())
-                    .unwrap_or_default()
-                    .to_string();
-                let lun = new_object
-                    .config
-                    .get("lun")
-                    .and_then(|v| v.as_i64())
-                    .unwrap_or(0);
-                serde_json::json!({ "portal": portal, "iqn": iqn, "lun": lun })
+                    .unwrap_or_default();
+                storage_pools::RbdPoolConfig::from_value(&storage_pool.config)
+                    .map(|rbd| rbd.image_config(image))
+                    .unwrap_or_else(|| new_object.config.clone())
+            }
+            _ => new_object.config.clone(),
+        }
+    } else if new_object.object_type == StorageObjectType::Disk
+        && new_object.config.get("lun").is_some()
+    {
+        // Block disks: caller supplies {"lun": N} (plus wwn/serial when
+        // discovered); we enrich with pool portals/iqn so the node backend has
+        // everything it needs without a pool lookup.
+        match storage_pools::get(pool, pool_id).await {
+            Ok(storage_pool) if storage_pool.pool_type == storage_pools::StoragePoolType::Block => {
+                let (portals, iqn) =
+                    storage_pools::BlockPoolConfig::from_value(&storage_pool.config)
+                        .map(|block| (block.all_portals(), block.iqn))
+                        .unwrap_or_default();
+                let mut config = new_object.config.clone();
+                config["portals"] = serde_json::json!(portals);
+                config["iqn"] = serde_json::json!(iqn);
+                config
             }
             _ => new_object.config.clone(),
         }
@@ -369,6 +384,95 @@ WHERE id = $1
     Ok(())
 }
 
+/// Count rows outside the backup → snapshot chain that still point at a
+/// storage object: child objects, VM disks, snapshot upper-layer copies,
+/// templates and boot sources.
+pub async fn count_references(pool: &PgPool, object_id: Uuid) -> Result<i64, sqlx::Error> {
+    let (count,): (i64,) = sqlx::query_as(
+        r#"
+SELECT (SELECT COUNT(*) FROM storage_objects WHERE parent_id = $1)
+     + (SELECT COUNT(*) FROM vm_disks
+        WHERE storage_object_id = $1 OR upper_storage_object_id = $1)
+     + (SELECT COUNT(*) FROM vm_snapshots WHERE upper_storage_object_id = $1)
+     + (SELECT COUNT(*) FROM vm_templates WHERE root_disk_object_id = $1)
+     + (SELECT COUNT(*) FROM boot_sources
+        WHERE kernel_image_id = $1 OR initrd_image_id = $1 OR firmware_image_id = $1)
+        "#,
+    )
+    .bind(object_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// Return the subset of `ids` that still have a storage object row.
+pub async fn existing_ids(pool: &PgPool, ids: &[Uuid]) -> Result<Vec<Uuid>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (Uuid,)>("SELECT id FROM storage_objects WHERE id = ANY($1)")
+        .bind(ids)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
+/// Whether an OverlayBD upper other than `excluding` still stacks on the
+/// frozen layer at `path`. Snapshot copies share their upper's frozen layers.
+pub async fn frozen_layer_in_use(
+    pool: &PgPool,
+    path: &str,
+    excluding: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let (in_use,): (bool,) = sqlx::query_as(
+        "SELECT EXISTS (SELECT 1 FROM storage_objects WHERE object_type = 'OVERLAYBD_UPPER' \
+         AND id <> $2 AND config->'frozen_layers' @> jsonb_build_array($1::text))",
+    )
+    .bind(path)
+    .bind(excluding)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(in_use)
+}
+
+/// Whether a storage object in `pool_id` already points at `path`.
+pub async fn path_in_use(pool: &PgPool, pool_id: Uuid, path: &str) -> Result<bool, sqlx::Error> {
+    let (in_use,): (bool,) = sqlx::query_as(
+        "SELECT EXISTS (SELECT 1 FROM storage_objects WHERE storage_pool_id = $1 AND config->>'path' = $2)",
+    )
+    .bind(pool_id)
+    .bind(path)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(in_use)
+}
+
+/// Image references and manifest digests that VMs, VM templates and
+/// `OCI_IMAGE` storage objects still point at. References may be either the
+/// source ref a user asked for or the converted ref in a pool registry.
+pub async fn referenced_images(pool: &PgPool) -> Result<(Vec<String>, Vec<String>), sqlx::Error> {
+    let refs = sqlx::query_as::<_, (String,)>(
+        "SELECT image_ref FROM vms WHERE image_ref IS NOT NULL \
+         UNION SELECT image_ref FROM vm_templates WHERE image_ref IS NOT NULL \
+         UNION SELECT config->>'image_ref' FROM storage_objects \
+         WHERE object_type = 'OCI_IMAGE' AND config->>'image_ref' IS NOT NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+    let digests = sqlx::query_as::<_, (String,)>(
+        "SELECT DISTINCT config->>'digest' FROM storage_objects \
+         WHERE object_type = 'OCI_IMAGE' AND COALESCE(config->>'digest', '') <> ''",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok((
+        refs.into_iter().map(|(r,)| r).collect(),
+        digests.into_iter().map(|(d,)| d).collect(),
+    ))
+}
+
 pub async fn update_config(
     pool: &PgPool,
     object_id: Uuid,
@@ -423,6 +527,10 @@ impl OciImageConfig {
 pub struct OverlaybdUpperConfig {
     pub upper_data: String,
     pub upper_index: String,
+    /// Layers committed from the upper by earlier resizes, oldest first. They
+    /// are stacked between the image and the upper when the disk is mounted.
+    #[serde(default)]
+    pub frozen_layers: Vec<String>,
 }
 
 impl OverlaybdUpperConfig {
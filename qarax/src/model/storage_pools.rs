@@ -1,9 +1,12 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Type, types::Json};
 use strum_macros::{Display, EnumString};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::secrets::{self, SecretsError};
+
 /// Configuration for an OverlayBD storage pool, extracted from the JSONB `config` column.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OverlayBdPoolConfig {
@@ -18,18 +21,146 @@ impl OverlayBdPoolConfig {
 
 /// Configuration for a BLOCK (iSCSI) storage pool, extracted from the JSONB `config` column.
 ///
-/// `portal` is the iSCSI target portal (host:port, e.g. `10.0.0.5:3260`).
+/// `portal` / `portals` are the iSCSI target portals (host:port, e.g. `10.0.0.5:3260`);
+/// more than one portal gives the node several paths, combined with dm-multipath.
 /// `iqn` is the target IQN (e.g. `iqn.2024-01.qarax:target0`).
+/// `chap` authenticates the initiator to the target, `mutual_chap` the target
+/// back to the initiator. Their secrets are stored encrypted
+/// (`secret_encrypted`) and only decrypted when sent to a node.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BlockPoolConfig {
-    pub portal: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub portal: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub portals: Vec<String>,
     pub iqn: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chap: Option<ChapCredentials>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mutual_chap: Option<ChapCredentials>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ChapCredentials {
+    pub username: String,
+    /// Plaintext secret; only present in create requests and node payloads.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// Hex-encoded ciphertext of the secret, as stored in the database.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_encrypted: Option<String>,
 }
 
+impl std::fmt::Debug for ChapCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChapCredentials")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Associated data for CHAP secret ciphertexts.
+const CHAP_SECRET_AAD: &[u8] = b"storage-pool-chap";
+
 impl BlockPoolConfig {
     pub fn from_value(v: &serde_json::Value) -> Option<Self> {
         serde_json::from_value(v.clone()).ok()
     }
+
+    /// `portal` followed by `portals`, without duplicates.
+    pub fn all_portals(&self) -> Vec<String> {
+        let mut portals: Vec<String> = Vec::new();
+        for portal in self.portal.iter().chain(&self.portals) {
+            if !portals.contains(portal) {
+                portals.push(portal.clone());
+            }
+        }
+        portals
+    }
+
+    /// Check a config supplied when creating a pool.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.all_portals().is_empty() {
+            return Err("BLOCK pools require at least one portal".into());
+        }
+        if !self.iqn.starts_with("iqn.") {
+            return Err(format!("iqn must start with 'iqn.': {:?}", self.iqn));
+        }
+        for (field, chap) in [("chap", &self.chap), ("mutual_chap", &self.mutual_chap)] {
+            if let Some(chap) = chap {
+                if chap.username.trim().is_empty() {
+                    return Err(format!("{field}.username must not be empty"));
+                }
+                if chap.secret.as_deref().is_none_or(str::is_empty) {
+                    return Err(format!("{field}.secret must not be empty"));
+                }
+            }
+        }
+        if self.mutual_chap.is_some() && self.chap.is_none() {
+            return Err("mutual_chap requires chap".into());
+        }
+        Ok(())
+    }
+
+    /// Replace plaintext CHAP secrets with their encrypted form for storage.
+    pub fn seal_secrets(&mut self) -> Result<(), SecretsError> {
+        for chap in [&mut self.chap, &mut self.mutual_chap]
+            .into_iter()
+            .flatten()
+        {
+            if let Some(secret) = chap.secret.take() {
+                let sealed = secrets::cipher()?.encrypt(secret.as_bytes(), CHAP_SECRET_AAD)?;
+                chap.secret_encrypted = Some(hex::encode(sealed));
+            }
+        }
+        Ok(())
+    }
+
+    /// Decrypt stored CHAP secrets so the config can be handed to a node.
+    pub fn open_secrets(&mut self) -> Result<(), SecretsError> {
+        for chap in [&mut self.chap, &mut self.mutual_chap]
+            .into_iter()
+            .flatten()
+        {
+            if let Some(sealed) = chap.secret_encrypted.take() {
+                let sealed = hex::decode(sealed).map_err(|_| SecretsError::Decrypt)?;
+                let secret = secrets::cipher()?.decrypt(&sealed, CHAP_SECRET_AAD)?;
+                chap.secret = Some(String::from_utf8(secret).map_err(|_| SecretsError::Decrypt)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Configuration for an RBD (Ceph) storage pool, extracted from the JSONB `config` column.
+///
+/// `pool` is the RADOS pool holding the images. `monitors`, `user` and `keyring`
+/// are optional and fall back to the node's `/etc/ceph` defaults.
+/// `map_type` selects `krbd` (default) or `nbd` (rbd-nbd).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RbdPoolConfig {
+    pub pool: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitors: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyring: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub map_type: Option<String>,
+}
+
+impl RbdPoolConfig {
+    pub fn from_value(v: &serde_json::Value) -> Option<Self> {
+        serde_json::from_value(v.clone()).ok()
+    }
+
+    /// Config the node's RBD backend expects for a single image in this pool.
+    pub fn image_config(&self, image: &str) -> serde_json::Value {
+        let mut config = serde_json::to_value(self).unwrap_or_default();
+        config["image"] = serde_json::Value::String(image.to_string());
+        config
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -40,7 +171,11 @@ pub struct StoragePool {
     pub status: StoragePoolStatus,
     pub config: serde_json::Value,
     pub capacity_bytes: Option<i64>,
+    /// Sum of `size_bytes` over the pool's storage objects.
     pub allocated_bytes: Option<i64>,
+    /// Space measured by each attached host. Only filled in by `GET /storage-pools/{id}`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub host_capacity: Vec<HostPoolCapacity>,
 }
 
 #[derive(sqlx::FromRow)]
@@ -54,6 +189,35 @@ pub struct StoragePoolRow {
     pub allocated_bytes: Option<i64>,
 }
 
+impl StoragePool {
+    /// Pool config as sent to a node: identical to `config` except that
+    /// encrypted secrets (BLOCK CHAP) are decrypted.
+    pub fn node_config(&self) -> Result<serde_json::Value, SecretsError> {
+        if self.pool_type != StoragePoolType::Block {
+            return Ok(self.config.clone());
+        }
+        let Some(mut block) = BlockPoolConfig::from_value(&self.config) else {
+            return Ok(self.config.clone());
+        };
+        block.open_secrets()?;
+        Ok(serde_json::to_value(block).unwrap_or_else(|_| self.config.clone()))
+    }
+
+    /// Directory that holds this pool's file-backed objects on an attached
+    /// node. Mirrors the layout used when deriving storage object paths.
+    pub fn node_base_path(&self) -> Option<String> {
+        match self.pool_type {
+            StoragePoolType::Local => self
+                .config
+                .get("path")
+                .and_then(|v| v.as_str())
+                .map(|p| p.trim_end_matches('/').to_string()),
+            StoragePoolType::Nfs => Some(format!("/var/lib/qarax/pools/{}", self.id)),
+            _ => None,
+        }
+    }
+}
+
 impl From<StoragePoolRow> for StoragePool {
     fn from(row: StoragePoolRow) -> Self {
         StoragePool {
@@ -64,6 +228,7 @@ impl From<StoragePoolRow> for StoragePool {
             config: row.config.0,
             capacity_bytes: row.capacity_bytes,
             allocated_bytes: row.allocated_bytes,
+            host_capacity: Vec::new(),
         }
     }
 }
@@ -82,6 +247,7 @@ pub enum StoragePoolType {
     #[serde(alias = "overlaybd")]
     OverlayBd,
     Block,
+    Rbd,
 }
 
 impl StoragePoolType {
@@ -90,11 +256,15 @@ impl StoragePoolType {
     /// NFS pools are accessible from multiple hosts at the same path.
     /// OverlayBD pools are backed by a shared OCI registry.
     /// BLOCK pools are iSCSI targets, reachable from any initiator on the network.
+    /// RBD pools live in a Ceph cluster that every node can map images from.
     /// Local pools are host-specific and must be attached explicitly.
     pub fn is_shared(&self) -> bool {
         matches!(
             self,
-            StoragePoolType::Nfs | StoragePoolType::OverlayBd | StoragePoolType::Block
+            StoragePoolType::Nfs
+                | StoragePoolType::OverlayBd
+                | StoragePoolType::Block
+                | StoragePoolType::Rbd
         )
     }
 
@@ -115,6 +285,18 @@ pub enum StoragePoolStatus {
     Active,
     Inactive,
     Error,
+    /// Usable, but at least one attached host has lost a path to the storage.
+    Degraded,
+}
+
+impl StoragePoolStatus {
+    /// Whether new disks and VMs may be placed on the pool.
+    pub fn is_usable(&self) -> bool {
+        matches!(
+            self,
+            StoragePoolStatus::Active | StoragePoolStatus::Degraded
+        )
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -215,7 +397,7 @@ pub async fn pick_active_non_overlaybd(
 ) -> Result<Option<Uuid>, sqlx::Error> {
     if let Some(id) = prefer_pool_id {
         let row = sqlx::query_as::<_, (Uuid,)>(
-            "SELECT id FROM storage_pools WHERE id = $1 AND status = 'ACTIVE' AND pool_type != 'OVERLAYBD'",
+            "SELECT id FROM storage_pools WHERE id = $1 AND status IN ('ACTIVE', 'DEGRADED') AND pool_type != 'OVERLAYBD'",
         )
         .bind(id)
         .fetch_optional(pool)
@@ -329,6 +511,21 @@ pub async fn find_host_for_pool(pool: &PgPool, pool_id: Uuid) -> Result<Option<U
     Ok(row.map(|(id,)| id))
 }
 
+/// IDs of the hosts attached to a storage pool that are currently UP.
+pub async fn list_up_host_ids(pool: &PgPool, pool_id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (Uuid,)>(
+        "SELECT hsp.host_id FROM host_storage_pools hsp \
+         JOIN hosts h ON h.id = hsp.host_id \
+         WHERE hsp.storage_pool_id = $1 AND h.status = 'UP' \
+         ORDER BY h.name",
+    )
+    .bind(pool_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
 /// Check whether a host is attached to a given storage pool.
 pub async fn host_has_pool(
     pool: &PgPool,
@@ -369,3 +566,253 @@ LIMIT 1
 
     Ok(row.map(|r| r.into()))
 }
+
+/// State of one path from a host to a pool's storage, as last reported by the node.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct StoragePathHealth {
+    /// Portal (host:port) for BLOCK pools.
+    pub path: String,
+    /// Session state reported by the node, e.g. `LOGGED_IN` or `NOT_CONNECTED`.
+    pub state: String,
+    pub healthy: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct HostPathHealth {
+    pub host_id: Uuid,
+    pub host_name: String,
+    pub paths: Vec<StoragePathHealth>,
+    pub checked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(sqlx::FromRow)]
+struct HostPathHealthRow {
+    host_id: Uuid,
+    host_name: String,
+    path_health: Option<Json<Vec<StoragePathHealth>>>,
+    path_health_checked_at: Option<DateTime<Utc>>,
+}
+
+/// Record the paths a host reported for a pool, then mark the pool DEGRADED
+/// while any attached host has an unhealthy path (and ACTIVE again once all
+/// are healthy). INACTIVE and ERROR pools are left alone.
+pub async fn record_path_health(
+    pool: &PgPool,
+    pool_id: Uuid,
+    host_id: Uuid,
+    paths: &[StoragePathHealth],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        r#"
+UPDATE host_storage_pools
+SET path_health = $3, path_health_checked_at = NOW()
+WHERE host_id = $1 AND storage_pool_id = $2
+        "#,
+    )
+    .bind(host_id)
+    .bind(pool_id)
+    .bind(Json(paths))
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+UPDATE storage_pools
+SET status = CASE
+    WHEN EXISTS (
+        SELECT 1
+        FROM host_storage_pools hsp,
+             jsonb_array_elements(COALESCE(hsp.path_health, '[]'::jsonb)) AS p
+        WHERE hsp.storage_pool_id = $1
+          AND NOT (p->>'healthy')::boolean
+    ) THEN 'DEGRADED'::storage_pool_status
+    ELSE 'ACTIVE'::storage_pool_status
+END
+WHERE id = $1 AND status IN ('ACTIVE', 'DEGRADED')
+        "#,
+    )
+    .bind(pool_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await
+}
+
+/// Last reported path health of every host attached to a pool.
+pub async fn list_path_health(
+    pool: &PgPool,
+    pool_id: Uuid,
+) -> Result<Vec<HostPathHealth>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, HostPathHealthRow>(
+        r#"
+SELECT hsp.host_id, h.name AS host_name, hsp.path_health, hsp.path_health_checked_at
+FROM host_storage_pools hsp
+JOIN hosts h ON h.id = hsp.host_id
+WHERE hsp.storage_pool_id = $1
+ORDER BY h.name
+        "#,
+    )
+    .bind(pool_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| HostPathHealth {
+            host_id: row.host_id,
+            host_name: row.host_name,
+            paths: row.path_health.map(|p| p.0).unwrap_or_default(),
+            checked_at: row.path_health_checked_at,
+        })
+        .collect())
+}
+
+/// Space of a pool as measured by a node.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub struct PoolCapacity {
+    pub total_bytes: i64,
+    pub used_bytes: i64,
+    pub free_bytes: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, sqlx::FromRow)]
+pub struct HostPoolCapacity {
+    pub host_id: Uuid,
+    pub host_name: String,
+    pub total_bytes: i64,
+    pub used_bytes: i64,
+    pub free_bytes: i64,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Record the space a host measured for a pool it has attached.
+pub async fn record_capacity(
+    pool: &PgPool,
+    pool_id: Uuid,
+    host_id: Uuid,
+    capacity: &PoolCapacity,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+UPDATE host_storage_pools
+SET total_bytes = $3, used_bytes = $4, free_bytes = $5, capacity_checked_at = NOW()
+WHERE host_id = $1 AND storage_pool_id = $2
+        "#,
+    )
+    .bind(host_id)
+    .bind(pool_id)
+    .bind(capacity.total_bytes)
+    .bind(capacity.used_bytes)
+    .bind(capacity.free_bytes)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Last measured capacity of a pool on every attached host that reported one.
+pub async fn list_capacity(
+    pool: &PgPool,
+    pool_id: Uuid,
+) -> Result<Vec<HostPoolCapacity>, sqlx::Error> {
+    sqlx::query_as::<_, HostPoolCapacity>(
+        r#"
+SELECT hsp.host_id, h.name AS host_name, hsp.total_bytes, hsp.used_bytes, hsp.free_bytes,
+       hsp.capacity_checked_at AS checked_at
+FROM host_storage_pools hsp
+JOIN hosts h ON h.id = hsp.host_id
+WHERE hsp.storage_pool_id = $1 AND hsp.capacity_checked_at IS NOT NULL
+ORDER BY h.name
+        "#,
+    )
+    .bind(pool_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Free bytes of a pool as last measured by one host, if it has reported.
+pub async fn measured_free_bytes(
+    pool: &PgPool,
+    pool_id: Uuid,
+    host_id: Uuid,
+) -> Result<Option<i64>, sqlx::Error> {
+    let free: Option<(Option<i64>,)> = sqlx::query_as(
+        "SELECT free_bytes FROM host_storage_pools WHERE host_id = $1 AND storage_pool_id = $2",
+    )
+    .bind(host_id)
+    .bind(pool_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(free.and_then(|(free,)| free))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_config(value: serde_json::Value) -> BlockPoolConfig {
+        BlockPoolConfig::from_value(&value).expect("valid block config")
+    }
+
+    #[test]
+    fn block_config_validation() {
+        let cfg = block_config(serde_json::json!({
+            "portal": "10.0.0.5:3260",
+            "portals": ["10.0.0.5:3260", "10.0.1.5:3260"],
+            "iqn": "iqn.2024-01.qarax:target0",
+            "chap": {"username": "initiator", "secret": "s3cret-initiator"},
+        }));
+        assert!(cfg.validate().is_ok());
+        assert_eq!(cfg.all_portals(), vec!["10.0.0.5:3260", "10.0.1.5:3260"]);
+
+        let no_portal = block_config(serde_json::json!({ "iqn": "iqn.2024-01.qarax:t" }));
+        assert!(no_portal.validate().is_err());
+
+        let mutual_only = block_config(serde_json::json!({
+            "portal": "10.0.0.5:3260",
+            "iqn": "iqn.2024-01.qarax:target0",
+            "mutual_chap": {"username": "target", "secret": "s3cret-target"},
+        }));
+        assert_eq!(
+            mutual_only.validate().unwrap_err(),
+            "mutual_chap requires chap"
+        );
+
+        let no_secret = block_config(serde_json::json!({
+            "portal": "10.0.0.5:3260",
+            "iqn": "iqn.2024-01.qarax:target0",
+            "chap": {"username": "initiator"},
+        }));
+        assert!(no_secret.validate().is_err());
+    }
+
+    #[test]
+    fn chap_secrets_are_sealed_and_hidden_from_debug() {
+        secrets::init("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f").unwrap();
+        let mut cfg = block_config(serde_json::json!({
+            "portal": "10.0.0.5:3260",
+            "iqn": "iqn.2024-01.qarax:target0",
+            "chap": {"username": "initiator", "secret": "s3cret-initiator"},
+        }));
+        assert!(!format!("{cfg:?}").contains("s3cret-initiator"));
+
+        cfg.seal_secrets().unwrap();
+        let stored = serde_json::to_value(&cfg).unwrap();
+        assert!(!stored.to_string().contains("s3cret-initiator"));
+        assert!(stored["chap"].get("secret").is_none());
+
+        let pool = StoragePool {
+            id: Uuid::new_v4(),
+            name: "san".into(),
+            pool_type: StoragePoolType::Block,
+            status: StoragePoolStatus::Active,
+            config: stored,
+            capacity_bytes: None,
+            allocated_bytes: None,
+            host_capacity: Vec::new(),
+        };
+        let node_config = pool.node_config().unwrap();
+        assert_eq!(node_config["chap"]["secret"], "s3cret-initiator");
+        assert!(node_config["chap"].get("secret_encrypted").is_none());
+    }
+}
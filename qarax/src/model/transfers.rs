@@ -20,6 +20,13 @@ pub struct Transfer {
     pub total_bytes: Option<i64>,
     pub transferred_bytes: i64,
     pub error_message: Option<String>,
+    /// Expected digest of the downloaded data, e.g. `sha256:<hex>`.
+    pub expected_checksum: Option<String>,
+    /// Digest computed by the node while streaming (only when one was expected).
+    pub actual_checksum: Option<String>,
+    /// `true` when `actual_checksum` matched, `false` on mismatch, unset when not checked.
+    pub checksum_verified: Option<bool>,
+    pub bandwidth_limit_bytes_per_sec: Option<i64>,
     pub created_at: Option<NaiveDateTime>,
     pub updated_at: Option<NaiveDateTime>,
     pub started_at: Option<NaiveDateTime>,
@@ -39,6 +46,10 @@ pub struct TransferRow {
     pub total_bytes: Option<i64>,
     pub transferred_bytes: Option<i64>,
     pub error_message: Option<String>,
+    pub expected_checksum: Option<String>,
+    pub actual_checksum: Option<String>,
+    pub checksum_verified: Option<bool>,
+    pub bandwidth_limit_bytes_per_sec: Option<i64>,
     pub created_at: Option<NaiveDateTime>,
     pub updated_at: Option<NaiveDateTime>,
     pub started_at: Option<NaiveDateTime>,
@@ -59,6 +70,10 @@ impl From<TransferRow> for Transfer {
             total_bytes: row.total_bytes,
             transferred_bytes: row.transferred_bytes.unwrap_or(0),
             error_message: row.error_message,
+            expected_checksum: row.expected_checksum,
+            actual_checksum: row.actual_checksum,
+            checksum_verified: row.checksum_verified,
+            bandwidth_limit_bytes_per_sec: row.bandwidth_limit_bytes_per_sec,
             created_at: row.created_at,
             updated_at: row.updated_at,
             started_at: row.started_at,
@@ -77,6 +92,7 @@ impl From<TransferRow> for Transfer {
 pub enum TransferType {
     Download,
     LocalCopy,
+    Upload,
 }
 
 #[derive(
@@ -98,6 +114,13 @@ pub struct NewTransfer {
     pub name: String,
     pub source: String,
     pub object_type: StorageObjectType,
+    /// Expected digest of a URL download as `sha256:<hex>` or `sha512:<hex>`.
+    /// The transfer fails if the downloaded data does not match.
+    #[serde(default)]
+    pub expected_checksum: Option<String>,
+    /// Cap on download throughput for this transfer, in bytes per second.
+    #[serde(default)]
+    pub bandwidth_limit_bytes_per_sec: Option<i64>,
 }
 
 pub async fn create(
@@ -108,34 +131,40 @@ pub async fn create(
 ) -> Result<Transfer, sqlx::Error> {
     let id = Uuid::new_v4();
 
-    let row: TransferRow = sqlx::query_as!(
-        TransferRow,
+    let row = sqlx::query_as::<_, TransferRow>(
         r#"
-INSERT INTO transfers (id, name, transfer_type, source, storage_pool_id, object_type)
-VALUES ($1, $2, $3, $4, $5, $6)
+INSERT INTO transfers (id, name, transfer_type, source, storage_pool_id, object_type,
+                       expected_checksum, bandwidth_limit_bytes_per_sec)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
 RETURNING id,
           name,
-          transfer_type as "transfer_type: _",
-          status as "status: _",
+          transfer_type,
+          status,
           source,
           storage_pool_id,
-          object_type as "object_type: _",
-          storage_object_id as "storage_object_id?",
-          total_bytes as "total_bytes?",
-          transferred_bytes as "transferred_bytes?",
-          error_message as "error_message?",
-          created_at as "created_at?",
-          updated_at as "updated_at?",
-          started_at as "started_at?",
-          completed_at as "completed_at?"
+          object_type,
+          storage_object_id,
+          total_bytes,
+          transferred_bytes,
+          error_message,
+          expected_checksum,
+          actual_checksum,
+          checksum_verified,
+          bandwidth_limit_bytes_per_sec,
+          created_at,
+          updated_at,
+          started_at,
+          completed_at
         "#,
-        id,
-        new_transfer.name,
-        transfer_type as TransferType,
-        new_transfer.source,
-        storage_pool_id,
-        new_transfer.object_type.clone() as StorageObjectType,
     )
+    .bind(id)
+    .bind(&new_transfer.name)
+    .bind(transfer_type)
+    .bind(&new_transfer.source)
+    .bind(storage_pool_id)
+    .bind(&new_transfer.object_type)
+    .bind(&new_transfer.expected_checksum)
+    .bind(new_transfer.bandwidth_limit_bytes_per_sec)
     .fetch_one(pool)
     .await?;
 
@@ -143,29 +172,32 @@ RETURNING id,
 }
 
 pub async fn get(pool: &PgPool, transfer_id: Uuid) -> Result<Transfer, sqlx::Error> {
-    let row: TransferRow = sqlx::query_as!(
-        TransferRow,
+    let row = sqlx::query_as::<_, TransferRow>(
         r#"
 SELECT id,
        name,
-       transfer_type as "transfer_type: _",
-       status as "status: _",
+       transfer_type,
+       status,
        source,
        storage_pool_id,
-       object_type as "object_type: _",
-       storage_object_id as "storage_object_id?",
-       total_bytes as "total_bytes?",
-       transferred_bytes as "transferred_bytes?",
-       error_message as "error_message?",
-       created_at as "created_at?",
-       updated_at as "updated_at?",
-       started_at as "started_at?",
-       completed_at as "completed_at?"
+       object_type,
+       storage_object_id,
+       total_bytes,
+       transferred_bytes,
+       error_message,
+       expected_checksum,
+       actual_checksum,
+       checksum_verified,
+       bandwidth_limit_bytes_per_sec,
+       created_at,
+       updated_at,
+       started_at,
+       completed_at
 FROM transfers
 WHERE id = $1
         "#,
-        transfer_id
     )
+    .bind(transfer_id)
     .fetch_one(pool)
     .await?;
 
@@ -177,37 +209,153 @@ pub async fn list_by_pool(
     pool_id: Uuid,
     name_filter: Option<&str>,
 ) -> Result<Vec<Transfer>, sqlx::Error> {
-    let rows: Vec<TransferRow> = sqlx::query_as!(
-        TransferRow,
+    let rows = sqlx::query_as::<_, TransferRow>(
         r#"
 SELECT id,
        name,
-       transfer_type as "transfer_type: _",
-       status as "status: _",
+       transfer_type,
+       status,
        source,
        storage_pool_id,
-       object_type as "object_type: _",
-       storage_object_id as "storage_object_id?",
-       total_bytes as "total_bytes?",
-       transferred_bytes as "transferred_bytes?",
-       error_message as "error_message?",
-       created_at as "created_at?",
-       updated_at as "updated_at?",
-       started_at as "started_at?",
-       completed_at as "completed_at?"
+       object_type,
+       storage_object_id,
+       total_bytes,
+       transferred_bytes,
+       error_message,
+       expected_checksum,
+       actual_checksum,
+       checksum_verified,
+       bandwidth_limit_bytes_per_sec,
+       created_at,
+       updated_at,
+       started_at,
+       completed_at
 FROM transfers
 WHERE storage_pool_id = $1 AND ($2::text IS NULL OR name = $2)
 ORDER BY created_at DESC
         "#,
-        pool_id,
-        name_filter
     )
+    .bind(pool_id)
+    .bind(name_filter)
     .fetch_all(pool)
     .await?;
 
     Ok(rows.into_iter().map(|r| r.into()).collect())
 }
 
+/// Start tracking a client upload. Uploads are running from the first byte;
+/// there is no pending phase because the client drives the transfer.
+pub async fn create_upload(
+    pool: &PgPool,
+    storage_pool_id: Uuid,
+    name: &str,
+    object_type: &StorageObjectType,
+    total_bytes: i64,
+) -> Result<Transfer, sqlx::Error> {
+    let row = sqlx::query_as::<_, TransferRow>(
+        r#"
+INSERT INTO transfers (id, name, transfer_type, status, source, storage_pool_id, object_type,
+                       total_bytes, transferred_bytes, started_at)
+VALUES ($1, $2, 'UPLOAD', 'RUNNING', 'upload', $3, $4, $5, 0, CURRENT_TIMESTAMP)
+RETURNING id,
+          name,
+          transfer_type,
+          status,
+          source,
+          storage_pool_id,
+          object_type,
+          storage_object_id,
+          total_bytes,
+          transferred_bytes,
+          error_message,
+          expected_checksum,
+          actual_checksum,
+          checksum_verified,
+          bandwidth_limit_bytes_per_sec,
+          created_at,
+          updated_at,
+          started_at,
+          completed_at
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(name)
+    .bind(storage_pool_id)
+    .bind(object_type)
+    .bind(total_bytes)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.into())
+}
+
+/// The in-progress upload with this name in the pool, if any.
+pub async fn find_active_upload(
+    pool: &PgPool,
+    storage_pool_id: Uuid,
+    name: &str,
+) -> Result<Option<Transfer>, sqlx::Error> {
+    let row = sqlx::query_as::<_, TransferRow>(
+        r#"
+SELECT id,
+       name,
+       transfer_type,
+       status,
+       source,
+       storage_pool_id,
+       object_type,
+       storage_object_id,
+       total_bytes,
+       transferred_bytes,
+       error_message,
+       expected_checksum,
+       actual_checksum,
+       checksum_verified,
+       bandwidth_limit_bytes_per_sec,
+       created_at,
+       updated_at,
+       started_at,
+       completed_at
+FROM transfers
+WHERE storage_pool_id = $1
+  AND name = $2
+  AND transfer_type = 'UPLOAD'
+  AND status = 'RUNNING'
+        "#,
+    )
+    .bind(storage_pool_id)
+    .bind(name)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(Into::into))
+}
+
+/// Advance an upload's offset, but only if nobody else moved it since
+/// `previous_bytes` was read. Returns `false` when the update lost that race.
+pub async fn advance_upload(
+    pool: &PgPool,
+    transfer_id: Uuid,
+    previous_bytes: i64,
+    transferred_bytes: i64,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+UPDATE transfers
+SET transferred_bytes = $3,
+    updated_at = CURRENT_TIMESTAMP
+WHERE id = $1 AND status = 'RUNNING' AND transferred_bytes = $2
+        "#,
+    )
+    .bind(transfer_id)
+    .bind(previous_bytes)
+    .bind(transferred_bytes)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
 pub async fn mark_running(pool: &PgPool, transfer_id: Uuid) -> Result<(), sqlx::Error> {
     sqlx::query!(
         r#"
@@ -251,6 +399,31 @@ WHERE id = $1
     Ok(())
 }
 
+/// Record the digest the node computed and whether it matched the expected one.
+pub async fn record_checksum(
+    pool: &PgPool,
+    transfer_id: Uuid,
+    actual_checksum: &str,
+    verified: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+UPDATE transfers
+SET actual_checksum = $2,
+    checksum_verified = $3,
+    updated_at = CURRENT_TIMESTAMP
+WHERE id = $1
+        "#,
+    )
+    .bind(transfer_id)
+    .bind(actual_checksum)
+    .bind(verified)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
 pub async fn mark_failed(
     pool: &PgPool,
     transfer_id: Uuid,
@@ -396,10 +396,12 @@ pub async fn update_storage_object(
 #[derive(sqlx::FromRow)]
 pub struct OciDiskRow {
     pub disk_id: Uuid,
+    pub disk_config: sqlx::types::Json<serde_json::Value>,
     pub oci_storage_object_id: Uuid,
     pub oci_config: sqlx::types::Json<serde_json::Value>,
     pub upper_storage_object_id: Option<Uuid>,
     pub upper_config: Option<sqlx::types::Json<serde_json::Value>>,
+    pub upper_size_bytes: Option<i64>,
 }
 
 /// Find the OCI image disk on a VM in a single SQL join.
@@ -414,10 +416,12 @@ pub async fn find_oci_disk_for_vm(
         r#"
         SELECT
             vd.id                       AS disk_id,
+            vd.config                   AS disk_config,
             so.id                       AS oci_storage_object_id,
             so.config                   AS oci_config,
             vd.upper_storage_object_id,
-            upper_so.config             AS upper_config
+            upper_so.config             AS upper_config,
+            upper_so.size_bytes         AS upper_size_bytes
         FROM vm_disks vd
         JOIN storage_objects so ON vd.storage_object_id = so.id
         JOIN storage_pools sp ON so.storage_pool_id = sp.id
@@ -34,6 +34,7 @@ pub struct VmTemplate {
     pub root_disk_object_id: Option<Uuid>,
     pub boot_mode: Option<BootMode>,
     pub image_ref: Option<String>,
+    pub image_catalog_id: Option<Uuid>,
     pub cloud_init_user_data: Option<String>,
     pub cloud_init_meta_data: Option<String>,
     pub cloud_init_network_config: Option<String>,
@@ -64,6 +65,7 @@ struct VmTemplateRow {
     pub root_disk_object_id: Option<Uuid>,
     pub boot_mode: Option<BootMode>,
     pub image_ref: Option<String>,
+    pub image_catalog_id: Option<Uuid>,
     pub cloud_init_user_data: Option<String>,
     pub cloud_init_meta_data: Option<String>,
     pub cloud_init_network_config: Option<String>,
@@ -95,6 +97,7 @@ impl From<VmTemplateRow> for VmTemplate {
             root_disk_object_id: row.root_disk_object_id,
             boot_mode: row.boot_mode,
             image_ref: row.image_ref,
+            image_catalog_id: row.image_catalog_id,
             cloud_init_user_data: row.cloud_init_user_data,
             cloud_init_meta_data: row.cloud_init_meta_data,
             cloud_init_network_config: row.cloud_init_network_config,
@@ -126,6 +129,9 @@ pub struct NewVmTemplate {
     pub root_disk_object_id: Option<Uuid>,
     pub boot_mode: Option<BootMode>,
     pub image_ref: Option<String>,
+    /// Use the newest version of this image catalog as the image. Mutually
+    /// exclusive with `image_ref`.
+    pub image_catalog_id: Option<Uuid>,
     pub cloud_init_user_data: Option<String>,
     pub cloud_init_meta_data: Option<String>,
     pub cloud_init_network_config: Option<String>,
@@ -167,6 +173,7 @@ SELECT id,
        root_disk_object_id,
        boot_mode,
        image_ref,
+       image_catalog_id,
        cloud_init_user_data,
        cloud_init_meta_data,
        cloud_init_network_config,
@@ -208,6 +215,7 @@ SELECT id,
        root_disk_object_id,
        boot_mode,
        image_ref,
+       image_catalog_id,
        cloud_init_user_data,
        cloud_init_meta_data,
        cloud_init_network_config,
@@ -254,6 +262,7 @@ INSERT INTO vm_templates (
     root_disk_object_id,
     boot_mode,
     image_ref,
+    image_catalog_id,
     cloud_init_user_data,
     cloud_init_meta_data,
     cloud_init_network_config,
@@ -265,7 +274,7 @@ VALUES (
     $1, $2, $3, $4, $5, $6, $7, $8,
     $9, $10, $11, $12, $13, $14, $15, $16,
     $17, $18, $19, $20, $21, $22, $23, $24, $25,
-    $26
+    $26, $27
 )
         "#,
     )
@@ -289,6 +298,7 @@ VALUES (
     .bind(new_vm_template.root_disk_object_id)
     .bind(new_vm_template.boot_mode)
     .bind(new_vm_template.image_ref)
+    .bind(new_vm_template.image_catalog_id)
     .bind(new_vm_template.cloud_init_user_data)
     .bind(new_vm_template.cloud_init_meta_data)
     .bind(new_vm_template.cloud_init_network_config)
@@ -339,6 +349,7 @@ pub async fn create_from_vm(
         root_disk_object_id,
         boot_mode: Some(vm.boot_mode),
         image_ref: vm.image_ref.clone(),
+        image_catalog_id: None,
         cloud_init_user_data: vm.cloud_init_user_data.clone(),
         cloud_init_meta_data: vm.cloud_init_meta_data.clone(),
         cloud_init_network_config: vm.cloud_init_network_config.clone(),
@@ -8,10 +8,12 @@ use uuid::Uuid;
 
 use crate::{
     errors::Error,
-    model::{instance_types, vm_templates},
+    model::{image_catalogs, instance_types, vm_templates},
 };
 
-use crate::model::network_interfaces::{InterfaceType, RateLimiterConfig, VhostMode};
+use crate::model::network_interfaces::{
+    AllowedAddressPair, InterfaceType, RateLimiterConfig, VhostMode,
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct CpuTopology {
@@ -289,6 +291,18 @@ pub enum BootMode {
     Firmware,
 }
 
+/// How the root disk of a VM created from `image_ref` is provisioned.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, Eq, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RootDiskMode {
+    /// Boot from the OverlayBD image, loading blocks lazily from the registry.
+    #[default]
+    Overlaybd,
+    /// Copy the image into a flat raw disk on `root_disk_pool_id` at create
+    /// time. The VM no longer depends on the registry once created.
+    FullClone,
+}
+
 #[derive(
     Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Type, EnumString, Display, ToSchema,
 )]
@@ -353,6 +367,9 @@ pub struct NewVmNetwork {
     pub pci_segment: Option<i32>,
     /// Enable IOMMU for the device
     pub iommu: Option<bool>,
+    /// Extra addresses the NIC may send from on a managed network
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_address_pairs: Vec<AllowedAddressPair>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -428,6 +445,19 @@ pub struct NewVm {
     /// must be attached to the host running the VM.
     pub persistent_upper_pool_id: Option<Uuid>,
 
+    /// Virtual size in bytes of the OverlayBD disk created from `image_ref`,
+    /// in whole GiB. Defaults to 64 GiB, or with `root_disk_mode=full_clone`
+    /// to twice the image's unpacked size (at least 4 GiB). A full clone
+    /// smaller than the unpacked image fails.
+    pub overlaybd_disk_size_bytes: Option<i64>,
+
+    /// How the root disk is provisioned from `image_ref`. Defaults to `overlaybd`.
+    pub root_disk_mode: Option<RootDiskMode>,
+
+    /// Local or NFS pool a `full_clone` root disk is written to. Must be
+    /// attached to the host running the VM.
+    pub root_disk_pool_id: Option<Uuid>,
+
     /// Placement policy controlling host reservation classes, labels, affinity,
     /// anti-affinity, and spread preferences during scheduling.
     pub placement_policy: Option<PlacementPolicy>,
@@ -485,6 +515,9 @@ pub struct ResolvedNewVm {
     pub accelerator_config: Option<serde_json::Value>,
     pub numa_config: Option<serde_json::Value>,
     pub persistent_upper_pool_id: Option<Uuid>,
+    pub overlaybd_disk_size_bytes: Option<i64>,
+    pub root_disk_mode: RootDiskMode,
+    pub root_disk_pool_id: Option<Uuid>,
     pub placement_policy: Option<PlacementPolicy>,
     pub config: serde_json::Value,
 }
@@ -509,6 +542,20 @@ fn merge_config(
     }
 }
 
+/// Pinned reference of the newest version of an image catalog.
+async fn latest_catalog_image(pool: &PgPool, catalog_id: Uuid) -> Result<String, Error> {
+    let catalog = image_catalogs::get(pool, catalog_id).await?;
+    image_catalogs::latest_version(pool, catalog_id)
+        .await?
+        .map(|version| version.image_ref)
+        .ok_or_else(|| {
+            Error::UnprocessableEntity(format!(
+                "image catalog '{}' has no imported versions yet",
+                catalog.name
+            ))
+        })
+}
+
 pub async fn resolve_create_request(pool: &PgPool, request: NewVm) -> Result<ResolvedNewVm, Error> {
     let NewVm {
         name,
@@ -543,6 +590,9 @@ pub async fn resolve_create_request(pool: &PgPool, request: NewVm) -> Result<Res
         accelerator_config,
         numa_config,
         persistent_upper_pool_id,
+        overlaybd_disk_size_bytes,
+        root_disk_mode,
+        root_disk_pool_id,
         placement_policy,
         guest_agent,
         config,
@@ -556,6 +606,14 @@ pub async fn resolve_create_request(pool: &PgPool, request: NewVm) -> Result<Res
         Some(id) => Some(instance_types::get(pool, id).await?),
         None => None,
     };
+    let image_ref = match (image_ref, vm_template.as_ref()) {
+        (Some(image_ref), _) => Some(image_ref),
+        (None, Some(template)) => match template.image_catalog_id {
+            Some(catalog_id) => Some(latest_catalog_image(pool, catalog_id).await?),
+            None => template.image_ref.clone(),
+        },
+        (None, None) => None,
+    };
 
     let architecture = architecture
         .or(instance_type
@@ -718,11 +776,7 @@ pub async fn resolve_create_request(pool: &PgPool, request: NewVm) -> Result<Res
                 .as_ref()
                 .and_then(|template| template.description.clone())
         }),
-        image_ref: image_ref.or_else(|| {
-            vm_template
-                .as_ref()
-                .and_then(|template| template.image_ref.clone())
-        }),
+        image_ref,
         cloud_init_user_data: cloud_init_user_data.or_else(|| {
             vm_template
                 .as_ref()
@@ -766,6 +820,9 @@ pub async fn resolve_create_request(pool: &PgPool, request: NewVm) -> Result<Res
                 .filter(|v| !v.is_null())
         }),
         persistent_upper_pool_id,
+        overlaybd_disk_size_bytes,
+        root_disk_mode: root_disk_mode.unwrap_or_default(),
+        root_disk_pool_id,
         placement_policy,
         // NOTE: numa_config is intentionally not merged into `config` here;
         // the handler merges it in create_vm_internal before persisting.
@@ -1105,6 +1162,28 @@ pub async fn update_host_id(pool: &PgPool, vm_id: Uuid, host_id: Uuid) -> Result
     Ok(())
 }
 
+/// Whether packets dropped by the VM's security groups are logged.
+pub async fn firewall_log_denied(pool: &PgPool, vm_id: Uuid) -> Result<bool, sqlx::Error> {
+    let (enabled,): (bool,) = sqlx::query_as("SELECT firewall_log_denied FROM vms WHERE id = $1")
+        .bind(vm_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(enabled)
+}
+
+pub async fn update_firewall_log_denied(
+    pool: &PgPool,
+    vm_id: Uuid,
+    enabled: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE vms SET firewall_log_denied = $1 WHERE id = $2")
+        .bind(enabled)
+        .bind(vm_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
 pub async fn update_name(pool: &PgPool, vm_id: Uuid, name: &str) -> Result<(), sqlx::Error> {
     sqlx::query("UPDATE vms SET name = $1 WHERE id = $2")
         .bind(name)
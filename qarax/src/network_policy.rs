@@ -1,6 +1,7 @@
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use futures::future::try_join_all;
+use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::{
@@ -9,13 +10,22 @@ use crate::{
     grpc_client::{
         NodeClient,
         node::{
-            FirewallDirection, FirewallProtocol, VmFirewallInterface, VmFirewallRule,
-            VpcOverlayConfig, VpcOverlayRoute,
+            AllowedAddressPair as ProtoAllowedAddressPair, DhcpOptions, DhcpReservation,
+            DhcpStaticRoute, DnsRecord, FirewallDirection, FirewallProtocol, FloatingIpMapping,
+            PortForwardRule, StretchedMacEntry, SyncDhcpReservationsRequest, SyncNetworkDnsRequest,
+            SyncStretchedNetworkRequest, SyncVmNatRequest, VmFirewallInterface, VmFirewallRule,
+            VmPortSecurity, VpcOverlayConfig, VpcOverlayRoute,
         },
     },
     model::{
-        hosts, network_interfaces, networks,
-        security_groups::{self, SecurityGroupDirection, SecurityGroupProtocol},
+        floating_ips, hosts,
+        network_interfaces::{self, NetworkInterface},
+        networks,
+        port_forwards::{self, PortForwardProtocol},
+        security_groups::{
+            self, DeniedPacket, SecurityGroupDirection, SecurityGroupMemberStats,
+            SecurityGroupProtocol, SecurityGroupRule, SecurityGroupRuleStats, SecurityGroupStats,
+        },
         vms::{self, VmStatus},
     },
 };
@@ -36,6 +46,13 @@ fn firewall_protocol_to_proto(protocol: SecurityGroupProtocol) -> i32 {
     }
 }
 
+fn port_forward_protocol_to_proto(protocol: PortForwardProtocol) -> i32 {
+    match protocol {
+        PortForwardProtocol::Tcp => FirewallProtocol::Tcp as i32,
+        PortForwardProtocol::Udp => FirewallProtocol::Udp as i32,
+    }
+}
+
 pub async fn sync_host_network_isolation(env: &App, host_id: Uuid) -> Result<(), Error> {
     let host = hosts::require_by_id(env.pool(), host_id).await?;
     let attached_cluster = networks::list_attached(env.pool()).await?;
@@ -59,6 +76,7 @@ async fn sync_host_network_isolation_for_host(
             .sync_network_isolation(
                 bridge_name,
                 &network.subnet,
+                network.subnet6.as_deref(),
                 &blocked_subnets,
                 &nat_exempt_subnets,
             )
@@ -87,7 +105,7 @@ fn blocked_subnets_for_network(
     attached_host_networks
         .iter()
         .filter(|(other, _)| other.id != network.id && other.vpc_name.as_deref() != Some(vpc_name))
-        .map(|(other, _)| other.subnet.clone())
+        .flat_map(|(other, _)| network_subnets(other))
         .chain(
             attached_cluster
                 .iter()
@@ -96,7 +114,7 @@ fn blocked_subnets_for_network(
                         && other.network.id != network.id
                         && other.network.vpc_name.as_deref() != Some(vpc_name)
                 })
-                .map(|other| other.network.subnet.clone()),
+                .flat_map(|other| network_subnets(&other.network)),
         )
         .collect::<BTreeSet<_>>()
         .into_iter()
@@ -110,7 +128,10 @@ fn nat_exempt_subnets_for_network(
     let Some(vpc_name) = network.vpc_name.as_deref() else {
         return Vec::new();
     };
-    if network.network_type.as_deref() != Some("isolated") {
+    if !matches!(
+        network.network_type.as_deref(),
+        Some("isolated" | networks::STRETCHED_NETWORK_TYPE)
+    ) {
         return Vec::new();
     }
 
@@ -119,12 +140,17 @@ fn nat_exempt_subnets_for_network(
         .filter(|other| {
             other.network.id != network.id && other.network.vpc_name.as_deref() == Some(vpc_name)
         })
-        .map(|other| other.network.subnet.clone())
+        .flat_map(|other| network_subnets(&other.network))
         .collect::<BTreeSet<_>>()
         .into_iter()
         .collect()
 }
 
+/// IPv4 subnet of a network, plus its IPv6 prefix when it is dual-stack.
+fn network_subnets(network: &networks::Network) -> impl Iterator<Item = String> {
+    std::iter::once(network.subnet.clone()).chain(network.subnet6.clone())
+}
+
 pub async fn sync_cluster_vpc_state(
     env: &App,
     network: &networks::Network,
@@ -210,7 +236,7 @@ async fn sync_host_vpc_overlays_for_host(
     let mut overlays = Vec::new();
     let mut resolved_underlay_ips = HashMap::new();
 
-    for (vpc_name, _local_attachments) in local_vpcs {
+    for (vpc_name, local_attachments) in local_vpcs {
         let participants = vpc_participants(attached, &vpc_name);
         if participants.len() <= 1 {
             continue;
@@ -268,6 +294,14 @@ async fn sync_host_vpc_overlays_for_host(
                 })?;
             let remote_overlay_ip = overlay_ip(&vpc_name, remote_index)?;
             for attachment in remote_attachments {
+                // A stretched subnet attached here is already on-link.
+                if attachment.network.is_stretched()
+                    && local_attachments
+                        .iter()
+                        .any(|local| local.network.id == attachment.network.id)
+                {
+                    continue;
+                }
                 routes.insert((attachment.network.subnet.clone(), remote_overlay_ip.clone()));
             }
         }
@@ -330,7 +364,10 @@ fn overlay_interface_name(vpc_name: &str) -> String {
 }
 
 fn overlay_vni(vpc_name: &str) -> u32 {
-    let seed = overlay_seed(vpc_name);
+    vni_from_seed(overlay_seed(vpc_name))
+}
+
+fn vni_from_seed(seed: Uuid) -> u32 {
     let bytes = seed.as_bytes();
     let vni = ((u32::from(bytes[0])) << 16) | ((u32::from(bytes[1])) << 8) | u32::from(bytes[2]);
     if vni == 0 { 1 } else { vni }
@@ -355,6 +392,120 @@ fn overlay_ip_cidr(vpc_name: &str, host_index: usize) -> Result<String, Error> {
     Ok(format!("{}/24", overlay_ip(vpc_name, host_index)?))
 }
 
+fn stretched_interface_name(network_id: Uuid) -> String {
+    format!("qst{}", &network_id.simple().to_string()[..8])
+}
+
+fn stretched_vni(network_id: Uuid) -> u32 {
+    vni_from_seed(Uuid::new_v5(
+        &Uuid::NAMESPACE_DNS,
+        format!("qarax:stretched:{network_id}").as_bytes(),
+    ))
+}
+
+/// Push a stretched network's VXLAN peers, remote VM MACs and DHCP ownership
+/// to every host it is attached to.
+pub async fn sync_stretched_network(
+    pool: &PgPool,
+    network: &networks::Network,
+) -> Result<(), Error> {
+    let attached: Vec<networks::AttachedNetwork> = networks::list_attached(pool)
+        .await?
+        .into_iter()
+        .filter(|attachment| attachment.network.id == network.id)
+        .collect();
+    if attached.is_empty() {
+        return Ok(());
+    }
+
+    let mut resolved_underlay_ips = HashMap::new();
+    let mut underlay_ips = BTreeMap::new();
+    for attachment in &attached {
+        let ip = resolve_underlay_ip(
+            &mut resolved_underlay_ips,
+            &attachment.host_address,
+            attachment.host_port as u16,
+        )
+        .await?;
+        underlay_ips.insert(attachment.host_id, ip);
+    }
+
+    let nic_locations = networks::nic_locations(pool, network.id).await?;
+    // The lowest host id serves DHCP, so ownership only moves when that host
+    // detaches.
+    let dhcp_host_id = underlay_ips.keys().next().copied();
+    let gateway = network.ipv4_gateway();
+    let dns = network.dns.clone().unwrap_or_else(|| gateway.clone());
+    let (dhcp_range_start, dhcp_range_end) =
+        networks::compute_dhcp_range(&network.subnet, network.gateway.as_deref());
+    let dhcp_options = dhcp_options(network);
+
+    try_join_all(attached.iter().map(|attachment| {
+        let request = SyncStretchedNetworkRequest {
+            bridge_name: attachment.bridge_name.clone(),
+            interface_name: stretched_interface_name(network.id),
+            vni: stretched_vni(network.id),
+            local_underlay_ip: underlay_ips[&attachment.host_id].clone(),
+            peer_underlay_ips: underlay_ips
+                .iter()
+                .filter(|(host_id, _)| **host_id != attachment.host_id)
+                .map(|(_, ip)| ip.clone())
+                .collect(),
+            remote_macs: nic_locations
+                .iter()
+                .filter(|(_, host_id)| *host_id != attachment.host_id)
+                .filter_map(|(mac, host_id)| {
+                    Some(StretchedMacEntry {
+                        mac: mac.clone(),
+                        underlay_ip: underlay_ips.get(host_id)?.clone(),
+                    })
+                })
+                .collect(),
+            serve_dhcp: dhcp_host_id == Some(attachment.host_id),
+            dhcp_range_start: dhcp_range_start.clone(),
+            dhcp_range_end: dhcp_range_end.clone(),
+            gateway: gateway.clone(),
+            dns: dns.clone(),
+            subnet: network.subnet.clone(),
+            dhcp_options: Some(dhcp_options.clone()),
+        };
+        async move {
+            NodeClient::new(&attachment.host_address, attachment.host_port as u16)
+                .sync_stretched_network(request)
+                .await
+                .map_err(|e| {
+                    Error::UnprocessableEntity(format!(
+                        "Failed to sync stretched network {} on host {}: {e}",
+                        network.name, attachment.host_id
+                    ))
+                })
+        }
+    }))
+    .await?;
+
+    Ok(())
+}
+
+/// Networks a VM has at least one interface on.
+pub async fn vm_network_ids(pool: &PgPool, vm_id: Uuid) -> Result<BTreeSet<Uuid>, Error> {
+    Ok(network_interfaces::list_by_vm(pool, vm_id)
+        .await?
+        .into_iter()
+        .filter_map(|nic| nic.network_id)
+        .collect())
+}
+
+/// Re-point the stretched networks a VM is on at the host it now runs on.
+pub async fn sync_stretched_networks_for_vm(pool: &PgPool, vm_id: Uuid) -> Result<(), Error> {
+    for network_id in vm_network_ids(pool, vm_id).await? {
+        let network = networks::get(pool, network_id).await?;
+        if network.is_stretched() {
+            sync_stretched_network(pool, &network).await?;
+        }
+    }
+    Ok(())
+}
+
 async fn resolve_underlay_ip(
     cache: &mut HashMap<String, String>,
     address: &str,
@@ -402,19 +553,20 @@ async fn firewall_interfaces_for_vm(
     }
 
     let rules = security_groups::rule_set_for_vm(env.pool(), vm_id).await?;
-
-    let proto_rules: Vec<VmFirewallRule> = rules
+    let restrict_egress = rules
+        .iter()
+        .any(|rule| rule.direction == SecurityGroupDirection::Egress);
+    let peer_group_ids: Vec<Uuid> = rules
+        .iter()
+        .filter_map(|rule| rule.remote_security_group_id)
+        .collect::<BTreeSet<_>>()
         .into_iter()
-        .map(|rule| VmFirewallRule {
-            direction: firewall_direction_to_proto(rule.direction),
-            protocol: firewall_protocol_to_proto(rule.protocol),
-            cidr: rule.cidr.unwrap_or_default(),
-            port_start: rule.port_start,
-            port_end: rule.port_end,
-        })
         .collect();
+    let peer_addresses = security_groups::member_addresses(env.pool(), &peer_group_ids).await?;
+    let proto_rules = expand_firewall_rules(rules, &peer_addresses);
 
     let nics = network_interfaces::list_by_vm(env.pool(), vm_id).await?;
+    let ipv6_addresses = networks::ipv6_addresses_for_vm(env.pool(), vm_id).await?;
     let mut interfaces = Vec::new();
     for nic in nics {
         let Some(network_id) = nic.network_id else {
@@ -445,19 +597,97 @@ async fn firewall_interfaces_for_vm(
                 .unwrap_or(ip_address)
                 .to_string(),
             rules: proto_rules.clone(),
+            ip6: ipv6_addresses.get(&nic.id).cloned().unwrap_or_default(),
+            restrict_egress,
         });
     }
 
     Ok(interfaces)
 }
 
+/// Anti-spoofing for every NIC of the VM on a managed network: its MAC, its
+/// allocated addresses and any allowed address pairs.
+async fn port_security_for_vm(pool: &PgPool, vm_id: Uuid) -> Result<Vec<VmPortSecurity>, Error> {
+    let nics = network_interfaces::list_by_vm(pool, vm_id).await?;
+    let ipv6_addresses = networks::ipv6_addresses_for_vm(pool, vm_id).await?;
+    let mut allowed_pairs = network_interfaces::allowed_address_pairs_by_vm(pool, vm_id).await?;
+    let mut ports = Vec::new();
+    for nic in nics {
+        let (Some(network_id), Some(mac)) = (nic.network_id, nic.mac_address.as_deref()) else {
+            continue;
+        };
+        let network = networks::get(pool, network_id).await?;
+        if network.network_type.as_deref() == Some("passt") {
+            continue;
+        }
+
+        let addresses = nic
+            .ip_address
+            .as_deref()
+            .map(|ip| ip.split('/').next().unwrap_or(ip).to_string())
+            .into_iter()
+            .chain(ipv6_addresses.get(&nic.id).cloned())
+            .collect();
+        let allowed_address_pairs = allowed_pairs
+            .remove(&nic.id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|pair| ProtoAllowedAddressPair {
+                cidr: pair.ip_address,
+                mac: pair.mac_address.unwrap_or_default(),
+            })
+            .collect();
+        ports.push(VmPortSecurity {
+            mac: mac.to_ascii_lowercase(),
+            addresses,
+            allowed_address_pairs,
+        });
+    }
+    Ok(ports)
+}
+
+/// Turn security-group rules into node rules. A rule whose peer is another
+/// group becomes one host-prefix rule per member address, and nothing while
+/// the group has no members.
+fn expand_firewall_rules(
+    rules: Vec<SecurityGroupRule>,
+    peer_addresses: &HashMap<Uuid, Vec<String>>,
+) -> Vec<VmFirewallRule> {
+    let mut expanded = Vec::with_capacity(rules.len());
+    for rule in rules {
+        let cidrs = match rule.remote_security_group_id {
+            Some(peer_id) => peer_addresses
+                .get(&peer_id)
+                .into_iter()
+                .flatten()
+                .map(|address| {
+                    let prefix = if address.contains(':') { 128 } else { 32 };
+                    format!("{address}/{prefix}")
+                })
+                .collect(),
+            None => vec![rule.cidr.unwrap_or_default()],
+        };
+        expanded.extend(cidrs.into_iter().map(|cidr| VmFirewallRule {
+            direction: firewall_direction_to_proto(rule.direction),
+            protocol: firewall_protocol_to_proto(rule.protocol),
+            cidr,
+            port_start: rule.port_start,
+            port_end: rule.port_end,
+            rule_id: rule.id.to_string(),
+        }));
+    }
+    expanded
+}
+
 pub async fn sync_vm_firewall_on_host(env: &App, vm_id: Uuid, host_id: Uuid) -> Result<(), Error> {
     let host = hosts::require_by_id(env.pool(), host_id).await?;
     let node_client = NodeClient::new(&host.address, host.port as u16);
     let interfaces = firewall_interfaces_for_vm(env, vm_id).await?;
+    let ports = port_security_for_vm(env.pool(), vm_id).await?;
+    let log_denied = vms::firewall_log_denied(env.pool(), vm_id).await?;
 
     node_client
-        .sync_vm_firewall(vm_id, &interfaces)
+        .sync_vm_firewall(vm_id, &interfaces, &ports, log_denied)
         .await
         .map_err(|e| {
             Error::UnprocessableEntity(format!(
@@ -480,17 +710,480 @@ pub async fn sync_vm_firewall(env: &App, vm_id: Uuid) -> Result<(), Error> {
     sync_vm_firewall_on_host(env, vm_id, host_id).await
 }
 
+/// Resync the VMs in a security group and the VMs whose rules use the group
+/// as their peer.
 pub async fn sync_security_group_members(env: &App, security_group_id: Uuid) -> Result<(), Error> {
+    let mut vm_ids: BTreeSet<Uuid> = security_groups::list_vm_ids(env.pool(), security_group_id)
+        .await?
+        .into_iter()
+        .collect();
+    vm_ids.extend(security_groups::list_peer_vm_ids(env.pool(), security_group_id).await?);
+    for vm_id in vm_ids {
+        sync_vm_firewall(env, vm_id).await?;
+    }
+    Ok(())
+}
+
+/// Rule counters of a security group, gathered from the hosts of its running
+/// members. A member whose host cannot be reached is left out.
+pub async fn security_group_stats(
+    env: &App,
+    security_group_id: Uuid,
+) -> Result<SecurityGroupStats, Error> {
+    let rules = security_groups::list_rules(env.pool(), security_group_id).await?;
+    let mut totals: BTreeMap<Uuid, (u64, u64)> =
+        rules.iter().map(|rule| (rule.id, (0, 0))).collect();
+    let mut members = Vec::new();
+
     for vm_id in security_groups::list_vm_ids(env.pool(), security_group_id).await? {
+        let vm = vms::get(env.pool(), vm_id).await?;
+        let Some(host_id) = vm.host_id else {
+            continue;
+        };
+        if !matches!(vm.status, VmStatus::Running | VmStatus::Paused) {
+            continue;
+        }
+        let host = hosts::require_by_id(env.pool(), host_id).await?;
+        let counters = match NodeClient::new(&host.address, host.port as u16)
+            .get_vm_firewall_counters(vm_id)
+            .await
+        {
+            Ok(counters) => counters.counters,
+            Err(e) => {
+                tracing::warn!(vm_id = %vm_id, host = %host.name, error = %e, "Failed to read firewall counters");
+                continue;
+            }
+        };
+
+        let mut member = SecurityGroupMemberStats {
+            vm_id,
+            dropped_ingress_packets: 0,
+            dropped_egress_packets: 0,
+        };
+        for counter in counters {
+            if counter.rule_id.is_empty() {
+                if counter.direction == FirewallDirection::Egress as i32 {
+                    member.dropped_egress_packets += counter.packets;
+                } else {
+                    member.dropped_ingress_packets += counter.packets;
+                }
+            } else if let Ok(rule_id) = counter.rule_id.parse::<Uuid>()
+                && let Some((packets, bytes)) = totals.get_mut(&rule_id)
+            {
+                *packets += counter.packets;
+                *bytes += counter.bytes;
+            }
+        }
+        members.push(member);
+    }
+
+    Ok(SecurityGroupStats {
+        security_group_id,
+        rules: rules
+            .iter()
+            .map(|rule| {
+                let (packets, bytes) = totals[&rule.id];
+                SecurityGroupRuleStats {
+                    rule_id: rule.id,
+                    direction: rule.direction,
+                    packets,
+                    bytes,
+                }
+            })
+            .collect(),
+        members,
+    })
+}
+
+/// Denied packets the VM's host has logged for it, oldest first. A VM that
+/// is not running has no rules on its host, so nothing is logged.
+pub async fn vm_denied_packets(pool: &PgPool, vm_id: Uuid) -> Result<Vec<DeniedPacket>, Error> {
+    let vm = vms::get(pool, vm_id).await?;
+    let Some(host_id) = vm.host_id else {
+        return Ok(Vec::new());
+    };
+    if !matches!(vm.status, VmStatus::Running | VmStatus::Paused) {
+        return Ok(Vec::new());
+    }
+    let host = hosts::require_by_id(pool, host_id).await?;
+    let denied = NodeClient::new(&host.address, host.port as u16)
+        .get_vm_firewall_denied(vm_id)
+        .await
+        .map_err(|e| {
+            Error::UnprocessableEntity(format!(
+                "Failed to read denied packets for {vm_id} on host {}: {e}",
+                host.name
+            ))
+        })?;
+
+    Ok(denied
+        .packets
+        .into_iter()
+        .map(|packet| DeniedPacket {
+            timestamp: chrono::DateTime::from_timestamp_millis(packet.timestamp_ms)
+                .unwrap_or_default(),
+            direction: if packet.direction == FirewallDirection::Egress as i32 {
+                SecurityGroupDirection::Egress
+            } else {
+                SecurityGroupDirection::Ingress
+            },
+            protocol: packet.protocol,
+            source: packet.source,
+            destination: packet.destination,
+            source_port: packet.source_port,
+            destination_port: packet.destination_port,
+        })
+        .collect())
+}
+
+/// Security groups a VM belongs to, for resyncing their peers once the VM's
+/// addresses change.
+pub async fn vm_security_group_ids(pool: &PgPool, vm_id: Uuid) -> Result<Vec<Uuid>, Error> {
+    Ok(security_groups::list_by_vm(pool, vm_id)
+        .await?
+        .into_iter()
+        .map(|group| group.id)
+        .collect())
+}
+
+/// Resync the VMs whose rules use any of these groups as their peer.
+pub async fn sync_security_group_peers(
+    env: &App,
+    security_group_ids: &[Uuid],
+) -> Result<(), Error> {
+    let mut vm_ids = BTreeSet::new();
+    for security_group_id in security_group_ids {
+        vm_ids.extend(security_groups::list_peer_vm_ids(env.pool(), *security_group_id).await?);
+    }
+    for vm_id in vm_ids {
         sync_vm_firewall(env, vm_id).await?;
     }
     Ok(())
 }
 
+/// Resync the peers of every security group a VM belongs to.
+pub async fn sync_security_group_peers_for_vm(env: &App, vm_id: Uuid) -> Result<(), Error> {
+    let security_group_ids = vm_security_group_ids(env.pool(), vm_id).await?;
+    sync_security_group_peers(env, &security_group_ids).await
+}
+
+/// Interface a floating IP or port forward translates to: the one asked for,
+/// or the VM's first interface with an address on a managed network.
+pub async fn nat_target_interface(
+    pool: &PgPool,
+    vm_id: Uuid,
+    network_interface_id: Option<Uuid>,
+) -> Result<NetworkInterface, Error> {
+    let nics = network_interfaces::list_by_vm(pool, vm_id).await?;
+    let nic = match network_interface_id {
+        Some(nic_id) => nics
+            .into_iter()
+            .find(|nic| nic.id == nic_id)
+            .ok_or_else(|| {
+                Error::UnprocessableEntity(format!(
+                    "Interface {nic_id} does not belong to VM {vm_id}"
+                ))
+            })?,
+        None => nics
+            .into_iter()
+            .find(|nic| nic.network_id.is_some() && nic.ip_address.is_some())
+            .ok_or_else(|| {
+                Error::UnprocessableEntity(format!(
+                    "VM {vm_id} has no interface with an address on a managed network"
+                ))
+            })?,
+    };
+
+    let (Some(network_id), Some(_)) = (nic.network_id, nic.ip_address.as_deref()) else {
+        return Err(Error::UnprocessableEntity(format!(
+            "Interface {} has no address on a managed network",
+            nic.id
+        )));
+    };
+    if networks::get(pool, network_id)
+        .await?
+        .network_type
+        .as_deref()
+        == Some("passt")
+    {
+        return Err(Error::UnprocessableEntity(format!(
+            "Interface {} is on a passt network, which the host cannot forward to",
+            nic.id
+        )));
+    }
+    Ok(nic)
+}
+
+async fn nat_request_for_vm(pool: &PgPool, vm_id: Uuid) -> Result<SyncVmNatRequest, Error> {
+    let floating_ips = floating_ips::list_by_vm(pool, vm_id)
+        .await?
+        .into_iter()
+        .filter_map(|floating_ip| {
+            Some(FloatingIpMapping {
+                floating_ip: floating_ip.address,
+                vm_ip: floating_ip.vm_ip?,
+            })
+        })
+        .collect();
+    let port_forwards = port_forwards::list(pool, Some(vm_id))
+        .await?
+        .into_iter()
+        .filter_map(|forward| {
+            Some(PortForwardRule {
+                protocol: port_forward_protocol_to_proto(forward.protocol),
+                host_port: u32::try_from(forward.host_port).ok()?,
+                vm_ip: forward.vm_ip?,
+                vm_port: u32::try_from(forward.vm_port).ok()?,
+            })
+        })
+        .collect();
+
+    Ok(SyncVmNatRequest {
+        vm_id: vm_id.to_string(),
+        floating_ips,
+        port_forwards,
+    })
+}
+
+/// Push a VM's floating IPs and port forwards to a host. The node replaces the
+/// VM's previous NAT state, so this also drops anything no longer assigned.
+pub async fn sync_vm_nat_on_host(pool: &PgPool, vm_id: Uuid, host_id: Uuid) -> Result<(), Error> {
+    let host = hosts::require_by_id(pool, host_id).await?;
+    let request = nat_request_for_vm(pool, vm_id).await?;
+
+    NodeClient::new(&host.address, host.port as u16)
+        .sync_vm_nat(request)
+        .await
+        .map_err(|e| {
+            Error::UnprocessableEntity(format!(
+                "Failed to sync NAT for VM {} on host {}: {e}",
+                vm_id, host.name
+            ))
+        })
+}
+
+pub async fn sync_vm_nat(pool: &PgPool, vm_id: Uuid) -> Result<(), Error> {
+    let vm = vms::get(pool, vm_id).await?;
+    let Some(host_id) = vm.host_id else {
+        return Ok(());
+    };
+
+    if !matches!(vm.status, VmStatus::Running | VmStatus::Shutdown) {
+        return Ok(());
+    }
+
+    sync_vm_nat_on_host(pool, vm_id, host_id).await
+}
+
+/// Fold a VM or network name into a single DNS label: lowercase ASCII
+/// letters, digits and inner hyphens, at most 63 bytes.
+pub fn dns_label(name: &str) -> Option<String> {
+    let folded: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let mut label = folded.trim_matches('-').to_string();
+    label.truncate(63);
+    let label = label.trim_end_matches('-');
+    (!label.is_empty()).then(|| label.to_string())
+}
+
+fn dns_zone(network_name: &str) -> Option<String> {
+    dns_label(network_name).map(|label| format!("{label}.internal"))
+}
+
+/// Push a network's `<vm>.<network>.internal` zone to the resolver on every
+/// host it is attached to. VLAN and passt networks have no bridge address for
+/// a resolver to listen on.
+pub async fn sync_network_dns(pool: &PgPool, network_id: Uuid) -> Result<(), Error> {
+    let network = networks::get(pool, network_id).await?;
+    if network.is_vlan() || network.network_type.as_deref() == Some("passt") {
+        return Ok(());
+    }
+    let Some(zone) = dns_zone(&network.name) else {
+        return Ok(());
+    };
+
+    let attached: Vec<networks::AttachedNetwork> = networks::list_attached(pool)
+        .await?
+        .into_iter()
+        .filter(|attachment| attachment.network.id == network_id)
+        .collect();
+    if attached.is_empty() {
+        return Ok(());
+    }
+
+    let records: Vec<DnsRecord> = networks::dns_records(pool, network_id)
+        .await?
+        .into_iter()
+        .filter_map(|(name, ip, ip6)| {
+            Some(DnsRecord {
+                name: dns_label(&name)?,
+                ip: ip.unwrap_or_default(),
+                ip6: ip6.unwrap_or_default(),
+            })
+        })
+        .collect();
+    let listen_ip = network.ipv4_gateway();
+    let listen_ip6 = network.ipv6_gateway().unwrap_or_default();
+    let network_name = network.name.as_str();
+
+    try_join_all(attached.iter().map(|attachment| {
+        let request = SyncNetworkDnsRequest {
+            bridge_name: attachment.bridge_name.clone(),
+            zone: zone.clone(),
+            listen_ip: listen_ip.clone(),
+            listen_ip6: listen_ip6.clone(),
+            records: records.clone(),
+        };
+        async move {
+            NodeClient::new(&attachment.host_address, attachment.host_port as u16)
+                .sync_network_dns(request)
+                .await
+                .map_err(|e| {
+                    Error::UnprocessableEntity(format!(
+                        "Failed to sync DNS for network {network_name} on host {}: {e}",
+                        attachment.host_id
+                    ))
+                })
+        }
+    }))
+    .await?;
+
+    Ok(())
+}
+
+/// Refresh the DNS zone of every network a VM has an interface on.
+pub async fn sync_network_dns_for_vm(pool: &PgPool, vm_id: Uuid) -> Result<(), Error> {
+    for network_id in vm_network_ids(pool, vm_id).await? {
+        sync_network_dns(pool, network_id).await?;
+    }
+    Ok(())
+}
+
+/// DHCP options for a network's guests. Without an explicit search domain,
+/// guests resolving through the gateway get the built-in zone so bare VM
+/// names work.
+pub fn dhcp_options(network: &networks::Network) -> DhcpOptions {
+    let options = &network.dhcp_options;
+    let domain_name = options.domain_name.clone().or_else(|| {
+        (network.dns.is_none() && !network.is_vlan())
+            .then(|| dns_zone(&network.name))
+            .flatten()
+    });
+
+    DhcpOptions {
+        domain_name: domain_name.unwrap_or_default(),
+        ntp_servers: options.ntp_servers.clone(),
+        mtu: options.mtu.map(u32::from).unwrap_or_default(),
+        static_routes: options
+            .static_routes
+            .iter()
+            .map(|route| DhcpStaticRoute {
+                destination: route.destination.clone(),
+                gateway: route.gateway.clone(),
+            })
+            .collect(),
+    }
+}
+
+/// A network's MAC -> IPv4 reservations, sent with every attach and on every
+/// NIC change. VLAN and passt networks have no qarax DHCP server, so none.
+pub async fn dhcp_reservations(
+    pool: &PgPool,
+    network: &networks::Network,
+) -> Result<Vec<DhcpReservation>, Error> {
+    if network.is_vlan() || network.network_type.as_deref() == Some("passt") {
+        return Ok(Vec::new());
+    }
+
+    Ok(networks::dhcp_reservations(pool, network.id)
+        .await?
+        .into_iter()
+        .map(|(mac, ip, vm_name)| DhcpReservation {
+            mac,
+            ip,
+            hostname: dns_label(&vm_name).unwrap_or_default(),
+        })
+        .collect())
+}
+
+/// Push a network's MAC -> IPv4 reservations to the DHCP server on every
+/// host it is attached to, so guests lease the address qarax allocated.
+/// VLAN and passt networks have no qarax DHCP server.
+pub async fn sync_network_dhcp(pool: &PgPool, network_id: Uuid) -> Result<(), Error> {
+    let network = networks::get(pool, network_id).await?;
+    if network.is_vlan() || network.network_type.as_deref() == Some("passt") {
+        return Ok(());
+    }
+
+    let attached: Vec<networks::AttachedNetwork> = networks::list_attached(pool)
+        .await?
+        .into_iter()
+        .filter(|attachment| attachment.network.id == network_id)
+        .collect();
+    if attached.is_empty() {
+        return Ok(());
+    }
+
+    let reservations = dhcp_reservations(pool, &network).await?;
+    let network_name = network.name.as_str();
+
+    try_join_all(attached.iter().map(|attachment| {
+        let request = SyncDhcpReservationsRequest {
+            bridge_name: attachment.bridge_name.clone(),
+            reservations: reservations.clone(),
+        };
+        async move {
+            NodeClient::new(&attachment.host_address, attachment.host_port as u16)
+                .sync_dhcp_reservations(request)
+                .await
+                .map_err(|e| {
+                    Error::UnprocessableEntity(format!(
+                        "Failed to sync DHCP reservations for network {network_name} on host {}: {e}",
+                        attachment.host_id
+                    ))
+                })
+        }
+    }))
+    .await?;
+
+    Ok(())
+}
+
+/// Refresh the DHCP reservations of every network a VM has an interface on.
+pub async fn sync_network_dhcp_for_vm(pool: &PgPool, vm_id: Uuid) -> Result<(), Error> {
+    for network_id in vm_network_ids(pool, vm_id).await? {
+        sync_network_dhcp(pool, network_id).await?;
+    }
+    Ok(())
+}
+
+/// Push everything the node serves about a network's VMs: DHCP reservations
+/// and DNS records.
+pub async fn sync_network_records(pool: &PgPool, network_id: Uuid) -> Result<(), Error> {
+    sync_network_dhcp(pool, network_id).await?;
+    sync_network_dns(pool, network_id).await
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{blocked_subnets_for_network, nat_exempt_subnets_for_network};
-    use crate::model::networks::{AttachedNetwork, Network, NetworkStatus};
+    use std::collections::HashMap;
+
+    use super::{
+        blocked_subnets_for_network, dns_label, expand_firewall_rules,
+        nat_exempt_subnets_for_network, stretched_interface_name, stretched_vni,
+    };
+    use crate::model::{
+        networks::{AttachedNetwork, Network, NetworkStatus},
+        security_groups::{SecurityGroupDirection, SecurityGroupProtocol, SecurityGroupRule},
+    };
     use uuid::Uuid;
 
     fn make_network(
@@ -504,9 +1197,13 @@ mod tests {
             name: name.to_string(),
             subnet: subnet.to_string(),
             gateway: None,
+            subnet6: None,
+            gateway6: None,
             dns: None,
             vpc_name: vpc_name.map(str::to_string),
             network_type: network_type.map(str::to_string),
+            vlan_id: None,
+            dhcp_options: Default::default(),
             status: NetworkStatus::Active,
         }
     }
@@ -560,6 +1257,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn dual_stack_prefixes_are_blocked_with_their_ipv4_subnets() {
+        let host = Uuid::new_v4();
+        let local = make_network("a", "10.10.1.0/24", Some("vpc-a"), Some("isolated"));
+        let mut other = make_network("b", "10.20.1.0/24", Some("vpc-b"), Some("isolated"));
+        other.subnet6 = Some("fd00:20::/64".to_string());
+
+        let attached_host_networks = vec![
+            (local.clone(), "bra".to_string()),
+            (other.clone(), "brb".to_string()),
+        ];
+        let attached_cluster = vec![make_attachment(host, &local), make_attachment(host, &other)];
+
+        assert_eq!(
+            blocked_subnets_for_network(&local, host, &attached_host_networks, &attached_cluster),
+            vec!["10.20.1.0/24".to_string(), "fd00:20::/64".to_string()]
+        );
+    }
+
     #[test]
     fn affected_hosts_are_scoped_to_the_changed_vpc() {
         let host_a = Uuid::new_v4();
@@ -582,4 +1298,59 @@ mod tests {
             expected
         );
     }
+
+    #[test]
+    fn stretched_network_device_fits_ifnamsiz() {
+        let network = make_network("l2", "10.40.0.0/24", None, Some("stretched"));
+        let name = stretched_interface_name(network.id);
+
+        assert!(name.starts_with("qst"));
+        assert!(name.len() <= 15);
+        assert_eq!(stretched_vni(network.id), stretched_vni(network.id));
+        assert_ne!(stretched_vni(network.id), 0);
+    }
+
+    #[test]
+    fn dns_label_folds_names_into_one_label() {
+        assert_eq!(dns_label("Web_1").as_deref(), Some("web-1"));
+        assert_eq!(dns_label("etcd.node-0").as_deref(), Some("etcd-node-0"));
+        assert_eq!(dns_label("--db--").as_deref(), Some("db"));
+        assert_eq!(dns_label("___"), None);
+        assert_eq!(
+            dns_label(&"a".repeat(70)).map(|label| label.len()),
+            Some(63)
+        );
+    }
+
+    #[test]
+    fn peer_rules_expand_to_member_addresses() {
+        let web = Uuid::new_v4();
+        let empty = Uuid::new_v4();
+        let rule = |cidr: Option<&str>, remote_security_group_id| SecurityGroupRule {
+            id: Uuid::new_v4(),
+            security_group_id: Uuid::new_v4(),
+            direction: SecurityGroupDirection::Ingress,
+            protocol: SecurityGroupProtocol::Tcp,
+            cidr: cidr.map(str::to_string),
+            remote_security_group_id,
+            port_start: Some(5432),
+            port_end: Some(5432),
+            description: None,
+        };
+        let peer_addresses =
+            HashMap::from([(web, vec!["10.0.0.5".to_string(), "fd00::5".to_string()])]);
+
+        let expanded = expand_firewall_rules(
+            vec![
+                rule(Some("192.168.0.0/16"), None),
+                rule(None, Some(web)),
+                rule(None, Some(empty)),
+            ],
+            &peer_addresses,
+        );
+
+        let cidrs: Vec<&str> = expanded.iter().map(|rule| rule.cidr.as_str()).collect();
+        assert_eq!(cidrs, ["192.168.0.0/16", "10.0.0.5/32", "fd00::5/128"]);
+        assert!(expanded.iter().all(|rule| rule.port_start == Some(5432)));
+    }
 }
@@ -7,6 +7,7 @@ use tracing::warn;
 use crate::model::host_gpus::{self, GpuDiscovery};
 use crate::model::host_numa::{self, NumaNodeDiscovery};
 use crate::model::hosts::{self, Host, HostStatus};
+use crate::model::storage_pools::{self, PoolCapacity, StoragePathHealth, StoragePoolType};
 use crate::{
     App,
     grpc_client::{NodeClient, node::NodeInfo},
@@ -115,6 +116,73 @@ async fn handle_probe_result(env: &App, host: &Host, node_info: Result<NodeInfo>
     }
 }
 
+/// Ask the node for the capacity of every pool it has attached and, for BLOCK
+/// pools, the state of every path. Path health flips pools between ACTIVE
+/// and DEGRADED; capacity feeds disk-create admission.
+async fn probe_storage_pools(env: &App, host: &Host, client: &NodeClient) {
+    let pools = match storage_pools::list_for_host(env.pool(), host.id).await {
+        Ok(pools) => pools,
+        Err(e) => {
+            warn!(
+                "Resource monitor: failed to list storage pools for host {}: {}",
+                host.name, e
+            );
+            return;
+        }
+    };
+
+    for pool in &pools {
+        let health = match client.storage_pool_health(pool).await {
+            Ok(health) => health,
+            Err(e) => {
+                warn!(
+                    "Resource monitor: failed to probe pool {} on host {}: {}",
+                    pool.name, host.name, e
+                );
+                continue;
+            }
+        };
+
+        if let Some(capacity) = health.capacity.map(PoolCapacity::from)
+            && let Err(e) =
+                storage_pools::record_capacity(env.pool(), pool.id, host.id, &capacity).await
+        {
+            warn!(
+                "Resource monitor: failed to record capacity of pool {} on host {}: {}",
+                pool.name, host.name, e
+            );
+        }
+
+        if pool.pool_type != StoragePoolType::Block {
+            continue;
+        }
+        let paths = health
+            .paths
+            .into_iter()
+            .map(|p| StoragePathHealth {
+                path: p.path,
+                state: p.state,
+                healthy: p.healthy,
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(down) = paths.iter().find(|p| !p.healthy) {
+            warn!(
+                "Resource monitor: pool {} on host {} has an unhealthy path {} ({})",
+                pool.name, host.name, down.path, down.state
+            );
+        }
+        if let Err(e) =
+            storage_pools::record_path_health(env.pool(), pool.id, host.id, &paths).await
+        {
+            warn!(
+                "Resource monitor: failed to record path health of pool {} on host {}: {}",
+                pool.name, host.name, e
+            );
+        }
+    }
+}
+
 pub async fn start_resource_monitor(env: App) {
     let period = Duration::from_secs(30);
     let mut ticker = interval_at(Instant::now() + period, period);
@@ -146,7 +214,12 @@ pub async fn start_resource_monitor(env: App) {
             let env = env.clone();
             join_set.spawn(async move {
                 let client = NodeClient::new(&host.address, host.port as u16);
-                handle_probe_result(&env, &host, client.get_node_info().await).await;
+                let node_info = client.get_node_info().await;
+                let reachable = node_info.is_ok();
+                handle_probe_result(&env, &host, node_info).await;
+                if reachable {
+                    probe_storage_pools(&env, &host, &client).await;
+                }
             });
         }
         while let Some(result) = join_set.join_next().await {
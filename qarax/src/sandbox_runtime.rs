@@ -56,6 +56,9 @@ pub(crate) async fn resolve_sandbox_vm(
         accelerator_config: None,
         numa_config: None,
         persistent_upper_pool_id: None,
+        overlaybd_disk_size_bytes: None,
+        root_disk_mode: None,
+        root_disk_pool_id: None,
         placement_policy: None,
         guest_agent: Some(true),
         config: serde_json::json!({}),
@@ -102,7 +105,15 @@ pub(crate) async fn destroy_vm(pool: &PgPool, vm_id: Uuid) {
         }
     }
 
+    let network_ids = crate::network_policy::vm_network_ids(pool, vm_id)
+        .await
+        .unwrap_or_default();
     if let Err(e) = vms::delete(pool, vm_id).await {
         tracing::error!(vm_id = %vm_id, error = %e, "Failed to delete sandbox VM from DB");
     }
+    for network_id in network_ids {
+        if let Err(e) = crate::network_policy::sync_network_records(pool, network_id).await {
+            tracing::warn!(network_id = %network_id, error = %e, "Failed to sync network DHCP and DNS records");
+        }
+    }
 }
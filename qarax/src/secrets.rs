@@ -0,0 +1,112 @@
+//! Encryption of secrets stored in the database (registry passwords).
+//!
+//! Values are sealed with AES-256-GCM under the key from
+//! `secrets.encryption_key` (64 hex characters). The stored blob is the random
+//! 12-byte nonce followed by the ciphertext and tag. Callers pass associated
+//! data — typically the owning row's id — so a ciphertext copied into another
+//! row fails to decrypt.
+
+use std::sync::OnceLock;
+
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+
+static CIPHER: OnceLock<SecretCipher> = OnceLock::new();
+
+#[derive(Debug, thiserror::Error)]
+pub enum SecretsError {
+    #[error("no secrets.encryption_key is configured")]
+    NotConfigured,
+    #[error("encryption key must be 64 hex characters (32 bytes)")]
+    InvalidKey,
+    #[error("failed to encrypt secret")]
+    Encrypt,
+    #[error("failed to decrypt secret (wrong key or corrupted value)")]
+    Decrypt,
+}
+
+pub struct SecretCipher {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl SecretCipher {
+    pub fn from_hex(key: &str) -> Result<Self, SecretsError> {
+        let bytes = hex::decode(key.trim()).map_err(|_| SecretsError::InvalidKey)?;
+        let unbound =
+            UnboundKey::new(&AES_256_GCM, &bytes).map_err(|_| SecretsError::InvalidKey)?;
+        Ok(Self {
+            key: LessSafeKey::new(unbound),
+            rng: SystemRandom::new(),
+        })
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, SecretsError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| SecretsError::Encrypt)?;
+
+        let mut sealed = plaintext.to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(aad),
+                &mut sealed,
+            )
+            .map_err(|_| SecretsError::Encrypt)?;
+
+        let mut out = nonce.to_vec();
+        out.extend_from_slice(&sealed);
+        Ok(out)
+    }
+
+    pub fn decrypt(&self, blob: &[u8], aad: &[u8]) -> Result<Vec<u8>, SecretsError> {
+        if blob.len() < NONCE_LEN {
+            return Err(SecretsError::Decrypt);
+        }
+        let (nonce, sealed) = blob.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| SecretsError::Decrypt)?;
+        let mut buf = sealed.to_vec();
+        let plaintext = self
+            .key
+            .open_in_place(nonce, Aad::from(aad), &mut buf)
+            .map_err(|_| SecretsError::Decrypt)?;
+        Ok(plaintext.to_vec())
+    }
+}
+
+/// Install the process-wide key. Later calls are ignored.
+pub fn init(key: &str) -> Result<(), SecretsError> {
+    let cipher = SecretCipher::from_hex(key)?;
+    let _ = CIPHER.set(cipher);
+    Ok(())
+}
+
+pub fn cipher() -> Result<&'static SecretCipher, SecretsError> {
+    CIPHER.get().ok_or(SecretsError::NotConfigured)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    #[test]
+    fn round_trips_and_binds_associated_data() {
+        let cipher = SecretCipher::from_hex(KEY).unwrap();
+        let blob = cipher.encrypt(b"hunter2", b"row-1").unwrap();
+
+        assert!(!blob.windows(7).any(|w| w == b"hunter2"));
+        assert_eq!(cipher.decrypt(&blob, b"row-1").unwrap(), b"hunter2");
+        assert!(cipher.decrypt(&blob, b"row-2").is_err());
+        assert_ne!(blob, cipher.encrypt(b"hunter2", b"row-1").unwrap());
+    }
+
+    #[test]
+    fn rejects_malformed_keys() {
+        assert!(SecretCipher::from_hex("abcd").is_err());
+        assert!(SecretCipher::from_hex(&"zz".repeat(32)).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+/// Background task that removes snapshot directories left on storage pools
+/// after their storage object row is gone, e.g. when the control plane died
+/// mid-snapshot or the object was deleted through `/storage-objects` directly.
+use std::collections::HashSet;
+
+use anyhow::Result;
+use tokio::time::{Duration, Instant, interval_at};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    App,
+    grpc_client::{NodeClient, node::DirectoryEntry},
+    model::{
+        hosts::{self, Host},
+        storage_objects,
+        storage_pools::{self, StoragePool},
+    },
+};
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Directories younger than this are left alone so an in-flight snapshot is
+/// never mistaken for an orphan.
+const MIN_ORPHAN_AGE_SECS: i64 = 3600;
+
+/// Files written by Cloud Hypervisor (`state.json`) and Firecracker
+/// (`vm.snap`) snapshots. Directories without one are not ours to remove.
+const SNAPSHOT_MARKERS: &[&str] = &["state.json", "vm.snap"];
+
+pub async fn start_snapshot_gc(env: App) {
+    let mut ticker = interval_at(Instant::now() + SWEEP_INTERVAL, SWEEP_INTERVAL);
+
+    loop {
+        ticker.tick().await;
+
+        if env.maintenance_mode() {
+            continue;
+        }
+
+        if let Err(e) = sweep(&env).await {
+            warn!("Snapshot GC: sweep failed: {:#}", e);
+        }
+    }
+}
+
+async fn sweep(env: &App) -> Result<()> {
+    let pools = storage_pools::list(env.pool(), None).await?;
+    // NFS pools are mounted under a directory named after the pool ID, which
+    // may sit inside a Local pool's path.
+    let pool_ids: HashSet<Uuid> = pools.iter().map(|p| p.id).collect();
+
+    for pool in pools.iter().filter(|p| p.status.is_usable()) {
+        if let Err(e) = sweep_pool(env, pool, &pool_ids).await {
+            warn!(pool = %pool.name, "Snapshot GC: failed to sweep pool: {:#}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Sweep the pool on every UP host attached to it: a Local pool path is a
+/// separate directory on each host. An NFS export is shared, so hosts after
+/// the first find nothing left to remove.
+async fn sweep_pool(env: &App, pool: &StoragePool, pool_ids: &HashSet<Uuid>) -> Result<()> {
+    let Some(base_path) = pool.node_base_path() else {
+        return Ok(());
+    };
+
+    for host_id in storage_pools::list_up_host_ids(env.pool(), pool.id).await? {
+        let host = hosts::require_by_id(env.pool(), host_id).await?;
+        if let Err(e) = sweep_pool_on_host(env, pool, &host, &base_path, pool_ids).await {
+            warn!(
+                pool = %pool.name,
+                host = %host.name,
+                "Snapshot GC: failed to sweep pool on host: {:#}",
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn sweep_pool_on_host(
+    env: &App,
+    pool: &StoragePool,
+    host: &Host,
+    base_path: &str,
+    pool_ids: &HashSet<Uuid>,
+) -> Result<()> {
+    let client = NodeClient::new(&host.address, host.port as u16);
+    let entries = client.list_directory(base_path).await?;
+    let now = chrono::Utc::now().timestamp();
+    let candidates = orphan_candidates(&entries, now, pool_ids);
+    if candidates.is_empty() {
+        return Ok(());
+    }
+
+    let existing: HashSet<Uuid> = storage_objects::existing_ids(env.pool(), &candidates)
+        .await?
+        .into_iter()
+        .collect();
+
+    for id in candidates.into_iter().filter(|id| !existing.contains(id)) {
+        let path = format!("{}/{}", base_path, id);
+        let children = client.list_directory(&path).await?;
+        if !is_snapshot_dir(&children) {
+            continue;
+        }
+
+        match client.delete_path(&path, true).await {
+            Ok(bytes_freed) => info!(
+                pool = %pool.name,
+                host = %host.name,
+                path = %path,
+                bytes_freed,
+                "Snapshot GC: removed orphaned snapshot directory"
+            ),
+            Err(e) => warn!(
+                pool = %pool.name,
+                host = %host.name,
+                path = %path,
+                "Snapshot GC: failed to remove orphaned directory: {:#}",
+                e
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// UUID-named directories old enough to be considered, excluding pool IDs.
+fn orphan_candidates(entries: &[DirectoryEntry], now: i64, pool_ids: &HashSet<Uuid>) -> Vec<Uuid> {
+    entries
+        .iter()
+        .filter(|e| e.is_dir && now - e.modified_unix >= MIN_ORPHAN_AGE_SECS)
+        .filter_map(|e| Uuid::parse_str(&e.name).ok())
+        .filter(|id| !pool_ids.contains(id))
+        .collect()
+}
+
+fn is_snapshot_dir(children: &[DirectoryEntry]) -> bool {
+    children
+        .iter()
+        .any(|c| !c.is_dir && SNAPSHOT_MARKERS.contains(&c.name.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, is_dir: bool, modified_unix: i64) -> DirectoryEntry {
+        DirectoryEntry {
+            name: name.to_string(),
+            is_dir,
+            size_bytes: 0,
+            modified_unix,
+        }
+    }
+
+    #[test]
+    fn orphan_candidates_skips_recent_files_and_pool_dirs() {
+        let old = Uuid::new_v4();
+        let recent = Uuid::new_v4();
+        let disk = Uuid::new_v4();
+        let nfs_pool = Uuid::new_v4();
+        let now = 10_000;
+        let entries = vec![
+            entry(&old.to_string(), true, now - MIN_ORPHAN_AGE_SECS),
+            entry(&recent.to_string(), true, now - 10),
+            entry(&disk.to_string(), false, 0),
+            entry(&nfs_pool.to_string(), true, 0),
+            entry("lost+found", true, 0),
+        ];
+
+        let candidates = orphan_candidates(&entries, now, &HashSet::from([nfs_pool]));
+
+        assert_eq!(candidates, vec![old]);
+    }
+
+    #[test]
+    fn is_snapshot_dir_requires_a_marker_file() {
+        assert!(is_snapshot_dir(&[
+            entry("config.json", false, 0),
+            entry("state.json", false, 0),
+        ]));
+        assert!(is_snapshot_dir(&[entry("vm.snap", false, 0)]));
+        assert!(!is_snapshot_dir(&[entry("disk0.raw", false, 0)]));
+        assert!(!is_snapshot_dir(&[entry("state.json", true, 0)]));
+    }
+}
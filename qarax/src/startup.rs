@@ -45,6 +45,14 @@ pub async fn run(
         a.clone(),
     ));
 
+    // Spawn background task to remove orphaned snapshot directories
+    tokio::spawn(crate::snapshot_gc::start_snapshot_gc(a.clone()));
+
+    // Spawn background task to import new digests of tracked images
+    tokio::spawn(crate::image_catalog_refresh::start_image_catalog_refresh(
+        a.clone(),
+    ));
+
     let app = app(a);
     let server = axum::serve(listener, app);
     Ok(server)
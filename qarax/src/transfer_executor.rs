@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use sqlx::PgPool;
 use tracing::debug;
 
-use crate::grpc_client::NodeClient;
+use crate::grpc_client::{ChecksumMismatch, DownloadOptions, NodeClient};
 use crate::model::{
     hosts,
     storage_pools::{self, StoragePool, StoragePoolType},
@@ -12,6 +12,8 @@ use crate::model::{
 /// Result of a successful transfer execution.
 pub struct TransferResult {
     pub bytes_written: i64,
+    /// Verified digest of the downloaded data, when the transfer asked for one.
+    pub checksum: Option<String>,
     /// Config for the resulting storage object (e.g. `{"path": "/var/lib/qarax/images/foo.qcow2"}`)
     pub storage_config: serde_json::Value,
 }
@@ -33,6 +35,9 @@ pub enum TransferError {
 
     #[error("transfer failed: {0}")]
     TransferFailed(String),
+
+    #[error("transfer failed: {message}")]
+    ChecksumMismatch { actual: String, message: String },
 }
 
 /// Abstraction for how transfers are executed, decoupled from pool type.
@@ -100,19 +105,41 @@ impl TransferExecutor for FilesystemTransferExecutor {
         let node_client = NodeClient::new(&host.address, host.port as u16);
         let transfer_id_str = transfer.id.to_string();
 
-        let bytes_written = match transfer.transfer_type {
-            TransferType::Download => node_client
-                .download_file(&transfer_id_str, &transfer.source, &destination)
-                .await
-                .map_err(|e| TransferError::TransferFailed(format!("{:#}", e)))?,
-            TransferType::LocalCopy => node_client
-                .copy_file(&transfer_id_str, &transfer.source, &destination)
-                .await
-                .map_err(|e| TransferError::TransferFailed(format!("{:#}", e)))?,
+        let (bytes_written, checksum) = match transfer.transfer_type {
+            TransferType::Download => {
+                let options = DownloadOptions {
+                    expected_checksum: transfer.expected_checksum.clone(),
+                    bandwidth_limit_bytes_per_sec: transfer.bandwidth_limit_bytes_per_sec,
+                };
+                let result = node_client
+                    .download_file(&transfer_id_str, &transfer.source, &destination, &options)
+                    .await
+                    .map_err(|e| match e.downcast::<ChecksumMismatch>() {
+                        Ok(mismatch) => TransferError::ChecksumMismatch {
+                            actual: mismatch.actual,
+                            message: mismatch.message,
+                        },
+                        Err(e) => TransferError::TransferFailed(format!("{:#}", e)),
+                    })?;
+                (result.bytes_written, result.checksum)
+            }
+            TransferType::LocalCopy => {
+                let bytes_written = node_client
+                    .copy_file(&transfer_id_str, &transfer.source, &destination)
+                    .await
+                    .map_err(|e| TransferError::TransferFailed(format!("{:#}", e)))?;
+                (bytes_written, None)
+            }
+            TransferType::Upload => {
+                return Err(TransferError::TransferFailed(
+                    "uploads are pushed by the client, not executed".to_string(),
+                ));
+            }
         };
 
         Ok(TransferResult {
             bytes_written,
+            checksum,
             storage_config: serde_json::json!({ "path": destination }),
         })
     }
@@ -122,7 +149,7 @@ impl TransferExecutor for FilesystemTransferExecutor {
 pub fn executor_for_pool(pool: &StoragePool) -> Box<dyn TransferExecutor> {
     match pool.pool_type {
         StoragePoolType::Local | StoragePoolType::Nfs => Box::new(FilesystemTransferExecutor),
-        StoragePoolType::OverlayBd | StoragePoolType::Block => {
+        StoragePoolType::OverlayBd | StoragePoolType::Block | StoragePoolType::Rbd => {
             Box::new(UnsupportedTransferExecutor)
         }
     }
@@ -0,0 +1,510 @@
+use tokio::net::TcpListener;
+
+use common::telemtry::{get_subscriber, init_subscriber};
+use once_cell::sync::Lazy;
+use qarax::{
+    configuration::{DatabaseSettings, default_control_plane_architecture, get_configuration},
+    model::networks as network_model,
+    startup::run,
+};
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use tokio::runtime::Runtime;
+use uuid::Uuid;
+
+struct TestApp {
+    pub db_name: String,
+    pub address: String,
+    pub pool: PgPool,
+}
+
+static TRACING: Lazy<()> = Lazy::new(|| {
+    let default_filter_level = "info".to_string();
+    let subscriber_name = "test".to_string();
+    if std::env::var("TEST_LOG").is_ok() {
+        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::stdout);
+        init_subscriber(subscriber);
+    } else {
+        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::sink);
+        init_subscriber(subscriber);
+    }
+});
+
+pub async fn configure_database(config: &DatabaseSettings) -> PgPool {
+    let mut connection = PgConnection::connect(&config.connection_string_without_db())
+        .await
+        .expect("Failed to connect to Postgres");
+    connection
+        .execute(format!(r#"CREATE DATABASE "{}";"#, config.name).as_str())
+        .await
+        .expect("Failed to create database.");
+    let connection_pool = PgPool::connect(&config.connection_string())
+        .await
+        .expect("Failed to connect to Postgres.");
+    sqlx::migrate!("../migrations")
+        .run(&connection_pool)
+        .await
+        .expect("Failed to migrate the database");
+    connection_pool
+}
+
+async fn spawn_app() -> TestApp {
+    Lazy::force(&TRACING);
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Failed to bind random port");
+    let port = listener.local_addr().unwrap().port();
+    let address = format!("http://127.0.0.1:{}", port);
+    let mut configuration =
+        qarax::configuration::get_configuration().expect("Failed to read configuration.");
+    configuration.database.name = Uuid::new_v4().to_string();
+    let connection_pool = configure_database(&configuration.database).await;
+
+    let server = run(
+        listener,
+        connection_pool.clone(),
+        configuration.database.clone(),
+        configuration.vm_defaults.clone(),
+        configuration.scheduling.clone(),
+        default_control_plane_architecture(),
+    )
+    .await
+    .unwrap();
+    std::thread::spawn(move || {
+        let rt = Runtime::new().unwrap();
+        let _ = rt.block_on(async move { server.await });
+    });
+    TestApp {
+        db_name: configuration.database.name,
+        address,
+        pool: connection_pool,
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let db_name = self.db_name.clone();
+        std::thread::spawn(move || {
+            let rt = Runtime::new().unwrap();
+            rt.block_on(async {
+                let config = get_configuration().expect("Failed to read configuration");
+                let mut conn = PgConnection::connect_with(&config.database.without_db())
+                    .await
+                    .expect("Failed to connect to Postgres");
+                conn.execute(&*format!("DROP DATABASE \"{}\" WITH (FORCE)", db_name))
+                    .await
+                    .expect("Failed to drop database.");
+                let _ = tx.send(());
+            })
+        });
+        let _ = rx.recv();
+    }
+}
+
+/// Create a host and set it to UP so the scheduler can assign VMs.
+async fn ensure_host_up(client: &reqwest::Client, address: &str) -> String {
+    ensure_host_up_with_name(client, address, "test-host", 50051).await
+}
+
+async fn ensure_host_up_with_name(
+    client: &reqwest::Client,
+    address: &str,
+    name: &str,
+    port: u16,
+) -> String {
+    let res = client
+        .post(format!("{}/hosts", address))
+        .json(&json!({
+            "name": name,
+            "address": "127.0.0.1",
+            "port": port,
+            "host_user": "root",
+            "password": ""
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let host_id = res.text().await.unwrap();
+
+    client
+        .patch(format!("{}/hosts/{}", address, host_id))
+        .json(&json!({"status": "up"}))
+        .send()
+        .await
+        .unwrap();
+
+    host_id
+}
+
+/// Create a VM via the API, returns the VM UUID string.
+async fn create_vm(client: &reqwest::Client, address: &str, body: serde_json::Value) -> String {
+    let res = client
+        .post(format!("{}/vms", address))
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED, "VM creation failed");
+    res.json().await.unwrap()
+}
+
+async fn create_network(
+    client: &reqwest::Client,
+    address: &str,
+    body: serde_json::Value,
+) -> String {
+    let res = client
+        .post(format!("{}/networks", address))
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED, "network creation failed");
+    res.text().await.unwrap()
+}
+
+async fn attach_network_to_host(pool: &PgPool, network_id: &str, host_id: &str, bridge_name: &str) {
+    network_model::attach_host(
+        pool,
+        Uuid::parse_str(network_id).unwrap(),
+        Uuid::parse_str(host_id).unwrap(),
+        bridge_name,
+    )
+    .await
+    .unwrap();
+}
+
+/// Create a VM with one NIC on a fresh managed network attached to the host.
+async fn create_vm_on_network(
+    app: &TestApp,
+    client: &reqwest::Client,
+    name: &str,
+    subnet: &str,
+    gateway: &str,
+) -> String {
+    let host_id = ensure_host_up(client, &app.address).await;
+    let network_id = create_network(
+        client,
+        &app.address,
+        json!({
+            "name": format!("{}-net", name),
+            "subnet": subnet,
+            "gateway": gateway
+        }),
+    )
+    .await;
+    attach_network_to_host(&app.pool, &network_id, &host_id, &format!("br-{}", name)).await;
+
+    create_vm(
+        client,
+        &app.address,
+        json!({
+            "name": name,
+            "hypervisor": "cloud_hv",
+            "boot_vcpus": 1,
+            "max_vcpus": 1,
+            "memory_size": 268435456,
+            "network_id": network_id,
+            "config": {}
+        }),
+    )
+    .await
+}
+
+async fn create_floating_ip(client: &reqwest::Client, address: &str, ip: &str) -> String {
+    let res = client
+        .post(format!("{}/floating-ips", address))
+        .json(&json!({"address": ip}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        res.status(),
+        StatusCode::CREATED,
+        "floating IP creation failed"
+    );
+    res.text().await.unwrap()
+}
+
+async fn get_floating_ip(client: &reqwest::Client, address: &str, id: &str) -> serde_json::Value {
+    let res = client
+        .get(format!("{}/floating-ips/{}", address, id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    res.json().await.unwrap()
+}
+
+async fn remove_nic(client: &reqwest::Client, address: &str, vm_id: &str) {
+    let res = client
+        .delete(format!("{}/vms/{}/nics/net0", address, vm_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_floating_ip_lifecycle() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let id = create_floating_ip(&client, &app.address, "203.0.113.10").await;
+    let floating_ip = get_floating_ip(&client, &app.address, &id).await;
+    assert_eq!(floating_ip["address"], "203.0.113.10");
+    assert!(floating_ip["vm_id"].is_null());
+
+    let res = client
+        .get(format!("{}/floating-ips", app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let list: Vec<serde_json::Value> = res.json().await.unwrap();
+    assert_eq!(list.len(), 1);
+
+    let res = client
+        .post(format!("{}/floating-ips", app.address))
+        .json(&json!({"address": "203.0.113.10"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    let res = client
+        .delete(format!("{}/floating-ips/{}", app.address, id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let res = client
+        .get(format!("{}/floating-ips/{}", app.address, id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_create_floating_ip_rejects_invalid_addresses() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    // Releasing a floating IP removes it from the uplink, so a host's own
+    // address is refused.
+    ensure_host_up(&client, &app.address).await;
+
+    for address in ["203.0.113.300", "2001:db8::10", "127.0.0.1"] {
+        let res = client
+            .post(format!("{}/floating-ips", app.address))
+            .json(&json!({"address": address}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            res.status(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "{} was accepted",
+            address
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_floating_ip_association_follows_the_nic() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let vm_id = create_vm_on_network(&app, &client, "fip-vm", "10.101.0.0/24", "10.101.0.1").await;
+    let id = create_floating_ip(&client, &app.address, "203.0.113.11").await;
+
+    // The VM is not running, so no node is involved yet.
+    let res = client
+        .post(format!("{}/floating-ips/{}/associate", app.address, id))
+        .json(&json!({"vm_id": vm_id}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let floating_ip: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(floating_ip["vm_id"], vm_id.as_str());
+    assert!(
+        floating_ip["vm_ip"]
+            .as_str()
+            .unwrap()
+            .starts_with("10.101.0.")
+    );
+
+    // One floating IP per interface.
+    let other = create_floating_ip(&client, &app.address, "203.0.113.12").await;
+    let res = client
+        .post(format!("{}/floating-ips/{}/associate", app.address, other))
+        .json(&json!({"vm_id": vm_id}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    let res = client
+        .post(format!("{}/floating-ips/{}/disassociate", app.address, id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let floating_ip: serde_json::Value = res.json().await.unwrap();
+    assert!(floating_ip["vm_id"].is_null());
+    assert!(floating_ip["network_interface_id"].is_null());
+
+    // Removing the NIC releases the address but keeps the floating IP.
+    let res = client
+        .post(format!("{}/floating-ips/{}/associate", app.address, other))
+        .json(&json!({"vm_id": vm_id}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    remove_nic(&client, &app.address, &vm_id).await;
+    let floating_ip = get_floating_ip(&client, &app.address, &other).await;
+    assert!(floating_ip["network_interface_id"].is_null());
+    assert_eq!(floating_ip["address"], "203.0.113.12");
+}
+
+#[tokio::test]
+async fn test_floating_ip_needs_a_managed_interface() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    ensure_host_up(&client, &app.address).await;
+    let vm_id = create_vm(
+        &client,
+        &app.address,
+        json!({
+            "name": "fip-no-nic",
+            "hypervisor": "cloud_hv",
+            "boot_vcpus": 1,
+            "max_vcpus": 1,
+            "memory_size": 268435456,
+            "config": {}
+        }),
+    )
+    .await;
+    let id = create_floating_ip(&client, &app.address, "203.0.113.13").await;
+
+    let res = client
+        .post(format!("{}/floating-ips/{}/associate", app.address, id))
+        .json(&json!({"vm_id": vm_id}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(get_floating_ip(&client, &app.address, &id).await["vm_id"].is_null());
+}
+
+#[tokio::test]
+async fn test_port_forward_lifecycle() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let vm_id = create_vm_on_network(&app, &client, "pf-vm", "10.102.0.0/24", "10.102.0.1").await;
+
+    let res = client
+        .post(format!("{}/port-forwards", app.address))
+        .json(&json!({
+            "vm_id": vm_id,
+            "protocol": "tcp",
+            "host_port": 8080,
+            "vm_port": 80
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let forward_id = res.text().await.unwrap();
+
+    let res = client
+        .get(format!("{}/port-forwards/{}", app.address, forward_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let forward: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(forward["vm_id"], vm_id.as_str());
+    assert_eq!(forward["protocol"], "tcp");
+    assert_eq!(forward["host_port"], 8080);
+    assert_eq!(forward["vm_port"], 80);
+
+    // Host ports are unique per protocol across the cluster.
+    for (protocol, expected) in [("tcp", StatusCode::CONFLICT), ("udp", StatusCode::CREATED)] {
+        let res = client
+            .post(format!("{}/port-forwards", app.address))
+            .json(&json!({
+                "vm_id": vm_id,
+                "protocol": protocol,
+                "host_port": 8080
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), expected, "{}", protocol);
+    }
+
+    let res = client
+        .get(format!("{}/port-forwards?vm_id={}", app.address, vm_id))
+        .send()
+        .await
+        .unwrap();
+    let forwards: Vec<serde_json::Value> = res.json().await.unwrap();
+    assert_eq!(forwards.len(), 2);
+
+    let res = client
+        .delete(format!("{}/port-forwards/{}", app.address, forward_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    // The remaining forward goes away with the NIC.
+    remove_nic(&client, &app.address, &vm_id).await;
+    let res = client
+        .get(format!("{}/port-forwards", app.address))
+        .send()
+        .await
+        .unwrap();
+    let forwards: Vec<serde_json::Value> = res.json().await.unwrap();
+    assert!(forwards.is_empty());
+}
+
+#[tokio::test]
+async fn test_port_forward_rejects_invalid_ports() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let vm_id = create_vm_on_network(&app, &client, "pf-bad", "10.103.0.0/24", "10.103.0.1").await;
+
+    for (host_port, vm_port) in [(0, 80), (8080, 70000)] {
+        let res = client
+            .post(format!("{}/port-forwards", app.address))
+            .json(&json!({
+                "vm_id": vm_id,
+                "protocol": "tcp",
+                "host_port": host_port,
+                "vm_port": vm_port
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let res = client
+        .post(format!("{}/port-forwards", app.address))
+        .json(&json!({
+            "vm_id": Uuid::new_v4(),
+            "protocol": "tcp",
+            "host_port": 8081
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,835 @@
+use tokio::net::TcpListener;
+
+use common::telemtry::{get_subscriber, init_subscriber};
+use once_cell::sync::Lazy;
+use qarax::{
+    configuration::{DatabaseSettings, default_control_plane_architecture, get_configuration},
+    model::networks as network_model,
+    startup::run,
+};
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use tokio::runtime::Runtime;
+use uuid::Uuid;
+
+struct TestApp {
+    pub db_name: String,
+    pub address: String,
+    pub pool: PgPool,
+}
+
+static TRACING: Lazy<()> = Lazy::new(|| {
+    let default_filter_level = "info".to_string();
+    let subscriber_name = "test".to_string();
+    if std::env::var("TEST_LOG").is_ok() {
+        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::stdout);
+        init_subscriber(subscriber);
+    } else {
+        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::sink);
+        init_subscriber(subscriber);
+    }
+});
+
+pub async fn configure_database(config: &DatabaseSettings) -> PgPool {
+    let mut connection = PgConnection::connect(&config.connection_string_without_db())
+        .await
+        .expect("Failed to connect to Postgres");
+    connection
+        .execute(format!(r#"CREATE DATABASE "{}";"#, config.name).as_str())
+        .await
+        .expect("Failed to create database.");
+    let connection_pool = PgPool::connect(&config.connection_string())
+        .await
+        .expect("Failed to connect to Postgres.");
+    sqlx::migrate!("../migrations")
+        .run(&connection_pool)
+        .await
+        .expect("Failed to migrate the database");
+    connection_pool
+}
+
+async fn spawn_app() -> TestApp {
+    Lazy::force(&TRACING);
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Failed to bind random port");
+    let port = listener.local_addr().unwrap().port();
+    let address = format!("http://127.0.0.1:{}", port);
+    let mut configuration =
+        qarax::configuration::get_configuration().expect("Failed to read configuration.");
+    configuration.database.name = Uuid::new_v4().to_string();
+    let connection_pool = configure_database(&configuration.database).await;
+
+    let server = run(
+        listener,
+        connection_pool.clone(),
+        configuration.database.clone(),
+        configuration.vm_defaults.clone(),
+        configuration.scheduling.clone(),
+        default_control_plane_architecture(),
+    )
+    .await
+    .unwrap();
+    std::thread::spawn(move || {
+        let rt = Runtime::new().unwrap();
+        let _ = rt.block_on(async move { server.await });
+    });
+    TestApp {
+        db_name: configuration.database.name,
+        address,
+        pool: connection_pool,
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let db_name = self.db_name.clone();
+        std::thread::spawn(move || {
+            let rt = Runtime::new().unwrap();
+            rt.block_on(async {
+                let config = get_configuration().expect("Failed to read configuration");
+                let mut conn = PgConnection::connect_with(&config.database.without_db())
+                    .await
+                    .expect("Failed to connect to Postgres");
+                conn.execute(&*format!("DROP DATABASE \"{}\" WITH (FORCE)", db_name))
+                    .await
+                    .expect("Failed to drop database.");
+                let _ = tx.send(());
+            })
+        });
+        let _ = rx.recv();
+    }
+}
+
+/// Create a host and set it to UP so the scheduler can assign VMs.
+async fn ensure_host_up(client: &reqwest::Client, address: &str) -> String {
+    ensure_host_up_with_name(client, address, "test-host", 50051).await
+}
+
+async fn ensure_host_up_with_name(
+    client: &reqwest::Client,
+    address: &str,
+    name: &str,
+    port: u16,
+) -> String {
+    let res = client
+        .post(format!("{}/hosts", address))
+        .json(&json!({
+            "name": name,
+            "address": "127.0.0.1",
+            "port": port,
+            "host_user": "root",
+            "password": ""
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let host_id = res.text().await.unwrap();
+
+    client
+        .patch(format!("{}/hosts/{}", address, host_id))
+        .json(&json!({"status": "up"}))
+        .send()
+        .await
+        .unwrap();
+
+    host_id
+}
+
+/// Create a VM via the API, returns the VM UUID string.
+async fn create_vm(client: &reqwest::Client, address: &str, body: serde_json::Value) -> String {
+    let res = client
+        .post(format!("{}/vms", address))
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED, "VM creation failed");
+    res.json().await.unwrap()
+}
+
+async fn create_network(
+    client: &reqwest::Client,
+    address: &str,
+    body: serde_json::Value,
+) -> String {
+    let res = client
+        .post(format!("{}/networks", address))
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED, "network creation failed");
+    res.text().await.unwrap()
+}
+
+async fn attach_network_to_host(pool: &PgPool, network_id: &str, host_id: &str, bridge_name: &str) {
+    network_model::attach_host(
+        pool,
+        Uuid::parse_str(network_id).unwrap(),
+        Uuid::parse_str(host_id).unwrap(),
+        bridge_name,
+    )
+    .await
+    .unwrap();
+}
+
+async fn count_host_attachments(pool: &PgPool, network_id: &str) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM host_networks WHERE network_id = $1")
+        .bind(Uuid::parse_str(network_id).unwrap())
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+async fn nic_mac(pool: &PgPool, vm_id: &str) -> String {
+    sqlx::query_scalar("SELECT mac_address::text FROM network_interfaces WHERE vm_id = $1")
+        .bind(Uuid::parse_str(vm_id).unwrap())
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+/// Allocated addresses of a network, without their prefix length.
+async fn list_ips(client: &reqwest::Client, address: &str, network_id: &str) -> Vec<String> {
+    let res = client
+        .get(format!("{}/networks/{}/ips", address, network_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let allocations: Vec<serde_json::Value> = res.json().await.unwrap();
+    allocations
+        .iter()
+        .map(|a| {
+            let ip = a["ip_address"].as_str().unwrap();
+            ip.split('/').next().unwrap().to_string()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_create_dual_stack_network_round_trips() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let network_id = create_network(
+        &client,
+        &app.address,
+        json!({
+            "name": "dual-stack-net",
+            "subnet": "10.93.0.0/24",
+            "gateway": "10.93.0.1",
+            "subnet6": "fd00:93::/64",
+            "gateway6": "fd00:93::1"
+        }),
+    )
+    .await;
+
+    let res = client
+        .get(format!("{}/networks/{}", app.address, network_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let network: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(network["subnet6"], "fd00:93::/64");
+    assert_eq!(network["gateway6"], "fd00:93::1");
+}
+
+#[tokio::test]
+async fn test_create_network_rejects_invalid_ipv6() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let cases = [
+        (
+            json!({"gateway6": "fd00:94::1"}),
+            "gateway6 requires subnet6",
+        ),
+        (json!({"subnet6": "fd00:94::/48"}), "must be a /64"),
+        (json!({"subnet6": "fd00:94::1/64"}), "host bits set"),
+        (
+            json!({"subnet6": "fd00:94::/64", "gateway6": "fd00:95::1"}),
+            "is outside",
+        ),
+    ];
+
+    for (i, (ipv6, expected)) in cases.into_iter().enumerate() {
+        let mut body = json!({
+            "name": format!("bad-ipv6-{}", i),
+            "subnet": "10.94.0.0/24",
+            "gateway": "10.94.0.1"
+        });
+        body.as_object_mut()
+            .unwrap()
+            .extend(ipv6.as_object().unwrap().clone());
+
+        let res = client
+            .post(format!("{}/networks", app.address))
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+        let text = res.text().await.unwrap();
+        assert!(text.contains(expected), "{}: {}", body, text);
+    }
+}
+
+#[tokio::test]
+async fn test_vm_on_dual_stack_network_gets_eui64_address() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let host_id = ensure_host_up(&client, &app.address).await;
+
+    let network_id = create_network(
+        &client,
+        &app.address,
+        json!({
+            "name": "dual-stack-vm-net",
+            "subnet": "10.95.0.0/24",
+            "gateway": "10.95.0.1",
+            "subnet6": "fd00:95::/64"
+        }),
+    )
+    .await;
+    attach_network_to_host(&app.pool, &network_id, &host_id, "testbr95").await;
+
+    let vm_id = create_vm(
+        &client,
+        &app.address,
+        json!({
+            "name": "vm-dual-stack",
+            "hypervisor": "cloud_hv",
+            "boot_vcpus": 1,
+            "max_vcpus": 1,
+            "memory_size": 268435456,
+            "network_id": network_id,
+            "config": {}
+        }),
+    )
+    .await;
+
+    let mac = nic_mac(&app.pool, &vm_id).await;
+    let expected = network_model::eui64_address("fd00:95::/64", &mac)
+        .unwrap()
+        .to_string();
+    let ips = list_ips(&client, &app.address, &network_id).await;
+    assert_eq!(
+        ips.len(),
+        2,
+        "expected an IPv4 and an IPv6 lease: {:?}",
+        ips
+    );
+    assert!(ips.contains(&expected), "{} not in {:?}", expected, ips);
+    assert!(ips.iter().any(|ip| ip.starts_with("10.95.0.")));
+
+    let res = client
+        .delete(format!("{}/vms/{}/nics/net0", app.address, vm_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert!(
+        list_ips(&client, &app.address, &network_id)
+            .await
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_stretched_network_refuses_a_parent_interface() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let host_id = ensure_host_up(&client, &app.address).await;
+
+    let network_id = create_network(
+        &client,
+        &app.address,
+        json!({
+            "name": "stretched-parent-net",
+            "subnet": "10.96.0.0/24",
+            "gateway": "10.96.0.1",
+            "type": "stretched"
+        }),
+    )
+    .await;
+
+    let res = client
+        .post(format!("{}/networks/{}/hosts", app.address, network_id))
+        .json(&json!({
+            "host_id": host_id,
+            "bridge_name": "testbr96",
+            "parent_interface": "eth0"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(count_host_attachments(&app.pool, &network_id).await, 0);
+}
+
+#[tokio::test]
+async fn test_stretched_network_nic_gets_a_fixed_mac_and_is_removable() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let host_id = ensure_host_up(&client, &app.address).await;
+
+    let network_id = create_network(
+        &client,
+        &app.address,
+        json!({
+            "name": "stretched-nic-net",
+            "subnet": "10.97.0.0/24",
+            "gateway": "10.97.0.1",
+            "type": "stretched"
+        }),
+    )
+    .await;
+    attach_network_to_host(&app.pool, &network_id, &host_id, "testbr97").await;
+
+    let vm_id = create_vm(
+        &client,
+        &app.address,
+        json!({
+            "name": "vm-stretched",
+            "hypervisor": "cloud_hv",
+            "boot_vcpus": 1,
+            "max_vcpus": 1,
+            "memory_size": 268435456,
+            "network_id": network_id,
+            "config": {}
+        }),
+    )
+    .await;
+
+    // Remote hosts learn where the NIC lives from its MAC, so it is chosen
+    // when the NIC is created rather than by the hypervisor.
+    let mac = nic_mac(&app.pool, &vm_id).await;
+    assert_eq!(mac.split(':').count(), 6, "unexpected MAC {}", mac);
+    let ips = list_ips(&client, &app.address, &network_id).await;
+    assert_eq!(ips.len(), 1);
+    assert!(ips[0].starts_with("10.97.0."));
+
+    // The host cannot be reached to drop the MAC from its peers, which must
+    // not keep the NIC or its address around.
+    let res = client
+        .delete(format!("{}/vms/{}/nics/net0", app.address, vm_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert!(
+        list_ips(&client, &app.address, &network_id)
+            .await
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_create_vlan_network_round_trips_its_tag() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let network_id = create_network(
+        &client,
+        &app.address,
+        json!({
+            "name": "vlan-net",
+            "subnet": "10.98.0.0/24",
+            "gateway": "10.98.0.1",
+            "type": "vlan",
+            "vlan_id": 100
+        }),
+    )
+    .await;
+
+    let res = client
+        .get(format!("{}/networks/{}", app.address, network_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let network: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(network["type"], "vlan");
+    assert_eq!(network["vlan_id"], 100);
+}
+
+#[tokio::test]
+async fn test_create_network_rejects_invalid_vlan_tags() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let cases = [
+        (json!({"type": "vlan"}), "require vlan_id"),
+        (json!({"type": "vlan", "vlan_id": 0}), "outside 1-4094"),
+        (json!({"type": "vlan", "vlan_id": 4095}), "outside 1-4094"),
+        (
+            json!({"type": "isolated", "vlan_id": 100}),
+            "only valid for vlan networks",
+        ),
+        (json!({"vlan_id": 100}), "only valid for vlan networks"),
+    ];
+
+    for (i, (vlan, expected)) in cases.into_iter().enumerate() {
+        let mut body = json!({
+            "name": format!("bad-vlan-{}", i),
+            "subnet": "10.99.0.0/24",
+            "gateway": "10.99.0.1"
+        });
+        body.as_object_mut()
+            .unwrap()
+            .extend(vlan.as_object().unwrap().clone());
+
+        let res = client
+            .post(format!("{}/networks", app.address))
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+        let text = res.text().await.unwrap();
+        assert!(text.contains(expected), "{}: {}", body, text);
+    }
+}
+
+#[tokio::test]
+async fn test_vlan_network_attach_requires_a_parent_interface() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let host_id = ensure_host_up(&client, &app.address).await;
+
+    let network_id = create_network(
+        &client,
+        &app.address,
+        json!({
+            "name": "vlan-attach-net",
+            "subnet": "10.100.0.0/24",
+            "gateway": "10.100.0.1",
+            "type": "vlan",
+            "vlan_id": 200
+        }),
+    )
+    .await;
+
+    let res = client
+        .post(format!("{}/networks/{}/hosts", app.address, network_id))
+        .json(&json!({
+            "host_id": host_id,
+            "bridge_name": "testbr100"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let text = res.text().await.unwrap();
+    assert!(text.contains("parent_interface"), "{}", text);
+    assert_eq!(count_host_attachments(&app.pool, &network_id).await, 0);
+}
+
+#[tokio::test]
+async fn test_dns_records_follow_the_network_members() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let host_id = ensure_host_up(&client, &app.address).await;
+
+    let network_id = create_network(
+        &client,
+        &app.address,
+        json!({
+            "name": "dns-net",
+            "subnet": "10.104.0.0/24",
+            "gateway": "10.104.0.1",
+            "subnet6": "fd00:104::/64"
+        }),
+    )
+    .await;
+    attach_network_to_host(&app.pool, &network_id, &host_id, "testbr104").await;
+
+    let mut vm_ids = Vec::new();
+    for name in ["web-1", "db-1"] {
+        vm_ids.push(
+            create_vm(
+                &client,
+                &app.address,
+                json!({
+                    "name": name,
+                    "hypervisor": "cloud_hv",
+                    "boot_vcpus": 1,
+                    "max_vcpus": 1,
+                    "memory_size": 268435456,
+                    "network_id": network_id,
+                    "config": {}
+                }),
+            )
+            .await,
+        );
+    }
+
+    let network_uuid = Uuid::parse_str(&network_id).unwrap();
+    let records = network_model::dns_records(&app.pool, network_uuid)
+        .await
+        .unwrap();
+    let names: Vec<&str> = records.iter().map(|(name, _, _)| name.as_str()).collect();
+    assert_eq!(names, ["db-1", "web-1"]);
+    for (name, ip, ip6) in &records {
+        let ip = ip.as_deref().unwrap_or_default();
+        assert!(ip.starts_with("10.104.0."), "{} has {:?}", name, ip);
+        let ip6 = ip6.as_deref().unwrap_or_default();
+        assert!(ip6.starts_with("fd00:104::"), "{} has {:?}", name, ip6);
+    }
+
+    // The resolver on the unreachable host misses the update, which must
+    // not block the delete.
+    let res = client
+        .delete(format!("{}/vms/{}", app.address, vm_ids[1]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let records = network_model::dns_records(&app.pool, network_uuid)
+        .await
+        .unwrap();
+    let names: Vec<&str> = records.iter().map(|(name, _, _)| name.as_str()).collect();
+    assert_eq!(names, ["web-1"]);
+}
+
+#[tokio::test]
+async fn test_create_network_round_trips_dhcp_options() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let network_id = create_network(
+        &client,
+        &app.address,
+        json!({
+            "name": "dhcp-opts-net",
+            "subnet": "10.105.0.0/24",
+            "gateway": "10.105.0.1",
+            "dhcp_options": {
+                "domain_name": "corp.example",
+                "ntp_servers": ["10.105.0.10"],
+                "mtu": 1450,
+                "static_routes": [
+                    {"destination": "10.50.0.0/16", "gateway": "10.105.0.254"}
+                ]
+            }
+        }),
+    )
+    .await;
+
+    let res = client
+        .get(format!("{}/networks/{}", app.address, network_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let network: serde_json::Value = res.json().await.unwrap();
+    let options = &network["dhcp_options"];
+    assert_eq!(options["domain_name"], "corp.example");
+    assert_eq!(options["ntp_servers"], json!(["10.105.0.10"]));
+    assert_eq!(options["mtu"], 1450);
+    assert_eq!(
+        options["static_routes"],
+        json!([{"destination": "10.50.0.0/16", "gateway": "10.105.0.254"}])
+    );
+}
+
+#[tokio::test]
+async fn test_create_network_rejects_invalid_dhcp_options() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let cases = [
+        (
+            json!({"domain_name": "-bad.example"}),
+            "invalid domain_name",
+        ),
+        (json!({"ntp_servers": ["fd00::1"]}), "invalid NTP server"),
+        (json!({"mtu": 100}), "outside 576-9216"),
+        (
+            json!({"static_routes": [{"destination": "10.50.0.0", "gateway": "10.106.0.254"}]}),
+            "invalid route destination",
+        ),
+        (
+            json!({"static_routes": [{"destination": "10.50.0.0/16", "gateway": "10.7.0.1"}]}),
+            "outside 10.106.0.0/24",
+        ),
+    ];
+
+    for (i, (options, expected)) in cases.into_iter().enumerate() {
+        let body = json!({
+            "name": format!("bad-dhcp-{}", i),
+            "subnet": "10.106.0.0/24",
+            "gateway": "10.106.0.1",
+            "dhcp_options": options
+        });
+
+        let res = client
+            .post(format!("{}/networks", app.address))
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+        let text = res.text().await.unwrap();
+        assert!(text.contains(expected), "{}: {}", body, text);
+    }
+}
+
+#[tokio::test]
+async fn test_dhcp_reservations_cover_the_network_nics() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let host_id = ensure_host_up(&client, &app.address).await;
+
+    let network_id = create_network(
+        &client,
+        &app.address,
+        json!({
+            "name": "dhcp-res-net",
+            "subnet": "10.107.0.0/24",
+            "gateway": "10.107.0.1"
+        }),
+    )
+    .await;
+    attach_network_to_host(&app.pool, &network_id, &host_id, "testbr107").await;
+
+    let vm_id = create_vm(
+        &client,
+        &app.address,
+        json!({
+            "name": "reserved-vm",
+            "hypervisor": "cloud_hv",
+            "boot_vcpus": 1,
+            "max_vcpus": 1,
+            "memory_size": 268435456,
+            "network_id": network_id,
+            "config": {}
+        }),
+    )
+    .await;
+
+    let network_uuid = Uuid::parse_str(&network_id).unwrap();
+    let reservations = network_model::dhcp_reservations(&app.pool, network_uuid)
+        .await
+        .unwrap();
+    assert_eq!(
+        reservations,
+        [(
+            nic_mac(&app.pool, &vm_id).await,
+            list_ips(&client, &app.address, &network_id).await[0].clone(),
+            "reserved-vm".to_string(),
+        )]
+    );
+
+    let res = client
+        .delete(format!("{}/vms/{}", app.address, vm_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let reservations = network_model::dhcp_reservations(&app.pool, network_uuid)
+        .await
+        .unwrap();
+    assert!(reservations.is_empty(), "{:?}", reservations);
+}
+
+#[tokio::test]
+async fn test_allowed_address_pairs_are_normalized_and_validated() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let host_id = ensure_host_up(&client, &app.address).await;
+
+    let network_id = create_network(
+        &client,
+        &app.address,
+        json!({
+            "name": "vrrp-net",
+            "subnet": "10.113.0.0/24",
+            "gateway": "10.113.0.1"
+        }),
+    )
+    .await;
+    attach_network_to_host(&app.pool, &network_id, &host_id, "testbr113").await;
+    let vm_id = create_vm(
+        &client,
+        &app.address,
+        json!({
+            "name": "keepalived-1",
+            "hypervisor": "cloud_hv",
+            "boot_vcpus": 1,
+            "max_vcpus": 1,
+            "memory_size": 268435456,
+            "network_id": network_id,
+            "config": {}
+        }),
+    )
+    .await;
+
+    let pairs_url = format!(
+        "{}/vms/{}/nics/net0/allowed-address-pairs",
+        app.address, vm_id
+    );
+    let res = client.get(&pairs_url).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let pairs: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(pairs, json!([]));
+
+    let res = client
+        .put(&pairs_url)
+        .json(&json!([
+            {"ip_address": "10.113.0.200"},
+            {"ip_address": "FD00:113::/64", "mac_address": "02:AA:BB:CC:DD:EE"}
+        ]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let expected = json!([
+        {"ip_address": "10.113.0.200/32"},
+        {"ip_address": "fd00:113::/64", "mac_address": "02:aa:bb:cc:dd:ee"}
+    ]);
+    let pairs: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(pairs, expected);
+    let res = client.get(&pairs_url).send().await.unwrap();
+    let pairs: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(pairs, expected);
+
+    let too_many: Vec<_> = (0..11)
+        .map(|i| json!({"ip_address": format!("10.113.0.{}", 200 + i)}))
+        .collect();
+    let cases = [
+        (json!([{"ip_address": "10.113.0.5/24"}]), "host bits set"),
+        (json!([{"ip_address": "vip"}]), "invalid address"),
+        (
+            json!([{"ip_address": "10.113.0.9", "mac_address": "02:00"}]),
+            "invalid MAC address",
+        ),
+        (json!(too_many), "at most 10"),
+    ];
+    for (body, message) in cases {
+        let res = client.put(&pairs_url).json(&body).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+        let text = res.text().await.unwrap();
+        assert!(text.contains(message), "{}: {}", body, text);
+    }
+    // A rejected update leaves the stored pairs alone.
+    let res = client.get(&pairs_url).send().await.unwrap();
+    let pairs: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(pairs, expected);
+
+    let res = client
+        .get(format!(
+            "{}/vms/{}/nics/net9/allowed-address-pairs",
+            app.address, vm_id
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
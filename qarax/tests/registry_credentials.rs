@@ -0,0 +1,214 @@
+use tokio::net::TcpListener;
+
+use common::telemtry::{get_subscriber, init_subscriber};
+use once_cell::sync::Lazy;
+use qarax::{
+    configuration::{DatabaseSettings, default_control_plane_architecture, get_configuration},
+    startup::run,
+};
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use tokio::runtime::Runtime;
+use uuid::Uuid;
+
+const TEST_ENCRYPTION_KEY: &str =
+    "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+struct TestApp {
+    db_name: String,
+    address: String,
+    pool: PgPool,
+}
+
+static TRACING: Lazy<()> = Lazy::new(|| {
+    let default_filter_level = "info".to_string();
+    let subscriber_name = "test".to_string();
+    if std::env::var("TEST_LOG").is_ok() {
+        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::stdout);
+        init_subscriber(subscriber);
+    } else {
+        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::sink);
+        init_subscriber(subscriber);
+    }
+});
+
+async fn configure_database(config: &DatabaseSettings) -> PgPool {
+    let mut connection = PgConnection::connect(&config.connection_string_without_db())
+        .await
+        .expect("Failed to connect to Postgres");
+    connection
+        .execute(format!(r#"CREATE DATABASE "{}";"#, config.name).as_str())
+        .await
+        .expect("Failed to create database.");
+
+    let connection_pool = PgPool::connect(&config.connection_string())
+        .await
+        .expect("Failed to connect to Postgres.");
+    sqlx::migrate!("../migrations")
+        .run(&connection_pool)
+        .await
+        .expect("Failed to migrate the database");
+    connection_pool
+}
+
+async fn spawn_app() -> TestApp {
+    Lazy::force(&TRACING);
+    qarax::secrets::init(TEST_ENCRYPTION_KEY).expect("valid test key");
+
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Failed to bind random port");
+    let port = listener.local_addr().unwrap().port();
+    let address = format!("http://127.0.0.1:{port}");
+
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.database.name = Uuid::new_v4().to_string();
+    let connection_pool = configure_database(&configuration.database).await;
+
+    let server = run(
+        listener,
+        connection_pool.clone(),
+        configuration.database.clone(),
+        configuration.vm_defaults.clone(),
+        configuration.scheduling.clone(),
+        default_control_plane_architecture(),
+    )
+    .await
+    .unwrap();
+
+    std::thread::spawn(move || {
+        let rt = Runtime::new().unwrap();
+        let _ = rt.block_on(async move { server.await });
+    });
+
+    TestApp {
+        db_name: configuration.database.name,
+        address,
+        pool: connection_pool,
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let db_name = self.db_name.clone();
+
+        std::thread::spawn(move || {
+            let rt = Runtime::new().unwrap();
+            rt.block_on(async {
+                let config = get_configuration().expect("Failed to read configuration");
+                let mut conn = PgConnection::connect_with(&config.database.without_db())
+                    .await
+                    .expect("Failed to connect to Postgres");
+
+                conn.execute(&*format!("DROP DATABASE \"{}\" WITH (FORCE)", db_name))
+                    .await
+                    .expect("Failed to drop database.");
+
+                let _ = tx.send(());
+            })
+        });
+
+        let _ = rx.recv();
+    }
+}
+
+#[tokio::test]
+async fn registry_credentials_are_encrypted_and_never_returned() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/registry-credentials", app.address))
+        .json(&json!({
+            "name": "ghcr",
+            "registry": "https://GHCR.io/",
+            "username": "ci-bot",
+            "password": "ghp_supersecret",
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let id: Uuid = response.text().await.unwrap().parse().unwrap();
+
+    let credential: serde_json::Value = client
+        .get(format!("{}/registry-credentials/{id}", app.address))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(credential["registry"], "ghcr.io");
+    assert_eq!(credential["username"], "ci-bot");
+    assert!(credential.get("password").is_none());
+    assert!(!credential.to_string().contains("ghp_supersecret"));
+
+    let (stored,): (Vec<u8>,) =
+        sqlx::query_as("SELECT password_encrypted FROM registry_credentials WHERE id = $1")
+            .bind(id)
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+    assert!(!stored.windows(15).any(|w| w == b"ghp_supersecret"));
+
+    let logins = qarax::model::registry_credentials::logins_for_image(
+        &app.pool,
+        "ghcr.io/org/app:1.0",
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(logins.len(), 1);
+    assert_eq!(logins[0].username, "ci-bot");
+
+    let (audit_rows,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM audit_logs WHERE resource_id = $1 AND metadata::text LIKE '%ghp_supersecret%'",
+    )
+    .bind(id)
+    .fetch_one(&app.pool)
+    .await
+    .unwrap();
+    assert_eq!(audit_rows, 0);
+}
+
+#[tokio::test]
+async fn registry_credentials_reject_duplicates_and_bad_registries() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let create = |name: &str, registry: &str| {
+        client
+            .post(format!("{}/registry-credentials", app.address))
+            .json(&json!({
+                "name": name,
+                "registry": registry,
+                "username": "user",
+                "password": "pass",
+            }))
+            .send()
+    };
+
+    assert_eq!(
+        create("one", "registry.example.com")
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::CREATED
+    );
+    assert_eq!(
+        create("two", "registry.example.com")
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::CONFLICT
+    );
+    assert_eq!(
+        create("three", "registry.example.com/org")
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+}
@@ -0,0 +1,561 @@
+use tokio::net::TcpListener;
+
+use common::telemtry::{get_subscriber, init_subscriber};
+use once_cell::sync::Lazy;
+use qarax::{
+    configuration::{DatabaseSettings, default_control_plane_architecture, get_configuration},
+    model::{networks as network_model, security_groups as security_group_model},
+    startup::run,
+};
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use tokio::runtime::Runtime;
+use uuid::Uuid;
+
+struct TestApp {
+    pub db_name: String,
+    pub address: String,
+    pub pool: PgPool,
+}
+
+static TRACING: Lazy<()> = Lazy::new(|| {
+    let default_filter_level = "info".to_string();
+    let subscriber_name = "test".to_string();
+    if std::env::var("TEST_LOG").is_ok() {
+        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::stdout);
+        init_subscriber(subscriber);
+    } else {
+        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::sink);
+        init_subscriber(subscriber);
+    }
+});
+
+pub async fn configure_database(config: &DatabaseSettings) -> PgPool {
+    let mut connection = PgConnection::connect(&config.connection_string_without_db())
+        .await
+        .expect("Failed to connect to Postgres");
+    connection
+        .execute(format!(r#"CREATE DATABASE "{}";"#, config.name).as_str())
+        .await
+        .expect("Failed to create database.");
+    let connection_pool = PgPool::connect(&config.connection_string())
+        .await
+        .expect("Failed to connect to Postgres.");
+    sqlx::migrate!("../migrations")
+        .run(&connection_pool)
+        .await
+        .expect("Failed to migrate the database");
+    connection_pool
+}
+
+async fn spawn_app() -> TestApp {
+    Lazy::force(&TRACING);
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Failed to bind random port");
+    let port = listener.local_addr().unwrap().port();
+    let address = format!("http://127.0.0.1:{}", port);
+    let mut configuration =
+        qarax::configuration::get_configuration().expect("Failed to read configuration.");
+    configuration.database.name = Uuid::new_v4().to_string();
+    let connection_pool = configure_database(&configuration.database).await;
+
+    let server = run(
+        listener,
+        connection_pool.clone(),
+        configuration.database.clone(),
+        configuration.vm_defaults.clone(),
+        configuration.scheduling.clone(),
+        default_control_plane_architecture(),
+    )
+    .await
+    .unwrap();
+    std::thread::spawn(move || {
+        let rt = Runtime::new().unwrap();
+        let _ = rt.block_on(async move { server.await });
+    });
+    TestApp {
+        db_name: configuration.database.name,
+        address,
+        pool: connection_pool,
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let db_name = self.db_name.clone();
+        std::thread::spawn(move || {
+            let rt = Runtime::new().unwrap();
+            rt.block_on(async {
+                let config = get_configuration().expect("Failed to read configuration");
+                let mut conn = PgConnection::connect_with(&config.database.without_db())
+                    .await
+                    .expect("Failed to connect to Postgres");
+                conn.execute(&*format!("DROP DATABASE \"{}\" WITH (FORCE)", db_name))
+                    .await
+                    .expect("Failed to drop database.");
+                let _ = tx.send(());
+            })
+        });
+        let _ = rx.recv();
+    }
+}
+
+/// Create a host and set it to UP so the scheduler can assign VMs.
+async fn ensure_host_up(client: &reqwest::Client, address: &str) -> String {
+    ensure_host_up_with_name(client, address, "test-host", 50051).await
+}
+
+async fn ensure_host_up_with_name(
+    client: &reqwest::Client,
+    address: &str,
+    name: &str,
+    port: u16,
+) -> String {
+    let res = client
+        .post(format!("{}/hosts", address))
+        .json(&json!({
+            "name": name,
+            "address": "127.0.0.1",
+            "port": port,
+            "host_user": "root",
+            "password": ""
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let host_id = res.text().await.unwrap();
+
+    client
+        .patch(format!("{}/hosts/{}", address, host_id))
+        .json(&json!({"status": "up"}))
+        .send()
+        .await
+        .unwrap();
+
+    host_id
+}
+
+/// Create a VM via the API, returns the VM UUID string.
+async fn create_vm(client: &reqwest::Client, address: &str, body: serde_json::Value) -> String {
+    let res = client
+        .post(format!("{}/vms", address))
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED, "VM creation failed");
+    res.json().await.unwrap()
+}
+
+async fn create_network(
+    client: &reqwest::Client,
+    address: &str,
+    body: serde_json::Value,
+) -> String {
+    let res = client
+        .post(format!("{}/networks", address))
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED, "network creation failed");
+    res.text().await.unwrap()
+}
+
+async fn attach_network_to_host(pool: &PgPool, network_id: &str, host_id: &str, bridge_name: &str) {
+    network_model::attach_host(
+        pool,
+        Uuid::parse_str(network_id).unwrap(),
+        Uuid::parse_str(host_id).unwrap(),
+        bridge_name,
+    )
+    .await
+    .unwrap();
+}
+
+/// Create a VM with one NIC on the given managed network.
+async fn create_vm_on_network(
+    client: &reqwest::Client,
+    address: &str,
+    name: &str,
+    network_id: &str,
+) -> String {
+    create_vm(
+        client,
+        address,
+        json!({
+            "name": name,
+            "hypervisor": "cloud_hv",
+            "boot_vcpus": 1,
+            "max_vcpus": 1,
+            "memory_size": 268435456,
+            "network_id": network_id,
+            "config": {}
+        }),
+    )
+    .await
+}
+
+async fn create_security_group(client: &reqwest::Client, address: &str, name: &str) -> String {
+    let res = client
+        .post(format!("{}/security-groups", address))
+        .json(&json!({"name": name}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        res.status(),
+        StatusCode::CREATED,
+        "security group creation failed"
+    );
+    res.text().await.unwrap()
+}
+
+async fn create_rule(
+    client: &reqwest::Client,
+    address: &str,
+    security_group_id: &str,
+    body: serde_json::Value,
+) -> reqwest::Response {
+    client
+        .post(format!(
+            "{}/security-groups/{}/rules",
+            address, security_group_id
+        ))
+        .json(&body)
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn attach_security_group(
+    client: &reqwest::Client,
+    address: &str,
+    vm_id: &str,
+    security_group_id: &str,
+) {
+    let res = client
+        .post(format!("{}/vms/{}/security-groups", address, vm_id))
+        .json(&json!({"security_group_id": security_group_id}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_security_group_rule_with_a_peer_group_round_trips() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let web = create_security_group(&client, &app.address, "web").await;
+    let db = create_security_group(&client, &app.address, "db").await;
+
+    let res = create_rule(
+        &client,
+        &app.address,
+        &db,
+        json!({
+            "direction": "ingress",
+            "protocol": "tcp",
+            "remote_security_group_id": web,
+            "port_start": 5432,
+            "port_end": 5432
+        }),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let rule_id = res.text().await.unwrap();
+
+    let res = client
+        .get(format!("{}/security-groups/{}/rules", app.address, db))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let rules: Vec<serde_json::Value> = res.json().await.unwrap();
+    assert_eq!(rules.len(), 1);
+    assert_eq!(rules[0]["id"], rule_id);
+    assert_eq!(rules[0]["remote_security_group_id"], web);
+    assert!(rules[0]["cidr"].is_null());
+    assert_eq!(rules[0]["port_start"], 5432);
+}
+
+#[tokio::test]
+async fn test_security_group_rule_rejects_a_cidr_with_a_peer_group() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let web = create_security_group(&client, &app.address, "web").await;
+    let db = create_security_group(&client, &app.address, "db").await;
+
+    let res = create_rule(
+        &client,
+        &app.address,
+        &db,
+        json!({
+            "direction": "ingress",
+            "protocol": "tcp",
+            "cidr": "10.0.0.0/24",
+            "remote_security_group_id": web
+        }),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let text = res.text().await.unwrap();
+    assert!(text.contains("mutually exclusive"), "{}", text);
+
+    let res = create_rule(
+        &client,
+        &app.address,
+        &db,
+        json!({
+            "direction": "ingress",
+            "protocol": "tcp",
+            "remote_security_group_id": Uuid::new_v4()
+        }),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_security_group_used_as_a_peer_cannot_be_deleted() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let web = create_security_group(&client, &app.address, "web").await;
+    let db = create_security_group(&client, &app.address, "db").await;
+    let res = create_rule(
+        &client,
+        &app.address,
+        &db,
+        json!({
+            "direction": "ingress",
+            "protocol": "any",
+            "remote_security_group_id": web
+        }),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let rule_id = res.text().await.unwrap();
+
+    let res = client
+        .delete(format!("{}/security-groups/{}", app.address, web))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let text = res.text().await.unwrap();
+    assert!(text.contains("db"), "{}", text);
+
+    let res = client
+        .delete(format!(
+            "{}/security-groups/{}/rules/{}",
+            app.address, db, rule_id
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = client
+        .delete(format!("{}/security-groups/{}", app.address, web))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_peer_group_members_resolve_to_their_addresses() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let host_id = ensure_host_up(&client, &app.address).await;
+
+    let network_id = create_network(
+        &client,
+        &app.address,
+        json!({
+            "name": "tier-net",
+            "subnet": "10.110.0.0/24",
+            "gateway": "10.110.0.1"
+        }),
+    )
+    .await;
+    attach_network_to_host(&app.pool, &network_id, &host_id, "testbr110").await;
+
+    let web = create_security_group(&client, &app.address, "web").await;
+    let db = create_security_group(&client, &app.address, "db").await;
+    let res = create_rule(
+        &client,
+        &app.address,
+        &db,
+        json!({
+            "direction": "ingress",
+            "protocol": "tcp",
+            "remote_security_group_id": web,
+            "port_start": 5432,
+            "port_end": 5432
+        }),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let web_vm = create_vm_on_network(&client, &app.address, "web-1", &network_id).await;
+    let db_vm = create_vm_on_network(&client, &app.address, "db-1", &network_id).await;
+    attach_security_group(&client, &app.address, &web_vm, &web).await;
+    attach_security_group(&client, &app.address, &db_vm, &db).await;
+
+    let web_uuid = Uuid::parse_str(&web).unwrap();
+    let db_uuid = Uuid::parse_str(&db).unwrap();
+    assert_eq!(
+        security_group_model::list_peer_vm_ids(&app.pool, web_uuid)
+            .await
+            .unwrap(),
+        [Uuid::parse_str(&db_vm).unwrap()]
+    );
+    let addresses = security_group_model::member_addresses(&app.pool, &[web_uuid, db_uuid])
+        .await
+        .unwrap();
+    let web_addresses = &addresses[&web_uuid];
+    assert_eq!(web_addresses.len(), 1, "{:?}", web_addresses);
+    assert!(web_addresses[0].starts_with("10.110.0."));
+    assert_ne!(web_addresses, &addresses[&db_uuid]);
+
+    // Leaving the group drops the VM from the peer's address list.
+    let res = client
+        .delete(format!(
+            "{}/vms/{}/security-groups/{}",
+            app.address, web_vm, web
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let addresses = security_group_model::member_addresses(&app.pool, &[web_uuid])
+        .await
+        .unwrap();
+    assert!(!addresses.contains_key(&web_uuid), "{:?}", addresses);
+}
+
+#[tokio::test]
+async fn test_security_group_stats_list_every_rule() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let host_id = ensure_host_up(&client, &app.address).await;
+
+    let network_id = create_network(
+        &client,
+        &app.address,
+        json!({
+            "name": "stats-net",
+            "subnet": "10.111.0.0/24",
+            "gateway": "10.111.0.1"
+        }),
+    )
+    .await;
+    attach_network_to_host(&app.pool, &network_id, &host_id, "testbr111").await;
+
+    let web = create_security_group(&client, &app.address, "web").await;
+    let mut rule_ids = Vec::new();
+    for body in [
+        json!({
+            "direction": "ingress",
+            "protocol": "tcp",
+            "cidr": "0.0.0.0/0",
+            "port_start": 443,
+            "port_end": 443
+        }),
+        json!({"direction": "egress", "protocol": "any", "cidr": "0.0.0.0/0"}),
+    ] {
+        let res = create_rule(&client, &app.address, &web, body).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        rule_ids.push(res.text().await.unwrap());
+    }
+    let vm_id = create_vm_on_network(&client, &app.address, "web-1", &network_id).await;
+    attach_security_group(&client, &app.address, &vm_id, &web).await;
+
+    // The member is not running, so its unreachable host is never asked.
+    let res = client
+        .get(format!("{}/security-groups/{}/stats", app.address, web))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let stats: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(stats["security_group_id"], web);
+    let rules = stats["rules"].as_array().unwrap();
+    assert_eq!(rules.len(), 2);
+    for rule in rules {
+        assert!(rule_ids.contains(&rule["rule_id"].as_str().unwrap().to_string()));
+        assert_eq!(rule["packets"], 0);
+        assert_eq!(rule["bytes"], 0);
+    }
+    assert_eq!(stats["members"], json!([]));
+
+    let res = client
+        .get(format!(
+            "{}/security-groups/{}/stats",
+            app.address,
+            Uuid::new_v4()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_vm_firewall_log_can_be_toggled() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let host_id = ensure_host_up(&client, &app.address).await;
+
+    let network_id = create_network(
+        &client,
+        &app.address,
+        json!({
+            "name": "log-net",
+            "subnet": "10.112.0.0/24",
+            "gateway": "10.112.0.1"
+        }),
+    )
+    .await;
+    attach_network_to_host(&app.pool, &network_id, &host_id, "testbr112").await;
+    let vm_id = create_vm_on_network(&client, &app.address, "web-1", &network_id).await;
+
+    let log_url = format!("{}/vms/{}/firewall-log", app.address, vm_id);
+    let res = client.get(&log_url).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let log: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(log, json!({"enabled": false, "denied": []}));
+
+    let res = client
+        .put(&log_url)
+        .json(&json!({"enabled": true}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Nothing is logged until the VM runs.
+    let res = client.get(&log_url).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let log: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(log, json!({"enabled": true, "denied": []}));
+
+    let res = client
+        .get(format!(
+            "{}/vms/{}/firewall-log",
+            app.address,
+            Uuid::new_v4()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
@@ -277,3 +277,331 @@ async fn test_create_snapshot_with_unavailable_node_creates_failed_record() {
         snapshots[0]["status"]
     );
 }
+
+#[tokio::test]
+async fn test_delete_snapshot_returns_404_for_unknown_snapshot() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    ensure_host_up(&client, &app.address).await;
+    ensure_storage_pool(&client, &app.address).await;
+
+    let vm_id = create_vm(
+        &client,
+        &app.address,
+        json!({
+            "name": "test-vm-snap-delete-404",
+            "hypervisor": "cloud_hv",
+            "boot_vcpus": 1,
+            "max_vcpus": 1,
+            "memory_size": 268435456,
+            "config": {}
+        }),
+    )
+    .await;
+
+    let res = client
+        .delete(format!(
+            "{}/vms/{}/snapshots/{}",
+            &app.address,
+            vm_id,
+            Uuid::new_v4()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = client
+        .delete(format!("{}/backups/{}", &app.address, Uuid::new_v4()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_delete_snapshot_referenced_by_backup_returns_409() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    ensure_host_up(&client, &app.address).await;
+    ensure_storage_pool(&client, &app.address).await;
+
+    let vm_id = create_vm(
+        &client,
+        &app.address,
+        json!({
+            "name": "test-vm-snap-delete-409",
+            "hypervisor": "cloud_hv",
+            "boot_vcpus": 1,
+            "max_vcpus": 1,
+            "memory_size": 268435456,
+            "config": {}
+        }),
+    )
+    .await;
+
+    // Node is unavailable, so this leaves a FAILED snapshot record behind.
+    let res = client
+        .post(format!("{}/vms/{}/snapshots", &app.address, vm_id))
+        .json(&json!({}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let res = client
+        .get(format!("{}/vms/{}/snapshots", &app.address, vm_id))
+        .send()
+        .await
+        .unwrap();
+    let snapshots: Vec<serde_json::Value> = res.json().await.unwrap();
+    let snapshot_id: Uuid = snapshots[0]["id"].as_str().unwrap().parse().unwrap();
+    let storage_object_id: Uuid = snapshots[0]["storage_object_id"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let vm_id: Uuid = vm_id.parse().unwrap();
+
+    sqlx::query(
+        r#"
+INSERT INTO backups (name, backup_type, status, vm_id, snapshot_id, storage_object_id)
+VALUES ('held-backup', 'VM', 'FAILED', $1, $2, $3)
+        "#,
+    )
+    .bind(vm_id)
+    .bind(snapshot_id)
+    .bind(storage_object_id)
+    .execute(&app.pool)
+    .await
+    .unwrap();
+
+    let res = client
+        .delete(format!(
+            "{}/vms/{}/snapshots/{}",
+            &app.address, vm_id, snapshot_id
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert!(
+        body["message"].as_str().unwrap().contains("held-backup"),
+        "Expected conflict message to name the backup, got: {}",
+        body
+    );
+}
+
+/// Insert a storage object row directly, returning its id.
+async fn insert_storage_object(
+    pool: &PgPool,
+    pool_id: Uuid,
+    name: &str,
+    object_type: &str,
+    size_bytes: i64,
+    config: serde_json::Value,
+    parent_id: Option<Uuid>,
+) -> Uuid {
+    sqlx::query_scalar(
+        r#"
+INSERT INTO storage_objects (name, storage_pool_id, object_type, size_bytes, config, parent_id)
+VALUES ($1, $2, $3::storage_object_type, $4, $5, $6)
+RETURNING id
+        "#,
+    )
+    .bind(name)
+    .bind(pool_id)
+    .bind(object_type)
+    .bind(size_bytes)
+    .bind(config)
+    .bind(parent_id)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+/// Give a VM an OverlayBD disk backed by a persistent upper layer.
+async fn attach_upper_disk(
+    pool: &PgPool,
+    pool_id: Uuid,
+    vm_id: Uuid,
+    logical_name: &str,
+    frozen_layers: serde_json::Value,
+) -> Uuid {
+    let image_id = insert_storage_object(
+        pool,
+        pool_id,
+        &format!("{logical_name}-image"),
+        "OCI_IMAGE",
+        0,
+        json!({ "image_ref": "registry:5000/alpine:obd" }),
+        None,
+    )
+    .await;
+    let upper_id = insert_storage_object(
+        pool,
+        pool_id,
+        &format!("{logical_name}-upper"),
+        "OVERLAYBD_UPPER",
+        4 * 1024 * 1024 * 1024,
+        json!({
+            "upper_data": format!("/tmp/test-pool/{logical_name}.upper.data"),
+            "upper_index": format!("/tmp/test-pool/{logical_name}.upper.index"),
+            "frozen_layers": frozen_layers,
+        }),
+        None,
+    )
+    .await;
+    sqlx::query(
+        r#"
+INSERT INTO vm_disks (vm_id, storage_object_id, logical_name, device_path, upper_storage_object_id)
+VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(vm_id)
+    .bind(image_id)
+    .bind(logical_name)
+    .bind(format!("/dev/{logical_name}"))
+    .bind(upper_id)
+    .execute(pool)
+    .await
+    .unwrap();
+    upper_id
+}
+
+#[tokio::test]
+async fn test_create_snapshot_refuses_vms_with_several_upper_layers() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    ensure_host_up(&client, &app.address).await;
+    let pool_id: Uuid = ensure_storage_pool(&client, &app.address)
+        .await
+        .parse()
+        .unwrap();
+
+    let vm_id: Uuid = create_vm(
+        &client,
+        &app.address,
+        json!({
+            "name": "test-vm-snap-two-uppers",
+            "hypervisor": "cloud_hv",
+            "boot_vcpus": 1,
+            "max_vcpus": 1,
+            "memory_size": 268435456,
+            "config": {}
+        }),
+    )
+    .await
+    .parse()
+    .unwrap();
+    attach_upper_disk(&app.pool, pool_id, vm_id, "disk0", json!([])).await;
+    attach_upper_disk(&app.pool, pool_id, vm_id, "disk1", json!([])).await;
+
+    let res = client
+        .post(format!("{}/vms/{}/snapshots", &app.address, vm_id))
+        .json(&json!({}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Nothing was recorded for the refused snapshot.
+    let res = client
+        .get(format!("{}/vms/{}/snapshots", &app.address, vm_id))
+        .send()
+        .await
+        .unwrap();
+    let snapshots: Vec<serde_json::Value> = res.json().await.unwrap();
+    assert!(snapshots.is_empty());
+}
+
+#[tokio::test]
+async fn test_failed_restore_keeps_the_upper_layer_layout() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    ensure_host_up(&client, &app.address).await;
+    let pool_id: Uuid = ensure_storage_pool(&client, &app.address)
+        .await
+        .parse()
+        .unwrap();
+
+    let vm_id: Uuid = create_vm(
+        &client,
+        &app.address,
+        json!({
+            "name": "test-vm-snap-restore",
+            "hypervisor": "cloud_hv",
+            "boot_vcpus": 1,
+            "max_vcpus": 1,
+            "memory_size": 268435456,
+            "config": {}
+        }),
+    )
+    .await
+    .parse()
+    .unwrap();
+    let live_layers = json!([
+        "/tmp/test-pool/disk0.layer0-aaaaaaaa.commit",
+        "/tmp/test-pool/disk0.layer1-bbbbbbbb.commit",
+    ]);
+    let upper_id = attach_upper_disk(&app.pool, pool_id, vm_id, "disk0", live_layers.clone()).await;
+
+    // A ready snapshot taken before the second resize.
+    let snapshot_object_id = insert_storage_object(
+        &app.pool,
+        pool_id,
+        "snap",
+        "SNAPSHOT",
+        0,
+        json!({ "path": "/tmp/test-pool/snap" }),
+        None,
+    )
+    .await;
+    let copy_id = insert_storage_object(
+        &app.pool,
+        pool_id,
+        "snap-upper",
+        "OVERLAYBD_UPPER",
+        2 * 1024 * 1024 * 1024,
+        json!({
+            "upper_data": "/tmp/test-pool/snap.upper.data",
+            "upper_index": "/tmp/test-pool/snap.upper.index",
+            "frozen_layers": ["/tmp/test-pool/disk0.layer0-aaaaaaaa.commit"],
+        }),
+        Some(upper_id),
+    )
+    .await;
+    let snapshot_id: Uuid = sqlx::query_scalar(
+        r#"
+INSERT INTO vm_snapshots (vm_id, storage_object_id, name, status, upper_storage_object_id)
+VALUES ($1, $2, 'snap', 'READY', $3)
+RETURNING id
+        "#,
+    )
+    .bind(vm_id)
+    .bind(snapshot_object_id)
+    .bind(copy_id)
+    .fetch_one(&app.pool)
+    .await
+    .unwrap();
+
+    // The node is unavailable, so the restore fails.
+    let res = client
+        .post(format!("{}/vms/{}/restore", &app.address, vm_id))
+        .json(&json!({ "snapshot_id": snapshot_id }))
+        .send()
+        .await
+        .unwrap();
+    assert!(!res.status().is_success(), "restore unexpectedly succeeded");
+
+    // The disk's upper still describes what is on the node.
+    let (size_bytes, config): (i64, serde_json::Value) =
+        sqlx::query_as("SELECT size_bytes, config FROM storage_objects WHERE id = $1")
+            .bind(upper_id)
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+    assert_eq!(size_bytes, 4 * 1024 * 1024 * 1024);
+    assert_eq!(config["frozen_layers"], live_layers);
+}
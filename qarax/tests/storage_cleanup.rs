@@ -141,6 +141,8 @@ async fn deleting_completed_transfer_artifacts_via_api_succeeds() {
             name: format!("transfer-{}", Uuid::new_v4()),
             source: "/tmp/source.img".to_string(),
             object_type: StorageObjectType::Kernel,
+            expected_checksum: None,
+            bandwidth_limit_bytes_per_sec: None,
         },
         TransferType::LocalCopy,
     )
@@ -6,7 +6,11 @@ use qarax::{
     configuration::{DatabaseSettings, default_control_plane_architecture, get_configuration},
     model::{
         hosts::{self, HostStatus, NewHost},
-        storage_pools::{self, NewStoragePool, StoragePoolType},
+        storage_objects::{self, NewStorageObject, StorageObjectType},
+        storage_pools::{
+            self, NewStoragePool, PoolCapacity, StoragePathHealth, StoragePoolStatus,
+            StoragePoolType,
+        },
     },
     startup::run,
 };
@@ -55,6 +59,8 @@ async fn configure_database(config: &DatabaseSettings) -> PgPool {
 
 async fn spawn_app() -> TestApp {
     Lazy::force(&TRACING);
+    qarax::secrets::init("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f")
+        .expect("valid test key");
 
     let listener = TcpListener::bind("127.0.0.1:0")
         .await
@@ -126,6 +132,7 @@ async fn create_test_pool(pool: &PgPool, pool_type: StoragePoolType) -> Uuid {
             "portal": "127.0.0.1:3260",
             "iqn": "iqn.2024-01.qarax:test",
         }),
+        StoragePoolType::Rbd => json!({ "pool": "vms", "monitors": "127.0.0.1:6789" }),
     };
 
     storage_pools::create(
@@ -170,6 +177,14 @@ async fn attach_host_to_pool(pool: &PgPool, pool_id: Uuid, host_id: Uuid) {
         .unwrap();
 }
 
+async fn count_pool_objects(pool: &PgPool, pool_id: Uuid) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*)::bigint FROM storage_objects WHERE storage_pool_id = $1")
+        .bind(pool_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
 #[tokio::test]
 async fn create_disk_accepts_source_backed_requests_without_size_bytes() {
     let app = spawn_app().await;
@@ -231,6 +246,62 @@ async fn create_disk_requires_positive_size_for_blank_disks() {
     assert_eq!(zero_body["message"], "size_bytes must be greater than 0");
 }
 
+#[tokio::test]
+async fn create_disk_validates_download_options() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let pool_id = create_test_pool(&app.pool, StoragePoolType::Local).await;
+
+    let blank_with_checksum = client
+        .post(format!("{}/storage-pools/{pool_id}/disks", app.address))
+        .json(&json!({
+            "name": "blank-disk",
+            "size_bytes": 1024,
+            "expected_checksum": format!("sha256:{}", "a".repeat(64)),
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        blank_with_checksum.status(),
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+    let body: serde_json::Value = blank_with_checksum.json().await.unwrap();
+    assert_eq!(
+        body["message"],
+        "expected_checksum and bandwidth_limit_bytes_per_sec require source_url"
+    );
+
+    let bad_checksum = client
+        .post(format!("{}/storage-pools/{pool_id}/disks", app.address))
+        .json(&json!({
+            "name": "image-disk",
+            "source_url": "https://example.com/disk.img",
+            "expected_checksum": "md5:abc",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(bad_checksum.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let local_transfer_with_checksum = client
+        .post(format!("{}/storage-pools/{pool_id}/transfers", app.address))
+        .json(&json!({
+            "name": "kernel",
+            "source": "/tmp/vmlinux",
+            "object_type": "kernel",
+            "expected_checksum": format!("sha256:{}", "a".repeat(64)),
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        local_transfer_with_checksum.status(),
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+}
+
 #[tokio::test]
 async fn create_disk_requires_an_up_host_attached_to_the_pool() {
     let app = spawn_app().await;
@@ -254,6 +325,114 @@ async fn create_disk_requires_an_up_host_attached_to_the_pool() {
     assert_eq!(body["message"], "No UP host attached to this storage pool");
 }
 
+#[tokio::test]
+async fn create_disk_is_refused_when_the_host_reports_too_little_space() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let pool_id = create_test_pool(&app.pool, StoragePoolType::Nfs).await;
+    let host_id = create_test_host(&app.pool, HostStatus::Up).await;
+    attach_host_to_pool(&app.pool, pool_id, host_id).await;
+    storage_pools::record_capacity(
+        &app.pool,
+        pool_id,
+        host_id,
+        &PoolCapacity {
+            total_bytes: 10 * 1024 * 1024,
+            used_bytes: 9 * 1024 * 1024,
+            free_bytes: 1024 * 1024,
+        },
+    )
+    .await
+    .unwrap();
+
+    let response = client
+        .post(format!("{}/storage-pools/{pool_id}/disks", app.address))
+        .json(&json!({
+            "name": "too-big",
+            "size_bytes": 2 * 1024 * 1024,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert!(
+        body["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Insufficient pool space")
+    );
+
+    let pool: serde_json::Value = client
+        .get(format!("{}/storage-pools/{pool_id}", app.address))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let reported = &pool["host_capacity"][0];
+    assert_eq!(reported["host_id"], host_id.to_string());
+    assert_eq!(reported["total_bytes"], 10 * 1024 * 1024);
+    assert_eq!(reported["free_bytes"], 1024 * 1024);
+}
+
+#[tokio::test]
+async fn allocated_bytes_follows_the_pools_objects_and_bounds_new_disks() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let pool_id = storage_pools::create(
+        &app.pool,
+        NewStoragePool {
+            name: format!("test-pool-{}", Uuid::new_v4()),
+            pool_type: StoragePoolType::Local,
+            config: json!({ "path": format!("/tmp/{}", Uuid::new_v4()) }),
+            capacity_bytes: Some(10 * 1024 * 1024),
+        },
+    )
+    .await
+    .unwrap();
+    let host_id = create_test_host(&app.pool, HostStatus::Up).await;
+    attach_host_to_pool(&app.pool, pool_id, host_id).await;
+
+    let object_id = storage_objects::create(
+        &app.pool,
+        NewStorageObject {
+            name: "existing-disk".to_string(),
+            storage_pool_id: Some(pool_id),
+            object_type: StorageObjectType::Disk,
+            size_bytes: 8 * 1024 * 1024,
+            config: json!({}),
+            parent_id: None,
+        },
+    )
+    .await
+    .unwrap();
+    let pool = storage_pools::get(&app.pool, pool_id).await.unwrap();
+    assert_eq!(pool.allocated_bytes, Some(8 * 1024 * 1024));
+
+    let response = client
+        .post(format!("{}/storage-pools/{pool_id}/disks", app.address))
+        .json(&json!({
+            "name": "too-big",
+            "size_bytes": 4 * 1024 * 1024,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        body["message"],
+        "Insufficient pool capacity: requested 4194304 bytes but only 2097152 available"
+    );
+    assert_eq!(count_pool_objects(&app.pool, pool_id).await, 1);
+
+    storage_objects::delete(&app.pool, object_id).await.unwrap();
+    let pool = storage_pools::get(&app.pool, pool_id).await.unwrap();
+    assert_eq!(pool.allocated_bytes, Some(0));
+}
+
 #[tokio::test]
 async fn import_to_pool_requires_an_up_host_attached_to_the_pool() {
     let app = spawn_app().await;
@@ -276,3 +455,499 @@ async fn import_to_pool_requires_an_up_host_attached_to_the_pool() {
     let body: serde_json::Value = response.json().await.unwrap();
     assert_eq!(body["message"], "No UP host attached to this storage pool");
 }
+
+#[tokio::test]
+async fn discover_luns_requires_a_block_pool_with_an_up_host() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let local_pool_id = create_test_pool(&app.pool, StoragePoolType::Local).await;
+    let response = client
+        .post(format!(
+            "{}/storage-pools/{local_pool_id}/luns/discover",
+            app.address
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        body["message"],
+        "LUN discovery is only valid for BLOCK pools"
+    );
+
+    let block_pool_id = create_test_pool(&app.pool, StoragePoolType::Block).await;
+    let down_host_id = create_test_host(&app.pool, HostStatus::Down).await;
+    attach_host_to_pool(&app.pool, block_pool_id, down_host_id).await;
+    let response = client
+        .post(format!(
+            "{}/storage-pools/{block_pool_id}/luns/discover",
+            app.address
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["message"], "No UP host attached to this storage pool");
+}
+
+#[tokio::test]
+async fn prewarm_targets_up_hosts_of_an_overlaybd_pool() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let image_ref = "registry:5000/library/alpine:latest_obd";
+
+    let local_pool_id = create_test_pool(&app.pool, StoragePoolType::Local).await;
+    let response = client
+        .post(format!(
+            "{}/storage-pools/{local_pool_id}/prewarm",
+            app.address
+        ))
+        .json(&json!({ "image_ref": image_ref }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        body["message"],
+        "Pre-warming is only valid for OVERLAYBD pools"
+    );
+
+    let obd_pool_id = create_test_pool(&app.pool, StoragePoolType::OverlayBd).await;
+    let up_host_id = create_test_host(&app.pool, HostStatus::Up).await;
+    let down_host_id = create_test_host(&app.pool, HostStatus::Down).await;
+    let stray_host_id = create_test_host(&app.pool, HostStatus::Up).await;
+    attach_host_to_pool(&app.pool, obd_pool_id, up_host_id).await;
+    attach_host_to_pool(&app.pool, obd_pool_id, down_host_id).await;
+    let url = format!("{}/storage-pools/{obd_pool_id}/prewarm", app.address);
+
+    let response = client
+        .post(&url)
+        .json(&json!({ "image_ref": image_ref, "host_ids": [stray_host_id] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = client
+        .post(&url)
+        .json(&json!({ "image_ref": image_ref, "host_ids": [down_host_id] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Without host_ids only the UP attached host is targeted.
+    let response = client
+        .post(&url)
+        .json(&json!({ "image_ref": image_ref }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["host_ids"], json!([up_host_id]));
+    assert!(body["job_id"].is_string());
+}
+
+#[tokio::test]
+async fn image_gc_requires_an_overlaybd_pool_with_an_up_host() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let local_pool_id = create_test_pool(&app.pool, StoragePoolType::Local).await;
+    let response = client
+        .post(format!("{}/storage-pools/{local_pool_id}/gc", app.address))
+        .json(&json!({ "dry_run": true }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        body["message"],
+        "Image GC is only valid for OVERLAYBD pools"
+    );
+
+    let obd_pool_id = create_test_pool(&app.pool, StoragePoolType::OverlayBd).await;
+    let down_host_id = create_test_host(&app.pool, HostStatus::Down).await;
+    attach_host_to_pool(&app.pool, obd_pool_id, down_host_id).await;
+    let url = format!("{}/storage-pools/{obd_pool_id}/gc", app.address);
+
+    let response = client.post(&url).json(&json!({})).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["message"], "No UP host attached to this storage pool");
+
+    let up_host_id = create_test_host(&app.pool, HostStatus::Up).await;
+    attach_host_to_pool(&app.pool, obd_pool_id, up_host_id).await;
+    let response = client
+        .post(&url)
+        .json(&json!({ "dry_run": true }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert!(body["job_id"].is_string());
+}
+
+#[tokio::test]
+async fn upload_rejects_bad_ranges_and_resumes_without_an_upload() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let pool_id = create_test_pool(&app.pool, StoragePoolType::Local).await;
+    let host_id = create_test_host(&app.pool, HostStatus::Up).await;
+    attach_host_to_pool(&app.pool, pool_id, host_id).await;
+    let url = format!("{}/storage-pools/{pool_id}/uploads/disk.img", app.address);
+
+    let bad_range = client
+        .put(&url)
+        .header("content-range", "bytes 9-3/10")
+        .body(vec![0u8; 4])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(bad_range.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let not_started = client
+        .put(&url)
+        .header("content-range", "bytes 5-9/10")
+        .body(vec![0u8; 5])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(not_started.status(), StatusCode::CONFLICT);
+    let body: serde_json::Value = not_started.json().await.unwrap();
+    assert_eq!(
+        body["message"],
+        "no upload in progress for 'disk.img'; start at offset 0"
+    );
+
+    let status = client.get(&url).send().await.unwrap();
+    assert_eq!(status.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn rbd_pools_validate_config_and_enrich_image_objects() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let missing_pool = client
+        .post(format!("{}/storage-pools", app.address))
+        .json(&json!({
+            "name": "ceph-without-pool",
+            "pool_type": "rbd",
+            "config": { "monitors": "127.0.0.1:6789" },
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing_pool.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let pool_id = create_test_pool(&app.pool, StoragePoolType::Rbd).await;
+    let from_url = client
+        .post(format!("{}/storage-pools/{pool_id}/disks", app.address))
+        .json(&json!({
+            "name": "cloud-image",
+            "source_url": "http://127.0.0.1:1/disk.img",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(from_url.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let object_id = storage_objects::create(
+        &app.pool,
+        NewStorageObject {
+            name: "existing-image".to_string(),
+            storage_pool_id: Some(pool_id),
+            object_type: StorageObjectType::Disk,
+            size_bytes: 1024 * 1024 * 1024,
+            config: json!({ "image": "vm-disk-1" }),
+            parent_id: None,
+        },
+    )
+    .await
+    .unwrap();
+    let object = storage_objects::get(&app.pool, object_id).await.unwrap();
+    assert_eq!(
+        object.config,
+        json!({ "pool": "vms", "monitors": "127.0.0.1:6789", "image": "vm-disk-1" })
+    );
+}
+
+#[tokio::test]
+async fn rbd_clones_and_removals_go_through_a_node() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let pool_id = create_test_pool(&app.pool, StoragePoolType::Rbd).await;
+    let host_id = create_test_host(&app.pool, HostStatus::Up).await;
+    attach_host_to_pool(&app.pool, pool_id, host_id).await;
+    let source_id = storage_objects::create(
+        &app.pool,
+        NewStorageObject {
+            name: "golden-image".to_string(),
+            storage_pool_id: Some(pool_id),
+            object_type: StorageObjectType::Disk,
+            size_bytes: 1024 * 1024 * 1024,
+            config: json!({ "image": "golden" }),
+            parent_id: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    let local_pool_id = create_test_pool(&app.pool, StoragePoolType::Local).await;
+    attach_host_to_pool(&app.pool, local_pool_id, host_id).await;
+    let into_local = client
+        .post(format!(
+            "{}/storage-pools/{local_pool_id}/disks",
+            app.address
+        ))
+        .json(&json!({ "name": "clone", "source_storage_object_id": source_id }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(into_local.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let with_size = client
+        .post(format!("{}/storage-pools/{pool_id}/disks", app.address))
+        .json(&json!({
+            "name": "clone",
+            "size_bytes": 1024,
+            "source_storage_object_id": source_id,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(with_size.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // The node is unreachable: the clone fails and its object is rolled back.
+    let clone = client
+        .post(format!("{}/storage-pools/{pool_id}/disks", app.address))
+        .json(&json!({ "name": "clone", "source_storage_object_id": source_id }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(clone.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = clone.json().await.unwrap();
+    assert!(
+        body["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Failed to clone RBD image"),
+        "{body}"
+    );
+    assert_eq!(count_pool_objects(&app.pool, pool_id).await, 1);
+
+    // Removing the image fails too, so the row stays.
+    let delete = client
+        .delete(format!("{}/storage-objects/{source_id}", app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(delete.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(count_pool_objects(&app.pool, pool_id).await, 1);
+
+    // An image with clones is refused before the node is asked.
+    storage_objects::create(
+        &app.pool,
+        NewStorageObject {
+            name: "child".to_string(),
+            storage_pool_id: Some(pool_id),
+            object_type: StorageObjectType::Disk,
+            size_bytes: 1024 * 1024 * 1024,
+            config: json!({ "image": "child" }),
+            parent_id: Some(source_id),
+        },
+    )
+    .await
+    .unwrap();
+    let delete = client
+        .delete(format!("{}/storage-objects/{source_id}", app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(delete.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn overlaybd_upper_removal_keeps_shared_frozen_layers() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let pool_id = create_test_pool(&app.pool, StoragePoolType::Local).await;
+    let host_id = create_test_host(&app.pool, HostStatus::Up).await;
+    attach_host_to_pool(&app.pool, pool_id, host_id).await;
+
+    let layer = "/var/lib/qarax/pools/vm.layer0-0badcafe.commit";
+    let mut uppers = Vec::new();
+    for name in ["upper", "snapshot-copy"] {
+        let id = storage_objects::create(
+            &app.pool,
+            NewStorageObject {
+                name: name.to_string(),
+                storage_pool_id: Some(pool_id),
+                object_type: StorageObjectType::OverlaybdUpper,
+                size_bytes: 1024 * 1024 * 1024,
+                config: json!({
+                    "upper_data": format!("/var/lib/qarax/pools/{name}.upper.data"),
+                    "upper_index": format!("/var/lib/qarax/pools/{name}.upper.index"),
+                    "frozen_layers": [layer],
+                }),
+                parent_id: None,
+            },
+        )
+        .await
+        .unwrap();
+        uppers.push(id);
+    }
+
+    // Each upper sees the layer held by the other, but not by itself alone.
+    assert!(
+        storage_objects::frozen_layer_in_use(&app.pool, layer, uppers[0])
+            .await
+            .unwrap()
+    );
+    storage_objects::delete(&app.pool, uppers[1]).await.unwrap();
+    assert!(
+        !storage_objects::frozen_layer_in_use(&app.pool, layer, uppers[0])
+            .await
+            .unwrap()
+    );
+
+    // The upper's files are removed through the node, which is unreachable
+    // here, so the row stays.
+    let delete = client
+        .delete(format!("{}/storage-objects/{}", app.address, uppers[0]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(delete.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(count_pool_objects(&app.pool, pool_id).await, 1);
+}
+
+#[tokio::test]
+async fn block_pools_store_chap_secrets_encrypted_and_track_path_health() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let mutual_without_chap = client
+        .post(format!("{}/storage-pools", app.address))
+        .json(&json!({
+            "name": "san-invalid",
+            "pool_type": "block",
+            "config": {
+                "portal": "10.0.0.5:3260",
+                "iqn": "iqn.2024-01.qarax:target0",
+                "mutual_chap": { "username": "target", "secret": "target-secret" },
+            },
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        mutual_without_chap.status(),
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+
+    let created = client
+        .post(format!("{}/storage-pools", app.address))
+        .json(&json!({
+            "name": "san",
+            "pool_type": "block",
+            "config": {
+                "portals": ["10.0.0.5:3260", "10.0.1.5:3260"],
+                "iqn": "iqn.2024-01.qarax:target0",
+                "chap": { "username": "initiator", "secret": "initiator-secret" },
+                "mutual_chap": { "username": "target", "secret": "target-secret" },
+            },
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(created.status(), StatusCode::CREATED);
+    let pool_id: Uuid = created.text().await.unwrap().parse().unwrap();
+
+    let pool = storage_pools::get(&app.pool, pool_id).await.unwrap();
+    let stored = pool.config.to_string();
+    assert!(!stored.contains("initiator-secret"));
+    assert!(!stored.contains("target-secret"));
+    let node_config = pool.node_config().unwrap();
+    assert_eq!(node_config["chap"]["secret"], "initiator-secret");
+    assert_eq!(node_config["mutual_chap"]["secret"], "target-secret");
+
+    let lun = storage_objects::create(
+        &app.pool,
+        NewStorageObject {
+            name: "lun-1".to_string(),
+            storage_pool_id: Some(pool_id),
+            object_type: StorageObjectType::Disk,
+            size_bytes: 1024 * 1024 * 1024,
+            config: json!({ "lun": 1 }),
+            parent_id: None,
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        storage_objects::get(&app.pool, lun).await.unwrap().config,
+        json!({
+            "portals": ["10.0.0.5:3260", "10.0.1.5:3260"],
+            "iqn": "iqn.2024-01.qarax:target0",
+            "lun": 1,
+        })
+    );
+
+    let host_id = create_test_host(&app.pool, HostStatus::Up).await;
+    attach_host_to_pool(&app.pool, pool_id, host_id).await;
+    let path = |portal: &str, state: &str, healthy: bool| StoragePathHealth {
+        path: portal.to_string(),
+        state: state.to_string(),
+        healthy,
+    };
+
+    storage_pools::record_path_health(
+        &app.pool,
+        pool_id,
+        host_id,
+        &[
+            path("10.0.0.5:3260", "LOGGED_IN", true),
+            path("10.0.1.5:3260", "FAILED", false),
+        ],
+    )
+    .await
+    .unwrap();
+    let pool = storage_pools::get(&app.pool, pool_id).await.unwrap();
+    assert_eq!(pool.status, StoragePoolStatus::Degraded);
+
+    let paths: serde_json::Value = client
+        .get(format!("{}/storage-pools/{pool_id}/paths", app.address))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let host_paths = &paths[0];
+    assert_eq!(host_paths["host_id"], host_id.to_string());
+    assert_eq!(host_paths["paths"][1]["state"], "FAILED");
+    assert_eq!(host_paths["paths"][1]["healthy"], false);
+
+    storage_pools::record_path_health(
+        &app.pool,
+        pool_id,
+        host_id,
+        &[
+            path("10.0.0.5:3260", "LOGGED_IN", true),
+            path("10.0.1.5:3260", "LOGGED_IN", true),
+        ],
+    )
+    .await
+    .unwrap();
+    let pool = storage_pools::get(&app.pool, pool_id).await.unwrap();
+    assert_eq!(pool.status, StoragePoolStatus::Active);
+}
@@ -0,0 +1,351 @@
+use std::time::Duration;
+
+use tokio::net::TcpListener;
+
+use common::telemtry::{get_subscriber, init_subscriber};
+use once_cell::sync::Lazy;
+use qarax::{
+    configuration::{DatabaseSettings, default_control_plane_architecture, get_configuration},
+    model::{
+        hosts::{self, HostStatus, NewHost},
+        storage_objects::{self, NewStorageObject, StorageObjectType},
+        storage_pools::{self, NewStoragePool, StoragePoolType},
+    },
+    startup::run,
+};
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use tokio::runtime::Runtime;
+use uuid::Uuid;
+
+struct TestApp {
+    db_name: String,
+    address: String,
+    pool: PgPool,
+}
+
+static TRACING: Lazy<()> = Lazy::new(|| {
+    let default_filter_level = "info".to_string();
+    let subscriber_name = "test".to_string();
+    if std::env::var("TEST_LOG").is_ok() {
+        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::stdout);
+        init_subscriber(subscriber);
+    } else {
+        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::sink);
+        init_subscriber(subscriber);
+    }
+});
+
+async fn configure_database(config: &DatabaseSettings) -> PgPool {
+    let mut connection = PgConnection::connect(&config.connection_string_without_db())
+        .await
+        .expect("Failed to connect to Postgres");
+    connection
+        .execute(format!(r#"CREATE DATABASE "{}";"#, config.name).as_str())
+        .await
+        .expect("Failed to create database.");
+
+    let connection_pool = PgPool::connect(&config.connection_string())
+        .await
+        .expect("Failed to connect to Postgres.");
+    sqlx::migrate!("../migrations")
+        .run(&connection_pool)
+        .await
+        .expect("Failed to migrate the database");
+    connection_pool
+}
+
+async fn spawn_app() -> TestApp {
+    Lazy::force(&TRACING);
+    qarax::secrets::init("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f")
+        .expect("valid test key");
+
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Failed to bind random port");
+    let port = listener.local_addr().unwrap().port();
+    let address = format!("http://127.0.0.1:{port}");
+
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.database.name = Uuid::new_v4().to_string();
+    let connection_pool = configure_database(&configuration.database).await;
+
+    let server = run(
+        listener,
+        connection_pool.clone(),
+        configuration.database.clone(),
+        configuration.vm_defaults.clone(),
+        configuration.scheduling.clone(),
+        default_control_plane_architecture(),
+    )
+    .await
+    .unwrap();
+
+    std::thread::spawn(move || {
+        let rt = Runtime::new().unwrap();
+        let _ = rt.block_on(async move { server.await });
+    });
+
+    TestApp {
+        db_name: configuration.database.name,
+        address,
+        pool: connection_pool,
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let db_name = self.db_name.clone();
+
+        std::thread::spawn(move || {
+            let rt = Runtime::new().unwrap();
+            rt.block_on(async {
+                let config = get_configuration().expect("Failed to read configuration");
+                let mut conn = PgConnection::connect_with(&config.database.without_db())
+                    .await
+                    .expect("Failed to connect to Postgres");
+
+                conn.execute(&*format!("DROP DATABASE \"{}\" WITH (FORCE)", db_name))
+                    .await
+                    .expect("Failed to drop database.");
+
+                let _ = tx.send(());
+            })
+        });
+
+        let _ = rx.recv();
+    }
+}
+
+/// A Local pool attached to an UP host. The host's node is unreachable, so
+/// every node call made on its behalf fails.
+async fn create_pool_with_host(pool: &PgPool) -> (Uuid, String) {
+    let path = format!("/tmp/{}", Uuid::new_v4());
+    let pool_id = storage_pools::create(
+        pool,
+        NewStoragePool {
+            name: format!("bundle-pool-{}", Uuid::new_v4()),
+            pool_type: StoragePoolType::Local,
+            config: json!({ "path": path }),
+            capacity_bytes: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    let host_id = hosts::add(
+        pool,
+        &NewHost {
+            name: format!("test-host-{}", Uuid::new_v4()),
+            address: "127.0.0.1".to_string(),
+            port: 1,
+            host_user: "root".to_string(),
+            password: String::new(),
+            reservation_class: None,
+            placement_labels: std::collections::BTreeMap::new(),
+        },
+    )
+    .await
+    .unwrap();
+    hosts::update_status(pool, host_id, HostStatus::Up)
+        .await
+        .unwrap();
+    storage_pools::attach_host(pool, pool_id, host_id)
+        .await
+        .unwrap();
+
+    (pool_id, path)
+}
+
+async fn create_vm(client: &reqwest::Client, address: &str, name: &str) -> Uuid {
+    let res = client
+        .post(format!("{address}/vms"))
+        .json(&json!({
+            "name": name,
+            "hypervisor": "cloud_hv",
+            "boot_vcpus": 1,
+            "max_vcpus": 1,
+            "memory_size": 268435456,
+            "config": {}
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED, "VM creation failed");
+    res.json().await.unwrap()
+}
+
+async fn wait_for_job(client: &reqwest::Client, address: &str, job_id: &str) -> serde_json::Value {
+    for _ in 0..100 {
+        let job: serde_json::Value = client
+            .get(format!("{address}/jobs/{job_id}"))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        if job["status"] == "completed" || job["status"] == "failed" {
+            return job;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("job {job_id} did not finish");
+}
+
+async fn count(pool: &PgPool, query: &str) -> i64 {
+    sqlx::query_scalar(query).fetch_one(pool).await.unwrap()
+}
+
+#[tokio::test]
+async fn export_then_import_round_trip_leaves_nothing_behind_when_the_node_fails() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let (pool_id, pool_path) = create_pool_with_host(&app.pool).await;
+    let vm_id = create_vm(&client, &app.address, "bundle-source").await;
+
+    let res = client
+        .post(format!("{}/vms/{vm_id}/export", app.address))
+        .json(&json!({ "storage_pool_id": pool_id }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    let export: serde_json::Value = res.json().await.unwrap();
+    let bundle_path = export["path"].as_str().unwrap().to_string();
+    assert!(
+        bundle_path.starts_with(&format!("{pool_path}/")),
+        "bundle should be written inside the pool, got {bundle_path}"
+    );
+    let bundle_id = export["storage_object_id"].as_str().unwrap();
+
+    let job = wait_for_job(&client, &app.address, export["job_id"].as_str().unwrap()).await;
+    assert_eq!(job["status"], "failed");
+    assert_eq!(job["job_type"], "vm_export");
+
+    // The half-written bundle is not left registered.
+    let res = client
+        .get(format!("{}/storage-objects/{bundle_id}", app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // Importing the same path reaches the node to read the manifest and fails
+    // before any VM or storage object is created.
+    let res = client
+        .post(format!("{}/vms/import", app.address))
+        .json(&json!({
+            "storage_pool_id": pool_id,
+            "path": bundle_path,
+            "name": "bundle-copy",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(
+        count(&app.pool, "SELECT COUNT(*)::bigint FROM vms").await,
+        1
+    );
+    assert_eq!(
+        count(&app.pool, "SELECT COUNT(*)::bigint FROM storage_objects").await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn export_requires_a_stopped_vm_unless_memory_is_included() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let (pool_id, _) = create_pool_with_host(&app.pool).await;
+    let vm_id = create_vm(&client, &app.address, "bundle-state").await;
+
+    let res = client
+        .post(format!("{}/vms/{vm_id}/export", app.address))
+        .json(&json!({ "storage_pool_id": pool_id, "include_memory": true }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let res = client
+        .post(format!("{}/vms/{}/export", app.address, Uuid::new_v4()))
+        .json(&json!({}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn import_rejects_paths_outside_the_storage_pool() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let (pool_id, pool_path) = create_pool_with_host(&app.pool).await;
+
+    for path in [
+        "/etc/passwd".to_string(),
+        format!("{pool_path}/../etc/passwd"),
+        format!("{pool_path}-other/bundle.tar"),
+        pool_path.clone(),
+    ] {
+        let res = client
+            .post(format!("{}/vms/import", app.address))
+            .json(&json!({ "storage_pool_id": pool_id, "path": path }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            res.status(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "path {path} should be rejected"
+        );
+    }
+}
+
+#[tokio::test]
+async fn import_requires_a_single_bundle_source() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let (pool_id, pool_path) = create_pool_with_host(&app.pool).await;
+
+    let disk = storage_objects::create(
+        &app.pool,
+        NewStorageObject {
+            name: "not-a-bundle".to_string(),
+            storage_pool_id: Some(pool_id),
+            object_type: StorageObjectType::Disk,
+            size_bytes: 1024,
+            config: json!({}),
+            parent_id: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    for body in [
+        json!({}),
+        json!({ "storage_pool_id": pool_id }),
+        json!({
+            "storage_object_id": disk,
+            "storage_pool_id": pool_id,
+            "path": format!("{pool_path}/bundle.tar"),
+        }),
+        json!({ "storage_object_id": disk }),
+    ] {
+        let res = client
+            .post(format!("{}/vms/import", app.address))
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            res.status(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "request {body} should be rejected"
+        );
+    }
+}
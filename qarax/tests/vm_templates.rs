@@ -515,3 +515,142 @@ async fn create_vm_from_template_attaches_root_disk_object() {
         root_disk_object_id
     );
 }
+
+#[tokio::test]
+async fn vm_template_can_follow_the_latest_image_catalog_version() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    ensure_host_up(&client, &app.address).await;
+
+    let res = client
+        .post(format!("{}/storage-pools", app.address))
+        .json(&json!({
+            "name": "oci",
+            "pool_type": "overlaybd",
+            "config": {"url": "http://registry:5000"}
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let pool_id = res.text().await.unwrap();
+
+    let res = client
+        .post(format!("{}/image-catalogs", app.address))
+        .json(&json!({
+            "name": "ubuntu",
+            "source_ref": "docker.io/library/ubuntu:24.04@sha256:abc",
+            "storage_pool_id": pool_id
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let res = client
+        .post(format!("{}/image-catalogs", app.address))
+        .json(&json!({
+            "name": "ubuntu",
+            "source_ref": "docker.io/library/ubuntu:24.04",
+            "storage_pool_id": pool_id
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let catalog_id = res.text().await.unwrap();
+
+    let res = client
+        .get(format!("{}/image-catalogs/{catalog_id}", app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let catalog: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(catalog["refresh_interval_secs"], 86400);
+    assert_eq!(catalog["keep_versions"], 3);
+
+    let template = json!({
+        "name": "ubuntu-latest",
+        "hypervisor": "cloud_hv",
+        "boot_vcpus": 1,
+        "max_vcpus": 1,
+        "memory_size": 268435456,
+        "image_catalog_id": catalog_id,
+    });
+    let mut with_image_ref = template.clone();
+    with_image_ref["image_ref"] = json!("docker.io/library/alpine:3.20");
+    let res = client
+        .post(format!("{}/vm-templates", app.address))
+        .json(&with_image_ref)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let res = client
+        .post(format!("{}/vm-templates", app.address))
+        .json(&template)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let vm_template_id = res.text().await.unwrap();
+
+    let create_vm = json!({ "name": "from-catalog", "vm_template_id": vm_template_id });
+    let res = client
+        .post(format!("{}/vms", app.address))
+        .json(&create_vm)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(
+        body["message"],
+        "image catalog 'ubuntu' has no imported versions yet"
+    );
+
+    let catalog_id = Uuid::parse_str(&catalog_id).unwrap();
+    for (digest, age) in [("sha256:old", "2 days"), ("sha256:new", "1 day")] {
+        sqlx::query(
+            "INSERT INTO image_catalog_versions (catalog_id, source_digest, image_ref, created_at) \
+             VALUES ($1, $2, $3, NOW() - $4::interval)",
+        )
+        .bind(catalog_id)
+        .bind(digest)
+        .bind(format!("docker.io/library/ubuntu:24.04@{digest}"))
+        .bind(age)
+        .execute(&app._pool)
+        .await
+        .unwrap();
+    }
+
+    let res = client
+        .get(format!(
+            "{}/image-catalogs/{catalog_id}/versions",
+            app.address
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let versions: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(versions[0]["source_digest"], "sha256:new");
+    assert_eq!(versions[1]["source_digest"], "sha256:old");
+
+    // With a version available the VM takes the OCI image path, which needs
+    // an OverlayBD pool on the selected host.
+    let res = client
+        .post(format!("{}/vms", app.address))
+        .json(&create_vm)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(
+        body["message"],
+        "no OverlayBD storage pool attached to the selected host"
+    );
+}
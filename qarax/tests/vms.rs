@@ -1198,3 +1198,238 @@ async fn test_preflight_rejects_selected_host_architecture_mismatch() {
             .contains("selected host architecture x86_64 does not match VM architecture aarch64")
     );
 }
+
+/// Create a storage pool through the API and attach it to `host_id` in the DB.
+async fn create_attached_pool(
+    client: &reqwest::Client,
+    address: &str,
+    db_pool: &PgPool,
+    host_id: &str,
+    name: &str,
+    pool_type: &str,
+    config: serde_json::Value,
+) -> String {
+    let res = client
+        .post(format!("{}/storage-pools", address))
+        .json(&json!({ "name": name, "pool_type": pool_type, "config": config }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let pool_id = res.text().await.unwrap();
+
+    sqlx::query("INSERT INTO host_storage_pools (host_id, storage_pool_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+        .bind(Uuid::parse_str(host_id).unwrap())
+        .bind(Uuid::parse_str(&pool_id).unwrap())
+        .execute(db_pool)
+        .await
+        .unwrap();
+    pool_id
+}
+
+async fn wait_for_job(client: &reqwest::Client, address: &str, job_id: &str) -> serde_json::Value {
+    for _ in 0..100 {
+        let job: serde_json::Value = client
+            .get(format!("{}/jobs/{}", address, job_id))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        if job["status"] == "completed" || job["status"] == "failed" {
+            return job;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    panic!("job {} did not finish", job_id);
+}
+
+fn full_clone_vm(name: &str, extra: serde_json::Value) -> serde_json::Value {
+    let mut body = json!({
+        "name": name,
+        "hypervisor": "cloud_hv",
+        "boot_vcpus": 1,
+        "max_vcpus": 1,
+        "memory_size": 268435456,
+        "config": {}
+    });
+    body.as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    body
+}
+
+#[tokio::test]
+async fn test_full_clone_options_are_validated() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    ensure_host_up(&client, &app.address).await;
+    let pool_id = Uuid::new_v4();
+
+    for (extra, message) in [
+        (
+            json!({ "root_disk_mode": "full_clone", "root_disk_pool_id": pool_id }),
+            "root_disk_mode=full_clone requires image_ref",
+        ),
+        (
+            json!({
+                "root_disk_mode": "full_clone",
+                "image_ref": "registry:5000/test/busybox:latest"
+            }),
+            "root_disk_mode=full_clone requires root_disk_pool_id",
+        ),
+        (
+            json!({
+                "root_disk_mode": "full_clone",
+                "image_ref": "registry:5000/test/busybox:latest",
+                "root_disk_pool_id": pool_id,
+                "persistent_upper_pool_id": pool_id
+            }),
+            "persistent_upper_pool_id cannot be combined with root_disk_mode=full_clone",
+        ),
+        (
+            json!({
+                "image_ref": "registry:5000/test/busybox:latest",
+                "root_disk_pool_id": pool_id
+            }),
+            "root_disk_pool_id requires root_disk_mode=full_clone",
+        ),
+    ] {
+        let res = client
+            .post(format!("{}/vms", &app.address))
+            .json(&full_clone_vm("full-clone-invalid", extra))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: serde_json::Value = res.json().await.unwrap();
+        assert_eq!(body["message"], message);
+    }
+}
+
+#[tokio::test]
+async fn test_full_clone_requires_a_file_pool_attached_to_the_host() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let host_id = ensure_host_up(&client, &app.address).await;
+    create_attached_pool(
+        &client,
+        &app.address,
+        &app.pool,
+        &host_id,
+        "oci",
+        "overlaybd",
+        json!({ "url": "http://registry:5000" }),
+    )
+    .await;
+
+    // A Local pool that the host does not have.
+    let res = client
+        .post(format!("{}/storage-pools", &app.address))
+        .json(&json!({
+            "name": "detached",
+            "pool_type": "local",
+            "config": { "path": "/tmp/detached" }
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let detached_pool_id = res.text().await.unwrap();
+
+    let res = client
+        .post(format!("{}/vms", &app.address))
+        .json(&full_clone_vm(
+            "full-clone-detached",
+            json!({
+                "image_ref": "registry:5000/test/busybox:latest",
+                "root_disk_mode": "full_clone",
+                "root_disk_pool_id": detached_pool_id
+            }),
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let vms: Vec<serde_json::Value> = client
+        .get(format!("{}/vms", &app.address))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(vms.is_empty());
+}
+
+#[tokio::test]
+async fn test_failed_full_clone_leaves_no_root_disk_behind() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let host_id = ensure_host_up(&client, &app.address).await;
+    create_attached_pool(
+        &client,
+        &app.address,
+        &app.pool,
+        &host_id,
+        "oci",
+        "overlaybd",
+        json!({ "url": "http://registry:5000" }),
+    )
+    .await;
+    let root_pool_id = create_attached_pool(
+        &client,
+        &app.address,
+        &app.pool,
+        &host_id,
+        "root-disks",
+        "local",
+        json!({ "path": "/tmp/root-disks" }),
+    )
+    .await;
+
+    let res = client
+        .post(format!("{}/vms", &app.address))
+        .json(&full_clone_vm(
+            "full-clone-vm",
+            json!({
+                "image_ref": "registry:5000/test/busybox:latest",
+                "root_disk_mode": "full_clone",
+                "root_disk_pool_id": root_pool_id
+            }),
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    let body: serde_json::Value = res.json().await.unwrap();
+    let vm_id = body["vm_id"].as_str().unwrap().to_string();
+    let job_id = body["job_id"].as_str().unwrap().to_string();
+
+    // The node is unavailable, so the import fails before any disk is made.
+    let job = wait_for_job(&client, &app.address, &job_id).await;
+    assert_eq!(job["status"], "failed");
+    assert_eq!(
+        job["description"],
+        "Cloning image registry:5000/test/busybox:latest to raw disk"
+    );
+
+    let vm: serde_json::Value = client
+        .get(format!("{}/vms/{}", &app.address, vm_id))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(vm["status"], "unknown");
+    let disks: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM storage_objects WHERE storage_pool_id = $1")
+            .bind(Uuid::parse_str(&root_pool_id).unwrap())
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+    assert_eq!(disks, 0);
+}